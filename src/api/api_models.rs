@@ -19,6 +19,19 @@ pub struct Uris {
     pub uris: Vec<String>,
 }
 
+// Tells Spotify which version of the playlist the edit was made against
+#[derive(Serialize)]
+pub struct SnapshotUris<'a> {
+    pub uris: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlaylistSnapshot {
+    pub snapshot_id: String,
+}
+
 #[derive(Serialize)]
 pub struct PlayOffset {
     pub position: u32,
@@ -30,10 +43,14 @@ pub enum PlayRequest {
     Contextual {
         context_uri: String,
         offset: PlayOffset,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        position_ms: Option<u32>,
     },
     Uris {
         uris: Vec<String>,
         offset: PlayOffset,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        position_ms: Option<u32>,
     },
 }
 
@@ -50,6 +67,7 @@ pub struct Name<'a> {
 pub enum SearchType {
     Artist,
     Album,
+    Playlist,
 }
 
 impl SearchType {
@@ -57,6 +75,7 @@ impl SearchType {
         match self {
             Self::Artist => "artist",
             Self::Album => "album",
+            Self::Playlist => "playlist",
         }
     }
 }
@@ -90,12 +109,21 @@ impl SearchQuery {
     }
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct Cursors {
+    after: Option<String>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct Page<T> {
     items: Option<Vec<T>>,
     offset: Option<usize>,
     limit: Option<usize>,
+    // Not always given for cursor-based pages
+    #[serde(default)]
     total: usize,
+    // Only there for cursor-based pages
+    cursors: Option<Cursors>,
 }
 
 impl<T> Page<T> {
@@ -106,6 +134,7 @@ impl<T> Page<T> {
             items: Some(items),
             offset: Some(0),
             limit: Some(l),
+            cursors: None,
         }
     }
 
@@ -118,12 +147,14 @@ impl<T> Page<T> {
             offset,
             limit,
             total,
+            cursors,
         } = self;
         Page {
             items: items.map(|item| item.into_iter().map(mapper).collect()),
             offset,
             limit,
             total,
+            cursors,
         }
     }
 
@@ -141,6 +172,32 @@ impl<T> Page<T> {
     pub fn offset(&self) -> usize {
         self.offset.unwrap_or(0)
     }
+
+    // Where the page after this one starts, if there's one
+    pub fn next_token(&self) -> Option<PageToken> {
+        let len = self.items.as_ref().map(Vec::len).unwrap_or(0);
+        match &self.cursors {
+            Some(cursors) => cursors
+                .after
+                .clone()
+                .filter(|_| len > 0)
+                .map(PageToken::After),
+            None => Some(self.offset() + len)
+                .filter(|next| *next < self.total)
+                .map(PageToken::Offset),
+        }
+    }
+
+    pub fn into_paged<Mapper, U>(self, mapper: Mapper) -> Paged<U>
+    where
+        Mapper: Fn(T) -> U,
+    {
+        let next = self.next_token();
+        Paged {
+            items: self.into_iter().map(mapper).collect(),
+            next,
+        }
+    }
 }
 
 impl<T> IntoIterator for Page<T> {
@@ -159,6 +216,7 @@ impl<T> Default for Page<T> {
             total: 0,
             offset: Some(0),
             limit: Some(0),
+            cursors: None,
         }
     }
 }
@@ -189,6 +247,104 @@ pub struct Playlist {
     pub images: Vec<Image>,
     pub tracks: Page<PlaylistTrack>,
     pub owner: PlaylistOwner,
+    #[serde(default)]
+    pub snapshot_id: Option<String>,
+}
+
+// Browse "views" (made-for-x and the like): a view holds either more views (the shelves) or actual content
+#[derive(Deserialize, Debug, Clone)]
+pub struct View {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub content: Page<ViewItem>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ViewItem {
+    View(View),
+    Playlist(Playlist),
+    // Albums, artists, shows... we only show playlists for now
+    #[serde(other)]
+    Other,
+}
+
+impl View {
+    pub fn playlists(self) -> Vec<PlaylistDescription> {
+        self.content
+            .into_iter()
+            .filter_map(|item| match item {
+                ViewItem::Playlist(playlist) => Some(playlist.into()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Shelves with nothing we can show are left out
+    pub fn shelves(self) -> Vec<HomeShelf> {
+        self.content
+            .into_iter()
+            .filter_map(|item| match item {
+                ViewItem::View(view) => Some(HomeShelf {
+                    id: view.id.clone(),
+                    title: view.name.clone(),
+                    playlists: view.playlists(),
+                }),
+                _ => None,
+            })
+            .filter(|shelf| !shelf.playlists.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Audiobook {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub authors: Vec<Author>,
+    pub images: Vec<Image>,
+    #[serde(default)]
+    pub total_chapters: usize,
+}
+
+impl WithImages for Audiobook {
+    fn images(&self) -> &[Image] {
+        &self.images[..]
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Author {
+    pub name: String,
+}
+
+// Chapters come with an episode uri, which is also what the player needs to play them
+#[derive(Deserialize, Debug, Clone)]
+pub struct Chapter {
+    pub id: String,
+    pub uri: String,
+    pub name: String,
+    pub duration_ms: u32,
+    #[serde(default)]
+    pub description: String,
+    pub chapter_number: Option<u32>,
+    #[serde(default)]
+    pub images: Vec<Image>,
+    pub resume_point: Option<ResumePoint>,
+}
+
+impl WithImages for Chapter {
+    fn images(&self) -> &[Image] {
+        &self.images[..]
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ResumePoint {
+    pub fully_played: bool,
+    pub resume_position_ms: u32,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -207,6 +363,8 @@ impl WithImages for Playlist {
 pub struct PlaylistTrack {
     pub is_local: bool,
     pub track: Option<FailibleTrackItem>,
+    #[serde(default)]
+    pub added_at: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -220,6 +378,11 @@ pub struct SavedAlbum {
     pub album: Album,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct FollowedArtists {
+    pub artists: Page<Artist>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct FullAlbum {
     #[serde(flatten)]
@@ -305,12 +468,50 @@ pub struct Devices {
     pub devices: Vec<Device>,
 }
 
+// Unknown or unavailable ids come back as null
+#[derive(Deserialize, Debug, Clone)]
+pub struct Tracks {
+    pub tracks: Vec<Option<TrackItem>>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct PlayerQueue {
     pub currently_playing: TrackItem,
     pub queue: Vec<TrackItem>,
 }
 
+// Group sessions ("Jams") aren't part of the public web API, this is what the official clients get
+#[derive(Deserialize, Debug, Clone)]
+pub struct SocialSession {
+    pub session_id: String,
+    pub join_session_token: String,
+    #[serde(default)]
+    pub is_session_owner: bool,
+    #[serde(default)]
+    pub session_members: Vec<SocialSessionMember>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SocialSessionMember {
+    pub id: String,
+    pub display_name: Option<String>,
+}
+
+impl From<SocialSession> for GroupSession {
+    fn from(session: SocialSession) -> Self {
+        Self {
+            id: session.session_id,
+            join_token: session.join_session_token,
+            is_owner: session.is_session_owner,
+            members: session
+                .session_members
+                .into_iter()
+                .map(|m| m.display_name.unwrap_or(m.id))
+                .collect(),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct PlayerContext {
     #[serde(alias = "type")]
@@ -326,6 +527,7 @@ pub struct PlayerState {
     pub shuffle_state: bool,
     pub item: FailibleTrackItem,
     pub context: Option<PlayerContext>,
+    pub device: Option<Device>,
 }
 
 impl From<PlayerState> for ConnectPlayerState {
@@ -337,6 +539,7 @@ impl From<PlayerState> for ConnectPlayerState {
             shuffle_state,
             item,
             context,
+            device,
         }: PlayerState,
     ) -> Self {
         let repeat = match &repeat_state[..] {
@@ -352,7 +555,8 @@ impl From<PlayerState> for ConnectPlayerState {
             _ => None,
         });
         let shuffle = shuffle_state;
-        let current_song_id = item.get().map(|i| i.track.id);
+        let current_song_id = item.get().map(|i| i.track.canonical_id().to_string());
+        let volume = device.map(|d| d.volume_percent.min(100) as u8);
         Self {
             is_playing,
             progress_ms,
@@ -360,10 +564,52 @@ impl From<PlayerState> for ConnectPlayerState {
             shuffle,
             source,
             current_song_id,
+            volume,
+            queue: vec![],
         }
     }
 }
 
+// Only what DJ mode needs (see MixPoints), there's a lot more in there
+#[derive(Deserialize, Debug, Clone)]
+pub struct AudioAnalysis {
+    pub track: AnalyzedTrack,
+    pub bars: Vec<TimeInterval>,
+    pub beats: Vec<TimeInterval>,
+    pub sections: Vec<TimeInterval>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnalyzedTrack {
+    pub end_of_fade_in: f64,
+    pub start_of_fade_out: f64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TimeInterval {
+    pub start: f64,
+}
+
+impl AudioAnalysis {
+    // Bars are best to cut on, beats will do when there are none
+    pub fn mix_points(&self) -> Option<MixPoints> {
+        let starts = |intervals: &[TimeInterval]| -> Vec<f64> {
+            intervals.iter().map(|i| i.start).collect()
+        };
+        let grid = if self.bars.is_empty() {
+            starts(&self.beats)
+        } else {
+            starts(&self.bars)
+        };
+        MixPoints::find(
+            &grid,
+            &starts(&self.sections),
+            self.track.end_of_fade_in,
+            self.track.start_of_fade_out,
+        )
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct TopTracks {
     pub tracks: Vec<TrackItem>,
@@ -373,10 +619,34 @@ pub struct TopTracks {
 pub struct AlbumTrackItem {
     pub id: String,
     pub track_number: Option<usize>,
+    pub disc_number: Option<usize>,
     pub uri: String,
     pub name: String,
     pub duration_ms: i64,
     pub artists: Vec<Artist>,
+    // Only set when asking for a specific market
+    pub is_playable: Option<bool>,
+    // Only set when not asking for a market
+    pub available_markets: Option<Vec<String>>,
+    // What was asked for, when Spotify swapped in the version of the track available in the user's market
+    pub linked_from: Option<LinkedTrack>,
+    pub preview_url: Option<String>,
+}
+
+impl AlbumTrackItem {
+    // The id the rest of Spotify knows the track by: that's what we like, queue and add to playlists
+    pub fn canonical_id(&self) -> &str {
+        self.linked_from
+            .as_ref()
+            .map(|linked| &linked.id[..])
+            .unwrap_or(&self.id)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LinkedTrack {
+    pub id: String,
+    pub uri: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -384,6 +654,11 @@ pub struct TrackItem {
     #[serde(flatten)]
     pub track: AlbumTrackItem,
     pub album: Album,
+    #[serde(default)]
+    pub popularity: Option<u32>,
+    // Not part of the track itself, it comes from the playlist or the library it's in
+    #[serde(skip)]
+    pub added_at: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -409,6 +684,27 @@ impl FailibleTrackItem {
 pub struct RawSearchResults {
     pub albums: Option<Page<Album>>,
     pub artists: Option<Page<Artist>>,
+    // Items can be null in there, for some reason
+    pub playlists: Option<Page<Option<Playlist>>>,
+}
+
+impl RawSearchResults {
+    // Discover Weekly and friends have the same name for everyone, but so do lots of copycats:
+    // only the ones owned by Spotify are kept, sorted so that Daily Mixes come in order
+    pub fn spotify_playlists_named(self, name: &str) -> Vec<PlaylistDescription> {
+        let name = name.to_lowercase();
+        let mut playlists: Vec<PlaylistDescription> = self
+            .playlists
+            .unwrap_or_default()
+            .into_iter()
+            .flatten()
+            .filter(|p| p.owner.id == "spotify" && p.name.to_lowercase().starts_with(&name))
+            .map(|p| p.into())
+            .collect();
+        playlists.sort_by(|a, b| a.title.cmp(&b.title));
+        playlists.dedup_by(|a, b| a.id == b.id);
+        playlists
+    }
 }
 
 impl From<Artist> for ArtistSummary {
@@ -422,14 +718,24 @@ impl From<Artist> for ArtistSummary {
 impl TryFrom<PlaylistTrack> for TrackItem {
     type Error = ();
 
-    fn try_from(PlaylistTrack { is_local, track }: PlaylistTrack) -> Result<Self, Self::Error> {
-        track.ok_or(())?.get().filter(|_| !is_local).ok_or(())
+    fn try_from(
+        PlaylistTrack {
+            is_local,
+            track,
+            added_at,
+        }: PlaylistTrack,
+    ) -> Result<Self, Self::Error> {
+        let track = track.ok_or(())?.get().filter(|_| !is_local).ok_or(())?;
+        Ok(TrackItem { added_at, ..track })
     }
 }
 
 impl From<SavedTrack> for TrackItem {
     fn from(track: SavedTrack) -> Self {
-        track.track
+        TrackItem {
+            added_at: Some(track.added_at),
+            ..track.track
+        }
     }
 }
 
@@ -445,10 +751,11 @@ impl From<PlayerQueue> for Vec<SongDescription> {
         let queue: Vec<TrackItem> = queue
             .into_iter()
             .take_while(|e| {
-                if ids.contains(&e.track.id) {
+                let id = e.track.canonical_id();
+                if ids.contains(id) {
                     false
                 } else {
-                    ids.insert(e.track.id.clone());
+                    ids.insert(id.to_string());
                     true
                 }
             })
@@ -457,6 +764,12 @@ impl From<PlayerQueue> for Vec<SongDescription> {
     }
 }
 
+impl From<Tracks> for Vec<SongDescription> {
+    fn from(tracks: Tracks) -> Self {
+        Page::new(tracks.tracks.into_iter().flatten().collect()).into()
+    }
+}
+
 impl From<TopTracks> for Vec<SongDescription> {
     fn from(top_tracks: TopTracks) -> Self {
         Page::new(top_tracks.tracks).into()
@@ -478,6 +791,8 @@ impl From<(Page<AlbumTrackItem>, &Album)> for SongBatch {
         Self::from(page.map(|track| TrackItem {
             track,
             album: album.clone(),
+            popularity: None,
+            added_at: None,
         }))
     }
 }
@@ -495,7 +810,12 @@ where
         let songs = page
             .into_iter()
             .filter_map(|t| {
-                let TrackItem { track, album } = t.try_into().ok()?;
+                let TrackItem {
+                    track,
+                    album,
+                    popularity,
+                    added_at,
+                } = t.try_into().ok()?;
                 let AlbumTrackItem {
                     artists,
                     id,
@@ -503,7 +823,20 @@ where
                     name,
                     duration_ms,
                     track_number,
+                    disc_number,
+                    is_playable,
+                    available_markets,
+                    linked_from,
+                    preview_url,
                 } = track;
+                let (id, uri) = match linked_from {
+                    Some(LinkedTrack { id, uri }) => (id, uri),
+                    None => (id, uri),
+                };
+                // A track with no market at all can't be played from anywhere
+                let is_playable = is_playable
+                    .or_else(|| available_markets.map(|markets| !markets.is_empty()))
+                    .unwrap_or(true);
                 let artists = artists
                     .into_iter()
                     .map(|a| ArtistRef {
@@ -527,12 +860,18 @@ where
                 Some(SongDescription {
                     id,
                     track_number: track_number.map(|u| u as u32),
+                    disc_number: disc_number.map(|u| u as u32),
                     uri,
                     title: name,
                     artists,
                     album: album_ref,
                     duration: duration_ms as u32,
                     art,
+                    chapters: Default::default(),
+                    is_playable,
+                    preview_url,
+                    added_at,
+                    popularity,
                 })
             })
             .collect();
@@ -540,6 +879,64 @@ where
     }
 }
 
+impl From<Audiobook> for AudiobookDescription {
+    fn from(audiobook: Audiobook) -> Self {
+        let art = audiobook.best_image_for_width(200).map(|i| i.url.clone());
+        Self {
+            id: audiobook.id,
+            title: audiobook.name,
+            authors: audiobook.authors.into_iter().map(|a| a.name).collect(),
+            art,
+            total_chapters: audiobook.total_chapters,
+        }
+    }
+}
+
+impl Chapter {
+    // Authors don't have pages of their own, so they get no id
+    pub fn into_description(self, audiobook: &AudiobookDescription) -> ChapterDescription {
+        let art = self
+            .best_image_for_width(200)
+            .map(|i| i.url.clone())
+            .or_else(|| audiobook.art.clone());
+        let chapters = ChapterList::from_description(&self.description, self.duration_ms);
+        let resume_point = self.resume_point.unwrap_or(ResumePoint {
+            fully_played: false,
+            resume_position_ms: 0,
+        });
+        ChapterDescription {
+            song: SongDescription {
+                id: self.id,
+                track_number: self.chapter_number.map(|n| n + 1),
+                uri: self.uri,
+                title: self.name,
+                artists: audiobook
+                    .authors
+                    .iter()
+                    .map(|name| ArtistRef {
+                        id: String::new(),
+                        name: name.clone(),
+                    })
+                    .collect(),
+                album: AlbumRef {
+                    id: audiobook.id.clone(),
+                    name: audiobook.title.clone(),
+                },
+                duration: self.duration_ms,
+                art,
+                chapters,
+                is_playable: true,
+                disc_number: None,
+                preview_url: None,
+                added_at: None,
+                popularity: None,
+            },
+            resume_position: resume_point.resume_position_ms,
+            fully_played: resume_point.fully_played,
+        }
+    }
+}
+
 impl TryFrom<Album> for SongBatch {
     type Error = ();
 
@@ -618,6 +1015,7 @@ impl From<Playlist> for PlaylistDescription {
             name,
             tracks,
             owner,
+            snapshot_id,
             ..
         } = playlist;
         let PlaylistOwner {
@@ -634,6 +1032,7 @@ impl From<Playlist> for PlaylistDescription {
                 id: owner_id,
                 display_name,
             },
+            snapshot_id,
         }
     }
 }
@@ -641,7 +1040,11 @@ impl From<Playlist> for PlaylistDescription {
 impl From<Device> for ConnectDevice {
     fn from(
         Device {
-            id, name, type_, ..
+            id,
+            name,
+            type_,
+            is_active,
+            ..
         }: Device,
     ) -> Self {
         let kind = match type_.to_lowercase().as_str() {
@@ -654,6 +1057,7 @@ impl From<Device> for ConnectDevice {
             id,
             label: name,
             kind,
+            is_active,
         }
     }
 }
@@ -663,6 +1067,86 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_view_shelves() {
+        let view = r#"{"id":"made-for-x","name":"Made For You","content":{"items":[
+            {"type":"view","id":"shelf","name":"Your top mixes","content":{"items":[
+                {"type":"playlist","id":"mix","name":"Daily Mix 1","images":[],"tracks":{"total":50},"owner":{"id":"spotify","display_name":"Spotify"}},
+                {"type":"album","id":"album"}
+            ],"total":2}},
+            {"type":"view","id":"empty","name":"Nothing for us","content":{"items":[{"type":"show","id":"show"}],"total":1}}
+        ],"total":2}}"#;
+        let view: View = serde_json::from_str(view).unwrap();
+        let shelves = view.shelves();
+        assert_eq!(shelves.len(), 1);
+        assert_eq!(shelves[0].title, "Your top mixes");
+        assert_eq!(shelves[0].playlists.len(), 1);
+        assert_eq!(shelves[0].playlists[0].id, "mix");
+    }
+
+    #[test]
+    fn test_spotify_playlists_named() {
+        let playlist = |id: &str, name: &str, owner: &str| {
+            format!(
+                r#"{{"id":"{id}","name":"{name}","images":[],"tracks":{{"total":30}},"owner":{{"id":"{owner}","display_name":"{owner}"}}}}"#
+            )
+        };
+        let results = format!(
+            r#"{{"playlists":{{"items":[{},null,{},{}],"total":4}}}}"#,
+            playlist("dm2", "Daily Mix 2", "spotify"),
+            playlist("fake", "Daily Mix 1", "someone"),
+            playlist("dm1", "Daily Mix 1", "spotify"),
+        );
+        let results: RawSearchResults = serde_json::from_str(&results).unwrap();
+        let ids: Vec<String> = results
+            .spotify_playlists_named("daily mix")
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(ids, vec!["dm1", "dm2"]);
+    }
+
+    #[test]
+    fn test_next_page_token() {
+        let page: Page<u32> =
+            serde_json::from_str(r#"{"items":[1,2],"offset":0,"limit":2,"total":3}"#).unwrap();
+        assert_eq!(page.next_token(), Some(PageToken::Offset(2)));
+
+        let page: Page<u32> =
+            serde_json::from_str(r#"{"items":[3],"offset":2,"limit":2,"total":3}"#).unwrap();
+        assert_eq!(page.next_token(), None);
+
+        let page: Page<u32> = serde_json::from_str(
+            r#"{"items":[1,2],"limit":2,"next":"https://...","cursors":{"after":"abc"}}"#,
+        )
+        .unwrap();
+        assert_eq!(page.next_token(), Some(PageToken::After("abc".to_string())));
+
+        let page: Page<u32> =
+            serde_json::from_str(r#"{"items":[],"limit":2,"cursors":{"after":null}}"#).unwrap();
+        assert_eq!(page.next_token(), None);
+    }
+
+    #[test]
+    fn test_chapter_description() {
+        let audiobook = AudiobookDescription {
+            id: "book".to_string(),
+            title: "A book".to_string(),
+            authors: vec!["Someone".to_string()],
+            art: Some("cover".to_string()),
+            total_chapters: 12,
+        };
+        let chapter = r#"{"id":"ch","uri":"spotify:episode:ch","name":"Chapter 1","duration_ms":1800000,
+            "chapter_number":0,"images":[],"resume_point":{"fully_played":false,"resume_position_ms":60000}}"#;
+        let chapter: Chapter = serde_json::from_str(chapter).unwrap();
+        let chapter = chapter.into_description(&audiobook);
+        assert_eq!(chapter.song.track_number, Some(1));
+        assert_eq!(chapter.song.album.name, "A book");
+        assert_eq!(chapter.song.art.as_deref(), Some("cover"));
+        assert_eq!(chapter.resume_position, 60000);
+        assert!(!chapter.fully_played);
+    }
+
     #[test]
     fn test_playlist_track_null() {
         let track = r#"{"is_local": false, "track": null}"#;
@@ -686,4 +1170,27 @@ mod tests {
         let track_item: Option<TrackItem> = deserialized.try_into().ok();
         assert!(track_item.is_some());
     }
+
+    #[test]
+    fn test_playlist_track_added_at() {
+        let track = r#"{"is_local":false,"added_at":"2023-03-12T10:00:00Z","track":{"album":{"artists":[],"id":"","images":[],"name":"Album"},"artists":[],"duration_ms":1,"id":"id","name":"","uri":"","popularity":54}}"#;
+        let deserialized: PlaylistTrack = serde_json::from_str(track).unwrap();
+        let songs = Vec::<SongDescription>::from(Page::new(vec![deserialized]));
+        assert_eq!(songs[0].added_at.as_deref(), Some("2023-03-12T10:00:00Z"));
+        assert_eq!(songs[0].popularity, Some(54));
+    }
+
+    #[test]
+    fn test_relinked_tracks_keep_their_original_id() {
+        let tracks = r#"{"tracks":[{
+            "id":"relinked","uri":"spotify:track:relinked","name":"Song","duration_ms":1000,
+            "track_number":1,"artists":[],"is_playable":true,
+            "linked_from":{"id":"original","uri":"spotify:track:original"},
+            "album":{"id":"album","artists":[],"name":"Album","images":[]}
+        }]}"#;
+        let tracks: Tracks = serde_json::from_str(tracks).unwrap();
+        let songs = Vec::<SongDescription>::from(tracks);
+        assert_eq!(songs[0].id, "original");
+        assert_eq!(songs[0].uri, "spotify:track:original");
+    }
 }
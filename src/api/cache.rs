@@ -5,14 +5,22 @@ use async_std::path::PathBuf;
 use async_std::prelude::*;
 use core::mem::size_of;
 use futures::join;
-use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
 use std::convert::From;
 use std::future::Future;
+use std::hash::Hasher;
+use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
+use crate::app::models::PageToken;
+
 const EXPIRY_FILE_EXT: &str = ".expiry";
 
+// Bumped whenever CacheKey::file_name changes, what was cached under the old names is then thrown away
+const CACHE_VERSION: &str = "2";
+const VERSION_FILE: &str = ".version";
+
 #[derive(Error, Debug)]
 pub enum CacheError {
     #[error("No content available")]
@@ -29,6 +37,15 @@ pub enum CacheError {
 
 pub type ETag = String;
 
+// From a Cache-Control header, in seconds
+pub fn max_age(cache_control: &str) -> Option<u64> {
+    cache_control
+        .split(',')
+        .find(|s| s.trim().starts_with("max-age="))
+        .and_then(|s| s.split('=').nth(1))
+        .and_then(|s| u64::from_str(s).ok())
+}
+
 pub enum CacheFile {
     Fresh(Vec<u8>),
     Expired(Vec<u8>, Option<ETag>),
@@ -69,7 +86,7 @@ impl CacheExpiry {
         }
     }
 
-    fn etag(&self) -> Option<&String> {
+    pub fn etag(&self) -> Option<&String> {
         match self {
             Self::Never => None,
             Self::AtUnixTimestamp(_, ref etag) => etag.as_ref(),
@@ -77,6 +94,232 @@ impl CacheExpiry {
     }
 }
 
+// What's in the cache, by kind, so that a whole kind can be expired or cleared at once (see CacheSelection)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheCategory {
+    SavedAlbums,
+    SavedArtists,
+    SavedTracks,
+    SavedPlaylists,
+    SavedAudiobooks,
+    Album,
+    AlbumLiked,
+    AlbumTracks,
+    Playlist,
+    PlaylistTracks,
+    Artist,
+    ArtistAlbums,
+    ArtistFollowed,
+    ArtistTopTracks,
+    User,
+    UserPlaylists,
+    View,
+    PinnedSearch,
+    Audiobook,
+    AudiobookChapters,
+    AudioAnalysis,
+    Image,
+    Thumbnail,
+}
+
+impl CacheCategory {
+    // File names start with this and a dash, which none of these contain
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::SavedAlbums => "me_albums",
+            Self::SavedArtists => "me_artists",
+            Self::SavedTracks => "me_tracks",
+            Self::SavedPlaylists => "me_playlists",
+            Self::SavedAudiobooks => "me_audiobooks",
+            Self::Album => "album",
+            Self::AlbumLiked => "album_liked",
+            Self::AlbumTracks => "album_tracks",
+            Self::Playlist => "playlist",
+            Self::PlaylistTracks => "playlist_tracks",
+            Self::Artist => "artist",
+            Self::ArtistAlbums => "artist_albums",
+            Self::ArtistFollowed => "artist_followed",
+            Self::ArtistTopTracks => "artist_top_tracks",
+            Self::User => "user",
+            Self::UserPlaylists => "user_playlists",
+            Self::View => "view",
+            Self::PinnedSearch => "pinned",
+            Self::Audiobook => "audiobook",
+            Self::AudiobookChapters => "audiobook_chapters",
+            Self::AudioAnalysis => "audio_analysis",
+            Self::Image => "img",
+            Self::Thumbnail => "thumb",
+        }
+    }
+
+    // Analyses don't change once made, unlike anything in the library.
+    // Pictures can (a new playlist cover...), they're revalidated with their etag like API responses.
+    fn never_expires(self) -> bool {
+        matches!(self, Self::AudioAnalysis)
+    }
+
+    pub fn all(self) -> CacheSelection {
+        CacheSelection {
+            category: self,
+            id: None,
+        }
+    }
+
+    // Only what's cached about one thing (a playlist, an artist...)
+    pub fn about(self, id: &str) -> CacheSelection {
+        CacheSelection {
+            category: self,
+            id: Some(escape(id)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheSelection {
+    category: CacheCategory,
+    id: Option<String>,
+}
+
+impl CacheSelection {
+    fn matches(&self, file_name: &str) -> bool {
+        let Some((prefix, rest)) = file_name.split_once('-') else {
+            return false;
+        };
+        if prefix != self.category.prefix() {
+            return false;
+        }
+        match &self.id {
+            None => true,
+            Some(id) => rest.split(['-', '.']).next() == Some(id.as_str()),
+        }
+    }
+}
+
+// The one way to name something in the cache, see CacheKey::file_name
+pub enum CacheKey<'a> {
+    SavedAlbums(&'a PageToken, usize),
+    SavedArtists(&'a PageToken, usize),
+    SavedTracks(usize, usize),
+    SavedPlaylists(usize, usize),
+    SavedAudiobooks(usize, usize),
+    Album(&'a str),
+    AlbumLiked(&'a str),
+    AlbumTracks(&'a str, usize, usize),
+    Playlist(&'a str),
+    PlaylistTracks(&'a str, usize, usize),
+    Artist(&'a str),
+    ArtistAlbums(&'a str, usize, usize),
+    // Cleared along with the albums
+    ArtistSingles(&'a str, usize, usize),
+    ArtistFollowed(&'a str),
+    ArtistTopTracks(&'a str),
+    User(&'a str),
+    UserPlaylists(&'a str, usize, usize),
+    View(&'a str, usize, usize),
+    // Searches made for a user, by user name and query
+    PinnedSearch(&'a str, &'a str),
+    Audiobook(&'a str),
+    AudiobookChapters(&'a str, usize, usize),
+    AudioAnalysis(&'a str),
+    // A downloaded image, by url and file extension
+    Image(&'a str, &'a str),
+    // A scaled down image, by url and size
+    Thumbnail(&'a str, i32, i32),
+}
+
+// Ids (user names, search queries...) can contain anything, but dashes and dots separate the parts of a name
+fn escape(id: &str) -> String {
+    id.replace('%', "%25")
+        .replace('-', "%2D")
+        .replace('.', "%2E")
+        .replace('/', "%2F")
+}
+
+fn url_hash(url: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(url.as_bytes());
+    hasher.finish().to_string()
+}
+
+impl<'a> CacheKey<'a> {
+    pub fn category(&self) -> CacheCategory {
+        match self {
+            Self::SavedAlbums(..) => CacheCategory::SavedAlbums,
+            Self::SavedArtists(..) => CacheCategory::SavedArtists,
+            Self::SavedTracks(..) => CacheCategory::SavedTracks,
+            Self::SavedPlaylists(..) => CacheCategory::SavedPlaylists,
+            Self::SavedAudiobooks(..) => CacheCategory::SavedAudiobooks,
+            Self::Album(_) => CacheCategory::Album,
+            Self::AlbumLiked(_) => CacheCategory::AlbumLiked,
+            Self::AlbumTracks(..) => CacheCategory::AlbumTracks,
+            Self::Playlist(_) => CacheCategory::Playlist,
+            Self::PlaylistTracks(..) => CacheCategory::PlaylistTracks,
+            Self::Artist(_) => CacheCategory::Artist,
+            Self::ArtistAlbums(..) | Self::ArtistSingles(..) => CacheCategory::ArtistAlbums,
+            Self::ArtistFollowed(_) => CacheCategory::ArtistFollowed,
+            Self::ArtistTopTracks(_) => CacheCategory::ArtistTopTracks,
+            Self::User(_) => CacheCategory::User,
+            Self::UserPlaylists(..) => CacheCategory::UserPlaylists,
+            Self::View(..) => CacheCategory::View,
+            Self::PinnedSearch(..) => CacheCategory::PinnedSearch,
+            Self::Audiobook(_) => CacheCategory::Audiobook,
+            Self::AudiobookChapters(..) => CacheCategory::AudiobookChapters,
+            Self::AudioAnalysis(_) => CacheCategory::AudioAnalysis,
+            Self::Image(..) => CacheCategory::Image,
+            Self::Thumbnail(..) => CacheCategory::Thumbnail,
+        }
+    }
+
+    // [category prefix]-[id]-[offset]-[limit].json and the like, dashes only ever separating the parts
+    pub fn file_name(&self) -> String {
+        let prefix = self.category().prefix();
+        match self {
+            Self::SavedAlbums(page, limit) | Self::SavedArtists(page, limit) => {
+                format!("{prefix}-{}-{limit}.json", escape(&page.key()))
+            }
+            Self::SavedTracks(offset, limit)
+            | Self::SavedPlaylists(offset, limit)
+            | Self::SavedAudiobooks(offset, limit) => format!("{prefix}-{offset}-{limit}.json"),
+            Self::Album(id)
+            | Self::AlbumLiked(id)
+            | Self::Playlist(id)
+            | Self::Artist(id)
+            | Self::ArtistFollowed(id)
+            | Self::ArtistTopTracks(id)
+            | Self::User(id)
+            | Self::Audiobook(id)
+            | Self::AudioAnalysis(id) => format!("{prefix}-{}.json", escape(id)),
+            Self::AlbumTracks(id, offset, limit)
+            | Self::PlaylistTracks(id, offset, limit)
+            | Self::ArtistAlbums(id, offset, limit)
+            | Self::UserPlaylists(id, offset, limit)
+            | Self::AudiobookChapters(id, offset, limit)
+            | Self::View(id, offset, limit) => {
+                format!("{prefix}-{}-{offset}-{limit}.json", escape(id))
+            }
+            Self::ArtistSingles(id, offset, limit) => {
+                format!("{prefix}-{}-singles-{offset}-{limit}.json", escape(id))
+            }
+            Self::PinnedSearch(user, query) => {
+                let query = query.to_lowercase().replace(' ', "_");
+                format!("{prefix}-{}-{}.json", escape(user), escape(&query))
+            }
+            Self::Image(url, ext) => format!("{prefix}-{}.{ext}", url_hash(url)),
+            Self::Thumbnail(url, width, height) => {
+                format!("{prefix}-{}.{width}x{height}.png", url_hash(url))
+            }
+        }
+    }
+
+    fn expiry(&self, suggested: CacheExpiry) -> CacheExpiry {
+        if self.category().never_expires() {
+            CacheExpiry::Never
+        } else {
+            suggested
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct CacheManager {
     root: PathBuf,
@@ -84,17 +327,37 @@ pub struct CacheManager {
 
 impl CacheManager {
     pub fn for_dir(dir: &str) -> Option<Self> {
-        let root: PathBuf = glib::user_cache_dir().into();
-        let root = root.join(dir);
+        let root = glib::user_cache_dir().join(dir);
         let mask = 0o744;
 
         glib::mkdir_with_parents(&root, mask);
+        Self::upgrade(&root);
+
+        Some(Self { root: root.into() })
+    }
 
-        Some(Self { root })
+    // Old names would never be read nor cleared again, so they're all removed once
+    fn upgrade(root: &std::path::Path) {
+        let version_file = root.join(VERSION_FILE);
+        if std::fs::read_to_string(&version_file).ok().as_deref() == Some(CACHE_VERSION) {
+            return;
+        }
+        info!("Clearing the cache in {}...", root.display());
+        if let Ok(entries) = std::fs::read_dir(root) {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+                .for_each(|entry| {
+                    let _ = std::fs::remove_file(entry.path());
+                });
+        }
+        if let Err(e) = std::fs::write(&version_file, CACHE_VERSION) {
+            warn!("Could not write {}: {}", version_file.display(), e);
+        }
     }
 
-    fn cache_path(&self, resource: &str) -> PathBuf {
-        self.root.join(resource)
+    pub fn cache_path(&self, key: &CacheKey<'_>) -> PathBuf {
+        self.root.join(key.file_name())
     }
 
     fn cache_meta_path(&self, resource: &str) -> PathBuf {
@@ -125,17 +388,25 @@ impl CacheManager {
         }
     }
 
+    // Never for what was cached without one
+    pub async fn expiry(&self, key: &CacheKey<'_>) -> CacheExpiry {
+        self.read_expiry_file(&key.file_name())
+            .await
+            .unwrap_or(CacheExpiry::Never)
+    }
+
     pub async fn read_cache_file(
         &self,
-        resource: &str,
+        key: &CacheKey<'_>,
         policy: CachePolicy,
     ) -> Result<CacheFile, CacheError> {
         if matches!(policy, CachePolicy::IgnoreCached) {
             return Ok(CacheFile::None);
         }
 
-        let path = self.cache_path(resource);
-        let (file, expiry) = join!(fs::read(&path), self.read_expiry_file(resource));
+        let resource = key.file_name();
+        let path = self.cache_path(key);
+        let (file, expiry) = join!(fs::read(&path), self.read_expiry_file(&resource));
 
         match (file, policy) {
             (Ok(buf), CachePolicy::IgnoreExpiry) => Ok(CacheFile::Fresh(buf)),
@@ -147,7 +418,11 @@ impl CacheManager {
             (Ok(buf), CachePolicy::Default) => {
                 let expiry = expiry?;
                 let etag = expiry.etag().cloned();
-                Ok(if expiry.is_expired() {
+                // Only what never expires is written without an expiry file. Anything else without one
+                // comes from an older version (artwork used to be kept forever), and gets checked once
+                let legacy =
+                    matches!(expiry, CacheExpiry::Never) && !key.category().never_expires();
+                Ok(if legacy || expiry.is_expired() {
                     CacheFile::Expired(buf, etag)
                 } else {
                     CacheFile::Fresh(buf)
@@ -180,7 +455,7 @@ impl CacheManager {
         Ok(())
     }
 
-    pub async fn clear_cache_pattern(&self, regex: &Regex) -> Result<(), CacheError> {
+    pub async fn clear(&self, selections: &[CacheSelection]) -> Result<(), CacheError> {
         let mut entries = fs::read_dir(&self.root)
             .await
             .map_err(CacheError::ReadError)?;
@@ -189,7 +464,8 @@ impl CacheManager {
             let matches = entry
                 .file_name()
                 .to_str()
-                .map(|s| regex.is_match(s))
+                .filter(|s| !s.ends_with(EXPIRY_FILE_EXT))
+                .map(|s| selections.iter().any(|selection| selection.matches(s)))
                 .unwrap_or(false);
             if matches {
                 info!("Removing {}...", entry.file_name().to_str().unwrap_or(""));
@@ -209,20 +485,27 @@ impl CacheManager {
         Ok(())
     }
 
-    pub async fn set_expired_pattern(&self, regex: &Regex) -> Result<(), CacheError> {
+    // Etags are kept, so that the next read revalidates (as with CachePolicy::Revalidate)
+    // and whatever didn't change comes back as not modified
+    pub async fn set_expired(&self, selections: &[CacheSelection]) -> Result<(), CacheError> {
         let mut entries = fs::read_dir(&self.root)
             .await
             .map_err(CacheError::ReadError)?;
 
         while let Some(Ok(entry)) = entries.next().await {
-            let matches = entry
+            let resource = entry
                 .file_name()
                 .to_str()
                 .and_then(|s| s.strip_suffix(EXPIRY_FILE_EXT))
-                .map(|s| regex.is_match(s))
-                .unwrap_or(false);
-            if matches {
-                self.set_expiry_for_path(&entry.path(), CacheExpiry::expire_in_seconds(0, None))
+                .filter(|s| selections.iter().any(|selection| selection.matches(s)))
+                .map(|s| s.to_string());
+            if let Some(resource) = resource {
+                let etag = self
+                    .read_expiry_file(&resource)
+                    .await
+                    .ok()
+                    .and_then(|expiry| expiry.etag().cloned());
+                self.set_expiry_for_path(&entry.path(), CacheExpiry::expire_in_seconds(0, etag))
                     .await?;
             }
         }
@@ -230,17 +513,18 @@ impl CacheManager {
         Ok(())
     }
 
+    // The expiry is only a suggestion, some categories never expire
     pub async fn write_cache_file(
         &self,
-        resource: &str,
+        key: &CacheKey<'_>,
         content: &[u8],
         expiry: CacheExpiry,
     ) -> Result<(), CacheError> {
-        let file = self.cache_path(resource);
-        let meta = self.cache_meta_path(resource);
+        let file = self.cache_path(key);
+        let meta = self.cache_meta_path(&key.file_name());
         let (r1, r2) = join!(
             fs::write(&file, content),
-            self.set_expiry_for_path(&meta, expiry)
+            self.set_expiry_for_path(&meta, key.expiry(expiry))
         );
         r1.map_err(CacheError::WriteError)?;
         r2?;
@@ -249,7 +533,7 @@ impl CacheManager {
 
     pub async fn get_or_write<O, F, E>(
         &self,
-        resource: &str,
+        key: &CacheKey<'_>,
         policy: CachePolicy,
         fetch: F,
     ) -> Result<Vec<u8>, E>
@@ -258,25 +542,41 @@ impl CacheManager {
         F: FnOnce(Option<ETag>) -> O,
         E: From<CacheError>,
     {
-        let file = self.read_cache_file(resource, policy).await?;
+        let (buf, _) = self.get_or_write_replacing(key, policy, fetch).await?;
+        Ok(buf)
+    }
+
+    // Same as above, but also gives back what was cached before, if the fetch replaced it
+    pub async fn get_or_write_replacing<O, F, E>(
+        &self,
+        key: &CacheKey<'_>,
+        policy: CachePolicy,
+        fetch: F,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), E>
+    where
+        O: Future<Output = Result<FetchResult, E>>,
+        F: FnOnce(Option<ETag>) -> O,
+        E: From<CacheError>,
+    {
+        let file = self.read_cache_file(key, policy).await?;
         match file {
-            CacheFile::Fresh(buf) => Ok(buf),
+            CacheFile::Fresh(buf) => Ok((buf, None)),
             CacheFile::Expired(buf, etag) => match fetch(etag).await? {
                 FetchResult::NotModified(expiry) => {
-                    let meta = self.cache_meta_path(resource);
-                    self.set_expiry_for_path(&meta, expiry).await?;
-                    Ok(buf)
+                    let meta = self.cache_meta_path(&key.file_name());
+                    self.set_expiry_for_path(&meta, key.expiry(expiry)).await?;
+                    Ok((buf, None))
                 }
                 FetchResult::Modified(fresh, expiry) => {
-                    self.write_cache_file(resource, &fresh, expiry).await?;
-                    Ok(fresh)
+                    self.write_cache_file(key, &fresh, expiry).await?;
+                    Ok((fresh, Some(buf)))
                 }
             },
             CacheFile::None => match fetch(None).await? {
                 FetchResult::NotModified(_) => Err(E::from(CacheError::NoContent)),
                 FetchResult::Modified(fresh, expiry) => {
-                    self.write_cache_file(resource, &fresh, expiry).await?;
-                    Ok(fresh)
+                    self.write_cache_file(key, &fresh, expiry).await?;
+                    Ok((fresh, None))
                 }
             },
         }
@@ -287,3 +587,72 @@ pub enum FetchResult {
     NotModified(CacheExpiry),
     Modified(Vec<u8>, CacheExpiry),
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_cache_selection() {
+        let playlist = CacheKey::Playlist("abc").file_name();
+        let tracks = CacheKey::PlaylistTracks("abc", 0, 50).file_name();
+        let other_tracks = CacheKey::PlaylistTracks("abcd", 0, 50).file_name();
+        assert_eq!(tracks, "playlist_tracks-abc-0-50.json");
+
+        let selection = CacheCategory::PlaylistTracks.about("abc");
+        assert!(selection.matches(&tracks));
+        assert!(!selection.matches(&other_tracks));
+        assert!(!selection.matches(&playlist));
+        assert!(CacheCategory::Playlist.about("abc").matches(&playlist));
+
+        // Prefixes that start the same don't get mixed up
+        let album = CacheKey::Album("abc").file_name();
+        assert!(CacheCategory::Album.all().matches(&album));
+        assert!(!CacheCategory::Album
+            .all()
+            .matches(&CacheKey::AlbumLiked("abc").file_name()));
+        assert!(!CacheCategory::Album
+            .all()
+            .matches(&CacheKey::AlbumTracks("abc", 0, 50).file_name()));
+
+        // Ids with dashes or dots in them only match themselves
+        let user = CacheCategory::UserPlaylists.about("jane");
+        assert!(user.matches(&CacheKey::UserPlaylists("jane", 0, 50).file_name()));
+        assert!(!user.matches(&CacheKey::UserPlaylists("jane-doe", 0, 50).file_name()));
+        assert!(!user.matches(&CacheKey::UserPlaylists("jane.doe", 0, 50).file_name()));
+        assert!(CacheCategory::UserPlaylists
+            .about("jane-doe")
+            .matches(&CacheKey::UserPlaylists("jane-doe", 0, 50).file_name()));
+        assert!(CacheCategory::View
+            .about("made-for-x")
+            .matches(&CacheKey::View("made-for-x", 0, 20).file_name()));
+
+        // Searches made for someone stay theirs
+        let pinned = CacheKey::PinnedSearch("jane", "Discover Weekly").file_name();
+        assert_eq!(pinned, "pinned-jane-discover_weekly.json");
+        assert!(CacheCategory::PinnedSearch.about("jane").matches(&pinned));
+        assert!(!CacheCategory::PinnedSearch.about("john").matches(&pinned));
+    }
+
+    #[test]
+    fn test_never_expires() {
+        let soon = CacheExpiry::expire_in_seconds(60, None);
+        assert_eq!(
+            CacheKey::AudioAnalysis("abc").expiry(soon.clone()),
+            CacheExpiry::Never
+        );
+        assert_eq!(CacheKey::Album("abc").expiry(soon.clone()), soon);
+        assert_eq!(
+            CacheKey::Image("https://i.scdn.co/image/x", "jpg").expiry(soon.clone()),
+            soon
+        );
+    }
+
+    #[test]
+    fn test_max_age() {
+        assert_eq!(max_age("public, max-age=31536000"), Some(31536000));
+        assert_eq!(max_age("max-age=10"), Some(10));
+        assert_eq!(max_age("no-cache"), None);
+    }
+}
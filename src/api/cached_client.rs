@@ -1,13 +1,21 @@
-use futures::future::BoxFuture;
+use futures::future::{try_join_all, BoxFuture};
 use futures::{join, FutureExt};
-use regex::Regex;
 use serde::de::DeserializeOwned;
 use serde_json::from_slice;
 use std::convert::Into;
 use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use super::cache::{CacheExpiry, CacheManager, CachePolicy, FetchResult};
+use super::cache::{
+    CacheCategory, CacheExpiry, CacheKey, CacheManager, CachePolicy, CacheSelection, FetchResult,
+};
 use super::client::*;
+use super::dealer::{listen_player_events, PlayerEvent};
+use super::health::HealthChanged;
+use super::lookup::BatchedLookup;
+use super::pending_writes::{PendingWrite, PendingWrites};
 use crate::app::models::*;
 
 pub type SpotifyResult<T> = Result<T, SpotifyApiError>;
@@ -33,14 +41,38 @@ pub trait SpotifyApiClient {
         limit: usize,
     ) -> BoxFuture<SpotifyResult<SongBatch>>;
 
-    fn get_saved_albums(
+    // Along with the batch that was cached until then, if the playlist changed since
+    fn get_playlist_tracks_with_previous(
         &self,
+        id: &str,
         offset: usize,
         limit: usize,
-    ) -> BoxFuture<SpotifyResult<Vec<AlbumDescription>>>;
+    ) -> BoxFuture<SpotifyResult<(SongBatch, Option<SongBatch>)>>;
+
+    fn get_saved_albums(
+        &self,
+        page: PageToken,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Paged<AlbumDescription>>>;
 
     fn get_saved_tracks(&self, offset: usize, limit: usize) -> BoxFuture<SpotifyResult<SongBatch>>;
 
+    fn get_followed_artists(
+        &self,
+        page: PageToken,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Paged<ArtistSummary>>>;
+
+    fn get_tracks(&self, ids: Vec<String>) -> BoxFuture<SpotifyResult<Vec<SongDescription>>>;
+
+    // From the audio analysis, None if there's no good place to mix that song
+    fn get_mix_points(&self, id: &str) -> BoxFuture<SpotifyResult<Option<MixPoints>>>;
+
+    fn are_tracks_saved(&self, ids: Vec<String>) -> BoxFuture<SpotifyResult<Vec<bool>>>;
+
+    // Lookups made within a short window are answered by a single are_tracks_saved call
+    fn is_track_saved(&self, id: &str) -> BoxFuture<SpotifyResult<bool>>;
+
     fn save_album(&self, id: &str) -> BoxFuture<SpotifyResult<AlbumDescription>>;
 
     fn save_tracks(&self, ids: Vec<String>) -> BoxFuture<SpotifyResult<()>>;
@@ -49,21 +81,65 @@ pub trait SpotifyApiClient {
 
     fn remove_saved_tracks(&self, ids: Vec<String>) -> BoxFuture<SpotifyResult<()>>;
 
+    fn follow_artist(&self, id: &str) -> BoxFuture<SpotifyResult<()>>;
+
+    fn unfollow_artist(&self, id: &str) -> BoxFuture<SpotifyResult<()>>;
+
     fn get_saved_playlists(
         &self,
         offset: usize,
         limit: usize,
     ) -> BoxFuture<SpotifyResult<Vec<PlaylistDescription>>>;
 
+    // Rows of playlists made for the user, each given with its first `limit` playlists
+    fn get_home_shelves(&self, limit: usize) -> BoxFuture<SpotifyResult<Vec<HomeShelf>>>;
+
+    fn get_home_shelf(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Vec<PlaylistDescription>>>;
+
+    // Discover Weekly, Release Radar and the Daily Mixes, as far as we can find them
+    fn get_pinned_playlists(&self) -> BoxFuture<SpotifyResult<Vec<PlaylistDescription>>>;
+
+    fn get_saved_audiobooks(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Vec<AudiobookDescription>>>;
+
+    fn get_audiobook_chapters(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Vec<ChapterDescription>>>;
+
+    // Made later if we're offline, like saving and unsaving tracks
     fn add_to_playlist(&self, id: &str, uris: Vec<String>) -> BoxFuture<SpotifyResult<()>>;
 
+    // Makes the writes that were put off while offline, and tells how many had to be dropped
+    // (say, because the playlist they were for isn't there anymore)
+    fn replay_pending_writes(&self) -> BoxFuture<SpotifyResult<usize>>;
+
+    // For a hard refresh: what's cached about these gets revalidated the next time it's asked for
+    fn revalidate(&self, stale: Vec<CacheSelection>) -> BoxFuture<SpotifyResult<()>>;
+
     fn create_new_playlist(
         &self,
         name: &str,
         user_id: &str,
     ) -> BoxFuture<SpotifyResult<PlaylistDescription>>;
 
-    fn remove_from_playlist(&self, id: &str, uris: Vec<String>) -> BoxFuture<SpotifyResult<()>>;
+    // Returns the snapshot id of the playlist after the removal
+    fn remove_from_playlist(
+        &self,
+        id: &str,
+        uris: Vec<String>,
+        snapshot_id: Option<String>,
+    ) -> BoxFuture<SpotifyResult<String>>;
 
     fn update_playlist_details(&self, id: &str, name: String) -> BoxFuture<SpotifyResult<()>>;
 
@@ -81,6 +157,13 @@ pub trait SpotifyApiClient {
         limit: usize,
     ) -> BoxFuture<SpotifyResult<Vec<AlbumDescription>>>;
 
+    fn get_artist_singles(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Vec<AlbumDescription>>>;
+
     fn get_user(&self, id: &str) -> BoxFuture<SpotifyResult<UserDescription>>;
 
     fn get_user_playlists(
@@ -92,17 +175,36 @@ pub trait SpotifyApiClient {
 
     fn list_available_devices(&self) -> BoxFuture<SpotifyResult<Vec<ConnectDevice>>>;
 
+    fn start_group_session(&self, device_id: String) -> BoxFuture<SpotifyResult<GroupSession>>;
+
+    fn join_group_session(&self, token: String) -> BoxFuture<SpotifyResult<GroupSession>>;
+
+    fn leave_group_session(&self, session_id: String) -> BoxFuture<SpotifyResult<()>>;
+
     fn get_player_queue(&self) -> BoxFuture<SpotifyResult<Vec<SongDescription>>>;
 
     fn update_token(&self, token: String);
 
+    // Refuse edits to playlists and anything that removes from the library, for as long as Spot is locked
+    fn set_read_only(&self, read_only: bool);
+
+    // Changed from the settings, applies to the requests made afterwards
+    fn set_retry_policy(&self, retry_policy: RetryPolicy);
+
+    // Told whenever the Web API stops answering or starts again
+    fn connect_web_api_health_changed(&self, f: HealthChanged);
+
+    // Whose writes made while offline are kept and replayed; logging out (None) gives up on those of the previous user
+    fn set_user(&self, username: Option<String>);
+
     fn player_pause(&self, device_id: String) -> BoxFuture<SpotifyResult<()>>;
 
     fn player_resume(&self, device_id: String) -> BoxFuture<SpotifyResult<()>>;
 
-    #[allow(dead_code)]
     fn player_next(&self, device_id: String) -> BoxFuture<SpotifyResult<()>>;
 
+    fn player_add_to_queue(&self, device_id: String, uri: String) -> BoxFuture<SpotifyResult<()>>;
+
     fn player_seek(&self, device_id: String, pos: usize) -> BoxFuture<SpotifyResult<()>>;
 
     fn player_repeat(&self, device_id: String, mode: RepeatMode) -> BoxFuture<SpotifyResult<()>>;
@@ -116,6 +218,7 @@ pub trait SpotifyApiClient {
         device_id: String,
         context: String,
         offset: usize,
+        position_ms: Option<u32>,
     ) -> BoxFuture<SpotifyResult<()>>;
 
     fn player_play_no_context(
@@ -123,79 +226,213 @@ pub trait SpotifyApiClient {
         device_id: String,
         uris: Vec<String>,
         offset: usize,
+        position_ms: Option<u32>,
     ) -> BoxFuture<SpotifyResult<()>>;
 
     fn player_state(&self) -> BoxFuture<SpotifyResult<ConnectPlayerState>>;
+
+    // Runs for as long as the connection stays open
+    fn listen_player_events(
+        &self,
+        on_event: Box<dyn FnMut(PlayerEvent) + Send>,
+    ) -> BoxFuture<SpotifyResult<()>>;
 }
 
-enum SpotCacheKey<'a> {
-    SavedAlbums(usize, usize),
-    SavedTracks(usize, usize),
-    SavedPlaylists(usize, usize),
-    Album(&'a str),
-    AlbumLiked(&'a str),
-    AlbumTracks(&'a str, usize, usize),
-    Playlist(&'a str),
-    PlaylistTracks(&'a str, usize, usize),
-    ArtistAlbums(&'a str, usize, usize),
-    Artist(&'a str),
-    ArtistTopTracks(&'a str),
-    User(&'a str),
-    UserPlaylists(&'a str, usize, usize),
+const HOME_VIEW: &str = "made-for-x";
+
+// What to search for, and how many of the results to keep
+const PINNED_PLAYLISTS: [(&str, usize); 3] = [
+    ("Discover Weekly", 1),
+    ("Release Radar", 1),
+    ("Daily Mix", 6),
+];
+
+// These are renewed once a week (on Mondays and Fridays, but close enough)
+const PINNED_MAX_AGE: u64 = 7 * 24 * 3600;
+
+// Everything the user saved, which other clients can change behind our back
+pub const USER_CACHE: [CacheCategory; 9] = [
+    CacheCategory::SavedAlbums,
+    CacheCategory::SavedArtists,
+    CacheCategory::SavedTracks,
+    CacheCategory::SavedPlaylists,
+    CacheCategory::SavedAudiobooks,
+    CacheCategory::AlbumLiked,
+    CacheCategory::ArtistFollowed,
+    CacheCategory::View,
+    CacheCategory::PinnedSearch,
+];
+
+// A playlist's name and cover also show in the lists it's part of
+fn playlist_cache(id: &str) -> Vec<CacheSelection> {
+    vec![
+        CacheCategory::Playlist.about(id),
+        CacheCategory::PlaylistTracks.about(id),
+        CacheCategory::SavedPlaylists.all(),
+        CacheCategory::UserPlaylists.all(),
+    ]
 }
 
-impl<'a> SpotCacheKey<'a> {
-    fn into_raw(self) -> String {
-        match self {
-            Self::SavedAlbums(offset, limit) => format!("me_albums_{offset}_{limit}.json"),
-            Self::SavedTracks(offset, limit) => format!("me_tracks_{offset}_{limit}.json"),
-            Self::SavedPlaylists(offset, limit) => format!("me_playlists_{offset}_{limit}.json"),
-            Self::Album(id) => format!("album_{id}.json"),
-            Self::AlbumTracks(id, offset, limit) => {
-                format!("album_item_{id}_{offset}_{limit}.json")
-            }
-            Self::AlbumLiked(id) => format!("album_liked_{id}.json"),
-            Self::Playlist(id) => format!("playlist_{id}.json"),
-            Self::PlaylistTracks(id, offset, limit) => {
-                format!("playlist_item_{id}_{offset}_{limit}.json")
-            }
-            Self::ArtistAlbums(id, offset, limit) => {
-                format!("artist_albums_{id}_{offset}_{limit}.json")
-            }
-            Self::Artist(id) => format!("artist_{id}.json"),
-            Self::ArtistTopTracks(id) => format!("artist_top_tracks_{id}.json"),
-            Self::User(id) => format!("user_{id}.json"),
-            Self::UserPlaylists(id, offset, limit) => {
-                format!("user_playlists_{id}_{offset}_{limit}.json")
-            }
+fn album_cache(id: &str) -> Vec<CacheSelection> {
+    vec![
+        CacheCategory::SavedAlbums.all(),
+        CacheCategory::AlbumLiked.about(id),
+    ]
+}
+
+// What's cached that a successful write makes stale
+fn invalidated_by(write: &PendingWrite) -> Vec<CacheSelection> {
+    match write {
+        PendingWrite::SaveTracks(_) | PendingWrite::RemoveSavedTracks(_) => {
+            vec![CacheCategory::SavedTracks.all()]
         }
+        PendingWrite::AddToPlaylist { id, .. } => playlist_cache(id),
     }
 }
 
-lazy_static! {
-    pub static ref ME_TRACKS_CACHE: Regex = Regex::new(r"^me_tracks_\w+_\w+\.json$").unwrap();
-    pub static ref ME_ALBUMS_CACHE: Regex = Regex::new(r"^me_albums_\w+_\w+\.json$").unwrap();
-    pub static ref USER_CACHE: Regex =
-        Regex::new(r"^me_(albums|playlists|tracks)_\w+_\w+\.json$").unwrap();
-}
+// The most songs Spotify removes from a playlist in one request
+const MAX_PLAYLIST_REMOVALS: usize = 100;
 
-fn playlist_cache_key(id: &str) -> Regex {
-    Regex::new(&format!(r"^playlist(_{id}|item_{id}_\w+_\w+)\.json$")).unwrap()
-}
+// How long individual lookups wait for others to join them in a batch
+const LOOKUP_COALESCE_DELAY: Duration = Duration::from_millis(30);
 
 pub struct CachedSpotifyClient {
     client: SpotifyClient,
     cache: CacheManager,
+    saved_tracks_lookup: BatchedLookup<bool, Arc<SpotifyApiError>>,
+    pending_writes: PendingWrites,
+    read_only: AtomicBool,
+    // Who's logged in, for what's cached on their behalf only
+    user: Mutex<Option<String>>,
 }
 
 impl CachedSpotifyClient {
-    pub fn new() -> CachedSpotifyClient {
+    pub fn new(retry_policy: RetryPolicy) -> CachedSpotifyClient {
         CachedSpotifyClient {
-            client: SpotifyClient::new(),
+            client: SpotifyClient::new(retry_policy),
             cache: CacheManager::for_dir("spot/net").unwrap(),
+            saved_tracks_lookup: BatchedLookup::new(),
+            pending_writes: Default::default(),
+            read_only: AtomicBool::new(false),
+            user: Mutex::new(None),
+        }
+    }
+
+    fn check_writable(&self) -> SpotifyResult<()> {
+        if self.read_only.load(Ordering::Relaxed) {
+            Err(SpotifyApiError::Locked)
+        } else {
+            Ok(())
         }
     }
 
+    // Rather than failing, writes made while offline are kept for later
+    // One request at a time, stopping at the first that fails; what's left gets put off if we're offline
+    async fn make_chunked_write(&self, write: PendingWrite) -> SpotifyResult<()> {
+        let mut done = 0;
+        let mut chunks = write.into_chunks().into_iter();
+        while let Some(chunk) = chunks.next() {
+            match self.make_write(&chunk).await {
+                Ok(()) => done += chunk.song_count(),
+                Err(err) if err.is_offline() => {
+                    for write in std::iter::once(chunk).chain(chunks) {
+                        info!("Offline, putting off {:?}", write);
+                        self.pending_writes.push(write);
+                    }
+                    return Ok(());
+                }
+                Err(err) if done == 0 => return Err(err),
+                Err(err) => return Err(SpotifyApiError::PartialWrite(done, Box::new(err))),
+            }
+        }
+        Ok(())
+    }
+
+    async fn make_write(&self, write: &PendingWrite) -> SpotifyResult<()> {
+        let result = match write {
+            PendingWrite::SaveTracks(ids) => {
+                self.client
+                    .save_tracks(ids.clone())
+                    .send_no_response()
+                    .await
+            }
+            PendingWrite::RemoveSavedTracks(ids) => {
+                self.client
+                    .remove_saved_tracks(ids.clone())
+                    .send_no_response()
+                    .await
+            }
+            PendingWrite::AddToPlaylist { id, uris } => {
+                self.client
+                    .add_to_playlist(id, uris.clone())
+                    .send_no_response()
+                    .await
+            }
+        };
+        self.invalidate(result, &invalidated_by(write)).await
+    }
+
+    // Once a write went through (even partly), what's cached about it is expired right away,
+    // rather than showing the old state until it expires on its own
+    async fn invalidate<T>(
+        &self,
+        result: SpotifyResult<T>,
+        stale: &[CacheSelection],
+    ) -> SpotifyResult<T> {
+        if matches!(result, Ok(_) | Err(SpotifyApiError::PartialWrite(..))) {
+            if let Err(e) = self.cache.set_expired(stale).await {
+                warn!("Could not expire cached content: {}", e);
+            }
+        }
+        result
+    }
+
+    async fn latest_snapshot(&self, id: &str) -> SpotifyResult<Option<String>> {
+        Ok(self
+            .client
+            .get_playlist(id)
+            .send()
+            .await?
+            .deserialize()
+            .ok_or(SpotifyApiError::NoContent)?
+            .snapshot_id)
+    }
+
+    async fn remove_chunk_from_playlist(
+        &self,
+        id: &str,
+        uris: Vec<String>,
+        snapshot_id: Option<String>,
+    ) -> SpotifyResult<String> {
+        let result = self
+            .client
+            .remove_from_playlist(id, uris.clone(), snapshot_id.as_deref())
+            .send()
+            .await;
+
+        // Someone else edited the playlist in the meantime: try again against its latest version,
+        // once, since removing by uri doesn't depend on where the songs ended up
+        let response = match result {
+            Err(e) if e.is_snapshot_conflict() => {
+                let latest = self.latest_snapshot(id).await?;
+                info!(
+                    "Playlist {} changed since {:?}, removing against {:?}",
+                    id, snapshot_id, latest
+                );
+                self.client
+                    .remove_from_playlist(id, uris, latest.as_deref())
+                    .send()
+                    .await?
+            }
+            result => result?,
+        };
+
+        Ok(response
+            .deserialize()
+            .ok_or(SpotifyApiError::NoContent)?
+            .snapshot_id)
+    }
+
     fn default_cache_policy(&self) -> CachePolicy {
         if self.client.has_token() {
             CachePolicy::Default
@@ -227,9 +464,17 @@ impl CachedSpotifyClient {
             .await
     }
 
+    // Spotify being down (or out of reach) is no reason not to show what it told us last time,
+    // however old that is. The error is only for what was never cached.
+    async fn stale_or(&self, key: &CacheKey<'_>, err: SpotifyApiError) -> SpotifyResult<Vec<u8>> {
+        self.cache
+            .get_or_write(key, CachePolicy::IgnoreExpiry, |_| async move { Err(err) })
+            .await
+    }
+
     async fn cache_get_or_write<T, O, F>(
         &self,
-        key: SpotCacheKey<'_>,
+        key: CacheKey<'_>,
         cache_policy: Option<CachePolicy>,
         write: F,
     ) -> SpotifyResult<T>
@@ -239,25 +484,28 @@ impl CachedSpotifyClient {
         T: DeserializeOwned,
     {
         let write = &write;
-        let cache_key = key.into_raw();
-        let raw = self
+        let raw = match self
             .cache
             .get_or_write(
-                &cache_key,
+                &key,
                 cache_policy.unwrap_or_else(|| self.default_cache_policy()),
                 |etag| Self::wrap_write(write, etag),
             )
-            .await?;
+            .await
+        {
+            Err(err) if err.is_transient() => self.stale_or(&key, err).await?,
+            result => result?,
+        };
 
         let result = from_slice::<T>(&raw);
         match result {
             Ok(t) => Ok(t),
             // parsing failed: cache is likely invalid, request again, ignoring cache
             Err(e) => {
-                dbg!(&cache_key, e);
+                dbg!(key.file_name(), e);
                 let new_raw = self
                     .cache
-                    .get_or_write(&cache_key, CachePolicy::IgnoreCached, |etag| {
+                    .get_or_write(&key, CachePolicy::IgnoreCached, |etag| {
                         Self::wrap_write(write, etag)
                     })
                     .await?;
@@ -265,6 +513,40 @@ impl CachedSpotifyClient {
             }
         }
     }
+
+    async fn cache_get_or_write_with_previous<T, O, F>(
+        &self,
+        key: CacheKey<'_>,
+        write: F,
+    ) -> SpotifyResult<(T, Option<T>)>
+    where
+        O: Future<Output = SpotifyResult<SpotifyResponse<T>>>,
+        F: Fn(Option<String>) -> O,
+        T: DeserializeOwned,
+    {
+        let (raw, previous) = match self
+            .cache
+            .get_or_write_replacing(&key, self.default_cache_policy(), |etag| {
+                Self::wrap_write(&write, etag)
+            })
+            .await
+        {
+            Err(err) if err.is_transient() => (self.stale_or(&key, err).await?, None),
+            result => result?,
+        };
+
+        // Whatever was cached before might not parse anymore, it's only good for comparing anyway
+        let previous = previous.and_then(|previous| from_slice::<T>(&previous).ok());
+        match from_slice::<T>(&raw) {
+            Ok(t) => Ok((t, previous)),
+            Err(_) => {
+                let t = self
+                    .cache_get_or_write(key, Some(CachePolicy::IgnoreCached), write)
+                    .await?;
+                Ok((t, None))
+            }
+        }
+    }
 }
 
 impl SpotifyApiClient for CachedSpotifyClient {
@@ -272,34 +554,65 @@ impl SpotifyApiClient for CachedSpotifyClient {
         self.client.update_token(new_token)
     }
 
+    fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    fn set_retry_policy(&self, retry_policy: RetryPolicy) {
+        self.client.set_retry_policy(retry_policy);
+    }
+
+    fn connect_web_api_health_changed(&self, f: HealthChanged) {
+        self.client.connect_health_changed(f);
+    }
+
+    fn set_user(&self, username: Option<String>) {
+        if username.is_none() {
+            self.pending_writes.clear();
+        }
+        self.pending_writes.set_user(username.clone());
+        *self.user.lock().unwrap() = username;
+    }
+
     fn get_saved_albums(
         &self,
-        offset: usize,
+        page: PageToken,
         limit: usize,
-    ) -> BoxFuture<SpotifyResult<Vec<AlbumDescription>>> {
+    ) -> BoxFuture<SpotifyResult<Paged<AlbumDescription>>> {
         Box::pin(async move {
-            let page = self
-                .cache_get_or_write(SpotCacheKey::SavedAlbums(offset, limit), None, |etag| {
+            let albums = self
+                .cache_get_or_write(CacheKey::SavedAlbums(&page, limit), None, |etag| {
+                    self.client.get_saved_albums(&page, limit).etag(etag).send()
+                })
+                .await?;
+
+            Ok(albums.into_paged(|saved| saved.album.into()))
+        })
+    }
+
+    fn get_followed_artists(
+        &self,
+        page: PageToken,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Paged<ArtistSummary>>> {
+        Box::pin(async move {
+            let followed: FollowedArtists = self
+                .cache_get_or_write(CacheKey::SavedArtists(&page, limit), None, |etag| {
                     self.client
-                        .get_saved_albums(offset, limit)
+                        .get_followed_artists(&page, limit)
                         .etag(etag)
                         .send()
                 })
                 .await?;
 
-            let albums = page
-                .into_iter()
-                .map(|saved| saved.album.into())
-                .collect::<Vec<AlbumDescription>>();
-
-            Ok(albums)
+            Ok(followed.artists.into_paged(|artist| artist.into()))
         })
     }
 
     fn get_saved_tracks(&self, offset: usize, limit: usize) -> BoxFuture<SpotifyResult<SongBatch>> {
         Box::pin(async move {
             let page = self
-                .cache_get_or_write(SpotCacheKey::SavedTracks(offset, limit), None, |etag| {
+                .cache_get_or_write(CacheKey::SavedTracks(offset, limit), None, |etag| {
                     self.client
                         .get_saved_tracks(offset, limit)
                         .etag(etag)
@@ -318,7 +631,7 @@ impl SpotifyApiClient for CachedSpotifyClient {
     ) -> BoxFuture<SpotifyResult<Vec<PlaylistDescription>>> {
         Box::pin(async move {
             let page = self
-                .cache_get_or_write(SpotCacheKey::SavedPlaylists(offset, limit), None, |etag| {
+                .cache_get_or_write(CacheKey::SavedPlaylists(offset, limit), None, |etag| {
                     self.client
                         .get_saved_playlists(offset, limit)
                         .etag(etag)
@@ -335,19 +648,156 @@ impl SpotifyApiClient for CachedSpotifyClient {
         })
     }
 
-    fn add_to_playlist(&self, id: &str, uris: Vec<String>) -> BoxFuture<SpotifyResult<()>> {
+    fn get_home_shelves(&self, limit: usize) -> BoxFuture<SpotifyResult<Vec<HomeShelf>>> {
+        Box::pin(async move {
+            let view = self
+                .cache_get_or_write(CacheKey::View(HOME_VIEW, 0, limit), None, |etag| {
+                    self.client.get_view(HOME_VIEW, 0, limit).etag(etag).send()
+                })
+                .await?;
+            Ok(view.shelves())
+        })
+    }
+
+    fn get_home_shelf(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Vec<PlaylistDescription>>> {
         let id = id.to_owned();
 
         Box::pin(async move {
-            self.cache
-                .set_expired_pattern(&playlist_cache_key(&id))
-                .await
-                .unwrap_or(());
+            let view = self
+                .cache_get_or_write(CacheKey::View(&id, offset, limit), None, |etag| {
+                    self.client.get_view(&id, offset, limit).etag(etag).send()
+                })
+                .await?;
+            Ok(view.playlists())
+        })
+    }
 
-            self.client
-                .add_to_playlist(&id, uris)
-                .send_no_response()
+    fn get_pinned_playlists(&self) -> BoxFuture<SpotifyResult<Vec<PlaylistDescription>>> {
+        // Made for each user, so they're kept apart
+        let user = self.user.lock().unwrap().clone().unwrap_or_default();
+        Box::pin(async move {
+            let user = &user;
+            let searches = PINNED_PLAYLISTS.iter().map(|(query, count)| async move {
+                let results: RawSearchResults = self
+                    .cache_get_or_write(CacheKey::PinnedSearch(user, query), None, |etag| {
+                        self.client
+                            .search_playlists(query.to_string(), 20)
+                            .etag(etag)
+                            .send()
+                            .map(|r| {
+                                r.map(|r| SpotifyResponse {
+                                    max_age: PINNED_MAX_AGE,
+                                    ..r
+                                })
+                            })
+                    })
+                    .await?;
+                let mut playlists = results.spotify_playlists_named(query);
+                playlists.truncate(*count);
+                SpotifyResult::Ok(playlists)
+            });
+            let playlists = try_join_all(searches).await?;
+            Ok(playlists.into_iter().flatten().collect())
+        })
+    }
+
+    fn get_saved_audiobooks(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Vec<AudiobookDescription>>> {
+        Box::pin(async move {
+            let page = self
+                .cache_get_or_write(CacheKey::SavedAudiobooks(offset, limit), None, |etag| {
+                    self.client
+                        .get_saved_audiobooks(offset, limit)
+                        .etag(etag)
+                        .send()
+                })
                 .await?;
+
+            Ok(page.into_iter().map(|a| a.into()).collect())
+        })
+    }
+
+    fn get_audiobook_chapters(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Vec<ChapterDescription>>> {
+        let id = id.to_owned();
+
+        Box::pin(async move {
+            let audiobook = self.cache_get_or_write(CacheKey::Audiobook(&id), None, |etag| {
+                self.client.get_audiobook(&id).etag(etag).send()
+            });
+            // Resume points change all the time, there's no point in caching them
+            let chapters = self.cache_get_or_write(
+                CacheKey::AudiobookChapters(&id, offset, limit),
+                Some(CachePolicy::IgnoreCached),
+                |etag| {
+                    self.client
+                        .get_audiobook_chapters(&id, offset, limit)
+                        .etag(etag)
+                        .send()
+                },
+            );
+            let (audiobook, chapters) = join!(audiobook, chapters);
+            let audiobook: AudiobookDescription = audiobook?.into();
+            let chapters: Page<Chapter> = chapters?;
+
+            Ok(chapters
+                .into_iter()
+                .map(|chapter| chapter.into_description(&audiobook))
+                .collect())
+        })
+    }
+
+    fn add_to_playlist(&self, id: &str, uris: Vec<String>) -> BoxFuture<SpotifyResult<()>> {
+        let write = PendingWrite::AddToPlaylist {
+            id: id.to_owned(),
+            uris,
+        };
+        Box::pin(async move {
+            self.check_writable()?;
+            self.make_chunked_write(write).await
+        })
+    }
+
+    fn replay_pending_writes(&self) -> BoxFuture<SpotifyResult<usize>> {
+        Box::pin(async move {
+            // Still queued, they'll go out next time once allowed
+            self.check_writable()?;
+            let mut writes = self.pending_writes.take_all().into_iter();
+            let mut dropped = 0;
+            while let Some(write) = writes.next() {
+                match self.make_write(&write).await {
+                    Ok(()) => {}
+                    // Not quite back online after all, the rest will wait until next time
+                    Err(err) if err.is_offline() => {
+                        self.pending_writes
+                            .put_back(std::iter::once(write).chain(writes).collect());
+                        return Err(err);
+                    }
+                    Err(err) => {
+                        warn!("Dropping {:?}: {}", write, err);
+                        dropped += 1;
+                    }
+                }
+            }
+            Ok(dropped)
+        })
+    }
+
+    fn revalidate(&self, stale: Vec<CacheSelection>) -> BoxFuture<SpotifyResult<()>> {
+        Box::pin(async move {
+            self.cache.set_expired(&stale).await?;
             Ok(())
         })
     }
@@ -361,10 +811,18 @@ impl SpotifyApiClient for CachedSpotifyClient {
         let user_id = user_id.to_owned();
 
         Box::pin(async move {
-            let playlist = self
+            self.check_writable()?;
+            let result = self
                 .client
                 .create_new_playlist(&name, &user_id)
                 .send()
+                .await;
+            let stale = [
+                CacheCategory::SavedPlaylists.all(),
+                CacheCategory::UserPlaylists.about(&user_id),
+            ];
+            let playlist = self
+                .invalidate(result, &stale)
                 .await?
                 .deserialize()
                 .unwrap();
@@ -373,20 +831,39 @@ impl SpotifyApiClient for CachedSpotifyClient {
         })
     }
 
-    fn remove_from_playlist(&self, id: &str, uris: Vec<String>) -> BoxFuture<SpotifyResult<()>> {
+    fn remove_from_playlist(
+        &self,
+        id: &str,
+        uris: Vec<String>,
+        snapshot_id: Option<String>,
+    ) -> BoxFuture<SpotifyResult<String>> {
         let id = id.to_owned();
 
         Box::pin(async move {
-            self.cache
-                .set_expired_pattern(&playlist_cache_key(&id))
-                .await
-                .unwrap_or(());
-
-            self.client
-                .remove_from_playlist(&id, uris)
-                .send_no_response()
-                .await?;
-            Ok(())
+            self.check_writable()?;
+            let result = async {
+                // Whichever version the songs were picked from, known before anything is sent:
+                // a version read only once a removal conflicted would already include the other edit
+                let mut snapshot_id = match snapshot_id {
+                    Some(snapshot_id) => Some(snapshot_id),
+                    None => self.latest_snapshot(&id).await?,
+                };
+                let mut done = 0;
+                for chunk in uris.chunks(MAX_PLAYLIST_REMOVALS) {
+                    match self
+                        .remove_chunk_from_playlist(&id, chunk.to_vec(), snapshot_id.clone())
+                        .await
+                    {
+                        Ok(snapshot) => snapshot_id = Some(snapshot),
+                        Err(err) if done == 0 => return Err(err),
+                        Err(err) => return Err(SpotifyApiError::PartialWrite(done, Box::new(err))),
+                    }
+                    done += chunk.len();
+                }
+                snapshot_id.ok_or(SpotifyApiError::NoContent)
+            }
+            .await;
+            self.invalidate(result, &playlist_cache(&id)).await
         })
     }
 
@@ -394,17 +871,13 @@ impl SpotifyApiClient for CachedSpotifyClient {
         let id = id.to_owned();
 
         Box::pin(async move {
-            self.cache
-                .set_expired_pattern(&playlist_cache_key(&id))
-                .await
-                .unwrap_or(());
-
-            self.client
+            self.check_writable()?;
+            let result = self
+                .client
                 .update_playlist_details(&id, name)
                 .send_no_response()
-                .await?;
-
-            Ok(())
+                .await;
+            self.invalidate(result, &playlist_cache(&id)).await
         })
     }
 
@@ -412,12 +885,12 @@ impl SpotifyApiClient for CachedSpotifyClient {
         let id = id.to_owned();
 
         Box::pin(async move {
-            let album = self.cache_get_or_write(SpotCacheKey::Album(&id), None, |etag| {
+            let album = self.cache_get_or_write(CacheKey::Album(&id), None, |etag| {
                 self.client.get_album(&id).etag(etag).send()
             });
 
             let liked = self.cache_get_or_write(
-                SpotCacheKey::AlbumLiked(&id),
+                CacheKey::AlbumLiked(&id),
                 Some(if self.client.has_token() {
                     CachePolicy::Revalidate
                 } else {
@@ -435,43 +908,142 @@ impl SpotifyApiClient for CachedSpotifyClient {
         })
     }
 
+    fn get_mix_points(&self, id: &str) -> BoxFuture<SpotifyResult<Option<MixPoints>>> {
+        let id = id.to_owned();
+
+        Box::pin(async move {
+            // Kept for good, an analysis never changes (see CacheCategory)
+            let analysis: AudioAnalysis = self
+                .cache_get_or_write(CacheKey::AudioAnalysis(&id), None, |etag| {
+                    self.client.get_audio_analysis(&id).etag(etag).send()
+                })
+                .await?;
+            Ok(analysis.mix_points())
+        })
+    }
+
+    fn get_tracks(&self, ids: Vec<String>) -> BoxFuture<SpotifyResult<Vec<SongDescription>>> {
+        Box::pin(async move {
+            let chunks = ids.chunks(MAX_IDS_PER_REQUEST).map(|chunk| async move {
+                let tracks = self
+                    .client
+                    .get_tracks(chunk)
+                    .send()
+                    .await?
+                    .deserialize()
+                    .ok_or(SpotifyApiError::NoContent)?;
+                SpotifyResult::Ok(Vec::<SongDescription>::from(tracks))
+            });
+            let songs = try_join_all(chunks).await?;
+            Ok(songs.into_iter().flatten().collect())
+        })
+    }
+
+    fn are_tracks_saved(&self, ids: Vec<String>) -> BoxFuture<SpotifyResult<Vec<bool>>> {
+        Box::pin(async move {
+            let chunks = ids.chunks(MAX_IDS_PER_REQUEST).map(|chunk| async move {
+                self.client
+                    .are_tracks_saved(chunk)
+                    .send()
+                    .await?
+                    .deserialize()
+                    .ok_or(SpotifyApiError::NoContent)
+            });
+            let saved = try_join_all(chunks).await?;
+            Ok(saved.into_iter().flatten().collect())
+        })
+    }
+
+    fn is_track_saved(&self, id: &str) -> BoxFuture<SpotifyResult<bool>> {
+        let receiver = self.saved_tracks_lookup.enqueue(id);
+
+        Box::pin(async move {
+            async_std::task::sleep(LOOKUP_COALESCE_DELAY).await;
+
+            // Everyone tries to flush, so that nobody is left hanging if the caller
+            // that enqueued first went away in the meantime
+            let pending = self.saved_tracks_lookup.take_pending();
+            if !pending.is_empty() {
+                let ids = pending.ids();
+                debug!("looking up saved state of {} tracks", ids.len());
+                match self.are_tracks_saved(ids.clone()).await {
+                    Ok(saved) => pending.resolve(ids.into_iter().zip(saved)),
+                    Err(err) => pending.fail(Arc::new(err)),
+                }
+            }
+
+            receiver
+                .await
+                .map_err(|_| SpotifyApiError::NoContent)?
+                .map_err(SpotifyApiError::Shared)
+        })
+    }
+
     fn save_album(&self, id: &str) -> BoxFuture<SpotifyResult<AlbumDescription>> {
         let id = id.to_owned();
 
         Box::pin(async move {
-            let _ = self.cache.set_expired_pattern(&ME_ALBUMS_CACHE).await;
-            self.client.save_album(&id).send_no_response().await?;
+            let result = self.client.save_album(&id).send_no_response().await;
+            self.invalidate(result, &album_cache(&id)).await?;
             self.get_album(&id[..]).await.map(|a| a.description)
         })
     }
 
     fn save_tracks(&self, ids: Vec<String>) -> BoxFuture<SpotifyResult<()>> {
-        Box::pin(async move {
-            let _ = self.cache.set_expired_pattern(&ME_TRACKS_CACHE).await;
-            self.client.save_tracks(ids).send_no_response().await?;
-            Ok(())
-        })
+        Box::pin(self.make_chunked_write(PendingWrite::SaveTracks(ids)))
     }
 
     fn remove_saved_album(&self, id: &str) -> BoxFuture<SpotifyResult<()>> {
         let id = id.to_owned();
 
         Box::pin(async move {
-            let _ = self.cache.set_expired_pattern(&ME_ALBUMS_CACHE).await;
-            self.client.remove_saved_album(&id).send_no_response().await
+            self.check_writable()?;
+            let result = self.client.remove_saved_album(&id).send_no_response().await;
+            self.invalidate(result, &album_cache(&id)).await
         })
     }
 
     fn remove_saved_tracks(&self, ids: Vec<String>) -> BoxFuture<SpotifyResult<()>> {
         Box::pin(async move {
-            let _ = self.cache.set_expired_pattern(&ME_TRACKS_CACHE).await;
-            self.client
-                .remove_saved_tracks(ids)
-                .send_no_response()
+            self.check_writable()?;
+            self.make_chunked_write(PendingWrite::RemoveSavedTracks(ids))
                 .await
         })
     }
 
+    fn follow_artist(&self, id: &str) -> BoxFuture<SpotifyResult<()>> {
+        let id = id.to_owned();
+
+        Box::pin(async move {
+            let result = self.client.follow_artist(&id).send_no_response().await;
+            self.invalidate(
+                result,
+                &[
+                    CacheCategory::ArtistFollowed.about(&id),
+                    CacheCategory::SavedArtists.all(),
+                ],
+            )
+            .await
+        })
+    }
+
+    fn unfollow_artist(&self, id: &str) -> BoxFuture<SpotifyResult<()>> {
+        let id = id.to_owned();
+
+        Box::pin(async move {
+            self.check_writable()?;
+            let result = self.client.unfollow_artist(&id).send_no_response().await;
+            self.invalidate(
+                result,
+                &[
+                    CacheCategory::ArtistFollowed.about(&id),
+                    CacheCategory::SavedArtists.all(),
+                ],
+            )
+            .await
+        })
+    }
+
     fn get_album_tracks(
         &self,
         id: &str,
@@ -482,21 +1054,18 @@ impl SpotifyApiClient for CachedSpotifyClient {
 
         Box::pin(async move {
             let album = self.cache_get_or_write(
-                SpotCacheKey::Album(&id),
+                CacheKey::Album(&id),
                 Some(CachePolicy::IgnoreExpiry),
                 |etag| self.client.get_album(&id).etag(etag).send(),
             );
 
-            let songs = self.cache_get_or_write(
-                SpotCacheKey::AlbumTracks(&id, offset, limit),
-                None,
-                |etag| {
+            let songs =
+                self.cache_get_or_write(CacheKey::AlbumTracks(&id, offset, limit), None, |etag| {
                     self.client
                         .get_album_tracks(&id, offset, limit)
                         .etag(etag)
                         .send()
-                },
-            );
+                });
 
             let (album, songs) = join!(album, songs);
             Ok((songs?, &album?.album).into())
@@ -508,7 +1077,7 @@ impl SpotifyApiClient for CachedSpotifyClient {
 
         Box::pin(async move {
             let playlist = self
-                .cache_get_or_write(SpotCacheKey::Playlist(&id), None, |etag| {
+                .cache_get_or_write(CacheKey::Playlist(&id), None, |etag| {
                     self.client.get_playlist(&id).etag(etag).send()
                 })
                 .await?;
@@ -527,9 +1096,30 @@ impl SpotifyApiClient for CachedSpotifyClient {
 
         Box::pin(async move {
             let songs = self
-                .cache_get_or_write(
-                    SpotCacheKey::PlaylistTracks(&id, offset, limit),
-                    None,
+                .cache_get_or_write(CacheKey::PlaylistTracks(&id, offset, limit), None, |etag| {
+                    self.client
+                        .get_playlist_tracks(&id, offset, limit)
+                        .etag(etag)
+                        .send()
+                })
+                .await?;
+
+            Ok(songs.into())
+        })
+    }
+
+    fn get_playlist_tracks_with_previous(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<(SongBatch, Option<SongBatch>)>> {
+        let id = id.to_owned();
+
+        Box::pin(async move {
+            let (songs, previous) = self
+                .cache_get_or_write_with_previous(
+                    CacheKey::PlaylistTracks(&id, offset, limit),
                     |etag| {
                         self.client
                             .get_playlist_tracks(&id, offset, limit)
@@ -539,7 +1129,7 @@ impl SpotifyApiClient for CachedSpotifyClient {
                 )
                 .await?;
 
-            Ok(songs.into())
+            Ok((songs.into(), previous.map(SongBatch::from)))
         })
     }
 
@@ -553,16 +1143,12 @@ impl SpotifyApiClient for CachedSpotifyClient {
 
         Box::pin(async move {
             let albums = self
-                .cache_get_or_write(
-                    SpotCacheKey::ArtistAlbums(&id, offset, limit),
-                    None,
-                    |etag| {
-                        self.client
-                            .get_artist_albums(&id, offset, limit)
-                            .etag(etag)
-                            .send()
-                    },
-                )
+                .cache_get_or_write(CacheKey::ArtistAlbums(&id, offset, limit), None, |etag| {
+                    self.client
+                        .get_artist_albums(&id, offset, limit)
+                        .etag(etag)
+                        .send()
+                })
                 .await?;
 
             let albums = albums
@@ -574,22 +1160,60 @@ impl SpotifyApiClient for CachedSpotifyClient {
         })
     }
 
+    fn get_artist_singles(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<SpotifyResult<Vec<AlbumDescription>>> {
+        let id = id.to_owned();
+
+        Box::pin(async move {
+            let singles = self
+                .cache_get_or_write(CacheKey::ArtistSingles(&id, offset, limit), None, |etag| {
+                    self.client
+                        .get_artist_singles(&id, offset, limit)
+                        .etag(etag)
+                        .send()
+                })
+                .await?;
+
+            let singles = singles
+                .into_iter()
+                .map(|a| a.into())
+                .collect::<Vec<AlbumDescription>>();
+
+            Ok(singles)
+        })
+    }
+
     fn get_artist(&self, id: &str) -> BoxFuture<SpotifyResult<ArtistDescription>> {
         let id = id.to_owned();
 
         Box::pin(async move {
-            let artist = self.cache_get_or_write(SpotCacheKey::Artist(&id), None, |etag| {
+            let artist = self.cache_get_or_write(CacheKey::Artist(&id), None, |etag| {
                 self.client.get_artist(&id).etag(etag).send()
             });
 
             let albums = self.get_artist_albums(&id, 0, 20);
 
             let top_tracks =
-                self.cache_get_or_write(SpotCacheKey::ArtistTopTracks(&id), None, |etag| {
+                self.cache_get_or_write(CacheKey::ArtistTopTracks(&id), None, |etag| {
                     self.client.get_artist_top_tracks(&id).etag(etag).send()
                 });
 
-            let (artist, albums, top_tracks) = join!(artist, albums, top_tracks);
+            let followed = self.cache_get_or_write(
+                CacheKey::ArtistFollowed(&id),
+                Some(if self.client.has_token() {
+                    CachePolicy::Revalidate
+                } else {
+                    CachePolicy::IgnoreExpiry
+                }),
+                |etag| self.client.is_artist_followed(&id).etag(etag).send(),
+            );
+
+            let (artist, albums, top_tracks, followed) =
+                join!(artist, albums, top_tracks, followed);
 
             let artist = artist?;
             let result = ArtistDescription {
@@ -597,6 +1221,11 @@ impl SpotifyApiClient for CachedSpotifyClient {
                 name: artist.name,
                 albums: albums?,
                 top_tracks: top_tracks?.into(),
+                // Tokens obtained before we asked for the follow scopes can't tell, that's no reason to fail
+                is_followed: followed
+                    .ok()
+                    .and_then(|f| f.first().copied())
+                    .unwrap_or(false),
             };
             Ok(result)
         })
@@ -647,16 +1276,12 @@ impl SpotifyApiClient for CachedSpotifyClient {
 
         Box::pin(async move {
             let playlists = self
-                .cache_get_or_write(
-                    SpotCacheKey::UserPlaylists(&id, offset, limit),
-                    None,
-                    |etag| {
-                        self.client
-                            .get_user_playlists(&id, offset, limit)
-                            .etag(etag)
-                            .send()
-                    },
-                )
+                .cache_get_or_write(CacheKey::UserPlaylists(&id, offset, limit), None, |etag| {
+                    self.client
+                        .get_user_playlists(&id, offset, limit)
+                        .etag(etag)
+                        .send()
+                })
                 .await?;
 
             let playlists = playlists
@@ -672,7 +1297,7 @@ impl SpotifyApiClient for CachedSpotifyClient {
         let id = id.to_owned();
 
         Box::pin(async move {
-            let user = self.cache_get_or_write(SpotCacheKey::User(&id), None, |etag| {
+            let user = self.cache_get_or_write(CacheKey::User(&id), None, |etag| {
                 self.client.get_user(&id).etag(etag).send()
             });
 
@@ -711,6 +1336,40 @@ impl SpotifyApiClient for CachedSpotifyClient {
         })
     }
 
+    fn start_group_session(&self, device_id: String) -> BoxFuture<SpotifyResult<GroupSession>> {
+        Box::pin(async move {
+            let session = self
+                .client
+                .group_session_current_or_new(&device_id)
+                .send()
+                .await?
+                .deserialize()
+                .ok_or(SpotifyApiError::NoContent)?;
+            Ok(session.into())
+        })
+    }
+
+    fn join_group_session(&self, token: String) -> BoxFuture<SpotifyResult<GroupSession>> {
+        Box::pin(async move {
+            let session = self
+                .client
+                .group_session_join(&token)
+                .send()
+                .await?
+                .deserialize()
+                .ok_or(SpotifyApiError::NoContent)?;
+            Ok(session.into())
+        })
+    }
+
+    fn leave_group_session(&self, session_id: String) -> BoxFuture<SpotifyResult<()>> {
+        Box::pin(
+            self.client
+                .group_session_leave(&session_id)
+                .send_no_response(),
+        )
+    }
+
     fn get_player_queue(&self) -> BoxFuture<SpotifyResult<Vec<SongDescription>>> {
         Box::pin(async move {
             let queue = self
@@ -737,6 +1396,7 @@ impl SpotifyApiClient for CachedSpotifyClient {
         device_id: String,
         context_uri: String,
         offset: usize,
+        position_ms: Option<u32>,
     ) -> BoxFuture<SpotifyResult<()>> {
         Box::pin(
             self.client
@@ -747,6 +1407,7 @@ impl SpotifyApiClient for CachedSpotifyClient {
                         offset: PlayOffset {
                             position: offset as u32,
                         },
+                        position_ms,
                     },
                 )
                 .send_no_response(),
@@ -758,6 +1419,7 @@ impl SpotifyApiClient for CachedSpotifyClient {
         device_id: String,
         uris: Vec<String>,
         offset: usize,
+        position_ms: Option<u32>,
     ) -> BoxFuture<SpotifyResult<()>> {
         Box::pin(
             self.client
@@ -768,6 +1430,7 @@ impl SpotifyApiClient for CachedSpotifyClient {
                         offset: PlayOffset {
                             position: offset as u32,
                         },
+                        position_ms,
                     },
                 )
                 .send_no_response(),
@@ -778,6 +1441,14 @@ impl SpotifyApiClient for CachedSpotifyClient {
         Box::pin(self.client.player_next(&device_id).send_no_response())
     }
 
+    fn player_add_to_queue(&self, device_id: String, uri: String) -> BoxFuture<SpotifyResult<()>> {
+        Box::pin(
+            self.client
+                .player_add_to_queue(&device_id, &uri)
+                .send_no_response(),
+        )
+    }
+
     fn player_seek(&self, device_id: String, pos: usize) -> BoxFuture<SpotifyResult<()>> {
         Box::pin(self.client.player_seek(&device_id, pos).send_no_response())
     }
@@ -787,6 +1458,8 @@ impl SpotifyApiClient for CachedSpotifyClient {
             let result = self
                 .client
                 .player_state()
+                // polled periodically anyway, no point in piling up retries
+                .no_retry()
                 .send()
                 .await?
                 .deserialize()
@@ -825,6 +1498,13 @@ impl SpotifyApiClient for CachedSpotifyClient {
                 .send_no_response(),
         )
     }
+
+    fn listen_player_events(
+        &self,
+        on_event: Box<dyn FnMut(PlayerEvent) + Send>,
+    ) -> BoxFuture<SpotifyResult<()>> {
+        Box::pin(listen_player_events(&self.client, on_event))
+    }
 }
 
 #[cfg(test)]
@@ -3,18 +3,25 @@ use isahc::config::Configurable;
 use isahc::http::{method::Method, request::Builder, StatusCode, Uri};
 use isahc::{AsyncReadResponseExt, HttpClient, Request};
 use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use rand::Rng;
 use serde::{de::Deserialize, Serialize};
 use serde_json::from_str;
 use std::convert::Into;
 use std::marker::PhantomData;
-use std::str::FromStr;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use thiserror::Error;
 
 pub use super::api_models::*;
-use super::cache::CacheError;
+use super::cache::{self, CacheError};
+use super::health::{HealthChanged, WebApiHealth};
 
 const SPOTIFY_HOST: &str = "api.spotify.com";
+// The internal API used by the official clients, for what the web API doesn't cover
+const SPCLIENT_HOST: &str = "spclient.wg.spotify.com";
+
+// Most endpoints taking a list of ids in the query string accept up to 50 of them
+pub(crate) const MAX_IDS_PER_REQUEST: usize = 50;
 
 // https://url.spec.whatwg.org/#path-percent-encode-set
 const PATH_ENCODE_SET: &AsciiSet = &CONTROLS
@@ -32,10 +39,59 @@ fn make_query_params<'a>() -> Serializer<'a, String> {
     Serializer::new(String::new())
 }
 
+// Offset or cursor, whichever the page is given by
+fn make_page_params<'a>(page: &PageToken, limit: usize) -> Serializer<'a, String> {
+    let mut params = make_query_params();
+    match page {
+        PageToken::Offset(offset) => params.append_pair("offset", &offset.to_string()[..]),
+        PageToken::After(cursor) => params.append_pair("after", cursor),
+    };
+    params.append_pair("limit", &limit.to_string()[..]);
+    params
+}
+
+// How hard we try again (see the api-* settings) when a GET fails for reasons that are likely to go away on their own
+// (server hiccups, flaky network). Writes are never retried, we can't know if they went through,
+// and neither are the few GETs that change something (see no_retry).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(4),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff, capped, with the upper half of the delay randomized.
+    // jitter is expected to be in [0, 1]
+    fn delay_for(&self, attempt: u32, jitter: f64) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = exp / 2;
+        half + half.mul_f64(jitter.clamp(0.0, 1.0))
+    }
+
+    fn should_retry(&self, attempt: u32, error: &SpotifyApiError) -> bool {
+        attempt < self.max_retries && error.is_transient()
+    }
+}
+
 pub(crate) struct SpotifyRequest<'a, Body, Response> {
     client: &'a SpotifyClient,
     request: Builder,
     body: Body,
+    retry: Option<RetryPolicy>,
     _type: PhantomData<Response>,
 }
 
@@ -48,14 +104,18 @@ where
         self
     }
 
-    fn uri(mut self, path: String, query: Option<&str>) -> Self {
+    fn uri(self, path: String, query: Option<&str>) -> Self {
+        self.uri_on(SPOTIFY_HOST, path, query)
+    }
+
+    fn uri_on(mut self, host: &str, path: String, query: Option<&str>) -> Self {
         let path_and_query = match query {
             None => path,
             Some(query) => format!("{path}?{query}"),
         };
         let uri = Uri::builder()
             .scheme("https")
-            .authority(SPOTIFY_HOST)
+            .authority(host)
             .path_and_query(&path_and_query[..])
             .build()
             .unwrap();
@@ -79,6 +139,12 @@ where
         self
     }
 
+    // Opt out of automatic retries, for requests where failing fast is preferable
+    pub(crate) fn no_retry(mut self) -> Self {
+        self.retry = None;
+        self
+    }
+
     pub(crate) fn json_body<NewBody>(self, body: NewBody) -> SpotifyRequest<'a, Vec<u8>, R>
     where
         NewBody: Serialize,
@@ -86,6 +152,7 @@ where
         let Self {
             client,
             request,
+            retry,
             _type,
             ..
         } = self;
@@ -93,6 +160,7 @@ where
             client,
             request: request.header("Content-Type", "application/json"),
             body: serde_json::to_vec(&body).unwrap(),
+            retry,
             _type,
         }
     }
@@ -102,9 +170,40 @@ where
             client,
             request,
             body,
+            retry,
             ..
         } = self.authenticated()?;
-        client.send_req(request.body(body).unwrap()).await
+        let request = request.body(body).unwrap();
+
+        let policy = match retry {
+            Some(policy) if request.method() == Method::GET => policy,
+            _ => return client.send_req(request).await,
+        };
+
+        let mut attempt = 0;
+        loop {
+            // GET requests have no body, so they can be rebuilt from the original for each attempt
+            let mut builder = Request::builder()
+                .method(request.method().clone())
+                .uri(request.uri().clone());
+            if let Some(headers) = builder.headers_mut() {
+                *headers = request.headers().clone();
+            }
+            match client.send_req(builder.body(()).unwrap()).await {
+                Err(e) if policy.should_retry(attempt, &e) => {
+                    let delay = policy.delay_for(attempt, rand::thread_rng().gen());
+                    warn!(
+                        "Request to {} failed ({}), retrying in {:?}",
+                        request.uri().path(),
+                        e,
+                        delay
+                    );
+                    async_std::task::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
     }
 
     pub(crate) async fn send_no_response(self) -> Result<(), SpotifyApiError> {
@@ -168,15 +267,57 @@ pub enum SpotifyApiError {
     ParseError(#[from] serde_json::Error),
     #[error(transparent)]
     ConversionError(#[from] std::string::FromUtf8Error),
+    #[error(transparent)]
+    WebsocketError(#[from] Box<tokio_tungstenite::tungstenite::Error>),
+    // A write made in several requests stopped part of the way, after that many songs
+    #[error("Stopped after {0} songs: {1}")]
+    PartialWrite(usize, Box<SpotifyApiError>),
+    // Refused before being sent, see set_read_only
+    #[error("Not allowed while locked")]
+    Locked,
+    // The failure of a request made on behalf of several callers, each gets it (see BatchedLookup)
+    #[error(transparent)]
+    Shared(Arc<SpotifyApiError>),
+}
+
+impl SpotifyApiError {
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::BadStatus(status, _) => *status >= 500,
+            Self::ClientError(e) => e.is_network() || e.is_timeout(),
+            Self::Shared(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+
+    // Couldn't even reach Spotify
+    pub fn is_offline(&self) -> bool {
+        match self {
+            Self::ClientError(e) => e.is_network(),
+            Self::Shared(e) => e.is_offline(),
+            _ => false,
+        }
+    }
+
+    // The playlist changed since the snapshot the edit was made against
+    pub fn is_snapshot_conflict(&self) -> bool {
+        match self {
+            Self::BadStatus(409, _) => true,
+            Self::BadStatus(400, message) => message.to_lowercase().contains("snapshot"),
+            _ => false,
+        }
+    }
 }
 
 pub(crate) struct SpotifyClient {
     token: Mutex<Option<String>>,
     client: HttpClient,
+    retry_policy: Mutex<RetryPolicy>,
+    health: WebApiHealth,
 }
 
 impl SpotifyClient {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(retry_policy: RetryPolicy) -> Self {
         let mut builder = HttpClient::builder();
         if cfg!(debug_assertions) {
             builder = builder.ssl_options(isahc::config::SslOption::DANGER_ACCEPT_INVALID_CERTS);
@@ -185,14 +326,39 @@ impl SpotifyClient {
         Self {
             token: Mutex::new(None),
             client,
+            retry_policy: Mutex::new(retry_policy),
+            health: Default::default(),
         }
     }
 
+    pub(crate) fn set_retry_policy(&self, retry_policy: RetryPolicy) {
+        *self.retry_policy.lock().unwrap() = retry_policy;
+    }
+
+    pub(crate) fn connect_health_changed(&self, f: HealthChanged) {
+        self.health.connect_changed(f);
+    }
+
+    // Every request goes through here, which is how we can tell when Spotify goes down or comes back
+    async fn send_tracked<B>(
+        &self,
+        request: Request<B>,
+    ) -> Result<isahc::Response<isahc::AsyncBody>, isahc::Error>
+    where
+        B: Into<isahc::AsyncBody>,
+    {
+        let started = self.health.start();
+        let response = self.client.send_async(request).await;
+        self.health.record(started, &response);
+        response
+    }
+
     pub(crate) fn request<T>(&self) -> SpotifyRequest<'_, (), T> {
         SpotifyRequest {
             client: self,
             request: Builder::new(),
             body: (),
+            retry: Some(*self.retry_policy.lock().unwrap()),
             _type: PhantomData,
         }
     }
@@ -201,6 +367,10 @@ impl SpotifyClient {
         self.token.lock().unwrap().is_some()
     }
 
+    pub(crate) fn token(&self) -> Option<String> {
+        self.token.lock().unwrap().clone()
+    }
+
     pub(crate) fn update_token(&self, new_token: String) {
         if let Ok(mut token) = self.token.lock() {
             *token = Some(new_token)
@@ -213,14 +383,6 @@ impl SpotifyClient {
         }
     }
 
-    fn parse_cache_control(cache_control: &str) -> Option<u64> {
-        cache_control
-            .split(',')
-            .find(|s| s.trim().starts_with("max-age="))
-            .and_then(|s| s.split('=').nth(1))
-            .and_then(|s| u64::from_str(s).ok())
-    }
-
     async fn send_req<B, T>(
         &self,
         request: Request<B>,
@@ -228,7 +390,7 @@ impl SpotifyClient {
     where
         B: Into<isahc::AsyncBody>,
     {
-        let mut result = self.client.send_async(request).await?;
+        let mut result = self.send_tracked(request).await?;
 
         let etag = result
             .headers()
@@ -240,7 +402,7 @@ impl SpotifyClient {
             .headers()
             .get("cache-control")
             .and_then(|header| header.to_str().ok())
-            .and_then(Self::parse_cache_control);
+            .and_then(cache::max_age);
 
         match result.status() {
             StatusCode::NO_CONTENT => Err(SpotifyApiError::NoContent),
@@ -273,7 +435,7 @@ impl SpotifyClient {
     where
         B: Into<isahc::AsyncBody>,
     {
-        let mut result = self.client.send_async(request).await?;
+        let mut result = self.send_tracked(request).await?;
         match result.status() {
             StatusCode::UNAUTHORIZED => {
                 self.clear_token();
@@ -305,9 +467,29 @@ impl SpotifyClient {
         id: &str,
         offset: usize,
         limit: usize,
+    ) -> SpotifyRequest<'_, (), Page<Album>> {
+        self.get_artist_releases(id, "album,single", offset, limit)
+    }
+
+    // Albums come before singles, newer ones can't be found among the albums
+    pub(crate) fn get_artist_singles(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> SpotifyRequest<'_, (), Page<Album>> {
+        self.get_artist_releases(id, "single", offset, limit)
+    }
+
+    fn get_artist_releases(
+        &self,
+        id: &str,
+        groups: &str,
+        offset: usize,
+        limit: usize,
     ) -> SpotifyRequest<'_, (), Page<Album>> {
         let query = make_query_params()
-            .append_pair("include_groups", "album,single")
+            .append_pair("include_groups", groups)
             .append_pair("country", "from_token")
             .append_pair("offset", &offset.to_string()[..])
             .append_pair("limit", &limit.to_string()[..])
@@ -328,6 +510,13 @@ impl SpotifyClient {
             .uri(format!("/v1/artists/{id}/top-tracks"), Some(&query))
     }
 
+    pub(crate) fn get_audio_analysis(&self, id: &str) -> SpotifyRequest<'_, (), AudioAnalysis> {
+        let id = utf8_percent_encode(id, PATH_ENCODE_SET);
+        self.request()
+            .method(Method::GET)
+            .uri(format!("/v1/audio-analysis/{id}"), None)
+    }
+
     pub(crate) fn is_album_saved(&self, id: &str) -> SpotifyRequest<'_, (), Vec<bool>> {
         let query = make_query_params().append_pair("ids", id).finish();
         self.request()
@@ -335,6 +524,35 @@ impl SpotifyClient {
             .uri("/v1/me/albums/contains".to_string(), Some(&query))
     }
 
+    pub(crate) fn is_artist_followed(&self, id: &str) -> SpotifyRequest<'_, (), Vec<bool>> {
+        let query = make_query_params()
+            .append_pair("type", "artist")
+            .append_pair("ids", id)
+            .finish();
+        self.request()
+            .method(Method::GET)
+            .uri("/v1/me/following/contains".to_string(), Some(&query))
+    }
+
+    pub(crate) fn get_tracks(&self, ids: &[String]) -> SpotifyRequest<'_, (), Tracks> {
+        let query = make_query_params()
+            .append_pair("ids", &ids.join(","))
+            .append_pair("market", "from_token")
+            .finish();
+        self.request()
+            .method(Method::GET)
+            .uri("/v1/tracks".to_string(), Some(&query))
+    }
+
+    pub(crate) fn are_tracks_saved(&self, ids: &[String]) -> SpotifyRequest<'_, (), Vec<bool>> {
+        let query = make_query_params()
+            .append_pair("ids", &ids.join(","))
+            .finish();
+        self.request()
+            .method(Method::GET)
+            .uri("/v1/me/tracks/contains".to_string(), Some(&query))
+    }
+
     pub(crate) fn save_album(&self, id: &str) -> SpotifyRequest<'_, (), ()> {
         let query = make_query_params().append_pair("ids", id).finish();
         self.request()
@@ -356,6 +574,26 @@ impl SpotifyClient {
             .uri("/v1/me/albums".to_string(), Some(&query))
     }
 
+    pub(crate) fn follow_artist(&self, id: &str) -> SpotifyRequest<'_, (), ()> {
+        let query = make_query_params()
+            .append_pair("type", "artist")
+            .append_pair("ids", id)
+            .finish();
+        self.request()
+            .method(Method::PUT)
+            .uri("/v1/me/following".to_string(), Some(&query))
+    }
+
+    pub(crate) fn unfollow_artist(&self, id: &str) -> SpotifyRequest<'_, (), ()> {
+        let query = make_query_params()
+            .append_pair("type", "artist")
+            .append_pair("ids", id)
+            .finish();
+        self.request()
+            .method(Method::DELETE)
+            .uri("/v1/me/following".to_string(), Some(&query))
+    }
+
     pub(crate) fn remove_saved_tracks(&self, ids: Vec<String>) -> SpotifyRequest<'_, Vec<u8>, ()> {
         self.request()
             .method(Method::DELETE)
@@ -364,9 +602,12 @@ impl SpotifyClient {
     }
 
     pub(crate) fn get_album(&self, id: &str) -> SpotifyRequest<'_, (), FullAlbum> {
+        let query = make_query_params()
+            .append_pair("market", "from_token")
+            .finish();
         self.request()
             .method(Method::GET)
-            .uri(format!("/v1/albums/{id}"), None)
+            .uri(format!("/v1/albums/{id}"), Some(&query))
     }
 
     pub(crate) fn get_album_tracks(
@@ -376,6 +617,7 @@ impl SpotifyClient {
         limit: usize,
     ) -> SpotifyRequest<'_, (), Page<AlbumTrackItem>> {
         let query = make_query_params()
+            .append_pair("market", "from_token")
             .append_pair("offset", &offset.to_string()[..])
             .append_pair("limit", &limit.to_string()[..])
             .finish();
@@ -390,7 +632,7 @@ impl SpotifyClient {
             .append_pair("market", "from_token")
             // why still grab the tracks field?
             // the model still expects the appearance of a tracks field
-            .append_pair("fields", "id,name,images,owner,tracks(total)")
+            .append_pair("fields", "id,name,images,owner,snapshot_id,tracks(total)")
             .finish();
         self.request()
             .method(Method::GET)
@@ -440,11 +682,12 @@ impl SpotifyClient {
         &self,
         playlist: &str,
         uris: Vec<String>,
-    ) -> SpotifyRequest<'_, Vec<u8>, ()> {
+        snapshot_id: Option<&str>,
+    ) -> SpotifyRequest<'_, Vec<u8>, PlaylistSnapshot> {
         self.request()
             .method(Method::DELETE)
             .uri(format!("/v1/playlists/{playlist}/tracks"), None)
-            .json_body(Uris { uris })
+            .json_body(SnapshotUris { uris, snapshot_id })
     }
 
     pub(crate) fn update_playlist_details(
@@ -460,25 +703,38 @@ impl SpotifyClient {
 
     pub(crate) fn get_saved_albums(
         &self,
-        offset: usize,
+        page: &PageToken,
         limit: usize,
     ) -> SpotifyRequest<'_, (), Page<SavedAlbum>> {
-        let query = make_query_params()
-            .append_pair("offset", &offset.to_string()[..])
-            .append_pair("limit", &limit.to_string()[..])
-            .finish();
+        let query = make_page_params(page, limit).finish();
 
         self.request()
             .method(Method::GET)
             .uri("/v1/me/albums".to_string(), Some(&query))
     }
 
+    // Only cursor-based, by name
+    pub(crate) fn get_followed_artists(
+        &self,
+        page: &PageToken,
+        limit: usize,
+    ) -> SpotifyRequest<'_, (), FollowedArtists> {
+        let query = make_page_params(page, limit)
+            .append_pair("type", "artist")
+            .finish();
+
+        self.request()
+            .method(Method::GET)
+            .uri("/v1/me/following".to_string(), Some(&query))
+    }
+
     pub(crate) fn get_saved_tracks(
         &self,
         offset: usize,
         limit: usize,
     ) -> SpotifyRequest<'_, (), Page<SavedTrack>> {
         let query = make_query_params()
+            .append_pair("market", "from_token")
             .append_pair("offset", &offset.to_string()[..])
             .append_pair("limit", &limit.to_string()[..])
             .finish();
@@ -503,6 +759,69 @@ impl SpotifyClient {
             .uri("/v1/me/playlists".to_string(), Some(&query))
     }
 
+    pub(crate) fn get_saved_audiobooks(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> SpotifyRequest<'_, (), Page<Audiobook>> {
+        let query = make_query_params()
+            .append_pair("offset", &offset.to_string()[..])
+            .append_pair("limit", &limit.to_string()[..])
+            .finish();
+
+        self.request()
+            .method(Method::GET)
+            .uri("/v1/me/audiobooks".to_string(), Some(&query))
+    }
+
+    pub(crate) fn get_audiobook(&self, id: &str) -> SpotifyRequest<'_, (), Audiobook> {
+        let id = utf8_percent_encode(id, PATH_ENCODE_SET);
+        let query = make_query_params()
+            .append_pair("market", "from_token")
+            .finish();
+
+        self.request()
+            .method(Method::GET)
+            .uri(format!("/v1/audiobooks/{id}"), Some(&query))
+    }
+
+    pub(crate) fn get_audiobook_chapters(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> SpotifyRequest<'_, (), Page<Chapter>> {
+        let id = utf8_percent_encode(id, PATH_ENCODE_SET);
+        let query = make_query_params()
+            .append_pair("market", "from_token")
+            .append_pair("offset", &offset.to_string()[..])
+            .append_pair("limit", &limit.to_string()[..])
+            .finish();
+
+        self.request()
+            .method(Method::GET)
+            .uri(format!("/v1/audiobooks/{id}/chapters"), Some(&query))
+    }
+
+    // Not part of the documented API, but it's what the official clients use for their home screen
+    pub(crate) fn get_view(
+        &self,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> SpotifyRequest<'_, (), View> {
+        let id = utf8_percent_encode(id, PATH_ENCODE_SET);
+        let query = make_query_params()
+            .append_pair("offset", &offset.to_string()[..])
+            .append_pair("limit", &limit.to_string()[..])
+            .append_pair("content_limit", &limit.to_string()[..])
+            .finish();
+
+        self.request()
+            .method(Method::GET)
+            .uri(format!("/v1/views/{id}"), Some(&query))
+    }
+
     pub(crate) fn search(
         &self,
         query: String,
@@ -521,6 +840,23 @@ impl SpotifyClient {
             .uri("/v1/search".to_string(), Some(&query.into_query_string()))
     }
 
+    pub(crate) fn search_playlists(
+        &self,
+        query: String,
+        limit: usize,
+    ) -> SpotifyRequest<'_, (), RawSearchResults> {
+        let query = SearchQuery {
+            query,
+            types: vec![SearchType::Playlist],
+            limit,
+            offset: 0,
+        };
+
+        self.request()
+            .method(Method::GET)
+            .uri("/v1/search".to_string(), Some(&query.into_query_string()))
+    }
+
     pub(crate) fn get_user(&self, id: &str) -> SpotifyRequest<'_, (), User> {
         let id = utf8_percent_encode(id, PATH_ENCODE_SET);
         self.request()
@@ -604,6 +940,20 @@ impl SpotifyClient {
             .uri("/v1/me/player/next".to_string(), Some(&query))
     }
 
+    pub(crate) fn player_add_to_queue(
+        &self,
+        device_id: &str,
+        uri: &str,
+    ) -> SpotifyRequest<'_, (), ()> {
+        let query = make_query_params()
+            .append_pair("device_id", device_id)
+            .append_pair("uri", uri)
+            .finish();
+        self.request()
+            .method(Method::POST)
+            .uri("/v1/me/player/queue".to_string(), Some(&query))
+    }
+
     pub(crate) fn player_seek(&self, device_id: &str, pos: usize) -> SpotifyRequest<'_, (), ()> {
         let query = make_query_params()
             .append_pair("device_id", device_id)
@@ -641,6 +991,63 @@ impl SpotifyClient {
             .uri("/v1/me/player/shuffle".to_string(), Some(&query))
     }
 
+    // Start getting player events over the dealer connection with that id
+    pub(crate) fn register_player_notifications(
+        &self,
+        connection_id: &str,
+    ) -> SpotifyRequest<'_, (), ()> {
+        let query = make_query_params()
+            .append_pair("connection_id", connection_id)
+            .finish();
+
+        self.request()
+            .method(Method::PUT)
+            .uri("/v1/me/notifications/player".to_string(), Some(&query))
+    }
+
+    pub(crate) fn group_session_current_or_new(
+        &self,
+        device_id: &str,
+    ) -> SpotifyRequest<'_, (), SocialSession> {
+        let query = make_query_params()
+            .append_pair("activeDeviceId", device_id)
+            .append_pair("type", "REMOTE")
+            .finish();
+
+        // A GET, but one that creates the session: trying again could start a second one
+        self.request()
+            .method(Method::GET)
+            .uri_on(
+                SPCLIENT_HOST,
+                "/social-connect/v2/sessions/current_or_new".to_string(),
+                Some(&query),
+            )
+            .no_retry()
+    }
+
+    pub(crate) fn group_session_join(&self, token: &str) -> SpotifyRequest<'_, (), SocialSession> {
+        let token = utf8_percent_encode(token, PATH_ENCODE_SET);
+        let query = make_query_params()
+            .append_pair("playback_control", "listen_and_control")
+            .append_pair("join_type", "deeplinking")
+            .finish();
+
+        self.request().method(Method::POST).uri_on(
+            SPCLIENT_HOST,
+            format!("/social-connect/v2/sessions/join/{token}"),
+            Some(&query),
+        )
+    }
+
+    pub(crate) fn group_session_leave(&self, session_id: &str) -> SpotifyRequest<'_, (), ()> {
+        let session_id = utf8_percent_encode(session_id, PATH_ENCODE_SET);
+        self.request().method(Method::POST).uri_on(
+            SPCLIENT_HOST,
+            format!("/social-connect/v3/sessions/{session_id}/leave"),
+            None,
+        )
+    }
+
     pub(crate) fn player_volume(&self, device_id: &str, volume: u8) -> SpotifyRequest<'_, (), ()> {
         let query = make_query_params()
             .append_pair("device_id", device_id)
@@ -661,7 +1068,7 @@ pub mod tests {
     #[test]
     fn test_username_encoding() {
         let username = "anna.lafuente❤";
-        let client = SpotifyClient::new();
+        let client = SpotifyClient::new(Default::default());
         let req = client.get_user(username);
         assert_eq!(
             req.request
@@ -673,6 +1080,43 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_retry_delay_is_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+
+        assert_eq!(policy.delay_for(0, 1.0), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(2, 1.0), Duration::from_millis(400));
+        assert_eq!(policy.delay_for(8, 1.0), Duration::from_secs(1));
+        assert_eq!(policy.delay_for(8, 0.0), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_only_transient_errors_are_retried() {
+        let policy = RetryPolicy::default();
+
+        let server_error = SpotifyApiError::BadStatus(503, String::new());
+        assert!(policy.should_retry(0, &server_error));
+        assert!(!policy.should_retry(policy.max_retries, &server_error));
+
+        let not_found = SpotifyApiError::BadStatus(404, String::new());
+        assert!(!policy.should_retry(0, &not_found));
+        assert!(!policy.should_retry(0, &SpotifyApiError::InvalidToken));
+    }
+
+    #[test]
+    fn test_snapshot_conflicts() {
+        assert!(SpotifyApiError::BadStatus(409, String::new()).is_snapshot_conflict());
+        let invalid_snapshot = r#"{"error":{"status":400,"message":"Invalid snapshot id"}}"#;
+        assert!(
+            SpotifyApiError::BadStatus(400, invalid_snapshot.to_string()).is_snapshot_conflict()
+        );
+        assert!(!SpotifyApiError::BadStatus(400, "Invalid uri".to_string()).is_snapshot_conflict());
+    }
+
     #[test]
     fn test_search_query() {
         let query = SearchQuery {
@@ -0,0 +1,163 @@
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use super::client::{PlayerState, SpotifyApiError, SpotifyClient};
+use crate::app::models::ConnectPlayerState;
+
+const DEALER_URL: &str = "wss://dealer.spotify.com/";
+const CONNECTION_ID_HEADER: &str = "Spotify-Connection-Id";
+// The dealer drops connections that stay silent for too long
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum PlayerEvent {
+    // Registered for notifications, updates get pushed from now on
+    Connected,
+    StateChanged(Box<ConnectPlayerState>),
+    DevicesChanged,
+}
+
+#[derive(Deserialize, Debug)]
+struct DealerMessage {
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    // Not all payloads are JSON objects, we don't care about the others
+    #[serde(default)]
+    payloads: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EventsPayload {
+    events: Vec<DealerEvent>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DealerEvent {
+    #[serde(rename = "type")]
+    type_: String,
+    event: Option<DealerEventContent>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DealerEventContent {
+    state: Option<PlayerState>,
+}
+
+#[derive(Debug)]
+enum Parsed {
+    ConnectionId(String),
+    Events(Vec<PlayerEvent>),
+    Ignored,
+}
+
+fn parse_message(text: &str) -> Parsed {
+    let Ok(message) = serde_json::from_str::<DealerMessage>(text) else {
+        return Parsed::Ignored;
+    };
+    if message.type_ != "message" {
+        return Parsed::Ignored;
+    }
+    if let Some(id) = message.headers.get(CONNECTION_ID_HEADER) {
+        return Parsed::ConnectionId(id.clone());
+    }
+    let events = message
+        .payloads
+        .into_iter()
+        .filter_map(|payload| serde_json::from_value::<EventsPayload>(payload).ok())
+        .flat_map(|payload| payload.events)
+        .filter_map(|event| match event.type_.as_str() {
+            "PLAYER_STATE_CHANGED" => {
+                let state = event.event?.state?;
+                Some(PlayerEvent::StateChanged(Box::new(state.into())))
+            }
+            "DEVICE_STATE_CHANGED" => Some(PlayerEvent::DevicesChanged),
+            _ => None,
+        })
+        .collect();
+    Parsed::Events(events)
+}
+
+fn websocket_error(e: tokio_tungstenite::tungstenite::Error) -> SpotifyApiError {
+    SpotifyApiError::from(Box::new(e))
+}
+
+// Spotify pushes player updates over a websocket (the "dealer"). The first thing we get is a connection id,
+// which has to be registered through the web API before any event is sent our way.
+// Returns when the connection gets closed.
+pub(crate) async fn listen_player_events<F>(
+    client: &SpotifyClient,
+    mut on_event: F,
+) -> Result<(), SpotifyApiError>
+where
+    F: FnMut(PlayerEvent),
+{
+    let token = client.token().ok_or(SpotifyApiError::NoToken)?;
+    let (socket, _) = connect_async(format!("{DEALER_URL}?access_token={token}"))
+        .await
+        .map_err(websocket_error)?;
+    let (mut sink, mut stream) = socket.split();
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = ping.tick() => {
+                sink.send(Message::Text(r#"{"type":"ping"}"#.to_string()))
+                    .await
+                    .map_err(websocket_error)?;
+            }
+            message = stream.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(websocket_error(e)),
+                    None => return Ok(()),
+                };
+                match parse_message(&text) {
+                    Parsed::ConnectionId(id) => {
+                        debug!("dealer connected");
+                        client.register_player_notifications(&id).send_no_response().await?;
+                        on_event(PlayerEvent::Connected);
+                    }
+                    Parsed::Events(events) => events.into_iter().for_each(&mut on_event),
+                    Parsed::Ignored => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_connection_id() {
+        let message = r#"{"type":"message","uri":"hm://pusher/v1/connections/abc","headers":{"Spotify-Connection-Id":"abc"},"method":"PUT"}"#;
+        assert!(matches!(parse_message(message), Parsed::ConnectionId(id) if id == "abc"));
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let message = r#"{"type":"message","uri":"wss://event","payloads":[{"events":[{"type":"DEVICE_STATE_CHANGED","event":{}},{"type":"SOMETHING_ELSE"}]}]}"#;
+        let Parsed::Events(events) = parse_message(message) else {
+            panic!("expected events");
+        };
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], PlayerEvent::DevicesChanged));
+    }
+
+    #[test]
+    fn test_ignore_pong() {
+        assert!(matches!(
+            parse_message(r#"{"type":"pong"}"#),
+            Parsed::Ignored
+        ));
+        assert!(matches!(parse_message("not json"), Parsed::Ignored));
+    }
+}
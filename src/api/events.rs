@@ -0,0 +1,108 @@
+use futures::future::BoxFuture;
+use isahc::AsyncReadResponseExt;
+use serde::Deserialize;
+
+use super::{SpotifyApiError, SpotifyResult};
+use crate::app::models::ConcertDescription;
+
+// Where upcoming concerts come from. Spotify doesn't say, so it's up to a third party the user set up
+pub trait EventsProvider: Send + Sync {
+    fn upcoming_events(&self, artist: &str) -> BoxFuture<SpotifyResult<Vec<ConcertDescription>>>;
+}
+
+#[derive(Deserialize)]
+struct BandsintownVenue {
+    name: String,
+    city: String,
+    country: String,
+}
+
+#[derive(Deserialize)]
+struct BandsintownEvent {
+    url: String,
+    // Local to the venue
+    datetime: String,
+    venue: BandsintownVenue,
+}
+
+impl From<BandsintownEvent> for ConcertDescription {
+    fn from(event: BandsintownEvent) -> Self {
+        Self {
+            date: event.datetime,
+            venue: event.venue.name,
+            location: format!("{}, {}", event.venue.city, event.venue.country),
+            url: event.url,
+        }
+    }
+}
+
+fn parse_bandsintown_events(body: &str) -> Vec<ConcertDescription> {
+    // Artists it doesn't know come back as an error object instead of a list
+    serde_json::from_str::<Vec<BandsintownEvent>>(body)
+        .map(|events| events.into_iter().map(|e| e.into()).collect())
+        .unwrap_or_default()
+}
+
+// The app id is what Bandsintown hands out to apps using its API
+pub struct Bandsintown {
+    app_id: String,
+}
+
+impl EventsProvider for Bandsintown {
+    fn upcoming_events(&self, artist: &str) -> BoxFuture<SpotifyResult<Vec<ConcertDescription>>> {
+        let uri = format!(
+            "https://rest.bandsintown.com/artists/{}/events?app_id={}",
+            glib::Uri::escape_string(artist, None, true),
+            glib::Uri::escape_string(&self.app_id, None, true)
+        );
+        Box::pin(async move {
+            let mut response = isahc::get_async(uri).await?;
+            let status = response.status();
+            let body = response.text().await?;
+            if !status.is_success() {
+                return Err(SpotifyApiError::BadStatus(status.as_u16(), body));
+            }
+            Ok(parse_bandsintown_events(&body))
+        })
+    }
+}
+
+// Nothing if there's no key, and then no concerts are shown at all
+pub fn events_provider(app_id: &str) -> Option<Box<dyn EventsProvider>> {
+    let app_id = app_id.trim();
+    (!app_id.is_empty()).then(|| {
+        Box::new(Bandsintown {
+            app_id: app_id.to_string(),
+        }) as Box<dyn EventsProvider>
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_bandsintown_events() {
+        let body = r#"[{
+            "id": "1",
+            "url": "https://www.bandsintown.com/e/1",
+            "datetime": "2024-06-01T19:30:00",
+            "venue": {"name": "The Venue", "city": "Lyon", "region": "", "country": "France"},
+            "lineup": ["Someone"]
+        }]"#;
+        assert_eq!(
+            parse_bandsintown_events(body),
+            vec![ConcertDescription {
+                date: "2024-06-01T19:30:00".to_string(),
+                venue: "The Venue".to_string(),
+                location: "Lyon, France".to_string(),
+                url: "https://www.bandsintown.com/e/1".to_string(),
+            }]
+        );
+        assert_eq!(
+            parse_bandsintown_events(r#"{"errorMessage": "[NotFound] The artist was not found"}"#),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use isahc::http::Response;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+pub type HealthChanged = Box<dyn Fn(bool) + Send>;
+
+// Whether the Web API answers, from the requests made to it. Requests overlap and come back in any order:
+// an answer only counts if no request made after it has answered already.
+pub struct WebApiHealth {
+    started: AtomicU64,
+    // The latest request to have answered, and whether the Web API was reachable then
+    latest: Mutex<(u64, bool)>,
+    on_changed: Mutex<Option<HealthChanged>>,
+}
+
+impl Default for WebApiHealth {
+    fn default() -> Self {
+        Self {
+            started: AtomicU64::new(0),
+            latest: Mutex::new((0, true)),
+            on_changed: Mutex::new(None),
+        }
+    }
+}
+
+impl WebApiHealth {
+    pub fn connect_changed(&self, f: HealthChanged) {
+        self.on_changed.lock().unwrap().replace(f);
+    }
+
+    // To be called right before sending a request, the number is given back to record()
+    pub fn start(&self) -> u64 {
+        self.started.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn record<B>(&self, request: u64, response: &Result<Response<B>, isahc::Error>) {
+        let reachable = match response {
+            Ok(response) => !response.status().is_server_error(),
+            Err(e) => !e.is_network() && !e.is_timeout(),
+        };
+        // Still locked when telling about it, so that changes are told in order
+        let mut latest = self.latest.lock().unwrap();
+        if request < latest.0 {
+            return;
+        }
+        let (_, was_reachable) = std::mem::replace(&mut *latest, (request, reachable));
+        if was_reachable != reachable {
+            if let Some(f) = self.on_changed.lock().unwrap().as_ref() {
+                f(reachable);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::Arc;
+
+    fn response(status: u16) -> Result<Response<()>, isahc::Error> {
+        Ok(Response::builder().status(status).body(()).unwrap())
+    }
+
+    fn watch(health: &WebApiHealth) -> Arc<Mutex<Vec<bool>>> {
+        let changes = Arc::new(Mutex::new(vec![]));
+        health.connect_changed(Box::new({
+            let changes = Arc::clone(&changes);
+            move |up| changes.lock().unwrap().push(up)
+        }));
+        changes
+    }
+
+    #[test]
+    fn test_record() {
+        let health = WebApiHealth::default();
+        let changes = watch(&health);
+        health.record(health.start(), &response(503));
+        // Refused, but Spotify did answer
+        health.record(health.start(), &response(404));
+        health.record(health.start(), &response(200));
+        assert_eq!(*changes.lock().unwrap(), vec![false, true]);
+    }
+
+    #[test]
+    fn test_out_of_order_answers() {
+        let health = WebApiHealth::default();
+        let changes = watch(&health);
+
+        let slow = health.start();
+        let fast = health.start();
+        health.record(fast, &response(200));
+        // Old news by now
+        health.record(slow, &response(503));
+        assert!(changes.lock().unwrap().is_empty());
+
+        let slow = health.start();
+        let fast = health.start();
+        health.record(fast, &response(503));
+        health.record(slow, &response(200));
+        assert_eq!(*changes.lock().unwrap(), vec![false]);
+    }
+}
@@ -0,0 +1,120 @@
+use futures::channel::oneshot;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+type Waiters<T, E> = HashMap<String, Vec<oneshot::Sender<Result<T, E>>>>;
+
+// Collects individual lookups (e.g. "is this track saved?") so that they can be answered
+// by a single batch request. Callers enqueue their id, wait a little, then try to flush:
+// whoever flushes first gets to answer everybody else, or tell them why it couldn't.
+pub(crate) struct BatchedLookup<T, E> {
+    pending: Mutex<Waiters<T, E>>,
+}
+
+impl<T, E> BatchedLookup<T, E>
+where
+    T: Clone,
+    E: Clone,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn enqueue(&self, id: &str) -> oneshot::Receiver<Result<T, E>> {
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .entry(id.to_string())
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    // Empty if someone else already flushed
+    pub(crate) fn take_pending(&self) -> PendingLookups<T, E> {
+        let waiters = std::mem::take(&mut *self.pending.lock().unwrap());
+        PendingLookups { waiters }
+    }
+}
+
+pub(crate) struct PendingLookups<T, E> {
+    waiters: Waiters<T, E>,
+}
+
+impl<T, E> PendingLookups<T, E>
+where
+    T: Clone,
+    E: Clone,
+{
+    pub(crate) fn ids(&self) -> Vec<String> {
+        self.waiters.keys().cloned().collect()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.waiters.is_empty()
+    }
+
+    // Anybody whose id is missing from the results sees their receiver cancelled
+    pub(crate) fn resolve(self, results: impl IntoIterator<Item = (String, T)>) {
+        let mut waiters = self.waiters;
+        for (id, value) in results {
+            for sender in waiters.remove(&id).unwrap_or_default() {
+                let _ = sender.send(Ok(value.clone()));
+            }
+        }
+    }
+
+    // The batch request failed, for everyone
+    pub(crate) fn fail(self, error: E) {
+        for sender in self.waiters.into_values().flatten() {
+            let _ = sender.send(Err(error.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_coalesces_same_ids() {
+        let lookup = BatchedLookup::<bool, ()>::new();
+        let first = lookup.enqueue("a");
+        let second = lookup.enqueue("a");
+        let third = lookup.enqueue("b");
+
+        let pending = lookup.take_pending();
+        let mut ids = pending.ids();
+        ids.sort();
+        assert_eq!(ids, vec!["a".to_string(), "b".to_string()]);
+        assert!(lookup.take_pending().is_empty());
+
+        pending.resolve(vec![("a".to_string(), true), ("b".to_string(), false)]);
+        assert_eq!(block_on(first), Ok(Ok(true)));
+        assert_eq!(block_on(second), Ok(Ok(true)));
+        assert_eq!(block_on(third), Ok(Ok(false)));
+    }
+
+    #[test]
+    fn test_missing_results_are_cancelled() {
+        let lookup = BatchedLookup::<bool, ()>::new();
+        let receiver = lookup.enqueue("a");
+        lookup.take_pending().resolve(vec![]);
+        assert!(block_on(receiver).is_err());
+    }
+
+    #[test]
+    fn test_failures_reach_everyone() {
+        let lookup = BatchedLookup::<bool, &str>::new();
+        let first = lookup.enqueue("a");
+        let second = lookup.enqueue("b");
+        lookup.take_pending().fail("offline");
+        assert_eq!(block_on(first), Ok(Err("offline")));
+        assert_eq!(block_on(second), Ok(Err("offline")));
+    }
+}
@@ -1,16 +1,27 @@
 mod api_models;
 mod cached_client;
 mod client;
+mod dealer;
+mod events;
+mod lookup;
+mod pending_writes;
 
 pub mod cache;
+pub mod health;
 pub(crate) mod oauth2;
 
 pub use cached_client::{CachedSpotifyClient, SpotifyApiClient, SpotifyResult};
-pub use client::SpotifyApiError;
+pub use client::{RetryPolicy, SpotifyApiError};
+pub use dealer::PlayerEvent;
+pub use events::{events_provider, EventsProvider};
 
 pub async fn clear_user_cache() -> Option<()> {
+    let selections: Vec<cache::CacheSelection> = cached_client::USER_CACHE
+        .iter()
+        .map(|category| category.all())
+        .collect();
     cache::CacheManager::for_dir("spot/net")?
-        .clear_cache_pattern(&cached_client::USER_CACHE)
+        .clear(&selections)
         .await
         .ok()
 }
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::app::kv_store::KeyValueStore;
+
+// A write that couldn't be made because we were offline
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PendingWrite {
+    SaveTracks(Vec<String>),
+    RemoveSavedTracks(Vec<String>),
+    AddToPlaylist { id: String, uris: Vec<String> },
+}
+
+// The most songs Spotify takes in one request
+const MAX_SAVED_TRACKS: usize = 50;
+const MAX_PLAYLIST_TRACKS: usize = 100;
+
+impl PendingWrite {
+    pub fn song_count(&self) -> usize {
+        match self {
+            Self::SaveTracks(ids) | Self::RemoveSavedTracks(ids) => ids.len(),
+            Self::AddToPlaylist { uris, .. } => uris.len(),
+        }
+    }
+
+    // Split into writes small enough for one request each, to be made in that order
+    pub fn into_chunks(self) -> Vec<PendingWrite> {
+        match self {
+            Self::SaveTracks(ids) => ids
+                .chunks(MAX_SAVED_TRACKS)
+                .map(|ids| Self::SaveTracks(ids.to_vec()))
+                .collect(),
+            Self::RemoveSavedTracks(ids) => ids
+                .chunks(MAX_SAVED_TRACKS)
+                .map(|ids| Self::RemoveSavedTracks(ids.to_vec()))
+                .collect(),
+            Self::AddToPlaylist { id, uris } => uris
+                .chunks(MAX_PLAYLIST_TRACKS)
+                .map(|uris| Self::AddToPlaylist {
+                    id: id.clone(),
+                    uris: uris.to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    fn saved_track_ids(&mut self) -> Option<&mut Vec<String>> {
+        match self {
+            Self::SaveTracks(ids) | Self::RemoveSavedTracks(ids) => Some(ids),
+            Self::AddToPlaylist { .. } => None,
+        }
+    }
+}
+
+// The writes to make again once we're back online, oldest first.
+// Kept in ~/.local/share/spot as well, so that quitting while offline doesn't lose them,
+// under the name of the user who made them: they're only ever made on behalf of that same user.
+pub struct PendingWrites {
+    store: Mutex<KeyValueStore<Vec<PendingWrite>>>,
+    user: Mutex<Option<String>>,
+}
+
+impl Default for PendingWrites {
+    fn default() -> Self {
+        Self::with_store(KeyValueStore::open("pending-writes"))
+    }
+}
+
+impl PendingWrites {
+    fn with_store(store: KeyValueStore<Vec<PendingWrite>>) -> Self {
+        Self {
+            store: Mutex::new(store),
+            user: Mutex::new(None),
+        }
+    }
+
+    pub fn set_user(&self, user: Option<String>) {
+        *self.user.lock().unwrap() = user;
+    }
+
+    // Logging out: whatever that user left is given up on
+    pub fn clear(&self) {
+        self.update(Vec::clear)
+    }
+
+    // Saved right after, whatever f did (nothing is kept while nobody is logged in)
+    fn update<R>(&self, f: impl FnOnce(&mut Vec<PendingWrite>) -> R) -> R {
+        let Some(user) = self.user.lock().unwrap().clone() else {
+            return f(&mut vec![]);
+        };
+        let mut store = self.store.lock().unwrap();
+        let mut writes = store.remove(&user).unwrap_or_default();
+        let result = f(&mut writes);
+        if !writes.is_empty() {
+            store.set(user, writes);
+        }
+        if let Err(e) = store.save() {
+            warn!("Could not save pending writes: {}", e);
+        }
+        result
+    }
+
+    // Saving a track and then unsaving it amounts to nothing, so only the last say on each track is kept
+    pub fn push(&self, mut write: PendingWrite) {
+        self.update(|writes| {
+            if let Some(ids) = write.saved_track_ids() {
+                let ids = ids.clone();
+                for previous in writes.iter_mut() {
+                    if let Some(previous_ids) = previous.saved_track_ids() {
+                        previous_ids.retain(|id| !ids.contains(id));
+                    }
+                }
+                writes.retain_mut(|w| {
+                    w.saved_track_ids()
+                        .map(|ids| !ids.is_empty())
+                        .unwrap_or(true)
+                });
+            }
+            writes.push(write);
+        })
+    }
+
+    pub fn take_all(&self) -> Vec<PendingWrite> {
+        self.update(std::mem::take)
+    }
+
+    // For writes that were taken but couldn't be made after all, they go before anything pushed since
+    pub fn put_back(&self, mut taken: Vec<PendingWrite>) {
+        self.update(|writes| {
+            taken.append(writes);
+            *writes = taken;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::test_utils::TempDir;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_into_chunks() {
+        let ids: Vec<String> = (0..120).map(|i| i.to_string()).collect();
+        let chunks = PendingWrite::SaveTracks(ids.clone()).into_chunks();
+        let sizes: Vec<usize> = chunks.iter().map(|c| c.song_count()).collect();
+        assert_eq!(sizes, vec![50, 50, 20]);
+        assert_eq!(chunks[2], PendingWrite::SaveTracks(ids[100..].to_vec()));
+
+        let chunks = PendingWrite::AddToPlaylist {
+            id: "playlist".to_string(),
+            uris: ids,
+        }
+        .into_chunks();
+        assert_eq!(chunks.len(), 2);
+    }
+
+    #[test]
+    fn test_last_say_on_a_track_wins() {
+        let dir = TempDir::create();
+        let path = dir.join("pending-writes.json");
+        let open = |user: &str| {
+            let writes = PendingWrites::with_store(KeyValueStore::open_at(path.clone()));
+            writes.set_user(Some(user.to_string()));
+            writes
+        };
+        let writes = open("user");
+        writes.push(PendingWrite::SaveTracks(ids(&["a", "b"])));
+        writes.push(PendingWrite::AddToPlaylist {
+            id: "playlist".to_string(),
+            uris: ids(&["spotify:track:a"]),
+        });
+        writes.push(PendingWrite::RemoveSavedTracks(ids(&["a"])));
+        writes.push(PendingWrite::SaveTracks(ids(&["b"])));
+
+        let expected = vec![
+            PendingWrite::AddToPlaylist {
+                id: "playlist".to_string(),
+                uris: ids(&["spotify:track:a"]),
+            },
+            PendingWrite::RemoveSavedTracks(ids(&["a"])),
+            PendingWrite::SaveTracks(ids(&["b"])),
+        ];
+        // Still there after a restart, but only for whoever made them
+        assert!(open("someone else").take_all().is_empty());
+        assert_eq!(open("user").take_all(), expected);
+
+        let taken = writes.take_all();
+        assert_eq!(taken, expected);
+        writes.push(PendingWrite::SaveTracks(ids(&["c"])));
+        writes.put_back(taken[1..].to_vec());
+        assert_eq!(
+            writes.take_all(),
+            vec![
+                PendingWrite::RemoveSavedTracks(ids(&["a"])),
+                PendingWrite::SaveTracks(ids(&["b"])),
+                PendingWrite::SaveTracks(ids(&["c"])),
+            ]
+        );
+
+        writes.push(PendingWrite::SaveTracks(ids(&["d"])));
+        writes.clear();
+        assert!(open("user").take_all().is_empty());
+    }
+}
@@ -1,37 +1,50 @@
-use gettextrs::gettext;
-use std::sync::Arc;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
-use crate::api::{SpotifyApiClient, SpotifyApiError};
+use crate::api::{SpotifyApiClient, SpotifyApiError, SpotifyResult};
 use crate::app::models::*;
-use crate::app::AppAction;
+use crate::app::{AppAction, AppError, Retry};
 
 // A wrapper around the Spotify API to load batches of songs from various sources (see below)
 #[derive(Clone)]
 pub struct BatchLoader {
-    api: Arc<dyn SpotifyApiClient + Send + Sync>,
+    fetch: Arc<FetchBatch>,
+    in_flight: Arc<Mutex<HashMap<InFlightKey, SharedBatch>>>,
 }
 
+type FetchBatch = dyn Fn(&BatchQuery) -> BoxFuture<'static, SpotifyResult<SongBatch>> + Send + Sync;
+
+type InFlightKey = (SongsSource, usize);
+
+// Whoever asks for a batch that's already loading gets the same result
+type SharedBatch = Shared<BoxFuture<'static, Result<SongBatch, Arc<SpotifyApiError>>>>;
+
+// Removes the query from the in-flight map, whatever happens to the future
+struct InFlightGuard {
+    in_flight: Arc<Mutex<HashMap<InFlightKey, SharedBatch>>>,
+    key: InFlightKey,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(&self.key);
+    }
+}
+
+// How many batches load_all fetches at the same time
+const PARALLEL_BATCHES: usize = 4;
+
 // The sources mentionned above
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SongsSource {
     Playlist(String),
     Album(String),
     SavedTracks,
 }
 
-impl PartialEq for SongsSource {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::Playlist(l), Self::Playlist(r)) => l == r,
-            (Self::Album(l), Self::Album(r)) => l == r,
-            (Self::SavedTracks, Self::SavedTracks) => true,
-            _ => false,
-        }
-    }
-}
-
-impl Eq for SongsSource {}
-
 impl SongsSource {
     pub fn has_spotify_uri(&self) -> bool {
         matches!(self, Self::Playlist(_) | Self::Album(_))
@@ -47,7 +60,7 @@ impl SongsSource {
 }
 
 // How to query for a batch: specify a source, and a batch to get (offset + number of elements to get)
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct BatchQuery {
     pub source: SongsSource,
     pub batch: Batch,
@@ -66,40 +79,198 @@ impl BatchQuery {
 
 impl BatchLoader {
     pub fn new(api: Arc<dyn SpotifyApiClient + Send + Sync>) -> Self {
-        Self { api }
+        Self::with_fetch(move |query| {
+            let api = Arc::clone(&api);
+            let BatchQuery { source, batch } = query.clone();
+            Box::pin(async move {
+                let Batch {
+                    offset, batch_size, ..
+                } = batch;
+                match &source {
+                    SongsSource::Playlist(id) => {
+                        api.get_playlist_tracks(id, offset, batch_size).await
+                    }
+                    SongsSource::SavedTracks => api.get_saved_tracks(offset, batch_size).await,
+                    SongsSource::Album(id) => api.get_album_tracks(id, offset, batch_size).await,
+                }
+            })
+        })
+    }
+
+    fn with_fetch<F>(fetch: F) -> Self
+    where
+        F: Fn(&BatchQuery) -> BoxFuture<'static, SpotifyResult<SongBatch>> + Send + Sync + 'static,
+    {
+        Self {
+            fetch: Arc::new(fetch),
+            in_flight: Default::default(),
+        }
+    }
+
+    // The batch, fetched only if it isn't already (say, prefetching kicked in and then we hit the bottom)
+    fn load_batch(&self, query: &BatchQuery) -> SharedBatch {
+        let key = (query.source.clone(), query.batch.offset);
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(batch) = in_flight.get(&key) {
+            debug!("Batch already loading: {:?}", query);
+            return batch.clone();
+        }
+        let guard = InFlightGuard {
+            in_flight: Arc::clone(&self.in_flight),
+            key: key.clone(),
+        };
+        let fetched = (self.fetch)(query);
+        let batch = async move {
+            let _guard = guard;
+            fetched.await.map_err(Arc::new)
+        }
+        .boxed()
+        .shared();
+        in_flight.insert(key, batch.clone());
+        batch
     }
 
-    // Query a batch and create an action when it's been retrieved succesfully
-    pub async fn query<ActionCreator>(
+    // Query a batch and create an action when it's been retrieved succesfully.
+    // If the very same batch is already being fetched, the action is created once that's done.
+    pub fn query<ActionCreator>(
         &self,
         query: BatchQuery,
         create_action: ActionCreator,
-    ) -> Option<AppAction>
+    ) -> BoxFuture<'static, Option<AppAction>>
     where
-        ActionCreator: FnOnce(SongsSource, SongBatch) -> AppAction,
+        ActionCreator: FnOnce(SongsSource, SongBatch) -> AppAction + Clone + Send + 'static,
     {
-        let api = Arc::clone(&self.api);
-
-        let Batch {
-            offset, batch_size, ..
-        } = query.batch;
-        let result = match &query.source {
-            SongsSource::Playlist(id) => api.get_playlist_tracks(id, offset, batch_size).await,
-            SongsSource::SavedTracks => api.get_saved_tracks(offset, batch_size).await,
-            SongsSource::Album(id) => api.get_album_tracks(id, offset, batch_size).await,
-        };
+        let actions = self.query_many(query, move |source, batch| {
+            vec![create_action(source, batch)]
+        });
+        Box::pin(async move { actions.await.into_iter().next() })
+    }
+
+    // Query all these batches, a few at a time, for when the whole list is needed at once (say, to play all of it).
+    // The actions come in the order of the queries, whatever order the batches actually arrive in.
+    pub fn load_all<ActionCreator>(
+        &self,
+        queries: Vec<BatchQuery>,
+        create_actions: ActionCreator,
+    ) -> BoxStream<'static, AppAction>
+    where
+        ActionCreator: FnOnce(SongsSource, SongBatch) -> Vec<AppAction> + Clone + Send + 'static,
+    {
+        let loader = self.clone();
+        let total = queries.len();
+        stream::iter(queries)
+            .map(move |query| loader.query_many(query, create_actions.clone()))
+            .buffered(PARALLEL_BATCHES)
+            .enumerate()
+            .flat_map(move |(i, actions)| {
+                debug!("Loaded batch {}/{}", i + 1, total);
+                stream::iter(actions)
+            })
+            .boxed()
+    }
 
-        match result {
-            Ok(batch) => Some(create_action(query.source, batch)),
-            // No token? Why was the batch loader called? Ah, whatever
-            Err(SpotifyApiError::NoToken) => None,
-            Err(err) => {
-                error!("Spotify API error: {}", err);
-                Some(AppAction::ShowNotification(gettext(
-                    // translators: This notification is the default message for unhandled errors. Logs refer to console output.
-                    "An error occured. Check logs for details!",
-                )))
+    fn query_many<ActionCreator>(
+        &self,
+        query: BatchQuery,
+        create_actions: ActionCreator,
+    ) -> BoxFuture<'static, Vec<AppAction>>
+    where
+        ActionCreator: FnOnce(SongsSource, SongBatch) -> Vec<AppAction> + Clone + Send + 'static,
+    {
+        let loader = self.clone();
+        Box::pin(async move {
+            let result = loader.load_batch(&query).await;
+
+            match result {
+                Ok(batch) => create_actions(query.source, batch),
+                // No token? Why was the batch loader called? Ah, whatever
+                Err(err) if matches!(*err, SpotifyApiError::NoToken) => vec![],
+                Err(err) => {
+                    error!("Spotify API error: {}", err);
+                    let retry = Retry::new(move || {
+                        loader.query_many(query.clone(), create_actions.clone())
+                    });
+                    vec![AppAction::ShowError(AppError::new(&err, retry))]
+                }
             }
-        }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::state::PlaybackAction;
+    use futures::executor::block_on;
+    use futures::future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Poll;
+
+    fn queries(total: usize) -> Vec<BatchQuery> {
+        (0..total)
+            .step_by(10)
+            .map(|offset| BatchQuery {
+                source: SongsSource::Album("a".to_string()),
+                batch: Batch {
+                    offset,
+                    batch_size: 10,
+                    total,
+                },
+            })
+            .collect()
+    }
+
+    fn offsets(actions: Vec<AppAction>) -> Vec<usize> {
+        actions
+            .into_iter()
+            .filter_map(|action| match action {
+                AppAction::PlaybackAction(PlaybackAction::AppendPagedSongs(_, batch)) => {
+                    Some(batch.batch.offset)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_overlapping_loads_share_batches() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let loader = BatchLoader::with_fetch({
+            let fetches = Arc::clone(&fetches);
+            move |query| {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                let batch = query.batch;
+                // Still loading when the other load asks for it
+                let mut yielded = false;
+                let wait = future::poll_fn(move |cx| {
+                    if std::mem::replace(&mut yielded, true) {
+                        Poll::Ready(())
+                    } else {
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                });
+                Box::pin(async move {
+                    wait.await;
+                    Ok(SongBatch {
+                        songs: vec![],
+                        batch,
+                    })
+                })
+            }
+        });
+
+        let append = |source: SongsSource, batch: SongBatch| -> Vec<AppAction> {
+            vec![PlaybackAction::AppendPagedSongs(source, batch).into()]
+        };
+        let first = loader.load_all(queries(30), append).collect::<Vec<_>>();
+        let second = loader.load_all(queries(30), append).collect::<Vec<_>>();
+        let (first, second) = block_on(future::join(first, second));
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+        assert_eq!(offsets(first), vec![0, 10, 20]);
+        assert_eq!(offsets(second), vec![0, 10, 20]);
+        assert!(loader.in_flight.lock().unwrap().is_empty());
     }
 }
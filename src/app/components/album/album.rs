@@ -1,12 +1,16 @@
 use crate::app::components::display_add_css_provider;
+use crate::app::components::dnd::attach_link_drag_source;
+use crate::app::components::share::{self, ShareKind};
+use crate::app::components::utils::attach_context_menu;
 use crate::app::dispatch::Worker;
-use crate::app::loader::ImageLoader;
+use crate::app::loader::{load_artwork, ArtworkRequest};
 use crate::app::models::AlbumModel;
 
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
 use libadwaita::subclass::prelude::BinImpl;
+use std::cell::RefCell;
 
 mod imp {
 
@@ -27,8 +31,15 @@ mod imp {
         #[template_child]
         pub cover_btn: TemplateChild<gtk::Button>,
 
+        #[template_child]
+        pub album_box: TemplateChild<gtk::Box>,
+
         #[template_child]
         pub cover_image: TemplateChild<gtk::Image>,
+
+        pub artwork: RefCell<Option<ArtworkRequest>>,
+
+        pub context_menu: RefCell<Option<gtk::PopoverMenu>>,
     }
 
     #[glib::object_subclass]
@@ -46,7 +57,13 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for AlbumWidget {}
+    impl ObjectImpl for AlbumWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let context_menu = attach_context_menu(&*self.obj());
+            self.context_menu.replace(Some(context_menu));
+        }
+    }
     impl WidgetImpl for AlbumWidget {}
     impl BinImpl for AlbumWidget {}
 }
@@ -86,15 +103,11 @@ impl AlbumWidget {
         widget.cover_image.set_overflow(gtk::Overflow::Hidden);
 
         if let Some(cover_art) = album_model.cover() {
-            let _self = self.downgrade();
-            worker.send_local_task(async move {
-                if let Some(_self) = _self.upgrade() {
-                    let loader = ImageLoader::new();
-                    let result = loader.load_remote(&cover_art, "jpg", 200, 200).await;
-                    _self.set_image(result.as_ref());
-                    _self.set_loaded();
-                }
+            let request = load_artwork(self, worker, cover_art, 200, |_self, pixbuf| {
+                _self.set_image(pixbuf.as_ref());
+                _self.set_loaded();
             });
+            widget.artwork.replace(Some(request));
         } else {
             self.set_loaded();
         }
@@ -119,6 +132,63 @@ impl AlbumWidget {
         }
     }
 
+    // Compact rows rather than cards, see album.css for the cover size
+    pub fn set_list_mode(&self, list_mode: bool) {
+        let widget = self.imp();
+        if list_mode {
+            self.add_css_class("album--row");
+            widget
+                .album_box
+                .set_orientation(gtk::Orientation::Horizontal);
+            widget.album_box.set_halign(gtk::Align::Fill);
+            widget.cover_btn.set_halign(gtk::Align::Fill);
+        } else {
+            self.remove_css_class("album--row");
+            widget.album_box.set_orientation(gtk::Orientation::Vertical);
+            widget.album_box.set_halign(gtk::Align::Center);
+            widget.cover_btn.set_halign(gtk::Align::Center);
+        }
+        widget.cover_btn.set_hexpand(list_mode);
+        widget.album_label.set_hexpand(list_mode);
+        widget
+            .album_label
+            .set_margin_top(if list_mode { 0 } else { 6 });
+
+        let labels = [
+            &*widget.album_label,
+            &*widget.artist_label,
+            &*widget.year_label,
+        ];
+        for label in labels {
+            label.set_wrap(!list_mode);
+            // Cards are as wide as their cover, the labels shouldn't make them any wider
+            label.set_max_width_chars(if list_mode { -1 } else { 1 });
+            label.set_xalign(if list_mode { 0.0 } else { 0.5 });
+            label.set_justify(if list_mode {
+                gtk::Justification::Left
+            } else {
+                gtk::Justification::Center
+            });
+        }
+    }
+
+    // Actions are looked up in the "album" group
+    pub fn set_menu(&self, actions: &gio::ActionGroup, menu: &gio::MenuModel) {
+        self.insert_action_group("album", Some(actions));
+        if let Some(context_menu) = self.imp().context_menu.borrow().as_ref() {
+            context_menu.set_menu_model(Some(menu));
+        }
+    }
+
+    // Albums and playlists are both shown with this widget, hence the kind.
+    // This is also what gets dragged out of the card.
+    pub fn set_share_menu(&self, kind: ShareKind, id: &str) {
+        let (actions, menu) = share::share_menu(kind, id, "album");
+        self.set_menu(&actions, &menu);
+        let link = share::web_link(kind, id);
+        attach_link_drag_source(self, move || Some(link.clone()));
+    }
+
     pub fn connect_album_pressed<F: Fn(&Self) + 'static>(&self, f: F) {
         self.imp()
             .cover_btn
@@ -1,8 +1,12 @@
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
+use std::cell::RefCell;
 
-use crate::app::loader::ImageLoader;
+use crate::app::components::dnd::attach_link_drag_source;
+use crate::app::components::share::{self, ShareKind};
+use crate::app::components::utils::attach_context_menu;
+use crate::app::loader::{load_artwork, ArtworkRequest};
 use crate::app::models::ArtistModel;
 use crate::app::Worker;
 
@@ -21,6 +25,10 @@ mod imp {
 
         #[template_child]
         pub avatar: TemplateChild<libadwaita::Avatar>,
+
+        pub artwork: RefCell<Option<ArtworkRequest>>,
+
+        pub context_menu: RefCell<Option<gtk::PopoverMenu>>,
     }
 
     #[glib::object_subclass]
@@ -38,7 +46,13 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for ArtistWidget {}
+    impl ObjectImpl for ArtistWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let context_menu = attach_context_menu(&*self.obj());
+            self.context_menu.replace(Some(context_menu));
+        }
+    }
     impl WidgetImpl for ArtistWidget {}
     impl BoxImpl for ArtistWidget {}
 }
@@ -72,19 +86,42 @@ impl ArtistWidget {
             }));
     }
 
+    pub fn set_share_menu(&self, id: &str) {
+        let (actions, menu) = share::share_menu(ShareKind::Artist, id, "artist");
+        self.insert_action_group("artist", Some(&actions));
+        if let Some(context_menu) = self.imp().context_menu.borrow().as_ref() {
+            context_menu.set_menu_model(Some(&menu));
+        }
+        let link = share::web_link(ShareKind::Artist, id);
+        attach_link_drag_source(self, move || Some(link.clone()));
+    }
+
+    // A small avatar next to the name, rather than a big one above it
+    pub fn set_list_mode(&self, list_mode: bool) {
+        let widget = self.imp();
+        let size = if list_mode { 48 } else { 150 };
+        widget.avatar.set_size(size);
+        widget.avatar_btn.set_size_request(size, size);
+        if list_mode {
+            self.set_orientation(gtk::Orientation::Horizontal);
+            self.set_spacing(12);
+            widget.artist.set_xalign(0.0);
+        } else {
+            self.set_orientation(gtk::Orientation::Vertical);
+            self.set_spacing(0);
+            widget.artist.set_xalign(0.5);
+        }
+    }
+
     fn bind(&self, model: &ArtistModel, worker: Worker) {
         let widget = self.imp();
 
         if let Some(url) = model.image() {
-            let avatar = widget.avatar.downgrade();
-            worker.send_local_task(async move {
-                if let Some(avatar) = avatar.upgrade() {
-                    let loader = ImageLoader::new();
-                    let pixbuf = loader.load_remote(&url, "jpg", 200, 200).await;
-                    let texture = pixbuf.as_ref().map(gdk::Texture::for_pixbuf);
-                    avatar.set_custom_image(texture.as_ref());
-                }
+            let request = load_artwork(&*widget.avatar, worker, url, 200, |avatar, pixbuf| {
+                let texture = pixbuf.as_ref().map(gdk::Texture::for_pixbuf);
+                avatar.set_custom_image(texture.as_ref());
             });
+            widget.artwork.replace(Some(request));
         }
 
         model
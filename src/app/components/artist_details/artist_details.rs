@@ -1,11 +1,17 @@
+use gettextrs::gettext;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
+use libadwaita::prelude::*;
 use std::rc::Rc;
 
+use crate::app::components::share::ShareKind;
+use crate::app::components::utils::connect_prefetch;
 use crate::app::components::{
     display_add_css_provider, AlbumWidget, Component, EventListener, Playlist,
 };
+use crate::app::format::format_release_date;
+use crate::app::state::ScreenName;
 use crate::app::{models::*, ListStore};
 use crate::app::{AppEvent, BrowserEvent, Worker};
 
@@ -21,9 +27,21 @@ mod imp {
         #[template_child]
         pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
 
+        #[template_child]
+        pub follow_button: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub notify_button: TemplateChild<gtk::ToggleButton>,
+
         #[template_child]
         pub top_tracks: TemplateChild<gtk::ListView>,
 
+        #[template_child]
+        pub concerts: TemplateChild<gtk::Box>,
+
+        #[template_child]
+        pub concerts_list: TemplateChild<gtk::ListBox>,
+
         #[template_child]
         pub artist_releases: TemplateChild<gtk::FlowBox>,
     }
@@ -66,17 +84,83 @@ impl ArtistDetailsWidget {
         self.add_css_class("artist__loaded");
     }
 
-    fn connect_bottom_edge<F>(&self, f: F)
+    fn set_followed(&self, is_followed: bool) {
+        // Only followed artists get their releases watched
+        self.imp().notify_button.set_visible(is_followed);
+        let button = &self.imp().follow_button;
+        if is_followed {
+            // translators: Label of the button on an artist page, once the artist is followed. Clicking it unfollows the artist.
+            button.set_label(&gettext("Following"));
+            button.remove_css_class("suggested-action");
+        } else {
+            // translators: Button on an artist page, to follow the artist.
+            button.set_label(&gettext("Follow"));
+            button.add_css_class("suggested-action");
+        }
+    }
+
+    fn set_concerts(&self, concerts: Vec<ConcertDescription>) {
+        let widget = self.imp();
+        let list = &widget.concerts_list;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        widget.concerts.set_visible(!concerts.is_empty());
+
+        for concert in concerts {
+            // The time doesn't matter that much, and it's local to the venue anyway
+            let date = concert.date.split('T').next().unwrap_or_default();
+            let row = libadwaita::ActionRow::builder()
+                .title(glib::markup_escape_text(&concert.venue))
+                .subtitle(glib::markup_escape_text(&format!(
+                    "{} · {}",
+                    format_release_date(date),
+                    concert.location
+                )))
+                .activatable(true)
+                .build();
+            row.add_suffix(&gtk::Image::from_icon_name("adw-external-link-symbolic"));
+            row.connect_activated(move |row| {
+                let window = row.root().and_then(|r| r.downcast::<gtk::Window>().ok());
+                gtk::UriLauncher::new(&concert.url).launch(
+                    window.as_ref(),
+                    None::<&gio::Cancellable>,
+                    |result| {
+                        if let Err(e) = result {
+                            warn!("Could not open concert page: {}", e);
+                        }
+                    },
+                );
+            });
+            list.append(&row);
+        }
+    }
+
+    fn connect_follow_clicked<F>(&self, f: F)
     where
         F: Fn() + 'static,
+    {
+        self.imp().follow_button.connect_clicked(move |_| f());
+    }
+
+    fn set_watching_releases(&self, is_watching: bool) {
+        self.imp().notify_button.set_active(is_watching);
+    }
+
+    fn connect_notify_toggled<F>(&self, f: F)
+    where
+        F: Fn(bool) + 'static,
     {
         self.imp()
-            .scrolled_window
-            .connect_edge_reached(move |_, pos| {
-                if let gtk::PositionType::Bottom = pos {
-                    f()
-                }
-            });
+            .notify_button
+            .connect_toggled(move |button| f(button.is_active()));
+    }
+
+    fn connect_bottom_edge<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        connect_prefetch(&self.imp().scrolled_window, f);
     }
 
     fn bind_artist_releases<F>(
@@ -93,6 +177,7 @@ impl ArtistDetailsWidget {
                 let item = item.downcast_ref::<AlbumModel>().unwrap();
                 let child = gtk::FlowBoxChild::new();
                 let album = AlbumWidget::for_model(item, worker.clone());
+                album.set_share_menu(ShareKind::Album, &item.uri());
                 let f = on_album_pressed.clone();
                 album.connect_album_pressed(clone!(@weak item => move |_| {
                     f(item.uri());
@@ -107,6 +192,8 @@ pub struct ArtistDetails {
     model: Rc<ArtistDetailsModel>,
     widget: ArtistDetailsWidget,
     children: Vec<Box<dyn EventListener>>,
+    // Details get updated again with every page of releases
+    concerts_requested: bool,
 }
 
 impl ArtistDetails {
@@ -119,6 +206,17 @@ impl ArtistDetails {
             model.load_more();
         }));
 
+        widget.connect_follow_clicked(clone!(@weak model => move || {
+            model.toggle_follow_artist();
+        }));
+
+        widget.connect_notify_toggled(clone!(@weak model => move |watching| {
+            // Also called when the button is made to match
+            if watching != model.is_watching_releases() {
+                model.set_watching_releases(watching);
+            }
+        }));
+
         if let Some(store) = model.get_list_store() {
             widget.bind_artist_releases(
                 worker.clone(),
@@ -139,6 +237,7 @@ impl ArtistDetails {
             model,
             widget,
             children: vec![playlist],
+            concerts_requested: false,
         }
     }
 }
@@ -159,7 +258,32 @@ impl EventListener for ArtistDetails {
             AppEvent::BrowserEvent(BrowserEvent::ArtistDetailsUpdated(id))
                 if id == &self.model.id =>
             {
+                self.widget.set_followed(self.model.is_followed());
+                self.widget
+                    .set_watching_releases(self.model.is_watching_releases());
                 self.widget.set_loaded();
+                if !self.concerts_requested {
+                    self.concerts_requested = true;
+                    self.model.load_concerts();
+                }
+            }
+            AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::Artist(id)))
+                if id == &self.model.id =>
+            {
+                self.model.load_artist_details(id.clone());
+            }
+            AppEvent::BrowserEvent(BrowserEvent::ArtistConcertsUpdated(id))
+                if id == &self.model.id =>
+            {
+                self.widget.set_concerts(self.model.concerts());
+            }
+            AppEvent::BrowserEvent(BrowserEvent::ArtistFollowed(id))
+            | AppEvent::BrowserEvent(BrowserEvent::ArtistUnfollowed(id))
+                if id == &self.model.id =>
+            {
+                self.widget.set_followed(self.model.is_followed());
+                self.widget
+                    .set_watching_releases(self.model.is_watching_releases());
             }
             _ => {}
         }
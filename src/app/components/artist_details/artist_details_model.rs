@@ -3,15 +3,16 @@ use gio::SimpleActionGroup;
 use std::ops::Deref;
 use std::rc::Rc;
 
-use crate::api::SpotifyApiError;
+use crate::api::{events_provider, SpotifyApiError};
 use crate::app::components::SimpleHeaderBarModel;
-use crate::app::components::{labels, PlaylistModel};
+use crate::app::components::{labels, new_releases, PlaylistModel};
 use crate::app::models::*;
 use crate::app::state::SelectionContext;
 use crate::app::state::{
     BrowserAction, BrowserEvent, PlaybackAction, SelectionAction, SelectionState,
 };
 use crate::app::{ActionDispatcher, AppAction, AppEvent, AppModel, ListStore};
+use crate::settings;
 
 pub struct ArtistDetailsModel {
     pub id: String,
@@ -33,6 +34,46 @@ impl ArtistDetailsModel {
             .map_state_opt(|s| s.browser.artist_state(&self.id)?.artist.as_ref())
     }
 
+    pub fn is_followed(&self) -> bool {
+        self.app_model
+            .get_state()
+            .browser
+            .artist_state(&self.id)
+            .map(|s| s.is_followed)
+            .unwrap_or(false)
+    }
+
+    pub fn toggle_follow_artist(&self) {
+        let id = self.id.clone();
+        let is_followed = self.is_followed();
+        let api = self.app_model.get_spotify();
+
+        self.dispatcher
+            .write_spotify_and_dispatch(move || async move {
+                if !is_followed {
+                    api.follow_artist(&id)
+                        .await
+                        .map(|_| BrowserAction::FollowArtist(id).into())
+                } else {
+                    api.unfollow_artist(&id)
+                        .await
+                        .map(|_| BrowserAction::UnfollowArtist(id).into())
+                }
+            });
+    }
+
+    pub fn is_watching_releases(&self) -> bool {
+        new_releases::is_watched(&self.id)
+    }
+
+    pub fn set_watching_releases(&self, watching: bool) {
+        let name = self
+            .get_artist_name()
+            .map(|name| name.clone())
+            .unwrap_or_default();
+        new_releases::set_watched(&self.id, &name, watching);
+    }
+
     pub fn get_list_store(&self) -> Option<impl Deref<Target = ListStore<AlbumModel>> + '_> {
         self.app_model
             .map_state_opt(|s| Some(&s.browser.artist_state(&self.id)?.albums))
@@ -54,6 +95,35 @@ impl ArtistDetailsModel {
             });
     }
 
+    pub fn concerts(&self) -> Vec<ConcertDescription> {
+        self.app_model
+            .get_state()
+            .browser
+            .artist_state(&self.id)
+            .map(|s| s.concerts.clone())
+            .unwrap_or_default()
+    }
+
+    // Not worth bothering the user about when it fails, the section just doesn't show
+    pub fn load_concerts(&self) {
+        let Some(provider) = events_provider(&settings::concerts_app_id()) else {
+            return;
+        };
+        let Some(name) = self.get_artist_name().map(|name| name.clone()) else {
+            return;
+        };
+        let id = self.id.clone();
+        self.dispatcher.dispatch_async(Box::pin(async move {
+            match provider.upcoming_events(&name).await {
+                Ok(concerts) => Some(BrowserAction::SetArtistConcerts(id, concerts).into()),
+                Err(e) => {
+                    warn!("Could not get upcoming concerts for {}: {}", name, e);
+                    None
+                }
+            }
+        }));
+    }
+
     pub fn open_album(&self, id: String) {
         self.dispatcher.dispatch(AppAction::ViewAlbum(id));
     }
@@ -65,7 +135,7 @@ impl ArtistDetailsModel {
 
         let id = next_page.data.clone();
         let batch_size = next_page.batch_size;
-        let offset = next_page.next_offset?;
+        let offset = next_page.next_offset()?;
 
         self.dispatcher
             .call_spotify_and_dispatch(move || async move {
@@ -116,7 +186,11 @@ impl PlaylistModel for ArtistDetailsModel {
         }
         group.add_action(&song.make_album_action(self.dispatcher.box_clone(), None));
         group.add_action(&song.make_link_action(None));
+        group.add_action(&song.make_uri_action(None));
         group.add_action(&song.make_queue_action(self.dispatcher.box_clone(), None));
+        for block in song.make_block_actions(self.dispatcher.box_clone()) {
+            group.add_action(&block);
+        }
 
         Some(group.upcast())
     }
@@ -135,7 +209,9 @@ impl PlaylistModel for ArtistDetailsModel {
         }
 
         menu.append(Some(&*labels::COPY_LINK), Some("song.copy_link"));
+        menu.append(Some(&*labels::COPY_URI), Some("song.copy_uri"));
         menu.append(Some(&*labels::ADD_TO_QUEUE), Some("song.queue"));
+        menu.append_section(None, &song.make_block_menu());
         Some(menu.upcast())
     }
 
@@ -152,6 +228,10 @@ impl PlaylistModel for ArtistDetailsModel {
             .dispatch(SelectionAction::Deselect(vec![id.to_string()]).into());
     }
 
+    fn update_selection(&self, action: SelectionAction) {
+        self.dispatcher.dispatch(action.into());
+    }
+
     fn enable_selection(&self) -> bool {
         self.dispatcher
             .dispatch(AppAction::EnableSelection(SelectionContext::Default));
@@ -178,10 +258,4 @@ impl SimpleHeaderBarModel for ArtistDetailsModel {
     fn selection_context(&self) -> Option<SelectionContext> {
         Some(SelectionContext::Default)
     }
-
-    fn select_all(&self) {
-        let songs: Vec<SongDescription> = self.song_list_model().collect();
-        self.dispatcher
-            .dispatch(SelectionAction::Select(songs).into());
-    }
 }
@@ -0,0 +1,157 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::CompositeTemplate;
+use std::rc::Rc;
+
+use super::AudiobooksModel;
+use crate::app::components::utils::connect_prefetch;
+use crate::app::components::{AlbumWidget, Component, EventListener};
+use crate::app::dispatch::Worker;
+use crate::app::models::AlbumModel;
+use crate::app::state::{LoginEvent, ScreenName};
+use crate::app::{AppEvent, BrowserEvent, ListStore};
+
+mod imp {
+
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/dev/alextren/Spot/components/audiobooks.ui")]
+    pub struct AudiobooksWidget {
+        #[template_child]
+        pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+
+        #[template_child]
+        pub flowbox: TemplateChild<gtk::FlowBox>,
+        #[template_child]
+        pub status_page: TemplateChild<libadwaita::StatusPage>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AudiobooksWidget {
+        const NAME: &'static str = "AudiobooksWidget";
+        type Type = super::AudiobooksWidget;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for AudiobooksWidget {}
+    impl WidgetImpl for AudiobooksWidget {}
+    impl BoxImpl for AudiobooksWidget {}
+}
+
+glib::wrapper! {
+    pub struct AudiobooksWidget(ObjectSubclass<imp::AudiobooksWidget>) @extends gtk::Widget, gtk::Box;
+}
+
+impl Default for AudiobooksWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudiobooksWidget {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn connect_bottom_edge<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        connect_prefetch(&self.imp().scrolled_window, f);
+    }
+
+    // Pressing a book plays it rather than opening it, there isn't much more to see than its chapters
+    fn bind_albums<F>(&self, worker: Worker, store: &ListStore<AlbumModel>, on_album_pressed: F)
+    where
+        F: Fn(String) + Clone + 'static,
+    {
+        self.imp()
+            .flowbox
+            .bind_model(Some(store.unsafe_store()), move |item| {
+                let album_model = item.downcast_ref::<AlbumModel>().unwrap();
+                let child = gtk::FlowBoxChild::new();
+                let album = AlbumWidget::for_model(album_model, worker.clone());
+
+                let f = on_album_pressed.clone();
+                album.connect_album_pressed(clone!(@weak album_model => move |_| {
+                    f(album_model.uri());
+                }));
+
+                child.set_child(Some(&album));
+                child.upcast::<gtk::Widget>()
+            });
+    }
+    pub fn get_status_page(&self) -> &libadwaita::StatusPage {
+        &self.imp().status_page
+    }
+}
+
+pub struct Audiobooks {
+    widget: AudiobooksWidget,
+    worker: Worker,
+    model: Rc<AudiobooksModel>,
+}
+
+impl Audiobooks {
+    pub fn new(worker: Worker, model: AudiobooksModel) -> Self {
+        let model = Rc::new(model);
+
+        let widget = AudiobooksWidget::new();
+
+        widget.connect_bottom_edge(clone!(@weak model => move || {
+            model.load_more_audiobooks();
+        }));
+
+        Self {
+            widget,
+            worker,
+            model,
+        }
+    }
+
+    fn bind_flowbox(&self) {
+        self.widget.bind_albums(
+            self.worker.clone(),
+            &self.model.get_list_store().unwrap(),
+            clone!(@weak self.model as model => move |id| {
+                model.play_audiobook(id);
+            }),
+        );
+    }
+}
+
+impl EventListener for Audiobooks {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Started => {
+                let _ = self.model.refresh_saved_audiobooks();
+                self.bind_flowbox();
+            }
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_))
+            | AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::Home)) => {
+                let _ = self.model.refresh_saved_audiobooks();
+            }
+            AppEvent::BrowserEvent(BrowserEvent::SavedAudiobooksUpdated) => {
+                self.widget
+                    .get_status_page()
+                    .set_visible(!self.model.has_audiobooks());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Component for Audiobooks {
+    fn get_root_widget(&self) -> &gtk::Widget {
+        self.widget.as_ref()
+    }
+}
@@ -0,0 +1,107 @@
+use std::cell::Ref;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::app::components::utils::or_unavailable;
+use crate::app::models::*;
+use crate::app::state::{HomeState, PlaybackAction};
+use crate::app::{ActionDispatcher, AppAction, AppModel, BrowserAction, ListStore};
+
+const CHAPTERS_BATCH_SIZE: usize = 50;
+// Some books have hundreds of chapters, that's more than enough to be going on with
+const MAX_CHAPTERS: usize = 500;
+
+pub struct AudiobooksModel {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl AudiobooksModel {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+
+    fn state(&self) -> Option<Ref<'_, HomeState>> {
+        self.app_model.map_state_opt(|s| s.browser.home_state())
+    }
+
+    pub fn get_list_store(&self) -> Option<impl Deref<Target = ListStore<AlbumModel>> + '_> {
+        Some(Ref::map(self.state()?, |s| &s.audiobooks))
+    }
+
+    pub fn has_audiobooks(&self) -> bool {
+        self.get_list_store()
+            .map(|list| list.len() > 0)
+            .unwrap_or(false)
+    }
+
+    // Audiobooks aren't sold everywhere, in which case there's simply nothing to show
+    pub fn refresh_saved_audiobooks(&self) -> Option<()> {
+        let api = self.app_model.get_spotify();
+        let batch_size = self.state()?.next_audiobooks_page.batch_size;
+
+        self.dispatcher
+            .call_spotify_and_dispatch(move || async move {
+                let audiobooks = api.get_saved_audiobooks(0, batch_size).await;
+                or_unavailable(audiobooks, vec![])
+                    .map(|audiobooks| BrowserAction::SetSavedAudiobooks(audiobooks).into())
+            });
+
+        Some(())
+    }
+
+    pub fn load_more_audiobooks(&self) -> Option<()> {
+        let api = self.app_model.get_spotify();
+
+        let next_page = &self.state()?.next_audiobooks_page;
+        let batch_size = next_page.batch_size;
+        let offset = next_page.next_offset()?;
+
+        self.dispatcher
+            .call_spotify_and_dispatch(move || async move {
+                api.get_saved_audiobooks(offset, batch_size)
+                    .await
+                    .map(|audiobooks| BrowserAction::AppendSavedAudiobooks(audiobooks).into())
+            });
+
+        Some(())
+    }
+
+    // Starts from the first chapter that wasn't listened to entirely, the resume tracker takes care of the position
+    pub fn play_audiobook(&self, id: String) {
+        let api = self.app_model.get_spotify();
+
+        self.dispatcher
+            .call_spotify_and_dispatch_many(move || async move {
+                let mut chapters = vec![];
+                loop {
+                    let mut batch = api
+                        .get_audiobook_chapters(&id, chapters.len(), CHAPTERS_BATCH_SIZE)
+                        .await?;
+                    let is_last = batch.len() < CHAPTERS_BATCH_SIZE;
+                    chapters.append(&mut batch);
+                    if is_last || chapters.len() >= MAX_CHAPTERS {
+                        break;
+                    }
+                }
+
+                let start = chapters
+                    .iter()
+                    .find(|c| !c.fully_played)
+                    .or_else(|| chapters.first())
+                    .map(|c| c.song.id.clone());
+                let Some(start) = start else {
+                    return Ok(vec![]);
+                };
+                let songs = chapters.iter().map(|c| c.song.clone()).collect();
+                Ok(vec![
+                    AppAction::SyncResumePoints(chapters),
+                    PlaybackAction::LoadSongs(songs).into(),
+                    PlaybackAction::Load(start).into(),
+                ])
+            });
+    }
+}
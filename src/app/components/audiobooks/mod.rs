@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+mod audiobooks;
+mod audiobooks_model;
+
+pub use audiobooks::*;
+pub use audiobooks_model::*;
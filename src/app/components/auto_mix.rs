@@ -0,0 +1,179 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::app::components::utils::Debouncer;
+use crate::app::components::EventListener;
+use crate::app::models::MixPoints;
+use crate::app::state::{PlaybackAction, PlaybackEvent};
+use crate::app::{ActionDispatcher, AppEvent, AppModel, Worker};
+use crate::settings;
+
+// Where we are in the current song, as far as the events tell
+#[derive(Clone, Copy)]
+struct Playhead {
+    position: u32,
+    // Unless paused
+    since: Option<Instant>,
+}
+
+impl Playhead {
+    fn current(&self) -> u32 {
+        let elapsed = self.since.map(|since| since.elapsed().as_millis() as u32);
+        self.position + elapsed.unwrap_or(0)
+    }
+}
+
+struct Mixer {
+    dispatcher: Box<dyn ActionDispatcher>,
+    // For the current song, once the analysis is in
+    mix: Cell<Option<MixPoints>>,
+    playhead: Cell<Playhead>,
+    exit: Debouncer,
+}
+
+impl Mixer {
+    fn clear(&self) {
+        self.mix.set(None);
+        self.exit.cancel();
+    }
+
+    fn schedule_exit(&self) {
+        let playhead = self.playhead.get();
+        let position = playhead.current();
+        match self.mix.get() {
+            // Seeking past the exit means the end of that song is wanted, so it plays out
+            Some(mix) if playhead.since.is_some() && position < mix.exit => {
+                let dispatcher = self.dispatcher.box_clone();
+                self.exit.debounce(mix.exit - position, move || {
+                    dispatcher.dispatch(PlaybackAction::Next.into())
+                });
+            }
+            _ => self.exit.cancel(),
+        }
+    }
+
+    fn set_playhead(&self, position: u32, playing: bool) {
+        self.playhead.set(Playhead {
+            position,
+            since: playing.then(Instant::now),
+        });
+        self.schedule_exit();
+    }
+
+    // The song gets picked up past its intro, the seek then schedules the exit
+    fn set_mix(&self, mix: Option<MixPoints>) {
+        self.mix.set(mix);
+        match mix {
+            Some(mix) if self.playhead.get().current() < mix.entry => self
+                .dispatcher
+                .dispatch(PlaybackAction::Seek(mix.entry).into()),
+            _ => self.schedule_exit(),
+        }
+    }
+}
+
+// DJ mode: rather than fading from one song to the next, cuts from the last bar of one before its outro
+// to the first bar of the next after its intro, using Spotify's audio analysis (see MixPoints)
+pub struct AutoMix {
+    app_model: Rc<AppModel>,
+    worker: Worker,
+    enabled: Rc<Cell<bool>>,
+    mixer: Rc<Mixer>,
+}
+
+impl AutoMix {
+    pub fn new(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+        worker: Worker,
+    ) -> Self {
+        let enabled = Rc::new(Cell::new(settings::dj_mode()));
+        let mixer = Rc::new(Mixer {
+            dispatcher,
+            mix: Cell::new(None),
+            playhead: Cell::new(Playhead {
+                position: 0,
+                since: None,
+            }),
+            exit: Debouncer::new(),
+        });
+        settings::connect_dj_mode_changed({
+            let enabled = Rc::clone(&enabled);
+            let mixer = Rc::downgrade(&mixer);
+            move |dj_mode| {
+                enabled.set(dj_mode);
+                if let Some(mixer) = mixer.upgrade().filter(|_| !dj_mode) {
+                    mixer.clear();
+                }
+            }
+        });
+        Self {
+            app_model,
+            worker,
+            enabled,
+            mixer,
+        }
+    }
+
+    // Previews are too short to mix anything
+    fn is_active(&self) -> bool {
+        self.enabled.get() && !self.app_model.get_state().playback.previews_only()
+    }
+
+    fn is_playing(&self) -> bool {
+        self.app_model.get_state().playback.is_playing()
+    }
+
+    fn fetch_mix(&self, id: &str) {
+        let api = self.app_model.get_spotify();
+        let app_model = Rc::downgrade(&self.app_model);
+        let mixer = Rc::downgrade(&self.mixer);
+        let id = id.to_string();
+        self.worker.send_local_task(async move {
+            let mix = api.get_mix_points(&id).await.ok().flatten();
+            let (Some(app_model), Some(mixer)) = (app_model.upgrade(), mixer.upgrade()) else {
+                return;
+            };
+            // Another song might have started in the meantime
+            if app_model.get_state().playback.current_song_id().as_ref() == Some(&id) {
+                mixer.set_mix(mix);
+            }
+        });
+    }
+
+    // So that it's already in the cache when that song comes up
+    fn prefetch_mix(&self, id: &str) {
+        let api = self.app_model.get_spotify();
+        let id = id.to_string();
+        self.worker.send_local_task(async move {
+            let _ = api.get_mix_points(&id).await;
+        });
+    }
+}
+
+impl EventListener for AutoMix {
+    fn on_event(&mut self, event: &AppEvent) {
+        let AppEvent::PlaybackEvent(event) = event else {
+            return;
+        };
+        let position = self.mixer.playhead.get().current();
+        match event {
+            PlaybackEvent::TrackChanged(id) => {
+                self.mixer.clear();
+                self.mixer.set_playhead(0, self.is_playing());
+                if self.is_active() {
+                    self.fetch_mix(id);
+                }
+            }
+            PlaybackEvent::Preload(id) if self.is_active() => self.prefetch_mix(id),
+            PlaybackEvent::PlaybackPaused => self.mixer.set_playhead(position, false),
+            PlaybackEvent::PlaybackResumed => self.mixer.set_playhead(position, true),
+            PlaybackEvent::TrackSeeked(position) | PlaybackEvent::SeekSynced(position) => {
+                self.mixer.set_playhead(*position, self.is_playing())
+            }
+            PlaybackEvent::PlaybackStopped => self.mixer.clear(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::app::components::EventListener;
+use crate::app::kv_store::KeyValueStore;
+use crate::app::models::BlockedItem;
+use crate::app::state::{PlaybackAction, PlaybackEvent};
+use crate::app::{ActionDispatcher, AppEvent, AppModel};
+
+thread_local!(static BLOCKED: RefCell<KeyValueStore<BlockedItem>> = RefCell::new(KeyValueStore::open("blocked")));
+
+// Loads the songs and artists not to play when Spot starts, and saves them whenever they change
+pub struct BlocklistKeeper {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl BlocklistKeeper {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+
+    fn load(&self) {
+        let items = BLOCKED.with(|store| store.borrow().values().cloned().collect());
+        self.dispatcher
+            .dispatch(PlaybackAction::SetBlocklist(items).into());
+    }
+
+    fn save(&self) {
+        let items = self.app_model.get_state().playback.blocklist().items();
+        BLOCKED.with(|store| {
+            let mut store = store.borrow_mut();
+            let unblocked: Vec<String> = store
+                .values()
+                .filter(|old| !items.contains(old))
+                .map(|old| old.uri.clone())
+                .collect();
+            for uri in unblocked {
+                store.remove(&uri);
+            }
+            for item in items {
+                store.set(item.uri.clone(), item);
+            }
+            if let Err(e) = store.save() {
+                warn!("Could not save the blocked songs and artists: {}", e);
+            }
+        });
+    }
+}
+
+impl EventListener for BlocklistKeeper {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Started => self.load(),
+            AppEvent::PlaybackEvent(PlaybackEvent::BlocklistChanged) => self.save(),
+            _ => {}
+        }
+    }
+}
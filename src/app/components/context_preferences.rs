@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::app::components::EventListener;
+use crate::app::kv_store::KeyValueStore;
+use crate::app::models::RepeatMode;
+use crate::app::state::{PlaybackAction, PlaybackEvent};
+use crate::app::{ActionDispatcher, AppEvent, AppModel, SongsSource};
+
+// Only what the user set while playing from that source, the rest is left as it is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct ContextPreferences {
+    shuffled: Option<bool>,
+    repeat: Option<RepeatMode>,
+}
+
+thread_local!(static PREFERENCES: RefCell<KeyValueStore<ContextPreferences>> = RefCell::new(KeyValueStore::open("playback-preferences")));
+
+fn key(source: &SongsSource) -> String {
+    match source {
+        SongsSource::Playlist(id) => format!("playlist:{id}"),
+        SongsSource::Album(id) => format!("album:{id}"),
+        SongsSource::SavedTracks => "saved-tracks".to_string(),
+    }
+}
+
+fn preferences_for(source: &SongsSource) -> Option<ContextPreferences> {
+    PREFERENCES.with(|preferences| preferences.borrow().get(&key(source)).copied())
+}
+
+fn update<F>(source: &SongsSource, f: F)
+where
+    F: FnOnce(&mut ContextPreferences),
+{
+    PREFERENCES.with(|preferences| {
+        let mut preferences = preferences.borrow_mut();
+        let key = key(source);
+        let mut value = preferences.get(&key).copied().unwrap_or_default();
+        f(&mut value);
+        preferences.set(key, value);
+        if let Err(e) = preferences.save() {
+            warn!("Could not save playback preferences: {}", e);
+        }
+    });
+}
+
+// For changes coming from the user, not for the ones we make when playback starts
+pub fn remember_shuffled(source: &SongsSource, shuffled: bool) {
+    update(source, |p| p.shuffled = Some(shuffled));
+}
+
+pub fn remember_repeat(source: &SongsSource, repeat: RepeatMode) {
+    update(source, |p| p.repeat = Some(repeat));
+}
+
+// Brings back how a playlist (or album...) was last played, whenever playback starts from it again
+pub struct ContextPreferencesApplier {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl ContextPreferencesApplier {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+
+    fn on_source_changed(&self) {
+        let preferences = self
+            .app_model
+            .get_state()
+            .playback
+            .current_source()
+            .and_then(preferences_for);
+        let Some(preferences) = preferences else {
+            return;
+        };
+        if let Some(shuffled) = preferences.shuffled {
+            self.dispatcher
+                .dispatch(PlaybackAction::SetShuffled(shuffled).into());
+        }
+        if let Some(repeat) = preferences.repeat {
+            self.dispatcher
+                .dispatch(PlaybackAction::SetRepeatMode(repeat).into());
+        }
+    }
+}
+
+impl EventListener for ContextPreferencesApplier {
+    fn on_event(&mut self, event: &AppEvent) {
+        if let AppEvent::PlaybackEvent(PlaybackEvent::SourceChanged) = event {
+            self.on_source_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert_eq!(key(&SongsSource::Album("id".to_string())), "album:id");
+        assert_ne!(
+            key(&SongsSource::Album("id".to_string())),
+            key(&SongsSource::Playlist("id".to_string()))
+        );
+    }
+}
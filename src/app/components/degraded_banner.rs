@@ -0,0 +1,64 @@
+use gettextrs::gettext;
+use gtk::prelude::*;
+use std::rc::Rc;
+
+use crate::app::components::EventListener;
+use crate::app::health::{Backend, Health};
+use crate::app::state::PlaybackAction;
+use crate::app::{ActionDispatcher, AppAction, AppEvent, AppModel};
+
+fn message(health: Health) -> String {
+    match (health.is_up(Backend::WebApi), health.is_up(Backend::Player)) {
+        // translators: This is shown in a banner at the top of the window when Spotify's servers don't answer. What was loaded before is kept in a cache.
+        (false, true) => gettext("Spotify can't be reached right now. Pages seen before and the queue still work."),
+        // translators: This is shown in a banner at the top of the window when the connection used to play music was lost, while browsing still works.
+        (true, false) => gettext("Playback is unavailable right now, you can still browse your library."),
+        // translators: This is shown in a banner at the top of the window when neither browsing nor playing works.
+        _ => gettext("Spotify can't be reached right now, nor can anything be played. Pages seen before still work."),
+    }
+}
+
+// Says so when Spotify (or part of it) is down, rather than leaving every failed page to its own error.
+// Meanwhile, pages come from the cache, and whatever failed is loaded again once it's back.
+pub struct DegradedBanner {
+    revealer: gtk::Revealer,
+    label: gtk::Label,
+}
+
+impl DegradedBanner {
+    pub fn new(
+        revealer: gtk::Revealer,
+        label: gtk::Label,
+        retry: gtk::Button,
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Self {
+        retry.connect_clicked(move |_| {
+            // That reconnects the player, and tries again what failed
+            dispatcher.dispatch(AppAction::NetworkRestored);
+            // Any call would do to see if the Web API answers, this one is never cached
+            let api = app_model.get_spotify();
+            dispatcher.call_spotify_and_dispatch(move || async move {
+                api.list_available_devices()
+                    .await
+                    .map(|devices| PlaybackAction::SetAvailableDevices(devices).into())
+            });
+        });
+        Self { revealer, label }
+    }
+
+    fn update(&self, health: Health) {
+        if health.is_degraded() {
+            self.label.set_label(&message(health));
+        }
+        self.revealer.set_reveal_child(health.is_degraded());
+    }
+}
+
+impl EventListener for DegradedBanner {
+    fn on_event(&mut self, event: &AppEvent) {
+        if let AppEvent::HealthChanged(health) = event {
+            self.update(*health);
+        }
+    }
+}
@@ -43,6 +43,15 @@ mod imp {
 
         #[template_child]
         pub year_label: TemplateChild<gtk::Label>,
+
+        #[template_child]
+        pub summary_box: TemplateChild<gtk::Box>,
+
+        #[template_child]
+        pub summary_label: TemplateChild<gtk::Label>,
+
+        #[template_child]
+        pub totals_button: TemplateChild<gtk::Button>,
     }
 
     #[glib::object_subclass]
@@ -152,12 +161,26 @@ impl AlbumHeaderWidget {
         }
     }
 
+    pub fn set_summary(&self, summary: &str, complete: bool) {
+        let widget = self.imp();
+        widget.summary_label.set_label(summary);
+        widget.totals_button.set_visible(!complete);
+    }
+
+    pub fn connect_totals<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.imp().totals_button.connect_clicked(move |_| f());
+    }
+
     pub fn set_centered(&self) {
         let widget = self.imp();
         widget.album_label.set_halign(gtk::Align::Center);
         widget.album_label.set_justify(gtk::Justification::Center);
         widget.artist_button.set_halign(gtk::Align::Center);
         widget.year_label.set_halign(gtk::Align::Center);
+        widget.summary_box.set_halign(gtk::Align::Center);
         widget.button_box.set_halign(gtk::Align::Center);
         widget.album_overlay.set_margin_start(0);
         widget.button_box.set_margin_end(0);
@@ -8,12 +8,15 @@ use super::release_details::ReleaseDetailsWindow;
 use super::DetailsModel;
 
 use crate::app::components::{
-    Component, EventListener, HeaderBarComponent, HeaderBarWidget, Playlist, ScrollingHeaderWidget,
+    Component, EventListener, HeaderBarComponent, HeaderBarWidget, Playlist, PlaylistModel,
+    ScrollingHeaderWidget,
 };
 use crate::app::dispatch::Worker;
+use crate::app::format::{format_release_date, songs_summary, songs_totals};
 use crate::app::loader::ImageLoader;
-use crate::app::state::PlaybackEvent;
+use crate::app::state::{PlaybackEvent, ScreenName};
 use crate::app::{AppEvent, BrowserEvent};
+use crate::settings;
 
 mod imp {
 
@@ -160,6 +163,19 @@ impl AlbumDetailsWidget {
         self.imp().headerbar.set_title_and_subtitle(album, artist);
     }
 
+    fn set_summary(&self, summary: &str, complete: bool) {
+        self.imp().header_widget.set_summary(summary, complete);
+        self.imp().header_mobile.set_summary(summary, complete);
+    }
+
+    fn connect_totals<F>(&self, f: F)
+    where
+        F: Fn() + Clone + 'static,
+    {
+        self.imp().header_widget.connect_totals(f.clone());
+        self.imp().header_mobile.connect_totals(f);
+    }
+
     fn set_artwork(&self, art: &gdk_pixbuf::Pixbuf) {
         self.imp().header_widget.set_artwork(art);
         self.imp().header_mobile.set_artwork(art);
@@ -209,6 +225,8 @@ impl Details {
 
         widget.connect_play(clone!(@weak model => move || model.toggle_play_album()));
 
+        widget.connect_totals(clone!(@weak model => move || model.load_all_tracks()));
+
         widget.connect_header();
 
         widget.connect_bottom_edge(clone!(@weak model => move || {
@@ -252,6 +270,13 @@ impl Details {
         self.widget.set_playing(is_playing);
     }
 
+    fn update_summary(&self) {
+        let songs = self.model.song_list_model();
+        let total_duration = songs.total_duration();
+        let summary = songs_totals(songs.len(), total_duration, settings::bitrate_kbps());
+        self.widget.set_summary(&summary, total_duration.is_some());
+    }
+
     fn update_details(&mut self) {
         if let Some(album) = self.model.get_album_info() {
             let details = &album.release_details;
@@ -264,6 +289,7 @@ impl Details {
                 &album.artists_name(),
                 album.year(),
             );
+            self.update_summary();
 
             self.widget.connect_artist_clicked(
                 clone!(@weak self.model as model => move || model.view_artist()),
@@ -273,8 +299,12 @@ impl Details {
                 &album.title,
                 &album.artists_name(),
                 &details.label,
-                album.release_date.as_ref().unwrap(),
-                details.total_tracks,
+                &album
+                    .release_date
+                    .as_deref()
+                    .map(format_release_date)
+                    .unwrap_or_default(),
+                &songs_summary(details.total_tracks, album.songs.total_duration()),
                 &details.copyright_text,
             );
 
@@ -316,6 +346,16 @@ impl EventListener for Details {
                 self.update_details();
                 self.update_playing(true);
             }
+            AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::AlbumDetails(id)))
+                if id == &self.model.id =>
+            {
+                self.model.load_album_info();
+            }
+            AppEvent::BrowserEvent(BrowserEvent::AlbumTracksAppended(id))
+                if id == &self.model.id =>
+            {
+                self.update_summary();
+            }
             AppEvent::BrowserEvent(BrowserEvent::AlbumSaved(id))
             | AppEvent::BrowserEvent(BrowserEvent::AlbumUnsaved(id))
                 if id == &self.model.id =>
@@ -85,7 +85,7 @@ impl DetailsModel {
             let api = self.app_model.get_spotify();
 
             self.dispatcher
-                .call_spotify_and_dispatch(move || async move {
+                .write_spotify_and_dispatch(move || async move {
                     if !is_liked {
                         api.save_album(&id)
                             .await
@@ -152,6 +152,29 @@ impl DetailsModel {
         Some(())
     }
 
+    // Whatever is missing all at once, so that the header can tell the album's length
+    pub fn load_all_tracks(&self) {
+        let songs = self.song_list_model();
+        let queries: Vec<BatchQuery> = songs
+            .needed_batches_in(0, songs.len())
+            .into_iter()
+            .map(|batch| BatchQuery {
+                source: SongsSource::Album(self.id.clone()),
+                batch,
+            })
+            .collect();
+        if queries.is_empty() {
+            return;
+        }
+
+        let id = self.id.clone();
+        let loader = self.app_model.get_batch_loader();
+        self.dispatcher
+            .dispatch_stream(loader.load_all(queries, move |_source, song_batch| {
+                vec![BrowserAction::AppendAlbumTracks(id, Box::new(song_batch)).into()]
+            }));
+    }
+
     pub fn to_headerbar_model(self: &Rc<Self>) -> Rc<impl HeaderBarModel> {
         Rc::new(SimpleHeaderBarModelWrapper::new(
             self.clone(),
@@ -193,6 +216,14 @@ impl PlaylistModel for DetailsModel {
             .dispatch(SelectionAction::Deselect(vec![id.to_string()]).into());
     }
 
+    fn update_selection(&self, action: SelectionAction) {
+        self.dispatcher.dispatch(action.into());
+    }
+
+    fn load_remaining(&self) {
+        self.load_more();
+    }
+
     fn enable_selection(&self) -> bool {
         self.dispatcher
             .dispatch(AppAction::EnableSelection(SelectionContext::Default));
@@ -207,15 +238,37 @@ impl PlaylistModel for DetailsModel {
         self.state().playback.current_song_id()
     }
 
+    // The whole album gets queued, not just the batch that song is in
     fn play_song_at(&self, pos: usize, id: &str) {
         let source = SongsSource::Album(self.id.clone());
-        let batch = self.song_list_model().song_batch_for(pos);
-        if let Some(batch) = batch {
-            self.dispatcher
-                .dispatch(PlaybackAction::LoadPagedSongs(source, batch).into());
+        let songs = self.song_list_model();
+        let Some(batch) = songs.song_batch_for(pos) else {
+            return;
+        };
+        self.dispatcher
+            .dispatch(PlaybackAction::LoadPagedSongs(source.clone(), batch).into());
+        for batch in songs.song_batches() {
             self.dispatcher
-                .dispatch(PlaybackAction::Load(id.to_string()).into());
+                .dispatch(PlaybackAction::AppendPagedSongs(source.clone(), batch).into());
         }
+        self.dispatcher
+            .dispatch(PlaybackAction::Load(id.to_string()).into());
+
+        // And what we don't have yet comes in the background. Batches still loading for the header
+        // (see load_all_tracks) aren't fetched again, they get queued once they're there
+        let queries = songs
+            .needed_batches_in(0, songs.len())
+            .into_iter()
+            .map(|batch| BatchQuery {
+                source: source.clone(),
+                batch,
+            })
+            .collect();
+        let loader = self.app_model.get_batch_loader();
+        self.dispatcher
+            .dispatch_stream(loader.load_all(queries, |source, song_batch| {
+                vec![PlaybackAction::AppendPagedSongs(source, song_batch).into()]
+            }));
     }
 
     fn actions_for(&self, id: &str) -> Option<gio::ActionGroup> {
@@ -228,7 +281,11 @@ impl PlaylistModel for DetailsModel {
             group.add_action(&view_artist);
         }
         group.add_action(&song.make_link_action(None));
+        group.add_action(&song.make_uri_action(None));
         group.add_action(&song.make_queue_action(self.dispatcher.box_clone(), None));
+        for block in song.make_block_actions(self.dispatcher.box_clone()) {
+            group.add_action(&block);
+        }
 
         Some(group.upcast())
     }
@@ -246,7 +303,9 @@ impl PlaylistModel for DetailsModel {
         }
 
         menu.append(Some(&*labels::COPY_LINK), Some("song.copy_link"));
+        menu.append(Some(&*labels::COPY_URI), Some("song.copy_uri"));
         menu.append(Some(&*labels::ADD_TO_QUEUE), Some("song.queue"));
+        menu.append_section(None, &song.make_block_menu());
         Some(menu.upcast())
     }
 }
@@ -263,10 +322,4 @@ impl SimpleHeaderBarModel for DetailsModel {
     fn selection_context(&self) -> Option<SelectionContext> {
         Some(SelectionContext::Default)
     }
-
-    fn select_all(&self) {
-        let songs: Vec<SongDescription> = self.song_list_model().collect();
-        self.dispatcher
-            .dispatch(SelectionAction::Select(songs).into());
-    }
 }
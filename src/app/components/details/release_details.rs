@@ -75,7 +75,7 @@ impl ReleaseDetailsWindow {
         artist: &str,
         label: &str,
         release_date: &str,
-        track_count: usize,
+        tracks: &str,
         copyright: &str,
     ) {
         let widget = self.imp();
@@ -86,7 +86,7 @@ impl ReleaseDetailsWindow {
 
         widget.label.set_text(label);
         widget.release.set_text(release_date);
-        widget.tracks.set_text(&track_count.to_string());
+        widget.tracks.set_text(tracks);
         widget.copyright.set_text(copyright);
     }
 }
@@ -1,12 +1,13 @@
 use std::ops::Deref;
 use std::rc::Rc;
 
+use gettextrs::gettext;
 use glib::Cast;
 
 use crate::app::components::{Component, EventListener};
-use crate::app::models::ConnectDevice;
+use crate::app::models::{ConnectDevice, GroupSession};
 use crate::app::state::{Device, LoginEvent, PlaybackAction, PlaybackEvent};
-use crate::app::{ActionDispatcher, AppEvent, AppModel};
+use crate::app::{ActionDispatcher, AppAction, AppEvent, AppModel};
 
 use super::widget::DeviceSelectorWidget;
 
@@ -42,15 +43,114 @@ impl DeviceSelectorModel {
         self.app_model.map_state(|s| s.playback.current_device())
     }
 
-    pub fn set_current_device(&self, id: Option<String>) {
+    fn find_device(&self, id: Option<String>) -> Device {
         let devices = self.get_available_devices();
         let connect_device = id
             .and_then(|id| devices.iter().find(|&d| d.id == id))
             .cloned();
-        let device = connect_device.map(Device::Connect).unwrap_or(Device::Local);
+        connect_device.map(Device::Connect).unwrap_or(Device::Local)
+    }
+
+    pub fn set_current_device(&self, id: Option<String>) {
+        let device = self.find_device(id);
         self.dispatcher
             .dispatch(PlaybackAction::SwitchDevice(device).into());
     }
+
+    pub fn start_group_session(&self) {
+        let device_id = match &*self.get_current_device() {
+            Device::Connect(device) => device.id.clone(),
+            Device::Local => {
+                self.dispatcher
+                    .dispatch(AppAction::ShowNotification(gettext(
+                        "Pick a device to start a group session",
+                    )));
+                return;
+            }
+        };
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .write_spotify_and_dispatch(move || async move {
+                api.start_group_session(device_id)
+                    .await
+                    .map(|session| PlaybackAction::SetGroupSession(Some(session)).into())
+            });
+    }
+
+    // Whoever started the session is playing, so that's the device we'll be controlling
+    pub fn join_group_session(&self, link: String) {
+        let Some(token) = GroupSession::token_from_link(&link) else {
+            self.dispatcher
+                .dispatch(AppAction::ShowNotification(gettext(
+                    "Copy a group session link first",
+                )));
+            return;
+        };
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .write_spotify_and_dispatch_many(move || async move {
+                let session = api.join_group_session(token).await?;
+                let devices = api.list_available_devices().await?;
+                let host_device = devices.iter().find(|d| d.is_active).cloned();
+                let mut actions = vec![
+                    PlaybackAction::SetGroupSession(Some(session)).into(),
+                    PlaybackAction::SetAvailableDevices(devices).into(),
+                ];
+                if let Some(device) = host_device {
+                    actions.push(PlaybackAction::SwitchDevice(Device::Connect(device)).into());
+                }
+                Ok(actions)
+            });
+    }
+
+    pub fn leave_group_session(&self) {
+        let session_id = self
+            .app_model
+            .get_state()
+            .playback
+            .group_session()
+            .map(|s| s.id.clone());
+        let Some(session_id) = session_id else {
+            return;
+        };
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .write_spotify_and_dispatch(move || async move {
+                api.leave_group_session(session_id)
+                    .await
+                    .map(|_| PlaybackAction::SetGroupSession(None).into())
+            });
+    }
+
+    pub fn group_session_link(&self) -> Option<String> {
+        let state = self.app_model.get_state();
+        state.playback.group_session().map(|s| s.invite_link())
+    }
+
+    pub fn get_group_session(&self) -> Option<impl Deref<Target = GroupSession> + '_> {
+        self.app_model.map_state_opt(|s| s.playback.group_session())
+    }
+
+    // Don't wait for a command to fail to find out
+    fn device_disappeared(&self, device: &ConnectDevice) {
+        let is_current = matches!(
+            &*self.get_current_device(),
+            Device::Connect(current) if current.id == device.id
+        );
+        if is_current {
+            self.dispatcher
+                .dispatch(AppAction::ShowNotification(gettext(
+                    "Connection to device lost!",
+                )));
+            self.set_current_device(None);
+        }
+    }
+
+    pub fn transfer_playback(&self, id: Option<String>) {
+        let device = self.find_device(id);
+        self.dispatcher
+            .dispatch(PlaybackAction::TransferPlayback(device).into());
+    }
 }
 
 pub struct DeviceSelector {
@@ -70,6 +170,27 @@ impl DeviceSelector {
             model.set_current_device(id);
         }));
 
+        widget.connect_transfer_playback(clone!(@weak model => move |id| {
+            model.transfer_playback(id);
+        }));
+
+        widget.connect_start_session(clone!(@weak model => move || {
+            model.start_group_session();
+        }));
+
+        widget.connect_join_session(clone!(@weak model => move |link| {
+            model.join_group_session(link);
+        }));
+
+        widget.connect_copy_session_link(clone!(@weak model => @default-return None, move || {
+            model.group_session_link()
+        }));
+
+        widget.connect_leave_session(clone!(@weak model => move || {
+            model.leave_group_session();
+        }));
+        widget.set_group_session(None);
+
         Self { widget, model }
     }
 }
@@ -86,11 +207,23 @@ impl EventListener for DeviceSelector {
             AppEvent::LoginEvent(LoginEvent::LoginCompleted(_)) => {
                 self.model.refresh_available_devices();
             }
+            AppEvent::PlaybackEvent(PlaybackEvent::DeviceAppeared(device)) => {
+                info!("device available: {}", device.label);
+            }
+            AppEvent::PlaybackEvent(PlaybackEvent::DeviceDisappeared(device)) => {
+                info!("device gone: {}", device.label);
+                self.model.device_disappeared(device);
+            }
+            AppEvent::PlaybackEvent(PlaybackEvent::GroupSessionChanged) => {
+                self.widget
+                    .set_group_session(self.model.get_group_session().as_deref());
+            }
             AppEvent::PlaybackEvent(PlaybackEvent::AvailableDevicesChanged) => {
                 self.widget
                     .update_devices_list(&self.model.get_available_devices());
             }
-            AppEvent::PlaybackEvent(PlaybackEvent::SwitchedDevice(_)) => {
+            AppEvent::PlaybackEvent(PlaybackEvent::SwitchedDevice(_))
+            | AppEvent::PlaybackEvent(PlaybackEvent::PlaybackTransferred(_)) => {
                 self.widget
                     .set_current_device(&self.model.get_current_device());
             }
@@ -1,4 +1,5 @@
-use crate::app::models::{ConnectDevice, ConnectDeviceKind};
+use crate::app::components::labels;
+use crate::app::models::{ConnectDevice, ConnectDeviceKind, GroupSession};
 use crate::app::state::Device;
 use gettextrs::gettext;
 use gio::{Action, SimpleAction, SimpleActionGroup};
@@ -10,6 +11,11 @@ use gtk::CompositeTemplate;
 const ACTIONS: &str = "devices";
 const CONNECT_ACTION: &str = "connect";
 const REFRESH_ACTION: &str = "refresh";
+const TRANSFER_ACTION: &str = "transfer";
+const START_SESSION_ACTION: &str = "start-session";
+const JOIN_SESSION_ACTION: &str = "join-session";
+const COPY_SESSION_LINK_ACTION: &str = "copy-session-link";
+const LEAVE_SESSION_ACTION: &str = "leave-session";
 
 mod imp {
 
@@ -33,6 +39,12 @@ mod imp {
         #[template_child]
         pub this_device_button: TemplateChild<gtk::CheckButton>,
 
+        #[template_child]
+        pub play_here_button: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub session_members: TemplateChild<gtk::Label>,
+
         #[template_child]
         pub menu: TemplateChild<gio::MenuModel>,
 
@@ -68,6 +80,10 @@ mod imp {
             this_device.set_action_name(Some(&format!("{}.{}", ACTIONS, CONNECT_ACTION)));
             this_device.set_action_target_value(Some(&Option::<String>::None.to_variant()));
 
+            let play_here = &*self.play_here_button;
+            play_here.set_action_name(Some(&format!("{}.{}", ACTIONS, TRANSFER_ACTION)));
+            play_here.set_action_target_value(Some(&Option::<String>::None.to_variant()));
+
             self.obj()
                 .insert_action_group(ACTIONS, Some(&self.action_group));
             self.obj()
@@ -123,6 +139,101 @@ impl DeviceSelectorWidget {
         });
     }
 
+    pub fn connect_transfer_playback<F>(&self, f: F)
+    where
+        F: Fn(Option<String>) + 'static,
+    {
+        self.imp().action_group.add_action(&{
+            let transfer = SimpleAction::new(
+                TRANSFER_ACTION,
+                Some(Option::<String>::static_variant_type().as_ref()),
+            );
+            transfer.connect_activate(move |_, device_id| {
+                if let Some(device_id) = device_id {
+                    f(Option::<String>::from_variant(device_id).unwrap());
+                }
+            });
+            transfer
+        });
+    }
+
+    fn add_simple_action<F>(&self, name: &str, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.imp().action_group.add_action(&{
+            let action = SimpleAction::new(name, None);
+            action.connect_activate(move |_, _| f());
+            action
+        });
+    }
+
+    pub fn connect_start_session<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.add_simple_action(START_SESSION_ACTION, f);
+    }
+
+    // Hands over whatever text is in the clipboard, hopefully an invite link
+    pub fn connect_join_session<F>(&self, f: F)
+    where
+        F: Fn(String) + 'static,
+    {
+        let f = std::rc::Rc::new(f);
+        self.add_simple_action(
+            JOIN_SESSION_ACTION,
+            clone!(@weak self as _self => move || {
+                let f = f.clone();
+                _self.clipboard().read_text_async(None::<&gio::Cancellable>, move |text| {
+                    f(text.ok().flatten().map(|t| t.to_string()).unwrap_or_default());
+                });
+            }),
+        );
+    }
+
+    pub fn connect_copy_session_link<F>(&self, f: F)
+    where
+        F: Fn() -> Option<String> + 'static,
+    {
+        self.add_simple_action(
+            COPY_SESSION_LINK_ACTION,
+            clone!(@weak self as _self => move || {
+                if let Some(link) = f() {
+                    _self.clipboard().set_text(&link);
+                }
+            }),
+        );
+    }
+
+    pub fn connect_leave_session<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.add_simple_action(LEAVE_SESSION_ACTION, f);
+    }
+
+    pub fn set_group_session(&self, session: Option<&GroupSession>) {
+        let in_session = session.is_some();
+        let enable = |name: &str, enabled: bool| {
+            if let Some(action) = self.action(name) {
+                if let Ok(action) = action.downcast::<SimpleAction>() {
+                    action.set_enabled(enabled);
+                }
+            }
+        };
+        enable(START_SESSION_ACTION, !in_session);
+        enable(JOIN_SESSION_ACTION, !in_session);
+        enable(COPY_SESSION_LINK_ACTION, in_session);
+        enable(LEAVE_SESSION_ACTION, in_session);
+
+        let members = &self.imp().session_members;
+        if let Some(session) = session {
+            members.set_text(&labels::listening_with_label(&session.members));
+        }
+        members.set_visible(in_session);
+    }
+
     pub fn set_current_device(&self, device: &Device) {
         if let Some(action) = self.action(CONNECT_ACTION) {
             let device_id = match device {
@@ -146,6 +257,9 @@ impl DeviceSelectorWidget {
         };
         self.imp().button_content.set_label(&label);
         self.imp().button_content.set_icon_name(icon);
+        self.imp()
+            .play_here_button
+            .set_visible(matches!(device, Device::Connect(_)));
     }
 
     pub fn update_devices_list(&self, devices: &[ConnectDevice]) {
@@ -162,8 +276,19 @@ impl DeviceSelectorWidget {
                 .action_target(&Some(&device.id).to_variant())
                 .group(&*widget.this_device_button)
                 .label(&device.label)
+                .hexpand(true)
+                .build();
+            let transfer = gtk::Button::builder()
+                .icon_name("send-to-symbolic")
+                .tooltip_text(labels::move_to_device_label(&device.label))
+                .action_name(format!("{}.{}", ACTIONS, TRANSFER_ACTION))
+                .action_target(&Some(&device.id).to_variant())
+                .css_classes(vec!["flat".to_string()])
                 .build();
-            widget.devices.append(&check);
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+            row.append(&check);
+            row.append(&transfer);
+            widget.devices.append(&row);
         }
     }
 }
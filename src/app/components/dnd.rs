@@ -0,0 +1,157 @@
+use gtk::prelude::*;
+use std::sync::Arc;
+
+use crate::api::{SpotifyApiClient, SpotifyResult};
+use crate::app::models::{SongDescription, SpotifyLink};
+
+const ALBUM_BATCH_SIZE: usize = 50;
+
+// Links get dragged out as both text/uri-list and plain text, so that about anything accepts them.
+// The link is asked for when the drag starts, since rows get recycled.
+pub fn attach_link_drag_source<F>(widget: &impl IsA<gtk::Widget>, link: F)
+where
+    F: Fn() -> Option<String> + 'static,
+{
+    let source = gtk::DragSource::new();
+    source.set_actions(gdk::DragAction::COPY);
+    source.connect_prepare(move |_, _, _| {
+        let link = link()?;
+        let uri_list = glib::Bytes::from_owned(format!("{link}\r\n").into_bytes());
+        Some(gdk::ContentProvider::new_union(&[
+            gdk::ContentProvider::for_bytes("text/uri-list", &uri_list),
+            gdk::ContentProvider::for_value(&link.to_value()),
+        ]))
+    });
+    widget.add_controller(source);
+}
+
+fn links_from_value(value: &glib::Value) -> Vec<SpotifyLink> {
+    if let Ok(files) = value.get::<gdk::FileList>() {
+        files
+            .files()
+            .iter()
+            .filter_map(|file| SpotifyLink::parse(&file.uri()))
+            .collect()
+    } else if let Ok(text) = value.get::<String>() {
+        text.lines().filter_map(SpotifyLink::parse).collect()
+    } else {
+        vec![]
+    }
+}
+
+fn is_song_link(link: &SpotifyLink) -> bool {
+    matches!(link, SpotifyLink::Track(_) | SpotifyLink::Album(_))
+}
+
+// Anything that isn't a track or album link is ignored
+pub fn attach_link_drop_target<F>(widget: &impl IsA<gtk::Widget>, on_drop: F)
+where
+    F: Fn(Vec<SpotifyLink>) + 'static,
+{
+    let target = gtk::DropTarget::new(glib::Type::INVALID, gdk::DragAction::COPY);
+    target.set_types(&[gdk::FileList::static_type(), String::static_type()]);
+    target.connect_drop(move |_, value, _, _| {
+        let links: Vec<SpotifyLink> = links_from_value(value)
+            .into_iter()
+            .filter(is_song_link)
+            .collect();
+        if links.is_empty() {
+            return false;
+        }
+        on_drop(links);
+        true
+    });
+    widget.add_controller(target);
+}
+
+// Ctrl+V anywhere in the window while the widget is showing, with one link per line.
+// Same as dropping, only for lists of links copied from elsewhere
+pub fn attach_link_paste_shortcut<F>(widget: &impl IsA<gtk::Widget>, on_paste: F)
+where
+    F: Fn(Vec<SpotifyLink>) + Clone + 'static,
+{
+    let action = gtk::CallbackAction::new(move |widget, _| {
+        if !widget.is_mapped() {
+            return false;
+        }
+        let on_paste = on_paste.clone();
+        widget
+            .clipboard()
+            .read_text_async(gio::Cancellable::NONE, move |text| {
+                let links: Vec<SpotifyLink> = match text {
+                    Ok(Some(text)) => text
+                        .lines()
+                        .filter_map(|line| SpotifyLink::parse(line.trim()))
+                        .filter(is_song_link)
+                        .collect(),
+                    _ => vec![],
+                };
+                if !links.is_empty() {
+                    on_paste(links);
+                }
+            });
+        true
+    });
+    let controller = gtk::ShortcutController::new();
+    controller.set_scope(gtk::ShortcutScope::Global);
+    controller.add_shortcut(gtk::Shortcut::new(
+        gtk::ShortcutTrigger::parse_string("<Ctrl>V"),
+        Some(action),
+    ));
+    widget.add_controller(controller);
+}
+
+// In the order the links were given, albums with all of their songs
+pub async fn songs_for_links(
+    api: Arc<dyn SpotifyApiClient + Send + Sync>,
+    links: Vec<SpotifyLink>,
+) -> SpotifyResult<Vec<SongDescription>> {
+    let mut songs = vec![];
+    let mut track_ids = vec![];
+    for link in links {
+        match link {
+            SpotifyLink::Track(id) => track_ids.push(id),
+            SpotifyLink::Album(id) => {
+                if !track_ids.is_empty() {
+                    songs.append(&mut api.get_tracks(std::mem::take(&mut track_ids)).await?);
+                }
+                let album = api.get_album(&id).await?.description.songs;
+                let total = album.batch.total;
+                let mut album_songs = album.songs;
+                while album_songs.len() < total {
+                    let batch = api
+                        .get_album_tracks(&id, album_songs.len(), ALBUM_BATCH_SIZE)
+                        .await?;
+                    if batch.songs.is_empty() {
+                        break;
+                    }
+                    album_songs.extend(batch.songs);
+                }
+                songs.append(&mut album_songs);
+            }
+            _ => {}
+        }
+    }
+    if !track_ids.is_empty() {
+        songs.append(&mut api.get_tracks(track_ids).await?);
+    }
+    Ok(songs)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_links_from_text() {
+        let value = "https://open.spotify.com/track/a\nspotify:album:b\nnope".to_value();
+        assert_eq!(
+            links_from_value(&value),
+            vec![
+                SpotifyLink::Track("a".to_string()),
+                SpotifyLink::Album("b".to_string())
+            ]
+        );
+    }
+}
@@ -5,7 +5,7 @@ use gtk::prelude::*;
 
 use crate::app::{
     components::{Component, EventListener, ListenerComponent},
-    state::{SelectionContext, SelectionEvent},
+    state::{SelectionAction, SelectionContext, SelectionEvent},
     ActionDispatcher, AppAction, AppEvent, AppModel, BrowserAction, BrowserEvent,
 };
 
@@ -95,7 +95,6 @@ pub trait SimpleHeaderBarModel {
     fn title(&self) -> Option<String>;
     fn title_updated(&self, event: &AppEvent) -> bool;
     fn selection_context(&self) -> Option<SelectionContext>;
-    fn select_all(&self);
 }
 
 pub struct SimpleHeaderBarModelWrapper<M> {
@@ -154,8 +153,9 @@ where
         }
     }
 
+    // The list on screen knows best, it also loads whatever is missing
     fn select_all(&self) {
-        self.wrapped_model.select_all()
+        self.dispatcher.dispatch(SelectionAction::SelectAll.into())
     }
 
     fn cancel_selection(&self) {
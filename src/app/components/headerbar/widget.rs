@@ -30,6 +30,9 @@ mod imp {
         #[template_child]
         pub start_selection: TemplateChild<gtk::Button>,
 
+        #[template_child]
+        pub refresh: TemplateChild<gtk::Button>,
+
         #[template_child]
         pub select_all: TemplateChild<gtk::Button>,
 
@@ -136,6 +139,11 @@ impl HeaderBarWidget {
         self.imp().start_selection.set_visible(possible);
     }
 
+    // Not every screen has something to refresh
+    pub fn set_refresh_possible(&self, possible: bool) {
+        self.imp().refresh.set_visible(possible);
+    }
+
     pub fn set_select_all_possible(&self, possible: bool) {
         self.imp().select_all.set_visible(possible);
     }
@@ -0,0 +1,232 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::app::components::utils::Clock;
+use crate::app::components::EventListener;
+use crate::app::listening_history::{ListenRecord, ListeningHistory};
+use crate::app::models::SongDescription;
+use crate::app::state::PlaybackEvent;
+use crate::app::{AppEvent, AppModel};
+use crate::settings;
+
+// Same rules as most scrobblers: half of the song or four minutes, whichever comes first, and nothing too short
+const MIN_DURATION_MS: u32 = 30 * 1000;
+const MAX_THRESHOLD_MS: u32 = 4 * 60 * 1000;
+
+fn threshold(song: &SongDescription) -> Option<u32> {
+    (song.duration >= MIN_DURATION_MS).then(|| u32::min(song.duration / 2, MAX_THRESHOLD_MS))
+}
+
+struct Playing {
+    song: SongDescription,
+    context: Option<String>,
+}
+
+// Where the recorder finds out what's playing, and how far into it we are
+trait PlaybackSource {
+    fn playing(&self) -> Option<Playing>;
+    fn position(&self) -> u32;
+}
+
+impl PlaybackSource for AppModel {
+    fn playing(&self) -> Option<Playing> {
+        let state = self.get_state();
+        state.playback.current_song().map(|song| Playing {
+            song,
+            context: state
+                .playback
+                .current_source()
+                .and_then(|source| source.spotify_uri()),
+        })
+    }
+
+    fn position(&self) -> u32 {
+        self.get_state().playback.seek_position()
+    }
+}
+
+// The song being played, and whether it was written down already
+struct Tracker {
+    playback: Rc<dyn PlaybackSource>,
+    history: ListeningHistory,
+    is_enabled: fn() -> bool,
+    current: RefCell<Option<Playing>>,
+    recorded: Cell<bool>,
+}
+
+impl Tracker {
+    fn tick(&self) {
+        let position = self.playback.position();
+        let current = self.current.borrow();
+        let Some(playing) = current.as_ref() else {
+            return;
+        };
+        let reached = threshold(&playing.song).map(|t| position >= t);
+        if self.recorded.get() || reached != Some(true) {
+            return;
+        }
+        self.recorded.set(true);
+        if !(self.is_enabled)() {
+            return;
+        }
+        let song = &playing.song;
+        let record = ListenRecord {
+            played_at: glib::DateTime::now_utc()
+                .and_then(|now| now.format_iso8601())
+                .map(|now| now.to_string())
+                .unwrap_or_default(),
+            track_id: song.id.clone(),
+            title: song.title.clone(),
+            artists: song.artists_name(),
+            album: song.album.name.clone(),
+            context: playing.context.clone(),
+        };
+        if let Err(e) = self.history.append(&record) {
+            warn!("Could not save listening history: {}", e);
+        }
+    }
+}
+
+// Writes down the songs that were listened to, when the history is enabled in the settings
+pub struct HistoryRecorder {
+    tracker: Rc<Tracker>,
+    clock: Clock,
+}
+
+impl HistoryRecorder {
+    pub fn new(app_model: Rc<AppModel>) -> Self {
+        Self::with(
+            app_model,
+            ListeningHistory::default(),
+            settings::listening_history_enabled,
+        )
+    }
+
+    fn with(
+        playback: Rc<dyn PlaybackSource>,
+        history: ListeningHistory,
+        is_enabled: fn() -> bool,
+    ) -> Self {
+        Self {
+            tracker: Rc::new(Tracker {
+                playback,
+                history,
+                is_enabled,
+                current: Default::default(),
+                recorded: Default::default(),
+            }),
+            clock: Clock::new(5000),
+        }
+    }
+
+    fn start_clock(&self) {
+        if self.tracker.current.borrow().is_none() || self.tracker.recorded.get() {
+            return;
+        }
+        let tracker = Rc::clone(&self.tracker);
+        self.clock.start(move || tracker.tick());
+    }
+
+    fn on_track_changed(&self) {
+        let playing = self.tracker.playback.playing();
+        self.tracker.current.replace(playing);
+        self.tracker.recorded.set(false);
+        self.start_clock();
+    }
+}
+
+impl EventListener for HistoryRecorder {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::PlaybackEvent(PlaybackEvent::TrackChanged(_)) => self.on_track_changed(),
+            AppEvent::PlaybackEvent(PlaybackEvent::PlaybackResumed) => self.start_clock(),
+            AppEvent::PlaybackEvent(PlaybackEvent::PlaybackPaused) => self.clock.stop(),
+            AppEvent::PlaybackEvent(PlaybackEvent::PlaybackStopped) => {
+                self.clock.stop();
+                self.tracker.current.replace(None);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::test_utils::{self, TempDir};
+
+    fn song(duration: u32) -> SongDescription {
+        SongDescription {
+            duration,
+            ..test_utils::song("id")
+        }
+    }
+
+    #[test]
+    fn test_threshold() {
+        assert_eq!(threshold(&song(10_000)), None);
+        assert_eq!(threshold(&song(180_000)), Some(90_000));
+        assert_eq!(threshold(&song(60 * 60 * 1000)), Some(MAX_THRESHOLD_MS));
+    }
+
+    #[derive(Default)]
+    struct FakePlayback {
+        song: RefCell<Option<SongDescription>>,
+        position: Cell<u32>,
+    }
+
+    impl PlaybackSource for FakePlayback {
+        fn playing(&self) -> Option<Playing> {
+            let song = self.song.borrow().clone()?;
+            Some(Playing {
+                song,
+                context: Some("spotify:album:album".to_string()),
+            })
+        }
+
+        fn position(&self) -> u32 {
+            self.position.get()
+        }
+    }
+
+    fn playback_event(recorder: &mut HistoryRecorder, event: PlaybackEvent) {
+        recorder.on_event(&AppEvent::PlaybackEvent(event));
+    }
+
+    // Plays the song from start to end, the clock ticking every 5 seconds as it would
+    fn play_through(recorder: &mut HistoryRecorder, playback: &FakePlayback, until: u32) {
+        playback_event(recorder, PlaybackEvent::TrackChanged("id".to_string()));
+        playback_event(recorder, PlaybackEvent::PlaybackResumed);
+        for position in (0..=until).step_by(5000) {
+            playback.position.set(position);
+            recorder.tracker.tick();
+        }
+    }
+
+    #[test]
+    fn test_records_a_full_play() {
+        let dir = TempDir::create();
+        let playback = Rc::new(FakePlayback::default());
+        let history = ListeningHistory::at(dir.join("history.jsonl"));
+        let mut recorder = HistoryRecorder::with(playback.clone(), history, || true);
+        playback.song.replace(Some(song(180_000)));
+
+        // Skipped before halfway
+        play_through(&mut recorder, &playback, 60_000);
+        assert!(recorder.tracker.history.read_all().unwrap().is_empty());
+
+        play_through(&mut recorder, &playback, 180_000);
+        playback_event(&mut recorder, PlaybackEvent::PlaybackStopped);
+        let records = recorder.tracker.history.read_all().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].track_id, "id");
+        assert_eq!(records[0].context.as_deref(), Some("spotify:album:album"));
+
+        // Unless the history is turned off
+        let history = ListeningHistory::at(dir.join("disabled.jsonl"));
+        let mut recorder = HistoryRecorder::with(playback.clone(), history, || false);
+        play_through(&mut recorder, &playback, 180_000);
+        assert!(recorder.tracker.history.read_all().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::CompositeTemplate;
+use std::rc::Rc;
+
+use super::HomeShelvesModel;
+use crate::app::components::share::ShareKind;
+use crate::app::components::{AlbumWidget, Component, EventListener};
+use crate::app::dispatch::Worker;
+use crate::app::models::{AlbumModel, SpotifyLink};
+use crate::app::state::{LoginEvent, ScreenName};
+use crate::app::{AppEvent, BrowserEvent, ListStore};
+
+fn bind_playlists<F>(
+    flowbox: &gtk::FlowBox,
+    worker: Worker,
+    store: &ListStore<AlbumModel>,
+    kind: ShareKind,
+    on_album_pressed: F,
+) where
+    F: Fn(String) + Clone + 'static,
+{
+    flowbox.bind_model(Some(store.unsafe_store()), move |item| {
+        let album_model = item.downcast_ref::<AlbumModel>().unwrap();
+        let child = gtk::FlowBoxChild::new();
+        let album = AlbumWidget::for_model(album_model, worker.clone());
+        album.set_share_menu(kind, &album_model.uri());
+
+        let f = on_album_pressed.clone();
+        album.connect_album_pressed(clone!(@weak album_model => move |_| {
+            f(album_model.uri());
+        }));
+
+        child.set_child(Some(&album));
+        child.upcast::<gtk::Widget>()
+    });
+}
+
+// Same as above, except these can be albums or artists too, and their uri is a full spotify: URI
+fn bind_recent<F>(
+    flowbox: &gtk::FlowBox,
+    worker: Worker,
+    store: &ListStore<AlbumModel>,
+    on_pressed: F,
+) where
+    F: Fn(String) + Clone + 'static,
+{
+    flowbox.bind_model(Some(store.unsafe_store()), move |item| {
+        let album_model = item.downcast_ref::<AlbumModel>().unwrap();
+        let child = gtk::FlowBoxChild::new();
+        let album = AlbumWidget::for_model(album_model, worker.clone());
+        let share = match SpotifyLink::parse(&album_model.uri()) {
+            Some(SpotifyLink::Album(id)) => Some((ShareKind::Album, id)),
+            Some(SpotifyLink::Artist(id)) => Some((ShareKind::Artist, id)),
+            Some(SpotifyLink::Playlist(id)) => Some((ShareKind::Playlist, id)),
+            _ => None,
+        };
+        if let Some((kind, id)) = share {
+            album.set_share_menu(kind, &id);
+        }
+
+        let f = on_pressed.clone();
+        album.connect_album_pressed(clone!(@weak album_model => move |_| {
+            f(album_model.uri());
+        }));
+
+        child.set_child(Some(&album));
+        child.upcast::<gtk::Widget>()
+    });
+}
+
+mod imp {
+
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/dev/alextren/Spot/components/home_shelves.ui")]
+    pub struct HomeShelvesWidget {
+        #[template_child]
+        pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+
+        #[template_child]
+        pub releases_shelf: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub releases_flowbox: TemplateChild<gtk::FlowBox>,
+        #[template_child]
+        pub recent_shelf: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub recent_flowbox: TemplateChild<gtk::FlowBox>,
+        #[template_child]
+        pub pinned_shelf: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub pinned_flowbox: TemplateChild<gtk::FlowBox>,
+        #[template_child]
+        pub shelves_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub status_page: TemplateChild<libadwaita::StatusPage>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HomeShelvesWidget {
+        const NAME: &'static str = "HomeShelvesWidget";
+        type Type = super::HomeShelvesWidget;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for HomeShelvesWidget {}
+    impl WidgetImpl for HomeShelvesWidget {}
+    impl BoxImpl for HomeShelvesWidget {}
+}
+
+glib::wrapper! {
+    pub struct HomeShelvesWidget(ObjectSubclass<imp::HomeShelvesWidget>) @extends gtk::Widget, gtk::Box;
+}
+
+impl Default for HomeShelvesWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HomeShelvesWidget {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn clear(&self) {
+        let shelves_box = &self.imp().shelves_box;
+        while let Some(child) = shelves_box.first_child() {
+            shelves_box.remove(&child);
+        }
+    }
+
+    // A title, and a single row of playlists scrolling sideways; reaching its end asks for more
+    fn add_shelf<F, G>(
+        &self,
+        worker: Worker,
+        title: &str,
+        store: &ListStore<AlbumModel>,
+        on_album_pressed: F,
+        on_end_reached: G,
+    ) where
+        F: Fn(String) + Clone + 'static,
+        G: Fn() + 'static,
+    {
+        let label = gtk::Label::builder()
+            .label(title)
+            .halign(gtk::Align::Start)
+            .margin_start(8)
+            .margin_end(8)
+            .build();
+        label.add_css_class("title-4");
+
+        let flowbox = gtk::FlowBox::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .min_children_per_line(1)
+            .max_children_per_line(1)
+            .selection_mode(gtk::SelectionMode::None)
+            .activate_on_single_click(false)
+            .margin_start(8)
+            .margin_end(8)
+            .build();
+        bind_playlists(
+            &flowbox,
+            worker,
+            store,
+            ShareKind::Playlist,
+            on_album_pressed,
+        );
+
+        let scrolled_window = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Automatic)
+            .vscrollbar_policy(gtk::PolicyType::Never)
+            .child(&flowbox)
+            .build();
+        scrolled_window.connect_edge_reached(move |_, pos| {
+            if let gtk::PositionType::Right = pos {
+                on_end_reached();
+            }
+        });
+
+        let shelf = gtk::Box::new(gtk::Orientation::Vertical, 4);
+        shelf.append(&label);
+        shelf.append(&scrolled_window);
+        self.imp().shelves_box.append(&shelf);
+    }
+
+    fn bind_pinned<F>(&self, worker: Worker, store: &ListStore<AlbumModel>, on_album_pressed: F)
+    where
+        F: Fn(String) + Clone + 'static,
+    {
+        bind_playlists(
+            &self.imp().pinned_flowbox,
+            worker,
+            store,
+            ShareKind::Playlist,
+            on_album_pressed,
+        );
+    }
+
+    fn set_pinned_visible(&self, visible: bool) {
+        self.imp().pinned_shelf.set_visible(visible);
+    }
+
+    fn bind_releases<F>(&self, worker: Worker, store: &ListStore<AlbumModel>, on_album_pressed: F)
+    where
+        F: Fn(String) + Clone + 'static,
+    {
+        bind_playlists(
+            &self.imp().releases_flowbox,
+            worker,
+            store,
+            ShareKind::Album,
+            on_album_pressed,
+        );
+    }
+
+    fn set_releases_visible(&self, visible: bool) {
+        self.imp().releases_shelf.set_visible(visible);
+    }
+
+    fn bind_recent<F>(&self, worker: Worker, store: &ListStore<AlbumModel>, on_pressed: F)
+    where
+        F: Fn(String) + Clone + 'static,
+    {
+        bind_recent(&self.imp().recent_flowbox, worker, store, on_pressed);
+    }
+
+    fn set_recent_visible(&self, visible: bool) {
+        self.imp().recent_shelf.set_visible(visible);
+    }
+
+    pub fn get_status_page(&self) -> &libadwaita::StatusPage {
+        &self.imp().status_page
+    }
+}
+
+pub struct HomeShelves {
+    widget: HomeShelvesWidget,
+    worker: Worker,
+    model: Rc<HomeShelvesModel>,
+}
+
+impl HomeShelves {
+    pub fn new(worker: Worker, model: HomeShelvesModel) -> Self {
+        Self {
+            widget: HomeShelvesWidget::new(),
+            worker,
+            model: Rc::new(model),
+        }
+    }
+
+    // Each shelf has its own store, and they're all replaced when the shelves are refreshed
+    fn rebuild_shelves(&self) {
+        self.widget.clear();
+        let shelves = self.model.shelves();
+        for (id, title, store) in shelves {
+            self.widget.add_shelf(
+                self.worker.clone(),
+                &title,
+                &store,
+                clone!(@weak self.model as model => move |id| {
+                    model.open_playlist(id);
+                }),
+                clone!(@weak self.model as model => move || {
+                    model.load_more(id.clone());
+                }),
+            );
+        }
+        self.update_status();
+    }
+
+    fn update_status(&self) {
+        let has_pinned = self.model.has_pinned_playlists();
+        let has_recent = self.model.has_recently_viewed();
+        let has_releases = self.model.has_new_releases();
+        self.widget.set_pinned_visible(has_pinned);
+        self.widget.set_recent_visible(has_recent);
+        self.widget.set_releases_visible(has_releases);
+        self.widget
+            .get_status_page()
+            .set_visible(!has_pinned && !has_recent && !has_releases && !self.model.has_shelves());
+    }
+
+    fn bind_pinned(&self) {
+        self.widget.bind_pinned(
+            self.worker.clone(),
+            &self.model.get_pinned_store().unwrap(),
+            clone!(@weak self.model as model => move |id| {
+                model.open_playlist(id);
+            }),
+        );
+    }
+
+    fn bind_releases(&self) {
+        self.widget.bind_releases(
+            self.worker.clone(),
+            &self.model.get_releases_store().unwrap(),
+            clone!(@weak self.model as model => move |id| {
+                model.open_album(id);
+            }),
+        );
+    }
+
+    fn bind_recent(&self) {
+        self.widget.bind_recent(
+            self.worker.clone(),
+            &self.model.get_recent_store().unwrap(),
+            clone!(@weak self.model as model => move |uri| {
+                model.open_uri(uri);
+            }),
+        );
+    }
+}
+
+impl EventListener for HomeShelves {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Started => {
+                self.model.refresh_shelves();
+                self.model.refresh_pinned_playlists();
+                self.bind_pinned();
+                self.bind_recent();
+                self.bind_releases();
+            }
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_))
+            | AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::Home)) => {
+                self.model.refresh_shelves();
+                self.model.refresh_pinned_playlists();
+            }
+            AppEvent::BrowserEvent(BrowserEvent::HomeShelvesUpdated) => {
+                self.rebuild_shelves();
+            }
+            AppEvent::BrowserEvent(BrowserEvent::PinnedPlaylistsUpdated)
+            | AppEvent::BrowserEvent(BrowserEvent::RecentlyViewedUpdated)
+            | AppEvent::BrowserEvent(BrowserEvent::NewReleasesUpdated) => {
+                self.update_status();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Component for HomeShelves {
+    fn get_root_widget(&self) -> &gtk::Widget {
+        self.widget.as_ref()
+    }
+}
@@ -0,0 +1,133 @@
+use std::cell::Ref;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::app::components::utils::or_unavailable;
+use crate::app::models::*;
+use crate::app::state::{HomeShelfState, HomeState};
+use crate::app::{ActionDispatcher, AppAction, AppModel, BrowserAction, ListStore};
+
+pub struct HomeShelvesModel {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl HomeShelvesModel {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+
+    fn state(&self) -> Option<Ref<'_, HomeState>> {
+        self.app_model.map_state_opt(|s| s.browser.home_state())
+    }
+
+    // (id, title, playlists) of each shelf, the stores are shared with the state
+    pub fn shelves(&self) -> Vec<(String, String, ListStore<AlbumModel>)> {
+        self.state()
+            .map(|s| {
+                s.shelves
+                    .iter()
+                    .map(|shelf| {
+                        (
+                            shelf.id.clone(),
+                            shelf.title.clone(),
+                            shelf.playlists.clone(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn has_shelves(&self) -> bool {
+        self.state().map(|s| !s.shelves.is_empty()).unwrap_or(false)
+    }
+
+    pub fn get_pinned_store(&self) -> Option<impl Deref<Target = ListStore<AlbumModel>> + '_> {
+        Some(Ref::map(self.state()?, |s| &s.pinned_playlists))
+    }
+
+    pub fn has_pinned_playlists(&self) -> bool {
+        self.get_pinned_store()
+            .map(|list| list.len() > 0)
+            .unwrap_or(false)
+    }
+
+    pub fn get_recent_store(&self) -> Option<impl Deref<Target = ListStore<AlbumModel>> + '_> {
+        Some(Ref::map(self.state()?, |s| &s.recently_viewed))
+    }
+
+    pub fn has_recently_viewed(&self) -> bool {
+        self.get_recent_store()
+            .map(|list| list.len() > 0)
+            .unwrap_or(false)
+    }
+
+    pub fn get_releases_store(&self) -> Option<impl Deref<Target = ListStore<AlbumModel>> + '_> {
+        Some(Ref::map(self.state()?, |s| &s.new_releases))
+    }
+
+    pub fn has_new_releases(&self) -> bool {
+        self.get_releases_store()
+            .map(|list| list.len() > 0)
+            .unwrap_or(false)
+    }
+
+    // Found through search, so not quite as reliable as the shelves; the results are kept for a week
+    pub fn refresh_pinned_playlists(&self) {
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .call_spotify_and_dispatch(move || async move {
+                let playlists = api.get_pinned_playlists().await;
+                or_unavailable(playlists, vec![])
+                    .map(|p| BrowserAction::SetPinnedPlaylists(p).into())
+            });
+    }
+
+    pub fn refresh_shelves(&self) {
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .call_spotify_and_dispatch(move || async move {
+                let shelves = api.get_home_shelves(HomeShelfState::BATCH_SIZE).await;
+                or_unavailable(shelves, vec![]).map(|s| BrowserAction::SetHomeShelves(s).into())
+            });
+    }
+
+    pub fn load_more(&self, id: String) -> Option<()> {
+        let api = self.app_model.get_spotify();
+        let offset = {
+            let state = self.state()?;
+            let shelf = state.shelves.iter().find(|s| s.id == id)?;
+            shelf.next_page.next_offset()?
+        };
+
+        self.dispatcher
+            .call_spotify_and_dispatch(move || async move {
+                let playlists = api
+                    .get_home_shelf(&id, offset, HomeShelfState::BATCH_SIZE)
+                    .await;
+                // An empty page marks the shelf as fully loaded, so we don't keep on trying
+                or_unavailable(playlists, vec![])
+                    .map(|playlists| BrowserAction::AppendHomeShelf(id, playlists).into())
+            });
+
+        Some(())
+    }
+
+    pub fn open_playlist(&self, id: String) {
+        self.dispatcher.dispatch(AppAction::ViewPlaylist(id));
+    }
+
+    pub fn open_album(&self, id: String) {
+        self.dispatcher.dispatch(AppAction::ViewAlbum(id));
+    }
+
+    pub fn open_uri(&self, uri: String) {
+        if let Some(action) = AppAction::OpenURI(uri) {
+            self.dispatcher.dispatch(action);
+        }
+    }
+}
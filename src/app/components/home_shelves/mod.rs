@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+mod home_shelves;
+mod home_shelves_model;
+
+pub use home_shelves::*;
+pub use home_shelves_model::*;
@@ -7,11 +7,38 @@ lazy_static! {
     // translators: This is part of a contextual menu attached to a single track; the intent is to copy the link (public URL) to a specific track.
     pub static ref COPY_LINK: String = gettext("Copy link");
 
+    // translators: This is part of a contextual menu attached to a track, an album, an artist or a playlist; the intent is to copy its Spotify URI (spotify:track:...), which other Spotify apps understand.
+    pub static ref COPY_URI: String = gettext("Copy Spotify URI");
+
     // translators: This is part of a contextual menu attached to a single track; this entry adds a track at the end of the play queue.
     pub static ref ADD_TO_QUEUE: String = gettext("Add to queue");
 
     // translators: This is part of a contextual menu attached to a single track; this entry removes a track from the play queue.
     pub static ref REMOVE_FROM_QUEUE: String = gettext("Remove from queue");
+
+    // translators: This is a tooltip on songs played earlier, above the play queue; the song is played once more.
+    pub static ref PLAY_AGAIN: String = gettext("Play again");
+
+    // translators: This is a tooltip on songs that Spotify doesn't make available in the user's country; they are skipped during playback.
+    pub static ref UNAVAILABLE_IN_REGION: String = gettext("This song isn't available in your region");
+
+    // translators: This is part of a contextual menu attached to a single track; the track is then always skipped (it can be allowed again from the preferences).
+    pub static ref DONT_PLAY_SONG: String = gettext("Don't play this song");
+
+    // translators: In the list of what changed in a playlist since it was last seen, for a song that was added to it.
+    pub static ref SONG_ADDED: String = gettext("Added");
+
+    // translators: In the list of what changed in a playlist since it was last seen, for a song that was removed from it.
+    pub static ref SONG_REMOVED: String = gettext("Removed");
+
+    // translators: In the list of what changed in a playlist since it was last seen, for a song that's now somewhere else in it.
+    pub static ref SONG_MOVED: String = gettext("Moved");
+
+    // translators: Under the name of a playlist, in the results of the quick open palette (Ctrl+K).
+    pub static ref QUICK_OPEN_PLAYLIST: String = gettext("Playlist");
+
+    // translators: Under the name of an artist, in the results of the quick open palette (Ctrl+K).
+    pub static ref QUICK_OPEN_ARTIST: String = gettext("Artist");
 }
 
 pub fn add_to_playlist_label(playlist: &str) -> String {
@@ -41,6 +68,42 @@ pub fn more_from_label(artist: &str) -> String {
     gettext!("More from {}", glib::markup_escape_text(artist))
 }
 
+pub fn dont_play_artist_label(artist: &str) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: This is part of a contextual menu attached to a single track; the full text is "Don't play <artist>". Their songs are then always skipped.
+        gettext("Don't play {}");
+    }
+    gettext!("Don't play {}", glib::markup_escape_text(artist))
+}
+
+pub fn move_to_device_label(device: &str) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: This is a tooltip in the device picker, the full text is "Move to <device name>".
+        gettext("Move to {}");
+    }
+    gettext!("Move to {}", device)
+}
+
+pub fn active_on_device_label(device: &str) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Shown in the playback bar when something is playing on another device, the full text is "Active on <device name>".
+        gettext("Active on {}");
+    }
+    gettext!("Active on {}", device)
+}
+
+pub fn listening_with_label(members: &[String]) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Shown in the device picker during a group session, followed by a comma-separated list of people.
+        gettext("Listening with {}");
+    }
+    gettext!("Listening with {}", members.join(", "))
+}
+
 pub fn album_by_artist_label(album: &str, artist: &str) -> String {
     // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
     if cfg!(debug_assertions) {
@@ -53,3 +116,86 @@ pub fn album_by_artist_label(album: &str, artist: &str) -> String {
         glib::markup_escape_text(artist)
     )
 }
+
+pub fn partial_write_label(done: usize, total: usize) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Shown when only some of the selected songs could be saved, added to or removed from a playlist. The full text is "<done> of <total> songs done, the others are still selected"
+        ngettext(
+            "{} of {} song done, the others are still selected",
+            "{} of {} songs done, the others are still selected",
+            total as u32,
+        );
+    }
+    ngettext!(
+        "{} of {} song done, the others are still selected",
+        "{} of {} songs done, the others are still selected",
+        total as u32,
+        done,
+        total
+    )
+}
+
+pub fn partially_added_label(done: usize, total: usize) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Shown when only some of the songs dropped or pasted on a playlist could be added to it. The full text is "Only <done> of <total> songs were added"
+        ngettext(
+            "Only {} of {} song was added",
+            "Only {} of {} songs were added",
+            total as u32,
+        );
+    }
+    ngettext!(
+        "Only {} of {} song was added",
+        "Only {} of {} songs were added",
+        total as u32,
+        done,
+        total
+    )
+}
+
+pub fn n_songs_removed_label(n: usize) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: This is a notification shown after removing songs from a playlist or from the library. It can be undone.
+        ngettext("{} song removed", "{} songs removed", n as u32);
+    }
+    ngettext!("{} song removed", "{} songs removed", n as u32, n)
+}
+
+pub fn n_playlist_changes_label(n: usize) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Shown above a playlist that someone changed since it was last seen: songs were added, removed or moved around. The older version is shown until the user asks for the new one.
+        ngettext(
+            "{} change since you last saw this playlist",
+            "{} changes since you last saw this playlist",
+            n as u32,
+        );
+    }
+    ngettext!(
+        "{} change since you last saw this playlist",
+        "{} changes since you last saw this playlist",
+        n as u32,
+        n
+    )
+}
+
+pub fn new_release_label(artist: &str) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Title of the notification shown when an artist whose releases are watched puts out an album or single. The full text is "New from <artist>".
+        gettext("New from {}");
+    }
+    gettext!("New from {}", artist)
+}
+
+pub fn quick_open_album_label(artist: &str) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Under the name of an album, in the results of the quick open palette (Ctrl+K). The full text is "Album by <artist>".
+        gettext("Album by {}");
+    }
+    gettext!("Album by {}", artist)
+}
@@ -1,15 +1,23 @@
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
-use super::LibraryModel;
-use crate::app::components::utils::wrap_flowbox_item;
-use crate::app::components::{AlbumWidget, Component, EventListener};
+use super::{album_matches_filter, find_album_by_initial, LibraryModel, LibrarySort, RAIL_LETTERS};
+use crate::app::components::share::ShareKind;
+use crate::app::components::utils::{connect_prefetch, for_each_flowbox_item, wrap_flowbox_item};
+use crate::app::components::{display_add_css_provider, AlbumWidget, Component, EventListener};
 use crate::app::dispatch::Worker;
 use crate::app::models::AlbumModel;
-use crate::app::state::LoginEvent;
-use crate::app::{AppEvent, BrowserEvent, ListStore};
+use crate::app::state::{LoginEvent, ScreenName};
+use crate::app::{AppEvent, BrowserEvent};
+use crate::settings::ViewMode;
+
+const VIEW_MODE_KEY: &str = "library-view-mode";
+
+// GTK's default
+const GRID_MAX_CHILDREN_PER_LINE: u32 = 7;
 
 mod imp {
 
@@ -18,6 +26,15 @@ mod imp {
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(resource = "/dev/alextren/Spot/components/library.ui")]
     pub struct LibraryWidget {
+        #[template_child]
+        pub filter_entry: TemplateChild<gtk::SearchEntry>,
+
+        #[template_child]
+        pub sort_dropdown: TemplateChild<gtk::DropDown>,
+
+        #[template_child]
+        pub view_mode_toggle: TemplateChild<gtk::ToggleButton>,
+
         #[template_child]
         pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
 
@@ -26,6 +43,12 @@ mod imp {
 
         #[template_child]
         pub status_page: TemplateChild<libadwaita::StatusPage>,
+
+        #[template_child]
+        pub letter_rail: TemplateChild<gtk::Box>,
+
+        // Read when creating album widgets
+        pub list_mode: Rc<Cell<bool>>,
     }
 
     #[glib::object_subclass]
@@ -60,70 +83,229 @@ impl Default for LibraryWidget {
 
 impl LibraryWidget {
     pub fn new() -> Self {
+        display_add_css_provider(resource!("/components/library.css"));
         glib::Object::new()
     }
 
-    fn connect_bottom_edge<F>(&self, f: F)
+    fn connect_letter_clicked<F>(&self, f: F)
     where
-        F: Fn() + 'static,
+        F: Fn(char) + Clone + 'static,
+    {
+        let letter_rail = &self.imp().letter_rail;
+        for letter in RAIL_LETTERS.chars() {
+            let button = gtk::Button::builder()
+                .label(letter.to_string())
+                .css_classes(vec!["flat".to_string()])
+                .build();
+            let f = f.clone();
+            button.connect_clicked(move |_| f(letter));
+            letter_rail.append(&button);
+        }
+    }
+
+    fn connect_filter_changed<F>(&self, f: F)
+    where
+        F: Fn(String) + 'static,
     {
         self.imp()
-            .scrolled_window
-            .connect_edge_reached(move |_, pos| {
-                if let gtk::PositionType::Bottom = pos {
-                    f()
-                }
-            });
+            .filter_entry
+            .connect_search_changed(move |entry| f(entry.text().to_lowercase()));
     }
 
-    fn bind_albums<F>(&self, worker: Worker, store: &ListStore<AlbumModel>, on_album_pressed: F)
+    fn connect_sort_changed<F>(&self, f: F)
     where
-        F: Fn(String) + Clone + 'static,
+        F: Fn(LibrarySort) + 'static,
     {
         self.imp()
-            .flowbox
-            .bind_model(Some(store.unsafe_store()), move |item| {
-                wrap_flowbox_item(item, |album_model| {
-                    let f = on_album_pressed.clone();
-                    let album = AlbumWidget::for_model(album_model, worker.clone());
-                    album.connect_album_pressed(clone!(@weak album_model => move |_| {
-                        f(album_model.uri());
-                    }));
-                    album
-                })
+            .sort_dropdown
+            .connect_selected_notify(move |dropdown| {
+                f(LibrarySort::from_position(dropdown.selected()))
             });
     }
 
+    fn connect_view_mode_changed<F>(&self, f: F)
+    where
+        F: Fn(ViewMode) + 'static,
+    {
+        self.imp().view_mode_toggle.connect_toggled(move |toggle| {
+            f(if toggle.is_active() {
+                ViewMode::List
+            } else {
+                ViewMode::Grid
+            })
+        });
+    }
+
+    fn set_view_mode(&self, view_mode: ViewMode) {
+        let widget = self.imp();
+        let list_mode = view_mode == ViewMode::List;
+        widget.list_mode.set(list_mode);
+        widget.view_mode_toggle.set_active(list_mode);
+        widget.flowbox.set_max_children_per_line(if list_mode {
+            1
+        } else {
+            GRID_MAX_CHILDREN_PER_LINE
+        });
+        for_each_flowbox_item(&widget.flowbox, |album: &AlbumWidget| {
+            album.set_list_mode(list_mode)
+        });
+    }
+
+    fn scroll_to_album(&self, index: usize) {
+        // Give the flowbox a chance to allocate freshly loaded albums first
+        glib::idle_add_local_once(clone!(@weak self as _self => move || {
+            let widget = _self.imp();
+            let y = widget
+                .flowbox
+                .child_at_index(index as i32)
+                .and_then(|child| child.translate_coordinates(&*widget.flowbox, 0.0, 0.0))
+                .map(|(_, y)| y);
+            if let Some(y) = y {
+                widget.scrolled_window.vadjustment().set_value(y);
+            }
+        }));
+    }
+
+    fn connect_bottom_edge<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        connect_prefetch(&self.imp().scrolled_window, f);
+    }
+
+    fn bind_albums<F>(&self, worker: Worker, albums: &impl IsA<gio::ListModel>, on_album_pressed: F)
+    where
+        F: Fn(String) + Clone + 'static,
+    {
+        let list_mode = self.imp().list_mode.clone();
+        self.imp().flowbox.bind_model(Some(albums), move |item| {
+            wrap_flowbox_item(item, |album_model| {
+                let f = on_album_pressed.clone();
+                let album = AlbumWidget::for_model(album_model, worker.clone());
+                album.set_list_mode(list_mode.get());
+                album.set_share_menu(ShareKind::Album, &album_model.uri());
+                album.connect_album_pressed(clone!(@weak album_model => move |_| {
+                    f(album_model.uri());
+                }));
+                album
+            })
+        });
+    }
+
     pub fn status_page(&self) -> &libadwaita::StatusPage {
         &self.imp().status_page
     }
+
+    pub fn letter_rail(&self) -> &gtk::Box {
+        &self.imp().letter_rail
+    }
 }
 
 pub struct Library {
     widget: LibraryWidget,
     worker: Worker,
     model: Rc<LibraryModel>,
+    // Sorting and filtering only happen in the UI, the state keeps albums in the order Spotify gives them
+    sort: Rc<Cell<LibrarySort>>,
+    albums: gtk::SortListModel,
+    // Letter we want to jump to, but no album starting with it has been loaded (yet)
+    pending_letter: Rc<Cell<Option<char>>>,
 }
 
 impl Library {
     pub fn new(worker: Worker, model: LibraryModel) -> Self {
         let model = Rc::new(model);
         let widget = LibraryWidget::new();
+        let pending_letter = Rc::new(Cell::new(None));
+        let sort = Rc::new(Cell::new(LibrarySort::RecentlyAdded));
+        let filter_text = Rc::new(RefCell::new(String::new()));
+
+        let sorter = gtk::CustomSorter::new(clone!(@strong sort => move |a, b| {
+            let a = a.downcast_ref::<AlbumModel>().unwrap();
+            let b = b.downcast_ref::<AlbumModel>().unwrap();
+            sort.get().compare(a, b).into()
+        }));
+        let filter = gtk::CustomFilter::new(clone!(@strong filter_text => move |album| {
+            let album = album.downcast_ref::<AlbumModel>().unwrap();
+            album_matches_filter(album, &filter_text.borrow())
+        }));
+        let albums = {
+            let store = model.get_list_store().unwrap();
+            let filtered =
+                gtk::FilterListModel::new(Some(store.unsafe_store().clone()), Some(filter.clone()));
+            gtk::SortListModel::new(Some(filtered), Some(sorter.clone()))
+        };
+
+        widget.set_view_mode(ViewMode::new_from_gsettings(VIEW_MODE_KEY));
+        widget.connect_view_mode_changed(clone!(@weak widget => move |view_mode| {
+            widget.set_view_mode(view_mode);
+            view_mode.save(VIEW_MODE_KEY);
+        }));
+
         widget.connect_bottom_edge(clone!(@weak model => move || {
             model.load_more_albums();
         }));
 
+        widget.connect_letter_clicked(
+            clone!(@weak widget, @weak model, @weak pending_letter, @weak sort, @weak albums => move |letter| {
+                Self::jump_to_letter(&widget, &model, &albums, sort.get(), &pending_letter, letter);
+            }),
+        );
+
+        widget.connect_filter_changed(clone!(@weak filter => move |text| {
+            filter_text.replace(text);
+            filter.changed(gtk::FilterChange::Different);
+        }));
+
+        widget.connect_sort_changed(
+            clone!(@weak model, @weak sort, @weak sorter => move |new_sort| {
+                sort.set(new_sort);
+                sorter.changed(gtk::SorterChange::Different);
+                Self::load_all_if_needed(&model, new_sort);
+            }),
+        );
+
         Self {
             widget,
             worker,
             model,
+            sort,
+            albums,
+            pending_letter,
+        }
+    }
+
+    // Filtering only goes through what's loaded so far, but sorting a partial library would make no sense
+    fn load_all_if_needed(model: &LibraryModel, sort: LibrarySort) {
+        if sort.needs_all_albums() && model.has_more_albums() {
+            model.load_more_albums();
+        }
+    }
+
+    // Only a fraction of the library might be loaded, so we keep loading pages until we find something
+    fn jump_to_letter(
+        widget: &LibraryWidget,
+        model: &LibraryModel,
+        albums: &gtk::SortListModel,
+        sort: LibrarySort,
+        pending_letter: &Cell<Option<char>>,
+        letter: char,
+    ) {
+        if let Some(index) = find_album_by_initial(albums, sort, letter) {
+            pending_letter.set(None);
+            widget.scroll_to_album(index);
+        } else if model.has_more_albums() {
+            pending_letter.set(Some(letter));
+            model.load_more_albums();
+        } else {
+            pending_letter.set(None);
         }
     }
 
     fn bind_flowbox(&self) {
         self.widget.bind_albums(
             self.worker.clone(),
-            &self.model.get_list_store().unwrap(),
+            &self.albums,
             clone!(@weak self.model as model => move |id| {
                 model.open_album(id);
             }),
@@ -138,13 +320,25 @@ impl EventListener for Library {
                 let _ = self.model.refresh_saved_albums();
                 self.bind_flowbox();
             }
-            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_)) => {
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_))
+            | AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::Home)) => {
                 let _ = self.model.refresh_saved_albums();
             }
             AppEvent::BrowserEvent(BrowserEvent::LibraryUpdated) => {
-                self.widget
-                    .status_page()
-                    .set_visible(!self.model.has_albums());
+                let has_albums = self.model.has_albums();
+                self.widget.status_page().set_visible(!has_albums);
+                self.widget.letter_rail().set_visible(has_albums);
+                Self::load_all_if_needed(&self.model, self.sort.get());
+                if let Some(letter) = self.pending_letter.get() {
+                    Self::jump_to_letter(
+                        &self.widget,
+                        &self.model,
+                        &self.albums,
+                        self.sort.get(),
+                        &self.pending_letter,
+                        letter,
+                    );
+                }
             }
             _ => {}
         }
@@ -1,11 +1,91 @@
+use gio::prelude::*;
 use std::cell::Ref;
+use std::cmp::Ordering;
 use std::ops::Deref;
 use std::rc::Rc;
 
+use crate::app::collation;
 use crate::app::models::*;
 use crate::app::state::HomeState;
 use crate::app::{ActionDispatcher, AppAction, AppModel, BrowserAction, ListStore};
 
+// What the fast-scroll rail shows, '#' is for anything that doesn't start with a letter
+pub const RAIL_LETTERS: &str = "#ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// Same order as the sort dropdown in library.blp
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LibrarySort {
+    RecentlyAdded,
+    Artist,
+    Title,
+    ReleaseYear,
+}
+
+impl LibrarySort {
+    pub fn from_position(position: u32) -> Self {
+        match position {
+            1 => Self::Artist,
+            2 => Self::Title,
+            3 => Self::ReleaseYear,
+            _ => Self::RecentlyAdded,
+        }
+    }
+
+    // Saved albums come most recent first, any other order is only right once we have all of them
+    pub fn needs_all_albums(self) -> bool {
+        self != Self::RecentlyAdded
+    }
+
+    // What albums are sorted by, to be made once per album with sort_by_cached_key when they're all at hand
+    pub fn sort_key(self, album: &AlbumModel) -> (glib::CollationKey, i64, glib::CollationKey) {
+        let artist = || collation::sort_key(&album.artist());
+        let title = || collation::sort_key(&album.album());
+        let none = || collation::sort_key("");
+        let year = i64::from(album.year());
+        match self {
+            Self::RecentlyAdded => (none(), 0, none()),
+            // Oldest first within an artist, like a discography
+            Self::Artist => (artist(), year, title()),
+            Self::Title => (title(), 0, artist()),
+            Self::ReleaseYear => (none(), -year, artist()),
+        }
+    }
+
+    // For the GTK sorter, which only gets two albums at a time
+    pub fn compare(self, a: &AlbumModel, b: &AlbumModel) -> Ordering {
+        match self {
+            Self::RecentlyAdded => Ordering::Equal,
+            _ => self.sort_key(a).cmp(&self.sort_key(b)),
+        }
+    }
+
+    // The rail entry an album is filed under, which depends on what albums are sorted by
+    fn initial(self, album: &AlbumModel) -> char {
+        match self {
+            Self::Artist => collation::initial(&album.artist()),
+            _ => collation::initial(&album.album()),
+        }
+    }
+}
+
+// The filter is expected to be lowercase already
+pub fn album_matches_filter(album: &AlbumModel, filter: &str) -> bool {
+    filter.is_empty()
+        || album.album().to_lowercase().contains(filter)
+        || album.artist().to_lowercase().contains(filter)
+}
+
+// Position of the first album (as displayed, that is sorted and filtered) filed under that letter
+pub fn find_album_by_initial(
+    albums: &impl IsA<gio::ListModel>,
+    sort: LibrarySort,
+    letter: char,
+) -> Option<usize> {
+    (0..albums.n_items())
+        .filter_map(|i| albums.item(i)?.downcast::<AlbumModel>().ok())
+        .position(|album| sort.initial(&album) == letter)
+}
+
 pub struct LibraryModel {
     app_model: Rc<AppModel>,
     dispatcher: Box<dyn ActionDispatcher>,
@@ -33,9 +113,9 @@ impl LibraryModel {
 
         self.dispatcher
             .call_spotify_and_dispatch(move || async move {
-                api.get_saved_albums(0, batch_size)
+                api.get_saved_albums(PageToken::Offset(0), batch_size)
                     .await
-                    .map(|albums| BrowserAction::SetLibraryContent(albums).into())
+                    .map(|albums| BrowserAction::SetLibraryContent(albums.items).into())
             });
 
         Some(())
@@ -47,16 +127,22 @@ impl LibraryModel {
             .unwrap_or(false)
     }
 
+    pub fn has_more_albums(&self) -> bool {
+        self.state()
+            .map(|s| s.next_albums_page.next.is_some())
+            .unwrap_or(false)
+    }
+
     pub fn load_more_albums(&self) -> Option<()> {
         let api = self.app_model.get_spotify();
 
         let next_page = &self.state()?.next_albums_page;
         let batch_size = next_page.batch_size;
-        let offset = next_page.next_offset?;
+        let page = next_page.next.clone()?;
 
         self.dispatcher
             .call_spotify_and_dispatch(move || async move {
-                api.get_saved_albums(offset, batch_size)
+                api.get_saved_albums(page.clone(), batch_size)
                     .await
                     .map(|albums| BrowserAction::AppendLibraryContent(albums).into())
             });
@@ -68,3 +154,67 @@ impl LibraryModel {
         self.dispatcher.dispatch(AppAction::ViewAlbum(album_id));
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn album(artist: &str, title: &str, year: u32) -> AlbumModel {
+        AlbumModel::new(
+            &artist.to_string(),
+            &title.to_string(),
+            Some(year),
+            None,
+            &title.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_sort_albums() {
+        let abbey_road = album("The Beatles", "Abbey Road", 1969);
+        let help = album("the beatles", "Help!", 1965);
+        let kid_a = album("Radiohead", "Kid A", 2000);
+
+        let sorted = |sort: LibrarySort| {
+            let mut albums = vec![&kid_a, &abbey_road, &help];
+            albums.sort_by_cached_key(|album| sort.sort_key(album));
+            albums.iter().map(|a| a.album()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sorted(LibrarySort::RecentlyAdded),
+            vec!["Kid A", "Abbey Road", "Help!"]
+        );
+        assert_eq!(
+            sorted(LibrarySort::Artist),
+            vec!["Help!", "Abbey Road", "Kid A"]
+        );
+        assert_eq!(
+            sorted(LibrarySort::Title),
+            vec!["Abbey Road", "Help!", "Kid A"]
+        );
+        assert_eq!(
+            sorted(LibrarySort::ReleaseYear),
+            vec!["Kid A", "Abbey Road", "Help!"]
+        );
+        // Same order in the GTK sorter
+        assert_eq!(
+            LibrarySort::Artist.compare(&help, &abbey_road),
+            Ordering::Less
+        );
+        assert_eq!(
+            LibrarySort::ReleaseYear.compare(&help, &abbey_road),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_filter_albums() {
+        let kid_a = album("Radiohead", "Kid A", 2000);
+        assert!(album_matches_filter(&kid_a, ""));
+        assert!(album_matches_filter(&kid_a, "radio"));
+        assert!(album_matches_filter(&kid_a, "kid"));
+        assert!(!album_matches_filter(&kid_a, "beatles"));
+    }
+}
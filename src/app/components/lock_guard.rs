@@ -0,0 +1,44 @@
+use std::rc::Rc;
+
+use crate::app::components::EventListener;
+use crate::app::lock::Lock;
+use crate::app::{ActionDispatcher, AppAction, AppEvent, AppModel};
+use crate::settings;
+
+fn current_lock() -> AppAction {
+    let lock = settings::is_locked().then(|| Lock {
+        allowed_playlists: settings::lock_allowed_playlists(),
+    });
+    AppAction::SetLock(lock)
+}
+
+// Keeps the lock in the state in line with the settings, and the API client read-only while it's on
+pub struct LockGuard {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl LockGuard {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+}
+
+impl EventListener for LockGuard {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Started => {
+                self.dispatcher.dispatch(current_lock());
+                let dispatcher: Rc<dyn ActionDispatcher> = Rc::from(self.dispatcher.box_clone());
+                settings::connect_lock_changed(move || dispatcher.dispatch(current_lock()));
+            }
+            AppEvent::LockChanged(locked) => {
+                self.app_model.get_spotify().set_read_only(*locked);
+            }
+            _ => {}
+        }
+    }
+}
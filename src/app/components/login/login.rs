@@ -1,6 +1,8 @@
+use gettextrs::gettext;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use crate::app::components::EventListener;
@@ -21,6 +23,9 @@ mod imp {
         #[template_child]
         pub login_with_spotify_button: TemplateChild<gtk::Button>,
 
+        #[template_child]
+        pub import_credentials_button: TemplateChild<gtk::Button>,
+
         #[template_child]
         pub auth_error_container: TemplateChild<gtk::Revealer>,
     }
@@ -83,6 +88,40 @@ impl LoginWindow {
         );
     }
 
+    fn connect_import_credentials<F>(&self, on_import: F)
+    where
+        F: Fn(PathBuf) + Clone + 'static,
+    {
+        self.imp().import_credentials_button.connect_clicked(
+            clone!(@weak self as _self => move |_| {
+                _self.pick_credentials(on_import.clone());
+            }),
+        );
+    }
+
+    fn pick_credentials<F>(&self, on_import: F)
+    where
+        F: Fn(PathBuf) + 'static,
+    {
+        let filter = gtk::FileFilter::new();
+        filter.add_pattern("*.json");
+        let filters = gio::ListStore::new(gtk::FileFilter::static_type());
+        filters.append(&filter);
+        let dialog = gtk::FileDialog::builder()
+            // translators: Title of the dialog to pick the credentials.json of another client (librespot, spotifyd)
+            .title(gettext("Import credentials"))
+            // Where spotifyd keeps them by default
+            .initial_folder(&gio::File::for_path(glib::user_cache_dir()))
+            .filters(&filters)
+            .modal(true)
+            .build();
+        dialog.open(Some(self), gio::Cancellable::NONE, move |result| {
+            if let Some(path) = result.ok().and_then(|file| file.path()) {
+                on_import(path);
+            }
+        });
+    }
+
     fn show_auth_error(&self, shown: bool) {
         let widget = self.imp();
         widget.auth_error_container.set_reveal_child(shown);
@@ -111,6 +150,10 @@ impl Login {
             model.login_with_spotify();
         }));
 
+        login_window.connect_import_credentials(clone!(@weak model => move |path| {
+            model.import_credentials(path);
+        }));
+
         Self {
             parent,
             login_window,
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::time::SystemTime;
 
 use gettextrs::*;
@@ -67,4 +68,9 @@ impl LoginModel {
         self.dispatcher
             .dispatch(LoginAction::TryLogin(TryLoginAction::OAuthSpotify {}).into())
     }
+
+    pub fn import_credentials(&self, path: PathBuf) {
+        self.dispatcher
+            .dispatch(LoginAction::TryLogin(TryLoginAction::ImportCredentials(path)).into())
+    }
 }
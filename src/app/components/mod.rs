@@ -5,13 +5,13 @@ macro_rules! resource {
     };
 }
 
-use gettextrs::*;
+use futures::future::BoxFuture;
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::future::Future;
 
 use crate::api::SpotifyApiError;
-use crate::app::{state::LoginAction, ActionDispatcher, AppAction, AppEvent};
+use crate::app::{state::LoginAction, ActionDispatcher, AppAction, AppError, AppEvent, Retry};
 
 mod navigation;
 pub use navigation::*;
@@ -31,6 +31,57 @@ pub use settings::*;
 mod player_notifier;
 pub use player_notifier::PlayerNotifier;
 
+mod preview_player;
+pub use preview_player::PreviewPlayer;
+
+mod undo;
+pub use undo::{add_to_playlist, UndoHandler};
+
+mod track_opener;
+pub use track_opener::TrackOpener;
+
+mod resume;
+pub use resume::ResumeTracker;
+
+mod context_preferences;
+pub use context_preferences::ContextPreferencesApplier;
+
+mod volume_memory;
+pub use volume_memory::VolumeMemory;
+
+mod recently_viewed;
+pub use recently_viewed::RecentlyViewedTracker;
+
+mod new_releases;
+pub use new_releases::NewReleasesWatcher;
+
+mod history_recorder;
+pub use history_recorder::HistoryRecorder;
+
+mod network_watcher;
+pub use network_watcher::NetworkWatcher;
+
+mod lock_guard;
+pub use lock_guard::LockGuard;
+
+mod degraded_banner;
+pub use degraded_banner::DegradedBanner;
+
+mod blocklist;
+pub use blocklist::BlocklistKeeper;
+
+mod auto_mix;
+pub use auto_mix::AutoMix;
+
+mod refresher;
+pub use refresher::ScreenRefresher;
+
+mod playback_hooks;
+pub use playback_hooks::PlaybackHooks;
+
+mod status_stream;
+pub use status_stream::{StatusStream, STATUS_SOCKET_FLAG};
+
 mod library;
 pub use library::*;
 
@@ -40,6 +91,9 @@ pub use details::*;
 mod search;
 pub use search::*;
 
+mod quick_open;
+pub use quick_open::*;
+
 mod album;
 use album::*;
 
@@ -70,6 +124,12 @@ pub use notification::*;
 mod saved_playlists;
 pub use saved_playlists::*;
 
+mod home_shelves;
+pub use home_shelves::*;
+
+mod audiobooks;
+pub use audiobooks::*;
+
 mod playlist_details;
 pub use playlist_details::*;
 
@@ -89,6 +149,10 @@ pub mod utils;
 
 pub mod labels;
 
+pub mod share;
+
+pub mod dnd;
+
 pub mod sidebar;
 
 // without this the builder doesn't seen to know about the custom widgets
@@ -115,29 +179,58 @@ impl dyn ActionDispatcher {
         C: 'static + Send + Clone + FnOnce() -> F,
         F: Send + Future<Output = Result<Vec<AppAction>, SpotifyApiError>>,
     {
-        self.dispatch_many_async(Box::pin(async move {
-            let first_call = call.clone();
-            let result = first_call().await;
-            match result {
-                Ok(actions) => actions,
-                Err(SpotifyApiError::NoToken) => vec![],
-                Err(SpotifyApiError::InvalidToken) => {
-                    let mut retried = call().await.unwrap_or_else(|_| Vec::new());
-                    retried.insert(0, LoginAction::RefreshToken.into());
-                    retried
-                }
-                Err(err) => {
-                    error!("Spotify API error: {}", err);
-                    vec![AppAction::ShowNotification(gettext(
-                        // translators: This notification is the default message for unhandled errors. Logs refer to console output.
-                        "An error occured. Check logs for details!",
-                    ))]
-                }
-            }
-        }))
+        self.dispatch_many_async(call_spotify(call, false))
+    }
+
+    // Same as above, for calls that change something: they're only made again when the user asks
+    fn write_spotify_and_dispatch<F, C>(&self, call: C)
+    where
+        C: 'static + Send + Clone + FnOnce() -> F,
+        F: Send + Future<Output = Result<AppAction, SpotifyApiError>>,
+    {
+        self.write_spotify_and_dispatch_many(move || async { call().await.map(|a| vec![a]) })
+    }
+
+    fn write_spotify_and_dispatch_many<F, C>(&self, call: C)
+    where
+        C: 'static + Send + Clone + FnOnce() -> F,
+        F: Send + Future<Output = Result<Vec<AppAction>, SpotifyApiError>>,
+    {
+        self.dispatch_many_async(call_spotify(call, true))
     }
 }
 
+// Errors we can't deal with on our own are shown, along with a way to make the same call again
+fn call_spotify<F, C>(call: C, is_write: bool) -> BoxFuture<'static, Vec<AppAction>>
+where
+    C: 'static + Send + Clone + FnOnce() -> F,
+    F: Send + Future<Output = Result<Vec<AppAction>, SpotifyApiError>>,
+{
+    Box::pin(async move {
+        let first_call = call.clone();
+        let result = first_call().await;
+        match result {
+            Ok(actions) => actions,
+            Err(SpotifyApiError::NoToken) => vec![],
+            Err(SpotifyApiError::InvalidToken) => {
+                let mut retried = call().await.unwrap_or_else(|_| Vec::new());
+                retried.insert(0, LoginAction::RefreshToken.into());
+                retried
+            }
+            Err(err) => {
+                error!("Spotify API error: {}", err);
+                let retry = Retry::new(move || call_spotify(call.clone(), is_write));
+                let error = AppError::new(&err, retry);
+                vec![AppAction::ShowError(if is_write {
+                    error.for_write()
+                } else {
+                    error
+                })]
+            }
+        }
+    })
+}
+
 thread_local!(static CSS_ADDED: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new()));
 
 pub fn display_add_css_provider(resource: &'static str) {
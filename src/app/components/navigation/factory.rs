@@ -63,6 +63,36 @@ impl ScreenFactory {
         )
     }
 
+    pub fn make_audiobooks(&self) -> impl ListenerComponent {
+        let model = AudiobooksModel::new(Rc::clone(&self.app_model), self.dispatcher.box_clone());
+        let screen_model = DefaultHeaderBarModel::new(
+            Some(gettext("Audiobooks")),
+            None,
+            Rc::clone(&self.app_model),
+            self.dispatcher.box_clone(),
+        );
+        StandardScreen::new(
+            Audiobooks::new(self.worker.clone(), model),
+            &self.leaflet,
+            Rc::new(screen_model),
+        )
+    }
+
+    pub fn make_home_shelves(&self) -> impl ListenerComponent {
+        let model = HomeShelvesModel::new(Rc::clone(&self.app_model), self.dispatcher.box_clone());
+        let screen_model = DefaultHeaderBarModel::new(
+            Some(gettext("For you")),
+            None,
+            Rc::clone(&self.app_model),
+            self.dispatcher.box_clone(),
+        );
+        StandardScreen::new(
+            HomeShelves::new(self.worker.clone(), model),
+            &self.leaflet,
+            Rc::new(screen_model),
+        )
+    }
+
     pub fn make_now_playing(&self) -> impl ListenerComponent {
         let model = Rc::new(NowPlayingModel::new(
             Rc::clone(&self.app_model),
@@ -14,6 +14,8 @@ impl HomePane {
         let library = screen_factory.make_library();
         let saved_playlists = screen_factory.make_saved_playlists();
         let saved_tracks = screen_factory.make_saved_tracks();
+        let home_shelves = screen_factory.make_home_shelves();
+        let audiobooks = screen_factory.make_audiobooks();
         let now_playing = screen_factory.make_now_playing();
         let sidebar = screen_factory.make_sidebar(listbox);
 
@@ -34,6 +36,20 @@ impl HomePane {
             &dest.title(),
         );
 
+        let dest = SidebarDestination::ForYou;
+        stack.add_titled(
+            home_shelves.get_root_widget(),
+            Option::from(dest.id()),
+            &dest.title(),
+        );
+
+        let dest = SidebarDestination::Audiobooks;
+        stack.add_titled(
+            audiobooks.get_root_widget(),
+            Option::from(dest.id()),
+            &dest.title(),
+        );
+
         let dest = SidebarDestination::SavedPlaylists;
         stack.add_titled(
             saved_playlists.get_root_widget(),
@@ -55,6 +71,8 @@ impl HomePane {
                 Box::new(library),
                 Box::new(saved_playlists),
                 Box::new(saved_tracks),
+                Box::new(home_shelves),
+                Box::new(audiobooks),
                 Box::new(now_playing),
             ],
         }
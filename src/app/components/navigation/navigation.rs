@@ -1,10 +1,14 @@
-use gtk::traits::WidgetExt;
+use gtk::prelude::*;
 use libadwaita::NavigationDirection;
+use std::cell::RefCell;
 use std::rc::Rc;
 
-use crate::app::components::{EventListener, ListenerComponent};
-use crate::app::state::ScreenName;
+use crate::app::components::sidebar::SidebarDestination;
+use crate::app::components::utils::{find_descendant, restore_scroll};
+use crate::app::components::{Component, EventListener, ListenerComponent};
+use crate::app::state::{LoginEvent, ScreenName};
 use crate::app::{AppEvent, BrowserEvent};
+use crate::settings::NavigationSession;
 
 use super::{factory::ScreenFactory, home::HomePane, NavigationModel};
 
@@ -15,6 +19,9 @@ pub struct Navigation {
     home_listbox: gtk::ListBox,
     screen_factory: ScreenFactory,
     children: Vec<Box<dyn ListenerComponent>>,
+    // What gets saved when closing, loaded from the previous session initially
+    session: Rc<RefCell<NavigationSession>>,
+    restored: bool,
 }
 
 impl Navigation {
@@ -24,8 +31,15 @@ impl Navigation {
         navigation_stack: gtk::Stack,
         home_listbox: gtk::ListBox,
         screen_factory: ScreenFactory,
+        session: NavigationSession,
     ) -> Self {
         let model = Rc::new(model);
+        let session = Rc::new(RefCell::new(session));
+
+        navigation_stack.connect_unrealize(clone!(@strong session => move |_| {
+            debug!("saving navigation");
+            session.borrow().save();
+        }));
 
         leaflet.connect_folded_notify(
             clone!(@weak model => move |leaflet| {
@@ -50,14 +64,72 @@ impl Navigation {
             home_listbox,
             screen_factory,
             children: vec![],
+            session,
+            restored: false,
         }
     }
 
     fn make_home(&self) -> Box<dyn ListenerComponent> {
-        Box::new(HomePane::new(
-            self.home_listbox.clone(),
-            &self.screen_factory,
-        ))
+        let home = HomePane::new(self.home_listbox.clone(), &self.screen_factory);
+        if let Some(stack) = home.get_root_widget().downcast_ref::<gtk::Stack>() {
+            let pages = [
+                SidebarDestination::Library,
+                SidebarDestination::SavedTracks,
+                SidebarDestination::ForYou,
+                SidebarDestination::Audiobooks,
+                SidebarDestination::SavedPlaylists,
+                SidebarDestination::NowPlaying,
+            ];
+            for page in pages.iter().map(|dest| dest.id()) {
+                if let Some(widget) = stack.child_by_name(page) {
+                    self.track_scroll(&widget, page.to_string());
+                }
+            }
+        }
+        Box::new(home)
+    }
+
+    // Remembers how far down a screen was scrolled, and scrolls back there if it was known already
+    fn track_scroll(&self, widget: &gtk::Widget, identifier: String) {
+        let Some(scrolled_window) = find_descendant::<gtk::ScrolledWindow>(widget) else {
+            return;
+        };
+        let adjustment = scrolled_window.vadjustment();
+        let offset = self
+            .session
+            .borrow()
+            .scroll_offsets
+            .get(&identifier)
+            .copied();
+        if let Some(offset) = offset.filter(|o| *o > 0.0) {
+            restore_scroll(&adjustment, offset);
+        }
+        let session = Rc::clone(&self.session);
+        adjustment.connect_value_changed(move |adj| {
+            session
+                .borrow_mut()
+                .scroll_offsets
+                .insert(identifier.clone(), adj.value());
+        });
+    }
+
+    fn restore_session(&mut self) {
+        if self.restored {
+            return;
+        }
+        self.restored = true;
+        let session = self.session.borrow();
+        self.model.restore(&session.home_page, &session.screens);
+    }
+
+    fn update_session(&self) {
+        let screens = self.model.screen_identifiers();
+        let mut session = self.session.borrow_mut();
+        // Offsets of screens that were closed are useless, but home pages (which can't be pushed) stay
+        session
+            .scroll_offsets
+            .retain(|id, _| ScreenName::from_identifier(id).is_none() || screens.contains(id));
+        session.screens = screens;
     }
 
     fn show_navigation(&self) {
@@ -82,6 +154,9 @@ impl Navigation {
 
         let widget = component.get_root_widget().clone();
         self.children.push(component);
+        if !matches!(name, ScreenName::Home) {
+            self.track_scroll(&widget, name.identifier().into_owned());
+        }
 
         self.leaflet.navigate(NavigationDirection::Forward);
         self.navigation_stack
@@ -123,20 +198,27 @@ impl EventListener for Navigation {
             AppEvent::Started => {
                 self.push_screen(&ScreenName::Home);
             }
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_)) => {
+                self.restore_session();
+            }
             AppEvent::BrowserEvent(BrowserEvent::NavigationPushed(name)) => {
                 self.push_screen(name);
+                self.update_session();
             }
             AppEvent::BrowserEvent(BrowserEvent::NavigationHidden(false)) => {
                 self.show_navigation();
             }
             AppEvent::BrowserEvent(BrowserEvent::NavigationPopped) => {
                 self.pop();
+                self.update_session();
             }
             AppEvent::BrowserEvent(BrowserEvent::NavigationPoppedTo(name)) => {
                 self.pop_to(name);
+                self.update_session();
             }
-            AppEvent::BrowserEvent(BrowserEvent::HomeVisiblePageChanged(_)) => {
+            AppEvent::BrowserEvent(BrowserEvent::HomeVisiblePageChanged(page)) => {
                 self.leaflet.navigate(NavigationDirection::Forward);
+                self.session.borrow_mut().home_page = page.to_string();
             }
             _ => {}
         };
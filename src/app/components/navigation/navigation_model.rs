@@ -1,5 +1,6 @@
+use crate::app::components::sidebar::SidebarDestination;
 use crate::app::state::ScreenName;
-use crate::app::{ActionDispatcher, AppModel, BrowserAction};
+use crate::app::{ActionDispatcher, AppAction, AppModel, BrowserAction};
 use std::ops::Deref;
 use std::rc::Rc;
 
@@ -28,4 +29,40 @@ impl NavigationModel {
     pub fn children_count(&self) -> usize {
         self.app_model.get_state().browser.count()
     }
+
+    pub fn screen_identifiers(&self) -> Vec<String> {
+        self.app_model
+            .get_state()
+            .browser
+            .screen_names()
+            .map(|name| name.identifier().into_owned())
+            .collect()
+    }
+
+    // Gets back to the screens of a previous session, stuff that can't be understood is skipped
+    pub fn restore(&self, home_page: &str, screens: &[String]) {
+        let home_page = [
+            SidebarDestination::Library,
+            SidebarDestination::SavedTracks,
+            SidebarDestination::ForYou,
+            SidebarDestination::Audiobooks,
+            SidebarDestination::SavedPlaylists,
+            SidebarDestination::NowPlaying,
+        ]
+        .iter()
+        .map(|dest| dest.id())
+        .find(|id| *id == home_page);
+        let screens = screens
+            .iter()
+            .filter_map(|identifier| ScreenName::from_identifier(identifier))
+            .filter(|name| !matches!(name, ScreenName::Home));
+
+        let actions: Vec<AppAction> = home_page
+            .map(BrowserAction::SetHomeVisiblePage)
+            .into_iter()
+            .chain(screens.map(BrowserAction::NavigationPush))
+            .map(|action| action.into())
+            .collect();
+        self.dispatcher.dispatch_many(actions);
+    }
 }
@@ -0,0 +1,160 @@
+use gettextrs::gettext;
+use gio::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::app::components::EventListener;
+use crate::app::health::Backend;
+use crate::app::state::{LoginCompletedEvent, LoginEvent, PlaybackEvent, SettingsAction};
+use crate::app::{ActionDispatcher, AppAction, AppError, AppEvent, AppModel, ErrorKind, Retry};
+
+// Failed loads worth trying again once we're back online, past that it's probably not worth it
+// (the user is told that some are left out)
+const MAX_PENDING_RETRIES: usize = 20;
+
+// Keeps an eye on the network we're on, for the settings that depend on it (the bitrate, so far),
+// and to pick things up where they failed once we're back online
+pub struct NetworkWatcher {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+    // Changing the bitrate means starting a new player, which would cut the current song short:
+    // it waits for the next one
+    pending_reload: Rc<Cell<bool>>,
+    pending_retries: Vec<Retry>,
+    dropped_retries: usize,
+    web_api_down: bool,
+    _monitor: gio::NetworkMonitor,
+}
+
+impl NetworkWatcher {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        let monitor = gio::NetworkMonitor::default();
+        let pending_reload = Rc::new(Cell::new(false));
+
+        let metered_dispatcher = dispatcher.box_clone();
+        monitor.connect_network_metered_notify(
+            clone!(@weak app_model, @weak pending_reload => move |monitor| {
+                debug!("metered network: {}", monitor.is_network_metered());
+                if app_model.get_state().playback.current_song_id().is_some() {
+                    pending_reload.set(true);
+                } else {
+                    metered_dispatcher.dispatch(SettingsAction::ChangeSettings.into());
+                }
+            }),
+        );
+
+        // network-changed fires for pretty much anything, we only care about going back online
+        let was_available = Cell::new(monitor.is_network_available());
+        let restored_dispatcher = dispatcher.box_clone();
+        monitor.connect_network_changed(move |_, available| {
+            let was_available = was_available.replace(available);
+            if available && !was_available {
+                info!("Network is back");
+                restored_dispatcher.dispatch(AppAction::NetworkRestored);
+            }
+        });
+
+        Self {
+            app_model,
+            dispatcher,
+            pending_reload,
+            pending_retries: vec![],
+            dropped_retries: 0,
+            web_api_down: false,
+            _monitor: monitor,
+        }
+    }
+
+    // Likes and playlist edits made while offline
+    fn replay_pending_writes(&self) {
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .write_spotify_and_dispatch_many(move || async move {
+                let dropped = api.replay_pending_writes().await?;
+                if dropped == 0 {
+                    return Ok(vec![]);
+                }
+                Ok(vec![AppAction::ShowNotification(gettext(
+                    // translators: This notification is shown when, once back online, some of the changes made while offline turned out to be impossible to make (for instance, the playlist was deleted in the meantime).
+                    "Some changes made while offline could not be applied",
+                ))])
+            });
+    }
+
+    // Failing while Spotify is down doesn't say much about the request itself.
+    // Writes aren't made again behind the user's back, they might have gone through
+    fn is_worth_retrying(&self, error: &AppError) -> bool {
+        !error.is_write
+            && (error.kind == ErrorKind::Network
+                || (error.kind == ErrorKind::Other
+                    && !self.app_model.get_state().health().is_up(Backend::WebApi)))
+    }
+
+    fn retry_failed_loads(&mut self) {
+        for retry in self.pending_retries.drain(..) {
+            self.dispatcher.dispatch_many_async(retry.run());
+        }
+        if std::mem::take(&mut self.dropped_retries) > 0 {
+            self.dispatcher
+                .dispatch(AppAction::ShowNotification(gettext(
+                    // translators: This notification is shown when, once back online, not everything that failed to load in the meantime could be loaded again. Refreshing (Ctrl+R) fixes that.
+                    "Some pages could not be loaded again, refresh them to see everything",
+                )));
+        }
+    }
+}
+
+impl EventListener for NetworkWatcher {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::PlaybackEvent(
+                PlaybackEvent::TrackChanged(_) | PlaybackEvent::PlaybackStopped,
+            ) => {
+                if self.pending_reload.take() {
+                    self.dispatcher
+                        .dispatch(SettingsAction::ChangeSettings.into());
+                }
+            }
+            AppEvent::ErrorShown(error) if self.is_worth_retrying(error) => {
+                if let Some(retry) = error.retry.clone() {
+                    if self.pending_retries.len() >= MAX_PENDING_RETRIES {
+                        self.pending_retries.remove(0);
+                        self.dropped_retries += 1;
+                    }
+                    self.pending_retries.push(retry);
+                }
+            }
+            // Might as well catch up with what was left from last time
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(
+                LoginCompletedEvent::Password(creds) | LoginCompletedEvent::Token(creds),
+            )) => {
+                self.app_model
+                    .get_spotify()
+                    .set_user(Some(creds.username.clone()));
+                self.replay_pending_writes();
+                self.retry_failed_loads();
+            }
+            // Whatever failed was loaded for someone else
+            AppEvent::LoginEvent(LoginEvent::LogoutCompleted) => {
+                self.app_model.get_spotify().set_user(None);
+                self.pending_retries.clear();
+                self.dropped_retries = 0;
+            }
+            AppEvent::NetworkRestored => {
+                self.replay_pending_writes();
+                self.retry_failed_loads();
+            }
+            // Same as above, but it was Spotify that was away
+            AppEvent::HealthChanged(health) => {
+                let was_down = self.web_api_down;
+                self.web_api_down = !health.is_up(Backend::WebApi);
+                if was_down && !self.web_api_down {
+                    info!("Spotify is back");
+                    self.replay_pending_writes();
+                    self.retry_failed_loads();
+                }
+            }
+            _ => {}
+        }
+    }
+}
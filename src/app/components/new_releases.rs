@@ -0,0 +1,348 @@
+use gio::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app::components::{labels, EventListener};
+use crate::app::kv_store::KeyValueStore;
+use crate::app::models::{AlbumDescription, NewRelease};
+use crate::app::state::{LoginCompletedEvent, LoginEvent};
+use crate::app::{ActionDispatcher, AppEvent, AppModel, BrowserAction, BrowserEvent, Worker};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+// How long a release stays on the home page
+const SHOWN_FOR_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+// Newest first, of the albums and of the singles, this is plenty for a day's worth
+const CHECKED_RELEASES: usize = 50;
+
+// An artist whose new releases we were asked about
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct WatchedArtist {
+    name: String,
+    // YYYY-MM-DD, anything released before is old news
+    since: String,
+    // Found already, so that they're only notified once (only those still among the latest releases)
+    seen: Vec<String>,
+}
+
+// For each user, by artist id
+thread_local!(static WATCHED: RefCell<KeyValueStore<HashMap<String, WatchedArtist>>> = RefCell::new(KeyValueStore::open("watched-artists")));
+// For each user, by album id
+thread_local!(static FOUND: RefCell<KeyValueStore<HashMap<String, NewRelease>>> = RefCell::new(KeyValueStore::open("new-releases")));
+// Whose artists those are, nobody's until logged in
+thread_local!(static USER: RefCell<Option<String>> = RefCell::new(None));
+
+fn current_user() -> Option<String> {
+    USER.with(|user| user.borrow().clone())
+}
+
+fn watched<T>(f: impl FnOnce(&HashMap<String, WatchedArtist>) -> T) -> Option<T> {
+    let user = current_user()?;
+    WATCHED.with(|store| store.borrow().get(&user).map(f))
+}
+
+// Saved right after
+fn update_watched<T>(f: impl FnOnce(&mut HashMap<String, WatchedArtist>) -> T) -> Option<T> {
+    let user = current_user()?;
+    WATCHED.with(|store| {
+        let mut store = store.borrow_mut();
+        let mut watched = store.remove(&user).unwrap_or_default();
+        let result = f(&mut watched);
+        store.set(user, watched);
+        if let Err(e) = store.save() {
+            warn!("Could not save the watched artists: {}", e);
+        }
+        Some(result)
+    })
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn today() -> String {
+    glib::DateTime::now_local()
+        .and_then(|now| now.format("%F"))
+        .map(|today| today.to_string())
+        .unwrap_or_default()
+}
+
+pub fn is_watched(artist_id: &str) -> bool {
+    watched(|watched| watched.contains_key(artist_id)).unwrap_or(false)
+}
+
+// Toggled from the artist page
+pub fn set_watched(artist_id: &str, name: &str, watch: bool) {
+    update_watched(|watched| {
+        if watch {
+            watched.insert(
+                artist_id.to_string(),
+                WatchedArtist {
+                    name: name.to_string(),
+                    since: today(),
+                    seen: vec![],
+                },
+            );
+        } else {
+            watched.remove(artist_id);
+        }
+    });
+}
+
+// Dates that only have a year (or a month) can't tell, those are left out rather than notified every year
+fn is_new(watched: &WatchedArtist, album: &AlbumDescription) -> bool {
+    let Some(date) = album.release_date.as_deref() else {
+        return false;
+    };
+    date.len() == watched.since.len()
+        && date >= watched.since.as_str()
+        && !watched.seen.contains(&album.id)
+}
+
+fn shown_releases() -> Vec<NewRelease> {
+    let Some(user) = current_user() else {
+        return vec![];
+    };
+    let oldest = now_ms() - SHOWN_FOR_MS;
+    FOUND.with(|store| {
+        let mut releases: Vec<NewRelease> = store
+            .borrow()
+            .get(&user)
+            .into_iter()
+            .flat_map(|found| found.values())
+            .filter(|r| r.found_at >= oldest)
+            .cloned()
+            .collect();
+        releases.sort_by(|a, b| b.release_date.cmp(&a.release_date));
+        releases
+    })
+}
+
+// What isn't among the latest releases anymore won't come back as new
+fn forget_older(watched: &mut WatchedArtist, latest: &[AlbumDescription]) {
+    watched
+        .seen
+        .retain(|id| latest.iter().any(|album| &album.id == id));
+}
+
+fn notify(release: &NewRelease) {
+    let Some(app) = gio::Application::default() else {
+        return;
+    };
+    let notification = gio::Notification::new(&labels::new_release_label(&release.artist));
+    notification.set_body(Some(&release.title));
+    notification.set_default_action_and_target_value(
+        "app.open_album",
+        Some(&release.album_id.to_variant()),
+    );
+    app.send_notification(
+        Some(&format!("new-release-{}", release.album_id)),
+        &notification,
+    );
+}
+
+fn find_new(watched: &WatchedArtist, latest: &[AlbumDescription]) -> Vec<NewRelease> {
+    let found_at = now_ms();
+    latest
+        .iter()
+        .filter(|album| is_new(watched, album))
+        .map(|album| NewRelease {
+            album_id: album.id.clone(),
+            title: album.title.clone(),
+            artist: watched.name.clone(),
+            art: album.art.clone(),
+            release_date: album.release_date.clone().unwrap_or_default(),
+            found_at,
+        })
+        .collect()
+}
+
+// Writes down what's new for an artist among its latest releases, and returns it
+fn record(artist_id: &str, latest: &[AlbumDescription]) -> Vec<NewRelease> {
+    let releases = update_watched(|watched| {
+        // Might have been unwatched while we were looking
+        let watched = watched.get_mut(artist_id)?;
+        let releases = find_new(watched, latest);
+        forget_older(watched, latest);
+        watched
+            .seen
+            .extend(releases.iter().map(|r| r.album_id.clone()));
+        Some(releases)
+    })
+    .flatten()
+    .unwrap_or_default();
+    let Some(user) = current_user().filter(|_| !releases.is_empty()) else {
+        return releases;
+    };
+    let oldest = now_ms() - SHOWN_FOR_MS;
+    FOUND.with(|store| {
+        let mut store = store.borrow_mut();
+        let mut found = store.remove(&user).unwrap_or_default();
+        found.retain(|_, release| release.found_at >= oldest);
+        for release in &releases {
+            found.insert(release.album_id.clone(), release.clone());
+        }
+        store.set(user, found);
+        if let Err(e) = store.save() {
+            warn!("Could not save the new releases: {}", e);
+        }
+    });
+    releases
+}
+
+// Looks at the latest albums and singles of each watched artist, once logged in and then every day.
+// What's new gets a desktop notification, and shows up on the home page for a while.
+pub struct NewReleasesWatcher {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+    worker: Worker,
+    scheduled: bool,
+}
+
+impl NewReleasesWatcher {
+    pub fn new(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+        worker: Worker,
+    ) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+            worker,
+            scheduled: false,
+        }
+    }
+
+    fn dispatch_shown(dispatcher: &dyn ActionDispatcher) {
+        dispatcher.dispatch(BrowserAction::SetNewReleases(shown_releases()).into());
+    }
+
+    fn check(app_model: &AppModel, dispatcher: &dyn ActionDispatcher, worker: &Worker) {
+        let artists: Vec<String> =
+            watched(|watched| watched.keys().cloned().collect()).unwrap_or_default();
+        if artists.is_empty() {
+            return;
+        }
+        let api = app_model.get_spotify();
+        let dispatcher = dispatcher.box_clone();
+        worker.send_local_task(async move {
+            let mut found = false;
+            for id in artists {
+                // Both are needed, what's seen is forgotten once it's not among them anymore
+                let latest = futures::try_join!(
+                    api.get_artist_albums(&id, 0, CHECKED_RELEASES),
+                    api.get_artist_singles(&id, 0, CHECKED_RELEASES)
+                );
+                let latest = match latest {
+                    Ok((albums, singles)) => [albums, singles].concat(),
+                    Err(e) => {
+                        warn!("Could not check for new releases: {}", e);
+                        continue;
+                    }
+                };
+                for release in record(&id, &latest) {
+                    notify(&release);
+                    found = true;
+                }
+            }
+            if found {
+                Self::dispatch_shown(dispatcher.as_ref());
+            }
+        });
+    }
+
+    fn schedule(&mut self) {
+        if self.scheduled {
+            return;
+        }
+        self.scheduled = true;
+        let app_model = Rc::clone(&self.app_model);
+        let dispatcher = self.dispatcher.box_clone();
+        let worker = self.worker.clone();
+        glib::timeout_add_local(CHECK_INTERVAL, move || {
+            Self::check(&app_model, dispatcher.as_ref(), &worker);
+            glib::Continue(true)
+        });
+    }
+}
+
+impl EventListener for NewReleasesWatcher {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(
+                LoginCompletedEvent::Password(creds) | LoginCompletedEvent::Token(creds),
+            )) => {
+                USER.with(|user| user.replace(Some(creds.username.clone())));
+                Self::dispatch_shown(self.dispatcher.as_ref());
+                Self::check(&self.app_model, self.dispatcher.as_ref(), &self.worker);
+                self.schedule();
+            }
+            AppEvent::LoginEvent(LoginEvent::LogoutCompleted) => {
+                USER.with(|user| user.replace(None));
+                Self::dispatch_shown(self.dispatcher.as_ref());
+            }
+            AppEvent::BrowserEvent(BrowserEvent::ArtistUnfollowed(id)) if is_watched(id) => {
+                set_watched(id, "", false);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::models::SongBatch;
+
+    fn album(id: &str, release_date: Option<&str>) -> AlbumDescription {
+        AlbumDescription {
+            id: id.to_string(),
+            title: id.to_string(),
+            artists: vec![],
+            release_date: release_date.map(|d| d.to_string()),
+            art: None,
+            songs: SongBatch::empty(),
+            is_liked: false,
+        }
+    }
+
+    #[test]
+    fn test_is_new() {
+        let watched = WatchedArtist {
+            name: "Artist".to_string(),
+            since: "2024-05-01".to_string(),
+            seen: vec!["seen".to_string()],
+        };
+        assert!(is_new(&watched, &album("a", Some("2024-05-01"))));
+        assert!(is_new(&watched, &album("b", Some("2024-06-12"))));
+        assert!(!is_new(&watched, &album("c", Some("2024-04-30"))));
+        assert!(!is_new(&watched, &album("seen", Some("2024-06-12"))));
+        assert!(!is_new(&watched, &album("d", Some("2025"))));
+        assert!(!is_new(&watched, &album("e", None)));
+    }
+
+    #[test]
+    fn test_seen_only_keeps_the_latest() {
+        let mut watched = WatchedArtist {
+            name: "Artist".to_string(),
+            since: "2024-05-01".to_string(),
+            seen: vec!["old".to_string(), "single".to_string()],
+        };
+        let latest = [
+            album("new", Some("2024-06-12")),
+            album("single", Some("2024-05-20")),
+        ];
+        let found = find_new(&watched, &latest);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].album_id, "new");
+
+        forget_older(&mut watched, &latest);
+        assert_eq!(watched.seen, vec!["single".to_string()]);
+    }
+}
@@ -1,15 +1,29 @@
-use crate::app::components::EventListener;
-use crate::app::AppEvent;
+use crate::app::components::{labels, EventListener};
+use crate::app::health::Backend;
+use crate::app::lock;
+use crate::app::state::Undoable;
+use crate::app::{ActionDispatcher, AppError, AppEvent, ErrorKind};
 use gettextrs::*;
 use glib::ToVariant;
 
 pub struct Notification {
     toast_overlay: libadwaita::ToastOverlay,
+    dispatcher: Box<dyn ActionDispatcher>,
+    // The banner says it already, and what failed to load is loaded again once it's back
+    // (what failed to change something is still shown)
+    web_api_down: bool,
 }
 
 impl Notification {
-    pub fn new(toast_overlay: libadwaita::ToastOverlay) -> Self {
-        Self { toast_overlay }
+    pub fn new(
+        toast_overlay: libadwaita::ToastOverlay,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Self {
+        Self {
+            toast_overlay,
+            dispatcher,
+            web_api_down: false,
+        }
     }
 
     fn show(&self, content: &str) {
@@ -20,6 +34,40 @@ impl Notification {
         self.toast_overlay.add_toast(toast);
     }
 
+    fn show_error(&self, error: &AppError) {
+        let is_retried = !error.is_write && error.retry.is_some();
+        if self.web_api_down
+            && is_retried
+            && matches!(error.kind, ErrorKind::Network | ErrorKind::Other)
+        {
+            return;
+        }
+        let message = match error.kind {
+            // translators: This notification is shown when something couldn't be loaded because the user isn't logged in anymore.
+            ErrorKind::Auth => gettext("Your session has expired, please log in again"),
+            // translators: This notification is shown when Spotify refuses to do something because the user's account isn't allowed to (for instance, editing someone else's playlist).
+            ErrorKind::Forbidden => gettext("Spotify does not allow this account to do that"),
+            // translators: This notification is shown when something couldn't be loaded because of connectivity issues.
+            ErrorKind::Network => gettext("Could not reach Spotify, check your connection"),
+            // translators: This notification is shown when Spotify refuses requests because too many were made.
+            ErrorKind::RateLimited => gettext("Spotify is busy, try again in a moment"),
+            ErrorKind::Locked => lock::refused_message(),
+            // translators: This notification is the default message for unhandled errors. Logs refer to console output.
+            ErrorKind::Other => gettext("An error occured. Check logs for details!"),
+        };
+        let toast = libadwaita::Toast::builder()
+            .title(message)
+            .timeout(8)
+            .build();
+        if let Some(retry) = error.retry.clone() {
+            // translators: This is a label in the notification shown when something failed to load. If it is clicked, it's loaded again.
+            toast.set_button_label(Some(&gettext("Retry")));
+            let dispatcher = self.dispatcher.box_clone();
+            toast.connect_button_clicked(move |_| dispatcher.dispatch_many_async(retry.run()));
+        }
+        self.toast_overlay.add_toast(toast);
+    }
+
     fn show_playlist_created(&self, id: &str) {
         // translators: This is a notification that pop ups when a new playlist is created. It includes the name of that playlist.
         let message = gettext("New playlist created.");
@@ -34,14 +82,56 @@ impl Notification {
             .build();
         self.toast_overlay.add_toast(toast);
     }
+
+    fn show_undoable(&self, undoable: &Undoable) {
+        let message = match undoable {
+            Undoable::TracksRemovedFromPlaylist { uris, .. } => {
+                labels::n_songs_removed_label(uris.len())
+            }
+            Undoable::TracksUnsaved(tracks) => labels::n_songs_removed_label(tracks.len()),
+            // translators: This is a notification shown after removing an album from the library. It can be undone.
+            Undoable::AlbumUnsaved(_) => gettext("Album removed from your library"),
+        };
+        // translators: This is a label in the notification shown after removing something. If it is clicked, the removal is reverted.
+        let label = gettext("Undo");
+        let toast = libadwaita::Toast::builder()
+            .title(message)
+            .timeout(6)
+            .action_name("app.undo")
+            .button_label(label)
+            .build();
+        self.toast_overlay.add_toast(toast);
+    }
+
+    fn show_undone(&self) {
+        // translators: This is a notification shown after undoing a removal. It can be redone.
+        let message = gettext("Removal undone");
+        // translators: This is a label in the notification shown after undoing a removal. If it is clicked, the removal is made again.
+        let label = gettext("Redo");
+        let toast = libadwaita::Toast::builder()
+            .title(message)
+            .timeout(6)
+            .action_name("app.redo")
+            .button_label(label)
+            .build();
+        self.toast_overlay.add_toast(toast);
+    }
 }
 
 impl EventListener for Notification {
     fn on_event(&mut self, event: &AppEvent) {
         if let AppEvent::NotificationShown(content) = event {
             self.show(content)
+        } else if let AppEvent::ErrorShown(error) = event {
+            self.show_error(error)
         } else if let AppEvent::PlaylistCreatedNotificationShown(id) = event {
             self.show_playlist_created(id)
+        } else if let AppEvent::UndoableActionDone(undoable) = event {
+            self.show_undoable(undoable)
+        } else if let AppEvent::UndoRequested(_) = event {
+            self.show_undone()
+        } else if let AppEvent::HealthChanged(health) = event {
+            self.web_api_down = !health.is_up(Backend::WebApi);
         }
     }
 }
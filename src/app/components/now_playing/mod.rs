@@ -4,3 +4,5 @@ pub use now_playing::*;
 
 mod now_playing_model;
 pub use now_playing_model::*;
+
+mod visualizer;
@@ -1,13 +1,18 @@
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
+use libadwaita::prelude::*;
 use std::rc::Rc;
 
+use super::visualizer::attach_visualizer;
 use super::NowPlayingModel;
+use crate::app::components::dnd::{attach_link_drop_target, attach_link_paste_shortcut};
+use crate::app::components::utils::connect_prefetch;
 use crate::app::components::{
-    Component, DeviceSelector, DeviceSelectorWidget, EventListener, HeaderBarComponent,
+    labels, Component, DeviceSelector, DeviceSelectorWidget, EventListener, HeaderBarComponent,
     HeaderBarWidget, Playlist,
 };
+use crate::app::models::SongDescription;
 use crate::app::state::PlaybackEvent;
 use crate::app::{AppEvent, Worker};
 
@@ -29,6 +34,15 @@ mod imp {
 
         #[template_child]
         pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+
+        #[template_child]
+        pub history_expander: TemplateChild<gtk::Expander>,
+
+        #[template_child]
+        pub history_list: TemplateChild<gtk::ListBox>,
+
+        #[template_child]
+        pub visualizer: TemplateChild<gtk::DrawingArea>,
     }
 
     #[glib::object_subclass]
@@ -49,6 +63,7 @@ mod imp {
     impl ObjectImpl for NowPlayingWidget {
         fn constructed(&self) {
             self.parent_constructed();
+            attach_visualizer(&self.visualizer);
         }
     }
 
@@ -69,13 +84,7 @@ impl NowPlayingWidget {
     where
         F: Fn() + 'static,
     {
-        self.imp()
-            .scrolled_window
-            .connect_edge_reached(move |_, pos| {
-                if let gtk::PositionType::Bottom = pos {
-                    f()
-                }
-            });
+        connect_prefetch(&self.imp().scrolled_window, f);
     }
 
     fn song_list_widget(&self) -> &gtk::ListView {
@@ -89,6 +98,34 @@ impl NowPlayingWidget {
     fn device_selector_widget(&self) -> &DeviceSelectorWidget {
         self.imp().device_selector.as_ref()
     }
+
+    // It's only a handful of rows, so they're simply rebuilt every time
+    fn set_history<F>(&self, songs: Vec<SongDescription>, on_replay: F)
+    where
+        F: Fn(SongDescription) + Clone + 'static,
+    {
+        let widget = self.imp();
+        let list = &widget.history_list;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        widget.history_expander.set_visible(!songs.is_empty());
+
+        for song in songs {
+            let row = libadwaita::ActionRow::builder()
+                .title(glib::markup_escape_text(&song.title))
+                .subtitle(glib::markup_escape_text(&song.artists_name()))
+                .activatable(true)
+                .build();
+            let icon = gtk::Image::from_icon_name("media-playback-start-symbolic");
+            icon.set_tooltip_text(Some(&*labels::PLAY_AGAIN));
+            row.add_suffix(&icon);
+
+            let on_replay = on_replay.clone();
+            row.connect_activated(move |_| on_replay(song.clone()));
+            list.append(&row);
+        }
+    }
 }
 
 pub struct NowPlaying {
@@ -98,9 +135,25 @@ pub struct NowPlaying {
 }
 
 impl NowPlaying {
+    fn update_history(&self) {
+        self.widget.set_history(
+            self.model.history(),
+            clone!(@weak self.model as model => move |song| model.replay(song)),
+        );
+    }
+
     pub fn new(model: Rc<NowPlayingModel>, worker: Worker, leaflet: &libadwaita::Leaflet) -> Self {
         let widget = NowPlayingWidget::new();
 
+        attach_link_drop_target(
+            &widget,
+            clone!(@weak model => move |links| model.queue_links(links)),
+        );
+        attach_link_paste_shortcut(
+            &widget,
+            clone!(@weak model => move |links| model.queue_links(links)),
+        );
+
         widget.connect_bottom_edge(clone!(@weak model => move || {
             model.load_more();
         }));
@@ -113,6 +166,8 @@ impl NowPlaying {
 
         let headerbar_widget = widget.headerbar_widget();
         headerbar_widget.bind_to_leaflet(leaflet);
+        // The queue isn't cached, there's nothing to revalidate
+        headerbar_widget.set_refresh_possible(false);
         let headerbar = Box::new(HeaderBarComponent::new(
             headerbar_widget.clone(),
             model.to_headerbar_model(),
@@ -145,6 +200,7 @@ impl EventListener for NowPlaying {
     fn on_event(&mut self, event: &AppEvent) {
         if let AppEvent::PlaybackEvent(PlaybackEvent::TrackChanged(_)) = event {
             self.model.load_more();
+            self.update_history();
         }
         self.broadcast_event(event);
     }
@@ -3,11 +3,12 @@ use gio::SimpleActionGroup;
 use std::ops::Deref;
 use std::rc::Rc;
 
+use crate::app::components::dnd::songs_for_links;
 use crate::app::components::{
     labels, DeviceSelectorModel, HeaderBarModel, PlaylistModel, SimpleHeaderBarModel,
     SimpleHeaderBarModelWrapper,
 };
-use crate::app::models::{SongDescription, SongListModel};
+use crate::app::models::{SongDescription, SongListModel, SpotifyLink};
 use crate::app::state::Device;
 use crate::app::state::{
     PlaybackAction, PlaybackState, SelectionAction, SelectionContext, SelectionState,
@@ -27,10 +28,29 @@ impl NowPlayingModel {
         }
     }
 
+    // Links dropped or pasted on the queue get queued
+    pub fn queue_links(&self, links: Vec<SpotifyLink>) {
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .call_spotify_and_dispatch(move || async move {
+                let songs = songs_for_links(api, links).await?;
+                Ok(PlaybackAction::Queue(songs).into())
+            });
+    }
+
     fn queue(&self) -> impl Deref<Target = PlaybackState> + '_ {
         self.app_model.map_state(|s| &s.playback)
     }
 
+    pub fn history(&self) -> Vec<SongDescription> {
+        self.queue().history().cloned().collect()
+    }
+
+    pub fn replay(&self, song: SongDescription) {
+        self.dispatcher
+            .dispatch(PlaybackAction::Replay(song).into());
+    }
+
     pub fn load_more(&self) -> Option<()> {
         let queue = self.queue();
         let loader = self.app_model.get_batch_loader();
@@ -40,7 +60,7 @@ impl NowPlayingModel {
         self.dispatcher.dispatch_async(Box::pin(async move {
             loader
                 .query(query, |source, song_batch| {
-                    PlaybackAction::LoadPagedSongs(source, song_batch).into()
+                    PlaybackAction::AppendPagedSongs(source, song_batch).into()
                 })
                 .await
         }));
@@ -102,7 +122,11 @@ impl PlaylistModel for NowPlayingModel {
         }
         group.add_action(&song.make_album_action(self.dispatcher.box_clone(), None));
         group.add_action(&song.make_link_action(None));
+        group.add_action(&song.make_uri_action(None));
         group.add_action(&song.make_dequeue_action(self.dispatcher.box_clone(), None));
+        for block in song.make_block_actions(self.dispatcher.box_clone()) {
+            group.add_action(&block);
+        }
 
         Some(group.upcast())
     }
@@ -122,7 +146,9 @@ impl PlaylistModel for NowPlayingModel {
         }
 
         menu.append(Some(&*labels::COPY_LINK), Some("song.copy_link"));
+        menu.append(Some(&*labels::COPY_URI), Some("song.copy_uri"));
         menu.append(Some(&*labels::REMOVE_FROM_QUEUE), Some("song.dequeue"));
+        menu.append_section(None, &song.make_block_menu());
 
         Some(menu.upcast())
     }
@@ -141,6 +167,14 @@ impl PlaylistModel for NowPlayingModel {
             .dispatch(SelectionAction::Deselect(vec![id.to_string()]).into());
     }
 
+    fn update_selection(&self, action: SelectionAction) {
+        self.dispatcher.dispatch(action.into());
+    }
+
+    fn load_remaining(&self) {
+        self.load_more();
+    }
+
     fn enable_selection(&self) -> bool {
         self.dispatcher
             .dispatch(AppAction::EnableSelection(self.current_selection_context()));
@@ -165,10 +199,4 @@ impl SimpleHeaderBarModel for NowPlayingModel {
     fn selection_context(&self) -> Option<SelectionContext> {
         Some(self.current_selection_context())
     }
-
-    fn select_all(&self) {
-        let songs: Vec<SongDescription> = self.queue().songs().collect();
-        self.dispatcher
-            .dispatch(SelectionAction::Select(songs).into());
-    }
 }
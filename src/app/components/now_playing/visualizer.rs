@@ -0,0 +1,53 @@
+use gtk::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::player::spectrum::{self, BANDS};
+use crate::settings;
+
+// How much of its height a bar keeps from one frame to the next, so that it falls back smoothly
+const DECAY: f32 = 0.9;
+const GAP: f64 = 3.0;
+
+// Bars for the levels the player works out, which it only does while they're on screen
+pub fn attach_visualizer(area: &gtk::DrawingArea) {
+    let shown = Rc::new(RefCell::new([0f32; BANDS]));
+
+    area.set_draw_func(clone!(@strong shown => move |area, cr, width, height| {
+        let color = area.color();
+        cr.set_source_rgba(
+            color.red() as f64,
+            color.green() as f64,
+            color.blue() as f64,
+            color.alpha() as f64,
+        );
+        let (width, height) = (width as f64, height as f64);
+        let bar_width = (width - GAP * (BANDS - 1) as f64) / BANDS as f64;
+        for (i, level) in shown.borrow().iter().enumerate() {
+            let bar_height = f64::max(*level as f64 * height, 1.0);
+            cr.rectangle(
+                i as f64 * (bar_width + GAP),
+                height - bar_height,
+                bar_width,
+                bar_height,
+            );
+        }
+        let _ = cr.fill();
+    }));
+
+    area.add_tick_callback(move |area, _| {
+        for (shown, latest) in shown.borrow_mut().iter_mut().zip(spectrum::levels()) {
+            *shown = f32::max(latest, *shown * DECAY);
+        }
+        area.queue_draw();
+        glib::Continue(true)
+    });
+
+    area.connect_map(|_| spectrum::set_enabled(true));
+    area.connect_unmap(|_| spectrum::set_enabled(false));
+
+    area.set_visible(settings::visualizer_enabled());
+    settings::connect_visualizer_changed(clone!(@weak area => move |enabled| {
+        area.set_visible(enabled);
+    }));
+}
@@ -1,13 +1,19 @@
+use gio::prelude::*;
+use gio::SimpleActionGroup;
 use std::ops::Deref;
 use std::rc::Rc;
 
-use crate::app::components::EventListener;
+use crate::app::components::context_preferences;
+use crate::app::components::{labels, EventListener};
+use crate::app::listening_history::ListeningHistory;
 use crate::app::models::*;
-use crate::app::state::{PlaybackAction, PlaybackEvent, ScreenName, SelectionEvent};
+use crate::app::state::{PlaybackAction, PlaybackEvent, QueueLimits, ScreenName, SelectionEvent};
 use crate::app::{
-    ActionDispatcher, AppAction, AppEvent, AppModel, AppState, BrowserAction, Worker,
+    ActionDispatcher, AppAction, AppEvent, AppModel, AppState, BrowserAction, BrowserEvent, Worker,
 };
 
+use crate::settings;
+
 use super::playback_widget::PlaybackWidget;
 
 pub struct PlaybackModel {
@@ -37,14 +43,72 @@ impl PlaybackModel {
         self.state().playback.is_playing()
     }
 
+    fn seek_position(&self) -> u32 {
+        self.state().playback.seek_position()
+    }
+
     fn is_shuffled(&self) -> bool {
         self.state().playback.is_shuffled()
     }
 
+    fn active_remote_device(&self) -> Option<String> {
+        let state = self.state();
+        state
+            .playback
+            .active_remote_device()
+            .map(|d| d.label.clone())
+    }
+
+    // Only the local player falls back to previews
+    fn playing_previews(&self) -> bool {
+        let state = self.state();
+        state.playback.previews_only()
+            && state.playback.active_remote_device().is_none()
+            && state.playback.current_song_id().is_some()
+    }
+
     fn current_song(&self) -> Option<SongDescription> {
         self.app_model.get_state().playback.current_song()
     }
 
+    // Same entries as in the queue, minus what only makes sense there
+    fn song_menu(&self, song: &SongDescription) -> (gio::ActionGroup, gio::MenuModel) {
+        let group = SimpleActionGroup::new();
+        for view_artist in song.make_artist_actions(self.dispatcher.box_clone(), None) {
+            group.add_action(&view_artist);
+        }
+        group.add_action(&song.make_album_action(self.dispatcher.box_clone(), None));
+        group.add_action(&song.make_link_action(None));
+        group.add_action(&song.make_uri_action(None));
+
+        let menu = gio::Menu::new();
+        menu.append(Some(&*labels::VIEW_ALBUM), Some("song.view_album"));
+        for artist in song.artists.iter() {
+            menu.append(
+                Some(&labels::more_from_label(&artist.name)),
+                Some(&format!("song.view_artist_{}", artist.id)),
+            );
+        }
+        menu.append(Some(&*labels::COPY_LINK), Some("song.copy_link"));
+        menu.append(Some(&*labels::COPY_URI), Some("song.copy_uri"));
+
+        (group.upcast(), menu.upcast())
+    }
+
+    fn toggle_saved(&self, song: SongDescription, saved: bool) {
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .write_spotify_and_dispatch(move || async move {
+                if saved {
+                    api.remove_saved_tracks(vec![song.id.clone()]).await?;
+                    Ok(BrowserAction::RemoveSavedTracks(vec![song.id]).into())
+                } else {
+                    api.save_tracks(vec![song.id.clone()]).await?;
+                    Ok(BrowserAction::SaveTracks(vec![song]).into())
+                }
+            });
+    }
+
     fn play_next_song(&self) {
         self.dispatcher.dispatch(PlaybackAction::Next.into());
     }
@@ -53,16 +117,72 @@ impl PlaybackModel {
         self.dispatcher.dispatch(PlaybackAction::Previous.into());
     }
 
+    fn play_prev_track(&self) {
+        self.dispatcher
+            .dispatch(PlaybackAction::PreviousTrack.into());
+    }
+
+    fn set_restart_threshold(&self, threshold: Option<u32>) {
+        self.dispatcher
+            .dispatch(PlaybackAction::SetRestartThreshold(threshold).into());
+    }
+
+    fn set_queue_limits(&self, limits: QueueLimits) {
+        self.dispatcher
+            .dispatch(PlaybackAction::SetQueueLimits(limits).into());
+    }
+
     fn toggle_playback(&self) {
         self.dispatcher.dispatch(PlaybackAction::TogglePlay.into());
     }
 
+    // Changed from here, it's what the user wants for whatever is playing
     fn toggle_shuffle(&self) {
+        if let Some(source) = self.state().playback.current_source() {
+            context_preferences::remember_shuffled(source, !self.is_shuffled());
+        }
         self.dispatcher
             .dispatch(PlaybackAction::ToggleShuffle.into());
     }
 
+    // The mode picked last time comes back, without shuffling until asked to
+    fn restore_shuffle_mode(&self) {
+        self.apply_shuffle_mode(settings::shuffle_mode());
+    }
+
+    // Picking a way to shuffle from the menu means shuffling
+    fn set_shuffle_mode(&self, mode: ShuffleMode) {
+        settings::save_shuffle_mode(mode);
+        if let Some(source) = self.state().playback.current_source() {
+            context_preferences::remember_shuffled(source, true);
+        }
+        self.apply_shuffle_mode(mode);
+        self.dispatcher
+            .dispatch(PlaybackAction::SetShuffled(true).into());
+    }
+
+    fn apply_shuffle_mode(&self, mode: ShuffleMode) {
+        if mode == ShuffleMode::LeastRecentlyPlayed {
+            match ListeningHistory::default().read_all() {
+                Ok(records) => self.dispatcher.dispatch(
+                    PlaybackAction::SetListeningHistory(
+                        records.into_iter().map(|r| r.track_id).collect(),
+                    )
+                    .into(),
+                ),
+                Err(e) => warn!("Could not read listening history: {}", e),
+            }
+        }
+        self.dispatcher
+            .dispatch(PlaybackAction::SetShuffleMode(mode).into());
+    }
+
     fn toggle_repeat(&self) {
+        let state = self.state();
+        if let Some(source) = state.playback.current_source() {
+            context_preferences::remember_repeat(source, state.playback.repeat_mode().next());
+        }
+        drop(state);
         self.dispatcher
             .dispatch(PlaybackAction::ToggleRepeat.into());
     }
@@ -71,6 +191,11 @@ impl PlaybackModel {
         self.dispatcher
             .dispatch(PlaybackAction::Seek(position).into());
     }
+
+    fn set_volume(&self, volume: f64) {
+        self.dispatcher
+            .dispatch(PlaybackAction::SetVolume(volume).into());
+    }
 }
 
 pub struct PlaybackControl {
@@ -86,10 +211,30 @@ impl PlaybackControl {
         widget.connect_play_pause(clone!(@weak model => move || model.toggle_playback() ));
         widget.connect_next(clone!(@weak model => move || model.play_next_song()));
         widget.connect_prev(clone!(@weak model => move || model.play_prev_song()));
+        widget.connect_prev_track(clone!(@weak model => move || model.play_prev_track()));
         widget.connect_shuffle(clone!(@weak model => move || model.toggle_shuffle()));
+        widget
+            .connect_shuffle_mode(clone!(@weak model => move |mode| model.set_shuffle_mode(mode)));
         widget.connect_repeat(clone!(@weak model => move || model.toggle_repeat()));
         widget.connect_seek(clone!(@weak model => move |position| model.seek_to(position)));
+        widget
+            .connect_volume_changed(clone!(@weak model => move |volume| model.set_volume(volume)));
         widget.connect_now_playing_clicked(clone!(@weak model => move || model.go_home()));
+        widget.connect_like_clicked(clone!(@weak model => move |liked| {
+            if let Some(song) = model.current_song() {
+                model.toggle_saved(song, liked);
+            }
+        }));
+
+        model.restore_shuffle_mode();
+        model.set_restart_threshold(settings::previous_restart_threshold());
+        settings::connect_previous_restart_changed(
+            clone!(@weak model => move |threshold| model.set_restart_threshold(threshold)),
+        );
+        model.set_queue_limits(settings::queue_limits());
+        settings::connect_queue_limits_changed(
+            clone!(@weak model => move |limits| model.set_queue_limits(limits)),
+        );
 
         Self {
             model,
@@ -108,7 +253,10 @@ impl PlaybackControl {
 
     fn update_playing(&self) {
         let is_playing = self.model.is_playing();
-        self.widget.set_playing(is_playing);
+        let model = Rc::downgrade(&self.model);
+        self.widget.set_playing(is_playing, move || {
+            model.upgrade().map(|model| model.seek_position())
+        });
     }
 
     fn update_current_info(&self) {
@@ -116,12 +264,47 @@ impl PlaybackControl {
             self.widget
                 .set_title_and_artist(&song.title, &song.artists_name());
             self.widget.set_song_duration(Some(song.duration as f64));
+            self.widget.set_chapters(&song.chapters);
+            let (actions, menu) = self.model.song_menu(&song);
+            self.widget.set_song_menu(Some(&actions), Some(&menu));
             if let Some(url) = song.art {
                 self.widget.set_artwork_from_url(url, &self.worker);
             }
         } else {
             self.widget.reset_info();
         }
+        self.update_preview();
+    }
+
+    // The lookup is batched with whatever else wants to know about saved tracks at that moment
+    fn update_liked(&self) {
+        let Some(id) = self.model.current_song().map(|song| song.id) else {
+            self.widget.set_liked(None);
+            return;
+        };
+        let api = self.model.app_model.get_spotify();
+        let model = Rc::downgrade(&self.model);
+        let widget = self.widget.downgrade();
+        self.worker.send_local_task(async move {
+            let saved = api.is_track_saved(&id).await.ok();
+            let (Some(model), Some(widget)) = (model.upgrade(), widget.upgrade()) else {
+                return;
+            };
+            // The song might have changed while we were waiting
+            if model.current_song().map(|song| song.id).as_ref() == Some(&id) {
+                widget.set_liked(saved);
+            }
+        });
+    }
+
+    fn update_active_device(&self) {
+        let device = self.model.active_remote_device();
+        self.widget.set_active_device(device.as_deref());
+        self.update_preview();
+    }
+
+    fn update_preview(&self) {
+        self.widget.set_preview(self.model.playing_previews());
     }
 
     fn sync_seek(&self, pos: u32) {
@@ -142,8 +325,15 @@ impl EventListener for PlaybackControl {
             AppEvent::PlaybackEvent(PlaybackEvent::ShuffleChanged(_)) => {
                 self.update_shuffled();
             }
+            AppEvent::PlaybackEvent(PlaybackEvent::ShuffleModeChanged(mode)) => {
+                self.widget.set_shuffle_mode(*mode);
+            }
             AppEvent::PlaybackEvent(PlaybackEvent::TrackChanged(_)) => {
                 self.update_current_info();
+                self.update_liked();
+            }
+            AppEvent::BrowserEvent(BrowserEvent::SavedTracksUpdated) => {
+                self.update_liked();
             }
             AppEvent::PlaybackEvent(PlaybackEvent::PlaybackStopped) => {
                 self.update_playing();
@@ -153,9 +343,21 @@ impl EventListener for PlaybackControl {
             | AppEvent::PlaybackEvent(PlaybackEvent::TrackSeeked(pos)) => {
                 self.sync_seek(*pos);
             }
+            AppEvent::PlaybackEvent(PlaybackEvent::VolumeSet(volume))
+            | AppEvent::PlaybackEvent(PlaybackEvent::VolumeSynced(volume)) => {
+                self.widget.set_volume(*volume);
+            }
+            AppEvent::PlaybackEvent(PlaybackEvent::AvailableDevicesChanged)
+            | AppEvent::PlaybackEvent(PlaybackEvent::SwitchedDevice(_))
+            | AppEvent::PlaybackEvent(PlaybackEvent::PlaybackTransferred(_)) => {
+                self.update_active_device();
+            }
             AppEvent::SelectionEvent(SelectionEvent::SelectionModeChanged(active)) => {
                 self.widget.set_seekbar_visible(!active);
             }
+            AppEvent::PlaybackEvent(PlaybackEvent::PreviewsOnlyChanged(_)) => {
+                self.update_preview();
+            }
             _ => {}
         }
     }
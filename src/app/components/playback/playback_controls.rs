@@ -1,9 +1,26 @@
 use gettextrs::gettext;
+use gio::SimpleAction;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::{glib, CompositeTemplate};
 
-use crate::app::models::RepeatMode;
+use crate::app::components::utils::attach_context_menu;
+use crate::app::models::{RepeatMode, ShuffleMode};
+
+const SHUFFLE_MODE_ACTION: &str = "mode";
+
+fn shuffle_mode_label(mode: ShuffleMode) -> String {
+    match mode {
+        // translators: An option in the menu of the shuffle button (right click or long press): every song has the same chance to come next
+        ShuffleMode::Uniform => gettext("Shuffle"),
+        // translators: An option in the menu of the shuffle button: avoids playing the same artist twice in a row
+        ShuffleMode::ArtistSpread => gettext("Spread out artists"),
+        // translators: An option in the menu of the shuffle button: every album gets the same chance, however many of its songs are in the list
+        ShuffleMode::AlbumWeighted => gettext("Even out albums"),
+        // translators: An option in the menu of the shuffle button: songs not heard in a long time come first, according to the listening history
+        ShuffleMode::LeastRecentlyPlayed => gettext("Least recently played first"),
+    }
+}
 
 mod imp {
 
@@ -26,6 +43,8 @@ mod imp {
 
         #[template_child]
         pub repeat: TemplateChild<gtk::Button>,
+
+        pub shuffle_actions: gio::SimpleActionGroup,
     }
 
     #[glib::object_subclass]
@@ -43,7 +62,23 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for PlaybackControlsWidget {}
+    impl ObjectImpl for PlaybackControlsWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj()
+                .insert_action_group("shuffle", Some(&self.shuffle_actions));
+
+            let menu = gio::Menu::new();
+            for mode in ShuffleMode::ALL {
+                menu.append(
+                    Some(&shuffle_mode_label(mode)),
+                    Some(&format!("shuffle.{}::{}", SHUFFLE_MODE_ACTION, mode.id())),
+                );
+            }
+            let context_menu = attach_context_menu(&*self.shuffle);
+            context_menu.set_menu_model(Some(&menu));
+        }
+    }
     impl WidgetImpl for PlaybackControlsWidget {}
     impl BoxImpl for PlaybackControlsWidget {}
 }
@@ -76,6 +111,16 @@ impl PlaybackControlsWidget {
         self.imp().shuffle.set_active(shuffled);
     }
 
+    pub fn set_shuffle_mode(&self, mode: ShuffleMode) {
+        let widget = self.imp();
+        if let Some(action) = widget.shuffle_actions.lookup_action(SHUFFLE_MODE_ACTION) {
+            action.change_state(&mode.id().to_variant());
+        }
+        widget
+            .shuffle
+            .set_tooltip_text(Some(&shuffle_mode_label(mode)));
+    }
+
     pub fn set_repeat_mode(&self, mode: RepeatMode) {
         let repeat_mode_icon = match mode {
             RepeatMode::Song => "media-playlist-repeat-song-symbolic",
@@ -100,6 +145,32 @@ impl PlaybackControlsWidget {
         self.imp().prev.connect_clicked(move |_| f());
     }
 
+    // Right click or long press, so it works with a mouse too
+    pub fn connect_prev_track<F>(&self, f: F)
+    where
+        F: Fn() + Clone + 'static,
+    {
+        let prev = &*self.imp().prev;
+
+        let click = gtk::GestureClick::new();
+        click.set_button(gdk::BUTTON_SECONDARY);
+        let g = f.clone();
+        click.connect_pressed(move |gesture, _, _, _| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            g();
+        });
+        prev.add_controller(click);
+
+        // Seen before the button's own click, so that claiming it means no plain "previous" on release
+        let long_press = gtk::GestureLongPress::new();
+        long_press.set_propagation_phase(gtk::PropagationPhase::Capture);
+        long_press.connect_pressed(move |gesture, _, _| {
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+            f();
+        });
+        prev.add_controller(long_press);
+    }
+
     pub fn connect_next<F>(&self, f: F)
     where
         F: Fn() + 'static,
@@ -114,6 +185,23 @@ impl PlaybackControlsWidget {
         self.imp().shuffle.connect_clicked(move |_| f());
     }
 
+    pub fn connect_shuffle_mode<F>(&self, f: F)
+    where
+        F: Fn(ShuffleMode) + 'static,
+    {
+        let action = SimpleAction::new_stateful(
+            SHUFFLE_MODE_ACTION,
+            Some(glib::VariantTy::STRING),
+            ShuffleMode::default().id().to_variant(),
+        );
+        action.connect_activate(move |_, mode| {
+            if let Some(mode) = mode.and_then(|m| m.str()).and_then(ShuffleMode::from_id) {
+                f(mode);
+            }
+        });
+        self.imp().shuffle_actions.add_action(&action);
+    }
+
     pub fn connect_repeat<F>(&self, f: F)
     where
         F: Fn() + 'static,
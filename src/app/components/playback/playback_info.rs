@@ -2,6 +2,9 @@ use gettextrs::gettext;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::{glib, CompositeTemplate};
+use std::cell::RefCell;
+
+use crate::app::components::utils::attach_context_menu;
 
 mod imp {
 
@@ -15,6 +18,11 @@ mod imp {
 
         #[template_child]
         pub current_song_info: TemplateChild<gtk::Label>,
+
+        #[template_child]
+        pub preview_badge: TemplateChild<gtk::Label>,
+
+        pub context_menu: RefCell<Option<gtk::PopoverMenu>>,
     }
 
     #[glib::object_subclass]
@@ -32,7 +40,13 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for PlaybackInfoWidget {}
+    impl ObjectImpl for PlaybackInfoWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let context_menu = attach_context_menu(&*self.obj());
+            self.context_menu.replace(Some(context_menu));
+        }
+    }
     impl WidgetImpl for PlaybackInfoWidget {}
     impl ButtonImpl for PlaybackInfoWidget {}
 }
@@ -68,6 +82,16 @@ impl PlaybackInfoWidget {
         self.imp().current_song_info.set_visible(visible);
     }
 
+    pub fn set_preview(&self, preview: bool) {
+        self.imp().preview_badge.set_visible(preview);
+    }
+
+    pub fn set_menu(&self, menu: Option<&gio::MenuModel>) {
+        if let Some(context_menu) = self.imp().context_menu.borrow().as_ref() {
+            context_menu.set_menu_model(menu);
+        }
+    }
+
     pub fn set_artwork(&self, art: &gdk_pixbuf::Pixbuf) {
         self.imp().playing_image.set_from_pixbuf(Some(art));
     }
@@ -1,16 +1,23 @@
+use gettextrs::gettext;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::{glib, CompositeTemplate};
+use std::cell::{Cell, RefCell};
 
-use crate::app::components::display_add_css_provider;
-use crate::app::components::utils::{format_duration, Clock, Debouncer};
+use crate::app::components::utils::{Clock, Debouncer};
+use crate::app::components::{display_add_css_provider, labels};
+use crate::app::format::format_duration;
 use crate::app::loader::ImageLoader;
-use crate::app::models::RepeatMode;
+use crate::app::models::{ChapterList, RepeatMode, ShuffleMode};
 use crate::app::Worker;
 
 use super::playback_controls::PlaybackControlsWidget;
 use super::playback_info::PlaybackInfoWidget;
 
+// How far the arrow keys seek on the seek bar, in ms (with Shift for the long one)
+const SEEK_STEP: f64 = 5_000.0;
+const LONG_SEEK_STEP: f64 = 30_000.0;
+
 mod imp {
 
     use super::*;
@@ -33,12 +40,37 @@ mod imp {
         #[template_child]
         pub seek_bar: TemplateChild<gtk::Scale>,
 
+        #[template_child]
+        pub scrub_preview: TemplateChild<gtk::Popover>,
+
+        #[template_child]
+        pub scrub_label: TemplateChild<gtk::Label>,
+
+        #[template_child]
+        pub like_button: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub active_device: TemplateChild<gtk::Label>,
+
         #[template_child]
         pub track_position: TemplateChild<gtk::Label>,
 
         #[template_child]
         pub track_duration: TemplateChild<gtk::Label>,
 
+        #[template_child]
+        pub volume: TemplateChild<gtk::VolumeButton>,
+
+        pub volume_handler: RefCell<Option<glib::SignalHandlerId>>,
+
+        pub liked: Cell<Option<bool>>,
+
+        // While the seek bar is held down, and where it would seek to if let go now
+        pub scrubbing: Cell<bool>,
+        pub scrub_target: Cell<Option<f64>>,
+        // Where the arrow keys pressed so far would seek to, until it's done
+        pub key_target: Cell<Option<f64>>,
+
         pub clock: Clock,
     }
 
@@ -62,8 +94,13 @@ mod imp {
             self.parent_constructed();
             self.now_playing_mobile.set_info_visible(false);
             self.now_playing.set_info_visible(true);
+            self.scrub_preview.set_parent(&*self.seek_bar);
             display_add_css_provider(resource!("/components/playback.css"));
         }
+
+        fn dispose(&self) {
+            self.scrub_preview.unparent();
+        }
     }
 
     impl WidgetImpl for PlaybackWidget {}
@@ -80,11 +117,19 @@ impl PlaybackWidget {
         widget.now_playing.set_title_and_artist(title, artist);
     }
 
+    pub fn set_preview(&self, preview: bool) {
+        let widget = self.imp();
+        widget.now_playing.set_preview(preview);
+        widget.now_playing_mobile.set_preview(preview);
+    }
+
     pub fn reset_info(&self) {
         let widget = self.imp();
         widget.now_playing.reset_info();
         widget.now_playing_mobile.reset_info();
         self.set_song_duration(None);
+        self.set_liked(None);
+        self.set_song_menu(None, None);
     }
 
     fn set_artwork(&self, image: &gdk_pixbuf::Pixbuf) {
@@ -93,6 +138,14 @@ impl PlaybackWidget {
         widget.now_playing_mobile.set_artwork(image);
     }
 
+    // Actions are looked up in the "song" group
+    pub fn set_song_menu(&self, actions: Option<&gio::ActionGroup>, menu: Option<&gio::MenuModel>) {
+        let widget = self.imp();
+        self.insert_action_group("song", actions);
+        widget.now_playing.set_menu(menu);
+        widget.now_playing_mobile.set_menu(menu);
+    }
+
     pub fn set_artwork_from_url(&self, url: String, worker: &Worker) {
         let weak_self = self.downgrade();
         worker.send_local_task(async move {
@@ -119,21 +172,35 @@ impl PlaybackWidget {
             widget.track_duration.set_visible(true);
         } else {
             self.remove_css_class(class);
+            widget.seek_bar.clear_marks();
             widget.seek_bar.set_range(0.0, 0.0);
             widget.track_position.set_visible(false);
             widget.track_duration.set_visible(false);
         }
     }
 
+    // The first chapter usually starts right away, no need for a mark there
+    pub fn set_chapters(&self, chapters: &ChapterList) {
+        let seek_bar = &self.imp().seek_bar;
+        seek_bar.clear_marks();
+        for marker in chapters.markers().iter().filter(|m| m.start > 0) {
+            seek_bar.add_mark(marker.start as f64, gtk::PositionType::Top, None);
+        }
+    }
+
     pub fn set_seek_position(&self, pos: f64) {
         let widget = self.imp();
-        widget.seek_bar.set_value(pos);
-        widget.track_position.set_text(&format_duration(pos));
+        // The bar is the user's until they let go of it (or stop pressing keys)
+        if widget.scrubbing.get() || widget.key_target.get().is_some() {
+            return;
+        }
+        self.show_seek_position(pos);
     }
 
-    pub fn increment_seek_position(&self) {
-        let value = self.imp().seek_bar.value() + 1_000.0;
-        self.set_seek_position(value);
+    fn show_seek_position(&self, pos: f64) {
+        let widget = self.imp();
+        widget.seek_bar.set_value(pos);
+        widget.track_position.set_text(&format_duration(pos));
     }
 
     pub fn connect_now_playing_clicked<F>(&self, f: F)
@@ -146,33 +213,180 @@ impl PlaybackWidget {
         widget.now_playing_mobile.connect_clicked(move |_| f());
     }
 
+    fn clamp_to_song(&self, position: f64) -> f64 {
+        let adjustment = self.imp().seek_bar.adjustment();
+        position.clamp(adjustment.lower(), adjustment.upper())
+    }
+
+    // The time at some point of the seek bar (x from its left edge)
+    fn position_at(&self, x: f64) -> f64 {
+        let seek_bar = &self.imp().seek_bar;
+        let adjustment = seek_bar.adjustment();
+        let trough = seek_bar.range_rect();
+        let fraction = ((x - trough.x() as f64) / trough.width().max(1) as f64).clamp(0.0, 1.0);
+        adjustment.lower() + fraction * (adjustment.upper() - adjustment.lower())
+    }
+
+    // A timestamp above the slider, for as long as it's dragged around
+    fn show_scrub_preview(&self, position: f64) {
+        let widget = self.imp();
+        let adjustment = widget.seek_bar.adjustment();
+        let span = adjustment.upper() - adjustment.lower();
+        if span <= 0.0 {
+            return;
+        }
+        let trough = widget.seek_bar.range_rect();
+        let fraction = (position - adjustment.lower()) / span;
+        let x = trough.x() + (fraction * trough.width() as f64) as i32;
+        widget.scrub_label.set_text(&format_duration(position));
+        widget
+            .scrub_preview
+            .set_pointing_to(Some(&gdk::Rectangle::new(
+                x,
+                trough.y(),
+                1,
+                trough.height(),
+            )));
+        widget.scrub_preview.popup();
+    }
+
+    // Dragging (or clicking) the seek bar only seeks once it's let go, the arrow keys move by 5s (30s with Shift),
+    // and hovering shows the time at the pointer
     pub fn connect_seek<Seek>(&self, seek: Seek)
     where
         Seek: Fn(u32) + Clone + 'static,
     {
-        let debouncer = Debouncer::new();
         let widget = self.imp();
-        widget.seek_bar.set_increments(5_000.0, 10_000.0);
+        widget.seek_bar.set_increments(SEEK_STEP, LONG_SEEK_STEP);
+        widget.seek_bar.set_has_tooltip(true);
+
+        // Scrolling and the other keys the bar handles itself still seek as they go, just not too often
+        let debouncer = Debouncer::new();
         widget.seek_bar.connect_change_value(
-            clone!(@weak self as _self => @default-return glib::signal::Inhibit(false), move |_, _, requested| {
-                _self.imp()
+            clone!(@weak self as _self, @strong seek => @default-return glib::signal::Inhibit(false), move |_, _, requested| {
+                let requested = _self.clamp_to_song(requested);
+                let widget = _self.imp();
+                widget
                     .track_position
                     .set_text(&format_duration(requested));
-                let seek = seek.clone();
-                debouncer.debounce(200, move || seek(requested as u32));
+                if widget.scrubbing.get() {
+                    widget.scrub_target.set(Some(requested));
+                    _self.show_scrub_preview(requested);
+                } else {
+                    let seek = seek.clone();
+                    debouncer.debounce(200, move || seek(requested as u32));
+                }
+                glib::signal::Inhibit(false)
+            }),
+        );
+
+        // Looking at the raw events, as the bar's own gestures would get in the way of ours
+        let presses = gtk::EventControllerLegacy::new();
+        presses.set_propagation_phase(gtk::PropagationPhase::Capture);
+        presses.connect_event(
+            clone!(@weak self as _self, @strong seek => @default-return glib::signal::Inhibit(false), move |_, event| {
+                let widget = _self.imp();
+                match event.event_type() {
+                    gdk::EventType::ButtonPress | gdk::EventType::TouchBegin => {
+                        widget.scrubbing.set(true);
+                    }
+                    gdk::EventType::ButtonRelease
+                    | gdk::EventType::TouchEnd
+                    | gdk::EventType::TouchCancel if widget.scrubbing.get() => {
+                        widget.scrubbing.set(false);
+                        widget.scrub_preview.popdown();
+                        if let Some(target) = widget.scrub_target.take() {
+                            seek(target as u32);
+                        }
+                    }
+                    _ => {}
+                }
                 glib::signal::Inhibit(false)
             }),
         );
+        widget.seek_bar.add_controller(presses);
+
+        let keys = gtk::EventControllerKey::new();
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let debouncer = Debouncer::new();
+        keys.connect_key_pressed(
+            clone!(@weak self as _self => @default-return glib::signal::Inhibit(false), move |_, keyval, _, state| {
+                let step = if state.contains(gdk::ModifierType::SHIFT_MASK) {
+                    LONG_SEEK_STEP
+                } else {
+                    SEEK_STEP
+                };
+                let step = match keyval {
+                    gdk::Key::Left | gdk::Key::KP_Left => -step,
+                    gdk::Key::Right | gdk::Key::KP_Right => step,
+                    _ => return glib::signal::Inhibit(false),
+                };
+                let widget = _self.imp();
+                let from = widget.key_target.get().unwrap_or_else(|| widget.seek_bar.value());
+                let target = _self.clamp_to_song(from + step);
+                widget.key_target.set(Some(target));
+                _self.show_seek_position(target);
+                // Holding the key down makes for a single seek
+                let seek = seek.clone();
+                debouncer.debounce(200, clone!(@weak _self => move || {
+                    _self.imp().key_target.set(None);
+                    seek(target as u32);
+                }));
+                glib::signal::Inhibit(true)
+            }),
+        );
+        widget.seek_bar.add_controller(keys);
+
+        widget.seek_bar.connect_query_tooltip(
+            clone!(@weak self as _self => @default-return false, move |_, x, _, _, tooltip| {
+                if _self.imp().seek_bar.adjustment().upper() <= 0.0 {
+                    return false;
+                }
+                tooltip.set_text(Some(&format_duration(_self.position_at(x as f64))));
+                true
+            }),
+        );
+    }
+
+    pub fn connect_volume_changed<F>(&self, f: F)
+    where
+        F: Fn(f64) + Clone + 'static,
+    {
+        let debouncer = Debouncer::new();
+        let handler = self.imp().volume.connect_value_changed(move |_, volume| {
+            let f = f.clone();
+            debouncer.debounce(200, move || f(volume));
+        });
+        self.imp().volume_handler.replace(Some(handler));
+    }
+
+    // Without notifying, this is for changes that happened elsewhere
+    pub fn set_volume(&self, volume: f64) {
+        let widget = self.imp();
+        let handler = widget.volume_handler.borrow();
+        if let Some(handler) = handler.as_ref() {
+            widget.volume.block_signal(handler);
+        }
+        widget.volume.set_value(volume);
+        if let Some(handler) = handler.as_ref() {
+            widget.volume.unblock_signal(handler);
+        }
     }
 
-    pub fn set_playing(&self, is_playing: bool) {
+    // While playing, the seek bar follows the given position rather than counting seconds on its own
+    pub fn set_playing<F>(&self, is_playing: bool, position: F)
+    where
+        F: Fn() -> Option<u32> + 'static,
+    {
         let widget = self.imp();
         widget.controls.set_playing(is_playing);
         widget.controls_mobile.set_playing(is_playing);
         if is_playing {
-            widget
-                .clock
-                .start(clone!(@weak self as _self => move || _self.increment_seek_position()));
+            widget.clock.start(clone!(@weak self as _self => move || {
+                if let Some(position) = position() {
+                    _self.set_seek_position(position as f64);
+                }
+            }));
         } else {
             widget.clock.stop();
         }
@@ -190,6 +404,50 @@ impl PlaybackWidget {
         widget.controls_mobile.set_shuffled(shuffled);
     }
 
+    pub fn set_shuffle_mode(&self, mode: ShuffleMode) {
+        let widget = self.imp();
+        widget.controls.set_shuffle_mode(mode);
+        widget.controls_mobile.set_shuffle_mode(mode);
+    }
+
+    pub fn set_active_device(&self, device: Option<&str>) {
+        let label = &self.imp().active_device;
+        if let Some(device) = device {
+            label.set_text(&labels::active_on_device_label(device));
+        }
+        label.set_visible(device.is_some());
+    }
+
+    // None when we don't know (yet) whether the current song is saved
+    pub fn set_liked(&self, liked: Option<bool>) {
+        let widget = self.imp();
+        widget.liked.set(liked);
+        let button = &widget.like_button;
+        button.set_visible(liked.is_some());
+        if liked == Some(true) {
+            button.add_css_class("like-button--liked");
+            // translators: Tooltip of the heart button of the playback bar, when the current song is already saved
+            button.set_tooltip_text(Some(&gettext("Remove from saved tracks")));
+        } else {
+            button.remove_css_class("like-button--liked");
+            // translators: Tooltip of the heart button of the playback bar
+            button.set_tooltip_text(Some(&gettext("Add to saved tracks")));
+        }
+    }
+
+    pub fn connect_like_clicked<F>(&self, f: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        self.imp()
+            .like_button
+            .connect_clicked(clone!(@weak self as _self => move |_| {
+                if let Some(liked) = _self.imp().liked.get() {
+                    f(liked);
+                }
+            }));
+    }
+
     pub fn set_seekbar_visible(&self, visible: bool) {
         let widget = self.imp();
         widget.seek_bar.set_visible(visible);
@@ -213,6 +471,15 @@ impl PlaybackWidget {
         widget.controls_mobile.connect_prev(f);
     }
 
+    pub fn connect_prev_track<F>(&self, f: F)
+    where
+        F: Fn() + Clone + 'static,
+    {
+        let widget = self.imp();
+        widget.controls.connect_prev_track(f.clone());
+        widget.controls_mobile.connect_prev_track(f);
+    }
+
     pub fn connect_next<F>(&self, f: F)
     where
         F: Fn() + Clone + 'static,
@@ -231,6 +498,15 @@ impl PlaybackWidget {
         widget.controls_mobile.connect_shuffle(f);
     }
 
+    pub fn connect_shuffle_mode<F>(&self, f: F)
+    where
+        F: Fn(ShuffleMode) + Clone + 'static,
+    {
+        let widget = self.imp();
+        widget.controls.connect_shuffle_mode(f.clone());
+        widget.controls_mobile.connect_shuffle_mode(f);
+    }
+
     pub fn connect_repeat<F>(&self, f: F)
     where
         F: Fn() + Clone + 'static,
@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Sender};
+
+use crate::app::components::EventListener;
+use crate::app::models::SongDescription;
+use crate::app::state::PlaybackEvent;
+use crate::app::{AppEvent, AppModel};
+use crate::settings;
+
+// Also how the status stream describes songs
+#[derive(Debug, Serialize)]
+pub struct HookTrack {
+    id: String,
+    uri: String,
+    title: String,
+    artists: Vec<String>,
+    album: String,
+    duration_ms: u32,
+    art_url: Option<String>,
+}
+
+impl From<&SongDescription> for HookTrack {
+    fn from(song: &SongDescription) -> Self {
+        Self {
+            id: song.id.clone(),
+            uri: song.uri.clone(),
+            title: song.title.clone(),
+            artists: song.artists.iter().map(|a| a.name.clone()).collect(),
+            album: song.album.name.clone(),
+            duration_ms: song.duration,
+            art_url: song.art.clone(),
+        }
+    }
+}
+
+// What the hook gets told, as JSON on its standard input
+#[derive(Debug, Serialize)]
+struct HookEvent {
+    // track-changed, playing, paused or stopped
+    event: &'static str,
+    position_ms: u32,
+    track: Option<HookTrack>,
+}
+
+impl HookEvent {
+    // The same, for scripts that would rather not parse JSON
+    fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("SPOT_EVENT", self.event.to_string()),
+            ("SPOT_POSITION_MS", self.position_ms.to_string()),
+        ];
+        if let Some(track) = &self.track {
+            env.extend([
+                ("SPOT_TRACK_ID", track.id.clone()),
+                ("SPOT_TRACK_URI", track.uri.clone()),
+                ("SPOT_TITLE", track.title.clone()),
+                ("SPOT_ARTISTS", track.artists.join(", ")),
+                ("SPOT_ALBUM", track.album.clone()),
+                ("SPOT_DURATION_MS", track.duration_ms.to_string()),
+                ("SPOT_ART_URL", track.art_url.clone().unwrap_or_default()),
+            ]);
+        }
+        env
+    }
+}
+
+fn hook_command(command: &str, event: &HookEvent) -> Option<Command> {
+    let argv = match glib::shell_parse_argv(command) {
+        Ok(argv) if !argv.is_empty() => argv,
+        Ok(_) => return None,
+        Err(e) => {
+            warn!("Could not parse the playback hook: {}", e);
+            return None;
+        }
+    };
+    let mut command = Command::new(&argv[0]);
+    command
+        .args(&argv[1..])
+        .envs(event.env())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null());
+    Some(command)
+}
+
+fn run_hook(mut command: Command, input: Vec<u8>) {
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Could not run the playback hook: {}", e);
+            return;
+        }
+    };
+    // Scripts that don't read their input are fine too
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(&input);
+    }
+    match child.wait() {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("The playback hook failed: {}", status),
+        Err(e) => warn!("The playback hook failed: {}", e),
+    }
+}
+
+// Runs the command from the preferences when the song changes or playback starts, pauses or stops,
+// for status bars, home automation and the like (inside the sandbox when Spot is a flatpak)
+pub struct PlaybackHooks {
+    app_model: Rc<AppModel>,
+    // Not worth holding up the app for, however long the script takes, but one run at a time
+    // so that the script sees the events in the order they happened
+    runs: Sender<(Command, Vec<u8>)>,
+}
+
+impl PlaybackHooks {
+    pub fn new(app_model: Rc<AppModel>) -> Self {
+        let (runs, queued) = channel::<(Command, Vec<u8>)>();
+        std::thread::spawn(move || {
+            for (command, input) in queued {
+                run_hook(command, input);
+            }
+        });
+        Self { app_model, runs }
+    }
+
+    fn notify(&self, event: &'static str) {
+        let command = settings::playback_hook();
+        if command.trim().is_empty() {
+            return;
+        }
+        let state = self.app_model.get_state();
+        let event = HookEvent {
+            event,
+            position_ms: state.playback.seek_position(),
+            track: state.playback.current_song().as_ref().map(HookTrack::from),
+        };
+        drop(state);
+        if let Some(command) = hook_command(&command, &event) {
+            let input = serde_json::to_vec(&event).unwrap_or_default();
+            let _ = self.runs.send((command, input));
+        }
+    }
+}
+
+impl EventListener for PlaybackHooks {
+    fn on_event(&mut self, event: &AppEvent) {
+        let AppEvent::PlaybackEvent(event) = event else {
+            return;
+        };
+        match event {
+            PlaybackEvent::TrackChanged(_) => self.notify("track-changed"),
+            PlaybackEvent::PlaybackResumed => self.notify("playing"),
+            PlaybackEvent::PlaybackPaused => self.notify("paused"),
+            PlaybackEvent::PlaybackStopped => self.notify("stopped"),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_hook_event() {
+        let event = HookEvent {
+            event: "playing",
+            position_ms: 1000,
+            track: Some(HookTrack {
+                id: "id".to_string(),
+                uri: "spotify:track:id".to_string(),
+                title: "Title".to_string(),
+                artists: vec!["A".to_string(), "B".to_string()],
+                album: "Album".to_string(),
+                duration_ms: 2000,
+                art_url: None,
+            }),
+        };
+        let env = event.env();
+        assert!(env.contains(&("SPOT_EVENT", "playing".to_string())));
+        assert!(env.contains(&("SPOT_ARTISTS", "A, B".to_string())));
+        assert!(env.contains(&("SPOT_ART_URL", "".to_string())));
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "playing");
+        assert_eq!(json["track"]["uri"], "spotify:track:id");
+
+        let stopped = HookEvent {
+            event: "stopped",
+            position_ms: 0,
+            track: None,
+        };
+        assert_eq!(stopped.env().len(), 2);
+    }
+}
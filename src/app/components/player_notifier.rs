@@ -4,9 +4,12 @@ use std::rc::Rc;
 use futures::channel::mpsc::UnboundedSender;
 use librespot::core::spotify_id::{SpotifyId, SpotifyItemType};
 
-use crate::app::components::EventListener;
+use gettextrs::gettext;
+
+use crate::app::components::{EventListener, PreviewPlayer};
 use crate::app::state::{
-    Device, LoginAction, LoginEvent, LoginStartedEvent, PlaybackEvent, SettingsEvent,
+    Device, LoginAction, LoginEvent, LoginStartedEvent, PlaybackAction, PlaybackEvent,
+    SettingsEvent,
 };
 use crate::app::{ActionDispatcher, AppAction, AppEvent, AppModel, SongsSource};
 use crate::connect::ConnectCommand;
@@ -36,6 +39,7 @@ impl CurrentlyPlaying {
 pub struct PlayerNotifier {
     app_model: Rc<AppModel>,
     dispatcher: Box<dyn ActionDispatcher>,
+    preview_player: PreviewPlayer,
     command_sender: UnboundedSender<Command>,
     connect_command_sender: UnboundedSender<ConnectCommand>,
 }
@@ -44,12 +48,14 @@ impl PlayerNotifier {
     pub fn new(
         app_model: Rc<AppModel>,
         dispatcher: Box<dyn ActionDispatcher>,
+        preview_player: PreviewPlayer,
         command_sender: UnboundedSender<Command>,
         connect_command_sender: UnboundedSender<ConnectCommand>,
     ) -> Self {
         Self {
             app_model,
             dispatcher,
+            preview_player,
             command_sender,
             connect_command_sender,
         }
@@ -78,6 +84,44 @@ impl PlayerNotifier {
         Some(result)
     }
 
+    // Chapters of audiobooks are episodes to the player, which it can only tell from the uri
+    fn playable_id(&self, id: &str) -> Option<SpotifyId> {
+        let uri = self
+            .app_model
+            .get_state()
+            .playback
+            .songs()
+            .get(id)
+            .map(|song| song.description().uri.clone());
+        uri.and_then(|uri| SpotifyId::from_uri(&uri).ok())
+            .or_else(|| {
+                let mut track = SpotifyId::from_base62(id).ok()?;
+                track.item_type = SpotifyItemType::Track;
+                Some(track)
+            })
+    }
+
+    fn previews_only(&self) -> bool {
+        self.app_model.get_state().playback.previews_only()
+    }
+
+    fn preview_url(&self, id: &str) -> Option<String> {
+        self.app_model
+            .get_state()
+            .playback
+            .songs()
+            .get(id)
+            .and_then(|song| song.description().preview_url.clone())
+    }
+
+    fn in_group_session(&self) -> bool {
+        self.app_model
+            .get_state()
+            .playback
+            .group_session()
+            .is_some()
+    }
+
     fn device(&self) -> impl Deref<Target = Device> + '_ {
         self.app_model.map_state(|s| s.playback.current_device())
     }
@@ -94,6 +138,9 @@ impl PlayerNotifier {
             LoginEvent::LoginStarted(LoginStartedEvent::OAuthSpotify {}) => {
                 Some(Command::OAuthLogin)
             }
+            LoginEvent::LoginStarted(LoginStartedEvent::ImportCredentials(path)) => {
+                Some(Command::ImportLogin(path.clone()))
+            }
             LoginEvent::FreshTokenRequested => Some(Command::RefreshToken),
             LoginEvent::LogoutCompleted => Some(Command::Logout),
             _ => None,
@@ -104,32 +151,35 @@ impl PlayerNotifier {
         }
     }
 
+    fn connect_load_command(&self) -> Option<ConnectCommand> {
+        match self.currently_playing()? {
+            CurrentlyPlaying::WithSource {
+                source,
+                offset,
+                song,
+            } => Some(ConnectCommand::PlayerLoadInContext {
+                source,
+                offset,
+                song,
+            }),
+            CurrentlyPlaying::Songs { songs, offset } => {
+                Some(ConnectCommand::PlayerLoad { songs, offset })
+            }
+        }
+    }
+
     fn notify_connect_player(&self, event: &PlaybackEvent) {
         let event = event.clone();
-        let currently_playing = self.currently_playing();
         let command = match event {
             PlaybackEvent::TrackChanged(_) | PlaybackEvent::SourceChanged => {
-                match currently_playing {
-                    Some(CurrentlyPlaying::WithSource {
-                        source,
-                        offset,
-                        song,
-                    }) => Some(ConnectCommand::PlayerLoadInContext {
-                        source,
-                        offset,
-                        song,
-                    }),
-                    Some(CurrentlyPlaying::Songs { songs, offset }) => {
-                        Some(ConnectCommand::PlayerLoad { songs, offset })
-                    }
-                    None => None,
-                }
+                self.connect_load_command()
             }
             PlaybackEvent::TrackSeeked(position) => {
                 Some(ConnectCommand::PlayerSeek(position as usize))
             }
             PlaybackEvent::PlaybackPaused => Some(ConnectCommand::PlayerPause),
             PlaybackEvent::PlaybackResumed => Some(ConnectCommand::PlayerResume),
+            PlaybackEvent::TracksQueued(ids) => Some(ConnectCommand::PlayerQueue(ids)),
             PlaybackEvent::VolumeSet(volume) => Some(ConnectCommand::PlayerSetVolume(
                 (volume * 100f64).trunc() as u8,
             )),
@@ -145,7 +195,55 @@ impl PlayerNotifier {
         }
     }
 
+    fn load_preview(&self, id: &str, play: bool) {
+        if let Some(url) = self.preview_url(id) {
+            self.preview_player.load(&url, play);
+        } else {
+            self.preview_player.stop();
+            self.dispatcher.dispatch(PlaybackAction::Pause.into());
+            self.dispatcher.dispatch(AppAction::ShowNotification(
+                // translators: Shown when trying to play a song without Spotify Premium, and Spotify has no 30 second preview of it
+                gettext("No preview of this song to play"),
+            ));
+        }
+    }
+
+    fn notify_preview_player(&self, event: &PlaybackEvent) {
+        match event {
+            PlaybackEvent::PlaybackPaused => self.preview_player.pause(),
+            PlaybackEvent::PlaybackResumed => self.preview_player.resume(),
+            PlaybackEvent::PlaybackStopped => self.preview_player.stop(),
+            PlaybackEvent::TrackChanged(id) => self.load_preview(id, true),
+            PlaybackEvent::SourceChanged => {
+                if let Some(current) = self.currently_playing() {
+                    self.load_preview(current.song_id(), self.is_playing());
+                }
+            }
+            PlaybackEvent::TrackSeeked(position) => self.preview_player.seek(*position),
+            _ => {}
+        }
+    }
+
+    // Picks up where the other player left off, once Premium playback becomes possible (or stops being)
+    fn switch_to_previews(&self, previews_only: bool) {
+        if !previews_only {
+            self.preview_player.stop();
+        }
+        if self.is_playing() {
+            self.notify_local_player(&PlaybackEvent::SourceChanged);
+        }
+    }
+
     fn notify_local_player(&self, event: &PlaybackEvent) {
+        // Both get it, so that the preview player is already at the right volume when it's needed
+        if let PlaybackEvent::VolumeSet(volume) = event {
+            self.preview_player.set_volume(*volume);
+        }
+        if self.previews_only() {
+            self.notify_preview_player(event);
+            return;
+        }
+
         let command = match event {
             PlaybackEvent::PlaybackPaused => Some(Command::PlayerPause),
             PlaybackEvent::PlaybackResumed => Some(Command::PlayerResume),
@@ -153,37 +251,78 @@ impl PlayerNotifier {
             PlaybackEvent::VolumeSet(volume) => Some(Command::PlayerSetVolume(*volume)),
             PlaybackEvent::TrackChanged(id) => {
                 info!("track changed: {}", id);
-                SpotifyId::from_base62(id).ok().map(|mut track| {
-                    track.item_type = SpotifyItemType::Track;
-                    Command::PlayerLoad {
-                        track,
-                        resume: true,
-                    }
+                self.playable_id(id).map(|track| Command::PlayerLoad {
+                    track,
+                    resume: true,
                 })
             }
             PlaybackEvent::SourceChanged => {
                 let resume = self.is_playing();
                 self.currently_playing()
-                    .and_then(|c| SpotifyId::from_base62(c.song_id()).ok())
-                    .map(|mut track| {
-                        track.item_type = SpotifyItemType::Track;
-                        Command::PlayerLoad { track, resume }
-                    })
+                    .and_then(|c| self.playable_id(c.song_id()))
+                    .map(|track| Command::PlayerLoad { track, resume })
             }
             PlaybackEvent::TrackSeeked(position) => Some(Command::PlayerSeek(*position)),
-            PlaybackEvent::Preload(id) => SpotifyId::from_base62(id)
-                .ok()
-                .map(|mut track| {
-                    track.item_type = SpotifyItemType::Track;
-                    track
-                })
-                .map(Command::PlayerPreload),
+            PlaybackEvent::Preload(id) => self.playable_id(id).map(Command::PlayerPreload),
             _ => None,
         };
 
         if let Some(command) = command {
             self.send_command_to_local_player(command);
         }
+
+        if let PlaybackEvent::SourceChanged = event {
+            self.send_command_to_local_player(self.precache_album());
+        }
+
+        // Whenever what comes next might have changed
+        if matches!(
+            event,
+            PlaybackEvent::TrackChanged(_)
+                | PlaybackEvent::SourceChanged
+                | PlaybackEvent::PlaylistChanged
+                | PlaybackEvent::ShuffleChanged(_)
+                | PlaybackEvent::RepeatModeChanged(_)
+                | PlaybackEvent::TracksQueued(_)
+        ) {
+            if let Some(command) = self.preload_next() {
+                self.send_command_to_local_player(command);
+            }
+        }
+    }
+
+    // Right away, rather than only close to the end of the song like librespot asks, so that pressing next is instant too.
+    // Going back to the previous song is already quick: librespot keeps the songs it fully downloaded in its cache.
+    fn preload_next(&self) -> Option<Command> {
+        let (current, next) = {
+            let state = self.app_model.get_state();
+            (state.playback.current_song_id(), state.playback.next_id()?)
+        };
+        if current.as_ref() == Some(&next) {
+            return None;
+        }
+        self.playable_id(&next).map(Command::PlayerPreload)
+    }
+
+    // The rest of the album, in the order it'll play in. Any other source gets nothing, and what was
+    // downloaded for the previous album is let go (the player only does it if it's enabled in the preferences)
+    fn precache_album(&self) -> Command {
+        let state = self.app_model.get_state();
+        let tracks = match (
+            state.playback.current_source(),
+            state.playback.current_song_index(),
+        ) {
+            (Some(SongsSource::Album(_)), Some(index)) => state
+                .playback
+                .songs()
+                .map_collect(|s| s.uri)
+                .iter()
+                .skip(index + 1)
+                .filter_map(|uri| SpotifyId::from_uri(uri).ok())
+                .collect(),
+            _ => vec![],
+        };
+        Command::PlayerPrecache(tracks)
     }
 
     fn send_command_to_connect_player(&self, command: ConnectCommand) {
@@ -203,8 +342,12 @@ impl PlayerNotifier {
         match device {
             Device::Connect(device) => {
                 self.send_command_to_local_player(Command::PlayerStop);
+                self.preview_player.stop();
                 self.send_command_to_connect_player(ConnectCommand::SetDevice(device.id.clone()));
-                self.notify_connect_player(&PlaybackEvent::SourceChanged);
+                // The queue is shared with others in a session, we'll get it from the device instead
+                if !self.in_group_session() {
+                    self.notify_connect_player(&PlaybackEvent::SourceChanged);
+                }
             }
             Device::Local => {
                 self.send_command_to_connect_player(ConnectCommand::PlayerStop);
@@ -212,6 +355,62 @@ impl PlayerNotifier {
             }
         }
     }
+
+    fn reconnect(&self, device: Device) {
+        let track = match device {
+            Device::Local if !self.previews_only() => self
+                .currently_playing()
+                .and_then(|c| self.playable_id(c.song_id())),
+            _ => None,
+        };
+        self.send_command_to_local_player(Command::Reconnect {
+            track,
+            position: self.app_model.get_state().playback.seek_position(),
+            resume: self.is_playing(),
+        });
+    }
+
+    fn transfer_playback(&mut self, device: &Device) {
+        let position_ms = self.app_model.get_state().playback.seek_position();
+        match device {
+            Device::Connect(device) => {
+                self.send_command_to_local_player(Command::PlayerStop);
+                self.preview_player.stop();
+                self.send_command_to_connect_player(ConnectCommand::TransferPlayback {
+                    device_id: device.id.clone(),
+                    load: self.connect_load_command().map(Box::new),
+                    position_ms,
+                });
+            }
+            Device::Local if self.previews_only() => {
+                self.send_command_to_connect_player(ConnectCommand::PlayerStop);
+                self.notify_preview_player(&PlaybackEvent::SourceChanged);
+            }
+            Device::Local => {
+                self.send_command_to_connect_player(ConnectCommand::PlayerStop);
+                self.load_current_track(true, position_ms);
+            }
+        }
+    }
+
+    // The player starts over with the new settings, and whatever it was playing with it
+    fn reload_settings(&self) {
+        self.send_command_to_local_player(Command::ReloadSettings);
+        if !self.previews_only() {
+            let position_ms = self.app_model.get_state().playback.seek_position();
+            self.load_current_track(self.is_playing(), position_ms);
+        }
+    }
+
+    fn load_current_track(&self, resume: bool, position_ms: u32) {
+        let track = self
+            .currently_playing()
+            .and_then(|c| self.playable_id(c.song_id()));
+        if let Some(track) = track {
+            self.send_command_to_local_player(Command::PlayerLoad { track, resume });
+            self.send_command_to_local_player(Command::PlayerSeek(position_ms));
+        }
+    }
 }
 
 impl EventListener for PlayerNotifier {
@@ -219,10 +418,18 @@ impl EventListener for PlayerNotifier {
         let device = self.device().clone();
         match (device, event) {
             (_, AppEvent::LoginEvent(event)) => self.notify_login(event),
+            // The session might not have survived, the player checks
+            (device, AppEvent::NetworkRestored) => self.reconnect(device),
             (_, AppEvent::PlaybackEvent(PlaybackEvent::SwitchedDevice(d))) => self.switch_device(d),
+            (_, AppEvent::PlaybackEvent(PlaybackEvent::PlaybackTransferred(d))) => {
+                self.transfer_playback(d)
+            }
+            (Device::Local, AppEvent::PlaybackEvent(PlaybackEvent::PreviewsOnlyChanged(p))) => {
+                self.switch_to_previews(*p)
+            }
             (Device::Local, AppEvent::PlaybackEvent(event)) => self.notify_local_player(event),
             (Device::Local, AppEvent::SettingsEvent(SettingsEvent::PlayerSettingsChanged)) => {
-                self.send_command_to_local_player(Command::ReloadSettings)
+                self.reload_settings()
             }
             (Device::Connect(_), AppEvent::PlaybackEvent(event)) => {
                 self.notify_connect_player(event)
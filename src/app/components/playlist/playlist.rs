@@ -1,13 +1,15 @@
 use gio::prelude::*;
 use gtk::prelude::*;
-use std::ops::Deref;
+use std::cell::Cell;
+use std::ops::{Deref, Range};
 use std::rc::Rc;
 
-use crate::app::components::utils::{ancestor, AnimatorDefault};
+use crate::app::components::utils::{ancestor, AnimatorDefault, Debouncer};
 use crate::app::components::{Component, EventListener, SongWidget};
-use crate::app::models::{SongListModel, SongModel, SongState};
-use crate::app::state::{PlaybackEvent, SelectionEvent, SelectionState};
+use crate::app::models::{SongDescription, SongListModel, SongModel, SongState};
+use crate::app::state::{PlaybackEvent, SelectionAction, SelectionEvent, SelectionState};
 use crate::app::{AppEvent, Worker};
+use crate::settings::DisplaySettings;
 
 pub trait PlaylistModel {
     fn is_paused(&self) -> bool;
@@ -18,6 +20,9 @@ pub trait PlaylistModel {
 
     fn play_song_at(&self, pos: usize, id: &str);
 
+    // Called when placeholders got displayed for rows [start, end) (see sparse SongListModel)
+    fn load_range(&self, _start: usize, _end: usize) {}
+
     fn autoscroll_to_playing(&self) -> bool {
         true
     }
@@ -35,6 +40,8 @@ pub trait PlaylistModel {
 
     fn select_song(&self, _id: &str) {}
     fn deselect_song(&self, _id: &str) {}
+    // For the actions about several songs at once (ranges, select all...)
+    fn update_selection(&self, _action: SelectionAction) {}
     fn enable_selection(&self) -> bool {
         false
     }
@@ -61,6 +68,19 @@ pub trait PlaylistModel {
         }
     }
 
+    // When selecting everything: loads the next bit that's missing, and gets called again as it comes in.
+    // One batch at a time, so that nothing gets asked for twice
+    fn load_remaining(&self) {
+        let list_model = self.song_list_model();
+        let next = list_model
+            .needed_batches_in(0, list_model.visible_len())
+            .into_iter()
+            .next();
+        if let Some(batch) = next {
+            self.load_range(batch.offset, batch.offset + batch.batch_size);
+        }
+    }
+
     fn toggle_select(&self, id: &str) {
         if let Some(selection) = self.selection() {
             if selection.is_song_selected(id) {
@@ -72,10 +92,53 @@ pub trait PlaylistModel {
     }
 }
 
+// Placeholders rows that got displayed recently, as a [start, end) range
+#[derive(Clone, Default)]
+struct PendingRange(Rc<Cell<Option<(usize, usize)>>>);
+
+impl PendingRange {
+    fn extend(&self, pos: usize) {
+        let range = match self.0.get() {
+            Some((start, end)) => (usize::min(start, pos), usize::max(end, pos + 1)),
+            None => (pos, pos + 1),
+        };
+        self.0.set(Some(range));
+    }
+
+    fn take(&self) -> Option<(usize, usize)> {
+        self.0.take()
+    }
+}
+
+// Placeholders are skipped
+fn songs_in(list_model: &SongListModel, rows: Range<usize>) -> Vec<SongDescription> {
+    rows.filter_map(|i| list_model.index_visible(i))
+        .filter(|song| !song.is_placeholder())
+        .map(|song| song.into_description())
+        .collect()
+}
+
+fn is_shift_pressed(widget: &impl IsA<gtk::Widget>) -> bool {
+    widget
+        .display()
+        .default_seat()
+        .and_then(|seat| seat.keyboard())
+        .map(|keyboard| {
+            keyboard
+                .modifier_state()
+                .contains(gdk::ModifierType::SHIFT_MASK)
+        })
+        .unwrap_or(false)
+}
+
 pub struct Playlist<Model> {
     animator: AnimatorDefault,
     listview: gtk::ListView,
     model: Rc<Model>,
+    // Where shift-clicking selects from
+    range_anchor: Rc<Cell<Option<usize>>>,
+    // Set when asked to select everything, so that songs still loading get selected once they're in
+    selecting_all: Rc<Cell<bool>>,
 }
 
 impl<Model> Playlist<Model>
@@ -87,6 +150,13 @@ where
         let selection_model = gtk::NoSelection::new(Some(list_model.clone()));
         let factory = gtk::SignalListItemFactory::new();
 
+        // Dragging the scrollbar around binds lots of rows; only load what we end up looking at
+        let debouncer = Debouncer::new();
+        let pending_range = PendingRange::default();
+        let range_anchor: Rc<Cell<Option<usize>>> = Default::default();
+        let selecting_all: Rc<Cell<bool>> = Default::default();
+        let song_rows = Rc::new(Cell::new(DisplaySettings::new_from_gsettings().song_rows));
+
         listview.add_css_class("playlist");
         listview.set_show_separators(true);
         listview.set_valign(gtk::Align::Start);
@@ -102,13 +172,26 @@ where
             item.set_child(Some(&SongWidget::new()));
         });
 
-        factory.connect_bind(clone!(@weak model => move |_, item| {
+        factory.connect_bind(clone!(@weak model, @strong song_rows => move |_, item| {
             let item = item.downcast_ref::<gtk::ListItem>().unwrap();
             let song_model = item.item().unwrap().downcast::<SongModel>().unwrap();
             song_model.set_state(model.song_state(&song_model.get_id()));
 
             let widget = item.child().unwrap().downcast::<SongWidget>().unwrap();
-            widget.bind(&song_model, worker.clone(), model.show_song_covers());
+            widget.bind(&song_model, worker.clone(), model.show_song_covers(), song_rows.get());
+
+            if song_model.is_placeholder() {
+                pending_range.extend(item.position() as usize);
+                let pending_range = pending_range.clone();
+                debouncer.debounce(100, clone!(@weak model => move || {
+                    if let Some((start, end)) = pending_range.take() {
+                        model.load_range(start, end);
+                    }
+                }));
+                widget.set_actions(None);
+                widget.set_menu(None);
+                return;
+            }
 
             let id = &song_model.get_id();
             widget.set_actions(model.actions_for(id).as_ref());
@@ -119,19 +202,59 @@ where
             let item = item.downcast_ref::<gtk::ListItem>().unwrap();
             let song_model = item.item().unwrap().downcast::<SongModel>().unwrap();
             song_model.unbind_all();
+
+            let widget = item.child().unwrap().downcast::<SongWidget>().unwrap();
+            widget.set_actions(None);
+            widget.set_menu(None);
         });
 
-        listview.connect_activate(clone!(@weak list_model, @weak model => move |_, position| {
-            let song = list_model.index_continuous(position as usize).expect("attempt to access invalid index");
+        listview.connect_activate(clone!(@weak list_model, @weak model, @strong range_anchor => move |listview, position| {
+            let position = position as usize;
+            let song = match list_model.index_visible(position) {
+                Some(song) => song,
+                // Not loaded yet
+                None => return,
+            };
             let song = song.description();
             let selection_enabled = model.is_selection_enabled();
-            if selection_enabled {
-                model.toggle_select(&song.id);
-            } else {
-                model.play_song_at(position as usize, &song.id);
+            if !selection_enabled {
+                model.play_song_at(position, &song.id);
+                return;
+            }
+            match range_anchor.replace(Some(position)) {
+                Some(anchor) if is_shift_pressed(listview) => {
+                    let rows = usize::min(anchor, position)..usize::max(anchor, position) + 1;
+                    let songs = songs_in(&list_model, rows);
+                    model.update_selection(SelectionAction::Select(songs));
+                }
+                _ => model.toggle_select(&song.id),
             }
         }));
 
+        list_model.connect_items_changed(
+            clone!(@weak model, @strong selecting_all => move |list_model, _, _, added| {
+                if added == 0 || !selecting_all.get() {
+                    return;
+                }
+                if !model.is_selection_enabled() {
+                    selecting_all.set(false);
+                    return;
+                }
+                model.update_selection(SelectionAction::Select(list_model.collect()));
+                model.load_remaining();
+            }),
+        );
+
+        // Rows only get to pick up the setting when they're bound, so they're all bound again
+        DisplaySettings::connect_changed(
+            clone!(@weak listview, @weak factory, @weak song_rows => move |display_settings| {
+                if song_rows.replace(display_settings.song_rows) != display_settings.song_rows {
+                    listview.set_factory(None::<&gtk::ListItemFactory>);
+                    listview.set_factory(Some(&factory));
+                }
+            }),
+        );
+
         let press_gesture = gtk::GestureLongPress::new();
         press_gesture.set_touch_only(false);
         press_gesture.set_propagation_phase(gtk::PropagationPhase::Capture);
@@ -144,11 +267,33 @@ where
             animator: AnimatorDefault::ease_in_out_animator(),
             listview,
             model,
+            range_anchor,
+            selecting_all,
+        }
+    }
+
+    fn select_all(&self) {
+        if !self.model.is_selection_enabled() && !self.model.enable_selection() {
+            return;
+        }
+        self.selecting_all.set(true);
+        self.model.update_selection(SelectionAction::Select(
+            self.model.song_list_model().collect(),
+        ));
+        self.model.load_remaining();
+    }
+
+    fn invert_selection(&self) {
+        if self.model.is_selection_enabled() {
+            let list_model = self.model.song_list_model();
+            let songs = songs_in(&list_model, 0..list_model.visible_len());
+            self.selecting_all.set(false);
+            self.model.update_selection(SelectionAction::Invert(songs));
         }
     }
 
     fn autoscroll_to_playing(&self, index: usize) {
-        let len = self.model.song_list_model().partial_len() as f64;
+        let len = self.model.song_list_model().visible_len() as f64;
         let scrolled_window: Option<gtk::ScrolledWindow> = ancestor(&self.listview);
         let adj = scrolled_window.map(|w| w.vadjustment());
         if let Some(adj) = adj {
@@ -234,8 +379,20 @@ where
             }
             AppEvent::SelectionEvent(SelectionEvent::SelectionModeChanged(_)) => {
                 Self::set_selection_active(&self.listview, self.model.is_selection_enabled());
+                self.range_anchor.set(None);
                 self.update_list();
             }
+            // Only the list on screen answers
+            AppEvent::SelectionEvent(SelectionEvent::SelectAllRequested)
+                if self.listview.is_mapped() =>
+            {
+                self.select_all();
+            }
+            AppEvent::SelectionEvent(SelectionEvent::InvertRequested)
+                if self.listview.is_mapped() =>
+            {
+                self.invert_selection();
+            }
             _ => {}
         }
     }
@@ -1,13 +1,19 @@
-use crate::app::components::display_add_css_provider;
-use crate::app::loader::ImageLoader;
+use crate::app::components::dnd::attach_link_drag_source;
+use crate::app::components::resume::{is_long_form, saved_position};
+use crate::app::components::share::web_link;
+use crate::app::components::{display_add_css_provider, labels};
+use crate::app::format::song_details;
+use crate::app::loader::{load_artwork, ArtworkRequest};
 use crate::app::models::SongModel;
 use crate::app::Worker;
+use crate::settings::SongRows;
 use gio::MenuModel;
 use glib::subclass::InitializingObject;
 
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
+use std::cell::RefCell;
 
 mod imp {
 
@@ -36,11 +42,24 @@ mod imp {
         #[template_child]
         pub song_length: TemplateChild<gtk::Label>,
 
+        #[template_child]
+        pub song_details: TemplateChild<libadwaita::Squeezer>,
+
+        #[template_child]
+        pub song_details_label: TemplateChild<gtk::Label>,
+
+        #[template_child]
+        pub song_progress: TemplateChild<gtk::ProgressBar>,
+
         #[template_child]
         pub menu_btn: TemplateChild<gtk::MenuButton>,
 
         #[template_child]
         pub song_cover: TemplateChild<gtk::Image>,
+
+        pub artwork: RefCell<Option<ArtworkRequest>>,
+
+        pub link: RefCell<Option<String>>,
     }
 
     #[glib::object_subclass]
@@ -103,6 +122,11 @@ mod imp {
         fn constructed(&self) {
             self.parent_constructed();
             self.song_checkbox.set_sensitive(false);
+            let obj = self.obj();
+            attach_link_drag_source(
+                &*obj,
+                clone!(@weak obj => @default-return None, move || obj.imp().link.borrow().clone()),
+            );
         }
 
         fn dispose(&self) {
@@ -136,11 +160,14 @@ impl SongWidget {
         self.insert_action_group("song", actions);
     }
 
+    // Rows are recycled, so this also takes away the menu of the song shown before
     pub fn set_menu(&self, menu: Option<&MenuModel>) {
+        let widget = self.imp();
+        widget.menu_btn.set_menu_model(menu);
         if menu.is_some() {
-            let widget = self.imp();
-            widget.menu_btn.set_menu_model(menu);
             widget.menu_btn.add_css_class("song__menu--enabled");
+        } else {
+            widget.menu_btn.remove_css_class("song__menu--enabled");
         }
     }
 
@@ -153,24 +180,70 @@ impl SongWidget {
         }
     }
 
+    fn set_placeholder(&self, is_placeholder: bool) {
+        let song_class = "song--placeholder";
+        if is_placeholder {
+            self.add_css_class(song_class);
+            // The widget might have been showing another song's cover before being recycled
+            self.set_image(None);
+        } else {
+            self.remove_css_class(song_class);
+        }
+    }
+
+    fn set_playable(&self, is_playable: bool) {
+        let song_class = "song--unavailable";
+        if is_playable {
+            self.remove_css_class(song_class);
+            self.set_tooltip_text(None);
+        } else {
+            self.add_css_class(song_class);
+            self.set_tooltip_text(Some(&*labels::UNAVAILABLE_IN_REGION));
+        }
+    }
+
     fn set_image(&self, pixbuf: Option<&gdk_pixbuf::Pixbuf>) {
         self.imp().song_cover.set_from_pixbuf(pixbuf);
     }
 
     pub fn set_art(&self, model: &SongModel, worker: Worker) {
-        if let Some(url) = model.description().art.clone() {
-            let _self = self.downgrade();
-            worker.send_local_task(async move {
-                if let Some(_self) = _self.upgrade() {
-                    let loader = ImageLoader::new();
-                    let result = loader.load_remote(&url, "jpg", 100, 100).await;
-                    _self.set_image(result.as_ref());
-                }
-            });
+        // Rows get recycled, whatever this row was waiting for is no longer relevant
+        let request = model.description().art.clone().map(|url| {
+            load_artwork(self, worker, url, 100, |_self, pixbuf| {
+                _self.set_image(pixbuf.as_ref());
+            })
+        });
+        self.imp().artwork.replace(request);
+    }
+
+    fn set_details(&self, model: &SongModel, song_rows: SongRows) {
+        let widget = self.imp();
+        let detailed = song_rows == SongRows::Detailed && !model.is_placeholder();
+        widget.song_details.set_visible(detailed);
+        if detailed {
+            let song = model.description();
+            widget.song_details_label.set_label(&song_details(
+                &song.album.name,
+                song.added_at.as_deref(),
+                song.popularity,
+            ));
         }
     }
 
-    pub fn bind(&self, model: &SongModel, worker: Worker, show_cover: bool) {
+    // Only read when the row gets bound, which is good enough to see where we're at in a list
+    fn set_progress(&self, model: &SongModel) {
+        let progress = {
+            let song = model.description();
+            Some(&*song)
+                .filter(|song| !model.is_placeholder() && is_long_form(song))
+                .and_then(|song| Some(saved_position(&song.id)? as f64 / song.duration as f64))
+        };
+        let bar = &self.imp().song_progress;
+        bar.set_visible(progress.is_some());
+        bar.set_fraction(progress.unwrap_or(0.0));
+    }
+
+    pub fn bind(&self, model: &SongModel, worker: Worker, show_cover: bool, song_rows: SongRows) {
         let widget = self.imp();
 
         model.bind_title(&*widget.song_title, "label");
@@ -180,6 +253,14 @@ impl SongWidget {
         model.bind_selected(self, "selected");
 
         self.set_show_cover(show_cover);
+        self.set_placeholder(model.is_placeholder());
+        self.set_playable(model.description().is_playable);
+        self.set_progress(model);
+        self.set_details(model, song_rows);
+        let kind = model.description().share_kind();
+        widget
+            .link
+            .replace(Some(web_link(kind, &model.get_id())).filter(|_| !model.is_placeholder()));
         if show_cover {
             self.set_art(model, worker);
         } else {
@@ -1,7 +1,8 @@
-use gdk::prelude::*;
 use gio::SimpleAction;
 
-use crate::app::models::SongDescription;
+use crate::app::components::labels;
+use crate::app::components::share::{self, ShareKind};
+use crate::app::models::{BlockedItem, SongDescription};
 use crate::app::state::{AppAction, PlaybackAction};
 use crate::app::ActionDispatcher;
 
@@ -32,17 +33,20 @@ impl SongDescription {
         dequeue
     }
 
+    pub fn share_kind(&self) -> ShareKind {
+        if self.is_chapter() {
+            ShareKind::Episode
+        } else {
+            ShareKind::Track
+        }
+    }
+
     pub fn make_link_action(&self, name: Option<&str>) -> SimpleAction {
-        let track_id = self.id.clone();
-        let copy_link = SimpleAction::new(name.unwrap_or("copy_link"), None);
-        copy_link.connect_activate(move |_, _| {
-            let link = format!("https://open.spotify.com/track/{track_id}");
-            let clipboard = gdk::Display::default().unwrap().clipboard();
-            clipboard
-                .set_content(Some(&gdk::ContentProvider::for_value(&link.to_value())))
-                .expect("Failed to set clipboard content");
-        });
-        copy_link
+        share::make_link_action(self.share_kind(), &self.id, name)
+    }
+
+    pub fn make_uri_action(&self, name: Option<&str>) -> SimpleAction {
+        share::make_uri_action(self.share_kind(), &self.id, name)
     }
 
     pub fn make_album_action(
@@ -55,6 +59,8 @@ impl SongDescription {
         view_album.connect_activate(move |_, _| {
             dispatcher.dispatch(AppAction::ViewAlbum(album_id.clone()));
         });
+        // The "album" of a chapter is its audiobook, which has no page here
+        view_album.set_enabled(!self.is_chapter());
         view_album
     }
 
@@ -75,8 +81,48 @@ impl SongDescription {
                 view_artist.connect_activate(move |_, _| {
                     dispatcher.dispatch(AppAction::ViewArtist(id.clone()));
                 });
+                // Audiobook authors aren't artists we could go to
+                view_artist.set_enabled(!id.is_empty());
                 view_artist
             })
             .collect()
     }
+
+    // "block" for the song itself, "block_artist_<id>" for each of its artists
+    pub fn make_block_actions(&self, dispatcher: Box<dyn ActionDispatcher>) -> Vec<SimpleAction> {
+        let block = SimpleAction::new("block", None);
+        let item = BlockedItem::song(self);
+        let song_dispatcher = dispatcher.box_clone();
+        block.connect_activate(move |_, _| {
+            song_dispatcher.dispatch(PlaybackAction::Block(item.clone()).into());
+        });
+
+        let artists = self
+            .artists
+            .iter()
+            .filter(|artist| !artist.id.is_empty())
+            .map(|artist| {
+                let block_artist = SimpleAction::new(&format!("block_artist_{}", artist.id), None);
+                let item = BlockedItem::artist(&artist.id, &artist.name);
+                let dispatcher = dispatcher.box_clone();
+                block_artist.connect_activate(move |_, _| {
+                    dispatcher.dispatch(PlaybackAction::Block(item.clone()).into());
+                });
+                block_artist
+            });
+        std::iter::once(block).chain(artists).collect()
+    }
+
+    // A section of its own, for the actions above
+    pub fn make_block_menu(&self) -> gio::Menu {
+        let menu = gio::Menu::new();
+        menu.append(Some(&*labels::DONT_PLAY_SONG), Some("song.block"));
+        for artist in self.artists.iter().filter(|artist| !artist.id.is_empty()) {
+            menu.append(
+                Some(&labels::dont_play_artist_label(&artist.name)),
+                Some(&format!("song.block_artist_{}", artist.id)),
+            );
+        }
+        menu
+    }
 }
@@ -1,19 +1,24 @@
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
+use libadwaita::prelude::*;
 use std::rc::Rc;
 
 use super::playlist_header::PlaylistHeaderWidget;
 use super::playlist_headerbar::PlaylistHeaderBarWidget;
 use super::PlaylistDetailsModel;
 
+use crate::app::components::dnd::attach_link_drop_target;
 use crate::app::components::{
-    Component, EventListener, Playlist, PlaylistModel, ScrollingHeaderWidget,
+    labels, Component, EventListener, Playlist, PlaylistModel, ScrollingHeaderWidget,
 };
 use crate::app::dispatch::Worker;
+use crate::app::format::songs_totals;
 use crate::app::loader::ImageLoader;
-use crate::app::state::{PlaybackEvent, SelectionEvent};
+use crate::app::models::PlaylistChanges;
+use crate::app::state::{PlaybackEvent, ScreenName, SelectionEvent};
 use crate::app::{AppEvent, BrowserEvent};
+use crate::settings;
 use libadwaita::subclass::prelude::BinImpl;
 
 mod imp {
@@ -26,6 +31,21 @@ mod imp {
         #[template_child]
         pub headerbar: TemplateChild<PlaylistHeaderBarWidget>,
 
+        #[template_child]
+        pub loading_progress: TemplateChild<gtk::ProgressBar>,
+
+        #[template_child]
+        pub changes_revealer: TemplateChild<gtk::Revealer>,
+
+        #[template_child]
+        pub changes_summary: TemplateChild<gtk::Label>,
+
+        #[template_child]
+        pub changes_apply: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub changes_list: TemplateChild<gtk::ListBox>,
+
         #[template_child]
         pub scrolling_header: TemplateChild<ScrollingHeaderWidget>,
 
@@ -116,6 +136,60 @@ impl PlaylistDetailsWidget {
             .add_css_class("container--loaded");
     }
 
+    fn set_loading_progress(&self, progress: Option<f64>) {
+        let bar = &self.imp().loading_progress;
+        bar.set_visible(progress.is_some());
+        bar.set_fraction(progress.unwrap_or(0.0));
+    }
+
+    fn set_changes(&self, changes: Option<&PlaylistChanges>) {
+        let widget = self.imp();
+        let list = &widget.changes_list;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        widget.changes_revealer.set_reveal_child(changes.is_some());
+        let Some(changes) = changes else {
+            return;
+        };
+        widget
+            .changes_summary
+            .set_label(&labels::n_playlist_changes_label(changes.count()));
+
+        let kinds = [
+            (&changes.added, "list-add-symbolic", &*labels::SONG_ADDED),
+            (
+                &changes.removed,
+                "list-remove-symbolic",
+                &*labels::SONG_REMOVED,
+            ),
+            (
+                &changes.moved,
+                "view-sort-descending-symbolic",
+                &*labels::SONG_MOVED,
+            ),
+        ];
+        for (songs, icon, kind) in kinds {
+            for song in songs {
+                let row = libadwaita::ActionRow::builder()
+                    .title(glib::markup_escape_text(&song.title))
+                    .subtitle(glib::markup_escape_text(&song.artists_name()))
+                    .build();
+                let icon = gtk::Image::from_icon_name(icon);
+                icon.set_tooltip_text(Some(kind));
+                row.add_prefix(&icon);
+                list.append(&row);
+            }
+        }
+    }
+
+    fn connect_apply_changes<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.imp().changes_apply.connect_clicked(move |_| f());
+    }
+
     fn set_editing(&self, editing: bool) {
         self.imp().header_widget.set_editing(editing);
         self.imp().header_mobile.set_editing(editing);
@@ -132,6 +206,19 @@ impl PlaylistDetailsWidget {
         self.imp().headerbar.set_title(Some(playlist));
     }
 
+    fn set_summary(&self, summary: &str, complete: bool) {
+        self.imp().header_widget.set_summary(summary, complete);
+        self.imp().header_mobile.set_summary(summary, complete);
+    }
+
+    fn connect_totals<F>(&self, f: F)
+    where
+        F: Fn() + Clone + 'static,
+    {
+        self.imp().header_widget.connect_totals(f.clone());
+        self.imp().header_mobile.connect_totals(f);
+    }
+
     fn set_playing(&self, is_playing: bool) {
         self.imp().header_widget.set_playing(is_playing);
         self.imp().header_mobile.set_playing(is_playing);
@@ -220,6 +307,11 @@ impl PlaylistDetails {
 
         widget.set_editable(model.is_playlist_editable());
 
+        attach_link_drop_target(
+            &widget,
+            clone!(@weak model => move |links| model.add_links(links)),
+        );
+
         widget.connect_header();
 
         widget.connect_bottom_edge(clone!(@weak model => move || {
@@ -240,8 +332,13 @@ impl PlaylistDetails {
 
         widget.connect_play(clone!(@weak model => move || model.toggle_play_playlist()));
 
+        widget.connect_totals(clone!(@weak model => move || model.load_remaining()));
+
         widget.connect_go_back(clone!(@weak model => move || model.go_back()));
 
+        widget.connect_apply_changes(clone!(@weak model => move || model.apply_changes()));
+        widget.set_changes(model.get_changes().as_deref());
+
         Self {
             model,
             worker,
@@ -257,6 +354,7 @@ impl PlaylistDetails {
             let art_url = info.art.as_ref();
 
             self.widget.set_info(title, owner);
+            self.update_summary();
 
             if let Some(art_url) = art_url.cloned() {
                 let widget = self.widget.downgrade();
@@ -275,6 +373,14 @@ impl PlaylistDetails {
         }
     }
 
+    // The length (and so the size) is only known once every song is in
+    fn update_summary(&self) {
+        let songs = self.model.song_list_model();
+        let total_duration = songs.total_duration();
+        let summary = songs_totals(songs.len(), total_duration, settings::bitrate_kbps());
+        self.widget.set_summary(&summary, total_duration.is_some());
+    }
+
     fn update_playing(&self, is_playing: bool) {
         if !self.model.playlist_is_playing() || !self.model.is_playing() {
             self.widget.set_playing(false);
@@ -310,6 +416,31 @@ impl EventListener for PlaylistDetails {
                 self.update_details();
                 self.update_playing(true);
             }
+            AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::PlaylistDetails(
+                id,
+            ))) if id == &self.model.id => {
+                self.model.load_playlist_info();
+            }
+            AppEvent::BrowserEvent(BrowserEvent::PlaylistChangesUpdated(id))
+                if id == &self.model.id =>
+            {
+                self.widget.set_changes(self.model.get_changes().as_deref());
+                self.model.resume_held_load();
+            }
+            AppEvent::BrowserEvent(BrowserEvent::PlaylistTracksAppended(id))
+                if id == &self.model.id =>
+            {
+                self.widget
+                    .set_loading_progress(self.model.loading_progress());
+                self.update_summary();
+            }
+            AppEvent::BrowserEvent(BrowserEvent::PlaylistTracksLoaded(id))
+                if id == &self.model.id =>
+            {
+                self.model.finish_loading_all();
+                self.widget.set_loading_progress(None);
+                self.update_summary();
+            }
             AppEvent::SelectionEvent(SelectionEvent::SelectionModeChanged(editing)) => {
                 self.set_editing(*editing);
             }
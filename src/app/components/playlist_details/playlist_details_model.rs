@@ -1,12 +1,17 @@
+use futures::stream::{self, StreamExt};
 use gettextrs::gettext;
 use gio::prelude::*;
 use gio::SimpleActionGroup;
-use std::cell::Ref;
+use std::cell::{Cell, Ref};
+use std::collections::HashSet;
 use std::ops::Deref;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use crate::api::SpotifyApiError;
-use crate::app::components::{labels, PlaylistModel};
+use crate::app::components::dnd::songs_for_links;
+use crate::app::components::{add_to_playlist, labels, PlaylistModel};
 use crate::app::models::*;
 use crate::app::state::SelectionContext;
 use crate::app::state::{BrowserAction, PlaybackAction, SelectionAction, SelectionState};
@@ -17,6 +22,18 @@ pub struct PlaylistDetailsModel {
     pub id: String,
     app_model: Rc<AppModel>,
     dispatcher: Box<dyn ActionDispatcher>,
+    // Whether all the songs are being loaded at once (see load_all_tracks)
+    loading_all: Cell<bool>,
+    // Whether the songs it loads also go to the play queue, which can be decided halfway
+    playing_all: Arc<AtomicBool>,
+    // What was asked for while changes were pending, see resume_held_load
+    held_load: Cell<Option<HeldLoad>>,
+}
+
+#[derive(Clone, Copy)]
+enum HeldLoad {
+    More,
+    All { play: bool },
 }
 
 impl PlaylistDetailsModel {
@@ -25,6 +42,9 @@ impl PlaylistDetailsModel {
             id,
             app_model,
             dispatcher,
+            loading_all: Cell::new(false),
+            playing_all: Arc::new(AtomicBool::new(false)),
+            held_load: Cell::new(None),
         }
     }
 
@@ -37,6 +57,34 @@ impl PlaylistDetailsModel {
         state.logged_user.playlists.iter().any(|p| p.id == self.id)
     }
 
+    // Links dropped on one of the user's playlist get added at the end of it,
+    // except for songs it already has (as far as the songs loaded so far tell)
+    pub fn add_links(&self, links: Vec<SpotifyLink>) {
+        if !self.is_playlist_editable() {
+            return;
+        }
+        let api = self.app_model.get_spotify();
+        let id = self.id.clone();
+        let loaded: HashSet<String> = self
+            .song_list_model()
+            .map_collect(|song| song.uri)
+            .into_iter()
+            .collect();
+        self.dispatcher
+            .write_spotify_and_dispatch_many(move || async move {
+                let songs = songs_for_links(api.clone(), links).await?;
+                let uris: Vec<String> = songs
+                    .into_iter()
+                    .map(|song| song.uri)
+                    .filter(|uri| !loaded.contains(uri))
+                    .collect();
+                if uris.is_empty() {
+                    return Ok(vec![]);
+                }
+                add_to_playlist(api, id, uris).await
+            });
+    }
+
     pub fn get_playlist_info(&self) -> Option<impl Deref<Target = PlaylistDescription> + '_> {
         self.app_model.map_state_opt(|s| {
             s.browser
@@ -88,56 +136,189 @@ impl PlaylistDetailsModel {
         }
     }
 
+    // If the first batch of songs changed since it was cached (someone else edited it, say), the cached one
+    // is shown first along with what changed, the new one replaces it once that's been looked at
     pub fn load_playlist_info(&self) {
         let api = self.app_model.get_spotify();
         let id = self.id.clone();
         self.dispatcher
-            .call_spotify_and_dispatch(move || async move {
+            .call_spotify_and_dispatch_many(move || async move {
                 let playlist = api.get_playlist(&id).await;
-                let playlist_tracks = api.get_playlist_tracks(&id, 0, 100).await?;
+                let (playlist_tracks, previous_tracks) =
+                    api.get_playlist_tracks_with_previous(&id, 0, 100).await?;
                 match playlist {
-                    Ok(playlist) => Ok(BrowserAction::SetPlaylistDetails(
-                        Box::new(playlist),
-                        Box::new(playlist_tracks),
-                    )
-                    .into()),
+                    Ok(playlist) => {
+                        let changes = previous_tracks
+                            .map(|previous| {
+                                let changes =
+                                    PlaylistChanges::between_batches(&previous, &playlist_tracks);
+                                (previous, changes)
+                            })
+                            .filter(|(_, changes)| !changes.is_empty());
+                        let Some((previous_tracks, changes)) = changes else {
+                            return Ok(vec![BrowserAction::SetPlaylistDetails(
+                                Box::new(playlist),
+                                Box::new(playlist_tracks),
+                            )
+                            .into()]);
+                        };
+                        Ok(vec![
+                            BrowserAction::SetPlaylistDetails(
+                                Box::new(playlist),
+                                Box::new(previous_tracks),
+                            )
+                            .into(),
+                            BrowserAction::SetPlaylistChanges(
+                                id,
+                                Box::new(changes),
+                                Box::new(playlist_tracks),
+                            )
+                            .into(),
+                        ])
+                    }
                     Err(SpotifyApiError::BadStatus(400, _))
                     | Err(SpotifyApiError::BadStatus(404, _)) => {
-                        Ok(BrowserAction::NavigationPop.into())
+                        Ok(vec![BrowserAction::NavigationPop.into()])
                     }
                     Err(e) => Err(e),
                 }
             });
     }
 
+    pub fn get_changes(&self) -> Option<impl Deref<Target = PlaylistChanges> + '_> {
+        self.app_model.map_state_opt(|s| {
+            s.browser
+                .playlist_details_state(&self.id)?
+                .changes
+                .as_ref()
+                .map(|(changes, _)| changes)
+        })
+    }
+
+    pub fn apply_changes(&self) {
+        self.dispatcher
+            .dispatch(BrowserAction::ApplyPlaylistChanges(self.id.clone()).into());
+    }
+
+    // Until the pending changes are applied, the songs shown are from the cached version:
+    // later batches come from the new one and wouldn't fit with them
+    fn hold_load(&self, load: HeldLoad) -> bool {
+        if self.get_changes().is_none() {
+            return false;
+        }
+        if !matches!(self.held_load.get(), Some(HeldLoad::All { .. })) {
+            self.held_load.set(Some(load));
+        }
+        true
+    }
+
+    pub fn resume_held_load(&self) {
+        if self.get_changes().is_some() {
+            return;
+        }
+        match self.held_load.take() {
+            Some(HeldLoad::More) => {
+                self.load_more_tracks();
+            }
+            Some(HeldLoad::All { play }) => self.load_all_tracks(play),
+            None => {}
+        }
+    }
+
     pub fn load_more_tracks(&self) -> Option<()> {
+        if self.hold_load(HeldLoad::More) {
+            return None;
+        }
         let last_batch = self.song_list_model().last_batch()?;
         let query = BatchQuery {
             source: SongsSource::Playlist(self.id.clone()),
             batch: last_batch,
         };
 
-        let id = self.id.clone();
         let next_query = query.next()?;
         debug!("next_query = {:?}", &next_query);
+        self.load_tracks(next_query);
+
+        Some(())
+    }
+
+    fn load_tracks(&self, query: BatchQuery) {
+        let id = self.id.clone();
         let loader = self.app_model.get_batch_loader();
 
         self.dispatcher.dispatch_async(Box::pin(async move {
             loader
-                .query(next_query, |_s, song_batch| {
+                .query(query, |_s, song_batch| {
                     BrowserAction::AppendPlaylistTracks(id, Box::new(song_batch)).into()
                 })
                 .await
         }));
+    }
 
-        Some(())
+    // Whatever is missing, a few batches at a time, for when the whole playlist is needed (playing or selecting all of it)
+    fn load_all_tracks(&self, play: bool) {
+        // Only one at a time, the one that's running is told what else to do with the songs
+        if self.loading_all.get() {
+            if play {
+                self.playing_all.store(true, Ordering::Relaxed);
+            }
+            return;
+        }
+        // The whole playlist is wanted, so that's the new version
+        if self.hold_load(HeldLoad::All { play }) {
+            self.apply_changes();
+            return;
+        }
+        let songs = self.song_list_model();
+        let queries: Vec<BatchQuery> = songs
+            .needed_batches_in(0, songs.len())
+            .into_iter()
+            .map(|batch| BatchQuery {
+                source: SongsSource::Playlist(self.id.clone()),
+                batch,
+            })
+            .collect();
+        if queries.is_empty() {
+            return;
+        }
+        self.loading_all.set(true);
+        self.playing_all.store(play, Ordering::Relaxed);
+
+        let id = self.id.clone();
+        let playing_all = Arc::clone(&self.playing_all);
+        // Counting songs can't tell when we're done: unavailable tracks are dropped and batches can fail
+        let finished: AppAction = BrowserAction::FinishLoadingPlaylistTracks(id.clone()).into();
+        let loader = self.app_model.get_batch_loader();
+        let actions = loader.load_all(queries, move |source, song_batch| {
+            let mut actions: Vec<AppAction> =
+                vec![BrowserAction::AppendPlaylistTracks(id, Box::new(song_batch.clone())).into()];
+            if playing_all.load(Ordering::Relaxed) {
+                actions.push(PlaybackAction::AppendPagedSongs(source, song_batch).into());
+            }
+            actions
+        });
+        self.dispatcher
+            .dispatch_stream(actions.chain(stream::once(async move { finished })).boxed());
+    }
+
+    pub fn finish_loading_all(&self) {
+        self.loading_all.set(false);
+    }
+
+    // How much of the playlist is loaded so far, while it's being loaded all at once
+    pub fn loading_progress(&self) -> Option<f64> {
+        if !self.loading_all.get() {
+            return None;
+        }
+        let songs = self.song_list_model();
+        Some((songs.partial_len() as f64 / songs.len().max(1) as f64).min(1.0))
     }
 
     pub fn update_playlist_details(&self, title: String) {
         let api = self.app_model.get_spotify();
         let id = self.id.clone();
         self.dispatcher
-            .call_spotify_and_dispatch(move || async move {
+            .write_spotify_and_dispatch(move || async move {
                 let playlist = api.update_playlist_details(&id, title.clone()).await;
                 match playlist {
                     Ok(_) => Ok(AppAction::UpdatePlaylistName(PlaylistSummary { id, title })),
@@ -182,14 +363,40 @@ impl PlaylistModel for PlaylistDetailsModel {
         self.state().playback.current_song_id()
     }
 
+    // Like albums, the whole playlist gets queued: what's loaded right away, the rest as it comes
     fn play_song_at(&self, pos: usize, id: &str) {
         let source = SongsSource::Playlist(self.id.clone());
-        let batch = self.song_list_model().song_batch_for(pos);
-        if let Some(batch) = batch {
-            self.dispatcher
-                .dispatch(PlaybackAction::LoadPagedSongs(source, batch).into());
+        let songs = self.song_list_model();
+        let Some(batch) = songs.song_batch_for(pos) else {
+            return;
+        };
+        self.dispatcher
+            .dispatch(PlaybackAction::LoadPagedSongs(source.clone(), batch).into());
+        for batch in songs.song_batches() {
             self.dispatcher
-                .dispatch(PlaybackAction::Load(id.to_string()).into());
+                .dispatch(PlaybackAction::AppendPagedSongs(source.clone(), batch).into());
+        }
+        self.dispatcher
+            .dispatch(PlaybackAction::Load(id.to_string()).into());
+        self.load_all_tracks(true);
+    }
+
+    // Not one batch at a time as the default does, that's slow for big playlists
+    fn load_remaining(&self) {
+        if self.loading_progress().is_none() {
+            self.load_all_tracks(false);
+        }
+    }
+
+    fn load_range(&self, start: usize, end: usize) {
+        if self.hold_load(HeldLoad::More) {
+            return;
+        }
+        for batch in self.song_list_model().needed_batches_in(start, end) {
+            self.load_tracks(BatchQuery {
+                source: SongsSource::Playlist(self.id.clone()),
+                batch,
+            });
         }
     }
 
@@ -204,7 +411,11 @@ impl PlaylistModel for PlaylistDetailsModel {
         }
         group.add_action(&song.make_album_action(self.dispatcher.box_clone(), None));
         group.add_action(&song.make_link_action(None));
+        group.add_action(&song.make_uri_action(None));
         group.add_action(&song.make_queue_action(self.dispatcher.box_clone(), None));
+        for block in song.make_block_actions(self.dispatcher.box_clone()) {
+            group.add_action(&block);
+        }
 
         Some(group.upcast())
     }
@@ -223,7 +434,9 @@ impl PlaylistModel for PlaylistDetailsModel {
         }
 
         menu.append(Some(&*labels::COPY_LINK), Some("song.copy_link"));
+        menu.append(Some(&*labels::COPY_URI), Some("song.copy_uri"));
         menu.append(Some(&*labels::ADD_TO_QUEUE), Some("song.queue"));
+        menu.append_section(None, &song.make_block_menu());
 
         Some(menu.upcast())
     }
@@ -241,6 +454,10 @@ impl PlaylistModel for PlaylistDetailsModel {
             .dispatch(SelectionAction::Deselect(vec![id.to_string()]).into());
     }
 
+    fn update_selection(&self, action: SelectionAction) {
+        self.dispatcher.dispatch(action.into());
+    }
+
     fn enable_selection(&self) -> bool {
         self.dispatcher
             .dispatch(AppAction::EnableSelection(if self.is_playlist_editable() {
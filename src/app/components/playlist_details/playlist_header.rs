@@ -35,6 +35,15 @@ mod imp {
         #[template_child]
         pub author_button_label: TemplateChild<gtk::Label>,
 
+        #[template_child]
+        pub summary_box: TemplateChild<gtk::Box>,
+
+        #[template_child]
+        pub summary_label: TemplateChild<gtk::Label>,
+
+        #[template_child]
+        pub totals_button: TemplateChild<gtk::Button>,
+
         #[template_child]
         pub play_button: TemplateChild<gtk::Button>,
 
@@ -136,6 +145,20 @@ impl PlaylistHeaderWidget {
         widget.author_button_label.set_label(owner);
     }
 
+    // Said as far as it's known, the button loads whatever is missing
+    pub fn set_summary(&self, summary: &str, complete: bool) {
+        let widget = self.imp();
+        widget.summary_label.set_label(summary);
+        widget.totals_button.set_visible(!complete);
+    }
+
+    pub fn connect_totals<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.imp().totals_button.connect_clicked(move |_| f());
+    }
+
     pub fn set_playing(&self, is_playing: bool) {
         let playback_icon = if is_playing {
             "media-playback-pause-symbolic"
@@ -162,6 +185,8 @@ impl PlaylistHeaderWidget {
         widget.playlist_image_box.set_margin_start(0);
         widget.playlist_label_entry.set_xalign(0.5);
         widget.author_button.set_halign(gtk::Align::Center);
+        widget.summary_box.set_halign(gtk::Align::Center);
+        widget.summary_box.set_margin_start(0);
     }
 
     pub fn set_editing(&self, editing: bool) {
@@ -0,0 +1,63 @@
+use gettextrs::gettext;
+use gtk::prelude::*;
+use std::rc::Rc;
+
+use crate::app::state::PlaybackAction;
+use crate::app::{ActionDispatcher, AppAction};
+
+// Plays the 30 second previews when librespot can't play the full songs.
+// GTK hands the link to GStreamer, which streams it over HTTP like any other media.
+pub struct PreviewPlayer {
+    media: gtk::MediaFile,
+}
+
+impl PreviewPlayer {
+    pub fn new(dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        let media = gtk::MediaFile::new();
+        let dispatcher: Rc<dyn ActionDispatcher> = Rc::from(dispatcher);
+
+        media.connect_ended_notify(clone!(@strong dispatcher => move |media| {
+            if media.is_ended() {
+                dispatcher.dispatch(PlaybackAction::Next.into());
+            }
+        }));
+        media.connect_error_notify(move |media| {
+            if let Some(error) = media.error() {
+                warn!("Could not play the preview: {}", error);
+                dispatcher.dispatch(AppAction::ShowNotification(
+                    // translators: Shown when the 30 second preview of a song (played without Spotify Premium) fails to play
+                    gettext("Could not play the preview"),
+                ));
+            }
+        });
+
+        Self { media }
+    }
+
+    pub fn load(&self, url: &str, play: bool) {
+        self.media.set_file(Some(&gio::File::for_uri(url)));
+        if play {
+            self.media.play();
+        }
+    }
+
+    pub fn resume(&self) {
+        self.media.play();
+    }
+
+    pub fn pause(&self) {
+        self.media.pause();
+    }
+
+    pub fn stop(&self) {
+        self.media.clear();
+    }
+
+    pub fn seek(&self, position_ms: u32) {
+        self.media.seek(position_ms as i64 * 1000);
+    }
+
+    pub fn set_volume(&self, volume: f64) {
+        self.media.set_volume(volume);
+    }
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+mod quick_open;
+pub use quick_open::*;
+
+mod quick_open_model;
+pub use quick_open_model::*;
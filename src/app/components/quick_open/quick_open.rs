@@ -0,0 +1,269 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::CompositeTemplate;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::app::components::utils::Debouncer;
+use crate::app::components::EventListener;
+use crate::app::state::LoginEvent;
+use crate::app::{AppEvent, BrowserEvent, Worker};
+
+use super::{QuickOpenItem, QuickOpenModel};
+
+// Typing is given that long to settle before Spotify gets asked
+const REMOTE_SEARCH_DELAY_MS: u32 = 400;
+
+mod imp {
+
+    use libadwaita::subclass::prelude::AdwWindowImpl;
+
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/dev/alextren/Spot/components/quick_open.ui")]
+    pub struct QuickOpenWindow {
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+
+        #[template_child]
+        pub results: TemplateChild<gtk::ListBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for QuickOpenWindow {
+        const NAME: &'static str = "QuickOpenWindow";
+        type Type = super::QuickOpenWindow;
+        type ParentType = libadwaita::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for QuickOpenWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            self.search_entry
+                .connect_stop_search(clone!(@weak obj => move |_| obj.set_visible(false)));
+
+            // The focus stays in the entry, the arrows move through the results from there
+            let controller = gtk::EventControllerKey::new();
+            controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+            controller.connect_key_pressed(
+                clone!(@weak obj => @default-return gtk::Inhibit(false), move |_, keyval, _, _| {
+                    let step = match keyval {
+                        gdk::Key::Up => -1,
+                        gdk::Key::Down => 1,
+                        _ => return gtk::Inhibit(false),
+                    };
+                    obj.move_selection(step);
+                    gtk::Inhibit(true)
+                }),
+            );
+            self.search_entry.add_controller(controller);
+        }
+    }
+
+    impl WidgetImpl for QuickOpenWindow {}
+    impl AdwWindowImpl for QuickOpenWindow {}
+    impl WindowImpl for QuickOpenWindow {}
+}
+
+glib::wrapper! {
+    pub struct QuickOpenWindow(ObjectSubclass<imp::QuickOpenWindow>) @extends gtk::Widget, gtk::Window, libadwaita::Window;
+}
+
+impl Default for QuickOpenWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuickOpenWindow {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    fn query(&self) -> String {
+        self.imp().search_entry.text().to_string()
+    }
+
+    fn connect_query_changed<F>(&self, f: F)
+    where
+        F: Fn(String) + 'static,
+    {
+        self.imp()
+            .search_entry
+            .connect_search_changed(move |entry| f(entry.text().to_string()));
+    }
+
+    // Called with the index of the result, and whether it should be played rather than opened
+    fn connect_chosen<F>(&self, f: F)
+    where
+        F: Fn(usize, bool) + Clone + 'static,
+    {
+        let widget = self.imp();
+
+        let controller = gtk::EventControllerKey::new();
+        controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        controller.connect_key_pressed(
+            clone!(@weak self as _self, @strong f => @default-return gtk::Inhibit(false), move |_, keyval, _, state| {
+                if keyval != gdk::Key::Return && keyval != gdk::Key::KP_Enter {
+                    return gtk::Inhibit(false);
+                }
+                if let Some(row) = _self.imp().results.selected_row() {
+                    _self.set_visible(false);
+                    f(row.index() as usize, state.contains(gdk::ModifierType::SHIFT_MASK));
+                }
+                gtk::Inhibit(true)
+            }),
+        );
+        widget.search_entry.add_controller(controller);
+
+        widget
+            .results
+            .connect_row_activated(clone!(@weak self as _self => move |_, row| {
+                _self.set_visible(false);
+                f(row.index() as usize, false);
+            }));
+    }
+
+    fn move_selection(&self, step: i32) {
+        let results = &self.imp().results;
+        let current = results.selected_row().map(|row| row.index()).unwrap_or(-1);
+        if let Some(row) = results.row_at_index(current + step) {
+            results.select_row(Some(&row));
+        }
+    }
+
+    fn set_items(&self, items: &[QuickOpenItem]) {
+        let results = &self.imp().results;
+        while let Some(child) = results.first_child() {
+            results.remove(&child);
+        }
+        for item in items {
+            let row = libadwaita::ActionRow::builder()
+                .title(glib::markup_escape_text(&item.title))
+                .subtitle(glib::markup_escape_text(&item.subtitle))
+                .activatable(true)
+                .build();
+            results.append(&row);
+        }
+        results.select_row(results.row_at_index(0).as_ref());
+    }
+
+    fn reset(&self) {
+        let widget = self.imp();
+        widget.search_entry.set_text("");
+        self.set_items(&[]);
+        widget.search_entry.grab_focus();
+    }
+}
+
+// A palette to jump to (or play) a playlist, album or artist by typing part of its name.
+// What's in the library comes first, Spotify's search fills in when that's not enough.
+pub struct QuickOpen {
+    parent: gtk::Window,
+    widget: QuickOpenWindow,
+    model: Rc<QuickOpenModel>,
+    worker: Worker,
+}
+
+impl QuickOpen {
+    pub fn new(parent: gtk::Window, model: QuickOpenModel, worker: Worker) -> Self {
+        let model = Rc::new(model);
+        let widget = QuickOpenWindow::new();
+        // What's shown, in the same order
+        let items: Rc<RefCell<Vec<QuickOpenItem>>> = Default::default();
+        let debouncer = Debouncer::new();
+
+        widget.connect_query_changed(
+            clone!(@weak model, @weak widget, @strong items => move |query| {
+                let matches = model.matches(&query);
+                let search_remote = !query.trim().is_empty() && model.needs_remote_search(&matches);
+                widget.set_items(&matches);
+                items.replace(matches);
+
+                debouncer.cancel();
+                if !search_remote {
+                    return;
+                }
+                debouncer.debounce(
+                    REMOTE_SEARCH_DELAY_MS,
+                    clone!(@weak model, @weak widget, @strong items, @strong worker => move || {
+                        let search = model.search_remote(query.clone());
+                        let query = query.clone();
+                        let widget = widget.downgrade();
+                        let items = Rc::clone(&items);
+                        worker.send_local_task(async move {
+                            let found = search.await;
+                            // Typed something else in the meantime
+                            let Some(widget) = widget.upgrade().filter(|w| w.query() == query) else {
+                                return;
+                            };
+                            let mut items = items.borrow_mut();
+                            for item in found {
+                                if !items.iter().any(|i| i.target == item.target) {
+                                    items.push(item);
+                                }
+                            }
+                            widget.set_items(&items);
+                        });
+                    }),
+                );
+            }),
+        );
+
+        widget.connect_chosen(clone!(@weak model, @strong items => move |index, play| {
+            let Some(item) = items.borrow().get(index).cloned() else {
+                return;
+            };
+            if play {
+                model.play(&item.target);
+            } else {
+                model.open(&item.target);
+            }
+        }));
+
+        Self {
+            parent,
+            widget,
+            model,
+            worker,
+        }
+    }
+
+    fn show_self(&self) {
+        self.widget.set_transient_for(Some(&self.parent));
+        self.widget.present();
+        self.widget.reset();
+    }
+
+    // Once logged in, and again whenever the library changes
+    fn index_library(&self) {
+        self.worker.send_local_task(self.model.index_library());
+    }
+}
+
+impl EventListener for QuickOpen {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::QuickOpenShown => self.show_self(),
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_))
+            | AppEvent::LoginEvent(LoginEvent::UserPlaylistsLoaded)
+            | AppEvent::BrowserEvent(BrowserEvent::ArtistFollowed(_))
+            | AppEvent::BrowserEvent(BrowserEvent::ArtistUnfollowed(_))
+            | AppEvent::BrowserEvent(BrowserEvent::AlbumSaved(_))
+            | AppEvent::BrowserEvent(BrowserEvent::AlbumUnsaved(_))
+            | AppEvent::BrowserEvent(BrowserEvent::SavedPlaylistsUpdated)
+            | AppEvent::PlaylistCreatedNotificationShown(_) => self.index_library(),
+            _ => {}
+        }
+    }
+}
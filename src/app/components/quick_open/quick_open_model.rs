@@ -0,0 +1,315 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::future::Future;
+use std::rc::Rc;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::app::components::labels;
+use crate::app::models::{AlbumModel, Batch, PageToken, SearchResults};
+use crate::app::state::PlaybackAction;
+use crate::app::{ActionDispatcher, AppAction, AppModel, BatchQuery, SongsSource};
+
+// Past that, it's no longer quick
+const MAX_RESULTS: usize = 12;
+// Spotify is asked too when the library has fewer matches than this
+const REMOTE_SEARCH_BELOW: usize = 3;
+// Pages of the library that are indexed, of each kind
+const INDEXED_PAGES: usize = 20;
+const PAGE_SIZE: usize = 50;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QuickOpenTarget {
+    Playlist(String),
+    Album(String),
+    Artist(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct QuickOpenItem {
+    pub title: String,
+    pub subtitle: String,
+    pub target: QuickOpenTarget,
+}
+
+impl QuickOpenItem {
+    fn playlist(playlist: &AlbumModel) -> Self {
+        Self {
+            title: playlist.album(),
+            subtitle: labels::QUICK_OPEN_PLAYLIST.clone(),
+            target: QuickOpenTarget::Playlist(playlist.uri()),
+        }
+    }
+
+    fn album(album: &AlbumModel) -> Self {
+        Self {
+            title: album.album(),
+            subtitle: labels::quick_open_album_label(&album.artist()),
+            target: QuickOpenTarget::Album(album.uri()),
+        }
+    }
+
+    fn artist(id: String, name: String) -> Self {
+        Self {
+            title: name,
+            subtitle: labels::QUICK_OPEN_ARTIST.clone(),
+            target: QuickOpenTarget::Artist(id),
+        }
+    }
+}
+
+// Case and accents don't matter
+fn fold(c: char) -> char {
+    let c = c.nfd().next().unwrap_or(c);
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// None unless every letter of the query shows up in the name, in order ("dsotm" is in "Dark Side of the Moon").
+// Letters that follow each other or start words count for more.
+fn fuzzy_score(query: &str, name: &str) -> Option<u32> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(fold)
+        .collect();
+    let mut score = 0;
+    let mut matched = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    for c in name.chars() {
+        if matched == query.len() {
+            break;
+        }
+        if fold(c) == query[matched] {
+            score += 1;
+            if previous_matched {
+                score += 4;
+            }
+            if !previous.is_some_and(char::is_alphanumeric) {
+                score += 6;
+            }
+            matched += 1;
+            previous_matched = true;
+        } else {
+            previous_matched = false;
+        }
+        previous = Some(c);
+    }
+    (matched == query.len()).then_some(score)
+}
+
+// Best matches first, and the shorter name of two that match as well
+fn rank(query: &str, items: Vec<QuickOpenItem>) -> Vec<QuickOpenItem> {
+    let mut scored: Vec<(u32, QuickOpenItem)> = items
+        .into_iter()
+        .filter_map(|item| Some((fuzzy_score(query, &item.title)?, item)))
+        .collect();
+    scored.sort_by_key(|(score, item)| (Reverse(*score), item.title.chars().count()));
+    scored
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(_, item)| item)
+        .collect()
+}
+
+fn from_search_results(results: SearchResults) -> Vec<QuickOpenItem> {
+    let artists = results
+        .artists
+        .into_iter()
+        .map(|artist| QuickOpenItem::artist(artist.id, artist.name));
+    let albums = results
+        .albums
+        .iter()
+        .map(|album| QuickOpenItem::album(&album.into()));
+    artists.chain(albums).collect()
+}
+
+pub struct QuickOpenModel {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+    // Saved playlists and albums, and followed artists, see index_library
+    indexed: Rc<RefCell<Vec<QuickOpenItem>>>,
+}
+
+impl QuickOpenModel {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+            indexed: Default::default(),
+        }
+    }
+
+    // The whole library rather than the pages loaded so far, straight from the cache most of the time
+    pub fn index_library(&self) -> impl Future<Output = ()> {
+        let api = self.app_model.get_spotify();
+        let indexed = Rc::clone(&self.indexed);
+        async move {
+            let mut items: Vec<QuickOpenItem> = vec![];
+            for offset in (0..INDEXED_PAGES).map(|page| page * PAGE_SIZE) {
+                match api.get_saved_playlists(offset, PAGE_SIZE).await {
+                    Ok(playlists) => {
+                        let done = playlists.len() < PAGE_SIZE;
+                        items.extend(playlists.iter().map(|p| QuickOpenItem::playlist(&p.into())));
+                        if done {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Could not index the playlists: {}", e);
+                        break;
+                    }
+                }
+            }
+            let mut page = Some(PageToken::Offset(0));
+            for _ in 0..INDEXED_PAGES {
+                let Some(token) = page.take() else { break };
+                match api.get_saved_albums(token, PAGE_SIZE).await {
+                    Ok(albums) => {
+                        items.extend(albums.items.iter().map(|a| QuickOpenItem::album(&a.into())));
+                        page = albums.next;
+                    }
+                    Err(e) => warn!("Could not index the albums: {}", e),
+                }
+            }
+            let mut page = Some(PageToken::Offset(0));
+            for _ in 0..INDEXED_PAGES {
+                let Some(token) = page.take() else { break };
+                match api.get_followed_artists(token, PAGE_SIZE).await {
+                    Ok(artists) => {
+                        items.extend(
+                            artists
+                                .items
+                                .into_iter()
+                                .map(|artist| QuickOpenItem::artist(artist.id, artist.name)),
+                        );
+                        page = artists.next;
+                    }
+                    Err(e) => warn!("Could not index the followed artists: {}", e),
+                }
+            }
+            indexed.replace(items);
+        }
+    }
+
+    // What's indexed, and whatever is loaded already: playlists and albums on the home page, the user's playlists
+    fn library(&self) -> Vec<QuickOpenItem> {
+        let state = self.app_model.get_state();
+        let mut items: Vec<QuickOpenItem> = self.indexed.borrow().clone();
+        if let Some(home) = state.browser.home_state() {
+            items.extend(home.playlists.iter().map(|p| QuickOpenItem::playlist(&p)));
+            items.extend(home.albums.iter().map(|a| QuickOpenItem::album(&a)));
+        }
+        items.extend(
+            state
+                .logged_user
+                .playlists
+                .iter()
+                .map(|playlist| QuickOpenItem {
+                    title: playlist.title.clone(),
+                    subtitle: labels::QUICK_OPEN_PLAYLIST.clone(),
+                    target: QuickOpenTarget::Playlist(playlist.id.clone()),
+                }),
+        );
+        drop(state);
+
+        let mut seen = HashSet::new();
+        items.retain(|item| seen.insert(item.target.clone()));
+        items
+    }
+
+    pub fn matches(&self, query: &str) -> Vec<QuickOpenItem> {
+        if query.trim().is_empty() {
+            return vec![];
+        }
+        rank(query, self.library())
+    }
+
+    pub fn needs_remote_search(&self, matches: &[QuickOpenItem]) -> bool {
+        matches.len() < REMOTE_SEARCH_BELOW
+    }
+
+    // Artists and albums, as Spotify's search finds them
+    pub fn search_remote(&self, query: String) -> impl Future<Output = Vec<QuickOpenItem>> {
+        let api = self.app_model.get_spotify();
+        async move {
+            match api.search(&query, 0, 5).await {
+                Ok(results) => from_search_results(results),
+                Err(e) => {
+                    warn!("Quick open search failed: {}", e);
+                    vec![]
+                }
+            }
+        }
+    }
+
+    pub fn open(&self, target: &QuickOpenTarget) {
+        self.dispatcher.dispatch(match target {
+            QuickOpenTarget::Playlist(id) => AppAction::ViewPlaylist(id.clone()),
+            QuickOpenTarget::Album(id) => AppAction::ViewAlbum(id.clone()),
+            QuickOpenTarget::Artist(id) => AppAction::ViewArtist(id.clone()),
+        });
+    }
+
+    // From the start, the rest is loaded as it plays. Artists are only opened.
+    pub fn play(&self, target: &QuickOpenTarget) {
+        let source = match target {
+            QuickOpenTarget::Playlist(id) => SongsSource::Playlist(id.clone()),
+            QuickOpenTarget::Album(id) => SongsSource::Album(id.clone()),
+            QuickOpenTarget::Artist(_) => return self.open(target),
+        };
+        let query = BatchQuery {
+            source,
+            batch: Batch::first_of_size(50),
+        };
+        let loader = self.app_model.get_batch_loader();
+        self.dispatcher
+            .dispatch_stream(loader.load_all(vec![query], |source, batch| {
+                let Some(first) = batch.songs.first().map(|song| song.id.clone()) else {
+                    return vec![];
+                };
+                vec![
+                    PlaybackAction::LoadPagedSongs(source, batch).into(),
+                    PlaybackAction::Load(first).into(),
+                ]
+            }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn item(title: &str) -> QuickOpenItem {
+        QuickOpenItem {
+            title: title.to_string(),
+            subtitle: "".to_string(),
+            target: QuickOpenTarget::Album(title.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("dsotm", "Dark Side of the Moon").is_some());
+        assert!(fuzzy_score("bjork", "Björk").is_some());
+        assert!(fuzzy_score("abc", "Cab").is_none());
+        assert!(fuzzy_score("ok", "OK Computer") > fuzzy_score("ok", "Brooklyn"));
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+    }
+
+    #[test]
+    fn test_rank() {
+        let ranked = rank(
+            "ok",
+            vec![
+                item("Brooklyn"),
+                item("OK Computer"),
+                item("Kid A"),
+                item("OK"),
+            ],
+        );
+        let titles: Vec<&str> = ranked.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, vec!["OK", "OK Computer", "Brooklyn"]);
+    }
+}
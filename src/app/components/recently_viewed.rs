@@ -0,0 +1,178 @@
+use gettextrs::gettext;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app::components::EventListener;
+use crate::app::kv_store::KeyValueStore;
+use crate::app::models::RecentlyViewed;
+use crate::app::{ActionDispatcher, AppEvent, AppModel, BrowserAction, BrowserEvent};
+
+// What the home shelf shows, and how much history is kept around besides
+const SHOWN: usize = 12;
+const KEPT: usize = 50;
+
+thread_local!(static RECENTLY_VIEWED: RefCell<KeyValueStore<RecentlyViewed>> = RefCell::new(KeyValueStore::open("recently-viewed")));
+
+fn most_recent<'a>(
+    items: impl Iterator<Item = &'a RecentlyViewed>,
+    n: usize,
+) -> Vec<RecentlyViewed> {
+    let mut items: Vec<RecentlyViewed> = items.cloned().collect();
+    items.sort_by(|a, b| b.viewed_at.cmp(&a.viewed_at));
+    items.truncate(n);
+    items
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// Writes down the albums, artists and playlists that get opened, for the "Jump back in" shelf on the home page
+pub struct RecentlyViewedTracker {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl RecentlyViewedTracker {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+
+    fn dispatch_shown(&self) {
+        let shown = RECENTLY_VIEWED.with(|store| most_recent(store.borrow().values(), SHOWN));
+        self.dispatcher
+            .dispatch(BrowserAction::SetRecentlyViewed(shown).into());
+    }
+
+    fn album_viewed(&self, id: &str) -> Option<RecentlyViewed> {
+        let state = self.app_model.get_state();
+        let album = &state
+            .browser
+            .details_state(id)?
+            .content
+            .as_ref()?
+            .description;
+        Some(RecentlyViewed {
+            uri: format!("spotify:album:{id}"),
+            title: album.title.clone(),
+            subtitle: album.artists_name(),
+            art: album.art.clone(),
+            viewed_at: now_ms(),
+        })
+    }
+
+    fn playlist_viewed(&self, id: &str) -> Option<RecentlyViewed> {
+        let state = self.app_model.get_state();
+        let playlist = state
+            .browser
+            .playlist_details_state(id)?
+            .playlist
+            .as_ref()?;
+        Some(RecentlyViewed {
+            uri: format!("spotify:playlist:{id}"),
+            title: playlist.title.clone(),
+            subtitle: playlist.owner.display_name.clone(),
+            art: playlist.art.clone(),
+            viewed_at: now_ms(),
+        })
+    }
+
+    // Artists don't come with a picture, so the cover of one of their albums will do
+    fn artist_viewed(&self, id: &str) -> Option<RecentlyViewed> {
+        let state = self.app_model.get_state();
+        let artist = state.browser.artist_state(id)?;
+        Some(RecentlyViewed {
+            uri: format!("spotify:artist:{id}"),
+            title: artist.artist.clone()?,
+            // translators: Shown under the name of an artist in the "Jump back in" shelf of the home page
+            subtitle: gettext("Artist"),
+            art: artist.albums.iter().find_map(|a| a.cover()),
+            viewed_at: now_ms(),
+        })
+    }
+
+    fn record(&self, item: Option<RecentlyViewed>) {
+        let Some(item) = item else {
+            return;
+        };
+        let changed = RECENTLY_VIEWED.with(|store| {
+            let mut store = store.borrow_mut();
+            // The same screen being updated (say, as more of it loads) isn't worth a write
+            let latest = most_recent(store.values(), 1);
+            if let Some(latest) = latest.first() {
+                if latest.uri == item.uri && latest.title == item.title && latest.art == item.art {
+                    return false;
+                }
+            }
+            store.set(item.uri.clone(), item);
+            for old in most_recent(store.values(), usize::MAX)
+                .into_iter()
+                .skip(KEPT)
+            {
+                store.remove(&old.uri);
+            }
+            if let Err(e) = store.save() {
+                warn!("Could not save recently viewed items: {}", e);
+            }
+            true
+        });
+        if changed {
+            self.dispatch_shown();
+        }
+    }
+}
+
+impl EventListener for RecentlyViewedTracker {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Started => self.dispatch_shown(),
+            AppEvent::BrowserEvent(BrowserEvent::AlbumDetailsLoaded(id)) => {
+                self.record(self.album_viewed(id))
+            }
+            AppEvent::BrowserEvent(BrowserEvent::PlaylistDetailsLoaded(id)) => {
+                self.record(self.playlist_viewed(id))
+            }
+            AppEvent::BrowserEvent(BrowserEvent::ArtistDetailsUpdated(id)) => {
+                self.record(self.artist_viewed(id))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn item(uri: &str, viewed_at: i64) -> RecentlyViewed {
+        RecentlyViewed {
+            uri: uri.to_string(),
+            title: "Title".to_string(),
+            subtitle: "Someone".to_string(),
+            art: None,
+            viewed_at,
+        }
+    }
+
+    #[test]
+    fn test_most_recent() {
+        let items = vec![
+            item("spotify:album:a", 10),
+            item("spotify:artist:b", 30),
+            item("spotify:playlist:c", 20),
+        ];
+        let uris: Vec<String> = most_recent(items.iter(), 2)
+            .into_iter()
+            .map(|i| i.uri)
+            .collect();
+        assert_eq!(uris, vec!["spotify:artist:b", "spotify:playlist:c"]);
+    }
+}
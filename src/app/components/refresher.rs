@@ -0,0 +1,93 @@
+use std::rc::Rc;
+
+use crate::api::cache::{CacheCategory, CacheSelection};
+use crate::app::components::EventListener;
+use crate::app::state::ScreenName;
+use crate::app::{ActionDispatcher, AppEvent, AppModel, BrowserAction, BrowserEvent};
+
+// All the pages past the first are in there too, so they're fetched again as they're scrolled to.
+// Search results aren't cached, they're just asked for again.
+fn stale_cache(screen: &ScreenName) -> Vec<CacheSelection> {
+    use CacheCategory::*;
+    let about = |categories: &[CacheCategory], id: &str| -> Vec<CacheSelection> {
+        categories
+            .iter()
+            .map(|category| category.about(id))
+            .collect()
+    };
+    match screen {
+        ScreenName::Home => [
+            SavedAlbums,
+            SavedTracks,
+            SavedPlaylists,
+            SavedAudiobooks,
+            View,
+            PinnedSearch,
+        ]
+        .iter()
+        .map(|category| category.all())
+        .collect(),
+        ScreenName::AlbumDetails(id) => about(&[Album, AlbumTracks, AlbumLiked], id),
+        ScreenName::PlaylistDetails(id) => about(&[Playlist, PlaylistTracks], id),
+        ScreenName::Artist(id) => {
+            about(&[Artist, ArtistAlbums, ArtistTopTracks, ArtistFollowed], id)
+        }
+        ScreenName::User(id) => about(&[User, UserPlaylists], id),
+        ScreenName::Search => vec![],
+    }
+}
+
+// Hard refresh (Ctrl+R, or the button in the header bar): rather than waiting for what's cached to expire,
+// it's revalidated right away and the screen is told to load its first batch again
+pub struct ScreenRefresher {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl ScreenRefresher {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+
+    fn refresh(&self, screen: &ScreenName) {
+        let api = self.app_model.get_spotify();
+        let stale = stale_cache(screen);
+        let screen = screen.clone();
+        self.dispatcher
+            .call_spotify_and_dispatch(move || async move {
+                api.revalidate(stale).await?;
+                Ok(BrowserAction::Reload(screen).into())
+            });
+    }
+}
+
+impl EventListener for ScreenRefresher {
+    fn on_event(&mut self, event: &AppEvent) {
+        if let AppEvent::BrowserEvent(BrowserEvent::RefreshRequested(screen)) = event {
+            self.refresh(screen);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_stale_cache() {
+        let stale = stale_cache(&ScreenName::PlaylistDetails("id".to_string()));
+        assert_eq!(
+            stale,
+            vec![
+                CacheCategory::Playlist.about("id"),
+                CacheCategory::PlaylistTracks.about("id"),
+            ]
+        );
+        assert!(stale_cache(&ScreenName::Home).contains(&CacheCategory::SavedAlbums.all()));
+        assert!(stale_cache(&ScreenName::Search).is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::app::components::utils::{Clock, Debouncer};
+use crate::app::components::EventListener;
+use crate::app::kv_store::KeyValueStore;
+use crate::app::models::{ChapterDescription, SongDescription};
+use crate::app::state::{PlaybackAction, PlaybackEvent};
+use crate::app::{ActionDispatcher, AppEvent, AppModel};
+
+// Closer than this to either end, there's nothing worth resuming
+const RESUME_MARGIN_MS: u32 = 30 * 1000;
+// Pausing and seeking come in bursts, the store is written once they're over
+const SAVE_DELAY_MS: u32 = 2000;
+
+thread_local!(static POSITIONS: RefCell<KeyValueStore<u32>> = RefCell::new(KeyValueStore::open("resume-positions")));
+
+// Podcast episodes and audiobook chapters (episodes too, as far as Spotify is concerned) pick up where they were left
+pub fn is_long_form(song: &SongDescription) -> bool {
+    song.is_chapter()
+}
+
+fn is_worth_resuming(position_ms: u32, duration_ms: u32) -> bool {
+    position_ms > RESUME_MARGIN_MS && position_ms + RESUME_MARGIN_MS < duration_ms
+}
+
+// Where we stopped last time, in ms
+pub fn saved_position(id: &str) -> Option<u32> {
+    POSITIONS.with(|positions| positions.borrow().get(id).copied())
+}
+
+// Whether anything changed, the store still has to be saved
+fn set_position(song: &SongDescription, position_ms: u32) -> bool {
+    POSITIONS.with(|positions| {
+        let mut positions = positions.borrow_mut();
+        if is_worth_resuming(position_ms, song.duration) {
+            positions.set(song.id.clone(), position_ms);
+            true
+        } else {
+            positions.remove(&song.id).is_some()
+        }
+    })
+}
+
+fn save_positions() {
+    POSITIONS.with(|positions| {
+        if let Err(e) = positions.borrow().save() {
+            warn!("Could not save resume positions: {}", e);
+        }
+    });
+}
+
+// Spotify only gets a say about chapters we haven't played here, what we saved ourselves is fresher
+fn merge_remote_positions(chapters: &[ChapterDescription]) -> bool {
+    POSITIONS.with(|positions| {
+        let mut positions = positions.borrow_mut();
+        let mut changed = false;
+        for chapter in chapters {
+            let song = &chapter.song;
+            if chapter.fully_played
+                || positions.get(&song.id).is_some()
+                || !is_worth_resuming(chapter.resume_position, song.duration)
+            {
+                continue;
+            }
+            positions.set(song.id.clone(), chapter.resume_position);
+            changed = true;
+        }
+        changed
+    })
+}
+
+// Keeps track of how far long-form items were played, and seeks back there when they're played again
+pub struct ResumeTracker {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+    current: Rc<RefCell<Option<SongDescription>>>,
+    // The state forgets about the position as soon as the track changes, so we keep our own copy
+    last_position: Rc<Cell<u32>>,
+    clock: Clock,
+    saver: Debouncer,
+}
+
+impl ResumeTracker {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+            current: Default::default(),
+            last_position: Default::default(),
+            clock: Clock::new(5000),
+            saver: Debouncer::new(),
+        }
+    }
+
+    fn read_position(&self) {
+        if self.current.borrow().is_some() {
+            let position = self.app_model.get_state().playback.seek_position();
+            self.last_position.set(position);
+        }
+    }
+
+    fn save(&self) {
+        let changed = self
+            .current
+            .borrow()
+            .as_ref()
+            .is_some_and(|song| set_position(song, self.last_position.get()));
+        if changed {
+            self.save_later();
+        }
+    }
+
+    fn save_later(&self) {
+        self.saver.debounce(SAVE_DELAY_MS, save_positions);
+    }
+
+    fn start_clock(&self) {
+        if self.current.borrow().is_none() {
+            return;
+        }
+        let app_model = Rc::clone(&self.app_model);
+        let last_position = Rc::clone(&self.last_position);
+        self.clock.start(move || {
+            last_position.set(app_model.get_state().playback.seek_position());
+        });
+    }
+
+    fn on_track_changed(&self) {
+        self.save();
+        let song = self
+            .app_model
+            .get_state()
+            .playback
+            .current_song()
+            .filter(is_long_form);
+        let position = song.as_ref().and_then(|s| saved_position(&s.id));
+        self.last_position.set(position.unwrap_or(0));
+        self.current.replace(song);
+
+        if let Some(position) = position {
+            self.dispatcher
+                .dispatch(PlaybackAction::Seek(position).into());
+        }
+        if self.current.borrow().is_some() {
+            self.start_clock();
+        } else {
+            self.clock.stop();
+        }
+    }
+}
+
+impl EventListener for ResumeTracker {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::ResumePointsSynced(chapters) => {
+                if merge_remote_positions(chapters) {
+                    self.save_later();
+                }
+            }
+            AppEvent::PlaybackEvent(PlaybackEvent::TrackChanged(_)) => self.on_track_changed(),
+            AppEvent::PlaybackEvent(PlaybackEvent::PlaybackPaused) => {
+                self.clock.stop();
+                self.read_position();
+                self.save();
+            }
+            AppEvent::PlaybackEvent(PlaybackEvent::PlaybackResumed) => self.start_clock(),
+            AppEvent::PlaybackEvent(
+                PlaybackEvent::TrackSeeked(position) | PlaybackEvent::SeekSynced(position),
+            ) => {
+                if self.current.borrow().is_some() {
+                    self.last_position.set(*position);
+                    self.save();
+                }
+            }
+            AppEvent::PlaybackEvent(PlaybackEvent::PlaybackStopped) => {
+                self.clock.stop();
+                self.save();
+                self.current.replace(None);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::test_utils::song;
+
+    #[test]
+    fn test_is_worth_resuming() {
+        let duration = 60 * 60 * 1000;
+        assert!(!is_worth_resuming(10_000, duration));
+        assert!(is_worth_resuming(20 * 60 * 1000, duration));
+        assert!(!is_worth_resuming(duration - 10_000, duration));
+    }
+
+    #[test]
+    fn test_only_episodes_are_long_form() {
+        let mut long_song = song("1");
+        long_song.duration = 60 * 60 * 1000;
+        assert!(!is_long_form(&long_song));
+
+        let mut episode = song("2");
+        episode.uri = "spotify:episode:2".to_string();
+        assert!(is_long_form(&episode));
+    }
+}
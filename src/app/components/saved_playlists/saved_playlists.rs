@@ -4,10 +4,12 @@ use gtk::CompositeTemplate;
 use std::rc::Rc;
 
 use super::SavedPlaylistsModel;
+use crate::app::components::share::ShareKind;
+use crate::app::components::utils::connect_prefetch;
 use crate::app::components::{AlbumWidget, Component, EventListener};
 use crate::app::dispatch::Worker;
 use crate::app::models::AlbumModel;
-use crate::app::state::LoginEvent;
+use crate::app::state::{LoginEvent, ScreenName};
 use crate::app::{AppEvent, BrowserEvent, ListStore};
 
 mod imp {
@@ -65,13 +67,7 @@ impl SavedPlaylistsWidget {
     where
         F: Fn() + 'static,
     {
-        self.imp()
-            .scrolled_window
-            .connect_edge_reached(move |_, pos| {
-                if let gtk::PositionType::Bottom = pos {
-                    f()
-                }
-            });
+        connect_prefetch(&self.imp().scrolled_window, f);
     }
 
     fn bind_albums<F>(&self, worker: Worker, store: &ListStore<AlbumModel>, on_album_pressed: F)
@@ -84,6 +80,7 @@ impl SavedPlaylistsWidget {
                 let album_model = item.downcast_ref::<AlbumModel>().unwrap();
                 let child = gtk::FlowBoxChild::new();
                 let album = AlbumWidget::for_model(album_model, worker.clone());
+                album.set_share_menu(ShareKind::Playlist, &album_model.uri());
 
                 let f = on_album_pressed.clone();
                 album.connect_album_pressed(clone!(@weak album_model => move |_| {
@@ -140,7 +137,8 @@ impl EventListener for SavedPlaylists {
                 let _ = self.model.refresh_saved_playlists();
                 self.bind_flowbox();
             }
-            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_)) => {
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_))
+            | AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::Home)) => {
                 let _ = self.model.refresh_saved_playlists();
             }
             AppEvent::BrowserEvent(BrowserEvent::SavedPlaylistsUpdated) => {
@@ -52,7 +52,7 @@ impl SavedPlaylistsModel {
 
         let next_page = &self.state()?.next_playlists_page;
         let batch_size = next_page.batch_size;
-        let offset = next_page.next_offset?;
+        let offset = next_page.next_offset()?;
 
         self.dispatcher
             .call_spotify_and_dispatch(move || async move {
@@ -4,9 +4,10 @@ use gtk::CompositeTemplate;
 use std::rc::Rc;
 
 use super::SavedTracksModel;
+use crate::app::components::utils::connect_prefetch;
 use crate::app::components::{Component, EventListener, Playlist};
-use crate::app::state::LoginEvent;
-use crate::app::{AppEvent, Worker};
+use crate::app::state::{LoginEvent, ScreenName};
+use crate::app::{AppEvent, BrowserEvent, Worker};
 use libadwaita::subclass::prelude::BinImpl;
 
 mod imp {
@@ -56,13 +57,7 @@ impl SavedTracksWidget {
     where
         F: Fn() + 'static,
     {
-        self.imp()
-            .scrolled_window
-            .connect_edge_reached(move |_, pos| {
-                if let gtk::PositionType::Bottom = pos {
-                    f()
-                }
-            });
+        connect_prefetch(&self.imp().scrolled_window, f);
     }
 
     fn song_list_widget(&self) -> &gtk::ListView {
@@ -107,7 +102,9 @@ impl Component for SavedTracks {
 impl EventListener for SavedTracks {
     fn on_event(&mut self, event: &AppEvent) {
         match event {
-            AppEvent::Started | AppEvent::LoginEvent(LoginEvent::LoginCompleted(_)) => {
+            AppEvent::Started
+            | AppEvent::LoginEvent(LoginEvent::LoginCompleted(_))
+            | AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::Home)) => {
                 self.model.load_initial();
             }
             _ => {}
@@ -99,6 +99,10 @@ impl PlaylistModel for SavedTracksModel {
         }
         group.add_action(&song.make_album_action(self.dispatcher.box_clone(), None));
         group.add_action(&song.make_link_action(None));
+        group.add_action(&song.make_uri_action(None));
+        for block in song.make_block_actions(self.dispatcher.box_clone()) {
+            group.add_action(&block);
+        }
 
         Some(group.upcast())
     }
@@ -117,6 +121,8 @@ impl PlaylistModel for SavedTracksModel {
         }
 
         menu.append(Some(&*labels::COPY_LINK), Some("song.copy_link"));
+        menu.append(Some(&*labels::COPY_URI), Some("song.copy_uri"));
+        menu.append_section(None, &song.make_block_menu());
 
         Some(menu.upcast())
     }
@@ -134,6 +140,14 @@ impl PlaylistModel for SavedTracksModel {
             .dispatch(SelectionAction::Deselect(vec![id.to_string()]).into());
     }
 
+    fn update_selection(&self, action: SelectionAction) {
+        self.dispatcher.dispatch(action.into());
+    }
+
+    fn load_remaining(&self) {
+        self.load_more();
+    }
+
     fn enable_selection(&self) -> bool {
         self.dispatcher
             .dispatch(AppAction::EnableSelection(SelectionContext::SavedTracks));
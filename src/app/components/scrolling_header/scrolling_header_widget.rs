@@ -2,6 +2,8 @@ use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
 
+use crate::app::components::utils::connect_prefetch;
+
 mod imp {
 
     use super::*;
@@ -106,12 +108,6 @@ impl ScrollingHeaderWidget {
     where
         F: Fn() + 'static,
     {
-        self.imp()
-            .scrolled_window
-            .connect_edge_reached(move |_, pos| {
-                if let gtk::PositionType::Bottom = pos {
-                    f()
-                }
-            });
+        connect_prefetch(&self.imp().scrolled_window, f);
     }
 }
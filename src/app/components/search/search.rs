@@ -1,13 +1,17 @@
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
+use std::cell::Cell;
 use std::rc::Rc;
 
-use crate::app::components::utils::{wrap_flowbox_item, Debouncer};
+use crate::app::components::utils::{for_each_flowbox_item, wrap_flowbox_item, Debouncer};
 use crate::app::components::{AlbumWidget, ArtistWidget, Component, EventListener};
 use crate::app::dispatch::Worker;
 use crate::app::models::{AlbumModel, ArtistModel};
-use crate::app::state::{AppEvent, BrowserEvent};
+use crate::app::state::{AppEvent, BrowserEvent, ScreenName};
+use crate::settings::ViewMode;
+
+const VIEW_MODE_KEY: &str = "search-view-mode";
 
 use super::SearchResultsModel;
 mod imp {
@@ -37,6 +41,12 @@ mod imp {
 
         #[template_child]
         pub artist_results: TemplateChild<gtk::FlowBox>,
+
+        #[template_child]
+        pub view_mode_toggle: TemplateChild<gtk::ToggleButton>,
+
+        // Read when creating album and artist widgets
+        pub list_mode: Rc<Cell<bool>>,
     }
 
     #[glib::object_subclass]
@@ -114,16 +124,67 @@ impl SearchResultsWidget {
             }));
     }
 
-    fn bind_albums_results<F>(&self, worker: Worker, store: &gio::ListStore, on_album_pressed: F)
+    fn connect_view_mode_changed<F>(&self, f: F)
     where
+        F: Fn(ViewMode) + 'static,
+    {
+        self.imp().view_mode_toggle.connect_toggled(move |toggle| {
+            f(if toggle.is_active() {
+                ViewMode::List
+            } else {
+                ViewMode::Grid
+            })
+        });
+    }
+
+    // Cards scroll sideways, rows are stacked
+    fn set_view_mode(&self, view_mode: ViewMode) {
+        let widget = self.imp();
+        let list_mode = view_mode == ViewMode::List;
+        widget.list_mode.set(list_mode);
+        widget.view_mode_toggle.set_active(list_mode);
+        let orientation = if list_mode {
+            gtk::Orientation::Horizontal
+        } else {
+            gtk::Orientation::Vertical
+        };
+        for flowbox in [&*widget.albums_results, &*widget.artist_results] {
+            flowbox.set_orientation(orientation);
+            flowbox.set_halign(if list_mode {
+                gtk::Align::Fill
+            } else {
+                gtk::Align::Start
+            });
+        }
+        for_each_flowbox_item(&widget.albums_results, |album: &AlbumWidget| {
+            album.set_list_mode(list_mode)
+        });
+        for_each_flowbox_item(&widget.artist_results, |artist: &ArtistWidget| {
+            artist.set_list_mode(list_mode)
+        });
+    }
+
+    fn bind_albums_results<F, M>(
+        &self,
+        worker: Worker,
+        store: &gio::ListStore,
+        on_album_pressed: F,
+        album_menu: M,
+    ) where
         F: Fn(String) + Clone + 'static,
+        M: Fn(&str) -> Option<(gio::ActionGroup, gio::MenuModel)> + 'static,
     {
+        let list_mode = self.imp().list_mode.clone();
         self.imp()
             .albums_results
             .bind_model(Some(store), move |item| {
                 wrap_flowbox_item(item, |album_model| {
                     let f = on_album_pressed.clone();
                     let album = AlbumWidget::for_model(album_model, worker.clone());
+                    album.set_list_mode(list_mode.get());
+                    if let Some((actions, menu)) = album_menu(&album_model.uri()) {
+                        album.set_menu(&actions, &menu);
+                    }
                     album.connect_album_pressed(clone!(@weak album_model => move |_| {
                         f(album_model.uri());
                     }));
@@ -136,12 +197,15 @@ impl SearchResultsWidget {
     where
         F: Fn(String) + Clone + 'static,
     {
+        let list_mode = self.imp().list_mode.clone();
         self.imp()
             .artist_results
             .bind_model(Some(store), move |item| {
                 wrap_flowbox_item(item, |artist_model| {
                     let f = on_artist_pressed.clone();
                     let artist = ArtistWidget::for_model(artist_model, worker.clone());
+                    artist.set_list_mode(list_mode.get());
+                    artist.set_share_menu(&artist_model.id());
                     artist.connect_artist_pressed(clone!(@weak artist_model => move |_| {
                         f(artist_model.id());
                     }));
@@ -169,6 +233,12 @@ impl SearchResults {
 
         widget.bind_to_leaflet(leaflet);
 
+        widget.set_view_mode(ViewMode::new_from_gsettings(VIEW_MODE_KEY));
+        widget.connect_view_mode_changed(clone!(@weak widget => move |view_mode| {
+            widget.set_view_mode(view_mode);
+            view_mode.save(VIEW_MODE_KEY);
+        }));
+
         widget.connect_go_back(clone!(@weak model => move || {
             model.go_back();
         }));
@@ -183,6 +253,9 @@ impl SearchResults {
             clone!(@weak model => move |uri| {
                 model.open_album(uri);
             }),
+            clone!(@weak model => @default-return None, move |id| {
+                model.album_menu(id)
+            }),
         );
 
         widget.bind_artists_results(
@@ -251,6 +324,9 @@ impl EventListener for SearchResults {
             AppEvent::BrowserEvent(BrowserEvent::SearchResultsUpdated) => {
                 self.update_results();
             }
+            AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::Search)) => {
+                self.model.fetch_results();
+            }
             _ => {}
         }
     }
@@ -1,6 +1,10 @@
+use gio::prelude::*;
+use gio::{SimpleAction, SimpleActionGroup};
 use std::ops::Deref;
 use std::rc::Rc;
 
+use crate::app::components::labels;
+use crate::app::components::share::{self, ShareKind};
 use crate::app::dispatch::ActionDispatcher;
 use crate::app::models::*;
 use crate::app::state::{AppAction, AppModel, BrowserAction};
@@ -63,4 +67,29 @@ impl SearchResultsModel {
     pub fn open_artist(&self, id: String) {
         self.dispatcher.dispatch(AppAction::ViewArtist(id));
     }
+
+    // Actions go in the "album" group
+    pub fn album_menu(&self, id: &str) -> Option<(gio::ActionGroup, gio::MenuModel)> {
+        let albums = self.get_album_results()?;
+        let album = albums.iter().find(|album| album.id == id)?;
+
+        let group = SimpleActionGroup::new();
+        let menu = gio::Menu::new();
+        for artist in album.artists.iter() {
+            let action_name = format!("view_artist_{}", artist.id);
+            let action = SimpleAction::new(&action_name, None);
+            let dispatcher = self.dispatcher.box_clone();
+            let artist_id = artist.id.clone();
+            action.connect_activate(move |_, _| {
+                dispatcher.dispatch(AppAction::ViewArtist(artist_id.clone()));
+            });
+            group.add_action(&action);
+            menu.append(
+                Some(&labels::more_from_label(&artist.name)),
+                Some(&format!("album.{action_name}")),
+            );
+        }
+        share::add_share_items(ShareKind::Album, &album.id, "album", &group, &menu);
+        Some((group.upcast(), menu.upcast()))
+    }
 }
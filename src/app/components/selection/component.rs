@@ -3,12 +3,15 @@ use gtk::prelude::*;
 use std::ops::Deref;
 use std::rc::Rc;
 
-use crate::app::components::{Component, EventListener};
-use crate::app::models::PlaylistSummary;
+use crate::api::SpotifyApiError;
+use crate::app::components::{labels, Component, EventListener};
+use crate::app::format::songs_totals;
+use crate::app::models::{PlaylistSummary, SongDescription};
 use crate::app::state::{
     LoginEvent, SelectionAction, SelectionContext, SelectionEvent, SelectionState,
 };
 use crate::app::{ActionDispatcher, AppAction, AppEvent, AppModel, BrowserAction};
+use crate::settings;
 
 use super::widget::{SelectionToolState, SelectionToolbarWidget};
 
@@ -33,6 +36,10 @@ impl SelectionToolbarModel {
         self.dispatcher.dispatch(AppAction::MoveDownSelection);
     }
 
+    pub fn invert_selection(&self) {
+        self.dispatcher.dispatch(SelectionAction::InvertAll.into());
+    }
+
     pub fn queue_selection(&self) {
         self.dispatcher.dispatch(AppAction::QueueSelection);
     }
@@ -52,34 +59,41 @@ impl SelectionToolbarModel {
 
     pub fn save_selection(&self) {
         let api = self.app_model.get_spotify();
-        let ids: Vec<String> = self
-            .selection()
-            .peek_selection()
-            .map(|s| &s.id)
-            .cloned()
-            .collect();
+        let songs: Vec<SongDescription> = self.selection().peek_selection().cloned().collect();
         self.dispatcher
-            .call_spotify_and_dispatch_many(move || async move {
-                api.save_tracks(ids).await?;
-                Ok(vec![
-                    AppAction::SaveSelection,
-                    AppAction::ShowNotification(gettext("Tracks saved!")),
-                ])
+            .write_spotify_and_dispatch_many(move || async move {
+                let ids = songs.iter().map(|s| s.id.clone()).collect();
+                match api.save_tracks(ids).await {
+                    Ok(()) => Ok(vec![
+                        AppAction::SaveSelection,
+                        AppAction::ShowNotification(gettext("Tracks saved!")),
+                    ]),
+                    Err(SpotifyApiError::PartialWrite(done, _)) => {
+                        let saved = songs[..done].to_vec();
+                        let mut actions = partially_done(&songs, done);
+                        actions.push(BrowserAction::SaveTracks(saved).into());
+                        Ok(actions)
+                    }
+                    Err(err) => Err(err),
+                }
             })
     }
 
     fn remove_saved_tracks(&self) {
         let api = self.app_model.get_spotify();
-        let ids: Vec<String> = self
-            .selection()
-            .peek_selection()
-            .map(|s| &s.id)
-            .cloned()
-            .collect();
+        let songs: Vec<SongDescription> = self.selection().peek_selection().cloned().collect();
         self.dispatcher
-            .call_spotify_and_dispatch_many(move || async move {
-                api.remove_saved_tracks(ids).await?;
-                Ok(vec![AppAction::UnsaveSelection])
+            .write_spotify_and_dispatch_many(move || async move {
+                let ids: Vec<String> = songs.iter().map(|s| s.id.clone()).collect();
+                match api.remove_saved_tracks(ids.clone()).await {
+                    Ok(()) => Ok(vec![AppAction::UnsaveSelection]),
+                    Err(SpotifyApiError::PartialWrite(done, _)) => {
+                        let mut actions = partially_done(&songs, done);
+                        actions.push(BrowserAction::RemoveSavedTracks(ids[..done].to_vec()).into());
+                        Ok(actions)
+                    }
+                    Err(err) => Err(err),
+                }
             })
     }
 
@@ -91,6 +105,15 @@ impl SelectionToolbarModel {
         self.selection().count()
     }
 
+    fn summary(&self) -> String {
+        let selection = self.selection();
+        songs_totals(
+            selection.count(),
+            Some(selection.total_duration()),
+            settings::bitrate_kbps(),
+        )
+    }
+
     fn user_playlists(&self) -> impl Deref<Target = Vec<PlaylistSummary>> + '_ {
         self.app_model.map_state(|s| &s.logged_user.playlists)
     }
@@ -98,39 +121,63 @@ impl SelectionToolbarModel {
     fn add_to_playlist(&self, id: &str) {
         let id = id.to_string();
         let api = self.app_model.get_spotify();
-        let uris: Vec<String> = self
-            .selection()
-            .peek_selection()
-            .map(|s| &s.uri)
-            .cloned()
-            .collect();
+        let songs: Vec<SongDescription> = self.selection().peek_selection().cloned().collect();
         self.dispatcher
-            .call_spotify_and_dispatch(move || async move {
-                api.add_to_playlist(&id, uris).await?;
-                Ok(SelectionAction::Clear.into())
+            .write_spotify_and_dispatch_many(move || async move {
+                let uris = songs.iter().map(|s| s.uri.clone()).collect();
+                match api.add_to_playlist(&id, uris).await {
+                    Ok(()) => Ok(vec![SelectionAction::Clear.into()]),
+                    Err(SpotifyApiError::PartialWrite(done, _)) => Ok(partially_done(&songs, done)),
+                    Err(err) => Err(err),
+                }
             })
     }
 
     fn remove_from_playlist(&self, id: &str) {
         let api = self.app_model.get_spotify();
         let id = id.to_string();
-        let uris: Vec<String> = self
-            .selection()
-            .peek_selection()
-            .map(|s| &s.uri)
-            .cloned()
-            .collect();
+        let songs: Vec<SongDescription> = self.selection().peek_selection().cloned().collect();
+        let uris: Vec<String> = songs.iter().map(|s| s.uri.clone()).collect();
+        let snapshot_id = self
+            .app_model
+            .get_state()
+            .browser
+            .playlist_details_state(&id)
+            .and_then(|state| state.playlist.as_ref()?.snapshot_id.clone());
         self.dispatcher
-            .call_spotify_and_dispatch_many(move || async move {
-                api.remove_from_playlist(&id, uris.clone()).await?;
+            .write_spotify_and_dispatch_many(move || async move {
+                let result = api
+                    .remove_from_playlist(&id, uris.clone(), snapshot_id.clone())
+                    .await;
+                let snapshot_id = match result {
+                    Ok(snapshot_id) => snapshot_id,
+                    // The snapshot we had is outdated now, which removing by uri copes with
+                    Err(SpotifyApiError::PartialWrite(done, _)) => {
+                        let removed = uris[..done].to_vec();
+                        let mut actions = partially_done(&songs, done);
+                        actions.push(BrowserAction::RemoveTracksFromPlaylist(id, removed).into());
+                        return Ok(actions);
+                    }
+                    Err(err) => return Err(err),
+                };
                 Ok(vec![
-                    BrowserAction::RemoveTracksFromPlaylist(id, uris).into(),
+                    BrowserAction::RemoveTracksFromPlaylist(id.clone(), uris).into(),
+                    BrowserAction::SetPlaylistSnapshot(id, snapshot_id).into(),
                     SelectionAction::Clear.into(),
                 ])
             })
     }
 }
 
+// The songs that made it are deselected, the others are left selected to try again
+fn partially_done(songs: &[SongDescription], done: usize) -> Vec<AppAction> {
+    let ids = songs[..done].iter().map(|s| s.id.clone()).collect();
+    vec![
+        SelectionAction::Deselect(ids).into(),
+        AppAction::ShowNotification(labels::partial_write_label(done, songs.len())),
+    ]
+}
+
 pub struct SelectionToolbar {
     model: Rc<SelectionToolbarModel>,
     widget: SelectionToolbarWidget,
@@ -141,6 +188,7 @@ impl SelectionToolbar {
         let model = Rc::new(model);
         widget.connect_move_up(clone!(@weak model => move || model.move_up_selection()));
         widget.connect_move_down(clone!(@weak model => move || model.move_down_selection()));
+        widget.connect_invert(clone!(@weak model => move || model.invert_selection()));
         widget.connect_queue(clone!(@weak model => move || model.queue_selection()));
         widget.connect_remove(clone!(@weak model => move || model.remove_selection()));
         widget.connect_save(clone!(@weak model => move || model.save_selection()));
@@ -149,6 +197,7 @@ impl SelectionToolbar {
 
     fn update_active_tools(&self) {
         let count = self.model.selected_count();
+        self.widget.set_summary(&self.model.summary());
         match self.model.selection().context {
             SelectionContext::Default => {
                 self.widget.set_move(SelectionToolState::Hidden);
@@ -17,6 +17,12 @@ mod imp {
         #[template_child]
         pub action_bar: TemplateChild<gtk::ActionBar>,
 
+        #[template_child]
+        pub invert: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub summary: TemplateChild<gtk::Label>,
+
         #[template_child]
         pub move_up: TemplateChild<gtk::Button>,
 
@@ -97,6 +103,13 @@ impl SelectionToolbarWidget {
         self.imp().move_up.connect_clicked(move |_| f());
     }
 
+    pub fn connect_invert<F>(&self, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.imp().invert.connect_clicked(move |_| f());
+    }
+
     pub fn connect_queue<F>(&self, f: F)
     where
         F: Fn() + 'static,
@@ -145,6 +158,10 @@ impl SelectionToolbarWidget {
         self.imp().save.set_visible(state.visible());
     }
 
+    pub fn set_summary(&self, summary: &str) {
+        self.imp().summary.set_label(summary);
+    }
+
     pub fn set_visible(&self, visible: bool) {
         gtk::Widget::set_visible(self.upcast_ref(), visible);
         self.imp().action_bar.set_revealed(visible);
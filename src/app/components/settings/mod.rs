@@ -1,6 +1,7 @@
 #[allow(clippy::module_inception)]
 mod settings;
 mod settings_model;
+mod shortcuts_editor;
 
 pub use settings::*;
 pub use settings_model::*;
@@ -1,16 +1,53 @@
+use gettextrs::gettext;
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use crate::app::components::EventListener;
+use crate::app::listening_history::{self, ListeningHistory};
+use crate::app::lock;
+use crate::app::models::BlockedItem;
 use crate::app::AppEvent;
-use crate::settings::SpotSettings;
+use crate::settings::{self, SpotSettings};
 
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
 use libadwaita::prelude::*;
 
+use super::shortcuts_editor::bind_shortcuts;
 use super::SettingsModel;
 
 const SETTINGS: &str = "dev.alextren.Spot";
 
+#[derive(Clone, Copy)]
+enum HistoryFormat {
+    Csv,
+    Json,
+}
+
+impl HistoryFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Csv => "listening-history.csv",
+            Self::Json => "listening-history.json",
+        }
+    }
+
+    fn write(self, file: &gio::File) -> Result<(), Box<dyn std::error::Error>> {
+        let records = ListeningHistory::default().read_all()?;
+        let content = match self {
+            Self::Csv => listening_history::to_csv(&records),
+            Self::Json => listening_history::to_json(&records)?,
+        };
+        let path = file.path().ok_or("not a local file")?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+// Same order as the text size choices in settings.blp
+const FONT_SCALES: [f64; 5] = [0.9, 1.0, 1.1, 1.25, 1.5];
+
 mod imp {
 
     use super::*;
@@ -22,6 +59,9 @@ mod imp {
         #[template_child]
         pub player_bitrate: TemplateChild<libadwaita::ComboRow>,
 
+        #[template_child]
+        pub player_bitrate_metered: TemplateChild<libadwaita::ComboRow>,
+
         #[template_child]
         pub alsa_device: TemplateChild<gtk::Entry>,
 
@@ -34,11 +74,124 @@ mod imp {
         #[template_child]
         pub gapless_playback: TemplateChild<libadwaita::ActionRow>,
 
+        #[template_child]
+        pub volume_control: TemplateChild<libadwaita::ComboRow>,
+
+        #[template_child]
+        pub volume_curve: TemplateChild<libadwaita::ComboRow>,
+
+        #[template_child]
+        pub mono_audio: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub skip_silence: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub precache_album: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub audio_balance_scale: TemplateChild<gtk::Scale>,
+
+        #[template_child]
+        pub background_playback: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub previous_restarts: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub previous_restart_delay: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub previous_restart_delay_spin: TemplateChild<gtk::SpinButton>,
+
+        #[template_child]
+        pub dj_mode: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub queue_no_duplicates: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub queue_trim: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub queue_max_length: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub queue_max_length_spin: TemplateChild<gtk::SpinButton>,
+
+        #[template_child]
+        pub api_max_retries_spin: TemplateChild<gtk::SpinButton>,
+
+        #[template_child]
+        pub grab_media_keys: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub startup_restore_screens: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub startup_minimized: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub startup_resume_playback: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub startup_autostart: TemplateChild<libadwaita::ActionRow>,
+
         #[template_child]
         pub ap_port: TemplateChild<gtk::Entry>,
 
         #[template_child]
         pub theme: TemplateChild<libadwaita::ComboRow>,
+
+        #[template_child]
+        pub accent_color: TemplateChild<libadwaita::EntryRow>,
+
+        #[template_child]
+        pub density: TemplateChild<libadwaita::ComboRow>,
+
+        #[template_child]
+        pub song_rows: TemplateChild<libadwaita::ComboRow>,
+
+        #[template_child]
+        pub font_scale: TemplateChild<libadwaita::ComboRow>,
+
+        #[template_child]
+        pub visualizer: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub listening_history: TemplateChild<libadwaita::ActionRow>,
+
+        #[template_child]
+        pub concerts_app_id: TemplateChild<gtk::Entry>,
+
+        #[template_child]
+        pub playback_hook: TemplateChild<gtk::Entry>,
+
+        #[template_child]
+        pub export_history_csv: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub export_history_json: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub blocked: TemplateChild<libadwaita::PreferencesGroup>,
+
+        pub blocked_rows: RefCell<Vec<libadwaita::ActionRow>>,
+
+        #[template_child]
+        pub lock_pin: TemplateChild<libadwaita::PasswordEntryRow>,
+
+        #[template_child]
+        pub lock_allowed_playlists: TemplateChild<libadwaita::EntryRow>,
+
+        #[template_child]
+        pub export_settings: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub import_settings: TemplateChild<gtk::Button>,
+
+        #[template_child]
+        pub shortcuts: TemplateChild<libadwaita::PreferencesGroup>,
     }
 
     #[glib::object_subclass]
@@ -80,6 +233,10 @@ impl SettingsWindow {
         window.bind_backend_and_device();
         window.bind_settings();
         window.connect_theme_select();
+        window.connect_history_export();
+        window.connect_settings_backup();
+        window.connect_lock();
+        bind_shortcuts(&window, &window.imp().shortcuts);
         window
     }
 
@@ -109,35 +266,37 @@ impl SettingsWindow {
         let widget = self.imp();
         let settings = gio::Settings::new(SETTINGS);
 
-        let player_bitrate = widget
-            .player_bitrate
-            .downcast_ref::<libadwaita::ComboRow>()
-            .unwrap();
-        settings
-            .bind("player-bitrate", player_bitrate, "selected")
-            .mapping(|variant, _| {
-                variant.str().map(|s| {
-                    match s {
-                        "96" => 0,
-                        "160" => 1,
-                        "320" => 2,
-                        _ => unreachable!(),
-                    }
-                    .to_value()
+        let bitrates = [
+            ("player-bitrate", &*widget.player_bitrate),
+            ("player-bitrate-metered", &*widget.player_bitrate_metered),
+        ];
+        for (key, row) in bitrates {
+            settings
+                .bind(key, row, "selected")
+                .mapping(|variant, _| {
+                    variant.str().map(|s| {
+                        match s {
+                            "96" => 0,
+                            "160" => 1,
+                            "320" => 2,
+                            _ => unreachable!(),
+                        }
+                        .to_value()
+                    })
                 })
-            })
-            .set_mapping(|value, _| {
-                value.get::<u32>().ok().map(|u| {
-                    match u {
-                        0 => "96",
-                        1 => "160",
-                        2 => "320",
-                        _ => unreachable!(),
-                    }
-                    .to_variant()
+                .set_mapping(|value, _| {
+                    value.get::<u32>().ok().map(|u| {
+                        match u {
+                            0 => "96",
+                            1 => "160",
+                            2 => "320",
+                            _ => unreachable!(),
+                        }
+                        .to_variant()
+                    })
                 })
-            })
-            .build();
+                .build();
+        }
 
         let alsa_device = widget.alsa_device.downcast_ref::<gtk::Entry>().unwrap();
         settings.bind("alsa-device", alsa_device, "text").build();
@@ -184,6 +343,228 @@ impl SettingsWindow {
             )
             .build();
 
+        settings
+            .bind("volume-control", &*widget.volume_control, "selected")
+            .mapping(|variant, _| {
+                variant.str().map(|s| {
+                    match s {
+                        "software" => 0,
+                        "system" => 1,
+                        _ => unreachable!(),
+                    }
+                    .to_value()
+                })
+            })
+            .set_mapping(|value, _| {
+                value.get::<u32>().ok().map(|u| {
+                    match u {
+                        0 => "software",
+                        1 => "system",
+                        _ => unreachable!(),
+                    }
+                    .to_variant()
+                })
+            })
+            .build();
+
+        settings
+            .bind("volume-curve", &*widget.volume_curve, "selected")
+            .mapping(|variant, _| {
+                variant.str().map(|s| {
+                    match s {
+                        "logarithmic" => 0,
+                        "cubic" => 1,
+                        "linear" => 2,
+                        _ => unreachable!(),
+                    }
+                    .to_value()
+                })
+            })
+            .set_mapping(|value, _| {
+                value.get::<u32>().ok().map(|u| {
+                    match u {
+                        0 => "logarithmic",
+                        1 => "cubic",
+                        2 => "linear",
+                        _ => unreachable!(),
+                    }
+                    .to_variant()
+                })
+            })
+            .build();
+
+        settings
+            .bind(
+                "mono-audio",
+                &widget.mono_audio.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        settings
+            .bind(
+                "skip-silence",
+                &widget.skip_silence.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        settings
+            .bind(
+                "precache-album",
+                &widget.precache_album.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        // Centered is where most people want it, so it gets a notch
+        let audio_balance = &*widget.audio_balance_scale;
+        audio_balance.add_mark(0.0, gtk::PositionType::Bottom, None);
+        settings
+            .bind("audio-balance", &audio_balance.adjustment(), "value")
+            .mapping(|variant, _| variant.get::<i32>().map(|b| (b as f64).to_value()))
+            .set_mapping(|value, _| {
+                value
+                    .get::<f64>()
+                    .ok()
+                    .map(|b| (b.round() as i32).to_variant())
+            })
+            .build();
+
+        settings
+            .bind(
+                "listening-history",
+                &widget.listening_history.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        settings
+            .bind("concerts-app-id", &*widget.concerts_app_id, "text")
+            .build();
+
+        settings
+            .bind("playback-hook", &*widget.playback_hook, "text")
+            .build();
+
+        settings
+            .bind(
+                "background-playback",
+                &widget.background_playback.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        settings
+            .bind(
+                "previous-restarts",
+                &widget.previous_restarts.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        settings
+            .bind(
+                "previous-restarts",
+                &*widget.previous_restart_delay,
+                "sensitive",
+            )
+            .get_only()
+            .build();
+
+        settings
+            .bind(
+                "previous-restart-delay",
+                &widget.previous_restart_delay_spin.adjustment(),
+                "value",
+            )
+            .mapping(|variant, _| variant.get::<u32>().map(|s| (s as f64).to_value()))
+            .set_mapping(|value, _| {
+                value
+                    .get::<f64>()
+                    .ok()
+                    .map(|s| (s.round() as u32).to_variant())
+            })
+            .build();
+
+        settings
+            .bind(
+                "dj-mode",
+                &widget.dj_mode.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        settings
+            .bind(
+                "queue-no-duplicates",
+                &widget.queue_no_duplicates.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        settings
+            .bind(
+                "queue-trim",
+                &widget.queue_trim.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        settings
+            .bind("queue-trim", &*widget.queue_max_length, "sensitive")
+            .get_only()
+            .build();
+
+        settings
+            .bind(
+                "queue-max-length",
+                &widget.queue_max_length_spin.adjustment(),
+                "value",
+            )
+            .mapping(|variant, _| variant.get::<u32>().map(|s| (s as f64).to_value()))
+            .set_mapping(|value, _| {
+                value
+                    .get::<f64>()
+                    .ok()
+                    .map(|s| (s.round() as u32).to_variant())
+            })
+            .build();
+
+        settings
+            .bind(
+                "grab-media-keys",
+                &widget.grab_media_keys.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
+
+        for (key, row) in [
+            ("startup-restore-screens", &widget.startup_restore_screens),
+            ("startup-minimized", &widget.startup_minimized),
+            ("startup-resume-playback", &widget.startup_resume_playback),
+            ("startup-autostart", &widget.startup_autostart),
+        ] {
+            settings
+                .bind(key, &row.activatable_widget().unwrap(), "active")
+                .build();
+        }
+
+        settings
+            .bind(
+                "api-max-retries",
+                &widget.api_max_retries_spin.adjustment(),
+                "value",
+            )
+            .mapping(|variant, _| variant.get::<u32>().map(|s| (s as f64).to_value()))
+            .set_mapping(|value, _| {
+                value
+                    .get::<f64>()
+                    .ok()
+                    .map(|s| (s.round() as u32).to_variant())
+            })
+            .build();
+
         let ap_port = widget.ap_port.downcast_ref::<gtk::Entry>().unwrap();
         settings
             .bind("ap-port", ap_port, "text")
@@ -217,6 +598,86 @@ impl SettingsWindow {
                 })
             })
             .build();
+
+        settings
+            .bind("accent-color", &*widget.accent_color, "text")
+            .build();
+
+        settings
+            .bind("density", &*widget.density, "selected")
+            .mapping(|variant, _| {
+                variant.str().map(|s| {
+                    match s {
+                        "compact" => 0,
+                        "comfortable" => 1,
+                        "spacious" => 2,
+                        _ => unreachable!(),
+                    }
+                    .to_value()
+                })
+            })
+            .set_mapping(|value, _| {
+                value.get::<u32>().ok().map(|u| {
+                    match u {
+                        0 => "compact",
+                        1 => "comfortable",
+                        2 => "spacious",
+                        _ => unreachable!(),
+                    }
+                    .to_variant()
+                })
+            })
+            .build();
+
+        settings
+            .bind("song-rows", &*widget.song_rows, "selected")
+            .mapping(|variant, _| {
+                variant.str().map(|s| {
+                    match s {
+                        "standard" => 0,
+                        "detailed" => 1,
+                        _ => unreachable!(),
+                    }
+                    .to_value()
+                })
+            })
+            .set_mapping(|value, _| {
+                value.get::<u32>().ok().map(|u| {
+                    match u {
+                        0 => "standard",
+                        1 => "detailed",
+                        _ => unreachable!(),
+                    }
+                    .to_variant()
+                })
+            })
+            .build();
+
+        settings
+            .bind("font-scale", &*widget.font_scale, "selected")
+            .mapping(|variant, _| {
+                let scale = variant.get::<f64>()?;
+                // Picks the closest entry, in case the value was set outside of the app
+                let closest = FONT_SCALES
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| (*a - scale).abs().total_cmp(&(*b - scale).abs()))
+                    .map(|(i, _)| i as u32)?;
+                Some(closest.to_value())
+            })
+            .set_mapping(|value, _| {
+                let index = value.get::<u32>().ok()?;
+                FONT_SCALES.get(index as usize).map(|s| s.to_variant())
+            })
+            .build();
+
+        settings
+            .bind(
+                "visualizer",
+                &widget.visualizer.activatable_widget().unwrap(),
+                "active",
+            )
+            .build();
     }
 
     fn connect_theme_select(&self) {
@@ -236,6 +697,185 @@ impl SettingsWindow {
         });
     }
 
+    fn connect_history_export(&self) {
+        let widget = self.imp();
+        widget
+            .export_history_csv
+            .connect_clicked(clone!(@weak self as _self => move |_| {
+                _self.export_history(HistoryFormat::Csv);
+            }));
+        widget
+            .export_history_json
+            .connect_clicked(clone!(@weak self as _self => move |_| {
+                _self.export_history(HistoryFormat::Json);
+            }));
+    }
+
+    fn export_history(&self, format: HistoryFormat) {
+        let dialog = gtk::FileDialog::builder()
+            // translators: Title of the dialog to pick where the listening history gets saved
+            .title(gettext("Export the history"))
+            .initial_name(format.file_name())
+            .modal(true)
+            .build();
+        dialog.save(
+            Some(self),
+            gio::Cancellable::NONE,
+            clone!(@weak self as _self => move |result| {
+                // Cancelling is an error too, there's nothing to say about it
+                let Ok(file) = result else {
+                    return;
+                };
+                if let Err(e) = format.write(&file) {
+                    warn!("Could not export the listening history: {}", e);
+                    // translators: Shown when the listening history could not be written to a file
+                    _self.add_toast(libadwaita::Toast::new(&gettext("Could not export the history")));
+                }
+            }),
+        );
+    }
+
+    fn connect_settings_backup(&self) {
+        let widget = self.imp();
+        widget
+            .export_settings
+            .connect_clicked(clone!(@weak self as _self => move |_| {
+                _self.export_settings();
+            }));
+        widget
+            .import_settings
+            .connect_clicked(clone!(@weak self as _self => move |_| {
+                _self.import_settings();
+            }));
+    }
+
+    // Rebuilt from scratch, there's never that many
+    fn set_blocked<F>(&self, items: &[BlockedItem], on_unblock: F)
+    where
+        F: Fn(&str) + Clone + 'static,
+    {
+        let widget = self.imp();
+        for row in widget.blocked_rows.take() {
+            widget.blocked.remove(&row);
+        }
+        let rows = items
+            .iter()
+            .map(|item| {
+                let row = libadwaita::ActionRow::builder()
+                    .title(glib::markup_escape_text(&item.name))
+                    .subtitle(if item.is_artist() {
+                        // translators: Subtitle of a blocked artist in the preferences
+                        gettext("Artist")
+                    } else {
+                        // translators: Subtitle of a blocked song in the preferences
+                        gettext("Song")
+                    })
+                    .build();
+                let button = gtk::Button::builder()
+                    .icon_name("edit-delete-symbolic")
+                    // translators: Tooltip of the button next to a blocked song or artist in the preferences
+                    .tooltip_text(gettext("Play it again"))
+                    .valign(gtk::Align::Center)
+                    .build();
+                button.add_css_class("flat");
+                let uri = item.uri.clone();
+                let on_unblock = on_unblock.clone();
+                button.connect_clicked(move |_| on_unblock(&uri));
+                row.add_suffix(&button);
+                widget.blocked.add(&row);
+                row
+            })
+            .collect();
+        widget.blocked_rows.replace(rows);
+    }
+
+    // Neither is bound to the settings, they're only saved once applied
+    fn connect_lock(&self) {
+        let widget = self.imp();
+        widget
+            .lock_pin
+            .connect_apply(clone!(@weak self as _self => move |row| {
+                let message = if settings::set_lock_pin(&row.text()).is_none() {
+                    // translators: Shown when the PIN to lock Spot could not be saved
+                    gettext("Could not save the PIN")
+                } else if settings::has_lock_pin() {
+                    // translators: Shown once a PIN to lock Spot was chosen
+                    gettext("PIN saved, Spot can now be locked from the menu")
+                } else {
+                    // translators: Shown once the PIN to lock Spot was removed
+                    gettext("PIN removed")
+                };
+                row.set_text("");
+                _self.add_toast(libadwaita::Toast::new(&message));
+            }));
+
+        widget
+            .lock_allowed_playlists
+            .set_text(&lock::playlist_links(&settings::lock_allowed_playlists()));
+        widget.lock_allowed_playlists.connect_apply(|row| {
+            let ids = lock::playlist_ids(&row.text());
+            settings::set_lock_allowed_playlists(&ids);
+            row.set_text(&lock::playlist_links(&ids));
+        });
+    }
+
+    fn export_settings(&self) {
+        let dialog = gtk::FileDialog::builder()
+            // translators: Title of the dialog to pick where the settings get saved
+            .title(gettext("Export the settings"))
+            .initial_name("spot-settings.json")
+            .modal(true)
+            .build();
+        dialog.save(
+            Some(self),
+            gio::Cancellable::NONE,
+            clone!(@weak self as _self => move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                let written = settings::export_backup()
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| std::fs::write(path, content).map_err(|e| e.to_string()));
+                if let Err(e) = written {
+                    warn!("Could not export the settings: {}", e);
+                    // translators: Shown when the settings could not be written to a file
+                    _self.add_toast(libadwaita::Toast::new(&gettext("Could not export the settings")));
+                }
+            }),
+        );
+    }
+
+    fn import_settings(&self) {
+        let dialog = gtk::FileDialog::builder()
+            // translators: Title of the dialog to pick a settings file to restore
+            .title(gettext("Import the settings"))
+            .modal(true)
+            .build();
+        dialog.open(
+            Some(self),
+            gio::Cancellable::NONE,
+            clone!(@weak self as _self => move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                let imported = std::fs::read(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| settings::import_backup(&content).map_err(|e| e.to_string()));
+                let message = match imported {
+                    // The shortcuts are only read when Spot starts
+                    // translators: Shown once settings were imported from a file
+                    Ok(()) => gettext("Settings imported, restart Spot to apply all of them"),
+                    Err(e) => {
+                        warn!("Could not import the settings: {}", e);
+                        // translators: Shown when a settings file could not be read
+                        gettext("Could not import the settings")
+                    }
+                };
+                _self.add_toast(libadwaita::Toast::new(&message));
+            }),
+        );
+    }
+
     fn connect_close<F>(&self, on_close: F)
     where
         F: Fn() + 'static,
@@ -254,26 +894,39 @@ impl SettingsWindow {
 pub struct Settings {
     parent: gtk::Window,
     settings_window: SettingsWindow,
+    model: Rc<SettingsModel>,
 }
 
 impl Settings {
     pub fn new(parent: gtk::Window, model: SettingsModel) -> Self {
         let settings_window = SettingsWindow::new();
+        let model = Rc::new(model);
 
-        settings_window.connect_close(move || {
+        settings_window.connect_close(clone!(@strong model => move || {
             let new_settings = SpotSettings::new_from_gsettings().unwrap_or_default();
             if model.settings().player_settings != new_settings.player_settings {
                 model.stop_player();
             }
             model.set_settings();
-        });
+        }));
 
         Self {
             parent,
             settings_window,
+            model,
         }
     }
 
+    pub fn update_blocked(&self) {
+        let model = Rc::downgrade(&self.model);
+        self.settings_window
+            .set_blocked(&self.model.blocked_items(), move |uri| {
+                if let Some(model) = model.upgrade() {
+                    model.unblock(uri);
+                }
+            });
+    }
+
     fn window(&self) -> &libadwaita::Window {
         self.settings_window.upcast_ref::<libadwaita::Window>()
     }
@@ -1,3 +1,4 @@
+use crate::app::models::BlockedItem;
 use crate::app::state::{PlaybackAction, SettingsAction};
 use crate::app::{ActionDispatcher, AppModel};
 use crate::settings::SpotSettings;
@@ -25,6 +26,15 @@ impl SettingsModel {
             .dispatch(SettingsAction::ChangeSettings.into());
     }
 
+    pub fn blocked_items(&self) -> Vec<BlockedItem> {
+        self.app_model.get_state().playback.blocklist().items()
+    }
+
+    pub fn unblock(&self, uri: &str) {
+        self.dispatcher
+            .dispatch(PlaybackAction::Unblock(uri.to_string()).into());
+    }
+
     pub fn settings(&self) -> SpotSettings {
         let state = self.app_model.get_state();
         state.settings.settings.clone()
@@ -0,0 +1,149 @@
+use gettextrs::*;
+use gtk::prelude::*;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::settings::Keymap;
+use crate::shortcuts::{self, AppShortcut, SHORTCUTS};
+
+// Pressing one of these alone doesn't make a shortcut
+const MODIFIER_KEYS: [gdk::Key; 12] = [
+    gdk::Key::Shift_L,
+    gdk::Key::Shift_R,
+    gdk::Key::Control_L,
+    gdk::Key::Control_R,
+    gdk::Key::Alt_L,
+    gdk::Key::Alt_R,
+    gdk::Key::Super_L,
+    gdk::Key::Super_R,
+    gdk::Key::Meta_L,
+    gdk::Key::Meta_R,
+    gdk::Key::ISO_Level3_Shift,
+    gdk::Key::Caps_Lock,
+];
+
+#[derive(Clone)]
+struct ShortcutRow {
+    shortcut: &'static AppShortcut,
+    row: libadwaita::ActionRow,
+    label: gtk::ShortcutLabel,
+    reset_button: gtk::Button,
+}
+
+impl ShortcutRow {
+    fn new(shortcut: &'static AppShortcut) -> Self {
+        let label = gtk::ShortcutLabel::builder()
+            .valign(gtk::Align::Center)
+            // translators: This is shown in preferences in place of a keyboard shortcut that was turned off
+            .disabled_text(gettext("Disabled"))
+            .build();
+        let reset_button = gtk::Button::builder()
+            .icon_name("edit-undo-symbolic")
+            .valign(gtk::Align::Center)
+            // translators: Tooltip of the button restoring the default keyboard shortcut of an action, in preferences
+            .tooltip_text(gettext("Restore default"))
+            .build();
+        reset_button.add_css_class("flat");
+        let row = libadwaita::ActionRow::builder()
+            .title(shortcut.title())
+            .activatable(true)
+            .build();
+        row.add_suffix(&label);
+        row.add_suffix(&reset_button);
+        Self {
+            shortcut,
+            row,
+            label,
+            reset_button,
+        }
+    }
+
+    fn refresh(&self, keymap: &Keymap) {
+        let action = self.shortcut.action;
+        let trigger = keymap.trigger(action, self.shortcut.default_trigger);
+        self.label.set_accelerator(trigger);
+        self.reset_button.set_visible(keymap.is_overridden(action));
+        self.row.set_subtitle("");
+    }
+
+    fn set_capturing(&self) {
+        // translators: Shown in preferences while waiting for the user to type a new keyboard shortcut
+        self.row.set_subtitle(&gettext(
+            "Press a key combination, Backspace to disable, Escape to cancel",
+        ));
+    }
+
+    fn set_conflicting(&self, other: &AppShortcut) {
+        // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+        if cfg!(debug_assertions) {
+            // translators: Shown in preferences when the key combination just typed is already the shortcut of another action. The full text is "Already used by “<action>”, press another key combination".
+            gettext("Already used by “{}”, press another key combination");
+        }
+        self.row.set_subtitle(&gettext!(
+            "Already used by “{}”, press another key combination",
+            other.title()
+        ));
+    }
+}
+
+// Lists the shortcuts in the given group; clicking one waits for the next key combination typed in that window
+pub fn bind_shortcuts(window: &impl IsA<gtk::Widget>, group: &libadwaita::PreferencesGroup) {
+    let keymap = Rc::new(RefCell::new(Keymap::new_from_gsettings()));
+    let capturing: Rc<RefCell<Option<ShortcutRow>>> = Default::default();
+
+    for shortcut in SHORTCUTS {
+        let row = ShortcutRow::new(shortcut);
+        row.refresh(&keymap.borrow());
+        group.add(&row.row);
+
+        row.row.connect_activated(
+            clone!(@strong capturing, @strong keymap, @strong row => move |_| {
+                if let Some(previous) = capturing.replace(Some(row.clone())) {
+                    previous.refresh(&keymap.borrow());
+                }
+                row.set_capturing();
+            }),
+        );
+
+        row.reset_button
+            .connect_clicked(clone!(@strong keymap, @strong row => move |_| {
+                let mut keymap = keymap.borrow_mut();
+                keymap.reset(row.shortcut.action);
+                keymap.save();
+                row.refresh(&keymap);
+            }));
+    }
+
+    let controller = gtk::EventControllerKey::new();
+    controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+    controller.connect_key_pressed(move |_, keyval, _, state| {
+        let Some(row) = capturing.borrow().clone() else {
+            return gtk::Inhibit(false);
+        };
+        if MODIFIER_KEYS.contains(&keyval) {
+            return gtk::Inhibit(true);
+        }
+
+        let mut keymap = keymap.borrow_mut();
+        let modifiers = state & gtk::accelerator_get_default_mod_mask();
+        if keyval == gdk::Key::BackSpace && modifiers.is_empty() {
+            keymap.set(row.shortcut.action, String::new());
+            keymap.save();
+        } else if keyval != gdk::Key::Escape || !modifiers.is_empty() {
+            let trigger = gtk::accelerator_name(keyval, modifiers);
+            // Still waiting for a combination nothing else uses
+            if let Some(other) = shortcuts::bound_elsewhere(&keymap, row.shortcut.action, &trigger)
+            {
+                row.set_conflicting(other);
+                return gtk::Inhibit(true);
+            }
+            keymap.set(row.shortcut.action, trigger.to_string());
+            keymap.save();
+        }
+        row.refresh(&keymap);
+        capturing.replace(None);
+        gtk::Inhibit(true)
+    });
+    window.add_controller(controller);
+}
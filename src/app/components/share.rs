@@ -0,0 +1,104 @@
+use gio::prelude::*;
+use gio::{SimpleAction, SimpleActionGroup};
+
+use crate::app::components::labels;
+
+// What a link points to, as it appears in open.spotify.com URLs and spotify: URIs
+#[derive(Clone, Copy, Debug)]
+pub enum ShareKind {
+    Track,
+    Album,
+    Artist,
+    Playlist,
+    Episode,
+}
+
+impl ShareKind {
+    fn path(self) -> &'static str {
+        match self {
+            Self::Track => "track",
+            Self::Album => "album",
+            Self::Artist => "artist",
+            Self::Playlist => "playlist",
+            Self::Episode => "episode",
+        }
+    }
+}
+
+pub fn web_link(kind: ShareKind, id: &str) -> String {
+    format!("https://open.spotify.com/{}/{}", kind.path(), id)
+}
+
+pub fn spotify_uri(kind: ShareKind, id: &str) -> String {
+    format!("spotify:{}:{}", kind.path(), id)
+}
+
+pub fn copy_to_clipboard(text: &str) {
+    if let Some(display) = gdk::Display::default() {
+        display.clipboard().set_text(text);
+    }
+}
+
+fn make_copy_action(name: &str, text: String) -> SimpleAction {
+    let action = SimpleAction::new(name, None);
+    action.connect_activate(move |_, _| copy_to_clipboard(&text));
+    action
+}
+
+pub fn make_link_action(kind: ShareKind, id: &str, name: Option<&str>) -> SimpleAction {
+    make_copy_action(name.unwrap_or("copy_link"), web_link(kind, id))
+}
+
+pub fn make_uri_action(kind: ShareKind, id: &str, name: Option<&str>) -> SimpleAction {
+    make_copy_action(name.unwrap_or("copy_uri"), spotify_uri(kind, id))
+}
+
+// Adds both copy actions to the group, and the matching entries to the menu
+pub fn add_share_items(
+    kind: ShareKind,
+    id: &str,
+    group_name: &str,
+    group: &SimpleActionGroup,
+    menu: &gio::Menu,
+) {
+    group.add_action(&make_link_action(kind, id, None));
+    group.add_action(&make_uri_action(kind, id, None));
+    menu.append(
+        Some(&*labels::COPY_LINK),
+        Some(&format!("{group_name}.copy_link")),
+    );
+    menu.append(
+        Some(&*labels::COPY_URI),
+        Some(&format!("{group_name}.copy_uri")),
+    );
+}
+
+// For cards (albums, playlists, artists) that have nothing else in their menu
+pub fn share_menu(
+    kind: ShareKind,
+    id: &str,
+    group_name: &str,
+) -> (gio::ActionGroup, gio::MenuModel) {
+    let group = SimpleActionGroup::new();
+    let menu = gio::Menu::new();
+    add_share_items(kind, id, group_name, &group, &menu);
+    (group.upcast(), menu.upcast())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_links() {
+        assert_eq!(
+            web_link(ShareKind::Album, "abc"),
+            "https://open.spotify.com/album/abc"
+        );
+        assert_eq!(
+            spotify_uri(ShareKind::Playlist, "abc"),
+            "spotify:playlist:abc"
+        );
+    }
+}
@@ -55,7 +55,7 @@ impl SidebarModel {
         let user_id = self.app_model.get_state().logged_user.user.clone().unwrap();
         let api = self.app_model.get_spotify();
         self.dispatcher
-            .call_spotify_and_dispatch(move || async move {
+            .write_spotify_and_dispatch(move || async move {
                 api.create_new_playlist(name.as_str(), user_id.as_str())
                     .await
                     .map(AppAction::CreatePlaylist)
@@ -66,6 +66,8 @@ impl SidebarModel {
         let actions = match dest {
             SidebarDestination::Library
             | SidebarDestination::SavedTracks
+            | SidebarDestination::ForYou
+            | SidebarDestination::Audiobooks
             | SidebarDestination::NowPlaying
             | SidebarDestination::SavedPlaylists => {
                 vec![
@@ -98,6 +100,10 @@ impl Sidebar {
         list_store.append(&SidebarItem::from_destination(
             SidebarDestination::SavedTracks,
         ));
+        list_store.append(&SidebarItem::from_destination(SidebarDestination::ForYou));
+        list_store.append(&SidebarItem::from_destination(
+            SidebarDestination::Audiobooks,
+        ));
         list_store.append(&SidebarItem::from_destination(
             SidebarDestination::NowPlaying,
         ));
@@ -7,6 +7,8 @@ use crate::app::models::PlaylistSummary;
 
 const LIBRARY: &str = "library";
 const SAVED_TRACKS: &str = "saved_tracks";
+const FOR_YOU: &str = "for_you";
+const AUDIOBOOKS: &str = "audiobooks";
 const NOW_PLAYING: &str = "now_playing";
 const SAVED_PLAYLISTS: &str = "saved_playlists";
 const PLAYLIST: &str = "playlist";
@@ -17,6 +19,8 @@ pub const CREATE_PLAYLIST_ITEM: &str = "create_playlist";
 pub enum SidebarDestination {
     Library,
     SavedTracks,
+    ForYou,
+    Audiobooks,
     NowPlaying,
     SavedPlaylists,
     Playlist(PlaylistSummary),
@@ -27,6 +31,8 @@ impl SidebarDestination {
         match self {
             Self::Library => LIBRARY,
             Self::SavedTracks => SAVED_TRACKS,
+            Self::ForYou => FOR_YOU,
+            Self::Audiobooks => AUDIOBOOKS,
             Self::NowPlaying => NOW_PLAYING,
             Self::SavedPlaylists => SAVED_PLAYLISTS,
             Self::Playlist(_) => PLAYLIST,
@@ -39,6 +45,10 @@ impl SidebarDestination {
             Self::Library => gettext("Library"),
             // translators: This is a sidebar entry to browse to saved tracks.
             Self::SavedTracks => gettext("Saved tracks"),
+            // translators: This is a sidebar entry to browse to playlists Spotify made for the user (Daily Mixes and such).
+            Self::ForYou => gettext("For you"),
+            // translators: This is a sidebar entry to browse to saved audiobooks.
+            Self::Audiobooks => gettext("Audiobooks"),
             // translators: This is a sidebar entry to browse to saved playlists.
             Self::NowPlaying => gettext("Now playing"),
             // translators: This is a sidebar entry that marks that the entries below are playlists.
@@ -51,6 +61,8 @@ impl SidebarDestination {
         match self {
             Self::Library => "library-music-symbolic",
             Self::SavedTracks => "starred-symbolic",
+            Self::ForYou => "emblem-music-symbolic",
+            Self::Audiobooks => "audio-x-generic-symbolic",
             Self::NowPlaying => "music-queue-symbolic",
             Self::SavedPlaylists => "view-app-grid-symbolic",
             Self::Playlist(_) => "playlist2-symbolic",
@@ -101,6 +113,8 @@ impl SidebarItem {
             match id.as_str() {
                 LIBRARY => Some(SidebarDestination::Library),
                 SAVED_TRACKS => Some(SidebarDestination::SavedTracks),
+                FOR_YOU => Some(SidebarDestination::ForYou),
+                AUDIOBOOKS => Some(SidebarDestination::Audiobooks),
                 NOW_PLAYING => Some(SidebarDestination::NowPlaying),
                 SAVED_PLAYLISTS => Some(SidebarDestination::SavedPlaylists),
                 PLAYLIST => Some(SidebarDestination::Playlist(PlaylistSummary {
@@ -0,0 +1,281 @@
+use serde::Serialize;
+use std::io::Write;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use super::playback_hooks::HookTrack;
+use crate::app::components::EventListener;
+use crate::app::state::PlaybackEvent;
+use crate::app::{AppEvent, AppModel};
+
+pub const STATUS_SOCKET_FLAG: &str = "status-socket";
+
+// A client that doesn't read what it's sent for that long gets dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+// How often new clients are looked for when nothing happens
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(250);
+
+// One JSON object per line, with the kind of event in "event"
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+enum StatusEvent {
+    TrackChanged {
+        track: Option<HookTrack>,
+    },
+    Playing {
+        position_ms: u32,
+    },
+    Paused {
+        position_ms: u32,
+    },
+    Stopped,
+    Position {
+        position_ms: u32,
+    },
+    QueueChanged {
+        length: usize,
+        position: Option<usize>,
+    },
+}
+
+type StatusLine = (Option<usize>, String);
+
+impl StatusEvent {
+    // Clients connecting later are first told the latest event of each slot (track, state, queue),
+    // so that they don't have to wait for something to happen
+    fn slot(&self) -> Option<usize> {
+        match self {
+            Self::TrackChanged { .. } => Some(0),
+            Self::Playing { .. } | Self::Paused { .. } | Self::Stopped => Some(1),
+            Self::QueueChanged { .. } => Some(2),
+            Self::Position { .. } => None,
+        }
+    }
+
+    fn to_line(&self) -> StatusLine {
+        (self.slot(), serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+// Runs on its own thread, so that a slow client never holds up the app
+fn serve(listener: UnixListener, lines: Receiver<StatusLine>) {
+    let mut latest: [Option<String>; 3] = Default::default();
+    let mut clients: Vec<UnixStream> = vec![];
+    loop {
+        match lines.recv_timeout(ACCEPT_INTERVAL) {
+            Ok((slot, line)) => {
+                clients.retain_mut(|client| writeln!(client, "{line}").is_ok());
+                if let Some(slot) = slot {
+                    latest[slot] = Some(line);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        while let Ok((mut client, _)) = listener.accept() {
+            let _ = client.set_nonblocking(false);
+            let _ = client.set_write_timeout(Some(WRITE_TIMEOUT));
+            if latest
+                .iter()
+                .flatten()
+                .all(|line| writeln!(client, "{line}").is_ok())
+            {
+                clients.push(client);
+            }
+        }
+    }
+}
+
+fn listen(path: &Path) -> std::io::Result<Sender<StatusLine>> {
+    // Left over by a previous run. Anything else at that path isn't ours to remove
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "not a socket",
+            ))
+        }
+        Err(_) => {}
+    }
+    let listener = UnixListener::bind(path)?;
+    listener.set_nonblocking(true)?;
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || serve(listener, receiver));
+    Ok(sender)
+}
+
+// Given with --status-socket=<path>
+fn path_from_args() -> Option<PathBuf> {
+    let flag = format!("--{STATUS_SOCKET_FLAG}");
+    let mut args = std::env::args();
+    loop {
+        let arg = args.next()?;
+        if arg == flag {
+            break args.next().map(PathBuf::from);
+        } else if let Some(path) = arg.strip_prefix(&format!("{flag}=")) {
+            break Some(path.into());
+        }
+    }
+}
+
+// Newline-delimited JSON about what's playing, for status bars (waybar, polybar...) to follow
+// without polling MPRIS: `socat - UNIX-CONNECT:<path>` prints it as it comes.
+// Does nothing unless Spot was started with --status-socket.
+pub struct StatusStream {
+    app_model: Rc<AppModel>,
+    sender: Option<Sender<StatusLine>>,
+    // Tells the position every second while playing
+    ticker: Option<glib::SourceId>,
+}
+
+impl StatusStream {
+    pub fn new(app_model: Rc<AppModel>) -> Self {
+        let sender = path_from_args().and_then(|path| {
+            listen(&path)
+                .map_err(|e| warn!("Could not listen on {}: {}", path.display(), e))
+                .ok()
+        });
+        Self {
+            app_model,
+            sender,
+            ticker: None,
+        }
+    }
+
+    fn send(&self, event: StatusEvent) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event.to_line());
+        }
+    }
+
+    fn position(&self) -> u32 {
+        self.app_model.get_state().playback.seek_position()
+    }
+
+    fn send_track(&self) {
+        let track = self
+            .app_model
+            .get_state()
+            .playback
+            .current_song()
+            .as_ref()
+            .map(HookTrack::from);
+        self.send(StatusEvent::TrackChanged { track });
+    }
+
+    fn send_queue(&self) {
+        let state = self.app_model.get_state();
+        let event = StatusEvent::QueueChanged {
+            length: state.playback.songs().len(),
+            position: state.playback.current_song_index(),
+        };
+        drop(state);
+        self.send(event);
+    }
+
+    fn set_ticking(&mut self, ticking: bool) {
+        if let Some(ticker) = self.ticker.take() {
+            ticker.remove();
+        }
+        let Some(sender) = self.sender.clone().filter(|_| ticking) else {
+            return;
+        };
+        let app_model = Rc::clone(&self.app_model);
+        self.ticker = Some(glib::timeout_add_seconds_local(1, move || {
+            let position_ms = app_model.get_state().playback.seek_position();
+            let _ = sender.send(StatusEvent::Position { position_ms }.to_line());
+            glib::Continue(true)
+        }));
+    }
+}
+
+impl EventListener for StatusStream {
+    fn on_event(&mut self, event: &AppEvent) {
+        if self.sender.is_none() {
+            return;
+        }
+        let AppEvent::PlaybackEvent(event) = event else {
+            return;
+        };
+        match event {
+            PlaybackEvent::TrackChanged(_) => {
+                self.send_track();
+                self.send_queue();
+            }
+            PlaybackEvent::PlaybackResumed => {
+                self.send(StatusEvent::Playing {
+                    position_ms: self.position(),
+                });
+                self.set_ticking(true);
+            }
+            PlaybackEvent::PlaybackPaused => {
+                self.send(StatusEvent::Paused {
+                    position_ms: self.position(),
+                });
+                self.set_ticking(false);
+            }
+            PlaybackEvent::PlaybackStopped => {
+                self.send(StatusEvent::Stopped);
+                self.set_ticking(false);
+            }
+            PlaybackEvent::TrackSeeked(position_ms) | PlaybackEvent::SeekSynced(position_ms) => {
+                self.send(StatusEvent::Position {
+                    position_ms: *position_ms,
+                });
+            }
+            PlaybackEvent::PlaylistChanged | PlaybackEvent::TracksQueued(_) => self.send_queue(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::test_utils::TempDir;
+
+    #[test]
+    fn test_status_lines() {
+        let (slot, line) = StatusEvent::Paused { position_ms: 1000 }.to_line();
+        assert_eq!(slot, Some(1));
+        assert_eq!(line, r#"{"event":"paused","position_ms":1000}"#);
+
+        let (slot, line) = StatusEvent::Stopped.to_line();
+        assert_eq!(slot, Some(1));
+        assert_eq!(line, r#"{"event":"stopped"}"#);
+
+        let (slot, line) = StatusEvent::QueueChanged {
+            length: 12,
+            position: None,
+        }
+        .to_line();
+        assert_eq!(slot, Some(2));
+        assert_eq!(
+            line,
+            r#"{"event":"queue-changed","length":12,"position":null}"#
+        );
+
+        let (slot, _) = StatusEvent::Position { position_ms: 0 }.to_line();
+        assert_eq!(slot, None);
+    }
+
+    #[test]
+    fn test_only_replaces_sockets() {
+        let dir = TempDir::create();
+        let notes = dir.join("notes.txt");
+        std::fs::write(&notes, "keep me").unwrap();
+        assert!(listen(&notes).is_err());
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "keep me");
+
+        // What a previous run left behind is reused
+        let socket = dir.join("status.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(listen(&socket).is_ok());
+    }
+}
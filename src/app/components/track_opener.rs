@@ -0,0 +1,47 @@
+use gettextrs::gettext;
+use std::rc::Rc;
+
+use crate::app::components::EventListener;
+use crate::app::state::PlaybackAction;
+use crate::app::{ActionDispatcher, AppAction, AppEvent, AppModel};
+
+// Plays the track a link pointed to, once we know what that track is
+pub struct TrackOpener {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl TrackOpener {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+
+    fn open_track(&self, id: String) {
+        let api = self.app_model.get_spotify();
+        self.dispatcher
+            .call_spotify_and_dispatch_many(move || async move {
+                let songs = api.get_tracks(vec![id.clone()]).await?;
+                if songs.is_empty() {
+                    // translators: Shown when a link to a track was opened, but Spotify doesn't know about that track
+                    return Ok(vec![AppAction::ShowNotification(gettext(
+                        "Could not find this track",
+                    ))]);
+                }
+                Ok(vec![
+                    PlaybackAction::LoadSongs(songs).into(),
+                    PlaybackAction::Load(id).into(),
+                ])
+            })
+    }
+}
+
+impl EventListener for TrackOpener {
+    fn on_event(&mut self, event: &AppEvent) {
+        if let AppEvent::TrackOpenRequested(id) = event {
+            self.open_track(id.clone());
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::api::{SpotifyApiClient, SpotifyApiError, SpotifyResult};
+use crate::app::components::{labels, EventListener};
+use crate::app::state::{BrowserAction, Undoable};
+use crate::app::{ActionDispatcher, AppAction, AppEvent, AppModel};
+
+// Songs added at the bottom of a playlist, which is then shown again from the top.
+// Also how links dropped on a playlist get added: a partial write says so, what made it is shown anyway
+pub async fn add_to_playlist(
+    api: Arc<dyn SpotifyApiClient + Send + Sync>,
+    playlist_id: String,
+    uris: Vec<String>,
+) -> SpotifyResult<Vec<AppAction>> {
+    let total = uris.len();
+    let mut actions = match api.add_to_playlist(&playlist_id, uris).await {
+        Ok(()) => vec![],
+        Err(SpotifyApiError::PartialWrite(done, _)) => vec![AppAction::ShowNotification(
+            labels::partially_added_label(done, total),
+        )],
+        Err(err) => return Err(err),
+    };
+    let playlist = api.get_playlist(&playlist_id).await?;
+    let tracks = api.get_playlist_tracks(&playlist_id, 0, 100).await?;
+    actions.push(BrowserAction::SetPlaylistDetails(Box::new(playlist), Box::new(tracks)).into());
+    Ok(actions)
+}
+
+// Reverts destructive actions by making the inverse API call, then updating the state like a regular save would.
+// Redoing makes the original call again
+pub struct UndoHandler {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+}
+
+impl UndoHandler {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+        }
+    }
+
+    fn undo(&self, undoable: Undoable) {
+        let api = self.app_model.get_spotify();
+        match undoable {
+            // Tracks end up at the bottom of the playlist, the API has no way to insert them back at several positions at once
+            Undoable::TracksRemovedFromPlaylist { playlist_id, uris } => self
+                .dispatcher
+                .write_spotify_and_dispatch_many(move || add_to_playlist(api, playlist_id, uris)),
+            Undoable::AlbumUnsaved(id) => {
+                self.dispatcher
+                    .write_spotify_and_dispatch(move || async move {
+                        let album = api.save_album(&id).await?;
+                        Ok(BrowserAction::SaveAlbum(Box::new(album)).into())
+                    })
+            }
+            Undoable::TracksUnsaved(tracks) => {
+                self.dispatcher
+                    .write_spotify_and_dispatch(move || async move {
+                        let ids = tracks.iter().map(|t| t.id.clone()).collect();
+                        api.save_tracks(ids).await?;
+                        Ok(BrowserAction::SaveTracks(tracks).into())
+                    })
+            }
+        }
+    }
+
+    fn redo(&self, undoable: Undoable) {
+        let api = self.app_model.get_spotify();
+        match undoable.clone() {
+            // What's there now is removed: the snapshot we had predates the undo
+            Undoable::TracksRemovedFromPlaylist { playlist_id, uris } => self
+                .dispatcher
+                .write_spotify_and_dispatch_many(move || async move {
+                    let snapshot_id = api
+                        .remove_from_playlist(&playlist_id, uris.clone(), None)
+                        .await?;
+                    let removed =
+                        BrowserAction::RemoveTracksFromPlaylist(playlist_id.clone(), uris);
+                    Ok(vec![
+                        AppAction::Redone(undoable, Box::new(removed.into())),
+                        BrowserAction::SetPlaylistSnapshot(playlist_id, snapshot_id).into(),
+                    ])
+                }),
+            Undoable::AlbumUnsaved(id) => {
+                self.dispatcher
+                    .write_spotify_and_dispatch(move || async move {
+                        api.remove_saved_album(&id).await?;
+                        let unsaved = BrowserAction::UnsaveAlbum(id);
+                        Ok(AppAction::Redone(undoable, Box::new(unsaved.into())))
+                    })
+            }
+            Undoable::TracksUnsaved(tracks) => {
+                self.dispatcher
+                    .write_spotify_and_dispatch(move || async move {
+                        let ids: Vec<String> = tracks.iter().map(|t| t.id.clone()).collect();
+                        api.remove_saved_tracks(ids.clone()).await?;
+                        let unsaved = BrowserAction::RemoveSavedTracks(ids);
+                        Ok(AppAction::Redone(undoable, Box::new(unsaved.into())))
+                    })
+            }
+        }
+    }
+}
+
+impl EventListener for UndoHandler {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::UndoRequested(undoable) => self.undo(undoable.clone()),
+            AppEvent::RedoRequested(undoable) => self.redo(undoable.clone()),
+            _ => {}
+        }
+    }
+}
@@ -3,8 +3,10 @@ use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
 use std::rc::Rc;
 
-use crate::app::components::utils::wrap_flowbox_item;
+use crate::app::components::share::ShareKind;
+use crate::app::components::utils::{connect_prefetch, wrap_flowbox_item};
 use crate::app::components::{display_add_css_provider, AlbumWidget, Component, EventListener};
+use crate::app::state::ScreenName;
 use crate::app::{models::*, ListStore};
 use crate::app::{AppEvent, BrowserEvent, Worker};
 
@@ -66,13 +68,7 @@ impl UserDetailsWidget {
     where
         F: Fn() + 'static,
     {
-        self.imp()
-            .scrolled_window
-            .connect_edge_reached(move |_, pos| {
-                if let gtk::PositionType::Bottom = pos {
-                    f()
-                }
-            });
+        connect_prefetch(&self.imp().scrolled_window, f);
     }
 
     fn bind_user_playlists<F>(&self, worker: Worker, store: &ListStore<AlbumModel>, on_pressed: F)
@@ -85,6 +81,7 @@ impl UserDetailsWidget {
                 wrap_flowbox_item(item, |item: &AlbumModel| {
                     let f = on_pressed.clone();
                     let album = AlbumWidget::for_model(item, worker.clone());
+                    album.set_share_menu(ShareKind::Playlist, &item.uri());
                     album.connect_album_pressed(clone!(@weak item => move |_| {
                         f(item.uri());
                     }));
@@ -144,6 +141,11 @@ impl EventListener for UserDetails {
             {
                 self.update_details();
             }
+            AppEvent::BrowserEvent(BrowserEvent::ReloadRequested(ScreenName::User(id)))
+                if id == &self.model.id =>
+            {
+                self.model.load_user_details(id.clone());
+            }
             _ => {}
         }
     }
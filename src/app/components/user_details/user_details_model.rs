@@ -50,7 +50,7 @@ impl UserDetailsModel {
 
         let id = next_page.data.clone();
         let batch_size = next_page.batch_size;
-        let offset = next_page.next_offset?;
+        let offset = next_page.next_offset()?;
         self.dispatcher
             .call_spotify_and_dispatch(move || async move {
                 api.get_user_playlists(&id, offset, batch_size)
@@ -1,17 +1,51 @@
 use gettextrs::*;
 use gio::{prelude::ActionMapExt, SimpleAction, SimpleActionGroup};
 use gtk::prelude::*;
+use libadwaita::prelude::*;
 use std::rc::Rc;
 
 use super::UserMenuModel;
 use crate::app::components::{EventListener, Settings};
-use crate::app::{state::LoginEvent, AppEvent};
+use crate::app::state::{LoginEvent, PlaybackEvent};
+use crate::app::AppEvent;
 
 pub struct UserMenu {
     user_button: gtk::MenuButton,
+    action_group: SimpleActionGroup,
+    settings: Rc<Settings>,
     model: Rc<UserMenuModel>,
 }
 
+fn ask_for_pin(user_button: &gtk::MenuButton, model: Rc<UserMenuModel>) {
+    let window = user_button
+        .root()
+        .and_then(|r| r.downcast::<gtk::Window>().ok());
+    let dialog = libadwaita::MessageDialog::new(
+        window.as_ref(),
+        // translators: Title of the dialog asking for the PIN to unlock Spot
+        Some(&gettext("Unlock Spot")),
+        None,
+    );
+    let entry = gtk::PasswordEntry::builder()
+        .activates_default(true)
+        .build();
+    dialog.set_extra_child(Some(&entry));
+    // translators: Button of the dialog asking for the PIN to unlock Spot
+    dialog.add_response("cancel", &gettext("Cancel"));
+    // translators: Button of the dialog asking for the PIN to unlock Spot
+    dialog.add_response("unlock", &gettext("Unlock"));
+    dialog.set_response_appearance("unlock", libadwaita::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("unlock"));
+    dialog.set_close_response("cancel");
+    dialog.connect_response(
+        Some("unlock"),
+        clone!(@weak entry => move |_, _| {
+            model.unlock(&entry.text());
+        }),
+    );
+    dialog.present();
+}
+
 impl UserMenu {
     pub fn new(
         user_button: gtk::MenuButton,
@@ -20,6 +54,7 @@ impl UserMenu {
         model: UserMenuModel,
     ) -> Self {
         let model = Rc::new(model);
+        let settings = Rc::new(settings);
 
         about.connect_close_request(
             clone!(@weak about => @default-return gtk::Inhibit(false), move |_| {
@@ -40,12 +75,28 @@ impl UserMenu {
 
         action_group.add_action(&{
             let settings_action = SimpleAction::new("settings", None);
-            settings_action.connect_activate(clone!(@weak model => move |_, _| {
+            settings_action.connect_activate(clone!(@weak settings => move |_, _| {
                 settings.show_self();
             }));
             settings_action
         });
 
+        action_group.add_action(&{
+            let lock = SimpleAction::new("lock", None);
+            lock.connect_activate(clone!(@weak model => move |_, _| {
+                model.lock();
+            }));
+            lock
+        });
+
+        action_group.add_action(&{
+            let unlock = SimpleAction::new("unlock", None);
+            unlock.connect_activate(clone!(@weak model, @weak user_button => move |_, _| {
+                ask_for_pin(&user_button, model);
+            }));
+            unlock
+        });
+
         action_group.add_action(&{
             let about_action = SimpleAction::new("about", None);
             about_action.connect_activate(clone!(@weak about => move |_, _| {
@@ -56,13 +107,40 @@ impl UserMenu {
 
         user_button.insert_action_group("menu", Some(&action_group));
 
-        Self { user_button, model }
+        Self {
+            user_button,
+            action_group,
+            settings,
+            model,
+        }
+    }
+
+    // No way around the lock through the preferences, or by logging in as someone else
+    fn update_lock(&self) {
+        let locked = self.model.is_locked();
+        for name in ["settings", "logout"] {
+            if let Some(action) = self
+                .action_group
+                .lookup_action(name)
+                .and_then(|a| a.downcast::<SimpleAction>().ok())
+            {
+                action.set_enabled(!locked);
+            }
+        }
+        self.update_menu();
     }
 
     fn update_menu(&self) {
         let menu = gio::Menu::new();
         // translators: This is a menu entry.
         menu.append(Some(&gettext("Preferences")), Some("menu.settings"));
+        if self.model.is_locked() {
+            // translators: This is a menu entry, it asks for the PIN that was chosen in the preferences.
+            menu.append(Some(&gettext("Unlock…")), Some("menu.unlock"));
+        } else {
+            // translators: This is a menu entry, see the Lock section of the preferences.
+            menu.append(Some(&gettext("Lock")), Some("menu.lock"));
+        }
         // translators: This is a menu entry.
         menu.append(Some(&gettext("About")), Some("menu.about"));
         // translators: This is a menu entry.
@@ -86,6 +164,10 @@ impl EventListener for UserMenu {
                 self.update_menu();
                 self.model.fetch_user_playlists();
             }
+            AppEvent::LockChanged(_) => self.update_lock(),
+            AppEvent::PlaybackEvent(PlaybackEvent::BlocklistChanged) => {
+                self.settings.update_blocked()
+            }
             _ => {}
         }
     }
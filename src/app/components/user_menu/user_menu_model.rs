@@ -1,7 +1,9 @@
 use crate::api::clear_user_cache;
 use crate::app::credentials::Credentials;
 use crate::app::state::{LoginAction, PlaybackAction};
-use crate::app::{ActionDispatcher, AppModel};
+use crate::app::{ActionDispatcher, AppAction, AppModel};
+use crate::settings;
+use gettextrs::gettext;
 use std::ops::Deref;
 use std::rc::Rc;
 
@@ -23,6 +25,33 @@ impl UserMenuModel {
             .map_state_opt(|s| s.logged_user.user.as_ref())
     }
 
+    pub fn is_locked(&self) -> bool {
+        self.app_model.get_state().is_locked()
+    }
+
+    // The rest follows from the setting changing, see LockGuard
+    pub fn lock(&self) {
+        if settings::has_lock_pin() {
+            settings::set_locked(true);
+        } else {
+            self.dispatcher.dispatch(AppAction::ShowNotification(
+                // translators: Shown when trying to lock Spot before a PIN was chosen in the preferences
+                gettext("Choose a PIN in the preferences first"),
+            ));
+        }
+    }
+
+    pub fn unlock(&self, pin: &str) {
+        if settings::check_lock_pin(pin) {
+            settings::set_locked(false);
+        } else {
+            self.dispatcher.dispatch(AppAction::ShowNotification(
+                // translators: Shown when the PIN typed to unlock Spot isn't the right one
+                gettext("Wrong PIN"),
+            ));
+        }
+    }
+
     pub fn logout(&self) {
         self.dispatcher.dispatch(PlaybackAction::Stop.into());
         self.dispatcher.dispatch_async(Box::pin(async {
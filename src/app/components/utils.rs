@@ -1,8 +1,10 @@
 use gtk::prelude::*;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::time::Duration;
 
+use crate::api::{SpotifyApiError, SpotifyResult};
+
 #[derive(Clone)]
 pub struct Clock {
     interval_ms: u32,
@@ -74,6 +76,12 @@ impl Debouncer {
             previous_source.remove();
         }
     }
+
+    pub fn cancel(&self) {
+        if let Some(source) = self.0.take() {
+            source.remove();
+        }
+    }
 }
 
 pub struct Animator<EasingFn> {
@@ -124,6 +132,84 @@ where
     }
 }
 
+// How far down (relative to the loaded content) we start fetching the next batch
+const PREFETCH_THRESHOLD: f64 = 0.75;
+
+// Calls f once the viewport crosses the threshold, so that the next batch is (hopefully) there
+// before the user reaches the bottom. It only fires once for a given content height, as the
+// height changes when the batch gets appended. Reaching the bottom still triggers f, in case
+// the earlier fetch failed or didn't complete yet.
+pub fn connect_prefetch<F>(scrolled_window: &gtk::ScrolledWindow, f: F)
+where
+    F: Fn() + 'static,
+{
+    let f = Rc::new(f);
+    let last_upper = Rc::new(Cell::new(0f64));
+
+    let f_clone = Rc::clone(&f);
+    scrolled_window
+        .vadjustment()
+        .connect_value_changed(move |adj| {
+            let upper = adj.upper();
+            let crossed = adj.value() + adj.page_size() >= PREFETCH_THRESHOLD * upper;
+            if crossed && (upper - last_upper.get()).abs() > f64::EPSILON {
+                last_upper.set(upper);
+                f_clone();
+            }
+        });
+
+    scrolled_window.connect_edge_reached(move |_, pos| {
+        if let gtk::PositionType::Bottom = pos {
+            f()
+        }
+    });
+}
+
+// Scrolls to offset as soon as enough content is loaded to get there,
+// unless the user scrolled somewhere else in the meantime
+pub fn restore_scroll(adjustment: &gtk::Adjustment, offset: f64) {
+    if adjustment.upper() - adjustment.page_size() >= offset {
+        adjustment.set_value(offset);
+        return;
+    }
+    let handlers: Rc<RefCell<Vec<glib::SignalHandlerId>>> = Default::default();
+    let stop = clone!(@strong handlers => move |adj: &gtk::Adjustment| {
+        for id in handlers.take() {
+            adj.disconnect(id);
+        }
+    });
+    let changed = adjustment.connect_changed(clone!(@strong stop => move |adj| {
+        if adj.upper() - adj.page_size() >= offset {
+            adj.set_value(offset);
+            stop(adj);
+        }
+    }));
+    let scrolled = adjustment.connect_value_changed(move |adj| {
+        if (adj.value() - offset).abs() >= 1.0 {
+            stop(adj);
+        }
+    });
+    handlers.borrow_mut().extend([changed, scrolled]);
+}
+
+// Depth first, the widget itself included
+pub fn find_descendant<Descendant>(widget: &gtk::Widget) -> Option<Descendant>
+where
+    Descendant: IsA<gtk::Widget>,
+{
+    if let Ok(found) = widget.clone().downcast::<Descendant>() {
+        return Some(found);
+    }
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        if let Some(found) = find_descendant(&current) {
+            return Some(found);
+        }
+        child = current.next_sibling();
+    }
+    None
+}
+
 pub fn ancestor<Current, Ancestor>(widget: &Current) -> Option<Ancestor>
 where
     Current: IsA<gtk::Widget>,
@@ -152,14 +238,67 @@ pub fn wrap_flowbox_item<
     child.upcast::<gtk::Widget>()
 }
 
-pub fn format_duration(duration: f64) -> String {
-    let seconds = (duration / 1000.0) as i32;
-    let hours = seconds.div_euclid(3600);
-    let minutes = seconds.div_euclid(60).rem_euclid(60);
-    let seconds = seconds.rem_euclid(60);
-    if hours > 0 {
-        format!("{hours}∶{minutes:02}∶{seconds:02}")
-    } else {
-        format!("{minutes}∶{seconds:02}")
+// Goes through the widgets of a flowbox that was bound with wrap_flowbox_item
+pub fn for_each_flowbox_item<Widget: glib::IsA<gtk::Widget>, F: Fn(&Widget)>(
+    flowbox: &gtk::FlowBox,
+    f: F,
+) {
+    let mut child = flowbox.first_child();
+    while let Some(current) = child {
+        if let Some(widget) = current
+            .downcast_ref::<gtk::FlowBoxChild>()
+            .and_then(|c| c.child())
+            .and_then(|c| c.downcast::<Widget>().ok())
+        {
+            f(&widget);
+        }
+        child = current.next_sibling();
+    }
+}
+
+// A menu popping up on right click (or long press, for touchscreens), at the pointer.
+// Nothing shows up until the popover is given a menu model.
+pub fn attach_context_menu(widget: &impl IsA<gtk::Widget>) -> gtk::PopoverMenu {
+    let popover = gtk::PopoverMenu::builder()
+        .has_arrow(false)
+        .halign(gtk::Align::Start)
+        .build();
+    popover.set_parent(widget);
+    widget.connect_destroy(clone!(@weak popover => move |_| popover.unparent()));
+
+    let popup_at = clone!(@weak popover => move |x: f64, y: f64| {
+        if popover.menu_model().is_some() {
+            popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+            popover.popup();
+        }
+    });
+
+    let click = gtk::GestureClick::new();
+    click.set_button(gdk::BUTTON_SECONDARY);
+    let f = popup_at.clone();
+    click.connect_pressed(move |gesture, _, x, y| {
+        gesture.set_state(gtk::EventSequenceState::Claimed);
+        f(x, y);
+    });
+    widget.add_controller(click);
+
+    let long_press = gtk::GestureLongPress::new();
+    long_press.set_touch_only(true);
+    long_press.connect_pressed(move |_, x, y| popup_at(x, y));
+    widget.add_controller(long_press);
+
+    popover
+}
+
+// For the parts of the API that aren't open to every client or in every market (personalized views, audiobooks...)
+// and for our own guesswork: when they fail for anything else than a token problem, we'd rather show less than pop an error up
+pub fn or_unavailable<T>(result: SpotifyResult<T>, fallback: T) -> SpotifyResult<T> {
+    match result {
+        Err(err @ (SpotifyApiError::InvalidToken | SpotifyApiError::NoToken)) => Err(err),
+        Err(err) => {
+            warn!("Ignoring Spotify API error: {}", err);
+            Ok(fallback)
+        }
+        ok => ok,
     }
 }
@@ -0,0 +1,160 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::app::components::EventListener;
+use crate::app::kv_store::KeyValueStore;
+use crate::app::state::{Device, LoginEvent, PlaybackAction, PlaybackEvent};
+use crate::app::{ActionDispatcher, AppEvent, AppModel};
+
+// Polls that were already on their way still report the device we just left
+const SYNC_GRACE: Duration = Duration::from_secs(10);
+
+fn key(device: &Device) -> String {
+    match device {
+        Device::Local => "local".to_string(),
+        Device::Connect(device) => format!("connect:{}", device.id),
+    }
+}
+
+// From 0 to 1, like the volume slider
+struct Volumes {
+    store: KeyValueStore<f64>,
+    ignore_syncs_until: Option<Instant>,
+}
+
+impl Volumes {
+    fn new(store: KeyValueStore<f64>) -> Self {
+        Self {
+            store,
+            ignore_syncs_until: None,
+        }
+    }
+
+    fn volume_for(&self, device: &Device) -> Option<f64> {
+        self.store.get(&key(device)).copied()
+    }
+
+    // What to bring back, if we've played there before
+    fn switched_to(&mut self, device: &Device, now: Instant) -> Option<f64> {
+        self.ignore_syncs_until = Some(now + SYNC_GRACE);
+        self.volume_for(device)
+    }
+
+    fn synced(&mut self, device: &Device, volume: f64, now: Instant) {
+        if self.ignore_syncs_until.is_some_and(|until| now < until) {
+            return;
+        }
+        self.remember(device, volume);
+    }
+
+    fn remember(&mut self, device: &Device, volume: f64) {
+        let key = key(device);
+        if self.store.get(&key) == Some(&volume) {
+            return;
+        }
+        self.store.set(key, volume);
+        if let Err(e) = self.store.save() {
+            warn!("Could not save the volume: {}", e);
+        }
+    }
+}
+
+// Keeps the last volume of each output (this computer, and each Connect device),
+// and brings it back when playback moves there: what was fine on headphones is rarely so on the living room speakers.
+pub struct VolumeMemory {
+    app_model: Rc<AppModel>,
+    dispatcher: Box<dyn ActionDispatcher>,
+    volumes: Volumes,
+}
+
+impl VolumeMemory {
+    pub fn new(app_model: Rc<AppModel>, dispatcher: Box<dyn ActionDispatcher>) -> Self {
+        Self {
+            app_model,
+            dispatcher,
+            volumes: Volumes::new(KeyValueStore::open("device-volumes")),
+        }
+    }
+
+    fn current_device(&self) -> Device {
+        self.app_model.get_state().playback.current_device().clone()
+    }
+
+    // Devices we've never played on keep whatever volume they're at
+    fn restore(&self, volume: Option<f64>) {
+        if let Some(volume) = volume {
+            self.dispatcher
+                .dispatch(PlaybackAction::SetVolume(volume).into());
+        }
+    }
+}
+
+impl EventListener for VolumeMemory {
+    fn on_event(&mut self, event: &AppEvent) {
+        match event {
+            // Set by the user
+            AppEvent::PlaybackEvent(PlaybackEvent::VolumeSet(volume)) => {
+                self.volumes.remember(&self.current_device(), *volume)
+            }
+            // Reported by the device itself (its own buttons, another app...)
+            AppEvent::PlaybackEvent(PlaybackEvent::VolumeSynced(volume)) => {
+                self.volumes
+                    .synced(&self.current_device(), *volume, Instant::now())
+            }
+            AppEvent::PlaybackEvent(
+                PlaybackEvent::SwitchedDevice(device) | PlaybackEvent::PlaybackTransferred(device),
+            ) => {
+                let volume = self.volumes.switched_to(device, Instant::now());
+                self.restore(volume);
+            }
+            // The local player only exists from then on
+            AppEvent::LoginEvent(LoginEvent::LoginCompleted(_)) => {
+                self.restore(self.volumes.volume_for(&self.current_device()))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::models::{ConnectDevice, ConnectDeviceKind};
+    use crate::app::test_utils::TempDir;
+
+    fn kitchen() -> Device {
+        Device::Connect(ConnectDevice {
+            id: "id".to_string(),
+            label: "Kitchen".to_string(),
+            kind: ConnectDeviceKind::Speaker,
+            is_active: false,
+        })
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(key(&kitchen()), "connect:id");
+        assert_eq!(key(&Device::Local), "local");
+    }
+
+    #[test]
+    fn test_switch_and_restore() {
+        let dir = TempDir::create();
+        let mut volumes = Volumes::new(KeyValueStore::open_at(dir.join("volumes.json")));
+        let now = Instant::now();
+        volumes.remember(&Device::Local, 0.3);
+        volumes.remember(&kitchen(), 0.8);
+
+        assert_eq!(volumes.switched_to(&kitchen(), now), Some(0.8));
+        // A late poll still reporting the local volume
+        volumes.synced(&kitchen(), 0.3, now + Duration::from_secs(2));
+        assert_eq!(volumes.switched_to(&Device::Local, now), Some(0.3));
+        volumes.synced(&Device::Local, 0.8, now + Duration::from_secs(2));
+        assert_eq!(volumes.switched_to(&kitchen(), now), Some(0.8));
+
+        // Once settled, what the device reports is kept
+        volumes.synced(&kitchen(), 0.5, now + SYNC_GRACE);
+        assert_eq!(volumes.volume_for(&kitchen()), Some(0.5));
+    }
+}
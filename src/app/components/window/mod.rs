@@ -1,10 +1,14 @@
+use gettextrs::gettext;
 use gtk::prelude::*;
 use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::app::components::EventListener;
+use crate::app::state::PlaybackEvent;
 use crate::app::{AppEvent, AppModel};
-use crate::settings::WindowGeometry;
+use crate::settings::{self, WindowGeometry};
+
+const BACKGROUND_NOTIFICATION: &str = "background-playback";
 
 thread_local! {
     static WINDOW_GEOMETRY: RefCell<WindowGeometry> = const { RefCell::new(WindowGeometry {
@@ -14,20 +18,23 @@ thread_local! {
 
 pub struct MainWindow {
     initial_window_geometry: WindowGeometry,
+    start_minimized: bool,
     window: libadwaita::ApplicationWindow,
 }
 
 impl MainWindow {
     pub fn new(
         initial_window_geometry: WindowGeometry,
+        start_minimized: bool,
         app_model: Rc<AppModel>,
         window: libadwaita::ApplicationWindow,
     ) -> Self {
         window.connect_close_request(
             clone!(@weak app_model => @default-return gtk::Inhibit(false), move |window| {
                 let state = app_model.get_state();
-                if state.playback.is_playing() {
+                if state.playback.is_playing() && settings::background_playback_enabled() {
                     window.set_visible(false);
+                    Self::notify_background_playback(window);
                     gtk::Inhibit(true)
                 } else {
                     gtk::Inhibit(false)
@@ -35,21 +42,42 @@ impl MainWindow {
             }),
         );
 
+        window.connect_show(|window| {
+            if let Some(app) = window.application() {
+                app.withdraw_notification(BACKGROUND_NOTIFICATION);
+            }
+        });
+
         window.connect_default_height_notify(Self::save_window_geometry);
         window.connect_default_width_notify(Self::save_window_geometry);
         window.connect_maximized_notify(Self::save_window_geometry);
 
-        window.connect_unrealize(|_| {
+        window.connect_unrealize(clone!(@weak app_model => move |_| {
             debug!("saving geometry");
             WINDOW_GEOMETRY.with(|g| g.borrow().save());
-        });
+            app_model.save_last_session();
+        }));
 
         Self {
             initial_window_geometry,
+            start_minimized,
             window,
         }
     }
 
+    // There's no tray in GTK 4, so that's how we tell the user where the music comes from.
+    // Clicking it activates the app, which brings the window back
+    fn notify_background_playback(window: &libadwaita::ApplicationWindow) {
+        let Some(app) = window.application() else {
+            return;
+        };
+        // translators: Title of the notification shown when the window is closed while music is playing
+        let notification = gio::Notification::new(&gettext("Spot is still playing"));
+        // translators: Body of the notification shown when the window is closed while music is playing
+        notification.set_body(Some(&gettext("Click to bring the window back")));
+        app.send_notification(Some(BACKGROUND_NOTIFICATION), &notification);
+    }
+
     fn start(&self) {
         self.window.set_default_size(
             self.initial_window_geometry.width,
@@ -58,6 +86,10 @@ impl MainWindow {
         if self.initial_window_geometry.is_maximized {
             self.window.maximize();
         }
+        // Done before presenting so that it never shows up, if the compositor goes along with it
+        if self.start_minimized {
+            self.window.minimize();
+        }
         self.window.present();
     }
 
@@ -84,6 +116,12 @@ impl EventListener for MainWindow {
         match event {
             AppEvent::Started => self.start(),
             AppEvent::Raised => self.raise(),
+            // Nothing left to keep running for
+            AppEvent::PlaybackEvent(PlaybackEvent::PlaybackStopped)
+                if !self.window.is_visible() =>
+            {
+                self.window.close()
+            }
             _ => {}
         }
     }
@@ -1,7 +1,7 @@
 use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::future::BoxFuture;
 use futures::future::Future;
-use futures::stream::StreamExt;
+use futures::stream::{BoxStream, StreamExt};
 use std::pin::Pin;
 
 use super::AppAction;
@@ -13,6 +13,8 @@ pub trait ActionDispatcher {
     fn dispatch_many(&self, actions: Vec<AppAction>);
     fn dispatch_async(&self, action: BoxFuture<'static, Option<AppAction>>);
     fn dispatch_many_async(&self, actions: BoxFuture<'static, Vec<AppAction>>);
+    // Each action as soon as it comes
+    fn dispatch_stream(&self, actions: BoxStream<'static, AppAction>);
     // Can't have impl Clone easily so there you go
     fn box_clone(&self) -> Box<dyn ActionDispatcher>;
 }
@@ -58,6 +60,14 @@ impl ActionDispatcher for ActionDispatcherImpl {
         });
     }
 
+    fn dispatch_stream(&self, actions: BoxStream<'static, AppAction>) {
+        let clone = self.sender.clone();
+        self.worker.send_task(actions.for_each(move |action| {
+            clone.unbounded_send(action).unwrap();
+            async {}
+        }));
+    }
+
     fn box_clone(&self) -> Box<dyn ActionDispatcher> {
         Box::new(self.clone())
     }
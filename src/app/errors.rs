@@ -0,0 +1,138 @@
+use futures::future::BoxFuture;
+use std::sync::{Arc, Mutex};
+
+use crate::api::SpotifyApiError;
+use crate::app::AppAction;
+
+// What went wrong, as far as the user is concerned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    // Logged out
+    Auth,
+    // Logged in, but not allowed to do that
+    Forbidden,
+    Network,
+    RateLimited,
+    // Refused on purpose, see app::lock
+    Locked,
+    Other,
+}
+
+impl From<&SpotifyApiError> for ErrorKind {
+    fn from(err: &SpotifyApiError) -> Self {
+        match err {
+            SpotifyApiError::InvalidToken
+            | SpotifyApiError::NoToken
+            | SpotifyApiError::BadStatus(401, _) => Self::Auth,
+            SpotifyApiError::BadStatus(403, _) => Self::Forbidden,
+            SpotifyApiError::TooManyRequests | SpotifyApiError::BadStatus(429, _) => {
+                Self::RateLimited
+            }
+            SpotifyApiError::ClientError(e) if e.is_network() || e.is_timeout() => Self::Network,
+            SpotifyApiError::WebsocketError(_) => Self::Network,
+            SpotifyApiError::PartialWrite(_, err) | SpotifyApiError::Shared(err) => {
+                Self::from(err.as_ref())
+            }
+            SpotifyApiError::Locked => Self::Locked,
+            _ => Self::Other,
+        }
+    }
+}
+
+// Makes the failed call again, giving the actions to dispatch once it's done
+// (the call itself only has to be Send, hence the mutex)
+#[derive(Clone)]
+pub struct Retry(Arc<Mutex<dyn Fn() -> BoxFuture<'static, Vec<AppAction>> + Send>>);
+
+impl Retry {
+    pub fn new<F>(retry: F) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, Vec<AppAction>> + Send + 'static,
+    {
+        Self(Arc::new(Mutex::new(retry)))
+    }
+
+    pub fn run(&self) -> BoxFuture<'static, Vec<AppAction>> {
+        let retry = self.0.lock().unwrap();
+        retry()
+    }
+}
+
+impl std::fmt::Debug for Retry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Retry")
+    }
+}
+
+// An error worth telling the user about, rather than just logging it
+#[derive(Clone, Debug)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub retry: Option<Retry>,
+    // Something was to be changed: it might have gone through anyway, so it's never retried on its own
+    pub is_write: bool,
+}
+
+impl AppError {
+    // Trying again won't help if we're not allowed to
+    pub fn new(err: &SpotifyApiError, retry: Retry) -> Self {
+        let kind = ErrorKind::from(err);
+        Self {
+            kind,
+            retry: !matches!(
+                kind,
+                ErrorKind::Auth | ErrorKind::Forbidden | ErrorKind::Locked
+            )
+            .then_some(retry),
+            is_write: false,
+        }
+    }
+
+    pub fn for_write(self) -> Self {
+        Self {
+            is_write: true,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(
+            ErrorKind::from(&SpotifyApiError::BadStatus(401, "".to_string())),
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            ErrorKind::from(&SpotifyApiError::BadStatus(403, "".to_string())),
+            ErrorKind::Forbidden
+        );
+        assert_eq!(
+            ErrorKind::from(&SpotifyApiError::TooManyRequests),
+            ErrorKind::RateLimited
+        );
+        assert_eq!(
+            ErrorKind::from(&SpotifyApiError::BadStatus(500, "".to_string())),
+            ErrorKind::Other
+        );
+
+        let retry = Retry::new(|| Box::pin(async { vec![] }));
+        let error = AppError::new(&SpotifyApiError::InvalidToken, retry.clone());
+        assert!(error.retry.is_none());
+        let error = AppError::new(
+            &SpotifyApiError::BadStatus(403, "".to_string()),
+            retry.clone(),
+        );
+        assert!(error.retry.is_none());
+        let error = AppError::new(&SpotifyApiError::Locked, retry.clone());
+        assert!(error.retry.is_none());
+        let error = AppError::new(&SpotifyApiError::TooManyRequests, retry);
+        assert!(error.retry.is_some());
+        assert!(!error.is_write);
+        assert!(error.for_write().is_write);
+    }
+}
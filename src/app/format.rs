@@ -0,0 +1,238 @@
+use gettextrs::*;
+use std::str::FromStr;
+
+// Spotify release dates come as YYYY, YYYY-MM or YYYY-MM-DD depending on how precise the label was
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReleaseDate {
+    Year(i32),
+    Month(i32, i32),
+    Day(i32, i32, i32),
+}
+
+impl ReleaseDate {
+    pub fn parse(date: &str) -> Option<Self> {
+        let mut parts = date.splitn(3, '-').map(i32::from_str);
+        let year = parts.next()?.ok()?;
+        let month = parts.next().transpose().ok()?;
+        let day = parts.next().transpose().ok()?;
+        match (month, day) {
+            (None, _) => Some(Self::Year(year)),
+            (Some(month), None) => Some(Self::Month(year, month)),
+            (Some(month), Some(day)) => Some(Self::Day(year, month, day)),
+        }
+    }
+
+    pub fn year(self) -> i32 {
+        match self {
+            Self::Year(year) | Self::Month(year, _) | Self::Day(year, _, _) => year,
+        }
+    }
+
+    fn to_datetime(self) -> Option<glib::DateTime> {
+        let (year, month, day) = match self {
+            Self::Year(_) => return None,
+            Self::Month(year, month) => (year, month, 1),
+            Self::Day(year, month, day) => (year, month, day),
+        };
+        glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok()
+    }
+
+    pub fn format(self) -> String {
+        let format = match self {
+            Self::Year(year) => return year.to_string(),
+            // translators: This is how a release date is displayed when only the month is known; the format is that of glib's g_date_time_format, see https://docs.gtk.org/glib/method.DateTime.format.html
+            Self::Month(_, _) => gettext("%B %Y"),
+            // translators: This is how a full release date is displayed; the format is that of glib's g_date_time_format, see https://docs.gtk.org/glib/method.DateTime.format.html
+            Self::Day(_, _, _) => gettext("%-d %B %Y"),
+        };
+        self.to_datetime()
+            .and_then(|date| date.format(&format).ok())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| self.year().to_string())
+    }
+}
+
+// Falls back to whatever we got if it can't be parsed, it's better than nothing
+pub fn format_release_date(date: &str) -> String {
+    ReleaseDate::parse(date)
+        .map(|date| date.format())
+        .unwrap_or_else(|| date.to_string())
+}
+
+// Clock-like, for song lengths and the playback position: 3∶07, 1∶02∶33
+pub fn format_duration(duration: f64) -> String {
+    let seconds = (duration / 1000.0) as i32;
+    let hours = seconds.div_euclid(3600);
+    let minutes = seconds.div_euclid(60).rem_euclid(60);
+    let seconds = seconds.rem_euclid(60);
+    if hours > 0 {
+        format!("{hours}∶{minutes:02}∶{seconds:02}")
+    } else {
+        format!("{minutes}∶{seconds:02}")
+    }
+}
+
+// For longer things (whole albums or playlists): 1 hr 12 min
+pub fn humanize_duration(duration_ms: u64) -> String {
+    let minutes = duration_ms / 60_000;
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: A duration in hours and minutes, as in "1 hr 12 min". This text should be as short as possible.
+        gettext("{} hr {} min");
+        // translators: A duration in hours, as in "2 hr". This text should be as short as possible.
+        gettext("{} hr");
+        // translators: A duration in minutes, as in "42 min". This text should be as short as possible.
+        gettext("{} min");
+    }
+    match (hours, minutes) {
+        (0, minutes) => gettext!("{} min", minutes.max(1)),
+        (hours, 0) => gettext!("{} hr", hours),
+        (hours, minutes) => gettext!("{} hr {} min", hours, minutes),
+    }
+}
+
+// "12 songs, 48 min" -- the length is left out when we don't know all the songs
+pub fn songs_summary(song_count: usize, total_duration_ms: Option<u64>) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: The number of songs in an album or a playlist.
+        ngettext("{} song", "{} songs", song_count as u32);
+    }
+    let songs = ngettext!("{} song", "{} songs", song_count as u32, song_count);
+    match total_duration_ms {
+        Some(duration) => format!("{}, {}", songs, humanize_duration(duration)),
+        None => songs,
+    }
+}
+
+// Roughly what the songs weigh once downloaded at that bitrate (which is only the nominal one, hence the rounding)
+pub fn estimated_size(duration_ms: u64, bitrate_kbps: u32) -> u64 {
+    duration_ms * u64::from(bitrate_kbps) / 8
+}
+
+// The same, with the size too once the length is known: "12 songs, 48 min, about 57.6 MB"
+pub fn songs_totals(
+    song_count: usize,
+    total_duration_ms: Option<u64>,
+    bitrate_kbps: u32,
+) -> String {
+    let summary = songs_summary(song_count, total_duration_ms);
+    match total_duration_ms {
+        Some(duration) => {
+            let size = glib::format_size(estimated_size(duration, bitrate_kbps));
+            format!("{}, {}", summary, estimated_size_label(&size))
+        }
+        None => summary,
+    }
+}
+
+// The second line of detailed song rows: "Abbey Road · Added 12 March 2023 · Popularity: 54%"
+pub fn song_details(album: &str, added_at: Option<&str>, popularity: Option<u32>) -> String {
+    let added_on = added_at
+        .and_then(|date| date.split('T').next())
+        .filter(|date| !date.is_empty())
+        .map(|date| added_on_label(&format_release_date(date)));
+    let popularity = popularity.map(popularity_label);
+    Some(album.to_string())
+        .filter(|album| !album.is_empty())
+        .into_iter()
+        .chain(added_on)
+        .chain(popularity)
+        .collect::<Vec<String>>()
+        .join(" · ")
+}
+
+fn added_on_label(date: &str) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Part of the second line of detailed song rows, the full text is "Added <date>", when the song was added to the playlist or the library.
+        gettext("Added {}");
+    }
+    gettext!("Added {}", date)
+}
+
+fn popularity_label(popularity: u32) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: Part of the second line of detailed song rows, how popular the song is on Spotify, from 0 to 100. The full text is "Popularity: <popularity>%".
+        gettext("Popularity: {}%");
+    }
+    gettext!("Popularity: {}%", popularity)
+}
+
+fn estimated_size_label(size: &str) -> String {
+    // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+    if cfg!(debug_assertions) {
+        // translators: How much space some songs would take once downloaded, at the bitrate picked in the preferences. The full text is "about <size>", as in "about 57.6 MB".
+        gettext("about {}");
+    }
+    gettext!("about {}", size)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_release_date() {
+        assert_eq!(ReleaseDate::parse("1981"), Some(ReleaseDate::Year(1981)));
+        assert_eq!(
+            ReleaseDate::parse("1981-12"),
+            Some(ReleaseDate::Month(1981, 12))
+        );
+        assert_eq!(
+            ReleaseDate::parse("1981-12-15"),
+            Some(ReleaseDate::Day(1981, 12, 15))
+        );
+        assert_eq!(ReleaseDate::parse("19xx"), None);
+        assert_eq!(ReleaseDate::parse("1981-xx"), None);
+    }
+
+    #[test]
+    fn test_format_release_date_fallbacks() {
+        assert_eq!(format_release_date("1981"), "1981");
+        assert_eq!(format_release_date("unknown"), "unknown");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(187_000.0), "3∶07");
+        assert_eq!(format_duration(3_753_000.0), "1∶02∶33");
+    }
+
+    #[test]
+    fn test_humanize_duration() {
+        assert_eq!(humanize_duration(20_000), "1 min");
+        assert_eq!(humanize_duration(42 * 60_000 + 10_000), "42 min");
+        assert_eq!(humanize_duration(2 * 3_600_000), "2 hr");
+        assert_eq!(humanize_duration(72 * 60_000), "1 hr 12 min");
+    }
+
+    #[test]
+    fn test_song_details() {
+        assert_eq!(song_details("Abbey Road", None, None), "Abbey Road");
+        assert_eq!(
+            song_details("Abbey Road", Some("1969T00:00:00Z"), Some(54)),
+            "Abbey Road · Added 1969 · Popularity: 54%"
+        );
+        assert_eq!(song_details("", None, Some(0)), "Popularity: 0%");
+    }
+
+    #[test]
+    fn test_songs_summary() {
+        assert_eq!(songs_summary(1, None), "1 song");
+        assert_eq!(songs_summary(12, Some(48 * 60_000)), "12 songs, 48 min");
+    }
+
+    #[test]
+    fn test_songs_totals() {
+        assert_eq!(estimated_size(60_000, 160), 1_200_000);
+        assert_eq!(songs_totals(3, None, 160), "3 songs");
+        assert_eq!(
+            songs_totals(12, Some(48 * 60_000), 160),
+            "12 songs, 48 min, about 57.6 MB"
+        );
+    }
+}
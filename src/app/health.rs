@@ -0,0 +1,55 @@
+// The two connections we have to Spotify, which can fail independently:
+// the Web API (browsing, the library, Connect devices) and the librespot session (playing on this computer)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    WebApi,
+    Player,
+}
+
+// As far as we can tell from the last time each was used
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Health {
+    web_api_down: bool,
+    player_down: bool,
+}
+
+impl Health {
+    pub fn is_up(&self, backend: Backend) -> bool {
+        match backend {
+            Backend::WebApi => !self.web_api_down,
+            Backend::Player => !self.player_down,
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.web_api_down || self.player_down
+    }
+
+    // Whether that changed anything
+    pub fn set(&mut self, backend: Backend, up: bool) -> bool {
+        let down = match backend {
+            Backend::WebApi => &mut self.web_api_down,
+            Backend::Player => &mut self.player_down,
+        };
+        std::mem::replace(down, !up) == up
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_set() {
+        let mut health = Health::default();
+        assert!(!health.is_degraded());
+        assert!(health.set(Backend::Player, false));
+        assert!(!health.set(Backend::Player, false));
+        assert!(health.is_up(Backend::WebApi));
+        assert!(!health.is_up(Backend::Player));
+        assert!(health.is_degraded());
+        assert!(health.set(Backend::Player, true));
+        assert!(!health.is_degraded());
+    }
+}
@@ -0,0 +1,87 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// A small map saved as JSON in ~/.local/share/spot, for per-item data that doesn't belong in gsettings.
+// It's all kept in memory and the whole file is rewritten on save, so it's not meant for much more than a few thousand entries.
+pub struct KeyValueStore<V> {
+    path: PathBuf,
+    values: HashMap<String, V>,
+}
+
+impl<V> KeyValueStore<V>
+where
+    V: Serialize + DeserializeOwned,
+{
+    pub fn open(name: &str) -> Self {
+        Self::open_at(
+            glib::user_data_dir()
+                .join("spot")
+                .join(format!("{name}.json")),
+        )
+    }
+
+    pub(crate) fn open_at(path: PathBuf) -> Self {
+        let values = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| warn!("Ignoring invalid {}: {}", path.display(), e))
+                .unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+        Self { path, values }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.values.get(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.values.keys()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.values.values()
+    }
+
+    pub fn set(&mut self, key: String, value: V) {
+        self.values.insert(key, value);
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        self.values.remove(key)
+    }
+
+    // Written next to the actual file first, so that a crash never leaves half a file behind
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_vec(&self.values)?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::test_utils::TempDir;
+
+    #[test]
+    fn test_save_and_reopen() {
+        let dir = TempDir::create();
+        let path = dir.join("store.json");
+        let mut store = KeyValueStore::<u32>::open_at(path.clone());
+        store.set("a".to_string(), 1);
+        store.set("b".to_string(), 2);
+        store.remove("b");
+        store.save().unwrap();
+
+        let store = KeyValueStore::<u32>::open_at(path.clone());
+        assert_eq!(store.get("a"), Some(&1));
+        assert_eq!(store.get("b"), None);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+// One song played past the point where it counts as listened to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListenRecord {
+    // ISO 8601, UTC
+    pub played_at: String,
+    pub track_id: String,
+    pub title: String,
+    pub artists: String,
+    pub album: String,
+    // What it was played from, as a spotify: URI when there is one
+    pub context: Option<String>,
+}
+
+// Kept in ~/.local/share/spot as JSON lines, so that recording a play only ever appends to the file
+pub struct ListeningHistory {
+    path: PathBuf,
+}
+
+impl Default for ListeningHistory {
+    fn default() -> Self {
+        Self::at(
+            glib::user_data_dir()
+                .join("spot")
+                .join("listening-history.jsonl"),
+        )
+    }
+}
+
+impl ListeningHistory {
+    pub(crate) fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn append(&self, record: &ListenRecord) -> Result<(), std::io::Error> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)
+    }
+
+    // Lines that can't be read (say, after a crash mid-write) are skipped
+    pub fn read_all(&self) -> Result<Vec<ListenRecord>, std::io::Error> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let mut records = vec![];
+        for line in BufReader::new(file).lines() {
+            match serde_json::from_str(&line?) {
+                Ok(record) => records.push(record),
+                Err(e) => warn!("Skipping invalid listening history entry: {}", e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(records: &[ListenRecord]) -> String {
+    let mut csv = String::from("played_at,track_id,title,artists,album,context\n");
+    for record in records {
+        let fields = [
+            record.played_at.as_str(),
+            &record.track_id,
+            &record.title,
+            &record.artists,
+            &record.album,
+            record.context.as_deref().unwrap_or(""),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn to_json(records: &[ListenRecord]) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(records)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::test_utils::TempDir;
+
+    fn record(title: &str) -> ListenRecord {
+        ListenRecord {
+            played_at: "2024-01-01T12:00:00Z".to_string(),
+            track_id: "id".to_string(),
+            title: title.to_string(),
+            artists: "Someone".to_string(),
+            album: "Album".to_string(),
+            context: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let dir = TempDir::create();
+        let history = ListeningHistory::at(dir.join("history.jsonl"));
+        history.append(&record("One")).unwrap();
+        history.append(&record("Two")).unwrap();
+
+        let records = history.read_all().unwrap();
+        assert_eq!(records, vec![record("One"), record("Two")]);
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        let csv = to_csv(&[record("Hello, \"world\"")]);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("2024-01-01T12:00:00Z,id,\"Hello, \"\"world\"\"\",Someone,Album,")
+        );
+    }
+}
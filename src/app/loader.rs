@@ -1,11 +1,17 @@
 use crate::api::cache::*;
+use crate::app::components::utils::ancestor;
+use crate::app::Worker;
+use async_std::task;
 use gdk_pixbuf::traits::PixbufLoaderExt;
-use gdk_pixbuf::{Pixbuf, PixbufLoader};
+use gdk_pixbuf::{Colorspace, Pixbuf, PixbufLoader};
+use gtk::prelude::*;
 use isahc::config::Configurable;
-use isahc::{AsyncBody, AsyncReadResponseExt, HttpClient, Response};
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
+use isahc::http::StatusCode;
+use isahc::{AsyncReadResponseExt, HttpClient, Request};
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::io::{Error, ErrorKind, Write};
+use std::path::PathBuf;
 
 // A wrapper to be able to implement the Write trait on a PixbufLoader
 struct LocalPixbufLoader<'a>(&'a PixbufLoader);
@@ -26,6 +32,51 @@ impl<'a> Write for LocalPixbufLoader<'a> {
     }
 }
 
+// Raw pixels, decoded off the main thread (unlike a Pixbuf, these can be sent back to it)
+struct DecodedImage {
+    bytes: glib::Bytes,
+    has_alpha: bool,
+    width: i32,
+    height: i32,
+    rowstride: i32,
+}
+
+fn decode_scaled(buffer: &[u8], width: i32, height: i32) -> Option<Pixbuf> {
+    let pixbuf_loader = PixbufLoader::new();
+    pixbuf_loader.set_size(width, height);
+    let mut loader = LocalPixbufLoader(&pixbuf_loader);
+    loader.write_all(buffer).ok()?;
+    pixbuf_loader.close().ok()?;
+    pixbuf_loader.pixbuf()
+}
+
+impl DecodedImage {
+    fn from_pixbuf(pixbuf: &Pixbuf) -> Self {
+        Self {
+            bytes: pixbuf.read_pixel_bytes(),
+            has_alpha: pixbuf.has_alpha(),
+            width: pixbuf.width(),
+            height: pixbuf.height(),
+            rowstride: pixbuf.rowstride(),
+        }
+    }
+
+    fn into_pixbuf(self) -> Pixbuf {
+        Pixbuf::from_bytes(
+            &self.bytes,
+            Colorspace::Rgb,
+            self.has_alpha,
+            8,
+            self.width,
+            self.height,
+            self.rowstride,
+        )
+    }
+}
+
+// When the server doesn't say
+const IMAGE_MAX_AGE: u64 = 7 * 24 * 60 * 60;
+
 // A helper to load remote images, with simple cache management
 pub struct ImageLoader {
     cache: CacheManager,
@@ -38,21 +89,50 @@ impl ImageLoader {
         }
     }
 
-    // Downloaded images are simply named [hash of url].[file extension]
-    fn resource_for(url: &str, ext: &str) -> String {
-        let mut hasher = DefaultHasher::new();
-        hasher.write(url.as_bytes());
-        let hashed = hasher.finish().to_string();
-        hashed + "." + ext
-    }
-
-    async fn get_image(url: &str) -> Option<Response<AsyncBody>> {
+    async fn fetch_image(url: &str, etag: Option<ETag>) -> Option<FetchResult> {
         let mut builder = HttpClient::builder();
         if cfg!(debug_assertions) {
             builder = builder.ssl_options(isahc::config::SslOption::DANGER_ACCEPT_INVALID_CERTS);
         }
         let client = builder.build().unwrap();
-        client.get_async(url).await.ok()
+        let mut request = Request::get(url);
+        if let Some(etag) = etag.as_ref() {
+            request = request.header("If-None-Match", etag);
+        }
+        let mut resp = client.send_async(request.body(()).ok()?).await.ok()?;
+
+        let header = |name: &str| {
+            resp.headers()
+                .get(name)
+                .and_then(|header| header.to_str().ok())
+                .map(|s| s.to_owned())
+        };
+        let seconds = header("cache-control")
+            .as_deref()
+            .and_then(max_age)
+            .unwrap_or(IMAGE_MAX_AGE);
+        // The etag we sent still holds if none came back
+        let expiry = CacheExpiry::expire_in_seconds(seconds, header("etag").or(etag));
+
+        match resp.status() {
+            StatusCode::NOT_MODIFIED => Some(FetchResult::NotModified(expiry)),
+            s if s.is_success() => {
+                let mut buffer = vec![];
+                resp.copy_to(&mut buffer).await.ok()?;
+                Some(FetchResult::Modified(buffer, expiry))
+            }
+            _ => None,
+        }
+    }
+
+    // Decoding (and scaling) a full size image is expensive, let's not do that on the main thread
+    async fn decode(buffer: Vec<u8>, width: i32, height: i32) -> Option<Pixbuf> {
+        let decoded = task::spawn_blocking(move || {
+            let pixbuf = decode_scaled(&buffer[..], width, height)?;
+            Some(DecodedImage::from_pixbuf(&pixbuf))
+        })
+        .await?;
+        Some(decoded.into_pixbuf())
     }
 
     pub async fn load_remote(
@@ -62,39 +142,356 @@ impl ImageLoader {
         width: i32,
         height: i32,
     ) -> Option<Pixbuf> {
-        let resource = Self::resource_for(url, ext);
-        let pixbuf_loader = PixbufLoader::new();
-        pixbuf_loader.set_size(width, height);
-        let mut loader = LocalPixbufLoader(&pixbuf_loader);
-
-        // Try to read from cache first, ignoring possible expiry
-        match self
+        // Thumbnails expire along with the image they were made from, and keep its etag:
+        // any other size might be the one that noticed the image changed
+        let thumbnail = CacheKey::Thumbnail(url, width, height);
+        let stale_thumbnail = match self
             .cache
-            .read_cache_file(&resource[..], CachePolicy::IgnoreExpiry)
+            .read_cache_file(&thumbnail, CachePolicy::Default)
             .await
         {
-            // Write content of cache file to the pixbuf loader if the cache contained something
             Ok(CacheFile::Fresh(buffer)) => {
-                loader.write_all(&buffer[..]).ok()?;
-            }
-            // Otherwise, get image over HTTP
-            _ => {
-                if let Some(mut resp) = Self::get_image(url).await {
-                    let mut buffer = vec![];
-                    // Copy the image to a buffer...
-                    resp.copy_to(&mut buffer).await.ok()?;
-                    // ... copy the buffer to the loader...
-                    loader.write_all(&buffer[..]).ok()?;
-                    // ... but also save that buffer to cache
-                    self.cache
-                        .write_cache_file(&resource[..], &buffer[..], CacheExpiry::Never)
-                        .await
-                        .ok()?;
+                if let Some(pixbuf) = Self::decode(buffer, width, height).await {
+                    return Some(pixbuf);
                 }
+                None
+            }
+            Ok(CacheFile::Expired(buffer, etag)) => Some((buffer, etag)),
+            _ => None,
+        };
+
+        let Some(buffer) = self.revalidate_original(url, ext).await else {
+            // Offline probably, an old picture is better than none
+            return Self::decode(stale_thumbnail?.0, width, height).await;
+        };
+        let image_expiry = self.cache.expiry(&CacheKey::Image(url, ext)).await;
+
+        // Made from the very same image, the thumbnail only needs to be told it's still good
+        let unchanged = stale_thumbnail
+            .filter(|(_, etag)| etag.is_some() && etag.as_ref() == image_expiry.etag());
+        if let Some((stale_thumbnail, _)) = unchanged {
+            let _ = self
+                .cache
+                .write_cache_file(&thumbnail, &stale_thumbnail[..], image_expiry)
+                .await;
+            return Self::decode(stale_thumbnail, width, height).await;
+        }
+
+        let (decoded, thumbnail_content) = task::spawn_blocking(move || {
+            let pixbuf = decode_scaled(&buffer[..], width, height)?;
+            let thumbnail_content = pixbuf.save_to_bufferv("png", &[]).ok();
+            Some((DecodedImage::from_pixbuf(&pixbuf), thumbnail_content))
+        })
+        .await?;
+
+        if let Some(thumbnail_content) = thumbnail_content {
+            let _ = self
+                .cache
+                .write_cache_file(&thumbnail, &thumbnail_content[..], image_expiry)
+                .await;
+        }
+
+        Some(decoded.into_pixbuf())
+    }
+
+    // The full size image as a file, for other apps to show (MPRIS clients for instance)
+    pub async fn load_file(&self, url: &str, ext: &str) -> Option<PathBuf> {
+        self.revalidate_original(url, ext).await?;
+        self.cached_file(url, ext)
+    }
+
+    // Same, but only if it was downloaded already
+    pub fn cached_file(&self, url: &str, ext: &str) -> Option<PathBuf> {
+        Some(self.cache.cache_path(&CacheKey::Image(url, ext))).filter(|path| path.exists())
+    }
+
+    // The full size image, from the cache or over HTTP once expired (which is nearly free when it
+    // didn't change, thanks to its etag)
+    async fn revalidate_original(&self, url: &str, ext: &str) -> Option<Vec<u8>> {
+        let image = CacheKey::Image(url, ext);
+        let (cached, etag) = match self
+            .cache
+            .read_cache_file(&image, CachePolicy::Default)
+            .await
+        {
+            Ok(CacheFile::Fresh(buffer)) => return Some(buffer),
+            Ok(CacheFile::Expired(buffer, etag)) => (Some(buffer), etag),
+            _ => (None, None),
+        };
+        match (Self::fetch_image(url, etag).await, cached) {
+            (Some(FetchResult::NotModified(expiry)), Some(cached)) => {
+                let _ = self
+                    .cache
+                    .write_cache_file(&image, &cached[..], expiry)
+                    .await;
+                Some(cached)
+            }
+            (Some(FetchResult::Modified(buffer, expiry)), _) => {
+                self.cache
+                    .write_cache_file(&image, &buffer[..], expiry)
+                    .await
+                    .ok()?;
+                Some(buffer)
             }
+            // Couldn't check, what we have will do for now
+            (_, cached) => cached,
+        }
+    }
+}
+
+// How many images can be loaded at the same time through the artwork queue
+const MAX_ARTWORK_IN_FLIGHT: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum ArtworkPriority {
+    Visible,
+    NearViewport,
+    Idle,
+}
+
+struct ArtworkJob {
+    id: u64,
+    widget: glib::WeakRef<gtk::Widget>,
+    url: String,
+    size: i32,
+    worker: Worker,
+    on_loaded: Box<dyn FnOnce(gtk::Widget, Option<Pixbuf>)>,
+}
+
+impl ArtworkJob {
+    // Recomputed every time we pick the next job, as the user might have scrolled in the meantime
+    fn priority(&self) -> ArtworkPriority {
+        let widget = match self.widget.upgrade() {
+            Some(widget) if widget.is_mapped() => widget,
+            _ => return ArtworkPriority::Idle,
         };
+        let scrolled_window: gtk::ScrolledWindow = match ancestor(&widget) {
+            Some(scrolled_window) => scrolled_window,
+            None => return ArtworkPriority::Visible,
+        };
+        let viewport = scrolled_window.height() as f64;
+        match widget.translate_coordinates(&scrolled_window, 0.0, 0.0) {
+            Some((_, top)) => {
+                let bottom = top + widget.height() as f64;
+                if bottom >= 0.0 && top <= viewport {
+                    ArtworkPriority::Visible
+                } else if bottom >= -viewport && top <= 2.0 * viewport {
+                    ArtworkPriority::NearViewport
+                } else {
+                    ArtworkPriority::Idle
+                }
+            }
+            None => ArtworkPriority::Idle,
+        }
+    }
+}
+
+// What a cancelled request was doing when it got cancelled
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Cancelled {
+    Pending,
+    // Whatever it loads is dropped once it's done
+    InFlight,
+}
+
+#[derive(Default)]
+struct ArtworkQueue {
+    next_id: u64,
+    pending: Vec<ArtworkJob>,
+    // Jobs that were started and haven't been cancelled since
+    started: HashSet<u64>,
+    in_flight: usize,
+    idle_scheduled: bool,
+}
+
+thread_local! {
+    static ARTWORK_QUEUE: RefCell<ArtworkQueue> = RefCell::new(ArtworkQueue::default());
+}
+
+impl ArtworkQueue {
+    // Low priority jobs only get to run one at a time, when there's nothing else going on
+    fn next_job(&mut self, allow_idle: bool) -> Option<ArtworkJob> {
+        if self.in_flight >= MAX_ARTWORK_IN_FLIGHT {
+            return None;
+        }
+        self.pending.retain(|job| job.widget.upgrade().is_some());
+        let (index, priority) = self
+            .pending
+            .iter()
+            .enumerate()
+            .map(|(i, job)| (i, job.priority()))
+            .min_by_key(|(_, priority)| *priority)?;
+        if priority == ArtworkPriority::Idle && !(allow_idle && self.in_flight == 0) {
+            return None;
+        }
+        let job = self.pending.remove(index);
+        self.mark_started(job.id);
+        Some(job)
+    }
+
+    fn mark_started(&mut self, id: u64) {
+        self.started.insert(id);
+        self.in_flight += 1;
+    }
+
+    // Whether the result is still wanted
+    fn finish(&mut self, id: u64) -> bool {
+        self.in_flight -= 1;
+        self.started.remove(&id)
+    }
+
+    fn cancel_job(&mut self, id: u64) -> Option<Cancelled> {
+        let pending = self.pending.len();
+        self.pending.retain(|job| job.id != id);
+        if self.pending.len() < pending {
+            Some(Cancelled::Pending)
+        } else if self.started.remove(&id) {
+            Some(Cancelled::InFlight)
+        } else {
+            None
+        }
+    }
+
+    fn pump(allow_idle: bool) {
+        while let Some(job) = ARTWORK_QUEUE.with(|q| q.borrow_mut().next_job(allow_idle)) {
+            Self::start(job);
+        }
+        let schedule_idle = ARTWORK_QUEUE.with(|q| {
+            let mut q = q.borrow_mut();
+            let schedule = !q.idle_scheduled && !q.pending.is_empty() && q.in_flight == 0;
+            q.idle_scheduled |= schedule;
+            schedule
+        });
+        if schedule_idle {
+            glib::idle_add_local_full(glib::PRIORITY_LOW, || {
+                ARTWORK_QUEUE.with(|q| q.borrow_mut().idle_scheduled = false);
+                Self::pump(true);
+                glib::Continue(false)
+            });
+        }
+    }
+
+    fn start(job: ArtworkJob) {
+        let ArtworkJob {
+            id,
+            widget,
+            url,
+            size,
+            worker,
+            on_loaded,
+        } = job;
+        worker.send_local_task(async move {
+            let pixbuf = ImageLoader::new()
+                .load_remote(&url, "jpg", size, size)
+                .await;
+            let wanted = ARTWORK_QUEUE.with(|q| q.borrow_mut().finish(id));
+            match widget.upgrade() {
+                Some(widget) if wanted => on_loaded(widget, pixbuf),
+                _ => debug!("Artwork {} loaded after being cancelled, dropped", url),
+            }
+            Self::pump(false);
+        });
+    }
+
+    fn cancel(id: u64) {
+        let _ = ARTWORK_QUEUE.try_with(|q| {
+            // Might be dropped while the queue itself is busy dropping stale jobs, that's fine
+            if let Ok(mut q) = q.try_borrow_mut() {
+                if let Some(cancelled) = q.cancel_job(id) {
+                    debug!("Artwork request {} cancelled ({:?})", id, cancelled);
+                }
+            }
+        });
+    }
+}
+
+// Keep this around for as long as the image is wanted, dropping it cancels the request
+#[derive(Debug)]
+pub struct ArtworkRequest(u64);
+
+impl Drop for ArtworkRequest {
+    fn drop(&mut self) {
+        ArtworkQueue::cancel(self.0);
+    }
+}
+
+// Queue up loading (square) artwork for a given widget: what's on screen gets loaded first,
+// what's close comes next, and the rest is loaded whenever we're idle
+pub fn load_artwork<W, F>(
+    widget: &W,
+    worker: Worker,
+    url: String,
+    size: i32,
+    on_loaded: F,
+) -> ArtworkRequest
+where
+    W: IsA<gtk::Widget>,
+    F: FnOnce(&W, Option<Pixbuf>) + 'static,
+{
+    let id = ARTWORK_QUEUE.with(|q| {
+        let mut q = q.borrow_mut();
+        let id = q.next_id;
+        q.next_id += 1;
+        q.pending.push(ArtworkJob {
+            id,
+            widget: widget.upcast_ref::<gtk::Widget>().downgrade(),
+            url,
+            size,
+            worker,
+            on_loaded: Box::new(move |widget, pixbuf| {
+                if let Ok(widget) = widget.downcast::<W>() {
+                    on_loaded(&widget, pixbuf);
+                }
+            }),
+        });
+        id
+    });
+    ArtworkQueue::pump(false);
+    ArtworkRequest(id)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_cancelled_loads_are_dropped() {
+        let mut queue = ArtworkQueue::default();
+        queue.mark_started(1);
+        queue.mark_started(2);
+
+        assert_eq!(queue.cancel_job(1), Some(Cancelled::InFlight));
+        assert!(!queue.finish(1));
+        assert!(queue.finish(2));
+        assert_eq!(queue.in_flight, 0);
+    }
+
+    #[test]
+    fn test_cancelling_twice_reports_nothing() {
+        let mut queue = ArtworkQueue::default();
+        queue.mark_started(1);
+
+        assert_eq!(queue.cancel_job(1), Some(Cancelled::InFlight));
+        assert_eq!(queue.cancel_job(1), None);
+        assert_eq!(queue.cancel_job(42), None);
+    }
 
-        pixbuf_loader.close().ok()?;
-        pixbuf_loader.pixbuf()
+    #[test]
+    fn test_visible_first() {
+        let mut priorities = vec![
+            ArtworkPriority::Idle,
+            ArtworkPriority::Visible,
+            ArtworkPriority::NearViewport,
+        ];
+        priorities.sort();
+        assert_eq!(
+            priorities,
+            vec![
+                ArtworkPriority::Visible,
+                ArtworkPriority::NearViewport,
+                ArtworkPriority::Idle
+            ]
+        );
     }
 }
@@ -0,0 +1,104 @@
+use gettextrs::gettext;
+
+use crate::app::models::SpotifyLink;
+use crate::app::state::PlaybackAction;
+use crate::app::{AppAction, SongsSource};
+
+// What's left to do while Spot is locked (see the Lock preferences).
+// Edits to the library and playlists are refused by the API client itself, this is about what can be played.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Lock {
+    // Anything can be played when empty
+    pub allowed_playlists: Vec<String>,
+}
+
+impl Lock {
+    fn restricts_playback(&self) -> bool {
+        !self.allowed_playlists.is_empty()
+    }
+
+    fn allows_source(&self, source: &SongsSource) -> bool {
+        match source {
+            SongsSource::Playlist(id) => {
+                !self.restricts_playback() || self.allowed_playlists.contains(id)
+            }
+            _ => !self.restricts_playback(),
+        }
+    }
+
+    pub fn allows(&self, action: &AppAction) -> bool {
+        match action {
+            AppAction::PlaybackAction(PlaybackAction::LoadPagedSongs(source, _)) => {
+                self.allows_source(source)
+            }
+            // Songs from just about anywhere
+            AppAction::PlaybackAction(PlaybackAction::LoadSongs(_))
+            | AppAction::PlaybackAction(PlaybackAction::Queue(_))
+            | AppAction::QueueSelection
+            | AppAction::OpenTrack(_) => !self.restricts_playback(),
+            _ => true,
+        }
+    }
+}
+
+// Links to playlists or just their ids, separated by spaces. Links to anything else are left out.
+pub fn playlist_ids(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter_map(|word| match SpotifyLink::parse(word) {
+            Some(SpotifyLink::Playlist(id)) => Some(id),
+            Some(_) => None,
+            None => word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric())
+                .then(|| word.to_string()),
+        })
+        .collect()
+}
+
+pub fn playlist_links(ids: &[String]) -> String {
+    ids.iter()
+        .map(|id| format!("spotify:playlist:{id}"))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn refused_message() -> String {
+    // translators: Shown when trying to do something that was turned off with a PIN (editing playlists, playing other playlists...)
+    gettext("Not possible while Spot is locked")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::models::SongBatch;
+
+    fn play_playlist(id: &str) -> AppAction {
+        PlaybackAction::LoadPagedSongs(SongsSource::Playlist(id.to_string()), SongBatch::empty())
+            .into()
+    }
+
+    #[test]
+    fn test_allowed_playlists() {
+        let lock = Lock::default();
+        assert!(lock.allows(&play_playlist("any")));
+        assert!(lock.allows(&AppAction::QueueSelection));
+
+        let lock = Lock {
+            allowed_playlists: vec!["kids".to_string()],
+        };
+        assert!(lock.allows(&play_playlist("kids")));
+        assert!(!lock.allows(&play_playlist("mine")));
+        assert!(!lock.allows(&AppAction::QueueSelection));
+        assert!(lock.allows(&PlaybackAction::Next.into()));
+    }
+
+    #[test]
+    fn test_playlist_ids() {
+        let ids = playlist_ids(
+            "https://open.spotify.com/playlist/abc?si=1 spotify:album:nope  def spotify:playlist:ghi",
+        );
+        assert_eq!(ids, vec!["abc", "def", "ghi"]);
+        assert_eq!(playlist_ids(&playlist_links(&ids)), ids);
+    }
+}
@@ -15,6 +15,21 @@ pub mod models;
 mod list_store;
 pub use list_store::*;
 
+pub mod kv_store;
+
+mod listening_history;
+
+pub mod lock;
+
+pub mod health;
+
+pub mod collation;
+
+pub mod format;
+
+mod errors;
+pub use errors::{AppError, ErrorKind, Retry};
+
 pub mod state;
 pub use state::{AppAction, AppEvent, AppModel, AppState, BrowserAction, BrowserEvent};
 
@@ -27,6 +42,9 @@ pub mod loader;
 pub mod rng;
 pub use rng::LazyRandomIndex;
 
+#[cfg(test)]
+pub mod test_utils;
+
 // Where all the app logic happens
 pub struct App {
     settings: SpotSettings,
@@ -49,8 +67,19 @@ impl App {
         worker: Worker,
     ) -> Self {
         let state = AppState::new();
-        let spotify_client = Arc::new(CachedSpotifyClient::new());
+        let spotify_client = Arc::new(CachedSpotifyClient::new(settings.retry_policy));
+        let client = Arc::clone(&spotify_client);
+        crate::settings::connect_retry_policy_changed(move |policy| {
+            client.set_retry_policy(policy)
+        });
+        // Told as soon as the request that tells is done, before its result is dispatched
+        let health_sender = sender.clone();
+        spotify_client.connect_web_api_health_changed(Box::new(move |up| {
+            let _ = health_sender
+                .unbounded_send(AppAction::SetBackendHealth(health::Backend::WebApi, up));
+        }));
         let model = Rc::new(AppModel::new(state, spotify_client));
+        model.restore_last_session(settings.startup.resume_playback);
 
         // Non widget components
         let components: Vec<Box<dyn EventListener>> = vec![
@@ -60,7 +89,60 @@ impl App {
                 Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
                 sender.clone(),
             ),
-            App::make_dbus(Rc::clone(&model), sender.clone()),
+            App::make_dbus(&settings, Rc::clone(&model), sender.clone()),
+            App::make_undo_handler(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_track_opener(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_resume_tracker(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_context_preferences(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_volume_memory(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_recently_viewed(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_new_releases(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+                worker.clone(),
+            ),
+            App::make_history_recorder(Rc::clone(&model)),
+            App::make_playback_hooks(Rc::clone(&model)),
+            App::make_status_stream(Rc::clone(&model)),
+            App::make_network_watcher(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_lock_guard(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_blocklist_keeper(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_screen_refresher(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+            ),
+            App::make_auto_mix(
+                Rc::clone(&model),
+                Box::new(ActionDispatcherImpl::new(sender.clone(), worker.clone())),
+                worker.clone(),
+            ),
         ];
 
         Self {
@@ -98,19 +180,125 @@ impl App {
             ),
             App::make_login(builder, dispatcher.box_clone(), worker.clone()),
             App::make_navigation(
+                &self.settings,
                 builder,
                 Rc::clone(model),
                 dispatcher.box_clone(),
                 worker.clone(),
             ),
             App::make_search_button(builder, dispatcher.box_clone()),
+            App::make_quick_open(
+                builder,
+                Rc::clone(model),
+                dispatcher.box_clone(),
+                worker.clone(),
+            ),
             App::make_user_menu(builder, Rc::clone(model), dispatcher),
-            App::make_notification(builder),
+            App::make_notification(builder, dispatcher.box_clone()),
+            App::make_degraded_banner(builder, Rc::clone(model), dispatcher.box_clone()),
         ];
 
         self.components.append(&mut components);
     }
 
+    fn make_undo_handler(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<UndoHandler> {
+        Box::new(UndoHandler::new(app_model, dispatcher))
+    }
+
+    fn make_track_opener(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<TrackOpener> {
+        Box::new(TrackOpener::new(app_model, dispatcher))
+    }
+
+    fn make_resume_tracker(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<ResumeTracker> {
+        Box::new(ResumeTracker::new(app_model, dispatcher))
+    }
+
+    fn make_context_preferences(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<ContextPreferencesApplier> {
+        Box::new(ContextPreferencesApplier::new(app_model, dispatcher))
+    }
+
+    fn make_recently_viewed(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<RecentlyViewedTracker> {
+        Box::new(RecentlyViewedTracker::new(app_model, dispatcher))
+    }
+
+    fn make_volume_memory(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<VolumeMemory> {
+        Box::new(VolumeMemory::new(app_model, dispatcher))
+    }
+
+    fn make_new_releases(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+        worker: Worker,
+    ) -> Box<NewReleasesWatcher> {
+        Box::new(NewReleasesWatcher::new(app_model, dispatcher, worker))
+    }
+
+    fn make_screen_refresher(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<ScreenRefresher> {
+        Box::new(ScreenRefresher::new(app_model, dispatcher))
+    }
+
+    fn make_history_recorder(app_model: Rc<AppModel>) -> Box<HistoryRecorder> {
+        Box::new(HistoryRecorder::new(app_model))
+    }
+
+    fn make_playback_hooks(app_model: Rc<AppModel>) -> Box<PlaybackHooks> {
+        Box::new(PlaybackHooks::new(app_model))
+    }
+
+    fn make_status_stream(app_model: Rc<AppModel>) -> Box<StatusStream> {
+        Box::new(StatusStream::new(app_model))
+    }
+
+    fn make_network_watcher(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<NetworkWatcher> {
+        Box::new(NetworkWatcher::new(app_model, dispatcher))
+    }
+
+    fn make_lock_guard(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<LockGuard> {
+        Box::new(LockGuard::new(app_model, dispatcher))
+    }
+
+    fn make_blocklist_keeper(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<BlocklistKeeper> {
+        Box::new(BlocklistKeeper::new(app_model, dispatcher))
+    }
+
+    fn make_auto_mix(
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+        worker: Worker,
+    ) -> Box<AutoMix> {
+        Box::new(AutoMix::new(app_model, dispatcher, worker))
+    }
+
     // A component that listens to what's happening in the app, and translates it for the actual player
     fn make_player_notifier(
         app_model: Rc<AppModel>,
@@ -119,9 +307,11 @@ impl App {
         sender: UnboundedSender<AppAction>,
     ) -> Box<impl EventListener> {
         let api = app_model.get_spotify();
+        let preview_player = PreviewPlayer::new(dispatcher.box_clone());
         Box::new(PlayerNotifier::new(
             app_model,
             dispatcher,
+            preview_player,
             // Either communications with the librespot player
             crate::player::start_player_service(settings.player_settings.clone(), sender.clone()),
             // or with a Spotify Connect device
@@ -131,10 +321,15 @@ impl App {
 
     // A component to handle anything DBUS related
     fn make_dbus(
+        settings: &SpotSettings,
         app_model: Rc<AppModel>,
         sender: UnboundedSender<AppAction>,
     ) -> Box<impl EventListener> {
-        Box::new(crate::dbus::start_dbus_server(app_model, sender))
+        Box::new(crate::dbus::start_dbus_server(
+            app_model,
+            sender,
+            settings.grab_media_keys,
+        ))
     }
 
     fn make_window(
@@ -143,10 +338,16 @@ impl App {
         app_model: Rc<AppModel>,
     ) -> Box<impl EventListener> {
         let window: libadwaita::ApplicationWindow = builder.object("window").unwrap();
-        Box::new(MainWindow::new(settings.window.clone(), app_model, window))
+        Box::new(MainWindow::new(
+            settings.window.clone(),
+            settings.startup.minimized,
+            app_model,
+            window,
+        ))
     }
 
     fn make_navigation(
+        settings: &SpotSettings,
         builder: &gtk::Builder,
         app_model: Rc<AppModel>,
         dispatcher: Box<dyn ActionDispatcher>,
@@ -169,6 +370,7 @@ impl App {
             navigation_stack,
             home_listbox,
             screen_factory,
+            settings.navigation.clone(),
         ))
     }
 
@@ -232,9 +434,36 @@ impl App {
         Box::new(user_menu)
     }
 
-    fn make_notification(builder: &gtk::Builder) -> Box<Notification> {
+    fn make_quick_open(
+        builder: &gtk::Builder,
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+        worker: Worker,
+    ) -> Box<QuickOpen> {
+        let parent: gtk::Window = builder.object("window").unwrap();
+        let model = QuickOpenModel::new(app_model, dispatcher);
+        Box::new(QuickOpen::new(parent, model, worker))
+    }
+
+    fn make_notification(
+        builder: &gtk::Builder,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<Notification> {
         let toast_overlay: libadwaita::ToastOverlay = builder.object("main").unwrap();
-        Box::new(Notification::new(toast_overlay))
+        Box::new(Notification::new(toast_overlay, dispatcher))
+    }
+
+    fn make_degraded_banner(
+        builder: &gtk::Builder,
+        app_model: Rc<AppModel>,
+        dispatcher: Box<dyn ActionDispatcher>,
+    ) -> Box<DegradedBanner> {
+        let revealer: gtk::Revealer = builder.object("degraded_banner").unwrap();
+        let label: gtk::Label = builder.object("degraded_label").unwrap();
+        let retry: gtk::Button = builder.object("degraded_retry").unwrap();
+        Box::new(DegradedBanner::new(
+            revealer, label, retry, app_model, dispatcher,
+        ))
     }
 
     // Main handler called in a loop
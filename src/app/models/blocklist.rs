@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::SongDescription;
+use crate::app::collation;
+
+fn artist_uri(id: &str) -> String {
+    format!("spotify:artist:{id}")
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockedItem {
+    // spotify:track:<id> or spotify:artist:<id>
+    pub uri: String,
+    pub name: String,
+}
+
+impl BlockedItem {
+    pub fn song(song: &SongDescription) -> Self {
+        Self {
+            uri: song.uri.clone(),
+            name: format!("{} · {}", song.title, song.artists_name()),
+        }
+    }
+
+    pub fn artist(id: &str, name: &str) -> Self {
+        Self {
+            uri: artist_uri(id),
+            name: name.to_string(),
+        }
+    }
+
+    pub fn is_artist(&self) -> bool {
+        self.uri.starts_with("spotify:artist:")
+    }
+}
+
+// Songs and artists that never get played, they're skipped over just like unavailable songs
+#[derive(Clone, Debug, Default)]
+pub struct Blocklist(HashMap<String, BlockedItem>);
+
+impl Blocklist {
+    pub fn new(items: Vec<BlockedItem>) -> Self {
+        Self(items.into_iter().map(|i| (i.uri.clone(), i)).collect())
+    }
+
+    // Artists first, then by name
+    pub fn items(&self) -> Vec<BlockedItem> {
+        let mut items: Vec<BlockedItem> = self.0.values().cloned().collect();
+        items.sort_by_cached_key(|item| (!item.is_artist(), collation::sort_key(&item.name)));
+        items
+    }
+
+    // False if it was already there
+    pub fn block(&mut self, item: BlockedItem) -> bool {
+        self.0.insert(item.uri.clone(), item).is_none()
+    }
+
+    pub fn unblock(&mut self, uri: &str) -> bool {
+        self.0.remove(uri).is_some()
+    }
+
+    pub fn blocks(&self, song: &SongDescription) -> bool {
+        !self.0.is_empty()
+            && (self.0.contains_key(&song.uri)
+                || song
+                    .artists
+                    .iter()
+                    .any(|artist| self.0.contains_key(&artist_uri(&artist.id))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::models::ArtistRef;
+    use crate::app::test_utils;
+
+    fn song(id: &str, artist: &str) -> SongDescription {
+        SongDescription {
+            artists: vec![ArtistRef {
+                id: artist.to_string(),
+                name: artist.to_string(),
+            }],
+            ..test_utils::song(id)
+        }
+    }
+
+    #[test]
+    fn test_blocks_songs_and_artists() {
+        let mut blocklist = Blocklist::default();
+        assert!(!blocklist.blocks(&song("a", "x")));
+
+        assert!(blocklist.block(BlockedItem::song(&song("a", "x"))));
+        assert!(!blocklist.block(BlockedItem::song(&song("a", "x"))));
+        blocklist.block(BlockedItem::artist("y", "Y"));
+        assert!(blocklist.blocks(&song("a", "x")));
+        assert!(blocklist.blocks(&song("b", "y")));
+        assert!(!blocklist.blocks(&song("c", "x")));
+        assert!(blocklist.items()[0].is_artist());
+
+        assert!(blocklist.unblock("spotify:artist:y"));
+        assert!(!blocklist.blocks(&song("b", "y")));
+    }
+}
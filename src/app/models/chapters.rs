@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+// Going back this far into a chapter goes to its start rather than to the previous one, like tracks do
+const RESTART_THRESHOLD_MS: u32 = 2000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChapterMarker {
+    // In ms, like the seek position
+    pub start: u32,
+    pub title: String,
+}
+
+// Sections of a long item (a mix, an episode...), sorted by start
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChapterList(Vec<ChapterMarker>);
+
+// "1:02:03" or "12:34", possibly wrapped in brackets
+fn parse_timestamp(text: &str) -> Option<u32> {
+    let text = text
+        .trim_start_matches(['(', '['])
+        .trim_end_matches([')', ']']);
+    let parts = text
+        .split(':')
+        .map(|part| {
+            if part.is_empty() || part.len() > 2 || !part.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            part.parse::<u32>().ok()
+        })
+        .collect::<Option<Vec<u32>>>()?;
+    let (hours, minutes, seconds) = match parts[..] {
+        [minutes, seconds] => (0, minutes, seconds),
+        [hours, minutes, seconds] if minutes < 60 => (hours, minutes, seconds),
+        _ => return None,
+    };
+    if seconds >= 60 {
+        return None;
+    }
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000)
+}
+
+impl ChapterList {
+    pub fn new(mut markers: Vec<ChapterMarker>) -> Self {
+        markers.sort_by_key(|m| m.start);
+        markers.dedup_by_key(|m| m.start);
+        Self(markers)
+    }
+
+    // Spotify has no such thing as chapters within an item, but descriptions often list them, one per line
+    // as in "12:34 Some title" or "1:02:03 - Some title"
+    pub fn from_description(description: &str, duration: u32) -> Self {
+        let markers: Vec<ChapterMarker> = description
+            .lines()
+            .filter_map(|line| {
+                let line =
+                    line.trim_start_matches(|c: char| c.is_whitespace() || c == '-' || c == '•');
+                let (timestamp, title) = line.split_once(char::is_whitespace)?;
+                let start = parse_timestamp(timestamp).filter(|start| *start < duration)?;
+                let title =
+                    title.trim_start_matches(|c: char| c.is_whitespace() || "-–—:|".contains(c));
+                Some(ChapterMarker {
+                    start,
+                    title: title.trim_end().to_string(),
+                })
+            })
+            .collect();
+        // A single timestamp is more likely to be a mention than a list of chapters
+        if markers.len() < 2 {
+            return Self::default();
+        }
+        Self::new(markers)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn markers(&self) -> &[ChapterMarker] {
+        &self.0
+    }
+
+    fn current_index(&self, position: u32) -> Option<usize> {
+        self.0.iter().rposition(|m| m.start <= position)
+    }
+
+    pub fn next_start(&self, position: u32) -> Option<u32> {
+        self.0.iter().find(|m| m.start > position).map(|m| m.start)
+    }
+
+    pub fn previous_start(&self, position: u32) -> Option<u32> {
+        let Some(i) = self.current_index(position) else {
+            // Before the first marker, which is as good as its start
+            return self.0.first().map(|_| 0);
+        };
+        let start = self.0[i].start;
+        if position - start > RESTART_THRESHOLD_MS || i == 0 {
+            Some(start)
+        } else {
+            Some(self.0[i - 1].start)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const DESCRIPTION: &str = "Recorded live.\n\
+        00:00 Intro\n\
+        - 4:30 - Second part\n\
+        1:02:03 | The long one\n\
+        Thanks to 12:00 for nothing\n\
+        99:99 Not a time";
+
+    #[test]
+    fn test_from_description() {
+        let chapters = ChapterList::from_description(DESCRIPTION, 2 * 60 * 60 * 1000);
+        let titles: Vec<(u32, &str)> = chapters
+            .markers()
+            .iter()
+            .map(|m| (m.start, m.title.as_str()))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                (0, "Intro"),
+                (270_000, "Second part"),
+                (3_723_000, "The long one")
+            ]
+        );
+
+        let chapters = ChapterList::from_description(DESCRIPTION, 60 * 60 * 1000);
+        assert_eq!(chapters.markers().len(), 2);
+
+        let chapters = ChapterList::from_description("Starts at 0:30 sharp\n0:30 Single", 60_000);
+        assert!(chapters.is_empty());
+    }
+
+    #[test]
+    fn test_navigation() {
+        let chapters = ChapterList::from_description(DESCRIPTION, 2 * 60 * 60 * 1000);
+        assert_eq!(chapters.next_start(0), Some(270_000));
+        assert_eq!(chapters.next_start(3_723_000), None);
+        assert_eq!(chapters.current_index(300_000), Some(1));
+        assert_eq!(chapters.previous_start(300_000), Some(270_000));
+        assert_eq!(chapters.previous_start(271_000), Some(0));
+        assert_eq!(chapters.previous_start(273_000), Some(270_000));
+        assert_eq!(chapters.previous_start(1000), Some(0));
+    }
+}
@@ -1,8 +1,8 @@
-use std::{
-    hash::{Hash, Hasher},
-    str::FromStr,
-};
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
 
+use super::ChapterList;
+use crate::app::format::ReleaseDate;
 use crate::app::SongsSource;
 
 // A batch of whatever
@@ -39,6 +39,54 @@ impl Batch {
         })
         .filter(|b| b.offset < total)
     }
+
+    // The batch an element at a given index belongs to
+    pub fn containing(index: usize, batch_size: usize, total: usize) -> Self {
+        Self {
+            offset: (index / batch_size) * batch_size,
+            batch_size,
+            total,
+        }
+    }
+
+    // All batches needed to get elements in [start, end)
+    pub fn covering(
+        start: usize,
+        end: usize,
+        batch_size: usize,
+        total: usize,
+    ) -> impl Iterator<Item = Self> {
+        let end = usize::min(end, total);
+        let first = Self::containing(start, batch_size, total);
+        std::iter::successors(Some(first).filter(|_| start < end), Self::next)
+            .take_while(move |b| b.offset < end)
+    }
+}
+
+// Where a page starts: most endpoints page by offset, but some (followed artists, recently played)
+// hand out an opaque cursor instead, and don't necessarily know how many items there are in total
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PageToken {
+    Offset(usize),
+    // Whatever comes after the item the cursor points to
+    After(String),
+}
+
+impl PageToken {
+    // Tells pages apart in the cache
+    pub fn key(&self) -> String {
+        match self {
+            Self::Offset(offset) => offset.to_string(),
+            Self::After(cursor) => format!("after_{cursor}"),
+        }
+    }
+}
+
+// A page of whatever, and where the next one starts if there's more to load
+#[derive(Clone, Debug)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub next: Option<PageToken>,
 }
 
 // "Something"Ref models usually boil down to an ID/url + a display name
@@ -49,13 +97,13 @@ pub struct UserRef {
     pub display_name: String,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ArtistRef {
     pub id: String,
     pub name: String,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AlbumRef {
     pub id: String,
     pub name: String,
@@ -89,9 +137,9 @@ impl AlbumDescription {
 
     pub fn year(&self) -> Option<u32> {
         self.release_date
-            .as_ref()
-            .and_then(|date| date.split('-').next())
-            .and_then(|y| u32::from_str(y).ok())
+            .as_deref()
+            .and_then(ReleaseDate::parse)
+            .and_then(|date| u32::try_from(date.year()).ok())
     }
 }
 
@@ -115,6 +163,39 @@ pub struct PlaylistDescription {
     pub art: Option<String>,
     pub songs: SongBatch,
     pub owner: UserRef,
+    // The version of the playlist the songs belong to, edits are made against it
+    pub snapshot_id: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AudiobookDescription {
+    pub id: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub art: Option<String>,
+    pub total_chapters: usize,
+}
+
+impl AudiobookDescription {
+    pub fn authors_name(&self) -> String {
+        self.authors.join(", ")
+    }
+}
+
+// A chapter plays like any song, it just also knows how far it was listened to (in ms)
+#[derive(Clone, Debug)]
+pub struct ChapterDescription {
+    pub song: SongDescription,
+    pub resume_position: u32,
+    pub fully_played: bool,
+}
+
+// A row of playlists picked by Spotify for the user, on the "For you" page
+#[derive(Clone, Debug)]
+pub struct HomeShelf {
+    pub id: String,
+    pub title: String,
+    pub playlists: Vec<PlaylistDescription>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -130,6 +211,8 @@ pub struct ConnectDevice {
     pub id: String,
     pub label: String,
     pub kind: ConnectDeviceKind,
+    // Whether something is playing on it
+    pub is_active: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -138,19 +221,44 @@ pub struct PlaylistSummary {
     pub title: String,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SongDescription {
     pub id: String,
     pub track_number: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disc_number: Option<u32>,
     pub uri: String,
     pub title: String,
     pub artists: Vec<ArtistRef>,
     pub album: AlbumRef,
     pub duration: u32,
     pub art: Option<String>,
+    #[serde(default, skip_serializing_if = "ChapterList::is_empty")]
+    pub chapters: ChapterList,
+    // False when it isn't available in the user's market
+    #[serde(default = "is_playable_default")]
+    pub is_playable: bool,
+    // 30 seconds of it as an MP3, playable without Premium (not every song has one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_url: Option<String>,
+    // When it was added to the playlist or the library it was loaded from (ISO 8601)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<String>,
+    // From 0 to 100, as Spotify sees it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub popularity: Option<u32>,
+}
+
+fn is_playable_default() -> bool {
+    true
 }
 
 impl SongDescription {
+    // Audiobook chapters play just like songs, but Spotify sees them as episodes
+    pub fn is_chapter(&self) -> bool {
+        self.uri.starts_with("spotify:episode:")
+    }
+
     pub fn artists_name(&self) -> String {
         self.artists
             .iter()
@@ -187,6 +295,14 @@ impl SongBatch {
         }
     }
 
+    // Only known once all the songs are there, that is when this batch covers the whole list
+    pub fn total_duration(&self) -> Option<u64> {
+        if self.batch.offset != 0 || self.batch.batch_size < self.batch.total {
+            return None;
+        }
+        Some(self.songs.iter().map(|s| u64::from(s.duration)).sum())
+    }
+
     pub fn resize(self, batch_size: usize) -> Vec<Self> {
         let SongBatch { mut songs, batch } = self;
         // Growing a batch is easy...
@@ -230,6 +346,41 @@ pub struct ArtistDescription {
     pub name: String,
     pub albums: Vec<AlbumDescription>,
     pub top_tracks: Vec<SongDescription>,
+    pub is_followed: bool,
+}
+
+// A date an artist is playing at, see api::events
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConcertDescription {
+    // ISO 8601, local to the venue
+    pub date: String,
+    pub venue: String,
+    pub location: String,
+    pub url: String,
+}
+
+// An album, artist or playlist that was opened, see components::recently_viewed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentlyViewed {
+    // spotify:album:<id> and such
+    pub uri: String,
+    pub title: String,
+    pub subtitle: String,
+    pub art: Option<String>,
+    // Milliseconds since the epoch
+    pub viewed_at: i64,
+}
+
+// An album or single that came out while its artist's releases were watched, see components::new_releases
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewRelease {
+    pub album_id: String,
+    pub title: String,
+    pub artist: String,
+    pub art: Option<String>,
+    pub release_date: String,
+    // Milliseconds since the epoch
+    pub found_at: i64,
 }
 
 #[derive(Clone, Debug)]
@@ -246,13 +397,140 @@ pub struct UserDescription {
     pub playlists: Vec<PlaylistDescription>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RepeatMode {
     Song,
     Playlist,
     None,
 }
 
+impl RepeatMode {
+    // What the repeat button goes to next
+    pub fn next(self) -> Self {
+        match self {
+            Self::Song => Self::None,
+            Self::Playlist => Self::Song,
+            Self::None => Self::Playlist,
+        }
+    }
+}
+
+// How the next song is picked when shuffling
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShuffleMode {
+    #[default]
+    Uniform,
+    // Not the same artist twice in a row, when there's a choice
+    ArtistSpread,
+    // Every album gets the same chance, however many of its songs are in the list
+    AlbumWeighted,
+    // Songs not heard in a long time (according to the listening history) are more likely to come first
+    LeastRecentlyPlayed,
+}
+
+impl ShuffleMode {
+    pub const ALL: [ShuffleMode; 4] = [
+        Self::Uniform,
+        Self::ArtistSpread,
+        Self::AlbumWeighted,
+        Self::LeastRecentlyPlayed,
+    ];
+
+    // Same as the nicks in the gschema
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Uniform => "uniform",
+            Self::ArtistSpread => "artist-spread",
+            Self::AlbumWeighted => "album-weighted",
+            Self::LeastRecentlyPlayed => "least-recently-played",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.id() == id)
+    }
+}
+
+const GROUP_SESSION_LINK: &str = "https://open.spotify.com/socialsession/";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupSession {
+    pub id: String,
+    pub join_token: String,
+    pub is_owner: bool,
+    // Display names
+    pub members: Vec<String>,
+}
+
+impl GroupSession {
+    pub fn invite_link(&self) -> String {
+        format!("{}{}", GROUP_SESSION_LINK, self.join_token)
+    }
+
+    pub fn token_from_link(link: &str) -> Option<String> {
+        let token = link.trim().strip_prefix(GROUP_SESSION_LINK)?;
+        let token = token.split(['?', '/']).next()?;
+        Some(token.to_string()).filter(|t| !t.is_empty())
+    }
+}
+
+const WEB_LINK_PREFIX: &str = "open.spotify.com/";
+
+// What spotify: URIs and open.spotify.com links can point to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpotifyLink {
+    Track(String),
+    Album(String),
+    Artist(String),
+    Playlist(String),
+    User(String),
+}
+
+impl SpotifyLink {
+    fn from_parts(kind: &str, id: &str) -> Option<Self> {
+        let id = id.to_string();
+        if id.is_empty() {
+            return None;
+        }
+        match kind {
+            "track" => Some(Self::Track(id)),
+            "album" => Some(Self::Album(id)),
+            "artist" => Some(Self::Artist(id)),
+            "playlist" => Some(Self::Playlist(id)),
+            "user" => Some(Self::User(id)),
+            _ => None,
+        }
+    }
+
+    // spotify:album:<id>, or https://open.spotify.com/album/<id>?si=... (possibly with an intl-xx/ in there)
+    pub fn parse(link: &str) -> Option<Self> {
+        let link = link.trim();
+        if let Some(uri) = link.strip_prefix("spotify:") {
+            // Might start with /// because of https://gitlab.gnome.org/GNOME/glib/-/issues/1886/
+            let parts: Vec<&str> = uri.trim_start_matches('/').split(':').collect();
+            return match parts[..] {
+                // Old style playlist URIs, still found in the wild
+                ["user", _, "playlist", id] => Self::from_parts("playlist", id),
+                [kind, id] => Self::from_parts(kind, id),
+                _ => None,
+            };
+        }
+
+        let path = link
+            .strip_prefix("https://")
+            .or_else(|| link.strip_prefix("http://"))
+            .unwrap_or(link)
+            .strip_prefix(WEB_LINK_PREFIX)?;
+        let path = path.split(['?', '#']).next()?;
+        let mut parts = path
+            .split('/')
+            .filter(|p| !p.is_empty() && !p.starts_with("intl-") && *p != "embed");
+        let kind = parts.next()?;
+        let id = parts.next()?;
+        Self::from_parts(kind, id)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ConnectPlayerState {
     pub is_playing: bool,
@@ -262,6 +540,17 @@ pub struct ConnectPlayerState {
     pub progress_ms: u32,
     pub repeat: RepeatMode,
     pub shuffle: bool,
+    // Percentage, if the device reported it
+    pub volume: Option<u8>,
+    // Ids of what's coming up next, not including the current song
+    pub queue: Vec<String>,
+}
+
+impl ConnectPlayerState {
+    // How many times to skip ahead to get to that song
+    pub fn skips_to(&self, id: &str) -> Option<usize> {
+        self.queue.iter().position(|s| s == id).map(|p| p + 1)
+    }
 }
 
 impl Default for ConnectPlayerState {
@@ -273,6 +562,8 @@ impl Default for ConnectPlayerState {
             progress_ms: 0,
             repeat: RepeatMode::None,
             shuffle: false,
+            volume: None,
+            queue: vec![],
         }
     }
 }
@@ -281,21 +572,73 @@ impl Default for ConnectPlayerState {
 mod tests {
 
     use super::*;
+    use crate::app::test_utils::song;
 
-    fn song(id: &str) -> SongDescription {
-        SongDescription {
-            id: id.to_string(),
-            uri: "".to_string(),
-            title: "Title".to_string(),
-            artists: vec![],
-            album: AlbumRef {
-                id: "".to_string(),
-                name: "".to_string(),
-            },
-            duration: 1000,
-            art: None,
-            track_number: None,
-        }
+    #[test]
+    fn test_skips_to_queued_song() {
+        let state = ConnectPlayerState {
+            queue: vec!["a".to_string(), "b".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(state.skips_to("b"), Some(2));
+        assert_eq!(state.skips_to("c"), None);
+    }
+
+    #[test]
+    fn test_group_session_link() {
+        let session = GroupSession {
+            id: "id".to_string(),
+            join_token: "abc123".to_string(),
+            is_owner: true,
+            members: vec![],
+        };
+        assert_eq!(
+            GroupSession::token_from_link(&session.invite_link()),
+            Some("abc123".to_string())
+        );
+        assert_eq!(
+            GroupSession::token_from_link(" https://open.spotify.com/socialsession/abc?si=x "),
+            Some("abc".to_string())
+        );
+        assert_eq!(
+            GroupSession::token_from_link("https://open.spotify.com/track/abc"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_spotify_link() {
+        let album = Some(SpotifyLink::Album("abc".to_string()));
+        assert_eq!(SpotifyLink::parse("spotify:album:abc"), album);
+        assert_eq!(SpotifyLink::parse("spotify:///album:abc"), album);
+        assert_eq!(
+            SpotifyLink::parse("https://open.spotify.com/album/abc?si=xyz"),
+            album
+        );
+        assert_eq!(
+            SpotifyLink::parse("https://open.spotify.com/intl-fr/album/abc"),
+            album
+        );
+        assert_eq!(
+            SpotifyLink::parse("spotify:user:someone:playlist:abc"),
+            Some(SpotifyLink::Playlist("abc".to_string()))
+        );
+        assert_eq!(
+            SpotifyLink::parse("https://open.spotify.com/track/abc"),
+            Some(SpotifyLink::Track("abc".to_string()))
+        );
+        assert_eq!(SpotifyLink::parse("spotify:episode:abc"), None);
+        assert_eq!(SpotifyLink::parse("https://example.com/album/abc"), None);
+        assert_eq!(SpotifyLink::parse("https://open.spotify.com/album/"), None);
+    }
+
+    #[test]
+    fn batches_covering_range() {
+        let offsets: Vec<usize> = Batch::covering(15, 45, 10, 40).map(|b| b.offset).collect();
+        assert_eq!(offsets, vec![10, 20, 30]);
+
+        assert_eq!(Batch::covering(20, 20, 10, 40).count(), 0);
+        assert_eq!(Batch::containing(39, 10, 40).offset, 30);
     }
 
     #[test]
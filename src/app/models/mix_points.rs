@@ -0,0 +1,64 @@
+// Where DJ mode leaves a song and picks up the next one (in ms), both on the start of a bar so that the cut lands on the beat
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MixPoints {
+    pub entry: u32,
+    pub exit: u32,
+}
+
+// Not worth mixing anything shorter than that
+const MIN_MIXED_LENGTH: f64 = 30.0;
+
+fn millis(seconds: f64) -> u32 {
+    (seconds * 1000.0).round() as u32
+}
+
+impl MixPoints {
+    // From the audio analysis (all in seconds): when the bars (or beats) and the sections start, and where the song fades in and out.
+    // The song is entered on the first bar after its fade in, and left on the last bar before it fades out,
+    // though never before its last section starts (the analysis can be a bit quick to call a fade out).
+    pub fn find(
+        grid: &[f64],
+        sections: &[f64],
+        end_of_fade_in: f64,
+        start_of_fade_out: f64,
+    ) -> Option<Self> {
+        let entry = grid.iter().copied().find(|&t| t >= end_of_fade_in)?;
+        let limit = sections
+            .last()
+            .copied()
+            .unwrap_or(0.0)
+            .max(start_of_fade_out);
+        let exit = grid.iter().copied().rev().find(|&t| t <= limit)?;
+        (exit - entry >= MIN_MIXED_LENGTH).then(|| Self {
+            entry: millis(entry),
+            exit: millis(exit),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_find_mix_points() {
+        let bars: Vec<f64> = (0..100).map(|i| 0.5 + 2.0 * i as f64).collect();
+
+        let mix = MixPoints::find(&bars, &[0.0, 60.0, 150.0], 3.0, 190.2);
+        assert_eq!(
+            mix,
+            Some(MixPoints {
+                entry: 4500,
+                exit: 188500
+            })
+        );
+
+        // The whole last section plays
+        let mix = MixPoints::find(&bars, &[0.0, 60.0, 150.0], 3.0, 100.0);
+        assert_eq!(mix.map(|m| m.exit), Some(148500));
+
+        assert_eq!(MixPoints::find(&[], &[], 0.0, 100.0), None);
+        assert_eq!(MixPoints::find(&bars[..10], &[], 0.0, 100.0), None);
+    }
+}
@@ -2,6 +2,18 @@
 mod main;
 pub use main::*;
 
+mod chapters;
+pub use chapters::*;
+
+mod blocklist;
+pub use blocklist::*;
+
+mod mix_points;
+pub use mix_points::*;
+
+mod playlist_changes;
+pub use playlist_changes::*;
+
 // UI models (GObject)
 mod songs;
 pub use songs::*;
@@ -43,6 +55,42 @@ impl From<&PlaylistDescription> for AlbumModel {
     }
 }
 
+impl From<&RecentlyViewed> for AlbumModel {
+    fn from(item: &RecentlyViewed) -> Self {
+        AlbumModel::new(
+            &item.subtitle,
+            &item.title,
+            None,
+            item.art.as_ref(),
+            &item.uri,
+        )
+    }
+}
+
+impl From<&NewRelease> for AlbumModel {
+    fn from(release: &NewRelease) -> Self {
+        AlbumModel::new(
+            &release.artist,
+            &release.title,
+            release.release_date.get(..4).and_then(|y| y.parse().ok()),
+            release.art.as_ref(),
+            &release.album_id,
+        )
+    }
+}
+
+impl From<&AudiobookDescription> for AlbumModel {
+    fn from(audiobook: &AudiobookDescription) -> Self {
+        AlbumModel::new(
+            &audiobook.authors_name(),
+            &audiobook.title,
+            None,
+            audiobook.art.as_ref(),
+            &audiobook.id,
+        )
+    }
+}
+
 impl From<PlaylistDescription> for PlaylistSummary {
     fn from(PlaylistDescription { id, title, .. }: PlaylistDescription) -> Self {
         Self { id, title }
@@ -0,0 +1,187 @@
+use super::{SongBatch, SongDescription};
+
+// What happened to a playlist between two versions of its tracklist
+#[derive(Clone, Debug, Default)]
+pub struct PlaylistChanges {
+    pub added: Vec<SongDescription>,
+    pub removed: Vec<SongDescription>,
+    // Still there, just somewhere else
+    pub moved: Vec<SongDescription>,
+}
+
+// Which songs of `before` and `after` are part of their longest common sequence (by id);
+// whatever's left out of it was either added, removed or moved
+fn common_sequence(
+    before: &[SongDescription],
+    after: &[SongDescription],
+) -> (Vec<bool>, Vec<bool>) {
+    let (n, m) = (before.len(), after.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if before[i].id == after[j].id {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut kept_before = vec![false; n];
+    let mut kept_after = vec![false; m];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if before[i].id == after[j].id {
+            kept_before[i] = true;
+            kept_after[j] = true;
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    (kept_before, kept_after)
+}
+
+// Past the last song both have in common, a window that doesn't reach the end of the playlist
+// can't tell songs that were removed from songs that were only pushed out of it
+fn known_until(kept: &[bool], truncated: bool) -> usize {
+    if !truncated {
+        return kept.len();
+    }
+    kept.iter()
+        .rposition(|kept| *kept)
+        .map(|i| i + 1)
+        .unwrap_or(0)
+}
+
+impl PlaylistChanges {
+    // Both batches start at the top of the playlist, but may not cover all of it
+    pub fn between_batches(before: &SongBatch, after: &SongBatch) -> Self {
+        let truncated = |b: &SongBatch| b.batch.offset + b.songs.len() < b.batch.total;
+        Self::between_windows(
+            &before.songs,
+            &after.songs,
+            truncated(before),
+            truncated(after),
+        )
+    }
+
+    fn between_windows(
+        before: &[SongDescription],
+        after: &[SongDescription],
+        before_truncated: bool,
+        after_truncated: bool,
+    ) -> Self {
+        let (kept_before, kept_after) = common_sequence(before, after);
+        // Songs added at the top push the last ones out of `after`, songs removed pull new ones into it
+        let known_before = known_until(&kept_before, after_truncated);
+        let known_after = known_until(&kept_after, before_truncated);
+
+        let mut removed: Vec<(usize, SongDescription)> = before
+            .iter()
+            .zip(kept_before)
+            .enumerate()
+            .filter(|(_, (_, kept))| !kept)
+            .map(|(i, (song, _))| (i, song.clone()))
+            .collect();
+
+        let mut added = vec![];
+        let mut moved = vec![];
+        for (i, (song, kept)) in after.iter().zip(kept_after).enumerate() {
+            if kept {
+                continue;
+            }
+            match removed.iter().position(|(_, s)| s.id == song.id) {
+                Some(index) => moved.push(removed.remove(index).1),
+                None if i < known_after => added.push(song.clone()),
+                None => {}
+            }
+        }
+
+        Self {
+            added,
+            removed: removed
+                .into_iter()
+                .filter(|(i, _)| *i < known_before)
+                .map(|(_, song)| song)
+                .collect(),
+            moved,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.added.len() + self.removed.len() + self.moved.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::models::Batch;
+    use crate::app::test_utils::song;
+
+    fn songs(ids: &[&str]) -> Vec<SongDescription> {
+        ids.iter().map(|id| song(id)).collect()
+    }
+
+    // Whole tracklists
+    fn between(before: &[SongDescription], after: &[SongDescription]) -> PlaylistChanges {
+        PlaylistChanges::between_windows(before, after, false, false)
+    }
+
+    fn ids(songs: &[SongDescription]) -> Vec<&str> {
+        songs.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_changes_between() {
+        let changes = between(&songs(&["a", "b", "c", "d"]), &songs(&["a", "c", "b", "e"]));
+        assert_eq!(ids(&changes.added), vec!["e"]);
+        assert_eq!(ids(&changes.removed), vec!["d"]);
+        assert_eq!(changes.moved.len(), 1);
+        assert_eq!(changes.count(), 3);
+
+        let same = songs(&["a", "b"]);
+        assert!(between(&same, &same).is_empty());
+    }
+
+    #[test]
+    fn test_changes_in_a_window() {
+        let window = |ids: &[&str]| SongBatch {
+            songs: songs(ids),
+            batch: Batch {
+                offset: 0,
+                batch_size: 3,
+                total: 10,
+            },
+        };
+
+        // "c" was only pushed out of the first batch
+        let changes =
+            PlaylistChanges::between_batches(&window(&["a", "b", "c"]), &window(&["n", "a", "b"]));
+        assert_eq!(ids(&changes.added), vec!["n"]);
+        assert!(changes.removed.is_empty());
+
+        // And "d" only pulled into it
+        let changes =
+            PlaylistChanges::between_batches(&window(&["a", "b", "c"]), &window(&["b", "c", "d"]));
+        assert!(changes.added.is_empty());
+        assert_eq!(ids(&changes.removed), vec!["a"]);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let changes = between(&songs(&["a", "b", "a"]), &songs(&["a", "b", "a", "a"]));
+        assert_eq!(ids(&changes.added), vec!["a"]);
+        assert!(changes.removed.is_empty());
+        assert!(changes.moved.is_empty());
+    }
+}
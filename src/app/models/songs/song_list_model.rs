@@ -69,6 +69,14 @@ impl SongListModel {
             .build()
     }
 
+    // For (potentially) huge lists: every row exists from the start, showing placeholders until loaded
+    pub fn new_sparse(batch_size: u32) -> Self {
+        glib::Object::builder()
+            .property("batch-size", batch_size)
+            .property("sparse", true)
+            .build()
+    }
+
     fn inner_mut(&mut self) -> RefMut<SongList> {
         self.imp().get_mut()
     }
@@ -93,7 +101,7 @@ impl SongListModel {
     where
         F: Fn(usize, &SongModel),
     {
-        for (i, song) in self.inner().iter().enumerate() {
+        for (i, song) in self.inner().iter_visible() {
             f(i, song);
         }
     }
@@ -122,14 +130,19 @@ impl SongListModel {
         self.inner().index(i).cloned()
     }
 
-    pub fn index_continuous(&self, i: usize) -> Option<SongModel> {
-        self.inner().index_continuous(i).cloned()
+    // The song at a given row of the list
+    pub fn index_visible(&self, i: usize) -> Option<SongModel> {
+        self.inner().index_visible(i).cloned()
     }
 
     pub fn song_batch_for(&self, i: usize) -> Option<SongBatch> {
         self.inner().song_batch_for(i)
     }
 
+    pub fn song_batches(&self) -> Vec<SongBatch> {
+        self.inner().song_batches()
+    }
+
     pub fn last_batch(&self) -> Option<Batch> {
         self.inner().last_batch()
     }
@@ -138,14 +151,34 @@ impl SongListModel {
         self.inner().needed_batch_for(i)
     }
 
-    pub fn partial_len(&self) -> usize {
-        self.inner().partial_len()
+    pub fn needed_batches_in(&self, start: usize, end: usize) -> Vec<Batch> {
+        self.inner().needed_batches_in(start, end)
+    }
+
+    pub fn visible_len(&self) -> usize {
+        self.inner().visible_len()
     }
 
     pub fn len(&self) -> usize {
         self.inner().len()
     }
 
+    pub fn partial_len(&self) -> usize {
+        self.inner().partial_len()
+    }
+
+    // Only known once all the songs are there
+    pub fn total_duration(&self) -> Option<u64> {
+        if !self.inner().is_complete() {
+            return None;
+        }
+        Some(
+            self.map_collect(|s| u64::from(s.duration))
+                .into_iter()
+                .sum(),
+        )
+    }
+
     pub fn append(&mut self, songs: Vec<SongDescription>) -> SongListModelPending {
         let range = self.inner_mut().append(songs);
         SongListModelPending::new(Some(range), self)
@@ -190,14 +223,31 @@ mod imp {
     pub struct SongListModel {
         #[property(get, set = Self::set_batch_size, name = "batch-size")]
         batch_size: Cell<u32>,
+        #[property(get, set = Self::set_sparse)]
+        sparse: Cell<bool>,
         song_list: RefCell<Option<SongList>>,
     }
 
     impl SongListModel {
         fn set_batch_size(&self, batch_size: u32) {
             self.batch_size.set(batch_size);
-            self.song_list
-                .replace(Some(SongList::new_sized(batch_size as usize)));
+            self.reset();
+        }
+
+        fn set_sparse(&self, sparse: bool) {
+            self.sparse.set(sparse);
+            self.reset();
+        }
+
+        // Both are construct time properties really, so there isn't anything to keep
+        fn reset(&self) {
+            let batch_size = self.batch_size.get() as usize;
+            let song_list = if self.sparse.get() {
+                SongList::new_sparse(batch_size)
+            } else {
+                SongList::new_sized(batch_size)
+            };
+            self.song_list.replace(Some(song_list));
         }
     }
 
@@ -229,13 +279,19 @@ mod imp {
         }
 
         fn n_items(&self) -> u32 {
-            self.get().partial_len() as u32
+            self.get().visible_len() as u32
         }
 
         fn item(&self, position: u32) -> Option<glib::Object> {
-            self.get()
-                .index_continuous(position as usize)
-                .map(|m| m.clone().upcast())
+            let song_list = self.get();
+            let position = position as usize;
+            match song_list.index_visible(position) {
+                Some(song) => Some(song.clone().upcast()),
+                None if song_list.is_sparse() && position < song_list.visible_len() => {
+                    Some(SongModel::placeholder().upcast())
+                }
+                None => None,
+            }
         }
     }
 
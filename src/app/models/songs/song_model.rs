@@ -4,7 +4,7 @@ use gio::prelude::*;
 use glib::{subclass::prelude::*, SignalHandlerId};
 use std::{cell::Ref, ops::Deref};
 
-use crate::app::components::utils::format_duration;
+use crate::app::format::format_duration;
 use crate::app::models::*;
 
 // UI model for a song
@@ -19,6 +19,33 @@ impl SongModel {
         o
     }
 
+    // Stands in for a song that hasn't been loaded yet
+    pub fn placeholder() -> Self {
+        Self::new(SongDescription {
+            id: String::new(),
+            uri: String::new(),
+            title: String::new(),
+            artists: vec![],
+            album: AlbumRef {
+                id: String::new(),
+                name: String::new(),
+            },
+            duration: 0,
+            art: None,
+            chapters: Default::default(),
+            is_playable: true,
+            disc_number: None,
+            preview_url: None,
+            added_at: None,
+            popularity: None,
+            track_number: None,
+        })
+    }
+
+    pub fn is_placeholder(&self) -> bool {
+        self.description().id.is_empty()
+    }
+
     pub fn set_playing(&self, is_playing: bool) {
         self.set_property("playing", is_playing);
     }
@@ -129,11 +129,15 @@ impl ListRangeUpdate {
 // Some operations are not very efficient. It might have been smarter to have different structures for our two use cases:
 // - fixed, batched sources (an album, a playlist)
 // - editable lists (queue)
+//
+// A sparse list is the exception to the above: it pretends to have all its songs from the start (that's `len`),
+// positions are absolute, and the UI shows placeholders for songs that haven't been loaded yet.
 #[derive(Clone, Debug)]
 pub struct SongList {
     total: usize,
     total_loaded: usize,
     batch_size: usize,
+    sparse: bool,
     last_batch_key: usize,
     // Here a batch has an index (key) and a list of associated song ids
     // Why not a Vec? We could have batch 1, 2, NOT 3, then 4
@@ -147,12 +151,24 @@ impl SongList {
             total: 0,
             total_loaded: 0,
             batch_size,
+            sparse: false,
             last_batch_key: 0,
             batches: Default::default(),
             indexed_songs: Default::default(),
         }
     }
 
+    pub fn new_sparse(batch_size: usize) -> Self {
+        Self {
+            sparse: true,
+            ..Self::new_sized(batch_size)
+        }
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        self.sparse
+    }
+
     pub fn batch_size(&self) -> usize {
         self.batch_size
     }
@@ -183,6 +199,25 @@ impl SongList {
         self.total
     }
 
+    // How many rows the UI should display
+    pub fn visible_len(&self) -> usize {
+        if self.sparse {
+            self.total
+        } else {
+            self.partial_len()
+        }
+    }
+
+    // Songs along with the row they're displayed at
+    pub fn iter_visible(&self) -> impl Iterator<Item = (usize, &SongModel)> {
+        let sparse = self.sparse;
+        let indexed_songs = &self.indexed_songs;
+        self.iter_ids_from(0)
+            .filter_map(move |(pos, id)| Some((pos, indexed_songs.get(id)?)))
+            .enumerate()
+            .map(move |(i, (pos, song))| if sparse { (pos, song) } else { (i, song) })
+    }
+
     fn iter_ids_from(&self, i: usize) -> impl Iterator<Item = (usize, &'_ String)> {
         let batch_size = self.batch_size;
         let index = i / batch_size;
@@ -226,13 +261,20 @@ impl SongList {
     }
 
     pub fn clear(&mut self) -> ListRangeUpdate {
-        let len = self.partial_len();
-        *self = Self::new_sized(self.batch_size);
+        let len = self.visible_len();
+        *self = Self {
+            sparse: self.sparse,
+            ..Self::new_sized(self.batch_size)
+        };
         ListRangeUpdate::removed(0, len)
     }
 
     pub fn remove(&mut self, ids: &[String]) -> ListRangeUpdate {
-        let len = self.total_loaded;
+        let len = self.visible_len();
+        if self.sparse {
+            // Batches after a gap would end up at the wrong position, forget them (they'll be loaded again)
+            self.truncate_to_contiguous();
+        }
         let mut batches = HashMap::<usize, Vec<String>>::default();
         self.iter_ids_from(0)
             .filter(|(_, s)| !ids.contains(s))
@@ -244,9 +286,48 @@ impl SongList {
         self.batches = batches;
         let removed = ids.len();
         self.total = self.total.saturating_sub(removed);
-        self.total_loaded = self.total_loaded.saturating_sub(removed);
+        if self.sparse {
+            self.drop_incomplete_last_batch();
+            self.total_loaded = self.batches.values().map(|b| b.len()).sum();
+        } else {
+            self.total_loaded = self.total_loaded.saturating_sub(removed);
+        }
         // Lazy computation of the affected range, basically assume everything has changed
-        ListRangeUpdate(0, len as i32, self.total_loaded as i32)
+        ListRangeUpdate(0, len as i32, self.visible_len() as i32)
+    }
+
+    fn truncate_to_contiguous(&mut self) {
+        let contiguous = (0..=self.last_batch_key)
+            .take_while(|i| self.batches.contains_key(i))
+            .count();
+        self.forget_batches(|k| k >= contiguous);
+    }
+
+    // A partial batch can't be completed later on (loading it again would be a no-op), unless it's really the end of the list
+    fn drop_incomplete_last_batch(&mut self) {
+        let key = self.last_batch_key;
+        let is_incomplete = self
+            .batches
+            .get(&key)
+            .map(|b| b.len() < self.batch_size && (key + 1) * self.batch_size < self.total)
+            .unwrap_or(false);
+        if is_incomplete {
+            self.forget_batches(|k| k == key);
+            self.last_batch_key = key.saturating_sub(1);
+        }
+    }
+
+    fn forget_batches(&mut self, predicate: impl Fn(usize) -> bool) {
+        let indexed_songs = &mut self.indexed_songs;
+        self.batches.retain(|k, ids| {
+            let forget = predicate(*k);
+            if forget {
+                ids.iter().for_each(|id| {
+                    indexed_songs.remove(id);
+                });
+            }
+            !forget
+        });
     }
 
     pub fn append(&mut self, songs: Vec<SongDescription>) -> ListRangeUpdate {
@@ -320,6 +401,7 @@ impl SongList {
         }
 
         let insertion_start = self.estimated_len(index);
+        let previous_total = self.total;
         let len = songs.len();
         let ids = songs
             .into_iter()
@@ -336,7 +418,15 @@ impl SongList {
         self.total_loaded += len;
         self.last_batch_key = usize::max(self.last_batch_key, index);
 
-        Some(ListRangeUpdate::inserted(insertion_start, len))
+        if !self.sparse {
+            Some(ListRangeUpdate::inserted(insertion_start, len))
+        } else if previous_total != self.total {
+            // First batch (or the playlist changed size), all rows are affected
+            Some(ListRangeUpdate(0, previous_total as i32, self.total as i32))
+        } else {
+            // Placeholders are replaced with actual songs
+            Some(ListRangeUpdate(batch.offset as i32, len as i32, len as i32))
+        }
     }
 
     fn index_mut(&mut self, i: usize) -> Option<&mut String> {
@@ -388,6 +478,15 @@ impl SongList {
             .and_then(move |id| self.indexed_songs.get(id))
     }
 
+    // The song displayed at row i, if loaded
+    pub fn index_visible(&self, i: usize) -> Option<&SongModel> {
+        if self.sparse {
+            self.index(i)
+        } else {
+            self.index_continuous(i)
+        }
+    }
+
     // Return the batch needed to access the song at index i (if it's not loaded yet)
     pub fn needed_batch_for(&self, i: usize) -> Option<Batch> {
         let total = self.total;
@@ -404,6 +503,20 @@ impl SongList {
         }
     }
 
+    // Batches that are still missing to display rows [start, end)
+    pub fn needed_batches_in(&self, start: usize, end: usize) -> Vec<Batch> {
+        let batch_size = self.batch_size;
+        Batch::covering(start, end, batch_size, self.total)
+            .filter(|b| !self.batches.contains_key(&(b.offset / batch_size)))
+            .collect()
+    }
+
+    // Whether every batch has been loaded, however many songs they actually had
+    // (unavailable tracks are dropped, so a batch can come back short)
+    pub fn is_complete(&self) -> bool {
+        self.needed_batches_in(0, self.total).is_empty()
+    }
+
     // Get the full song batch that contains i
     pub fn song_batch_for(&self, i: usize) -> Option<SongBatch> {
         let total = self.total;
@@ -423,6 +536,15 @@ impl SongList {
         })
     }
 
+    // All the batches loaded so far, in order
+    pub fn song_batches(&self) -> Vec<SongBatch> {
+        let mut keys: Vec<usize> = self.batches.keys().copied().collect();
+        keys.sort_unstable();
+        keys.into_iter()
+            .filter_map(|key| self.song_batch_for(key * self.batch_size))
+            .collect()
+    }
+
     // The last loaded batch
     pub fn last_batch(&self) -> Option<Batch> {
         if self.total_loaded == 0 {
@@ -445,6 +567,7 @@ impl SongList {
 mod tests {
 
     use super::*;
+    use crate::app::test_utils::song;
 
     const NO_CHANGE: ListRangeUpdate = ListRangeUpdate(0, 0, 0);
 
@@ -456,22 +579,6 @@ mod tests {
         }
     }
 
-    fn song(id: &str) -> SongDescription {
-        SongDescription {
-            id: id.to_string(),
-            uri: "".to_string(),
-            title: "Title".to_string(),
-            artists: vec![],
-            album: AlbumRef {
-                id: "".to_string(),
-                name: "".to_string(),
-            },
-            duration: 1000,
-            art: None,
-            track_number: None,
-        }
-    }
-
     fn batch(id: usize) -> SongBatch {
         let offset = id * 2;
         SongBatch {
@@ -649,6 +756,33 @@ mod tests {
         assert_eq!(batch.unwrap().batch.offset, 2);
     }
 
+    #[test]
+    fn test_complete_with_short_batches() {
+        let mut list = SongList::new_from_initial_batch(batch(0));
+        for id in 1..5 {
+            assert!(!list.is_complete());
+            let mut short = batch(id);
+            short.songs.pop();
+            list.add(short);
+        }
+        assert_eq!(list.partial_len(), 6);
+        assert!(list.is_complete());
+    }
+
+    #[test]
+    fn test_song_batches() {
+        let mut list = SongList::new_from_initial_batch(batch(0));
+        list.add(batch(3));
+        list.add(batch(1));
+
+        let offsets: Vec<usize> = list
+            .song_batches()
+            .into_iter()
+            .map(|b| b.batch.offset)
+            .collect();
+        assert_eq!(offsets, vec![0, 2, 6]);
+    }
+
     #[test]
     fn test_append() {
         let mut list = SongList::new_from_initial_batch(batch(0));
@@ -665,6 +799,50 @@ mod tests {
         assert!(list_iter.next().is_none());
     }
 
+    #[test]
+    fn test_sparse_add() {
+        let mut list = SongList::new_sparse(2);
+
+        let range = list.add(batch(0));
+        assert_eq!(range, Some(ListRangeUpdate(0, 0, 10)));
+        assert_eq!(list.visible_len(), 10);
+
+        let range = list.add(batch(3));
+        assert_eq!(range, Some(ListRangeUpdate(6, 2, 2)));
+        assert_eq!(list.partial_len(), 4);
+
+        assert!(list.index_visible(3).is_none());
+        assert_eq!(list.index_visible(6).unwrap().description().id, "song6");
+        assert_eq!(list.needed_batch_for(3).map(|b| b.offset), Some(2));
+
+        let positions: Vec<usize> = list.iter_visible().map(|(i, _)| i).collect();
+        assert_eq!(positions, vec![0, 1, 6, 7]);
+
+        let needed: Vec<usize> = list
+            .needed_batches_in(1, 20)
+            .into_iter()
+            .map(|b| b.offset)
+            .collect();
+        assert_eq!(needed, vec![2, 4, 8]);
+    }
+
+    #[test]
+    fn test_sparse_remove() {
+        let mut list = SongList::new_sparse(2);
+        list.add(batch(0));
+        list.add(batch(1));
+        list.add(batch(3));
+
+        list.remove(&["song0".to_string()]);
+
+        // Only [song1, song2] is still a full batch
+        assert_eq!(list.visible_len(), 9);
+        assert_eq!(list.partial_len(), 2);
+        assert_eq!(list.index_visible(0).unwrap().description().id, "song1");
+        assert!(list.index_visible(2).is_none());
+        assert_eq!(list.needed_batch_for(2).map(|b| b.offset), Some(2));
+    }
+
     #[test]
     fn test_swap() {
         let mut list = SongList::new_sized(10);
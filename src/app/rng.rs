@@ -1,5 +1,9 @@
 use rand::{rngs::SmallRng, RngCore, SeedableRng};
 
+// How many of the remaining indices are drawn for a weighted pick, so that a pick doesn't cost more with longer lists
+// (when there are fewer left than that, they're all candidates)
+const CANDIDATES: usize = 8;
+
 // A random, resizable mapping (i-th element to play => j-th track) used to handle shuffled playlists
 // It's lazy: initially we don't compute what index i maps to
 // It's resizable: if our playlist grows or shrinks, we have to keep the generated mappings stable
@@ -81,6 +85,64 @@ impl LazyRandomIndex {
         }
     }
 
+    // Same as next_until, see next_weighted
+    pub fn next_until_weighted<F>(&mut self, i: usize, weight: F) -> Option<usize>
+    where
+        F: Fn(Option<usize>, usize) -> f64,
+    {
+        if i >= self.indices.len() {
+            return None;
+        }
+
+        loop {
+            if self.generated > i {
+                break Some(self.indices[i]);
+            }
+            self.next_weighted(&weight);
+        }
+    }
+
+    // Generate the next mapping, drawing a few candidates and then picking one of them according to its weight,
+    // given the previously picked index (if any) and the candidate.
+    // When no candidate weighs anything, it's the same as a uniform pick.
+    pub fn next_weighted<F>(&mut self, weight: F) -> Option<usize>
+    where
+        F: Fn(Option<usize>, usize) -> f64,
+    {
+        let last = self.generated;
+        let left = self.indices.len().checked_sub(last).filter(|&n| n > 0)?;
+        let previous = last.checked_sub(1).map(|i| self.indices[i]);
+
+        let candidates: Vec<usize> = if left <= CANDIDATES {
+            (last..self.indices.len()).collect()
+        } else {
+            (0..CANDIDATES)
+                .map(|_| (self.rng.next_u64() as usize) % left + last)
+                .collect()
+        };
+        let weights: Vec<f64> = candidates
+            .iter()
+            .map(|&c| weight(previous, self.indices[c]).max(0.0))
+            .collect();
+        let total: f64 = weights.iter().sum();
+
+        let mut next = candidates[0];
+        if total > 0.0 {
+            let mut target = (self.rng.next_u64() as f64 / u64::MAX as f64) * total;
+            for (&candidate, &weight) in candidates.iter().zip(&weights) {
+                if weight <= 0.0 {
+                    continue;
+                }
+                next = candidate;
+                if target < weight {
+                    break;
+                }
+                target -= weight;
+            }
+        }
+        Some(self.pick_next(next))
+    }
+
     // Generate the next mapping
     pub fn next(&mut self) -> Option<usize> {
         if self.indices.len() < self.generated {
@@ -179,6 +241,23 @@ mod tests {
         assert_eq!(values, same_values);
     }
 
+    #[test]
+    fn test_weighted() {
+        let mut index = LazyRandomIndex::from(rng_for_test());
+        index.grow(CANDIDATES);
+
+        // Odd indices never get picked while there are even ones left
+        index.next_until_weighted(3, |_, i| if i % 2 == 0 { 1.0 } else { 0.0 });
+        let picked: Vec<usize> = (0..4).filter_map(|i| index.get(i)).collect();
+        assert!(picked.iter().all(|i| i % 2 == 0), "{:?}", picked);
+
+        // And with nothing to prefer, everything still comes up
+        index.next_until_weighted(CANDIDATES - 1, |_, _| 0.0);
+        let mut all: Vec<usize> = (0..CANDIDATES).filter_map(|i| index.get(i)).collect();
+        all.sort();
+        assert_eq!(all, (0..CANDIDATES).collect::<Vec<usize>>());
+    }
+
     #[test]
     fn test_reset() {
         let mut index = LazyRandomIndex::from(rng_for_test());
@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::app::state::{AppAction, AppEvent};
+
+pub const DEBUG_STATE_ENV_VAR: &str = "SPOT_DEBUG_STATE";
+pub const DEBUG_STATE_FLAG: &str = "debug-state";
+
+// Keeps the last few hundred updates around, enough to see what led to a broken UI
+const MAX_LOG_ENTRIES: usize = 500;
+
+// Opt-in logging of every action going through the state, the events it produced, and how long that took
+pub struct ActionLog {
+    enabled: bool,
+    started: Instant,
+    entries: VecDeque<String>,
+}
+
+impl ActionLog {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            started: Instant::now(),
+            entries: VecDeque::with_capacity(if enabled { MAX_LOG_ENTRIES } else { 0 }),
+        }
+    }
+
+    // Enabled with SPOT_DEBUG_STATE=1 or --debug-state
+    pub fn from_env() -> Self {
+        let flag = format!("--{DEBUG_STATE_FLAG}");
+        let enabled = std::env::var_os(DEBUG_STATE_ENV_VAR).is_some_and(|v| v != "0")
+            || std::env::args().any(|arg| arg == flag);
+        Self::new(enabled)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn record<F>(&mut self, action: AppAction, update: F) -> Vec<AppEvent>
+    where
+        F: FnOnce(AppAction) -> Vec<AppEvent>,
+    {
+        if !self.enabled {
+            return update(action);
+        }
+
+        let description = describe_action(&action);
+        let start = Instant::now();
+        let events = update(action);
+        let elapsed = start.elapsed();
+
+        let entry = format!(
+            "[{:>10.3}s] {} ({}µs) -> [{}]",
+            start.duration_since(self.started).as_secs_f64(),
+            description,
+            elapsed.as_micros(),
+            events
+                .iter()
+                .map(describe_event)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        info!("{}", entry);
+
+        if self.entries.len() == MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        events
+    }
+
+    pub fn dump(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        for entry in self.entries.iter() {
+            writeln!(file, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+// Login stuff carries credentials and tokens, which have no business ending up in a log file
+fn describe_action(action: &AppAction) -> String {
+    match action {
+        AppAction::LoginAction(_) => "LoginAction(..)".to_string(),
+        _ => format!("{action:?}"),
+    }
+}
+
+fn describe_event(event: &AppEvent) -> String {
+    match event {
+        AppEvent::LoginEvent(_) => "LoginEvent(..)".to_string(),
+        _ => format!("{event:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::state::LoginAction;
+
+    #[test]
+    fn test_disabled_log_records_nothing() {
+        let mut log = ActionLog::new(false);
+        let events = log.record(AppAction::Raise, |_| vec![AppEvent::Raised]);
+        assert_eq!(events.len(), 1);
+        assert!(log.entries.is_empty());
+    }
+
+    #[test]
+    fn test_log_keeps_latest_entries() {
+        let mut log = ActionLog::new(true);
+        for _ in 0..(MAX_LOG_ENTRIES + 10) {
+            log.record(AppAction::Raise, |_| vec![AppEvent::Raised]);
+        }
+        log.record(AppAction::ViewNowPlaying, |_| vec![]);
+
+        assert_eq!(log.entries.len(), MAX_LOG_ENTRIES);
+        let last = log.entries.back().unwrap();
+        assert!(last.contains("ViewNowPlaying"));
+        assert!(last.ends_with("-> []"));
+    }
+
+    #[test]
+    fn test_login_is_redacted() {
+        let mut log = ActionLog::new(true);
+        let action = LoginAction::SetRefreshedToken {
+            token: "secret".to_string(),
+            token_expiry_time: std::time::SystemTime::now(),
+        };
+        log.record(action.into(), |_| vec![]);
+
+        assert!(!log.entries[0].contains("secret"));
+    }
+}
@@ -1,7 +1,11 @@
 use crate::api::SpotifyApiClient;
-use crate::app::{state::*, BatchLoader};
+use crate::app::{
+    state::action_log::ActionLog, state::snapshot::StateSnapshot, state::*, BatchLoader,
+};
+use gettextrs::*;
 use ref_filter_map::*;
-use std::cell::{Ref, RefCell};
+use std::cell::{Cell, Ref, RefCell};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 pub struct AppServices {
@@ -14,6 +18,11 @@ pub struct AppServices {
 pub struct AppModel {
     state: RefCell<AppState>,
     services: AppServices,
+    action_log: RefCell<ActionLog>,
+    // Snapshot to restore once logged in, if any
+    pending_snapshot: RefCell<Option<StateSnapshot>>,
+    // Whether to pick up last session's queue once we know whose it is, and whether to play it
+    pending_last_session: Cell<Option<bool>>,
 }
 
 impl AppModel {
@@ -23,7 +32,15 @@ impl AppModel {
             spotify_api,
         };
         let state = RefCell::new(state);
-        Self { state, services }
+        let action_log = RefCell::new(ActionLog::from_env());
+        let pending_snapshot = RefCell::new(StateSnapshot::from_args());
+        Self {
+            state,
+            services,
+            action_log,
+            pending_snapshot,
+            pending_last_session: Cell::new(None),
+        }
     }
 
     pub fn get_spotify(&self) -> Arc<dyn SpotifyApiClient + Send + Sync> {
@@ -69,11 +86,108 @@ impl AppModel {
             AppAction::LoginAction(LoginAction::SetRefreshedToken { token, .. }) => {
                 self.services.spotify_api.update_token(token.clone());
             }
+            AppAction::DumpActionLog => return vec![self.dump_action_log()],
+            AppAction::SaveStateSnapshot => return vec![self.save_state_snapshot()],
             _ => {}
         }
 
         // And this is the only mutable borrow of our state!
         let mut state = self.state.borrow_mut();
-        state.update_state(action)
+        let mut action_log = self.action_log.borrow_mut();
+        let logging_out = matches!(action, AppAction::LoginAction(LoginAction::Logout))
+            .then(|| state.logged_user.user.clone())
+            .flatten();
+        let mut events = action_log.record(action, |action| state.update_state(action));
+
+        if let Some(user) = logging_out {
+            if let Err(e) = StateSnapshot::forget_last_session(&user) {
+                warn!("Could not forget the queue: {}", e);
+            }
+        }
+
+        // Replaying a snapshot involves loading stuff, so we need to be logged in first
+        let logged_in = events
+            .iter()
+            .any(|e| matches!(e, AppEvent::LoginEvent(LoginEvent::LoginCompleted(_))));
+        let snapshot = logged_in
+            .then(|| {
+                self.pending_snapshot.take().or_else(|| {
+                    let resume = self.pending_last_session.take()?;
+                    StateSnapshot::last_session(state.logged_user.user.as_deref()?, resume)
+                })
+            })
+            .flatten();
+        if let Some(snapshot) = snapshot {
+            info!("Restoring state snapshot");
+            for action in snapshot.into_actions() {
+                events.append(&mut action_log.record(action, |action| state.update_state(action)));
+            }
+        }
+        events
+    }
+
+    // The queue from last time, unless there's a snapshot to restore instead
+    pub fn restore_last_session(&self, resume: bool) {
+        if self.pending_snapshot.borrow().is_none() {
+            self.pending_last_session.set(Some(resume));
+        }
+    }
+
+    pub fn save_last_session(&self) {
+        let state = self.state.borrow();
+        let Some(user) = state.logged_user.user.as_deref() else {
+            return;
+        };
+        if let Err(e) = StateSnapshot::queue_of(&state).save_last_session(user) {
+            warn!("Could not save the queue for next time: {}", e);
+        }
+    }
+
+    fn save_state_snapshot(&self) -> AppEvent {
+        let path: PathBuf = glib::user_cache_dir()
+            .join("spot")
+            .join("state-snapshot.json");
+        // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+        if cfg!(debug_assertions) {
+            // translators: Notification shown after saving what the app was showing and playing to a file, meant to be attached to bug reports. The full text is "State snapshot written to <path>".
+            gettext("State snapshot written to {}");
+        }
+        let message = match StateSnapshot::of(&self.state.borrow()).save(&path) {
+            Ok(_) => gettext!("State snapshot written to {}", path.display()),
+            Err(e) => {
+                error!("Could not write state snapshot: {}", e);
+                // translators: Notification shown when saving the state snapshot (see "State snapshot written to {}") failed.
+                gettext("Could not write state snapshot")
+            }
+        };
+        AppEvent::NotificationShown(message)
+    }
+
+    fn dump_action_log(&self) -> AppEvent {
+        // this is just to fool xgettext, it doesn't like macros (or rust for that matter) :(
+        if cfg!(debug_assertions) {
+            // translators: Notification shown when asking for the log of state updates while it isn't kept. The full text is "State logging is off, start with --debug-state or SPOT_DEBUG_STATE=1", do not translate those two.
+            gettext("State logging is off, start with --{} or {}=1");
+            // translators: Notification shown after writing the log of state updates to a file, meant to be attached to bug reports. The full text is "State log written to <path>".
+            gettext("State log written to {}");
+        }
+        let log = self.action_log.borrow();
+        if !log.is_enabled() {
+            return AppEvent::NotificationShown(gettext!(
+                "State logging is off, start with --{} or {}=1",
+                DEBUG_STATE_FLAG,
+                DEBUG_STATE_ENV_VAR
+            ));
+        }
+        let path: PathBuf = glib::user_cache_dir().join("spot").join("state.log");
+        let message = match log.dump(&path) {
+            Ok(_) => gettext!("State log written to {}", path.display()),
+            Err(e) => {
+                error!("Could not write state log: {}", e);
+                // translators: Notification shown when writing the log of state updates (see "State log written to {}") failed.
+                gettext("Could not write state log")
+            }
+        };
+        AppEvent::NotificationShown(message)
     }
 }
@@ -1,14 +1,18 @@
 use std::borrow::Cow;
 
-use crate::app::models::{PlaylistDescription, PlaylistSummary};
+use crate::app::health::{Backend, Health};
+use crate::app::lock::{self, Lock};
+use crate::app::models::{ChapterDescription, PlaylistDescription, PlaylistSummary, SpotifyLink};
 use crate::app::state::{
     browser_state::{BrowserAction, BrowserEvent, BrowserState},
     login_state::{LoginAction, LoginEvent, LoginState},
     playback_state::{PlaybackAction, PlaybackEvent, PlaybackState},
     selection_state::{SelectionAction, SelectionContext, SelectionEvent, SelectionState},
     settings_state::{SettingsAction, SettingsEvent, SettingsState},
+    undo_state::{UndoState, Undoable},
     ScreenName, UpdatableState,
 };
+use crate::app::AppError;
 
 // It's a big one...
 // All possible actions!
@@ -25,7 +29,11 @@ pub enum AppAction {
     Start,
     Raise,
     ShowNotification(String),
+    // Failed loads and the like, see call_spotify_and_dispatch
+    ShowError(AppError),
     ViewNowPlaying,
+    // See QuickOpen
+    ShowQuickOpen,
     // Cross-state actions
     QueueSelection,
     DequeueSelection,
@@ -37,32 +45,36 @@ pub enum AppAction {
     CancelSelection,
     CreatePlaylist(PlaylistDescription),
     UpdatePlaylistName(PlaylistSummary),
+    Undo,
+    Redo,
+    // What a redo did once it went through, see UndoHandler
+    Redone(Undoable, Box<AppAction>),
+    DumpActionLog,
+    SaveStateSnapshot,
+    // Tracks need fetching before they can be played, see TrackOpener
+    OpenTrack(String),
+    // Where Spotify says chapters were left, see ResumeTracker
+    SyncResumePoints(Vec<ChapterDescription>),
+    // We're back online after losing the network for a while, see NetworkWatcher
+    NetworkRestored,
+    // None when unlocked, see LockGuard
+    SetLock(Option<Lock>),
+    // Whether that connection to Spotify works, see app::health
+    SetBackendHealth(Backend, bool),
 }
 
 // Not actual actions, just neat wrappers
 impl AppAction {
-    // An action to open a Spotify URI
+    // An action to open a Spotify URI or link
     #[allow(non_snake_case)]
     pub fn OpenURI(uri: String) -> Option<Self> {
         debug!("parsing {}", &uri);
-        let mut parts = uri.split(':');
-        if parts.next()? != "spotify" {
-            return None;
-        }
-
-        // Might start with /// because of https://gitlab.gnome.org/GNOME/glib/-/issues/1886/
-        let action = parts
-            .next()?
-            .strip_prefix("///")
-            .filter(|p| !p.is_empty())?;
-        let data = parts.next()?;
-
-        match action {
-            "album" => Some(Self::ViewAlbum(data.to_string())),
-            "artist" => Some(Self::ViewArtist(data.to_string())),
-            "playlist" => Some(Self::ViewPlaylist(data.to_string())),
-            "user" => Some(Self::ViewUser(data.to_string())),
-            _ => None,
+        match SpotifyLink::parse(&uri)? {
+            SpotifyLink::Album(id) => Some(Self::ViewAlbum(id)),
+            SpotifyLink::Artist(id) => Some(Self::ViewArtist(id)),
+            SpotifyLink::Playlist(id) => Some(Self::ViewPlaylist(id)),
+            SpotifyLink::User(id) => Some(Self::ViewUser(id)),
+            SpotifyLink::Track(id) => Some(Self::OpenTrack(id)),
         }
     }
 
@@ -103,9 +115,19 @@ pub enum AppEvent {
     Started,
     Raised,
     NotificationShown(String),
+    ErrorShown(AppError),
     PlaylistCreatedNotificationShown(String),
     NowPlayingShown,
+    QuickOpenShown,
     SettingsEvent(SettingsEvent),
+    UndoableActionDone(Undoable),
+    UndoRequested(Undoable),
+    RedoRequested(Undoable),
+    TrackOpenRequested(String),
+    ResumePointsSynced(Vec<ChapterDescription>),
+    NetworkRestored,
+    LockChanged(bool),
+    HealthChanged(Health),
 }
 
 // The actual state, split five-ways
@@ -116,6 +138,9 @@ pub struct AppState {
     pub selection: SelectionState,
     pub logged_user: LoginState,
     pub settings: SettingsState,
+    undo: UndoState,
+    lock: Option<Lock>,
+    health: Health,
 }
 
 impl AppState {
@@ -127,10 +152,43 @@ impl AppState {
             selection: Default::default(),
             logged_user: Default::default(),
             settings: Default::default(),
+            undo: Default::default(),
+            lock: None,
+            health: Health::default(),
         }
     }
 
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    pub fn health(&self) -> Health {
+        self.health
+    }
+
     pub fn update_state(&mut self, message: AppAction) -> Vec<AppEvent> {
+        if let Some(lock) = self.lock.as_ref().filter(|lock| !lock.allows(&message)) {
+            debug!("Refused while locked ({:?}): {:?}", lock, message);
+            return vec![AppEvent::NotificationShown(lock::refused_message())];
+        }
+        // Made again after an undo: it goes back on the undo stack, without forgetting what's left to redo
+        if let AppAction::Redone(undoable, action) = message {
+            let mut events = self.apply_action(*action);
+            self.undo.push_redone(undoable.clone());
+            events.push(AppEvent::UndoableActionDone(undoable));
+            return events;
+        }
+        // Destructive actions get remembered, so that they can be reverted for a little while
+        let undoable = Undoable::for_action(&message, &self.selection);
+        let mut events = self.apply_action(message);
+        if let Some(undoable) = undoable.filter(|_| !events.is_empty()) {
+            self.undo.push(undoable.clone());
+            events.push(AppEvent::UndoableActionDone(undoable));
+        }
+        events
+    }
+
+    fn apply_action(&mut self, message: AppAction) -> Vec<AppEvent> {
         match message {
             AppAction::Start if !self.started => {
                 self.started = true;
@@ -139,15 +197,40 @@ impl AppState {
             // Couple of actions that don't mutate the state (not intested in keeping track of what they change)
             // they're here just to have a consistent way of doing things (always an Action)
             AppAction::ShowNotification(c) => vec![AppEvent::NotificationShown(c)],
+            AppAction::ShowError(error) => vec![AppEvent::ErrorShown(error)],
             AppAction::ViewNowPlaying => vec![AppEvent::NowPlayingShown],
+            AppAction::ShowQuickOpen => vec![AppEvent::QuickOpenShown],
             AppAction::Raise => vec![AppEvent::Raised],
+            AppAction::OpenTrack(id) => vec![AppEvent::TrackOpenRequested(id)],
+            AppAction::SyncResumePoints(chapters) => vec![AppEvent::ResumePointsSynced(chapters)],
+            AppAction::NetworkRestored => vec![AppEvent::NetworkRestored],
+            AppAction::SetLock(lock) if lock != self.lock => {
+                self.lock = lock;
+                vec![AppEvent::LockChanged(self.lock.is_some())]
+            }
+            AppAction::SetBackendHealth(backend, up) if self.health.set(backend, up) => {
+                vec![AppEvent::HealthChanged(self.health)]
+            }
+            // The state itself isn't reverted here: the inverse API call is made first, and its result dispatched
+            AppAction::Undo => self
+                .undo
+                .pop()
+                .map(|undoable| vec![AppEvent::UndoRequested(undoable)])
+                .unwrap_or_default(),
+            AppAction::Redo => self
+                .undo
+                .pop_undone()
+                .map(|undoable| vec![AppEvent::RedoRequested(undoable)])
+                .unwrap_or_default(),
             // Cross-state actions: multiple "substates" are affected by these actions, that's why they're handled here
             // Might need some clean-up
             AppAction::QueueSelection => {
-                self.playback.queue(self.selection.take_selection());
+                let tracks = self.selection.take_selection();
+                let ids = self.playback.queue(tracks);
                 vec![
                     SelectionEvent::SelectionModeChanged(false).into(),
                     PlaybackEvent::PlaylistChanged.into(),
+                    PlaybackEvent::TracksQueued(ids).into(),
                 ]
             }
             AppAction::DequeueSelection => {
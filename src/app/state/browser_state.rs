@@ -13,30 +13,49 @@ pub enum BrowserAction {
     SetHomeVisiblePage(&'static str),
     SetLibraryContent(Vec<AlbumDescription>),
     PrependPlaylistsContent(Vec<PlaylistDescription>),
-    AppendLibraryContent(Vec<AlbumDescription>),
+    AppendLibraryContent(Paged<AlbumDescription>),
     SetPlaylistsContent(Vec<PlaylistDescription>),
     AppendPlaylistsContent(Vec<PlaylistDescription>),
     RemoveTracksFromPlaylist(String, Vec<String>),
+    SetPlaylistSnapshot(String, String),
     SetAlbumDetails(Box<AlbumFullDescription>),
     AppendAlbumTracks(String, Box<SongBatch>),
     SetPlaylistDetails(Box<PlaylistDescription>, Box<SongBatch>),
     UpdatePlaylistName(PlaylistSummary),
     AppendPlaylistTracks(String, Box<SongBatch>),
+    // Sent once all the batches asked for at once have come back (or failed)
+    FinishLoadingPlaylistTracks(String),
+    // For a playlist that changed since it was cached: what changed, and the songs to show once that's been seen
+    SetPlaylistChanges(String, Box<PlaylistChanges>, Box<SongBatch>),
+    ApplyPlaylistChanges(String),
     Search(String),
     SetSearchResults(Box<SearchResults>),
     SetArtistDetails(Box<ArtistDescription>),
     AppendArtistReleases(String, Vec<AlbumDescription>),
+    SetArtistConcerts(String, Vec<ConcertDescription>),
     NavigationPush(ScreenName),
     NavigationPop,
     NavigationPopTo(ScreenName),
     SaveAlbum(Box<AlbumDescription>),
     UnsaveAlbum(String),
+    FollowArtist(String),
+    UnfollowArtist(String),
     SetUserDetails(Box<UserDescription>),
     AppendUserPlaylists(String, Vec<PlaylistDescription>),
     SetSavedTracks(Box<SongBatch>),
     AppendSavedTracks(Box<SongBatch>),
     SaveTracks(Vec<SongDescription>),
     RemoveSavedTracks(Vec<String>),
+    SetHomeShelves(Vec<HomeShelf>),
+    AppendHomeShelf(String, Vec<PlaylistDescription>),
+    SetPinnedPlaylists(Vec<PlaylistDescription>),
+    SetRecentlyViewed(Vec<RecentlyViewed>),
+    SetNewReleases(Vec<NewRelease>),
+    SetSavedAudiobooks(Vec<AudiobookDescription>),
+    AppendSavedAudiobooks(Vec<AudiobookDescription>),
+    // Hard refresh of the current screen: its cache gets revalidated (see ScreenRefresher), then it's reloaded
+    Refresh,
+    Reload(ScreenName),
 }
 
 impl From<BrowserAction> for AppAction {
@@ -55,17 +74,30 @@ pub enum BrowserEvent {
     AlbumTracksAppended(String),
     PlaylistDetailsLoaded(String),
     PlaylistTracksAppended(String),
+    PlaylistTracksLoaded(String),
     PlaylistTracksRemoved(String),
+    PlaylistChangesUpdated(String),
     SearchUpdated,
     SearchResultsUpdated,
     ArtistDetailsUpdated(String),
+    ArtistConcertsUpdated(String),
     NavigationPushed(ScreenName),
     NavigationPopped,
     NavigationPoppedTo(ScreenName),
     AlbumSaved(String),
     AlbumUnsaved(String),
+    ArtistFollowed(String),
+    ArtistUnfollowed(String),
     UserDetailsUpdated(String),
     SavedTracksUpdated,
+    HomeShelvesUpdated,
+    HomeShelfAppended(String),
+    PinnedPlaylistsUpdated,
+    RecentlyViewedUpdated,
+    NewReleasesUpdated,
+    SavedAudiobooksUpdated,
+    RefreshRequested(ScreenName),
+    ReloadRequested(ScreenName),
 }
 
 impl From<BrowserEvent> for AppEvent {
@@ -158,6 +190,10 @@ where
         self.0.iter_mut().rev()
     }
 
+    fn iter(&self) -> impl Iterator<Item = &Screen> {
+        self.0.iter()
+    }
+
     fn iter_rev(&self) -> impl Iterator<Item = &Screen> {
         self.0.iter().rev()
     }
@@ -250,6 +286,11 @@ impl BrowserState {
         self.navigation.current().name()
     }
 
+    // From the bottom of the stack (always home) to the current screen
+    pub fn screen_names(&self) -> impl Iterator<Item = &ScreenName> {
+        self.navigation.iter().map(|screen| screen.name())
+    }
+
     pub fn can_pop(&self) -> bool {
         self.navigation.can_pop() || self.navigation_hidden
     }
@@ -332,6 +373,12 @@ impl UpdatableState for BrowserState {
                 events
             }
             BrowserAction::NavigationPush(name) => self.push_if_needed(name),
+            BrowserAction::Refresh => {
+                vec![BrowserEvent::RefreshRequested(
+                    self.current_screen().clone(),
+                )]
+            }
+            BrowserAction::Reload(name) => vec![BrowserEvent::ReloadRequested(name.clone())],
             BrowserAction::NavigationPopTo(name) => {
                 self.navigation.pop_to(name);
                 vec![BrowserEvent::NavigationPoppedTo(name.clone())]
@@ -376,6 +423,23 @@ pub mod tests {
         assert_eq!(state.artist_state("some_id").is_some(), true);
     }
 
+    #[test]
+    fn test_refresh_current_screen() {
+        let mut state = BrowserState::new();
+        let events = state.update_with(Cow::Owned(BrowserAction::Refresh));
+        assert_eq!(
+            events,
+            vec![BrowserEvent::RefreshRequested(ScreenName::Home)]
+        );
+
+        let new_screen = ScreenName::PlaylistDetails("some_id".to_string());
+        state.update_with(Cow::Owned(BrowserAction::NavigationPush(
+            new_screen.clone(),
+        )));
+        let events = state.update_with(Cow::Owned(BrowserAction::Refresh));
+        assert_eq!(events, vec![BrowserEvent::RefreshRequested(new_screen)]);
+    }
+
     #[test]
     fn test_navigation_pop() {
         let mut state = BrowserState::new();
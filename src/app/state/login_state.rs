@@ -1,5 +1,6 @@
 use gettextrs::*;
 use std::borrow::Cow;
+use std::path::PathBuf;
 use std::time::SystemTime;
 
 use crate::app::credentials::Credentials;
@@ -10,6 +11,8 @@ use crate::app::state::{AppAction, AppEvent, UpdatableState};
 pub enum TryLoginAction {
     Token { username: String, token: String },
     OAuthSpotify {},
+    // A credentials.json left by librespot or spotifyd
+    ImportCredentials(PathBuf),
 }
 
 #[derive(Clone, Debug)]
@@ -45,6 +48,7 @@ impl From<LoginAction> for AppAction {
 pub enum LoginStartedEvent {
     Token { username: String, token: String },
     OAuthSpotify {},
+    ImportCredentials(PathBuf),
 }
 
 #[derive(Clone, Debug)]
@@ -140,6 +144,9 @@ impl UpdatableState for LoginState {
             LoginAction::TryLogin(TryLoginAction::OAuthSpotify { .. }) => {
                 vec![LoginEvent::LoginStarted(LoginStartedEvent::OAuthSpotify {}).into()]
             }
+            LoginAction::TryLogin(TryLoginAction::ImportCredentials(path)) => {
+                vec![LoginEvent::LoginStarted(LoginStartedEvent::ImportCredentials(path)).into()]
+            }
         }
     }
 }
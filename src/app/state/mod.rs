@@ -1,3 +1,4 @@
+mod action_log;
 mod app_model;
 mod app_state;
 mod browser_state;
@@ -7,7 +8,10 @@ mod playback_state;
 mod screen_states;
 mod selection_state;
 mod settings_state;
+mod snapshot;
+mod undo_state;
 
+pub use action_log::{DEBUG_STATE_ENV_VAR, DEBUG_STATE_FLAG};
 pub use app_model::AppModel;
 pub use app_state::*;
 pub use browser_state::*;
@@ -16,6 +20,8 @@ pub use playback_state::*;
 pub use screen_states::*;
 pub use selection_state::*;
 pub use settings_state::*;
+pub use snapshot::RESTORE_STATE_FLAG;
+pub use undo_state::*;
 
 pub trait UpdatableState {
     type Action: Clone;
@@ -1,3 +1,5 @@
+use crate::app::models::PageToken;
+
 // A structure for batched queries that I introduced before proper batch management
 // Still used to load album lists for instance
 // Doesn't know how many elements exist in total ahead of time
@@ -7,9 +9,9 @@ where
     T: Clone,
 {
     pub data: T,
-    // The next offset (of things to load) is set to None whenever we get less than we asked for
-    // as it probably means we've reached the end of some list
-    pub next_offset: Option<usize>,
+    // Where the next page (of things to load) starts. When we only have offsets to go by, it's set to None
+    // whenever we get less than we asked for, as it probably means we've reached the end of some list
+    pub next: Option<PageToken>,
     pub batch_size: usize,
 }
 
@@ -20,41 +22,53 @@ where
     pub fn new(data: T, batch_size: usize) -> Self {
         Self {
             data,
-            next_offset: Some(0),
+            next: Some(PageToken::Offset(0)),
             batch_size,
         }
     }
 
+    pub fn next_offset(&self) -> Option<usize> {
+        match self.next {
+            Some(PageToken::Offset(offset)) => Some(offset),
+            _ => None,
+        }
+    }
+
     pub fn reset_count(&mut self, new_length: usize) {
-        self.next_offset = if new_length >= self.batch_size {
-            Some(self.batch_size)
+        self.next = if new_length >= self.batch_size {
+            Some(PageToken::Offset(self.batch_size))
         } else {
             None
         }
     }
 
     pub fn set_loaded_count(&mut self, loaded_count: usize) {
-        if let Some(offset) = self.next_offset.take() {
-            self.next_offset = if loaded_count >= self.batch_size {
-                Some(offset + self.batch_size)
+        if let Some(offset) = self.next_offset() {
+            self.next = if loaded_count >= self.batch_size {
+                Some(PageToken::Offset(offset + self.batch_size))
             } else {
                 None
             }
         }
     }
 
+    // For pages that tell where the next one starts, no guessing needed
+    pub fn set_next(&mut self, next: Option<PageToken>) {
+        self.next = next;
+    }
+
     // If we remove elements from paginated data without refetching from the source,
-    // we have to adjust the next offset to load
+    // we have to adjust the next offset to load (cursors don't move)
     pub fn decrement(&mut self) {
-        if let Some(offset) = self.next_offset.take() {
-            self.next_offset = Some(offset - 1);
+        if let Some(offset) = self.next_offset() {
+            self.next = Some(PageToken::Offset(offset - 1));
         }
     }
 
     // Same idea as decrement
     pub fn increment(&mut self) {
-        if let Some(offset) = self.next_offset.take() {
-            self.next_offset = Some(offset + 1);
+        if let Some(offset) = self.next_offset() {
+            self.next = Some(PageToken::Offset(offset + 1));
         }
     }
 }
@@ -1,14 +1,41 @@
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
 use crate::app::models::*;
 use crate::app::state::{AppAction, AppEvent, UpdatableState};
 use crate::app::{BatchQuery, LazyRandomIndex, SongsSource};
 
+// Only for this session, and only so far back
+const HISTORY_SIZE: usize = 100;
+
+// Off unless turned on in the preferences
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueLimits {
+    pub no_duplicates: bool,
+    // Songs that already played make room first, oldest first
+    pub max_length: Option<usize>,
+}
+
+impl QueueLimits {
+    // What's left of some tracks once the ones already queued (or repeated) are set aside
+    fn admit(&self, queued: Vec<String>, tracks: Vec<SongDescription>) -> Vec<SongDescription> {
+        if !self.no_duplicates {
+            return tracks;
+        }
+        let mut seen: HashSet<String> = queued.into_iter().collect();
+        tracks
+            .into_iter()
+            .filter(|t| seen.insert(t.id.clone()))
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 pub struct PlaybackState {
     available_devices: Vec<ConnectDevice>,
     current_device: Device,
+    group_session: Option<GroupSession>,
     // A mapping of indices for shuffled playback
     index: LazyRandomIndex,
     // The actual list like thing backing the currently playing tracks
@@ -19,6 +46,91 @@ pub struct PlaybackState {
     repeat: RepeatMode,
     is_playing: bool,
     is_shuffled: bool,
+    shuffle_mode: ShuffleMode,
+    // What actually played before the current song, most recent last
+    history: Vec<SongDescription>,
+    // From the listening history, for ShuffleMode::LeastRecentlyPlayed: tracks ranked by when they were last heard, 0 being the latest
+    heard_rank: HashMap<String, usize>,
+    // How far into a song going back restarts it instead, see play_prev
+    restart_threshold: Option<u32>,
+    // When librespot can't play full songs (no Premium, or not logged in yet), see PreviewPlayer
+    previews_only: bool,
+    blocklist: Blocklist,
+    // Only enforced on songs queued or loaded all at once, paged sources are positional and left as they are
+    queue_limits: QueueLimits,
+}
+
+// What the songs weigh when picking the next one with a ShuffleMode other than uniform
+struct ShuffleWeights<'a> {
+    mode: ShuffleMode,
+    songs: &'a SongListModel,
+    history: &'a [SongDescription],
+    heard_rank: &'a HashMap<String, usize>,
+    album_sizes: HashMap<String, usize>,
+}
+
+impl<'a> ShuffleWeights<'a> {
+    fn new(
+        mode: ShuffleMode,
+        songs: &'a SongListModel,
+        history: &'a [SongDescription],
+        heard_rank: &'a HashMap<String, usize>,
+    ) -> Self {
+        let mut album_sizes = HashMap::new();
+        if mode == ShuffleMode::AlbumWeighted {
+            for album in songs.map_collect(|s| s.album.id) {
+                *album_sizes.entry(album).or_insert(0) += 1;
+            }
+        }
+        Self {
+            mode,
+            songs,
+            history,
+            heard_rank,
+            album_sizes,
+        }
+    }
+
+    // Songs that aren't loaded yet are given the benefit of the doubt
+    fn weight(&self, previous: Option<usize>, candidate: usize) -> f64 {
+        let Some(song) = self.songs.index(candidate) else {
+            return 1.0;
+        };
+        let song = song.description();
+        match self.mode {
+            ShuffleMode::Uniform => 1.0,
+            ShuffleMode::ArtistSpread => {
+                let previous = previous.and_then(|i| self.songs.index(i));
+                let same_artist = previous
+                    .map(|p| {
+                        p.description()
+                            .artists
+                            .iter()
+                            .any(|a| song.artists.iter().any(|b| a.id == b.id))
+                    })
+                    .unwrap_or(false);
+                if same_artist {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            ShuffleMode::AlbumWeighted => {
+                1.0 / *self.album_sizes.get(&song.album.id).unwrap_or(&1) as f64
+            }
+            ShuffleMode::LeastRecentlyPlayed => {
+                // Heard during this session, so more recent than anything in the listening history
+                if self.history.iter().any(|s| s.id == song.id) {
+                    return 0.01;
+                }
+                let Some(rank) = self.heard_rank.get(&song.id) else {
+                    return 1.0;
+                };
+                let age = (*rank + 1) as f64 / (self.heard_rank.len() + 1) as f64;
+                age * age
+            }
+        }
+    }
 }
 
 // Most mutatings methods shouldn't be pub
@@ -32,14 +144,31 @@ impl PlaybackState {
         self.is_playing && self.list_position.is_some()
     }
 
+    pub fn previews_only(&self) -> bool {
+        self.previews_only
+    }
+
+    pub fn blocklist(&self) -> &Blocklist {
+        &self.blocklist
+    }
+
     pub fn is_shuffled(&self) -> bool {
         self.is_shuffled
     }
 
+    pub fn shuffle_mode(&self) -> ShuffleMode {
+        self.shuffle_mode
+    }
+
     pub fn repeat_mode(&self) -> RepeatMode {
         self.repeat
     }
 
+    // Most recent first
+    pub fn history(&self) -> impl Iterator<Item = &SongDescription> + '_ {
+        self.history.iter().rev()
+    }
+
     // Whatever batch of songs we would need to grab if we were to play the next track
     pub fn next_query(&self) -> Option<BatchQuery> {
         let next_index = self.next_index()?;
@@ -82,7 +211,12 @@ impl PlaybackState {
         self.index(self.list_position?)
     }
 
-    fn next_id(&self) -> Option<String> {
+    // Same unit as the Seek/SyncSeek actions
+    pub fn seek_position(&self) -> u32 {
+        self.seek_position.current() as u32
+    }
+
+    pub fn next_id(&self) -> Option<String> {
         self.next_index()
             .and_then(|i| Some(self.songs().index(i)?.description().id.clone()))
     }
@@ -109,14 +243,54 @@ impl PlaybackState {
 
     // Replaces (!) the current playlist with a bunch of songs (not batched, not expected to grow)
     fn set_queue(&mut self, tracks: Vec<SongDescription>) {
+        let mut tracks = self.queue_limits.admit(vec![], tracks);
+        if let Some(max_length) = self.queue_limits.max_length {
+            tracks.truncate(max_length);
+        }
         self.clear(None).and(|s| s.append(tracks)).commit();
         self.index.grow(self.songs.len());
     }
 
-    pub fn queue(&mut self, tracks: Vec<SongDescription>) {
+    // Returns the ids of the songs that actually made it in, given the queue limits
+    pub fn queue(&mut self, tracks: Vec<SongDescription>) -> Vec<String> {
+        let queued = if self.queue_limits.no_duplicates {
+            self.songs.map_collect(|s| s.id)
+        } else {
+            vec![]
+        };
+        let mut tracks = self.queue_limits.admit(queued, tracks);
+        if tracks.is_empty() {
+            return vec![];
+        }
+        if let Some(max_length) = self.queue_limits.max_length {
+            self.drop_played((self.songs.len() + tracks.len()).saturating_sub(max_length));
+            tracks.truncate(max_length.saturating_sub(self.songs.len()));
+        }
+        let ids = tracks.iter().map(|t| t.id.clone()).collect();
         self.source = None;
         self.songs.append(tracks).commit();
         self.index.grow(self.songs.len());
+        ids
+    }
+
+    // Makes room for new songs by removing up to `count` songs that were already played.
+    // Not while shuffling though, what comes before the current song isn't what played then.
+    fn drop_played(&mut self, count: usize) {
+        let Some(position) = self.list_position.filter(|_| !self.is_shuffled) else {
+            return;
+        };
+        let ids = self.songs.map_collect(|s| s.id);
+        let (played, coming) = ids.split_at(position.min(ids.len()));
+        // Without the option against duplicates, a song could be both
+        let played: Vec<String> = played
+            .iter()
+            .take(count)
+            .filter(|id| !coming.contains(id))
+            .cloned()
+            .collect();
+        if !played.is_empty() {
+            self.dequeue(&played);
+        }
     }
 
     pub fn dequeue(&mut self, ids: &[String]) {
@@ -158,19 +332,24 @@ impl PlaybackState {
             return false;
         }
 
-        let found_index = self.songs.find_index(id);
+        let Some(index) = self.songs.find_index(id) else {
+            return false;
+        };
+
+        // If shufflings songs, we make sure the track we just picked is the first to come up
+        if self.is_shuffled {
+            self.index.reset_picking_first(index);
+            self.play_index(0);
+            return true;
+        }
 
-        if let Some(index) = found_index {
-            // If shufflings songs, we make sure the track we just picked is the first to come up
-            if self.is_shuffled {
-                self.index.reset_picking_first(index);
-                self.play_index(0);
-            } else {
+        // A song that can't be played starts the next one instead, unless that one is already playing
+        match self.find_playable(index, |i| self.step_forward(i)) {
+            Some(index) if Some(index) != self.list_position => {
                 self.play_index(index);
+                true
             }
-            true
-        } else {
-            false
+            _ => false,
         }
     }
 
@@ -180,14 +359,133 @@ impl PlaybackState {
         self.seek_position.set(0, false);
     }
 
+    // Where a song is in the playing order, which isn't the list order when shuffling
+    fn position_of(&self, id: &str) -> Option<usize> {
+        let index = self.songs.find_index(id)?;
+        if self.is_shuffled {
+            (0..self.songs.len()).find(|&i| self.index.get(i) == Some(index))
+        } else {
+            Some(index)
+        }
+    }
+
+    fn remember_current(&mut self) {
+        let Some(song) = self.current_song() else {
+            return;
+        };
+        if self.history.last().map(|s| &s.id) == Some(&song.id) {
+            return;
+        }
+        if self.history.len() >= HISTORY_SIZE {
+            self.history.remove(0);
+        }
+        self.history.push(song);
+    }
+
+    // The most recent song of the history that can still be played from the list
+    fn pop_history(&mut self) -> Option<usize> {
+        let current_id = self.current_song_id();
+        while let Some(song) = self.history.pop() {
+            if Some(&song.id) == current_id.as_ref() {
+                continue;
+            }
+            if let Some(position) = self.position_of(&song.id) {
+                return Some(position);
+            }
+        }
+        None
+    }
+
     fn play_index(&mut self, index: usize) -> Option<String> {
+        self.remember_current();
+        self.move_to(index)
+    }
+
+    // Same as above, without touching the history (for when we go back in it)
+    fn move_to(&mut self, index: usize) -> Option<String> {
         self.is_playing = true;
-        self.list_position.replace(index);
         self.seek_position.set(0, true);
-        self.index.next_until(index + 1);
+        let mut index = index;
+        // When shuffling, we only find out which song comes next once it's picked
+        for _ in 0..self.songs.len() {
+            self.list_position.replace(index);
+            self.pick_until(index + 1);
+            if !self.is_skippable(index) {
+                break;
+            }
+            match self.next_index() {
+                Some(next) => index = next,
+                None => break,
+            }
+        }
         self.current_song_id()
     }
 
+    fn pick_until(&mut self, i: usize) {
+        if self.shuffle_mode == ShuffleMode::Uniform {
+            self.index.next_until(i);
+            return;
+        }
+        let weights = ShuffleWeights::new(
+            self.shuffle_mode,
+            &self.songs,
+            &self.history,
+            &self.heard_rank,
+        );
+        self.index
+            .next_until_weighted(i, |previous, candidate| weights.weight(previous, candidate));
+    }
+
+    // What was already picked was picked the old way, so everything after the current song is picked again
+    fn set_shuffle_mode(&mut self, mode: ShuffleMode) {
+        self.shuffle_mode = mode;
+        if !self.is_shuffled {
+            return;
+        }
+        if let Some(current) = self.list_position.and_then(|p| self.index.get(p)) {
+            self.index.reset_picking_first(current);
+            self.list_position = Some(0);
+        }
+    }
+
+    // Track ids, oldest play first
+    fn set_listening_history(&mut self, track_ids: Vec<String>) {
+        self.heard_rank.clear();
+        for id in track_ids.into_iter().rev() {
+            let rank = self.heard_rank.len();
+            self.heard_rank.entry(id).or_insert(rank);
+        }
+    }
+
+    // Songs that we know can't (or shouldn't) be played are passed over, the ones not loaded yet get the benefit of the doubt
+    fn is_skippable(&self, i: usize) -> bool {
+        let i = if self.is_shuffled {
+            self.index.get(i)
+        } else {
+            Some(i)
+        };
+        i.and_then(|i| self.songs.index(i))
+            .map(|song| {
+                let song = song.description();
+                !song.is_playable || self.blocklist.blocks(&song)
+            })
+            .unwrap_or(false)
+    }
+
+    fn find_playable<F>(&self, from: usize, step: F) -> Option<usize>
+    where
+        F: Fn(usize) -> Option<usize>,
+    {
+        let mut i = from;
+        for _ in 0..self.songs.len() {
+            if !self.is_skippable(i) {
+                return Some(i);
+            }
+            i = step(i)?;
+        }
+        None
+    }
+
     fn play_next(&mut self) -> Option<String> {
         self.next_index().and_then(|i| {
             self.seek_position.set(0, true);
@@ -196,38 +494,82 @@ impl PlaybackState {
     }
 
     pub fn next_index(&self) -> Option<usize> {
+        let next = self.step_forward(self.list_position?)?;
+        self.find_playable(next, |i| self.step_forward(i))
+    }
+
+    fn step_forward(&self, p: usize) -> Option<usize> {
         let len = self.songs.len();
-        self.list_position.and_then(|p| match self.repeat {
+        match self.repeat {
             RepeatMode::Song => Some(p),
             RepeatMode::Playlist if len != 0 => Some((p + 1) % len),
             RepeatMode::None => Some(p + 1).filter(|&i| i < len),
             _ => None,
-        })
+        }
+    }
+
+    fn seek_to_chapter<F>(&mut self, target: F) -> Vec<PlaybackEvent>
+    where
+        F: Fn(&ChapterList, u32) -> Option<u32>,
+    {
+        let position = self.seek_position();
+        let Some(pos) = self
+            .current_song()
+            .and_then(|song| target(&song.chapters, position))
+        else {
+            return vec![];
+        };
+        self.seek_position.set(pos as u64, true);
+        vec![PlaybackEvent::TrackSeeked(pos)]
     }
 
     fn play_prev(&mut self) -> Option<String> {
-        self.prev_index().and_then(|i| {
-            // Only jump to the previous track if we aren't more than 2 seconds (2,000 ms) into the current track.
-            // Otherwise, seek to the start of the current track.
-            // (This replicates the behavior of official Spotify clients.)
-            if self.seek_position.current() <= 2000 {
-                self.seek_position.set(0, true);
-                self.play_index(i)
-            } else {
-                self.seek_position.set(0, true);
-                None
-            }
-        })
+        // Only jump to the previous track if we aren't more than 2 seconds (2,000 ms, by default) into the current track.
+        // Otherwise, seek to the start of the current track.
+        // (This replicates the behavior of official Spotify clients.)
+        let restart = self
+            .restart_threshold
+            .is_some_and(|threshold| self.seek_position() > threshold);
+        if restart && (self.prev_index().is_some() || !self.history.is_empty()) {
+            self.seek_position.set(0, true);
+            None
+        } else {
+            self.play_prev_track()
+        }
+    }
+
+    fn play_prev_track(&mut self) -> Option<String> {
+        // What we actually heard comes first, it only differs from the list after jumping around
+        // (and can lead back even from the top of the list)
+        let i = self.pop_history().or_else(|| self.prev_index())?;
+        self.seek_position.set(0, true);
+        self.move_to(i)
+    }
+
+    fn previous_events(id: Option<String>) -> Vec<PlaybackEvent> {
+        if let Some(id) = id {
+            vec![
+                PlaybackEvent::TrackChanged(id),
+                PlaybackEvent::PlaybackResumed,
+            ]
+        } else {
+            vec![PlaybackEvent::TrackSeeked(0)]
+        }
     }
 
     pub fn prev_index(&self) -> Option<usize> {
+        let prev = self.step_back(self.list_position?)?;
+        self.find_playable(prev, |i| self.step_back(i))
+    }
+
+    fn step_back(&self, p: usize) -> Option<usize> {
         let len = self.songs.len();
-        self.list_position.and_then(|p| match self.repeat {
+        match self.repeat {
             RepeatMode::Song => Some(p),
             RepeatMode::Playlist if len != 0 => Some((if p == 0 { len } else { p }) - 1),
             RepeatMode::None => Some(p).filter(|&i| i > 0).map(|i| i - 1),
             _ => None,
-        })
+        }
     }
 
     fn toggle_play(&mut self) -> Option<bool> {
@@ -258,6 +600,44 @@ impl PlaybackState {
     pub fn current_device(&self) -> &Device {
         &self.current_device
     }
+
+    pub fn group_session(&self) -> Option<&GroupSession> {
+        self.group_session.as_ref()
+    }
+
+    // A device other than the one we're controlling is busy playing
+    pub fn active_remote_device(&self) -> Option<&ConnectDevice> {
+        let current_id = match &self.current_device {
+            Device::Local => None,
+            Device::Connect(device) => Some(&device.id),
+        };
+        self.available_devices
+            .iter()
+            .find(|d| d.is_active && Some(&d.id) != current_id)
+    }
+
+    fn set_available_devices(&mut self, list: Vec<ConnectDevice>) -> Vec<PlaybackEvent> {
+        fn has(list: &[ConnectDevice], device: &ConnectDevice) -> bool {
+            list.iter().any(|d| d.id == device.id)
+        }
+
+        let old_list = std::mem::replace(&mut self.available_devices, list);
+        let disappeared = old_list
+            .iter()
+            .filter(|d| !has(&self.available_devices, d))
+            .cloned()
+            .map(PlaybackEvent::DeviceDisappeared);
+        let appeared = self
+            .available_devices
+            .iter()
+            .filter(|d| !has(&old_list, d))
+            .cloned()
+            .map(PlaybackEvent::DeviceAppeared);
+        disappeared
+            .chain(appeared)
+            .chain(std::iter::once(PlaybackEvent::AvailableDevicesChanged))
+            .collect()
+    }
 }
 
 impl Default for PlaybackState {
@@ -265,6 +645,7 @@ impl Default for PlaybackState {
         Self {
             available_devices: vec![],
             current_device: Device::Local,
+            group_session: None,
             index: LazyRandomIndex::default(),
             songs: SongListModel::new(50),
             list_position: None,
@@ -273,6 +654,13 @@ impl Default for PlaybackState {
             repeat: RepeatMode::None,
             is_playing: false,
             is_shuffled: false,
+            shuffle_mode: ShuffleMode::Uniform,
+            history: vec![],
+            heard_rank: HashMap::new(),
+            restart_threshold: Some(2000),
+            previews_only: true,
+            blocklist: Blocklist::default(),
+            queue_limits: QueueLimits::default(),
         }
     }
 }
@@ -287,19 +675,42 @@ pub enum PlaybackAction {
     SetShuffled(bool),
     ToggleRepeat,
     ToggleShuffle,
+    SetShuffleMode(ShuffleMode),
+    // Track ids from the listening history, oldest play first
+    SetListeningHistory(Vec<String>),
     Seek(u32),
     // I can't remember the diff betweek Seek and SyncSeek right now. Probably the source of the action
     SyncSeek(u32),
     Load(String),
     LoadSongs(Vec<SongDescription>),
     LoadPagedSongs(SongsSource, SongBatch),
+    // More of the source that's playing, ignored if something else got played in the meantime
+    AppendPagedSongs(SongsSource, SongBatch),
     SetVolume(f64),
+    // Same as above, but coming from a remote device
+    SyncVolume(f64),
     Next,
     Previous,
+    // Same, but never just restarting the current song
+    PreviousTrack,
+    SetRestartThreshold(Option<u32>),
+    SetQueueLimits(QueueLimits),
+    SetPreviewsOnly(bool),
+    SetBlocklist(Vec<BlockedItem>),
+    Block(BlockedItem),
+    Unblock(String),
+    // Within the current item, if it has chapters
+    NextChapter,
+    PreviousChapter,
     Preload,
     Queue(Vec<SongDescription>),
     Dequeue(String),
+    // Plays a song from the history again, queuing it back if it's gone from the list
+    Replay(SongDescription),
     SwitchDevice(Device),
+    // Switch, and carry on playing what we were playing over there
+    TransferPlayback(Device),
+    SetGroupSession(Option<GroupSession>),
     SetAvailableDevices(Vec<ConnectDevice>),
 }
 
@@ -323,14 +734,23 @@ pub enum PlaybackEvent {
     TrackSeeked(u32),
     SeekSynced(u32),
     VolumeSet(f64),
+    VolumeSynced(f64),
     TrackChanged(String),
     SourceChanged,
     Preload(String),
     ShuffleChanged(bool),
+    ShuffleModeChanged(ShuffleMode),
     PlaylistChanged,
+    TracksQueued(Vec<String>),
     PlaybackStopped,
     SwitchedDevice(Device),
+    PlaybackTransferred(Device),
+    GroupSessionChanged,
+    DeviceAppeared(ConnectDevice),
+    DeviceDisappeared(ConnectDevice),
     AvailableDevicesChanged,
+    PreviewsOnlyChanged(bool),
+    BlocklistChanged,
 }
 
 impl From<PlaybackEvent> for AppEvent {
@@ -372,11 +792,7 @@ impl UpdatableState for PlaybackState {
                 }
             }
             PlaybackAction::ToggleRepeat => {
-                self.repeat = match self.repeat {
-                    RepeatMode::Song => RepeatMode::None,
-                    RepeatMode::Playlist => RepeatMode::Song,
-                    RepeatMode::None => RepeatMode::Playlist,
-                };
+                self.repeat = self.repeat.next();
                 vec![PlaybackEvent::RepeatModeChanged(self.repeat)]
             }
             PlaybackAction::SetRepeatMode(mode) if self.repeat != mode => {
@@ -391,6 +807,14 @@ impl UpdatableState for PlaybackState {
                 self.set_shuffled(!self.is_shuffled);
                 vec![PlaybackEvent::ShuffleChanged(self.is_shuffled)]
             }
+            PlaybackAction::SetShuffleMode(mode) if self.shuffle_mode != mode => {
+                self.set_shuffle_mode(mode);
+                vec![PlaybackEvent::ShuffleModeChanged(mode)]
+            }
+            PlaybackAction::SetListeningHistory(track_ids) => {
+                self.set_listening_history(track_ids);
+                vec![]
+            }
             PlaybackAction::Next => {
                 if let Some(id) = self.play_next() {
                     vec![
@@ -406,20 +830,54 @@ impl UpdatableState for PlaybackState {
                 self.stop();
                 vec![PlaybackEvent::PlaybackStopped]
             }
-            PlaybackAction::Previous => {
-                if let Some(id) = self.play_prev() {
-                    vec![
-                        PlaybackEvent::TrackChanged(id),
-                        PlaybackEvent::PlaybackResumed,
-                    ]
+            PlaybackAction::Previous => Self::previous_events(self.play_prev()),
+            PlaybackAction::PreviousTrack => Self::previous_events(self.play_prev_track()),
+            PlaybackAction::SetRestartThreshold(threshold) => {
+                self.restart_threshold = threshold;
+                vec![]
+            }
+            PlaybackAction::SetQueueLimits(limits) => {
+                self.queue_limits = limits;
+                vec![]
+            }
+            PlaybackAction::SetBlocklist(items) => {
+                self.blocklist = Blocklist::new(items);
+                vec![PlaybackEvent::BlocklistChanged]
+            }
+            PlaybackAction::Block(item) => {
+                if !self.blocklist.block(item) {
+                    return vec![];
+                }
+                let mut events = vec![PlaybackEvent::BlocklistChanged];
+                let blocks_current = self
+                    .current_song()
+                    .map(|song| self.blocklist.blocks(&song))
+                    .unwrap_or(false);
+                if blocks_current {
+                    events.append(&mut self.update_with(Cow::Owned(PlaybackAction::Next)));
+                }
+                events
+            }
+            PlaybackAction::Unblock(uri) => {
+                if self.blocklist.unblock(&uri) {
+                    vec![PlaybackEvent::BlocklistChanged]
                 } else {
-                    vec![PlaybackEvent::TrackSeeked(0)]
+                    vec![]
                 }
             }
+            PlaybackAction::SetPreviewsOnly(previews_only)
+                if previews_only != self.previews_only =>
+            {
+                self.previews_only = previews_only;
+                vec![PlaybackEvent::PreviewsOnlyChanged(previews_only)]
+            }
+            PlaybackAction::NextChapter => self.seek_to_chapter(ChapterList::next_start),
+            PlaybackAction::PreviousChapter => self.seek_to_chapter(ChapterList::previous_start),
             PlaybackAction::Load(id) => {
                 if self.play(&id) {
                     vec![
-                        PlaybackEvent::TrackChanged(id),
+                        // Not necessarily that song, if it couldn't be played
+                        PlaybackEvent::TrackChanged(self.current_song_id().unwrap_or(id)),
                         PlaybackEvent::PlaybackResumed,
                     ]
                 } else {
@@ -434,6 +892,7 @@ impl UpdatableState for PlaybackState {
                 }
             }
             PlaybackAction::LoadPagedSongs(source, batch)
+            | PlaybackAction::AppendPagedSongs(source, batch)
                 if Some(&source) == self.source.as_ref() =>
             {
                 if self.add_batch(batch) {
@@ -454,30 +913,62 @@ impl UpdatableState for PlaybackState {
                 vec![PlaybackEvent::PlaylistChanged, PlaybackEvent::SourceChanged]
             }
             PlaybackAction::Queue(tracks) => {
-                self.queue(tracks);
-                vec![PlaybackEvent::PlaylistChanged]
+                let ids = self.queue(tracks);
+                if ids.is_empty() {
+                    return vec![];
+                }
+                vec![
+                    PlaybackEvent::PlaylistChanged,
+                    PlaybackEvent::TracksQueued(ids),
+                ]
             }
             PlaybackAction::Dequeue(id) => {
                 self.dequeue(&[id]);
                 vec![PlaybackEvent::PlaylistChanged]
             }
+            PlaybackAction::Replay(song) => {
+                let id = song.id.clone();
+                let mut events = vec![];
+                if self.songs.find_index(&id).is_none() {
+                    self.queue(vec![song]);
+                    events.push(PlaybackEvent::PlaylistChanged);
+                }
+                if self.play(&id) {
+                    events.extend([
+                        PlaybackEvent::TrackChanged(id),
+                        PlaybackEvent::PlaybackResumed,
+                    ]);
+                }
+                events
+            }
             PlaybackAction::Seek(pos) => {
-                self.seek_position.set(pos as u64 * 1000, true);
+                self.seek_position.set(pos as u64, true);
                 vec![PlaybackEvent::TrackSeeked(pos)]
             }
             PlaybackAction::SyncSeek(pos) => {
-                self.seek_position.set(pos as u64 * 1000, true);
+                // Reported by the player itself, so it shouldn't start the clock if we're paused
+                self.seek_position.set(pos as u64, self.is_playing());
                 vec![PlaybackEvent::SeekSynced(pos)]
             }
             PlaybackAction::SetVolume(volume) => vec![PlaybackEvent::VolumeSet(volume)],
-            PlaybackAction::SetAvailableDevices(list) => {
-                self.available_devices = list;
-                vec![PlaybackEvent::AvailableDevicesChanged]
+            PlaybackAction::SyncVolume(volume) => vec![PlaybackEvent::VolumeSynced(volume)],
+            PlaybackAction::SetAvailableDevices(list) => self.set_available_devices(list),
+            PlaybackAction::SetGroupSession(session) if session != self.group_session => {
+                self.group_session = session;
+                vec![PlaybackEvent::GroupSessionChanged]
             }
             PlaybackAction::SwitchDevice(new_device) => {
                 self.current_device = new_device.clone();
                 vec![PlaybackEvent::SwitchedDevice(new_device)]
             }
+            PlaybackAction::TransferPlayback(new_device) => {
+                self.current_device = new_device.clone();
+                let mut events = vec![PlaybackEvent::PlaybackTransferred(new_device)];
+                if !self.is_playing() && self.toggle_play() == Some(true) {
+                    events.push(PlaybackEvent::PlaybackResumed);
+                }
+                events
+            }
             _ => vec![],
         }
     }
@@ -533,23 +1024,9 @@ impl PositionMillis {
 mod tests {
 
     use super::*;
-    use crate::app::models::AlbumRef;
-
-    fn song(id: &str) -> SongDescription {
-        SongDescription {
-            id: id.to_string(),
-            uri: "".to_string(),
-            title: "Title".to_string(),
-            artists: vec![],
-            album: AlbumRef {
-                id: "".to_string(),
-                name: "".to_string(),
-            },
-            duration: 1000,
-            art: None,
-            track_number: None,
-        }
-    }
+    use crate::app::models::ArtistRef;
+    use crate::app::test_utils::song;
+    use std::time::Duration;
 
     impl PlaybackState {
         fn current_position(&self) -> Option<usize> {
@@ -596,6 +1073,207 @@ mod tests {
         assert!(!state.is_playing());
     }
 
+    #[test]
+    fn test_unplayable_songs_are_skipped() {
+        let mut unavailable = song("2");
+        unavailable.is_playable = false;
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("1"), unavailable, song("3")]);
+
+        state.play("1");
+        assert_eq!(state.next_index(), Some(2));
+        state.update_with(Cow::Owned(PlaybackAction::Next));
+        assert_eq!(state.current_song_id(), Some("3".to_string()));
+        assert_eq!(state.prev_index(), Some(0));
+
+        let events = state.update_with(Cow::Owned(PlaybackAction::Load("2".to_string())));
+        assert_eq!(state.current_song_id(), Some("3".to_string()));
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_previous_goes_back_in_history() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("1"), song("2"), song("3"), song("4")]);
+        state.play("1");
+        state.play("4");
+        state.play("2");
+        assert_eq!(
+            state.history().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["4", "1"]
+        );
+
+        state.update_with(Cow::Owned(PlaybackAction::Previous));
+        assert_eq!(state.current_song_id(), Some("4".to_string()));
+        state.update_with(Cow::Owned(PlaybackAction::Previous));
+        assert_eq!(state.current_song_id(), Some("1".to_string()));
+        assert_eq!(state.history().count(), 0);
+    }
+
+    #[test]
+    fn test_previous_from_the_first_song() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("1"), song("2"), song("3")]);
+        state.play("3");
+        state.play("1");
+
+        state.update_with(Cow::Owned(PlaybackAction::Previous));
+        assert_eq!(state.current_song_id(), Some("3".to_string()));
+    }
+
+    #[test]
+    fn test_replay_song_gone_from_list() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("1"), song("2")]);
+        state.play("1");
+        state.play("2");
+        state.update_with(Cow::Owned(PlaybackAction::LoadSongs(vec![song("3")])));
+
+        let replayed = state.history().next().cloned().unwrap();
+        let events = state.update_with(Cow::Owned(PlaybackAction::Replay(replayed)));
+        assert!(matches!(
+            events[..],
+            [
+                PlaybackEvent::PlaylistChanged,
+                PlaybackEvent::TrackChanged(_),
+                PlaybackEvent::PlaybackResumed
+            ]
+        ));
+        assert_eq!(state.current_song_id(), Some("1".to_string()));
+    }
+
+    #[test]
+    fn test_chapter_navigation() {
+        let mut state = PlaybackState::default();
+        let mut long_song = song("foo");
+        long_song.duration = 600_000;
+        long_song.chapters = ChapterList::from_description("0:00 One\n5:00 Two", 600_000);
+        state.queue(vec![long_song, song("bar")]);
+        state.play("foo");
+
+        let events = state.update_with(Cow::Owned(PlaybackAction::NextChapter));
+        assert!(matches!(events[..], [PlaybackEvent::TrackSeeked(300_000)]));
+        assert!(state.seek_position() >= 300_000);
+
+        let events = state.update_with(Cow::Owned(PlaybackAction::NextChapter));
+        assert!(events.is_empty());
+        assert_eq!(state.current_song_id(), Some("foo".to_string()));
+    }
+
+    #[test]
+    fn test_sync_seek_while_paused() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("foo")]);
+        state.play("foo");
+        state.toggle_play();
+
+        state.update_with(Cow::Owned(PlaybackAction::SyncSeek(5000)));
+        assert_eq!(state.seek_position(), 5000);
+        assert!(state.seek_position.last_resume_instant.is_none());
+
+        state.toggle_play();
+        state.update_with(Cow::Owned(PlaybackAction::SyncSeek(6000)));
+        assert!(state.seek_position.last_resume_instant.is_some());
+    }
+
+    // As if the clock was started that much earlier
+    fn elapse(state: &mut PlaybackState, duration: Duration) {
+        let resumed = state.seek_position.last_resume_instant.unwrap();
+        state.seek_position.last_resume_instant = resumed.checked_sub(duration);
+    }
+
+    #[test]
+    fn test_position_advances_while_playing() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("foo")]);
+        state.play("foo");
+        state.update_with(Cow::Owned(PlaybackAction::Seek(5000)));
+
+        elapse(&mut state, Duration::from_secs(2));
+        let position = state.seek_position();
+        assert!((7000..7500).contains(&position), "{position}");
+
+        state.toggle_play();
+        let paused_at = state.seek_position();
+        assert!(paused_at >= 7000);
+        assert_eq!(state.seek_position(), paused_at);
+    }
+
+    // What the seek bar's clock reads every second, in between the player's own reports
+    #[test]
+    fn test_clock_moves_forward() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("foo")]);
+        state.play("foo");
+        state.update_with(Cow::Owned(PlaybackAction::SyncSeek(10_000)));
+
+        let mut last = state.seek_position();
+        for _ in 0..3 {
+            elapse(&mut state, Duration::from_secs(1));
+            let position = state.seek_position();
+            assert!(position >= last + 1000, "{position} after {last}");
+            last = position;
+        }
+        assert!(last < 14_000);
+
+        // The player catching up doesn't start it over
+        state.update_with(Cow::Owned(PlaybackAction::SyncSeek(13_000)));
+        elapse(&mut state, Duration::from_secs(1));
+        assert!(state.seek_position() >= 14_000);
+    }
+
+    #[test]
+    fn test_transfer_resumes_playback() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("foo")]);
+        state.play("foo");
+        state.toggle_play();
+        state.update_with(Cow::Owned(PlaybackAction::SyncSeek(5000)));
+
+        let events = state.update_with(Cow::Owned(PlaybackAction::TransferPlayback(Device::Local)));
+        assert!(matches!(
+            events[..],
+            [
+                PlaybackEvent::PlaybackTransferred(Device::Local),
+                PlaybackEvent::PlaybackResumed
+            ]
+        ));
+        assert!(state.is_playing());
+        assert!(state.seek_position() >= 5000);
+    }
+
+    #[test]
+    fn test_devices_appear_and_disappear() {
+        let device = |id: &str, is_active: bool| ConnectDevice {
+            id: id.to_string(),
+            label: id.to_string(),
+            kind: ConnectDeviceKind::Speaker,
+            is_active,
+        };
+        let mut state = PlaybackState::default();
+        state.update_with(Cow::Owned(PlaybackAction::SetAvailableDevices(vec![
+            device("kitchen", false),
+            device("phone", false),
+        ])));
+
+        let events = state.update_with(Cow::Owned(PlaybackAction::SetAvailableDevices(vec![
+            device("phone", false),
+            device("tv", true),
+        ])));
+        assert!(matches!(
+            &events[..],
+            [
+                PlaybackEvent::DeviceDisappeared(gone),
+                PlaybackEvent::DeviceAppeared(new),
+                PlaybackEvent::AvailableDevicesChanged
+            ] if gone.id == "kitchen" && new.id == "tv"
+        ));
+        assert_eq!(
+            state.active_remote_device().map(|d| d.id.as_str()),
+            Some("tv")
+        );
+    }
+
     #[test]
     fn test_queue() {
         let mut state = PlaybackState::default();
@@ -609,6 +1287,62 @@ mod tests {
         assert_eq!(state.songs().len(), 4);
     }
 
+    #[test]
+    fn test_queue_limits() {
+        let mut state = PlaybackState::default();
+        state.update_with(Cow::Owned(PlaybackAction::SetQueueLimits(QueueLimits {
+            no_duplicates: true,
+            max_length: Some(4),
+        })));
+        state.queue(vec![song("1"), song("2"), song("2"), song("3")]);
+        assert_eq!(state.songs().map_collect(|s| s.id), vec!["1", "2", "3"]);
+
+        state.play("3");
+        // Both played songs make room, and that's still not enough room for the last one
+        let queued = state.queue(vec![song("1"), song("4"), song("5"), song("6"), song("7")]);
+        assert_eq!(queued, vec!["4", "5", "6"]);
+        assert_eq!(
+            state.songs().map_collect(|s| s.id),
+            vec!["3", "4", "5", "6"]
+        );
+        assert_eq!(state.current_song_id(), Some("3".to_string()));
+    }
+
+    #[test]
+    fn test_append_paged_songs() {
+        let batch = |offset: usize| SongBatch {
+            songs: vec![song(&offset.to_string()), song(&(offset + 1).to_string())],
+            batch: Batch {
+                offset,
+                batch_size: 2,
+                total: 6,
+            },
+        };
+        let album = SongsSource::Album("album".to_string());
+        let mut state = PlaybackState::default();
+        state.update_with(Cow::Owned(PlaybackAction::LoadPagedSongs(
+            album.clone(),
+            batch(2),
+        )));
+        state.update_with(Cow::Owned(PlaybackAction::Load("3".to_string())));
+        state.update_with(Cow::Owned(PlaybackAction::AppendPagedSongs(
+            album.clone(),
+            batch(0),
+        )));
+        assert_eq!(state.prev_id(), Some("2".to_string()));
+        assert_eq!(state.current_song_id(), Some("3".to_string()));
+        assert_eq!(state.songs().find_index("0"), Some(0));
+
+        // Too late, something else is playing
+        state.update_with(Cow::Owned(PlaybackAction::LoadSongs(vec![song("a")])));
+        let events = state.update_with(Cow::Owned(PlaybackAction::AppendPagedSongs(
+            album,
+            batch(4),
+        )));
+        assert!(events.is_empty());
+        assert_eq!(state.songs().len(), 1);
+    }
+
     #[test]
     fn test_play_multiple() {
         let mut state = PlaybackState::default();
@@ -652,6 +1386,89 @@ mod tests {
         assert_eq!(state.current_song_id(), Some("1".to_string()));
     }
 
+    #[test]
+    fn test_previous_restarts_the_song() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("1"), song("2")]);
+        state.play("1");
+        state.play("2");
+
+        state.update_with(Cow::Owned(PlaybackAction::Seek(5000)));
+        let events = state.update_with(Cow::Owned(PlaybackAction::Previous));
+        assert!(matches!(events[..], [PlaybackEvent::TrackSeeked(0)]));
+        assert_eq!(state.current_song_id(), Some("2".to_string()));
+
+        state.update_with(Cow::Owned(PlaybackAction::Seek(5000)));
+        state.update_with(Cow::Owned(PlaybackAction::PreviousTrack));
+        assert_eq!(state.current_song_id(), Some("1".to_string()));
+
+        state.play("2");
+        state.update_with(Cow::Owned(PlaybackAction::SetRestartThreshold(None)));
+        state.update_with(Cow::Owned(PlaybackAction::Seek(5000)));
+        state.update_with(Cow::Owned(PlaybackAction::Previous));
+        assert_eq!(state.current_song_id(), Some("1".to_string()));
+    }
+
+    #[test]
+    fn test_restart_threshold() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("1"), song("2")]);
+        state.update_with(Cow::Owned(PlaybackAction::SetRestartThreshold(Some(3000))));
+
+        state.play("1");
+        state.play("2");
+        state.update_with(Cow::Owned(PlaybackAction::Pause));
+        state.update_with(Cow::Owned(PlaybackAction::SyncSeek(2900)));
+        state.update_with(Cow::Owned(PlaybackAction::Previous));
+        assert_eq!(state.current_song_id(), Some("1".to_string()));
+
+        state.play("2");
+        state.update_with(Cow::Owned(PlaybackAction::Pause));
+        state.update_with(Cow::Owned(PlaybackAction::SyncSeek(3100)));
+        let events = state.update_with(Cow::Owned(PlaybackAction::Previous));
+        assert!(matches!(events[..], [PlaybackEvent::TrackSeeked(0)]));
+        assert_eq!(state.current_song_id(), Some("2".to_string()));
+    }
+
+    #[test]
+    fn test_blocked_songs_are_skipped() {
+        let mut state = PlaybackState::default();
+        state.queue(vec![song("1"), song("2"), song("3")]);
+        state.play("1");
+
+        state.update_with(Cow::Owned(PlaybackAction::Block(BlockedItem::song(&song(
+            "2",
+        )))));
+        assert_eq!(state.next_id(), Some("3".to_string()));
+
+        // Not a second more of it
+        state.update_with(Cow::Owned(PlaybackAction::Block(BlockedItem::song(&song(
+            "1",
+        )))));
+        assert_eq!(state.current_song_id(), Some("3".to_string()));
+
+        state.update_with(Cow::Owned(PlaybackAction::Unblock(
+            "spotify:track:2".to_string(),
+        )));
+        assert_eq!(state.prev_index(), Some(1));
+    }
+
+    #[test]
+    fn test_previews_until_the_player_is_ready() {
+        let mut state = PlaybackState::default();
+        assert!(state.previews_only());
+
+        let events = state.update_with(Cow::Owned(PlaybackAction::SetPreviewsOnly(false)));
+        assert!(matches!(
+            events[..],
+            [PlaybackEvent::PreviewsOnlyChanged(false)]
+        ));
+        assert!(!state.previews_only());
+
+        let events = state.update_with(Cow::Owned(PlaybackAction::SetPreviewsOnly(false)));
+        assert!(events.is_empty());
+    }
+
     #[test]
     fn test_shuffle() {
         let mut state = PlaybackState::default();
@@ -684,6 +1501,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_artist_spread() {
+        let mut state = PlaybackState::default();
+        let songs = ["a1", "a2", "a3", "b1", "b2", "b3"].map(|id| SongDescription {
+            artists: vec![ArtistRef {
+                id: id[..1].to_string(),
+                name: "".to_string(),
+            }],
+            ..song(id)
+        });
+        state.queue(songs.to_vec());
+
+        state.play("a1");
+        state.update_with(Cow::Owned(PlaybackAction::SetShuffleMode(
+            ShuffleMode::ArtistSpread,
+        )));
+        state.set_shuffled(true);
+
+        let mut played = vec![state.current_song_id().unwrap()];
+        for _ in 0..5 {
+            played.push(state.play_next().unwrap());
+        }
+        let artists: String = played.iter().map(|id| &id[..1]).collect();
+        assert_eq!(artists, "ababab");
+    }
+
     #[test]
     fn test_shuffle_queue() {
         let mut state = PlaybackState::default();
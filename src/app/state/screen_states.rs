@@ -1,11 +1,13 @@
 use std::borrow::Cow;
 use std::cmp::PartialEq;
+use std::collections::HashSet;
 
 use super::{pagination::Pagination, BrowserAction, BrowserEvent, UpdatableState};
 use crate::app::models::*;
 use crate::app::ListStore;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ScreenName {
     Home,
     AlbumDetails(String),
@@ -26,6 +28,24 @@ impl ScreenName {
             Self::User(s) => Cow::Owned(format!("user_{s}")),
         }
     }
+
+    // The reverse of the above
+    pub fn from_identifier(identifier: &str) -> Option<Self> {
+        match identifier {
+            "home" => return Some(Self::Home),
+            "search" => return Some(Self::Search),
+            _ => {}
+        }
+        let (kind, id) = identifier.split_once('_')?;
+        let id = id.to_string();
+        match kind {
+            "album" => Some(Self::AlbumDetails(id)),
+            "artist" => Some(Self::Artist(id)),
+            "playlist" => Some(Self::PlaylistDetails(id)),
+            "user" => Some(Self::User(id)),
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq for ScreenName {
@@ -37,12 +57,25 @@ impl PartialEq for ScreenName {
 impl Eq for ScreenName {}
 
 // ALBUM details
+// The same page can come back twice (say, prefetching kicked in and then we reached the bottom):
+// everything in it would already be there
+fn is_page_loaded<'a>(
+    store: &ListStore<AlbumModel>,
+    ids: impl Iterator<Item = &'a String>,
+) -> bool {
+    let loaded: HashSet<String> = store.iter().map(|a| a.uri()).collect();
+    let mut ids = ids.peekable();
+    ids.peek().is_some() && ids.all(|id| loaded.contains(id))
+}
+
 pub struct DetailsState {
     pub id: String,
     pub name: ScreenName,
     pub content: Option<AlbumFullDescription>,
     // Read the songs from here, not content (won't get more than the initial batch of songs)
     pub songs: SongListModel,
+    // Until they're applied, the cached songs are the ones shown (see SetPlaylistChanges)
+    pub changes: Option<(PlaylistChanges, SongBatch)>,
 }
 
 impl DetailsState {
@@ -64,7 +97,8 @@ impl UpdatableState for DetailsState {
         match action.as_ref() {
             BrowserAction::SetAlbumDetails(album) if album.description.id == self.id => {
                 let AlbumDescription { id, songs, .. } = album.description.clone();
-                self.songs.add(songs).commit();
+                // Anything loaded past the first batch might be stale, in case of a refresh
+                self.songs.clear().and(|s| s.add(songs)).commit();
                 self.content = Some(*album.clone());
                 vec![BrowserEvent::AlbumDetailsLoaded(id)]
             }
@@ -108,7 +142,8 @@ impl PlaylistDetailsState {
             id: id.clone(),
             name: ScreenName::PlaylistDetails(id),
             playlist: None,
-            songs: SongListModel::new(100),
+            songs: SongListModel::new_sparse(100),
+            changes: None,
         }
     }
 }
@@ -121,9 +156,28 @@ impl UpdatableState for PlaylistDetailsState {
         match action.as_ref() {
             BrowserAction::SetPlaylistDetails(playlist, song_batch) if playlist.id == self.id => {
                 let PlaylistDescription { id, .. } = *playlist.clone();
-                self.songs.add(*song_batch.clone()).commit();
+                let song_batch = *song_batch.clone();
+                self.songs.clear().and(|s| s.add(song_batch)).commit();
                 self.playlist = Some(*playlist.clone());
-                vec![BrowserEvent::PlaylistDetailsLoaded(id)]
+                let mut events = vec![BrowserEvent::PlaylistDetailsLoaded(id.clone())];
+                if self.changes.take().is_some() {
+                    events.push(BrowserEvent::PlaylistChangesUpdated(id));
+                }
+                events
+            }
+            BrowserAction::SetPlaylistChanges(id, changes, song_batch) if id == &self.id => {
+                self.changes = Some((*changes.clone(), *song_batch.clone()));
+                vec![BrowserEvent::PlaylistChangesUpdated(id.clone())]
+            }
+            BrowserAction::ApplyPlaylistChanges(id) if id == &self.id => {
+                let Some((_, song_batch)) = self.changes.take() else {
+                    return vec![];
+                };
+                self.songs.clear().and(|s| s.add(song_batch)).commit();
+                vec![
+                    BrowserEvent::PlaylistDetailsLoaded(id.clone()),
+                    BrowserEvent::PlaylistChangesUpdated(id.clone()),
+                ]
             }
             BrowserAction::UpdatePlaylistName(PlaylistSummary { id, title }) if id == &self.id => {
                 if let Some(p) = self.playlist.as_mut() {
@@ -135,10 +189,19 @@ impl UpdatableState for PlaylistDetailsState {
                 self.songs.add(*song_batch.clone()).commit();
                 vec![BrowserEvent::PlaylistTracksAppended(id.clone())]
             }
+            BrowserAction::FinishLoadingPlaylistTracks(id) if id == &self.id => {
+                vec![BrowserEvent::PlaylistTracksLoaded(id.clone())]
+            }
             BrowserAction::RemoveTracksFromPlaylist(id, uris) if id == &self.id => {
                 self.songs.remove(&uris[..]).commit();
                 vec![BrowserEvent::PlaylistTracksRemoved(self.id.clone())]
             }
+            BrowserAction::SetPlaylistSnapshot(id, snapshot_id) if id == &self.id => {
+                if let Some(p) = self.playlist.as_mut() {
+                    p.snapshot_id = Some(snapshot_id.clone());
+                }
+                vec![]
+            }
             _ => vec![],
         }
     }
@@ -148,9 +211,11 @@ pub struct ArtistState {
     pub id: String,
     pub name: ScreenName,
     pub artist: Option<String>,
+    pub is_followed: bool,
     pub next_page: Pagination<String>,
     pub albums: ListStore<AlbumModel>,
     pub top_tracks: SongListModel,
+    pub concerts: Vec<ConcertDescription>,
 }
 
 impl ArtistState {
@@ -159,9 +224,11 @@ impl ArtistState {
             id: id.clone(),
             name: ScreenName::Artist(id.clone()),
             artist: None,
+            is_followed: false,
             next_page: Pagination::new(id, 20),
             albums: ListStore::new(),
             top_tracks: SongListModel::new(10),
+            concerts: vec![],
         }
     }
 }
@@ -178,27 +245,72 @@ impl UpdatableState for ArtistState {
                     name,
                     albums,
                     mut top_tracks,
+                    is_followed,
                 } = *details.clone();
                 self.artist = Some(name);
+                self.is_followed = is_followed;
                 self.albums
                     .replace_all(albums.into_iter().map(|a| a.into()));
                 self.next_page.reset_count(self.albums.len());
 
                 top_tracks.truncate(5);
-                self.top_tracks.append(top_tracks).commit();
+                self.top_tracks
+                    .clear()
+                    .and(|s| s.append(top_tracks))
+                    .commit();
 
                 vec![BrowserEvent::ArtistDetailsUpdated(id)]
             }
             BrowserAction::AppendArtistReleases(id, albums) if id == &self.id => {
+                if is_page_loaded(&self.albums, albums.iter().map(|a| &a.id)) {
+                    return vec![];
+                }
                 self.next_page.set_loaded_count(albums.len());
                 self.albums.extend(albums.iter().map(|a| a.into()));
                 vec![BrowserEvent::ArtistDetailsUpdated(self.id.clone())]
             }
+            BrowserAction::SetArtistConcerts(id, concerts) if id == &self.id => {
+                self.concerts = concerts.clone();
+                vec![BrowserEvent::ArtistConcertsUpdated(id.clone())]
+            }
+            BrowserAction::FollowArtist(id) if id == &self.id && !self.is_followed => {
+                self.is_followed = true;
+                vec![BrowserEvent::ArtistFollowed(id.clone())]
+            }
+            BrowserAction::UnfollowArtist(id) if id == &self.id && self.is_followed => {
+                self.is_followed = false;
+                vec![BrowserEvent::ArtistUnfollowed(id.clone())]
+            }
             _ => vec![],
         }
     }
 }
 
+// One row of the "for you" page, each loading its playlists on its own
+pub struct HomeShelfState {
+    pub id: String,
+    pub title: String,
+    pub playlists: ListStore<AlbumModel>,
+    pub next_page: Pagination<()>,
+}
+
+impl HomeShelfState {
+    pub const BATCH_SIZE: usize = 10;
+
+    fn new(shelf: &HomeShelf) -> Self {
+        let mut playlists = ListStore::new();
+        playlists.extend(shelf.playlists.iter().map(|p| p.into()));
+        let mut next_page = Pagination::new((), Self::BATCH_SIZE);
+        next_page.reset_count(playlists.len());
+        Self {
+            id: shelf.id.clone(),
+            title: shelf.title.clone(),
+            playlists,
+            next_page,
+        }
+    }
+}
+
 // The "home" represents screens visible initially (saved albums, saved playlists, saved tracks)
 pub struct HomeState {
     pub name: ScreenName,
@@ -208,6 +320,14 @@ pub struct HomeState {
     pub next_playlists_page: Pagination<()>,
     pub playlists: ListStore<AlbumModel>,
     pub saved_tracks: SongListModel,
+    pub shelves: Vec<HomeShelfState>,
+    pub pinned_playlists: ListStore<AlbumModel>,
+    // Most recent first, the uri of each is a full spotify: URI
+    pub recently_viewed: ListStore<AlbumModel>,
+    // Albums only, most recent first
+    pub new_releases: ListStore<AlbumModel>,
+    pub next_audiobooks_page: Pagination<()>,
+    pub audiobooks: ListStore<AlbumModel>,
 }
 
 impl Default for HomeState {
@@ -220,6 +340,12 @@ impl Default for HomeState {
             next_playlists_page: Pagination::new((), 30),
             playlists: ListStore::new(),
             saved_tracks: SongListModel::new(50),
+            shelves: vec![],
+            pinned_playlists: ListStore::new(),
+            recently_viewed: ListStore::new(),
+            new_releases: ListStore::new(),
+            next_audiobooks_page: Pagination::new((), 30),
+            audiobooks: ListStore::new(),
         }
     }
 }
@@ -247,9 +373,12 @@ impl UpdatableState for HomeState {
                 self.playlists.prepend(content.iter().map(|a| a.into()));
                 vec![BrowserEvent::SavedPlaylistsUpdated]
             }
-            BrowserAction::AppendLibraryContent(content) => {
-                self.next_albums_page.set_loaded_count(content.len());
-                self.albums.extend(content.iter().map(|a| a.into()));
+            BrowserAction::AppendLibraryContent(page) => {
+                if is_page_loaded(&self.albums, page.items.iter().map(|a| &a.id)) {
+                    return vec![];
+                }
+                self.next_albums_page.set_next(page.next.clone());
+                self.albums.extend(page.items.iter().map(|a| a.into()));
                 vec![BrowserEvent::LibraryUpdated]
             }
             BrowserAction::SaveAlbum(album) => {
@@ -283,6 +412,9 @@ impl UpdatableState for HomeState {
                 }
             }
             BrowserAction::AppendPlaylistsContent(content) => {
+                if is_page_loaded(&self.playlists, content.iter().map(|p| &p.id)) {
+                    return vec![];
+                }
                 self.next_playlists_page.set_loaded_count(content.len());
                 self.playlists.extend(content.iter().map(|p| p.into()));
                 vec![BrowserEvent::SavedPlaylistsUpdated]
@@ -321,6 +453,69 @@ impl UpdatableState for HomeState {
                 self.saved_tracks.remove(&tracks[..]).commit();
                 vec![BrowserEvent::SavedTracksUpdated]
             }
+            BrowserAction::SetHomeShelves(shelves) => {
+                self.shelves = shelves.iter().map(HomeShelfState::new).collect();
+                vec![BrowserEvent::HomeShelvesUpdated]
+            }
+            BrowserAction::AppendHomeShelf(id, content) => {
+                let Some(shelf) = self.shelves.iter_mut().find(|s| &s.id == id) else {
+                    return vec![];
+                };
+                if is_page_loaded(&shelf.playlists, content.iter().map(|p| &p.id)) {
+                    return vec![];
+                }
+                shelf.next_page.set_loaded_count(content.len());
+                shelf.playlists.extend(content.iter().map(|p| p.into()));
+                vec![BrowserEvent::HomeShelfAppended(id.clone())]
+            }
+            BrowserAction::SetSavedAudiobooks(content) => {
+                if !self.audiobooks.eq(content, |a, b| a.uri() == b.id) {
+                    self.audiobooks
+                        .replace_all(content.iter().map(|a| a.into()));
+                    self.next_audiobooks_page.reset_count(self.audiobooks.len());
+                    vec![BrowserEvent::SavedAudiobooksUpdated]
+                } else {
+                    vec![]
+                }
+            }
+            BrowserAction::AppendSavedAudiobooks(content) => {
+                if is_page_loaded(&self.audiobooks, content.iter().map(|a| &a.id)) {
+                    return vec![];
+                }
+                self.next_audiobooks_page.set_loaded_count(content.len());
+                self.audiobooks.extend(content.iter().map(|a| a.into()));
+                vec![BrowserEvent::SavedAudiobooksUpdated]
+            }
+            BrowserAction::SetPinnedPlaylists(content) => {
+                if !self.pinned_playlists.eq(content, |a, b| a.uri() == b.id) {
+                    self.pinned_playlists
+                        .replace_all(content.iter().map(|p| p.into()));
+                    vec![BrowserEvent::PinnedPlaylistsUpdated]
+                } else {
+                    vec![]
+                }
+            }
+            BrowserAction::SetRecentlyViewed(items) => {
+                if !self
+                    .recently_viewed
+                    .eq(items, |a, b| a.uri() == b.uri && a.album() == b.title)
+                {
+                    self.recently_viewed
+                        .replace_all(items.iter().map(|i| i.into()));
+                    vec![BrowserEvent::RecentlyViewedUpdated]
+                } else {
+                    vec![]
+                }
+            }
+            BrowserAction::SetNewReleases(releases) => {
+                if !self.new_releases.eq(releases, |a, b| a.uri() == b.album_id) {
+                    self.new_releases
+                        .replace_all(releases.iter().map(|r| r.into()));
+                    vec![BrowserEvent::NewReleasesUpdated]
+                } else {
+                    vec![]
+                }
+            }
             _ => vec![],
         }
     }
@@ -405,6 +600,9 @@ impl UpdatableState for UserState {
                 vec![BrowserEvent::UserDetailsUpdated(id)]
             }
             BrowserAction::AppendUserPlaylists(id, playlists) if id == &self.id => {
+                if is_page_loaded(&self.playlists, playlists.iter().map(|p| &p.id)) {
+                    return vec![];
+                }
                 self.next_page.set_loaded_count(playlists.len());
                 self.playlists.extend(playlists.iter().map(|p| p.into()));
                 vec![BrowserEvent::UserDetailsUpdated(self.id.clone())]
@@ -419,6 +617,20 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_screen_name_from_identifier() {
+        let names = vec![
+            ScreenName::Home,
+            ScreenName::Search,
+            ScreenName::AlbumDetails("a_b".to_owned()),
+            ScreenName::User("user".to_owned()),
+        ];
+        for name in names {
+            assert_eq!(ScreenName::from_identifier(&name.identifier()), Some(name));
+        }
+        assert_eq!(ScreenName::from_identifier("nope_1"), None);
+    }
+
     #[test]
     fn test_next_page_no_next() {
         let mut artist_state = ArtistState::new("id".to_owned());
@@ -428,11 +640,37 @@ mod tests {
                 name: "Foo".to_owned(),
                 albums: vec![],
                 top_tracks: vec![],
+                is_followed: false,
             },
         ))));
 
         let next = artist_state.next_page;
-        assert_eq!(None, next.next_offset);
+        assert_eq!(None, next.next_offset());
+    }
+
+    #[test]
+    fn test_follow_artist() {
+        let id = "id".to_string();
+        let mut artist_state = ArtistState::new(id.clone());
+
+        let events = artist_state.update_with(Cow::Owned(BrowserAction::FollowArtist(id.clone())));
+        assert_eq!(events, vec![BrowserEvent::ArtistFollowed(id.clone())]);
+        assert!(artist_state.is_followed);
+
+        let events = artist_state.update_with(Cow::Owned(BrowserAction::FollowArtist(id.clone())));
+        assert!(events.is_empty());
+
+        let events = artist_state.update_with(Cow::Owned(BrowserAction::UnfollowArtist(
+            "other".to_string(),
+        )));
+        assert!(events.is_empty());
+
+        let events = artist_state.update_with(Cow::Owned(BrowserAction::UnfollowArtist(id)));
+        assert_eq!(
+            events,
+            vec![BrowserEvent::ArtistUnfollowed("id".to_string())]
+        );
+        assert!(!artist_state.is_followed);
     }
 
     #[test]
@@ -454,11 +692,12 @@ mod tests {
                 name: "Foo".to_owned(),
                 albums: (0..20).map(|_| fake_album.clone()).collect(),
                 top_tracks: vec![],
+                is_followed: false,
             },
         ))));
 
         let next = &artist_state.next_page;
-        assert_eq!(Some(20), next.next_offset);
+        assert_eq!(Some(20), next.next_offset());
 
         artist_state.update_with(Cow::Owned(BrowserAction::AppendArtistReleases(
             id.clone(),
@@ -466,6 +705,139 @@ mod tests {
         )));
 
         let next = &artist_state.next_page;
-        assert_eq!(None, next.next_offset);
+        assert_eq!(None, next.next_offset());
+    }
+
+    #[test]
+    fn test_same_page_appended_twice() {
+        let album = |id: usize| AlbumDescription {
+            id: format!("album{id}"),
+            title: "".to_owned(),
+            artists: vec![],
+            release_date: None,
+            art: None,
+            songs: SongBatch::empty(),
+            is_liked: false,
+        };
+        let mut home_state = HomeState::default();
+        home_state.update_with(Cow::Owned(BrowserAction::SetLibraryContent(
+            (0..30).map(album).collect(),
+        )));
+
+        let page = Paged {
+            items: (30..60).map(album).collect(),
+            next: Some(PageToken::Offset(60)),
+        };
+        let events = home_state.update_with(Cow::Owned(BrowserAction::AppendLibraryContent(
+            page.clone(),
+        )));
+        assert_eq!(events, vec![BrowserEvent::LibraryUpdated]);
+
+        let events = home_state.update_with(Cow::Owned(BrowserAction::AppendLibraryContent(page)));
+        assert!(events.is_empty());
+        assert_eq!(home_state.albums.len(), 60);
+        assert_eq!(Some(60), home_state.next_albums_page.next_offset());
+    }
+
+    #[test]
+    fn test_home_shelf_pagination() {
+        let playlist = |id: usize| PlaylistDescription {
+            id: format!("playlist{id}"),
+            title: "".to_owned(),
+            art: None,
+            songs: SongBatch::empty(),
+            owner: UserRef {
+                id: "spotify".to_owned(),
+                display_name: "Spotify".to_owned(),
+            },
+            snapshot_id: None,
+        };
+        let mut home_state = HomeState::default();
+        let events =
+            home_state.update_with(Cow::Owned(BrowserAction::SetHomeShelves(vec![HomeShelf {
+                id: "mixes".to_owned(),
+                title: "Your mixes".to_owned(),
+                playlists: (0..10).map(playlist).collect(),
+            }])));
+        assert_eq!(events, vec![BrowserEvent::HomeShelvesUpdated]);
+        assert_eq!(Some(10), home_state.shelves[0].next_page.next_offset());
+
+        let events = home_state.update_with(Cow::Owned(BrowserAction::AppendHomeShelf(
+            "mixes".to_owned(),
+            (10..13).map(playlist).collect(),
+        )));
+        assert_eq!(
+            events,
+            vec![BrowserEvent::HomeShelfAppended("mixes".to_owned())]
+        );
+        assert_eq!(home_state.shelves[0].playlists.len(), 13);
+        assert_eq!(None, home_state.shelves[0].next_page.next_offset());
+
+        let events = home_state.update_with(Cow::Owned(BrowserAction::AppendHomeShelf(
+            "unknown".to_owned(),
+            (20..30).map(playlist).collect(),
+        )));
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_playlist_snapshot_follows_edits() {
+        let playlist = PlaylistDescription {
+            id: "playlist".to_owned(),
+            title: "".to_owned(),
+            art: None,
+            songs: SongBatch::empty(),
+            owner: UserRef {
+                id: "me".to_owned(),
+                display_name: "Me".to_owned(),
+            },
+            snapshot_id: Some("v1".to_owned()),
+        };
+        let mut state = PlaylistDetailsState::new("playlist".to_owned());
+        state.update_with(Cow::Owned(BrowserAction::SetPlaylistDetails(
+            Box::new(playlist),
+            Box::new(SongBatch::empty()),
+        )));
+
+        state.update_with(Cow::Owned(BrowserAction::SetPlaylistSnapshot(
+            "other".to_owned(),
+            "v3".to_owned(),
+        )));
+        state.update_with(Cow::Owned(BrowserAction::SetPlaylistSnapshot(
+            "playlist".to_owned(),
+            "v2".to_owned(),
+        )));
+        assert_eq!(
+            state.playlist.and_then(|p| p.snapshot_id),
+            Some("v2".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_apply_playlist_changes() {
+        let mut state = PlaylistDetailsState::new("playlist".to_owned());
+        let events = state.update_with(Cow::Owned(BrowserAction::ApplyPlaylistChanges(
+            "playlist".to_owned(),
+        )));
+        assert!(events.is_empty());
+
+        state.update_with(Cow::Owned(BrowserAction::SetPlaylistChanges(
+            "playlist".to_owned(),
+            Box::default(),
+            Box::new(SongBatch::empty()),
+        )));
+        assert!(state.changes.is_some());
+
+        let events = state.update_with(Cow::Owned(BrowserAction::ApplyPlaylistChanges(
+            "playlist".to_owned(),
+        )));
+        assert_eq!(
+            events,
+            vec![
+                BrowserEvent::PlaylistDetailsLoaded("playlist".to_owned()),
+                BrowserEvent::PlaylistChangesUpdated("playlist".to_owned()),
+            ]
+        );
+        assert!(state.changes.is_none());
     }
 }
@@ -8,6 +8,11 @@ use crate::app::state::{AppAction, AppEvent, UpdatableState};
 pub enum SelectionAction {
     Select(Vec<SongDescription>),
     Deselect(Vec<String>),
+    // Selects whichever of these songs weren't, and deselects the others
+    Invert(Vec<SongDescription>),
+    // Asks the list on screen for all of its songs, see SelectionEvent
+    SelectAll,
+    InvertAll,
     Clear,
 }
 
@@ -22,6 +27,9 @@ pub enum SelectionEvent {
     // Mode means selection active or not
     SelectionModeChanged(bool),
     SelectionChanged,
+    // For the list on screen to answer with its songs (it's the one that knows them)
+    SelectAllRequested,
+    InvertRequested,
 }
 
 impl From<SelectionEvent> for AppEvent {
@@ -107,6 +115,10 @@ impl SelectionState {
         self.selected_songs_ids.len()
     }
 
+    pub fn total_duration(&self) -> u64 {
+        self.selected_songs.iter().map(|s| s.duration as u64).sum()
+    }
+
     // Clears (!) the selection, returns associated memory
     pub fn take_selection(&mut self) -> Vec<SongDescription> {
         std::mem::take(self).selected_songs
@@ -144,6 +156,21 @@ impl UpdatableState for SelectionState {
                     vec![]
                 }
             }
+            SelectionAction::Invert(tracks) => {
+                let changed = !tracks.is_empty();
+                for track in tracks {
+                    if !self.deselect(&track.id) {
+                        self.select(track);
+                    }
+                }
+                if changed {
+                    vec![SelectionEvent::SelectionChanged]
+                } else {
+                    vec![]
+                }
+            }
+            SelectionAction::SelectAll => vec![SelectionEvent::SelectAllRequested],
+            SelectionAction::InvertAll => vec![SelectionEvent::InvertRequested],
             SelectionAction::Clear => {
                 self.take_selection();
                 vec![SelectionEvent::SelectionModeChanged(false)]
@@ -151,3 +178,28 @@ impl UpdatableState for SelectionState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::test_utils::song;
+
+    #[test]
+    fn test_invert() {
+        let mut state = SelectionState::default();
+        state.set_mode(Some(SelectionContext::Default));
+        state.update_with(Cow::Owned(SelectionAction::Select(vec![song("a")])));
+        state.update_with(Cow::Owned(SelectionAction::Invert(vec![
+            song("a"),
+            song("b"),
+            song("c"),
+        ])));
+
+        assert!(!state.is_song_selected("a"));
+        assert!(state.is_song_selected("b"));
+        assert!(state.is_song_selected("c"));
+        assert_eq!(state.count(), 2);
+        assert_eq!(state.total_duration(), 2000);
+    }
+}
@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::app::kv_store::KeyValueStore;
+use crate::app::models::{Batch, RepeatMode, SongBatch, SongDescription};
+use crate::app::state::{AppAction, AppState, BrowserAction, PlaybackAction, ScreenName};
+use crate::app::SongsSource;
+
+pub const RESTORE_STATE_FLAG: &str = "restore-state";
+
+// Same name as the single snapshot it used to be, so that one just gets replaced
+fn last_session_store() -> KeyValueStore<StateSnapshot> {
+    KeyValueStore::open("last-session")
+}
+
+// The single queue from before they were kept per user
+const LEGACY_KEY: &str = "queue";
+
+// What's needed to get the app back to where it was, for bug reports.
+// Nothing related to the user (credentials, token, name...) ends up in here!
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StateSnapshot {
+    screens: Vec<ScreenName>,
+    playback: PlaybackSnapshot,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PlaybackSnapshot {
+    songs: Vec<SongDescription>,
+    source: Option<SongsSource>,
+    current_song_id: Option<String>,
+    position: u32,
+    is_playing: bool,
+    repeat: RepeatMode,
+    is_shuffled: bool,
+}
+
+impl StateSnapshot {
+    pub fn of(state: &AppState) -> Self {
+        let playback = &state.playback;
+        Self {
+            screens: state.browser.screen_names().cloned().collect(),
+            playback: PlaybackSnapshot {
+                songs: playback.songs().collect(),
+                source: playback.current_source().cloned(),
+                current_song_id: playback.current_song_id(),
+                position: playback.seek_position(),
+                is_playing: playback.is_playing(),
+                repeat: playback.repeat_mode(),
+                is_shuffled: playback.is_shuffled(),
+            },
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+        let content = serde_json::to_vec_pretty(self)?;
+        std::fs::write(path, content)
+    }
+
+    fn load(path: &Path) -> Result<Self, std::io::Error> {
+        let content = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    // Only what's playing, for the next launch (screens are restored by the navigation itself)
+    pub fn queue_of(state: &AppState) -> Self {
+        Self {
+            screens: vec![],
+            ..Self::of(state)
+        }
+    }
+
+    // One per user, someone else logging in doesn't get it
+    pub fn save_last_session(self, user: &str) -> Result<(), std::io::Error> {
+        self.save_last_session_in(&mut last_session_store(), user)
+    }
+
+    fn save_last_session_in(
+        self,
+        store: &mut KeyValueStore<StateSnapshot>,
+        user: &str,
+    ) -> Result<(), std::io::Error> {
+        store.remove(LEGACY_KEY);
+        store.set(user.to_string(), self);
+        store.save()
+    }
+
+    pub fn last_session(user: &str, resume: bool) -> Option<Self> {
+        Self::last_session_in(last_session_store(), user, resume)
+    }
+
+    // Paused, unless asked to pick up right away (see the Startup preferences)
+    fn last_session_in(
+        mut store: KeyValueStore<StateSnapshot>,
+        user: &str,
+        resume: bool,
+    ) -> Option<Self> {
+        let mut snapshot = store.remove(user)?;
+        snapshot.playback.is_playing = resume;
+        Some(snapshot)
+    }
+
+    // Logging out leaves nothing behind
+    pub fn forget_last_session(user: &str) -> Result<(), std::io::Error> {
+        let mut store = last_session_store();
+        if store.remove(user).is_none() {
+            return Ok(());
+        }
+        store.save()
+    }
+
+    // Given with --restore-state=<file> (developers only, there's no UI to get there)
+    pub fn from_args() -> Option<Self> {
+        let flag = format!("--{RESTORE_STATE_FLAG}");
+        let mut args = std::env::args();
+        let path: PathBuf = loop {
+            let arg = args.next()?;
+            if arg == flag {
+                break args.next()?.into();
+            } else if let Some(path) = arg.strip_prefix(&format!("{flag}=")) {
+                break path.into();
+            }
+        };
+        Self::load(&path)
+            .map_err(|e| warn!("Could not restore state from {}: {}", path.display(), e))
+            .ok()
+    }
+
+    // The state is rebuilt by replaying actions, like anything else would
+    pub fn into_actions(self) -> Vec<AppAction> {
+        let mut actions: Vec<AppAction> = self
+            .screens
+            .into_iter()
+            .filter(|screen| !matches!(screen, ScreenName::Home))
+            .map(|screen| BrowserAction::NavigationPush(screen).into())
+            .collect();
+
+        let playback = self.playback;
+        if playback.songs.is_empty() {
+            return actions;
+        }
+
+        actions.push(PlaybackAction::SetRepeatMode(playback.repeat).into());
+        let songs = playback.songs;
+        actions.push(match playback.source {
+            Some(source) => {
+                let total = songs.len();
+                let batch = Batch {
+                    offset: 0,
+                    batch_size: total,
+                    total,
+                };
+                PlaybackAction::LoadPagedSongs(source, SongBatch { songs, batch }).into()
+            }
+            None => PlaybackAction::LoadSongs(songs).into(),
+        });
+        actions.push(PlaybackAction::SetShuffled(playback.is_shuffled).into());
+        if let Some(id) = playback.current_song_id {
+            actions.push(PlaybackAction::Load(id).into());
+            actions.push(PlaybackAction::Seek(playback.position).into());
+            if !playback.is_playing {
+                actions.push(PlaybackAction::Pause.into());
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::app::test_utils::{song, TempDir};
+
+    #[test]
+    fn test_snapshot_replays_into_same_state() {
+        let mut state = AppState::new();
+        state.update_state(BrowserAction::NavigationPush(ScreenName::Search).into());
+        state.update_state(PlaybackAction::LoadSongs(vec![song("1"), song("2")]).into());
+        state.update_state(PlaybackAction::Load("2".to_string()).into());
+
+        let json = serde_json::to_string(&StateSnapshot::of(&state)).unwrap();
+        let snapshot: StateSnapshot = serde_json::from_str(&json).unwrap();
+
+        let mut restored = AppState::new();
+        for action in snapshot.into_actions() {
+            restored.update_state(action);
+        }
+
+        assert!(matches!(
+            restored.browser.current_screen(),
+            ScreenName::Search
+        ));
+        assert_eq!(restored.playback.songs().len(), 2);
+        assert_eq!(restored.playback.current_song_id(), Some("2".to_string()));
+        assert!(restored.playback.is_playing());
+    }
+
+    #[test]
+    fn test_last_session_keeps_the_queue_only() {
+        let mut state = AppState::new();
+        state.update_state(BrowserAction::NavigationPush(ScreenName::Search).into());
+        state.update_state(PlaybackAction::LoadSongs(vec![song("1"), song("2")]).into());
+        state.update_state(PlaybackAction::Load("1".to_string()).into());
+
+        let dir = TempDir::create();
+        let path = dir.join("last-session.json");
+        StateSnapshot::queue_of(&state)
+            .save_last_session_in(&mut KeyValueStore::open_at(path.clone()), "user")
+            .unwrap();
+
+        let store = KeyValueStore::open_at(path.clone());
+        assert!(StateSnapshot::last_session_in(store, "someone else", true).is_none());
+        for resume in [false, true] {
+            let store = KeyValueStore::open_at(path.clone());
+            let snapshot = StateSnapshot::last_session_in(store, "user", resume).unwrap();
+            let mut restored = AppState::new();
+            for action in snapshot.into_actions() {
+                restored.update_state(action);
+            }
+            assert!(matches!(
+                restored.browser.current_screen(),
+                ScreenName::Home
+            ));
+            assert_eq!(restored.playback.songs().len(), 2);
+            assert_eq!(restored.playback.current_song_id(), Some("1".to_string()));
+            assert_eq!(restored.playback.is_playing(), resume);
+        }
+        let store = KeyValueStore::open_at(dir.join("nothing.json"));
+        assert!(StateSnapshot::last_session_in(store, "user", true).is_none());
+    }
+
+    #[test]
+    fn test_empty_snapshot() {
+        let snapshot = StateSnapshot::of(&AppState::new());
+        assert!(snapshot.into_actions().is_empty());
+    }
+}
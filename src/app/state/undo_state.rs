@@ -0,0 +1,168 @@
+use std::time::{Duration, Instant};
+
+use crate::app::models::SongDescription;
+use crate::app::state::{AppAction, BrowserAction, SelectionState};
+
+// A bit longer than the toast offering to undo stays up
+const UNDO_WINDOW: Duration = Duration::from_secs(10);
+const MAX_UNDO_ENTRIES: usize = 20;
+
+// Something destructive that just happened, with what we need to revert it
+#[derive(Clone, Debug)]
+pub enum Undoable {
+    TracksRemovedFromPlaylist {
+        playlist_id: String,
+        uris: Vec<String>,
+    },
+    AlbumUnsaved(String),
+    TracksUnsaved(Vec<SongDescription>),
+}
+
+impl Undoable {
+    // Has to be called before the action is applied: the selection is gone afterwards
+    pub fn for_action(action: &AppAction, selection: &SelectionState) -> Option<Self> {
+        match action {
+            AppAction::BrowserAction(BrowserAction::RemoveTracksFromPlaylist(id, uris)) => {
+                Some(Self::TracksRemovedFromPlaylist {
+                    playlist_id: id.clone(),
+                    uris: uris.clone(),
+                })
+            }
+            AppAction::BrowserAction(BrowserAction::UnsaveAlbum(id)) => {
+                Some(Self::AlbumUnsaved(id.clone()))
+            }
+            AppAction::UnsaveSelection => Some(Self::TracksUnsaved(
+                selection.peek_selection().cloned().collect(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+// What can be undone, and what was undone and can be made again. Both stacks follow the same window
+#[derive(Default)]
+pub struct UndoState {
+    entries: Vec<(Instant, Undoable)>,
+    undone: Vec<(Instant, Undoable)>,
+}
+
+impl UndoState {
+    // Something new was removed: what was undone before can't be redone anymore
+    pub fn push(&mut self, undoable: Undoable) {
+        self.push_at(Instant::now(), undoable);
+    }
+
+    fn push_at(&mut self, at: Instant, undoable: Undoable) {
+        self.undone.clear();
+        push_capped(&mut self.entries, at, undoable);
+    }
+
+    // A redo went through, it can be undone again
+    pub fn push_redone(&mut self, undoable: Undoable) {
+        push_capped(&mut self.entries, Instant::now(), undoable);
+    }
+
+    // Most recent first, anything older than the undo window is forgotten
+    pub fn pop(&mut self) -> Option<Undoable> {
+        self.pop_at(Instant::now())
+    }
+
+    fn pop_at(&mut self, now: Instant) -> Option<Undoable> {
+        let undoable = pop_recent(&mut self.entries, now)?;
+        push_capped(&mut self.undone, now, undoable.clone());
+        Some(undoable)
+    }
+
+    // The last thing undone, it's back on the undo stack once made again (see push_redone)
+    pub fn pop_undone(&mut self) -> Option<Undoable> {
+        self.pop_undone_at(Instant::now())
+    }
+
+    fn pop_undone_at(&mut self, now: Instant) -> Option<Undoable> {
+        pop_recent(&mut self.undone, now)
+    }
+}
+
+fn push_capped(stack: &mut Vec<(Instant, Undoable)>, at: Instant, undoable: Undoable) {
+    stack.push((at, undoable));
+    if stack.len() > MAX_UNDO_ENTRIES {
+        stack.remove(0);
+    }
+}
+
+fn pop_recent(stack: &mut Vec<(Instant, Undoable)>, now: Instant) -> Option<Undoable> {
+    stack.retain(|(at, _)| now.saturating_duration_since(*at) <= UNDO_WINDOW);
+    stack.pop().map(|(_, undoable)| undoable)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn unsaved(id: &str) -> Undoable {
+        Undoable::AlbumUnsaved(id.to_string())
+    }
+
+    #[test]
+    fn test_pops_most_recent_first() {
+        let mut state = UndoState::default();
+        let now = Instant::now();
+        state.push_at(now, unsaved("1"));
+        state.push_at(now, unsaved("2"));
+
+        assert!(matches!(state.pop_at(now), Some(Undoable::AlbumUnsaved(id)) if id == "2"));
+        assert!(matches!(state.pop_at(now), Some(Undoable::AlbumUnsaved(id)) if id == "1"));
+        assert!(state.pop_at(now).is_none());
+    }
+
+    #[test]
+    fn test_expired_entries_are_dropped() {
+        let mut state = UndoState::default();
+        let then = Instant::now();
+        state.push_at(then, unsaved("1"));
+
+        assert!(state.pop_at(then + UNDO_WINDOW * 2).is_none());
+    }
+
+    #[test]
+    fn test_entries_are_capped() {
+        let mut state = UndoState::default();
+        let now = Instant::now();
+        for i in 0..(MAX_UNDO_ENTRIES + 5) {
+            state.push_at(now, unsaved(&i.to_string()));
+        }
+
+        let mut count = 0;
+        while state.pop_at(now).is_some() {
+            count += 1;
+        }
+        assert_eq!(count, MAX_UNDO_ENTRIES);
+    }
+
+    #[test]
+    fn test_undone_entries_can_be_redone() {
+        let mut state = UndoState::default();
+        let now = Instant::now();
+        state.push_at(now, unsaved("1"));
+        state.push_at(now, unsaved("2"));
+        state.pop_at(now);
+        state.pop_at(now);
+
+        assert!(matches!(state.pop_undone_at(now), Some(Undoable::AlbumUnsaved(id)) if id == "1"));
+        assert!(matches!(state.pop_undone_at(now), Some(Undoable::AlbumUnsaved(id)) if id == "2"));
+        assert!(state.pop_undone_at(now).is_none());
+    }
+
+    #[test]
+    fn test_new_entries_drop_what_was_undone() {
+        let mut state = UndoState::default();
+        let now = Instant::now();
+        state.push_at(now, unsaved("1"));
+        state.pop_at(now);
+        state.push_at(now, unsaved("2"));
+
+        assert!(state.pop_undone_at(now).is_none());
+        assert!(matches!(state.pop_at(now), Some(Undoable::AlbumUnsaved(id)) if id == "2"));
+    }
+}
@@ -0,0 +1,55 @@
+// Fixtures shared by the tests all over the app
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::app::models::{AlbumRef, SongDescription};
+
+// Nothing special about it, tests set what they care about with ..song(id)
+pub fn song(id: &str) -> SongDescription {
+    SongDescription {
+        id: id.to_string(),
+        track_number: None,
+        disc_number: None,
+        uri: format!("spotify:track:{id}"),
+        title: id.to_string(),
+        artists: vec![],
+        album: AlbumRef {
+            id: "".to_string(),
+            name: "".to_string(),
+        },
+        duration: 1000,
+        art: None,
+        chapters: Default::default(),
+        is_playable: true,
+        preview_url: None,
+        added_at: None,
+        popularity: None,
+    }
+}
+
+// An empty directory of its own for a test to write files in, gone once dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn create() -> Self {
+        // Tests run in parallel, each needs its own
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "spot-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
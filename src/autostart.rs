@@ -0,0 +1,90 @@
+use gettextrs::gettext;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use zbus::zvariant::Value;
+
+use crate::config;
+
+// Sandboxed apps can't write autostart entries themselves, the Background portal does it for them
+fn is_sandboxed() -> bool {
+    Path::new("/.flatpak-info").exists()
+}
+
+fn request_background(enabled: bool) -> zbus::Result<()> {
+    let connection = zbus::blocking::Connection::session()?;
+    let reason = gettext("Start Spot when you log in");
+    let options: HashMap<&str, Value> = HashMap::from([
+        ("reason", Value::from(reason.as_str())),
+        ("autostart", Value::from(enabled)),
+        ("commandline", Value::from(vec!["spot"])),
+    ]);
+    // The answer comes later as a signal, what's being asked is enough for us
+    connection.call_method(
+        Some("org.freedesktop.portal.Desktop"),
+        "/org/freedesktop/portal/desktop",
+        Some("org.freedesktop.portal.Background"),
+        "RequestBackground",
+        &("", options),
+    )?;
+    Ok(())
+}
+
+fn entry_path() -> PathBuf {
+    glib::user_config_dir()
+        .join("autostart")
+        .join(format!("{}.desktop", config::APPID))
+}
+
+fn exec() -> String {
+    let Ok(path) = std::env::current_exe() else {
+        return "spot".to_string();
+    };
+    let path = path.display().to_string();
+    if path.contains(char::is_whitespace) {
+        format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        path
+    }
+}
+
+fn entry(exec: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Spot\n\
+         Icon={}\n\
+         Exec={}\n\
+         NoDisplay=true\n\
+         X-GNOME-Autostart-enabled=true\n",
+        config::APPID,
+        exec
+    )
+}
+
+fn write_entry(enabled: bool) -> std::io::Result<()> {
+    let path = entry_path();
+    if !enabled {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, entry(&exec()))
+}
+
+// The entry belongs to Spot: it's rewritten on every launch (in case Spot moved) and whenever the preference changes
+pub fn sync(enabled: bool) {
+    if is_sandboxed() {
+        // Not worth holding up the start for
+        std::thread::spawn(move || {
+            if let Err(e) = request_background(enabled) {
+                warn!("Could not update the autostart entry: {}", e);
+            }
+        });
+    } else if let Err(e) = write_entry(enabled) {
+        warn!("Could not update the autostart entry: {}", e);
+    }
+}
@@ -11,25 +11,58 @@ use crate::app::AppAction;
 mod player;
 pub use player::ConnectCommand;
 
+const DEALER_RETRY_DELAY: Duration = Duration::from_secs(10);
+// Devices changing should get pushed to us, this is in case we miss it
+const DEVICES_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
 #[tokio::main]
 async fn connect_server(
     api: Arc<dyn SpotifyApiClient + Send + Sync>,
     action_sender: UnboundedSender<AppAction>,
     receiver: UnboundedReceiver<ConnectCommand>,
 ) {
-    let player = Arc::new(player::ConnectPlayer::new(api, action_sender));
+    let player = Arc::new(player::ConnectPlayer::new(Arc::clone(&api), action_sender));
+
+    let player_clone = Arc::clone(&player);
+    task::spawn(async move {
+        loop {
+            // Only once the dealer says we're registered (see PlayerEvent::Connected) does polling stop
+            let (sender, receiver) = unbounded();
+            let (result, _) = futures::join!(
+                api.listen_player_events(Box::new(move |event| {
+                    let _ = sender.unbounded_send(event);
+                })),
+                receiver.for_each(|event| player_clone.handle_player_event(event))
+            );
+            player_clone.set_push_connected(false);
+            if let Err(e) = result {
+                debug!("dealer connection failed: {:?}", e);
+            }
+            time::sleep(DEALER_RETRY_DELAY).await;
+        }
+    });
 
+    // Polling is only a fallback for when the dealer connection is down
     let player_clone = Arc::clone(&player);
     task::spawn(async move {
         let mut interval = time::interval(Duration::from_secs(5));
         loop {
             interval.tick().await;
-            if player_clone.has_device() {
+            if player_clone.has_device() && !player_clone.is_push_connected() {
                 player_clone.sync_state().await;
             }
         }
     });
 
+    let player_clone = Arc::clone(&player);
+    task::spawn(async move {
+        let mut interval = time::interval(DEVICES_REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            player_clone.refresh_devices().await;
+        }
+    });
+
     receiver
         .for_each(|command| async { player.handle_command(command).await.unwrap() })
         .await;
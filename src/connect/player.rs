@@ -1,11 +1,12 @@
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 
 use futures::channel::mpsc::UnboundedSender;
 use gettextrs::gettext;
 
-use crate::api::{SpotifyApiClient, SpotifyApiError, SpotifyResult};
+use crate::api::{PlayerEvent, SpotifyApiClient, SpotifyApiError, SpotifyResult};
 use crate::app::models::{ConnectPlayerState, RepeatMode, SongDescription};
 use crate::app::state::{Device, PlaybackAction};
 use crate::app::{AppAction, SongsSource};
@@ -29,6 +30,20 @@ pub enum ConnectCommand {
     PlayerRepeat(RepeatMode),
     PlayerShuffle(bool),
     PlayerSetVolume(u8),
+    PlayerQueue(Vec<String>),
+    // Like SetDevice, except we're the ones telling the device what to play
+    TransferPlayback {
+        device_id: String,
+        load: Option<Box<ConnectCommand>>,
+        position_ms: u32,
+    },
+}
+
+fn track_uris(songs: Vec<String>) -> Vec<String> {
+    songs
+        .into_iter()
+        .map(|s| format!("spotify:track:{}", s))
+        .collect()
 }
 
 pub struct ConnectPlayer {
@@ -37,6 +52,7 @@ pub struct ConnectPlayer {
     device_id: RwLock<Option<String>>,
     last_queue: RwLock<u64>,
     last_state: RwLock<ConnectPlayerState>,
+    push_connected: AtomicBool,
 }
 
 impl ConnectPlayer {
@@ -50,6 +66,7 @@ impl ConnectPlayer {
             device_id: Default::default(),
             last_queue: Default::default(),
             last_state: Default::default(),
+            push_connected: Default::default(),
         }
     }
 
@@ -68,25 +85,26 @@ impl ConnectPlayer {
         ]);
     }
 
-    async fn get_queue_if_changed(&self) -> Option<Vec<SongDescription>> {
+    fn has_queue_changed(&self, songs: &[SongDescription]) -> bool {
         let last_queue = *self.last_queue.read().ok().as_deref().unwrap_or(&0u64);
-        let songs = self.api.get_player_queue().await.ok();
-        songs.filter(|songs| {
-            let hash = {
-                let mut hasher = DefaultHasher::new();
-                songs.hash(&mut hasher);
-                hasher.finish()
-            };
-            if let Some(last_queue) = self.last_queue.try_write().ok().as_deref_mut() {
-                *last_queue = hash;
-            }
-            hash != last_queue
-        })
+        let hash = {
+            let mut hasher = DefaultHasher::new();
+            songs.hash(&mut hasher);
+            hasher.finish()
+        };
+        if let Some(last_queue) = self.last_queue.try_write().ok().as_deref_mut() {
+            *last_queue = hash;
+        }
+        hash != last_queue
     }
 
-    async fn apply_remote_state(&self, state: &ConnectPlayerState) {
-        if let Some(songs) = self.get_queue_if_changed().await {
-            self.send_actions([PlaybackAction::LoadSongs(songs).into()]);
+    async fn apply_remote_state(&self, state: &mut ConnectPlayerState) {
+        if let Ok(songs) = self.api.get_player_queue().await {
+            // The first one is what's currently playing
+            state.queue = songs.iter().skip(1).map(|s| s.id.clone()).collect();
+            if self.has_queue_changed(&songs) {
+                self.send_actions([PlaybackAction::LoadSongs(songs).into()]);
+            }
         }
 
         let play_pause = if state.is_playing {
@@ -110,6 +128,30 @@ impl ConnectPlayer {
             .unwrap_or(false)
     }
 
+    // While we're getting pushed updates, polling is unnecessary
+    pub fn is_push_connected(&self) -> bool {
+        self.push_connected.load(Ordering::Relaxed)
+    }
+
+    pub fn set_push_connected(&self, connected: bool) {
+        self.push_connected.store(connected, Ordering::Relaxed);
+    }
+
+    async fn update_state(&self, mut state: ConnectPlayerState) {
+        self.apply_remote_state(&mut state).await;
+        let volume_changed = self
+            .last_state
+            .read()
+            .map(|last_state| last_state.volume != state.volume)
+            .unwrap_or(true);
+        if let Some(volume) = state.volume.filter(|_| volume_changed) {
+            self.send_actions([PlaybackAction::SyncVolume(volume as f64 / 100f64).into()]);
+        }
+        if let Ok(mut last_state) = self.last_state.write() {
+            *last_state = state;
+        }
+    }
+
     pub async fn sync_state(&self) {
         debug!("polling connect device...");
         let player_state = self.api.player_state().await;
@@ -117,9 +159,25 @@ impl ConnectPlayer {
             self.device_lost();
             return;
         };
-        self.apply_remote_state(&state).await;
-        if let Ok(mut last_state) = self.last_state.write() {
-            *last_state = state;
+        self.update_state(state).await;
+    }
+
+    pub async fn refresh_devices(&self) {
+        match self.api.list_available_devices().await {
+            Ok(devices) => self.send_actions([PlaybackAction::SetAvailableDevices(devices).into()]),
+            Err(e) => debug!("could not refresh devices: {:?}", e),
+        }
+    }
+
+    pub async fn handle_player_event(&self, event: PlayerEvent) {
+        match event {
+            PlayerEvent::Connected => self.set_push_connected(true),
+            PlayerEvent::StateChanged(state) if self.has_device() => {
+                debug!("connect state pushed");
+                self.update_state(*state).await;
+            }
+            PlayerEvent::DevicesChanged => self.refresh_devices().await,
+            _ => {}
         }
     }
 
@@ -146,7 +204,7 @@ impl ConnectPlayer {
         if is_diff_song {
             let context = source.spotify_uri().unwrap();
             self.api
-                .player_play_in_context(device_id, context, offset)
+                .player_play_in_context(device_id, context, offset, None)
                 .await
         } else if is_paused {
             self.api.player_resume(device_id).await
@@ -164,6 +222,10 @@ impl ConnectPlayer {
         let ConnectCommand::PlayerLoad { songs, offset } = command else {
             panic!("Illegal call");
         };
+        // Most likely picked from the device's own queue, skipping keeps the rest of it intact
+        if let Some(skips) = current_state.skips_to(&songs[offset]) {
+            return self.skip_forward(device_id, skips).await;
+        }
         let is_diff_song = current_state
             .current_song_id
             .as_ref()
@@ -172,14 +234,7 @@ impl ConnectPlayer {
         let is_paused = !current_state.is_playing;
         if is_diff_song {
             self.api
-                .player_play_no_context(
-                    device_id,
-                    songs
-                        .into_iter()
-                        .map(|s| format!("spotify:track:{}", s))
-                        .collect(),
-                    offset,
-                )
+                .player_play_no_context(device_id, track_uris(songs), offset, None)
                 .await
         } else if is_paused {
             self.api.player_resume(device_id).await
@@ -188,6 +243,20 @@ impl ConnectPlayer {
         }
     }
 
+    async fn skip_forward(&self, device_id: String, skips: usize) -> SpotifyResult<()> {
+        for _ in 0..skips {
+            self.api.player_next(device_id.clone()).await?;
+        }
+        Ok(())
+    }
+
+    async fn add_to_queue(&self, device_id: String, songs: Vec<String>) -> SpotifyResult<()> {
+        for uri in track_uris(songs) {
+            self.api.player_add_to_queue(device_id.clone(), uri).await?;
+        }
+        Ok(())
+    }
+
     async fn handle_other_command(
         &self,
         device_id: String,
@@ -219,6 +288,30 @@ impl ConnectPlayer {
             ConnectCommand::PlayerSetVolume(volume) => {
                 self.api.player_volume(device_id, volume).await
             }
+            ConnectCommand::PlayerQueue(songs) => self.add_to_queue(device_id, songs).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn handle_transfer(
+        &self,
+        device_id: String,
+        load: Option<ConnectCommand>,
+        position_ms: u32,
+    ) -> SpotifyResult<()> {
+        let position_ms = Some(position_ms);
+        match load {
+            Some(ConnectCommand::PlayerLoadInContext { source, offset, .. }) => {
+                let context = source.spotify_uri().unwrap();
+                self.api
+                    .player_play_in_context(device_id, context, offset, position_ms)
+                    .await
+            }
+            Some(ConnectCommand::PlayerLoad { songs, offset }) => {
+                self.api
+                    .player_play_no_context(device_id, track_uris(songs), offset, position_ms)
+                    .await
+            }
             _ => Ok(()),
         }
     }
@@ -230,6 +323,20 @@ impl ConnectPlayer {
                 self.sync_state().await;
                 false
             }
+            ConnectCommand::TransferPlayback {
+                device_id,
+                load,
+                position_ms,
+            } => {
+                self.device_id.write().ok()?.replace(device_id.clone());
+                let result = self
+                    .handle_transfer(device_id, load.map(|load| *load), position_ms)
+                    .await;
+                if result.is_ok() {
+                    self.sync_state().await;
+                }
+                matches!(result, Err(SpotifyApiError::BadStatus(404, _)))
+            }
             ConnectCommand::PlayerStop => {
                 let device_id = self.device_id.write().ok()?.take();
                 if let Some(old_id) = device_id {
@@ -3,12 +3,16 @@ use std::rc::Rc;
 
 use crate::app::{
     components::EventListener,
+    loader::ImageLoader,
     models::{RepeatMode, SongDescription},
     state::PlaybackEvent,
     AppEvent, AppModel,
 };
 
-use super::types::{LoopStatus, PlaybackStatus, TrackMetadata};
+use super::types::{track_path, LoopStatus, PlaybackStatus, TrackMetadata};
+
+// Same as the playback widget, so that we get to share the file
+const ART_EXT: &str = "jpg";
 
 #[derive(Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -39,26 +43,96 @@ impl AppPlaybackStateListener {
         Self { app_model, sender }
     }
 
+    // The cover of the song, or failing that the one of its album (if we've seen it)
+    fn remote_art(&self, song: &SongDescription) -> Option<String> {
+        song.art.clone().or_else(|| {
+            let state = self.app_model.get_state();
+            let album = state.browser.details_state(&song.album.id)?;
+            album.content.as_ref()?.description.art.clone()
+        })
+    }
+
+    // Local files are more reliable for desktops to show, the remote url will do until we have one
+    fn art_url(&self, song: &SongDescription) -> Option<String> {
+        let url = self.remote_art(song)?;
+        let file = ImageLoader::new()
+            .cached_file(&url, ART_EXT)
+            .and_then(|path| glib::filename_to_uri(path, None).ok());
+        Some(file.map(|uri| uri.to_string()).unwrap_or(url))
+    }
+
     fn make_track_meta(&self) -> Option<TrackMetadata> {
+        let song = self.app_model.get_state().playback.current_song()?;
+        let art = self.art_url(&song);
         let SongDescription {
             id,
             title,
             artists,
             album,
             duration,
-            art,
+            track_number,
+            disc_number,
             ..
-        } = self.app_model.get_state().playback.current_song()?;
+        } = song;
         Some(TrackMetadata {
-            id: format!("/dev/alextren/Spot/Track/{id}"),
+            id: track_path(&id),
             length: 1000 * duration as u64,
             title,
             album: album.name,
             artist: artists.into_iter().map(|a| a.name).collect(),
             art,
+            track_number,
+            disc_number,
         })
     }
 
+    fn current_track_update(&self) -> MprisStateUpdate {
+        let current = self.make_track_meta();
+        let (has_prev, has_next) = self.has_prev_next();
+        MprisStateUpdate::SetCurrentTrack {
+            has_prev,
+            has_next,
+            current,
+        }
+    }
+
+    // Downloads the cover if needed, and sends the track again with it if it's still playing by then
+    fn fetch_art(&self, id: &str) {
+        let Some(song) = self.app_model.get_state().playback.current_song() else {
+            return;
+        };
+        let Some(url) = self.remote_art(&song) else {
+            return;
+        };
+        let loader = ImageLoader::new();
+        if loader.cached_file(&url, ART_EXT).is_some() {
+            return;
+        }
+        let id = id.to_string();
+        let listener = Self::new(self.app_model.clone(), self.sender.clone());
+        glib::MainContext::default().spawn_local(async move {
+            if loader.load_file(&url, ART_EXT).await.is_none() {
+                return;
+            }
+            let still_playing = listener
+                .app_model
+                .get_state()
+                .playback
+                .current_song_id()
+                .map(|current| current == id)
+                .unwrap_or(false);
+            if still_playing {
+                listener.send(listener.current_track_update());
+            }
+        });
+    }
+
+    fn send(&self, update: MprisStateUpdate) {
+        self.sender
+            .unbounded_send(update)
+            .expect("Could not send event to DBUS server");
+    }
+
     fn has_prev_next(&self) -> (bool, bool) {
         let state = self.app_model.get_state();
         (
@@ -87,14 +161,9 @@ impl AppPlaybackStateListener {
             PlaybackEvent::PlaybackStopped => {
                 Some(MprisStateUpdate::SetPlaying(PlaybackStatus::Stopped))
             }
-            PlaybackEvent::TrackChanged(_) => {
-                let current = self.make_track_meta();
-                let (has_prev, has_next) = self.has_prev_next();
-                Some(MprisStateUpdate::SetCurrentTrack {
-                    has_prev,
-                    has_next,
-                    current,
-                })
+            PlaybackEvent::TrackChanged(id) => {
+                self.fetch_art(id);
+                Some(self.current_track_update())
             }
             PlaybackEvent::RepeatModeChanged(_) => {
                 let loop_status = self.loop_status();
@@ -112,7 +181,9 @@ impl AppPlaybackStateListener {
                 let pos = 1000 * (*pos as u128);
                 Some(MprisStateUpdate::SetPositionMs(pos))
             }
-            PlaybackEvent::VolumeSet(vol) => Some(MprisStateUpdate::SetVolume(*vol)),
+            PlaybackEvent::VolumeSet(vol) | PlaybackEvent::VolumeSynced(vol) => {
+                Some(MprisStateUpdate::SetVolume(*vol))
+            }
             _ => None,
         }
     }
@@ -122,9 +193,7 @@ impl EventListener for AppPlaybackStateListener {
     fn on_event(&mut self, event: &AppEvent) {
         if let AppEvent::PlaybackEvent(event) = event {
             if let Some(update) = self.update_for(event) {
-                self.sender
-                    .unbounded_send(update)
-                    .expect("Could not send event to DBUS server");
+                self.send(update);
             }
         }
     }
@@ -0,0 +1,76 @@
+use futures::channel::mpsc::UnboundedSender;
+use futures::StreamExt;
+use zbus::{dbus_proxy, Connection};
+
+use crate::app::state::PlaybackAction;
+use crate::app::AppAction;
+
+const APP_NAME: &str = "Spot";
+
+// Settings daemons (GNOME's and its forks) hand media keys over to whichever app grabbed them last
+#[dbus_proxy(
+    interface = "org.gnome.SettingsDaemon.MediaKeys",
+    default_service = "org.gnome.SettingsDaemon.MediaKeys",
+    default_path = "/org/gnome/SettingsDaemon/MediaKeys"
+)]
+trait MediaKeys {
+    fn grab_media_player_keys(&self, application: &str, time: u32) -> zbus::Result<()>;
+
+    #[dbus_proxy(signal)]
+    fn media_player_key_pressed(&self, application: &str, key: &str) -> zbus::Result<()>;
+}
+
+fn action_for_key(key: &str) -> Option<AppAction> {
+    let action = match key {
+        // There's no separate pause key on most keyboards
+        "Play" => PlaybackAction::TogglePlay,
+        "Pause" => PlaybackAction::Pause,
+        "Stop" => PlaybackAction::Stop,
+        "Next" => PlaybackAction::Next,
+        "Previous" => PlaybackAction::Previous,
+        _ => return None,
+    };
+    Some(action.into())
+}
+
+// For desktops where nothing forwards media keys to MPRIS players
+pub async fn listen_media_keys(
+    connection: &Connection,
+    sender: UnboundedSender<AppAction>,
+) -> zbus::Result<()> {
+    let proxy = MediaKeysProxy::new(connection).await?;
+    let mut presses = proxy.receive_media_player_key_pressed().await?;
+    proxy.grab_media_player_keys(APP_NAME, 0).await?;
+
+    while let Some(press) = presses.next().await {
+        let args = press.args()?;
+        if *args.application() != APP_NAME {
+            continue;
+        }
+        if let Some(action) = action_for_key(args.key()) {
+            if sender.unbounded_send(action).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_action_for_key() {
+        assert!(matches!(
+            action_for_key("Play"),
+            Some(AppAction::PlaybackAction(PlaybackAction::TogglePlay))
+        ));
+        assert!(matches!(
+            action_for_key("Next"),
+            Some(AppAction::PlaybackAction(PlaybackAction::Next))
+        ));
+        assert!(action_for_key("Eject").is_none());
+    }
+}
@@ -14,11 +14,16 @@ mod types;
 mod listener;
 use listener::*;
 
+mod media_keys;
+use media_keys::listen_media_keys;
+
 #[tokio::main]
 async fn dbus_server(
     mpris: SpotMpris,
     player: SpotMprisPlayer,
     receiver: UnboundedReceiver<MprisStateUpdate>,
+    // Only set if we should grab media keys ourselves
+    media_keys_sender: Option<UnboundedSender<AppAction>>,
 ) -> zbus::Result<()> {
     let connection = Connection::session().await?;
     connection
@@ -33,6 +38,15 @@ async fn dbus_server(
         .request_name("org.mpris.MediaPlayer2.Spot")
         .await?;
 
+    if let Some(sender) = media_keys_sender {
+        let connection = connection.clone();
+        tokio::spawn(async move {
+            if let Err(e) = listen_media_keys(&connection, sender).await {
+                warn!("Could not grab media keys: {}", e);
+            }
+        });
+    }
+
     receiver
         .for_each(|update| async {
             if let Ok(player_ref) = connection
@@ -91,13 +105,15 @@ async fn dbus_server(
 pub fn start_dbus_server(
     app_model: Rc<AppModel>,
     sender: UnboundedSender<AppAction>,
+    grab_media_keys: bool,
 ) -> AppPlaybackStateListener {
     let mpris = SpotMpris::new(sender.clone());
+    let media_keys_sender = Some(sender.clone()).filter(|_| grab_media_keys);
     let player = SpotMprisPlayer::new(sender);
 
     let (sender, receiver) = unbounded();
 
-    thread::spawn(move || dbus_server(mpris, player, receiver));
+    thread::spawn(move || dbus_server(mpris, player, receiver, media_keys_sender));
 
     AppPlaybackStateListener::new(app_model, sender)
 }
@@ -64,7 +64,7 @@ impl SpotMpris {
 
     #[dbus_interface(property)]
     fn supported_uri_schemes(&self) -> Vec<String> {
-        vec![]
+        vec!["spotify".to_string()]
     }
 
     #[dbus_interface(property)]
@@ -136,7 +136,11 @@ impl SpotMprisPlayer {
     }
 
     pub fn open_uri(&self, Uri: &str) -> Result<()> {
-        Err(Error::NotSupported("Not implemented".to_string()))
+        let action = AppAction::OpenURI(Uri.to_string())
+            .ok_or_else(|| Error::InvalidArgs(format!("Unsupported URI: {Uri}")))?;
+        self.sender
+            .unbounded_send(action)
+            .map_err(|_| Error::Failed("Could not send action".to_string()))
     }
 
     pub fn pause(&self) -> Result<()> {
@@ -267,12 +271,14 @@ impl SpotMprisPlayer {
             .current_track()
             .cloned()
             .unwrap_or_else(|| TrackMetadata {
-                id: String::new(),
+                id: NO_TRACK.to_string(),
                 length: 0,
                 title: "Not playing".to_string(),
                 artist: vec![],
                 album: String::new(),
                 art: None,
+                track_number: None,
+                disc_number: None,
             })
     }
 
@@ -1,7 +1,7 @@
 use std::convert::{Into, TryFrom};
 use std::time::Instant;
 use zvariant::Type;
-use zvariant::{Dict, Signature, Str, Value};
+use zvariant::{Dict, ObjectPath, Signature, Str, Value};
 
 fn boxed_value<'a, V: Into<Value<'a>>>(v: V) -> Value<'a> {
     Value::new(v.into())
@@ -104,14 +104,39 @@ impl PositionMicros {
     }
 }
 
+// The trackid when there's no track, as per the spec
+pub const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+// Object paths only allow [A-Za-z0-9_], anything else in an id gets escaped (the same way every time, so that a track keeps its path)
+pub fn track_path(id: &str) -> String {
+    if id.is_empty() {
+        return NO_TRACK.to_string();
+    }
+    let escaped: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_string()
+            } else {
+                format!("_{:x}_", c as u32)
+            }
+        })
+        .collect();
+    format!("/dev/alextren/Spot/Track/{escaped}")
+}
+
 #[derive(Debug, Clone)]
 pub struct TrackMetadata {
+    // An object path, see track_path
     pub id: String,
     pub length: u64,
     pub artist: Vec<String>,
     pub album: String,
     pub title: String,
+    // A file:// url when the image is in our cache, the remote one otherwise
     pub art: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
 }
 
 impl Type for TrackMetadata {
@@ -123,7 +148,9 @@ impl Type for TrackMetadata {
 impl From<TrackMetadata> for Value<'_> {
     fn from(meta: TrackMetadata) -> Self {
         let mut d = Dict::new(Str::signature(), Value::signature());
-        d.append("mpris:trackid".into(), boxed_value(meta.id))
+        let track_id = ObjectPath::try_from(meta.id)
+            .unwrap_or_else(|_| ObjectPath::from_static_str_unchecked(NO_TRACK));
+        d.append("mpris:trackid".into(), boxed_value(track_id))
             .unwrap();
         d.append("mpris:length".into(), boxed_value(meta.length))
             .unwrap();
@@ -138,6 +165,14 @@ impl From<TrackMetadata> for Value<'_> {
         if let Some(art) = meta.art {
             d.append("mpris:artUrl".into(), boxed_value(art)).unwrap();
         }
+        if let Some(number) = meta.track_number {
+            d.append("xesam:trackNumber".into(), boxed_value(number as i32))
+                .unwrap();
+        }
+        if let Some(number) = meta.disc_number {
+            d.append("xesam:discNumber".into(), boxed_value(number as i32))
+                .unwrap();
+        }
         Value::Dict(d)
     }
 }
@@ -245,3 +280,20 @@ impl MprisState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_track_path() {
+        assert_eq!(
+            track_path("4uLU6hMCjMI75M1A2tKUQC"),
+            "/dev/alextren/Spot/Track/4uLU6hMCjMI75M1A2tKUQC"
+        );
+        assert_eq!(track_path("a:b_c"), "/dev/alextren/Spot/Track/a_3a_b_5f_c");
+        assert_eq!(track_path(""), NO_TRACK);
+        assert!(ObjectPath::try_from(track_path("spotify:local:some+song")).is_ok());
+    }
+}
@@ -5,7 +5,7 @@ extern crate lazy_static;
 #[macro_use]
 extern crate log;
 
-use app::state::ScreenName;
+use app::state::{ScreenName, DEBUG_STATE_FLAG, RESTORE_STATE_FLAG};
 use futures::channel::mpsc::UnboundedSender;
 use gettextrs::*;
 use gio::prelude::*;
@@ -15,25 +15,56 @@ use gtk::prelude::*;
 
 mod api;
 mod app;
+mod autostart;
 mod config;
 mod connect;
 mod dbus;
 mod player;
 mod settings;
+mod shortcuts;
+mod user_style;
 
-use crate::app::components::expose_custom_widgets;
+use crate::app::components::{expose_custom_widgets, STATUS_SOCKET_FLAG};
 use crate::app::dispatch::{spawn_task_handler, DispatchLoop};
-use crate::app::{state::PlaybackAction, App, AppAction, BrowserAction};
+use crate::app::state::{PlaybackAction, SelectionAction};
+use crate::app::{App, AppAction, BrowserAction};
 
 fn main() {
     let settings = settings::SpotSettings::new_from_gsettings().unwrap_or_default();
     setup_gtk(&settings);
+    autostart::sync(settings::autostart_enabled());
+    settings::connect_autostart_changed(autostart::sync);
 
     // Looks like there's a side effect to declaring widgets that allows them to be referenced them in ui/blueprint files
     // so here goes!
     expose_custom_widgets();
 
     let gtk_app = gtk::Application::new(Some(config::APPID), ApplicationFlags::HANDLES_OPEN);
+    // Only declared so that it's accepted, the state picks it up by itself
+    gtk_app.add_main_option(
+        DEBUG_STATE_FLAG,
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Log every state update, and allow dumping them with Ctrl+Shift+D",
+        None,
+    );
+    gtk_app.add_main_option(
+        RESTORE_STATE_FLAG,
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::Filename,
+        "Restore a state snapshot (saved with Ctrl+Shift+S) after logging in",
+        Some("FILE"),
+    );
+    gtk_app.add_main_option(
+        STATUS_SOCKET_FLAG,
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::Filename,
+        "Tell what's playing as JSON lines to whoever connects to this UNIX socket",
+        Some("PATH"),
+    );
     let builder = gtk::Builder::from_resource("/dev/alextren/Spot/window.ui");
     let window: libadwaita::ApplicationWindow = builder.object("window").unwrap();
 
@@ -43,6 +74,7 @@ fn main() {
         window.add_css_class("devel");
         gtk_app.set_resource_base_path(Some("/dev/alextren/Spot"));
     }
+    user_style::bind_display_settings(&window);
 
     let context = glib::MainContext::default();
     let dispatch_loop = DispatchLoop::new();
@@ -50,6 +82,7 @@ fn main() {
 
     // Couple of actions used with shortcuts
     register_actions(&gtk_app, sender.clone());
+    shortcuts::register(&builder.object("shortcuts").unwrap());
     setup_credits(builder.object::<libadwaita::AboutWindow>("about").unwrap());
 
     // Main app logic is hooked up here
@@ -116,11 +149,13 @@ fn setup_gtk(settings: &settings::SpotSettings) {
     let provider = gtk::CssProvider::new();
     provider.load_from_resource("/dev/alextren/Spot/app.css");
 
+    let display = gdk::Display::default().unwrap();
     gtk::style_context_add_provider_for_display(
-        &gdk::Display::default().unwrap(),
+        &display,
         &provider,
         gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
     );
+    user_style::setup(&display);
 }
 
 fn setup_credits(about: libadwaita::AboutWindow) {
@@ -162,12 +197,30 @@ fn register_actions(app: &gtk::Application, sender: UnboundedSender<AppAction>)
         sender.clone(),
     ));
 
+    app.add_action(&make_action(
+        "player_prev_track",
+        PlaybackAction::PreviousTrack.into(),
+        sender.clone(),
+    ));
+
     app.add_action(&make_action(
         "player_next",
         PlaybackAction::Next.into(),
         sender.clone(),
     ));
 
+    app.add_action(&make_action(
+        "player_prev_chapter",
+        PlaybackAction::PreviousChapter.into(),
+        sender.clone(),
+    ));
+
+    app.add_action(&make_action(
+        "player_next_chapter",
+        PlaybackAction::NextChapter.into(),
+        sender.clone(),
+    ));
+
     app.add_action(&make_action(
         "nav_pop",
         AppAction::BrowserAction(BrowserAction::NavigationPop),
@@ -180,15 +233,80 @@ fn register_actions(app: &gtk::Application, sender: UnboundedSender<AppAction>)
         sender.clone(),
     ));
 
+    app.add_action(&make_action(
+        "quick_open",
+        AppAction::ShowQuickOpen,
+        sender.clone(),
+    ));
+
+    app.add_action(&make_action(
+        "refresh",
+        AppAction::BrowserAction(BrowserAction::Refresh),
+        sender.clone(),
+    ));
+
+    app.add_action(&make_action(
+        "queue_selection",
+        AppAction::QueueSelection,
+        sender.clone(),
+    ));
+
+    app.add_action(&make_action(
+        "select_all",
+        SelectionAction::SelectAll.into(),
+        sender.clone(),
+    ));
+
+    app.add_action(&make_action(
+        "invert_selection",
+        SelectionAction::InvertAll.into(),
+        sender.clone(),
+    ));
+
+    app.add_action(&make_action(
+        "cancel_selection",
+        AppAction::CancelSelection,
+        sender.clone(),
+    ));
+
+    app.add_action(&make_action("undo", AppAction::Undo, sender.clone()));
+    app.add_action(&make_action("redo", AppAction::Redo, sender.clone()));
+
+    app.add_action(&make_action(
+        "dump_state_log",
+        AppAction::DumpActionLog,
+        sender.clone(),
+    ));
+
+    app.add_action(&make_action(
+        "save_state_snapshot",
+        AppAction::SaveStateSnapshot,
+        sender.clone(),
+    ));
+
     app.add_action(&{
         let action = SimpleAction::new("open_playlist", Some(glib::VariantTy::STRING));
         action.set_enabled(true);
-        action.connect_activate(move |_, playlist_id| {
+        action.connect_activate(clone!(@strong sender => move |_, playlist_id| {
             if let Some(id) = playlist_id.and_then(|s| s.str()) {
                 sender
                     .unbounded_send(AppAction::ViewPlaylist(id.to_owned()))
                     .unwrap();
             }
+        }));
+        action
+    });
+
+    // What the new release notifications open
+    app.add_action(&{
+        let action = SimpleAction::new("open_album", Some(glib::VariantTy::STRING));
+        action.set_enabled(true);
+        action.connect_activate(move |_, album_id| {
+            if let Some(id) = album_id.and_then(|s| s.str()) {
+                sender
+                    .unbounded_send(AppAction::ViewAlbum(id.to_owned()))
+                    .unwrap();
+            }
         });
         action
     });
@@ -0,0 +1,83 @@
+use librespot::playback::audio_backend::{Sink, SinkResult};
+use librespot::playback::convert::Converter;
+use librespot::playback::decoder::AudioPacket;
+
+// Mono downmix and left/right balance, applied to the samples before they reach the actual backend
+pub struct ChannelMixSink {
+    inner: Box<dyn Sink>,
+    mono: bool,
+    // -1.0 is left only, 1.0 right only
+    balance: f64,
+}
+
+impl ChannelMixSink {
+    // The balance is in percent, like in the settings; with the defaults there's nothing to wrap
+    pub fn wrap(inner: Box<dyn Sink>, mono: bool, balance: i32) -> Box<dyn Sink> {
+        if !mono && balance == 0 {
+            return inner;
+        }
+        Box::new(Self {
+            inner,
+            mono,
+            balance: (balance.clamp(-100, 100) as f64) / 100.0,
+        })
+    }
+}
+
+// Samples are interleaved, left first (librespot always decodes to stereo)
+fn mix(samples: &mut [f64], mono: bool, balance: f64) {
+    let left_gain = (1.0 - balance).min(1.0);
+    let right_gain = (1.0 + balance).min(1.0);
+    for frame in samples.chunks_exact_mut(2) {
+        if mono {
+            let mid = (frame[0] + frame[1]) / 2.0;
+            frame[0] = mid;
+            frame[1] = mid;
+        }
+        frame[0] *= left_gain;
+        frame[1] *= right_gain;
+    }
+}
+
+impl Sink for ChannelMixSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.inner.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.inner.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        let packet = match packet {
+            AudioPacket::Samples(mut samples) => {
+                mix(&mut samples, self.mono, self.balance);
+                AudioPacket::Samples(samples)
+            }
+            // Passthrough, we can't touch that
+            raw => raw,
+        };
+        self.inner.write(packet, converter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_mix() {
+        let mut samples = vec![1.0, 0.0, 0.5, 0.5];
+        mix(&mut samples, true, 0.0);
+        assert_eq!(samples, vec![0.5, 0.5, 0.5, 0.5]);
+
+        let mut samples = vec![1.0, 1.0];
+        mix(&mut samples, false, -0.5);
+        assert_eq!(samples, vec![1.0, 0.5]);
+
+        let mut samples = vec![1.0, 1.0];
+        mix(&mut samples, false, 1.0);
+        assert_eq!(samples, vec![0.0, 1.0]);
+    }
+}
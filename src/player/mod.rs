@@ -1,31 +1,56 @@
 use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use librespot::core::spotify_id::SpotifyId;
 use std::cell::RefCell;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::SystemTime;
 use tokio::task;
 
 use crate::app::credentials::Credentials;
+use crate::app::health::Backend;
 use crate::app::state::{LoginAction, PlaybackAction, SetLoginSuccessAction};
 use crate::app::AppAction;
 #[allow(clippy::module_inception)]
 mod player;
 pub use player::*;
 
+mod channel_mix;
+mod precache;
+mod seek_buffer;
+mod silence;
+pub mod spectrum;
+mod system_volume;
+
 #[derive(Debug, Clone)]
 pub enum Command {
-    TokenLogin { username: String, token: String },
+    TokenLogin {
+        username: String,
+        token: String,
+    },
     OAuthLogin,
+    // From the credentials file of another librespot based client
+    ImportLogin(PathBuf),
     Logout,
-    PlayerLoad { track: SpotifyId, resume: bool },
+    PlayerLoad {
+        track: SpotifyId,
+        resume: bool,
+    },
     PlayerResume,
     PlayerPause,
     PlayerStop,
     PlayerSeek(u32),
     PlayerSetVolume(f64),
     PlayerPreload(SpotifyId),
+    // What's left of the album being played, downloaded ahead if the preferences say so
+    PlayerPrecache(Vec<SpotifyId>),
     RefreshToken,
     ReloadSettings,
+    // The track that was playing before the network went away, loaded again if the session didn't survive
+    Reconnect {
+        track: Option<SpotifyId>,
+        position: u32,
+        resume: bool,
+    },
 }
 
 struct AppPlayerDelegate {
@@ -79,7 +104,7 @@ impl SpotifyPlayerDelegate for AppPlayerDelegate {
             .unwrap();
     }
 
-    fn notify_playback_state(&self, position: u32) {
+    fn notify_position(&self, position: u32) {
         self.sender
             .borrow_mut()
             .unbounded_send(PlaybackAction::SyncSeek(position).into())
@@ -92,6 +117,20 @@ impl SpotifyPlayerDelegate for AppPlayerDelegate {
             .unbounded_send(PlaybackAction::Preload.into())
             .unwrap();
     }
+
+    fn full_playback_changed(&self, available: bool) {
+        self.sender
+            .borrow_mut()
+            .unbounded_send(PlaybackAction::SetPreviewsOnly(!available).into())
+            .unwrap();
+    }
+
+    fn session_health_changed(&self, alive: bool) {
+        self.sender
+            .borrow_mut()
+            .unbounded_send(AppAction::SetBackendHealth(Backend::Player, alive))
+            .unwrap();
+    }
 }
 
 #[tokio::main]
@@ -1,10 +1,14 @@
 use futures::channel::mpsc::UnboundedReceiver;
 use futures::stream::StreamExt;
+use isahc::AsyncReadResponseExt;
+use serde::Deserialize;
 
 use librespot::core::authentication::Credentials;
 use librespot::core::cache::Cache;
 use librespot::core::config::SessionConfig;
+use librespot::core::connection::AuthenticationError;
 use librespot::core::session::Session;
+use librespot::core::spotify_id::SpotifyId;
 
 use librespot::playback::mixer::softmixer::SoftMixer;
 use librespot::playback::mixer::{Mixer, MixerConfig};
@@ -12,18 +16,25 @@ use librespot::playback::mixer::{Mixer, MixerConfig};
 use librespot::playback::audio_backend;
 use librespot::playback::config::{AudioFormat, Bitrate, PlayerConfig, VolumeCtrl};
 use librespot::playback::player::{Player, PlayerEvent, PlayerEventChannel};
-
+use librespot::protocol::keyexchange::ErrorCode;
+
+use super::channel_mix::ChannelMixSink;
+use super::precache::AlbumPrecache;
+use super::seek_buffer::{SeekBuffer, SeekBufferSink};
+use super::silence::SilenceSkipSink;
+use super::spectrum::SpectrumSink;
+use super::system_volume::SystemVolume;
 use super::Command;
 use crate::api::oauth2::get_access_token;
 use crate::app::credentials;
 use crate::settings::SpotSettings;
-use std::cell::RefCell;
 use std::env;
 use std::error::Error;
 use std::fmt;
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::{Instant, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(Debug)]
 pub enum SpotifyError {
@@ -31,6 +42,7 @@ pub enum SpotifyError {
     TokenFailed,
     PlayerNotReady,
     TechnicalError,
+    PremiumRequired,
 }
 
 impl Error for SpotifyError {}
@@ -44,6 +56,12 @@ impl fmt::Display for SpotifyError {
             Self::TechnicalError => {
                 write!(f, "A technical error occured. Check your connectivity.")
             }
+            Self::PremiumRequired => {
+                write!(
+                    f,
+                    "Spotify Premium is needed to play full songs, only previews will play."
+                )
+            }
         }
     }
 }
@@ -53,8 +71,10 @@ pub trait SpotifyPlayerDelegate {
     fn token_login_successful(&self, credentials: credentials::Credentials);
     fn refresh_successful(&self, token: String, token_expiry_time: SystemTime);
     fn report_error(&self, error: SpotifyError);
-    fn notify_playback_state(&self, position: u32);
+    fn notify_position(&self, position: u32);
     fn preload_next_track(&self);
+    fn full_playback_changed(&self, available: bool);
+    fn session_health_changed(&self, alive: bool);
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -64,12 +84,47 @@ pub enum AudioBackend {
     Alsa(String),
 }
 
+// What the volume slider acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeControl {
+    // We scale the samples ourselves
+    Software,
+    // The output device's own volume, leaving our samples untouched
+    System,
+}
+
+// How the position of the volume slider maps to loudness, for the software volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeCurve {
+    Logarithmic,
+    Cubic,
+    Linear,
+}
+
+impl VolumeCurve {
+    fn volume_ctrl(self) -> VolumeCtrl {
+        match self {
+            // This value feels reasonable to me. Feel free to change it
+            Self::Logarithmic => VolumeCtrl::Log(VolumeCtrl::DEFAULT_DB_RANGE / 2.0),
+            Self::Cubic => VolumeCtrl::Cubic(VolumeCtrl::DEFAULT_DB_RANGE),
+            Self::Linear => VolumeCtrl::Linear,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpotifyPlayerSettings {
     pub bitrate: Bitrate,
     pub backend: AudioBackend,
     pub gapless: bool,
     pub ap_port: Option<u16>,
+    pub mono: bool,
+    // -100 (left only) to 100 (right only)
+    pub balance: i32,
+    pub skip_silence: bool,
+    pub volume_control: VolumeControl,
+    pub volume_curve: VolumeCurve,
+    pub precache_album: bool,
 }
 
 impl Default for SpotifyPlayerSettings {
@@ -79,6 +134,12 @@ impl Default for SpotifyPlayerSettings {
             gapless: true,
             backend: AudioBackend::PulseAudio,
             ap_port: None,
+            mono: false,
+            balance: 0,
+            skip_silence: false,
+            volume_control: VolumeControl::Software,
+            volume_curve: VolumeCurve::Logarithmic,
+            precache_album: false,
         }
     }
 }
@@ -88,6 +149,14 @@ pub struct SpotifyPlayer {
     player: Option<Arc<Player>>,
     mixer: Option<Box<dyn Mixer>>,
     session: Option<Session>,
+    // librespot only keeps the one, asking for it again would start the download over
+    preloaded: Option<SpotifyId>,
+    precache: AlbumPrecache,
+    // Shared with the sink of the current player
+    seek_buffer: SeekBuffer,
+    system_volume: SystemVolume,
+    // As last reported to the delegate
+    session_alive: bool,
     delegate: Rc<dyn SpotifyPlayerDelegate>,
 }
 
@@ -98,16 +167,39 @@ impl SpotifyPlayer {
             mixer: None,
             player: None,
             session: None,
+            preloaded: None,
+            precache: AlbumPrecache::default(),
+            seek_buffer: SeekBuffer::default(),
+            system_volume: SystemVolume::default(),
+            session_alive: true,
             delegate,
         }
     }
 
+    // librespot drops its connection without telling anyone, we only find out by looking.
+    // No session at all (logged out) isn't a problem with Spotify.
+    fn check_session(&mut self) {
+        let alive = self
+            .session
+            .as_ref()
+            .map(|session| !session.is_invalid())
+            .unwrap_or(true);
+        if alive != self.session_alive {
+            self.session_alive = alive;
+            self.delegate.session_health_changed(alive);
+        }
+    }
+
     async fn handle(&mut self, action: Command) -> Result<(), SpotifyError> {
         match action {
             Command::PlayerSetVolume(volume) => {
+                // Without effect when it's the system volume we control, but it's remembered for later
                 if let Some(mixer) = self.mixer.as_mut() {
                     mixer.set_volume((VolumeCtrl::MAX_VOLUME as f64 * volume) as u16);
                 }
+                if self.settings.volume_control == VolumeControl::System {
+                    self.system_volume.set(&self.settings.backend, volume);
+                }
                 Ok(())
             }
             Command::PlayerResume => {
@@ -118,6 +210,7 @@ impl SpotifyPlayer {
                 Ok(())
             }
             Command::PlayerPause => {
+                self.seek_buffer.interrupt();
                 self.player
                     .as_ref()
                     .ok_or(SpotifyError::PlayerNotReady)?
@@ -125,6 +218,8 @@ impl SpotifyPlayer {
                 Ok(())
             }
             Command::PlayerStop => {
+                self.seek_buffer.interrupt();
+                self.seek_buffer.reset(0);
                 self.player
                     .as_ref()
                     .ok_or(SpotifyError::PlayerNotReady)?
@@ -132,24 +227,44 @@ impl SpotifyPlayer {
                 Ok(())
             }
             Command::PlayerSeek(position) => {
-                self.player
-                    .as_ref()
-                    .ok_or(SpotifyError::PlayerNotReady)?
-                    .seek(position);
+                let player = self.player.as_ref().ok_or(SpotifyError::PlayerNotReady)?;
+                if !self.seek_buffer.replay_from(position) {
+                    self.seek_buffer.reset(position);
+                    player.seek(position);
+                }
                 Ok(())
             }
             Command::PlayerLoad { track, resume } => {
+                self.seek_buffer.reset(0);
                 self.player
                     .as_mut()
                     .ok_or(SpotifyError::PlayerNotReady)?
                     .load(track, resume, 0);
+                // Played now, if it was the preloaded one
+                self.preloaded = None;
+                self.precache.forget(track);
                 Ok(())
             }
             Command::PlayerPreload(track) => {
+                if self.preloaded == Some(track) {
+                    return Ok(());
+                }
                 self.player
                     .as_mut()
                     .ok_or(SpotifyError::PlayerNotReady)?
                     .preload(track);
+                self.preloaded = Some(track);
+                Ok(())
+            }
+            Command::PlayerPrecache(tracks) => {
+                let session = self.session.as_ref().ok_or(SpotifyError::PlayerNotReady)?;
+                let tracks = if self.settings.precache_album {
+                    tracks
+                } else {
+                    vec![]
+                };
+                self.precache
+                    .set_tracks(session, tracks, self.settings.bitrate);
                 Ok(())
             }
             Command::RefreshToken => {
@@ -159,6 +274,10 @@ impl SpotifyPlayer {
                 Ok(())
             }
             Command::Logout => {
+                self.delegate.full_playback_changed(false);
+                if let Some(session) = self.session.as_ref() {
+                    self.precache.clear(session);
+                }
                 self.session
                     .take()
                     .ok_or(SpotifyError::PlayerNotReady)?
@@ -169,7 +288,12 @@ impl SpotifyPlayer {
             Command::TokenLogin { username, token } => {
                 info!("Login with token, username {}", username);
                 let credentials = Credentials::with_access_token(token.clone());
-                let new_session = create_session(&credentials, self.settings.ap_port).await?;
+                let new_session = match create_session(&credentials, self.settings.ap_port).await {
+                    Err(SpotifyError::PremiumRequired) => {
+                        return self.login_for_previews(username, token, None);
+                    }
+                    result => result?,
+                };
                 let credentials = credentials::Credentials {
                     username: new_session.username(),
                     password: "".to_string(),
@@ -178,13 +302,8 @@ impl SpotifyPlayer {
                 };
                 self.delegate.token_login_successful(credentials);
 
-                let new_player = self.create_player(new_session.clone());
-                tokio::task::spawn_local(player_setup_delegate(
-                    new_player.get_player_event_channel(),
-                    Rc::clone(&self.delegate),
-                ));
-                self.player.replace(new_player);
-                self.session.replace(new_session);
+                self.start_player(new_session);
+                self.delegate.full_playback_changed(true);
 
                 Ok(())
             }
@@ -192,7 +311,33 @@ impl SpotifyPlayer {
                 let (token, token_expiry_time) = get_access_token_oauth()?;
                 info!("Login with OAuth2");
                 let credentials = Credentials::with_access_token(token.clone());
+                let new_session = match create_session(&credentials, self.settings.ap_port).await {
+                    Err(SpotifyError::PremiumRequired) => {
+                        let username = get_username(&token).await?;
+                        return self.login_for_previews(username, token, Some(token_expiry_time));
+                    }
+                    result => result?,
+                };
+                let credentials = credentials::Credentials {
+                    username: new_session.username(),
+                    password: "".to_string(),
+                    token,
+                    token_expiry_time: Some(token_expiry_time),
+                };
+                self.delegate.token_login_successful(credentials);
+
+                self.start_player(new_session);
+                self.delegate.full_playback_changed(true);
+
+                Ok(())
+            }
+            Command::ImportLogin(path) => {
+                info!("Login with imported credentials");
+                let credentials = read_credentials(&path)?;
+                // That's them checked: librespot connects with them, and keeps its own copy in our cache
                 let new_session = create_session(&credentials, self.settings.ap_port).await?;
+                let (token, token_expiry_time) =
+                    get_access_token_and_expiry_time(&new_session).await?;
                 let credentials = credentials::Credentials {
                     username: new_session.username(),
                     password: "".to_string(),
@@ -201,34 +346,95 @@ impl SpotifyPlayer {
                 };
                 self.delegate.token_login_successful(credentials);
 
-                let new_player = self.create_player(new_session.clone());
-                tokio::task::spawn_local(player_setup_delegate(
-                    new_player.get_player_event_channel(),
-                    Rc::clone(&self.delegate),
-                ));
-                self.player.replace(new_player);
-                self.session.replace(new_session);
+                self.start_player(new_session);
+                self.delegate.full_playback_changed(true);
+
+                Ok(())
+            }
+            Command::Reconnect {
+                track,
+                position,
+                resume,
+            } => {
+                let session = self.session.as_ref().ok_or(SpotifyError::PlayerNotReady)?;
+                if !session.is_invalid() {
+                    return Ok(());
+                }
+                info!("Session lost, reconnecting");
+                // Whatever librespot saved the last time we logged in
+                let credentials = make_cache()
+                    .and_then(|cache| cache.credentials())
+                    .ok_or(SpotifyError::PlayerNotReady)?;
+                let new_session = create_session(&credentials, self.settings.ap_port).await?;
+
+                self.start_player(new_session);
+
+                // The new player starts out empty
+                if let Some(track) = track {
+                    self.seek_buffer.reset(position);
+                    self.player
+                        .as_mut()
+                        .ok_or(SpotifyError::PlayerNotReady)?
+                        .load(track, resume, position);
+                }
 
                 Ok(())
             }
             Command::ReloadSettings => {
                 let settings = SpotSettings::new_from_gsettings().unwrap_or_default();
                 self.settings = settings.player_settings;
+                if !self.settings.precache_album {
+                    if let Some(session) = self.session.as_ref() {
+                        self.precache.clear(session);
+                    }
+                }
+                // The volume curve might have changed, the volume itself shouldn't
+                let volume = self.mixer.take().map(|mixer| mixer.volume());
+                if let Some(volume) = volume {
+                    self.create_mixer(volume);
+                }
 
-                let session = self.session.take().ok_or(SpotifyError::PlayerNotReady)?;
-                let new_player = self.create_player(session);
-                tokio::task::spawn_local(player_setup_delegate(
-                    new_player.get_player_event_channel(),
-                    Rc::clone(&self.delegate),
-                ));
-                self.player.replace(new_player);
+                let session = self.session.clone().ok_or(SpotifyError::PlayerNotReady)?;
+                self.start_player(session);
 
                 Ok(())
             }
         }
     }
 
+    // The Web API works just fine without Premium, it's only librespot that won't play anything
+    fn login_for_previews(
+        &self,
+        username: String,
+        token: String,
+        token_expiry_time: Option<SystemTime>,
+    ) -> Result<(), SpotifyError> {
+        self.delegate
+            .token_login_successful(credentials::Credentials {
+                username,
+                password: "".to_string(),
+                token,
+                token_expiry_time,
+            });
+        self.delegate.full_playback_changed(false);
+        self.delegate.report_error(SpotifyError::PremiumRequired);
+        Ok(())
+    }
+
+    // A new player for that session, with its events going to the delegate
+    fn start_player(&mut self, session: Session) {
+        let new_player = self.create_player(session.clone());
+        tokio::task::spawn_local(player_setup_delegate(
+            new_player.get_player_event_channel(),
+            Rc::clone(&self.delegate),
+            self.seek_buffer.clone(),
+        ));
+        self.player.replace(new_player);
+        self.session.replace(session);
+    }
+
     fn create_player(&mut self, session: Session) -> Arc<Player> {
+        self.preloaded = None;
         let backend = self.settings.backend.clone();
 
         let player_config = PlayerConfig {
@@ -238,51 +444,77 @@ impl SpotifyPlayer {
         };
         info!("bitrate: {:?}", &player_config.bitrate);
 
-        let soft_volume = self
-            .mixer
-            .get_or_insert_with(|| {
-                let mix = Box::new(SoftMixer::open(MixerConfig {
-                    // This value feels reasonable to me. Feel free to change it
-                    volume_ctrl: VolumeCtrl::Log(VolumeCtrl::DEFAULT_DB_RANGE / 2.0),
-                    ..Default::default()
-                }));
-                // TODO: Should read volume from somewhere instead of hard coding.
-                // Sets volume to 100%
-                mix.set_volume(VolumeCtrl::MAX_VOLUME);
-                mix
-            })
-            .get_soft_volume();
-        Player::new(player_config, session, soft_volume, move || match backend {
-            AudioBackend::GStreamer(pipeline) => {
-                let backend = audio_backend::find(Some("gstreamer".to_string())).unwrap();
-                backend(Some(pipeline), AudioFormat::default())
-            }
-            AudioBackend::PulseAudio => {
-                info!("using pulseaudio");
-                env::set_var("PULSE_PROP_application.name", "Spot");
-                let backend = audio_backend::find(Some("pulseaudio".to_string())).unwrap();
-                backend(None, AudioFormat::default())
-            }
-            AudioBackend::Alsa(device) => {
-                info!("using alsa ({})", &device);
-                let backend = audio_backend::find(Some("alsa".to_string())).unwrap();
-                backend(Some(device), AudioFormat::default())
-            }
+        if self.mixer.is_none() {
+            // TODO: Should read volume from somewhere instead of hard coding.
+            // Sets volume to 100%
+            self.create_mixer(VolumeCtrl::MAX_VOLUME);
+        }
+        let soft_volume = self.mixer.as_ref().unwrap().get_soft_volume();
+        let replay_volume = self.mixer.as_ref().unwrap().get_soft_volume();
+        let (mono, balance) = (self.settings.mono, self.settings.balance);
+        let skip_silence = self.settings.skip_silence;
+        let seek_buffer = self.seek_buffer.clone();
+        seek_buffer.reset(0);
+        Player::new(player_config, session, soft_volume, move || {
+            let sink = match backend {
+                AudioBackend::GStreamer(pipeline) => {
+                    let backend = audio_backend::find(Some("gstreamer".to_string())).unwrap();
+                    backend(Some(pipeline), AudioFormat::default())
+                }
+                AudioBackend::PulseAudio => {
+                    info!("using pulseaudio");
+                    env::set_var("PULSE_PROP_application.name", "Spot");
+                    let backend = audio_backend::find(Some("pulseaudio".to_string())).unwrap();
+                    backend(None, AudioFormat::default())
+                }
+                AudioBackend::Alsa(device) => {
+                    info!("using alsa ({})", &device);
+                    let backend = audio_backend::find(Some("alsa".to_string())).unwrap();
+                    backend(Some(device), AudioFormat::default())
+                }
+            };
+            // The visualizer gets to see what actually comes out, and replays go through the same as the rest
+            SeekBufferSink::wrap(
+                SilenceSkipSink::wrap(
+                    ChannelMixSink::wrap(SpectrumSink::wrap(sink), mono, balance),
+                    skip_silence,
+                ),
+                seek_buffer,
+                replay_volume,
+            )
         })
     }
 
-    #[allow(clippy::await_holding_refcell_ref)]
-    pub async fn start(self, receiver: UnboundedReceiver<Command>) -> Result<(), ()> {
-        let _self = RefCell::new(self);
-        receiver
-            .for_each(|action| async {
-                let mut _self = _self.borrow_mut();
-                match _self.handle(action).await {
-                    Ok(_) => {}
-                    Err(err) => _self.delegate.report_error(err),
+    fn create_mixer(&mut self, volume: u16) {
+        let volume_ctrl = match self.settings.volume_control {
+            VolumeControl::Software => self.settings.volume_curve.volume_ctrl(),
+            // Our samples stay at full volume: with flat volumes (PulseAudio's default on some distros),
+            // a quieter stream would drag the device volume down with it
+            VolumeControl::System => VolumeCtrl::Fixed,
+        };
+        let mixer = Box::new(SoftMixer::open(MixerConfig {
+            volume_ctrl,
+            ..Default::default()
+        }));
+        mixer.set_volume(volume);
+        self.mixer.replace(mixer);
+    }
+
+    pub async fn start(mut self, mut receiver: UnboundedReceiver<Command>) -> Result<(), ()> {
+        // The session can drop while nothing is asked of the player, so it's also checked regularly
+        let mut session_check = tokio::time::interval(SESSION_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = session_check.tick() => {}
+                action = receiver.next() => {
+                    let Some(action) = action else { break };
+                    if let Err(err) = self.handle(action).await {
+                        self.delegate.report_error(err);
+                    }
                 }
-            })
-            .await;
+            }
+            self.check_session();
+        }
         Ok(())
     }
 }
@@ -295,6 +527,8 @@ playlist-read-private,\
 playlist-read-collaborative,\
 user-library-read,\
 user-library-modify,\
+user-follow-read,\
+user-follow-modify,\
 user-top-read,\
 user-read-recently-played,\
 user-read-playback-state,\
@@ -304,6 +538,8 @@ user-modify-playback-state,\
 streaming,\
 playlist-modify-public";
 
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
 const KNOWN_AP_PORTS: [Option<u16>; 4] = [None, Some(80), Some(443), Some(4070)];
 
 async fn get_access_token_and_expiry_time(
@@ -333,6 +569,61 @@ fn get_access_token_oauth() -> Result<(String, SystemTime), SpotifyError> {
     }
 }
 
+// Without a session, there's only the Web API to tell who just logged in
+async fn get_username(token: &str) -> Result<String, SpotifyError> {
+    #[derive(Deserialize)]
+    struct Me {
+        id: String,
+    }
+
+    let request = isahc::Request::get("https://api.spotify.com/v1/me")
+        .header("Authorization", format!("Bearer {token}"))
+        .body(())
+        .map_err(|_| SpotifyError::TechnicalError)?;
+    let mut response = isahc::send_async(request)
+        .await
+        .map_err(|_| SpotifyError::TechnicalError)?;
+    let me: Me = response
+        .json()
+        .await
+        .map_err(|_| SpotifyError::LoginFailed)?;
+    Ok(me.id)
+}
+
+// librespot and spotifyd save them the same way (username, auth_type and a base64 auth_data)
+fn read_credentials(path: &Path) -> Result<Credentials, SpotifyError> {
+    let content = std::fs::read(path).map_err(|e| {
+        warn!("Could not read {}: {}", path.display(), e);
+        SpotifyError::LoginFailed
+    })?;
+    serde_json::from_slice(&content).map_err(|e| {
+        warn!("Not a credentials file: {}", e);
+        SpotifyError::LoginFailed
+    })
+}
+
+fn make_cache() -> Option<Cache> {
+    let root = glib::user_cache_dir().join("spot").join("librespot");
+    Cache::new(
+        Some(root.join("credentials")),
+        Some(root.join("volume")),
+        Some(root.join("audio")),
+        None,
+    )
+    .map_err(|e| dbg!(e))
+    .ok()
+}
+
+// The reason the access point gave, wrapped in librespot's own error
+fn is_premium_required(err: &librespot::core::Error) -> bool {
+    matches!(
+        err.error.downcast_ref::<AuthenticationError>(),
+        Some(AuthenticationError::LoginFailed(
+            ErrorCode::PremiumAccountRequired
+        ))
+    )
+}
+
 async fn create_session_with_port(
     credentials: &Credentials,
     ap_port: Option<u16>,
@@ -341,18 +632,13 @@ async fn create_session_with_port(
         ap_port,
         ..Default::default()
     };
-    let root = glib::user_cache_dir().join("spot").join("librespot");
-    let cache = Cache::new(
-        Some(root.join("credentials")),
-        Some(root.join("volume")),
-        Some(root.join("audio")),
-        None,
-    )
-    .map_err(|e| dbg!(e))
-    .ok();
-    let session = Session::new(session_config, cache);
+    let session = Session::new(session_config, make_cache());
     match session.connect(credentials.clone(), true).await {
         Ok(_) => Ok(session),
+        Err(err) if is_premium_required(&err) => {
+            warn!("Login failure: {}", err);
+            Err(SpotifyError::PremiumRequired)
+        }
         Err(err) => {
             warn!("Login failure: {}", err);
             Err(SpotifyError::LoginFailed)
@@ -386,14 +672,20 @@ async fn create_session(
 async fn player_setup_delegate(
     mut channel: PlayerEventChannel,
     delegate: Rc<dyn SpotifyPlayerDelegate>,
+    seek_buffer: SeekBuffer,
 ) {
     while let Some(event) = channel.recv().await {
         match event {
             PlayerEvent::EndOfTrack { .. } => {
                 delegate.end_of_track_reached();
             }
-            PlayerEvent::Playing { position_ms, .. } => {
-                delegate.notify_playback_state(position_ms);
+            // Whatever the player says is more accurate than our own clock, so we resync on all of these
+            PlayerEvent::Playing { position_ms, .. }
+            | PlayerEvent::Paused { position_ms, .. }
+            | PlayerEvent::Seeked { position_ms, .. }
+            | PlayerEvent::PositionCorrection { position_ms, .. } => {
+                // The player is ahead of what's heard while part of the track is replayed
+                delegate.notify_position(position_ms.saturating_sub(seek_buffer.lag_ms()));
             }
             PlayerEvent::TimeToPreloadNextTrack { .. } => {
                 debug!("Requesting next track to be preloaded...");
@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use librespot::audio::AudioFile;
+use librespot::core::session::Session;
+use librespot::core::spotify_id::SpotifyId;
+use librespot::core::FileId;
+use librespot::metadata::audio::AudioFileFormat;
+use librespot::metadata::{Metadata, Track};
+use librespot::playback::config::Bitrate;
+use tokio::task::JoinHandle;
+
+// Roughly two long albums at the best quality, past that the rest is streamed as usual
+const PRECACHE_LIMIT_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct PrecachedFile {
+    track: SpotifyId,
+    file: FileId,
+    size: usize,
+}
+
+// The same choice the player makes, so that the file we download is the one it'll look for
+fn formats(bitrate: Bitrate) -> [AudioFileFormat; 3] {
+    use AudioFileFormat::*;
+    match bitrate {
+        Bitrate::Bitrate96 => [OGG_VORBIS_96, OGG_VORBIS_160, OGG_VORBIS_320],
+        Bitrate::Bitrate160 => [OGG_VORBIS_160, OGG_VORBIS_96, OGG_VORBIS_320],
+        Bitrate::Bitrate320 => [OGG_VORBIS_320, OGG_VORBIS_160, OGG_VORBIS_96],
+    }
+}
+
+fn bytes_per_second(bitrate: Bitrate) -> usize {
+    let kbps = match bitrate {
+        Bitrate::Bitrate96 => 96,
+        Bitrate::Bitrate160 => 160,
+        Bitrate::Bitrate320 => 320,
+    };
+    kbps * 1024 / 8
+}
+
+// Downloads the whole file, which librespot then keeps in its audio cache.
+// None if it was in there already (or couldn't be had), in which case it's not ours to evict.
+async fn download(session: &Session, track: SpotifyId, bitrate: Bitrate) -> Option<PrecachedFile> {
+    let metadata = Track::get(session, &track).await.ok()?;
+    let file = formats(bitrate)
+        .iter()
+        .find_map(|format| metadata.files.get(format))
+        .copied()?;
+    let audio_file = AudioFile::open(session, file, bytes_per_second(bitrate))
+        .await
+        .ok()?;
+    if let AudioFile::Cached(_) = audio_file {
+        return None;
+    }
+    let controller = audio_file.get_stream_loader_controller().ok()?;
+    let size = controller.len();
+    tokio::task::spawn_blocking(move || controller.fetch_next_and_wait(size, size))
+        .await
+        .ok()?
+        .ok()?;
+    // librespot writes it to the cache once the last chunk is in, the file has to stay open until then
+    let cache = session.cache()?;
+    for _ in 0..10 {
+        if cache.file(file).is_some() {
+            drop(audio_file);
+            return Some(PrecachedFile { track, file, size });
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    None
+}
+
+// Keeps the rest of the album being played on disk, so that a network hiccup doesn't cut it short.
+// Unlike what librespot caches by itself while playing, what's downloaded here goes away with the album.
+#[derive(Default)]
+pub struct AlbumPrecache {
+    files: Rc<RefCell<Vec<PrecachedFile>>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl AlbumPrecache {
+    // Replaces whatever was precached for the previous album, an empty list just evicts it
+    pub fn set_tracks(&mut self, session: &Session, tracks: Vec<SpotifyId>, bitrate: Bitrate) {
+        self.clear_except(session, &tracks);
+        if tracks.is_empty() {
+            return;
+        }
+
+        let session = session.clone();
+        let files = Rc::clone(&self.files);
+        self.task = Some(tokio::task::spawn_local(async move {
+            for track in tracks {
+                if files.borrow().iter().any(|f| f.track == track) {
+                    continue;
+                }
+                let used: usize = files.borrow().iter().map(|f| f.size).sum();
+                if used >= PRECACHE_LIMIT_BYTES {
+                    debug!("Precache full, leaving the rest of the album to streaming");
+                    break;
+                }
+                if let Some(file) = download(&session, track, bitrate).await {
+                    debug!("Precached {}", track.to_base62().unwrap_or_default());
+                    files.borrow_mut().push(file);
+                }
+            }
+        }));
+    }
+
+    // Played for real: from now on, it's like any other song librespot cached
+    pub fn forget(&self, track: SpotifyId) {
+        self.files.borrow_mut().retain(|f| f.track != track);
+    }
+
+    pub fn clear(&mut self, session: &Session) {
+        self.clear_except(session, &[]);
+    }
+
+    fn clear_except(&mut self, session: &Session, tracks: &[SpotifyId]) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        let Some(cache) = session.cache() else {
+            return;
+        };
+        self.files.borrow_mut().retain(|f| {
+            if tracks.contains(&f.track) {
+                return true;
+            }
+            if let Err(e) = cache.remove_file(f.file) {
+                warn!("Could not evict a precached song: {}", e);
+            }
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            formats(Bitrate::Bitrate320)[0],
+            AudioFileFormat::OGG_VORBIS_320
+        );
+        assert_eq!(
+            formats(Bitrate::Bitrate96)[1],
+            AudioFileFormat::OGG_VORBIS_160
+        );
+        assert_eq!(bytes_per_second(Bitrate::Bitrate160), 20480);
+    }
+}
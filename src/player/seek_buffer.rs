@@ -0,0 +1,274 @@
+use librespot::playback::audio_backend::{Sink, SinkResult};
+use librespot::playback::convert::Converter;
+use librespot::playback::decoder::AudioPacket;
+use librespot::playback::mixer::VolumeGetter;
+use librespot::playback::SAMPLE_RATE;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// About 20 MB, kept as f32 since that's all the output gets in the end
+const BUFFERED_FRAMES: usize = 60 * SAMPLE_RATE as usize;
+// How much is replayed between checks for a pause, about 20 ms
+const CHUNK_FRAMES: usize = 1024;
+// Roughly how long the output holds on to samples before they're heard (it depends on the backend),
+// a replay is that much late already when it starts
+const OUTPUT_LATENCY_MS: u32 = 100;
+// Below that, there's not much left to bring back to full volume
+const MIN_ATTENUATION: f64 = 1e-4;
+
+fn ms_to_frames(ms: u32) -> usize {
+    ms as usize * SAMPLE_RATE as usize / 1000
+}
+
+fn frames_to_ms(frames: usize) -> u32 {
+    (frames * 1000 / SAMPLE_RATE as usize) as u32
+}
+
+// The last minute of the current track, as it came out of the player (interleaved stereo samples)
+// but at full volume, so that replays are played at whatever the volume is by then.
+// Positions are counted in frames from the start of the track.
+#[derive(Debug, Default)]
+struct Ring {
+    samples: VecDeque<f32>,
+    // Where the last sample is in the track
+    end_frame: usize,
+    // Frames at the end that were played once already, and are being played again
+    replaying: usize,
+}
+
+impl Ring {
+    fn len_frames(&self) -> usize {
+        self.samples.len() / 2
+    }
+
+    fn reset(&mut self, position_ms: u32) {
+        self.samples.clear();
+        self.end_frame = ms_to_frames(position_ms);
+        self.replaying = 0;
+    }
+
+    // The player has already applied the (software) volume to these
+    fn push(&mut self, samples: &[f64], attenuation: f64) {
+        let gain = if attenuation > MIN_ATTENUATION {
+            1.0 / attenuation
+        } else {
+            0.0
+        };
+        self.samples
+            .extend(samples.iter().map(|s| (*s * gain) as f32));
+        let frames = samples.len() / 2;
+        self.end_frame += frames;
+        if self.replaying > 0 {
+            // Heard after what's being replayed, so it's queued behind
+            self.replaying += frames;
+        }
+        let extra = self.len_frames().saturating_sub(BUFFERED_FRAMES);
+        self.samples.drain(..extra * 2);
+        self.replaying = self.replaying.min(self.len_frames());
+    }
+
+    // False if that part of the track isn't in here (anymore, or yet)
+    fn replay_from(&mut self, position_ms: u32) -> bool {
+        let frame = ms_to_frames(position_ms);
+        let start_frame = self.end_frame - self.len_frames();
+        if frame < start_frame || frame > self.end_frame {
+            return false;
+        }
+        // Heard once what the output already has is, so as if it had started playing right away
+        let frame = (frame + ms_to_frames(OUTPUT_LATENCY_MS)).min(self.end_frame);
+        self.replaying = self.end_frame - frame;
+        true
+    }
+
+    fn next_chunk(&mut self, attenuation: f64) -> Option<Vec<f64>> {
+        if self.replaying == 0 {
+            return None;
+        }
+        let start = (self.len_frames() - self.replaying) * 2;
+        let frames = self.replaying.min(CHUNK_FRAMES);
+        self.replaying -= frames;
+        Some(
+            self.samples
+                .range(start..start + frames * 2)
+                .map(|s| *s as f64 * attenuation)
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    ring: Mutex<Ring>,
+    // The player has a command to go through (pause, stop...), it can't while we're replaying
+    interrupted: AtomicBool,
+}
+
+// Our side of the buffer, for the commands the player gets
+#[derive(Debug, Clone, Default)]
+pub struct SeekBuffer(Arc<Shared>);
+
+impl SeekBuffer {
+    // A new track, or a seek the buffer can't serve: whatever comes next starts there
+    pub fn reset(&self, position_ms: u32) {
+        if let Ok(mut ring) = self.0.ring.lock() {
+            ring.reset(position_ms);
+        }
+    }
+
+    // Seeking back to somewhere we've just been plays it from memory, rather than through
+    // librespot (which would fetch it again). The player itself carries on from where it was once that's done.
+    pub fn replay_from(&self, position_ms: u32) -> bool {
+        self.0
+            .ring
+            .lock()
+            .map(|mut ring| ring.replay_from(position_ms))
+            .unwrap_or(false)
+    }
+
+    pub fn interrupt(&self) {
+        self.0.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    // How far behind the player what's heard is, while replaying
+    pub fn lag_ms(&self) -> u32 {
+        self.0
+            .ring
+            .lock()
+            .map(|ring| frames_to_ms(ring.replaying))
+            .unwrap_or(0)
+    }
+
+    fn next_chunk(&self, attenuation: f64) -> Option<Vec<f64>> {
+        if self.0.interrupted.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.0.ring.lock().ok()?.next_chunk(attenuation)
+    }
+}
+
+// Keeps what the player wrote lately, to play it again when asked. The player is held up
+// in write meanwhile, as if the output was slow to take its samples, so it doesn't get further ahead.
+pub struct SeekBufferSink {
+    inner: Box<dyn Sink>,
+    buffer: SeekBuffer,
+    // The same as the player's, volume changes made during a replay apply to it right away
+    volume: Box<dyn VolumeGetter + Send>,
+}
+
+impl SeekBufferSink {
+    pub fn wrap(
+        inner: Box<dyn Sink>,
+        buffer: SeekBuffer,
+        volume: Box<dyn VolumeGetter + Send>,
+    ) -> Box<dyn Sink> {
+        Box::new(Self {
+            inner,
+            buffer,
+            volume,
+        })
+    }
+
+    fn replay(&mut self, converter: &mut Converter) -> SinkResult<()> {
+        while let Some(chunk) = self.buffer.next_chunk(self.volume.attenuation_factor()) {
+            self.inner.write(AudioPacket::Samples(chunk), converter)?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for SeekBufferSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.buffer.0.interrupted.store(false, Ordering::Relaxed);
+        self.inner.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.inner.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        let AudioPacket::Samples(samples) = &packet else {
+            self.buffer.reset(0);
+            return self.inner.write(packet, converter);
+        };
+        let replaying = match self.buffer.0.ring.lock() {
+            Ok(mut ring) => {
+                ring.push(samples, self.volume.attenuation_factor());
+                ring.replaying > 0
+            }
+            Err(_) => false,
+        };
+        if replaying {
+            // This packet is part of the replay now, it gets played when the replay catches up with it
+            self.replay(converter)
+        } else {
+            self.inner.write(packet, converter)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn second(value: f64) -> Vec<f64> {
+        vec![value; 2 * SAMPLE_RATE as usize]
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut ring = Ring::default();
+        ring.reset(10000);
+        ring.push(&second(0.25), 1.0);
+        ring.push(&second(0.5), 1.0);
+
+        // Before the buffer, and not played yet
+        assert!(!ring.replay_from(9000));
+        assert!(!ring.replay_from(13000));
+
+        // Starting a little later, for what the output had yet to play
+        assert!(ring.replay_from(11000));
+        assert_eq!(frames_to_ms(ring.replaying), 1000 - OUTPUT_LATENCY_MS);
+        let chunk = ring.next_chunk(1.0).unwrap();
+        assert_eq!(chunk.len(), CHUNK_FRAMES * 2);
+        assert_eq!(chunk[0], 0.5);
+
+        // What comes in meanwhile waits its turn
+        ring.push(&second(0.75), 1.0);
+        assert_eq!(ring.end_frame, ms_to_frames(13000));
+        assert_eq!(
+            ring.replaying,
+            ms_to_frames(2000 - OUTPUT_LATENCY_MS) - CHUNK_FRAMES
+        );
+        while ring.next_chunk(1.0).is_some() {}
+        assert_eq!(ring.replaying, 0);
+    }
+
+    #[test]
+    fn test_replay_at_current_volume() {
+        let mut ring = Ring::default();
+        ring.reset(0);
+        ring.push(&second(0.25), 0.5);
+        assert!(ring.replay_from(0));
+        assert_eq!(ring.next_chunk(0.8).unwrap()[0], 0.4);
+
+        // Nothing to bring back from silence
+        ring.push(&second(0.25), 0.0);
+        assert!(ring.replay_from(1000));
+        assert_eq!(ring.next_chunk(1.0).unwrap()[0], 0.0);
+    }
+
+    #[test]
+    fn test_buffer_limit() {
+        let mut ring = Ring::default();
+        ring.reset(0);
+        for _ in 0..61 {
+            ring.push(&second(0.0), 1.0);
+        }
+        assert_eq!(ring.len_frames(), BUFFERED_FRAMES);
+        assert!(!ring.replay_from(500));
+        assert!(ring.replay_from(1000));
+    }
+}
@@ -0,0 +1,81 @@
+use librespot::playback::audio_backend::{Sink, SinkResult};
+use librespot::playback::convert::Converter;
+use librespot::playback::decoder::AudioPacket;
+use librespot::playback::SAMPLE_RATE;
+
+// About -60 dB, quieter than the noise floor of most old recordings
+const THRESHOLD: f64 = 0.001;
+// Shorter silences are part of the music (pauses, fade-outs, the usual gap between tracks) and are left alone
+const MIN_GAP_FRAMES: usize = 2 * SAMPLE_RATE as usize;
+
+// Whether a packet should still be played, given how many frames of silence came right before it.
+// Samples are interleaved stereo frames.
+fn keep(silent_frames: &mut usize, samples: &[f64]) -> bool {
+    if samples.iter().any(|s| s.abs() >= THRESHOLD) {
+        *silent_frames = 0;
+        return true;
+    }
+    *silent_frames += samples.len() / 2;
+    *silent_frames <= MIN_GAP_FRAMES
+}
+
+// Drops whatever silence goes on for too long, like the minutes before a hidden track.
+// The player notices it got ahead of the clock and corrects the position on its own.
+pub struct SilenceSkipSink {
+    inner: Box<dyn Sink>,
+    silent_frames: usize,
+}
+
+impl SilenceSkipSink {
+    pub fn wrap(inner: Box<dyn Sink>, enabled: bool) -> Box<dyn Sink> {
+        if !enabled {
+            return inner;
+        }
+        Box::new(Self {
+            inner,
+            silent_frames: 0,
+        })
+    }
+}
+
+impl Sink for SilenceSkipSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.silent_frames = 0;
+        self.inner.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.inner.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        if let AudioPacket::Samples(samples) = &packet {
+            if !keep(&mut self.silent_frames, samples) {
+                return Ok(());
+            }
+        }
+        self.inner.write(packet, converter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_keep() {
+        let second_of_silence = vec![0.0; 2 * SAMPLE_RATE as usize];
+        let sound = vec![0.5, 0.5];
+        let mut silent_frames = 0;
+
+        // Two seconds are fine, the third one goes
+        assert!(keep(&mut silent_frames, &second_of_silence));
+        assert!(keep(&mut silent_frames, &second_of_silence));
+        assert!(!keep(&mut silent_frames, &second_of_silence));
+
+        // Until the music starts again
+        assert!(keep(&mut silent_frames, &sound));
+        assert!(keep(&mut silent_frames, &second_of_silence));
+    }
+}
@@ -0,0 +1,130 @@
+use librespot::playback::audio_backend::{Sink, SinkResult};
+use librespot::playback::convert::Converter;
+use librespot::playback::decoder::AudioPacket;
+use librespot::playback::SAMPLE_RATE;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+pub const BANDS: usize = 24;
+
+const MIN_FREQUENCY: f64 = 50.0;
+const MAX_FREQUENCY: f64 = 16000.0;
+// Anything quieter than that doesn't show at all
+const FLOOR_DB: f64 = -60.0;
+// Frames looked at per packet, plenty for bars that get redrawn 60 times a second
+const WINDOW: usize = 2048;
+
+// Only worked out while someone is looking
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LEVELS: Mutex<[f32; BANDS]> = Mutex::new([0.0; BANDS]);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        clear_levels();
+    }
+}
+
+// From 0 to 1, lowest frequencies first
+pub fn levels() -> [f32; BANDS] {
+    LEVELS.lock().map(|levels| *levels).unwrap_or([0.0; BANDS])
+}
+
+fn clear_levels() {
+    if let Ok(mut levels) = LEVELS.lock() {
+        *levels = [0.0; BANDS];
+    }
+}
+
+// Spread out evenly on a log scale, like we hear them
+fn band_frequency(band: usize) -> f64 {
+    MIN_FREQUENCY * (MAX_FREQUENCY / MIN_FREQUENCY).powf(band as f64 / (BANDS - 1) as f64)
+}
+
+// One Goertzel filter per band, which is a lot cheaper than a whole FFT for this few frequencies
+fn band_levels(samples: &[f64], sample_rate: f64) -> [f32; BANDS] {
+    let frames: Vec<f64> = samples
+        .chunks_exact(2)
+        .take(WINDOW)
+        .map(|frame| (frame[0] + frame[1]) / 2.0)
+        .collect();
+    let mut levels = [0.0; BANDS];
+    if frames.is_empty() {
+        return levels;
+    }
+    for (band, level) in levels.iter_mut().enumerate() {
+        let coeff = 2.0 * (2.0 * PI * band_frequency(band) / sample_rate).cos();
+        let (mut s1, mut s2) = (0.0, 0.0);
+        for x in &frames {
+            let s = x + coeff * s1 - s2;
+            s2 = s1;
+            s1 = s;
+        }
+        let power = s1 * s1 + s2 * s2 - coeff * s1 * s2;
+        let amplitude = power.max(0.0).sqrt() * 2.0 / frames.len() as f64;
+        let db = 20.0 * amplitude.max(1e-9).log10();
+        *level = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0) as f32;
+    }
+    levels
+}
+
+// Looks at the samples on their way to the actual backend, without touching them
+pub struct SpectrumSink {
+    inner: Box<dyn Sink>,
+}
+
+impl SpectrumSink {
+    pub fn wrap(inner: Box<dyn Sink>) -> Box<dyn Sink> {
+        Box::new(Self { inner })
+    }
+}
+
+impl Sink for SpectrumSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.inner.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        // Paused or stopped, the bars shouldn't stay up
+        clear_levels();
+        self.inner.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        if let AudioPacket::Samples(samples) = &packet {
+            if ENABLED.load(Ordering::Relaxed) {
+                let new_levels = band_levels(samples, SAMPLE_RATE as f64);
+                if let Ok(mut levels) = LEVELS.lock() {
+                    *levels = new_levels;
+                }
+            }
+        }
+        self.inner.write(packet, converter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_band_levels() {
+        let sample_rate = 44100.0;
+        let frequency = band_frequency(12);
+        let samples: Vec<f64> = (0..WINDOW)
+            .flat_map(|i| {
+                let x = (2.0 * PI * frequency * i as f64 / sample_rate).sin();
+                [x, x]
+            })
+            .collect();
+
+        let levels = band_levels(&samples, sample_rate);
+        assert!(levels[12] > 0.9);
+        assert!(levels[0] < levels[12]);
+        assert!(levels[BANDS - 1] < levels[12]);
+
+        assert_eq!(band_levels(&[0.0; 64], sample_rate), [0.0; BANDS]);
+    }
+}
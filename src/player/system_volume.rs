@@ -0,0 +1,112 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use super::AudioBackend;
+
+// The volume of what Spot outputs to, through the usual command line tools.
+// Slider steps come much faster than the tools run, they're applied one at a time
+// and only the latest that's waiting is.
+pub struct SystemVolume {
+    sender: Sender<(AudioBackend, f64)>,
+}
+
+impl Default for SystemVolume {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || apply_latest(receiver));
+        Self { sender }
+    }
+}
+
+impl SystemVolume {
+    pub fn set(&self, backend: &AudioBackend, volume: f64) {
+        let _ = self.sender.send((backend.clone(), volume));
+    }
+}
+
+fn apply_latest(receiver: Receiver<(AudioBackend, f64)>) {
+    while let Ok(mut latest) = receiver.recv() {
+        while let Ok(next) = receiver.try_recv() {
+            latest = next;
+        }
+        let (backend, volume) = latest;
+        let Some(mut command) = volume_command(&backend, volume) else {
+            continue;
+        };
+        match command.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => warn!("Could not set the system volume: {}", status),
+            Err(e) => warn!("Could not set the system volume: {}", e),
+        }
+    }
+}
+
+fn volume_command(backend: &AudioBackend, volume: f64) -> Option<Command> {
+    let percent = format!("{}%", (volume.clamp(0.0, 1.0) * 100.0).round() as u32);
+    match backend {
+        // The device from the preferences
+        AudioBackend::Alsa(device) => {
+            let mut command = Command::new("amixer");
+            command.args(["-q", "-D", device.as_str(), "sset", "Master", &percent]);
+            Some(command)
+        }
+        // Our own stream, wherever it plays. PipeWire understands pactl just as well
+        AudioBackend::PulseAudio | AudioBackend::GStreamer(_) => {
+            let Some(index) = own_sink_input() else {
+                debug!("Not playing anything, the system volume is left alone");
+                return None;
+            };
+            let mut command = Command::new("pactl");
+            command.args(["set-sink-input-volume", &index.to_string(), &percent]);
+            Some(command)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SinkInput {
+    index: u32,
+    properties: HashMap<String, String>,
+}
+
+// The stream this process plays to, there's none while nothing is playing
+fn own_sink_input() -> Option<u32> {
+    let output = Command::new("pactl")
+        .args(["--format=json", "list", "sink-inputs"])
+        .output()
+        .map_err(|e| warn!("Could not list the audio streams: {}", e))
+        .ok()?;
+    let inputs: Vec<SinkInput> = serde_json::from_slice(&output.stdout)
+        .map_err(|e| warn!("Could not list the audio streams: {}", e))
+        .ok()?;
+    find_own(&inputs, std::process::id())
+}
+
+fn find_own(inputs: &[SinkInput], pid: u32) -> Option<u32> {
+    let pid = pid.to_string();
+    inputs
+        .iter()
+        .find(|input| input.properties.get("application.process.id") == Some(&pid))
+        .map(|input| input.index)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_find_own_sink_input() {
+        let inputs: Vec<SinkInput> = serde_json::from_str(
+            r#"[
+                {"index": 12, "properties": {"application.name": "Firefox", "application.process.id": "100"}},
+                {"index": 57, "properties": {"application.name": "Spot", "application.process.id": "200"}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(find_own(&inputs, 200), Some(57));
+        assert_eq!(find_own(&inputs, 300), None);
+    }
+}
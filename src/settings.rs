@@ -1,7 +1,15 @@
-use crate::player::{AudioBackend, SpotifyPlayerSettings};
-use gio::prelude::SettingsExt;
+use crate::api::RetryPolicy;
+use crate::app::models::ShuffleMode;
+use crate::app::state::QueueLimits;
+use crate::player::{AudioBackend, SpotifyPlayerSettings, VolumeControl, VolumeCurve};
+use gio::prelude::{NetworkMonitorExt, SettingsExt};
+use glib::ToVariant;
 use libadwaita::ColorScheme;
 use librespot::playback::config::Bitrate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::time::Duration;
 
 const SETTINGS: &str = "dev.alextren.Spot";
 
@@ -35,16 +43,508 @@ impl WindowGeometry {
     }
 }
 
+// Where we were when the app was closed (screens are stored using their identifiers)
+#[derive(Clone, Debug, Default)]
+pub struct NavigationSession {
+    pub home_page: String,
+    pub screens: Vec<String>,
+    pub scroll_offsets: HashMap<String, f64>,
+}
+
+impl NavigationSession {
+    pub fn new_from_gsettings() -> Self {
+        let settings = gio::Settings::new(SETTINGS);
+        Self {
+            home_page: settings.string("navigation-home-page").to_string(),
+            screens: settings
+                .strv("navigation-stack")
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            scroll_offsets: settings
+                .value("navigation-scroll-offsets")
+                .get()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn save(&self) -> Option<()> {
+        let settings = gio::Settings::new(SETTINGS);
+        let screens: Vec<&str> = self.screens.iter().map(|s| s.as_str()).collect();
+        settings.delay();
+        settings
+            .set_string("navigation-home-page", &self.home_page)
+            .ok()?;
+        settings.set_strv("navigation-stack", &screens[..]).ok()?;
+        settings
+            .set_value(
+                "navigation-scroll-offsets",
+                &self.scroll_offsets.to_variant(),
+            )
+            .ok()?;
+        settings.apply();
+        Some(())
+    }
+}
+
+// Shortcuts the user changed, as action name -> trigger (an empty trigger disables the shortcut)
+#[derive(Clone, Debug, Default)]
+pub struct Keymap {
+    overrides: HashMap<String, String>,
+}
+
+impl Keymap {
+    pub fn new_from_gsettings() -> Self {
+        let settings = gio::Settings::new(SETTINGS);
+        Self {
+            overrides: settings.value("keymap").get().unwrap_or_default(),
+        }
+    }
+
+    pub fn save(&self) -> Option<()> {
+        let settings = gio::Settings::new(SETTINGS);
+        settings
+            .set_value("keymap", &self.overrides.to_variant())
+            .ok()
+    }
+
+    pub fn connect_changed<F>(f: F)
+    where
+        F: Fn(Keymap) + 'static,
+    {
+        connect_key_changed("keymap", move || f(Self::new_from_gsettings()));
+    }
+
+    pub fn trigger<'a>(&'a self, action: &str, default: &'a str) -> &'a str {
+        self.overrides
+            .get(action)
+            .map(|t| t.as_str())
+            .unwrap_or(default)
+    }
+
+    pub fn is_overridden(&self, action: &str) -> bool {
+        self.overrides.contains_key(action)
+    }
+
+    pub fn set(&mut self, action: &str, trigger: String) {
+        self.overrides.insert(action.to_string(), trigger);
+    }
+
+    pub fn reset(&mut self, action: &str) {
+        self.overrides.remove(action);
+    }
+}
+
+// A CSS color, or None to stick to the default accent color
+pub fn accent_color_from_gsettings() -> Option<String> {
+    let settings = gio::Settings::new(SETTINGS);
+    Some(settings.string("accent-color").to_string()).filter(|c| !c.is_empty())
+}
+
+pub fn connect_accent_color_changed<F>(f: F)
+where
+    F: Fn(Option<String>) + 'static,
+{
+    connect_key_changed("accent-color", move || f(accent_color_from_gsettings()));
+}
+
+// Read every time a song is recorded, so that turning it off takes effect right away
+pub fn listening_history_enabled() -> bool {
+    gio::Settings::new(SETTINGS).boolean("listening-history")
+}
+
+pub fn background_playback_enabled() -> bool {
+    gio::Settings::new(SETTINGS).boolean("background-playback")
+}
+
+pub fn autostart_enabled() -> bool {
+    gio::Settings::new(SETTINGS).boolean("startup-autostart")
+}
+
+pub fn connect_autostart_changed<F>(f: F)
+where
+    F: Fn(bool) + 'static,
+{
+    connect_key_changed("startup-autostart", move || f(autostart_enabled()));
+}
+
+pub fn concerts_app_id() -> String {
+    gio::Settings::new(SETTINGS)
+        .string("concerts-app-id")
+        .to_string()
+}
+
+// Read every time, it's only run every so often
+pub fn playback_hook() -> String {
+    gio::Settings::new(SETTINGS)
+        .string("playback-hook")
+        .to_string()
+}
+
+// Picked from the shuffle button's menu and kept for next time
+pub fn shuffle_mode() -> ShuffleMode {
+    let settings = gio::Settings::new(SETTINGS);
+    ShuffleMode::from_id(&settings.string("shuffle-mode")).unwrap_or_default()
+}
+
+pub fn save_shuffle_mode(mode: ShuffleMode) -> Option<()> {
+    let settings = gio::Settings::new(SETTINGS);
+    settings.set_string("shuffle-mode", mode.id()).ok()
+}
+
+// How far into a song (in ms) the previous button restarts it, None when it always goes back
+pub fn previous_restart_threshold() -> Option<u32> {
+    let settings = gio::Settings::new(SETTINGS);
+    settings
+        .boolean("previous-restarts")
+        .then(|| settings.uint("previous-restart-delay") * 1000)
+}
+
+pub fn connect_previous_restart_changed<F>(f: F)
+where
+    F: Fn(Option<u32>) + Clone + 'static,
+{
+    let g = f.clone();
+    connect_key_changed("previous-restarts", move || f(previous_restart_threshold()));
+    connect_key_changed("previous-restart-delay", move || {
+        g(previous_restart_threshold())
+    });
+}
+
+pub fn dj_mode() -> bool {
+    gio::Settings::new(SETTINGS).boolean("dj-mode")
+}
+
+pub fn connect_dj_mode_changed<F>(f: F)
+where
+    F: Fn(bool) + 'static,
+{
+    connect_key_changed("dj-mode", move || f(dj_mode()));
+}
+
+pub fn queue_limits() -> QueueLimits {
+    let settings = gio::Settings::new(SETTINGS);
+    QueueLimits {
+        no_duplicates: settings.boolean("queue-no-duplicates"),
+        max_length: settings
+            .boolean("queue-trim")
+            .then(|| settings.uint("queue-max-length") as usize),
+    }
+}
+
+pub fn retry_policy() -> RetryPolicy {
+    let settings = gio::Settings::new(SETTINGS);
+    RetryPolicy {
+        max_retries: settings.uint("api-max-retries"),
+        base_delay: Duration::from_millis(settings.uint("api-retry-base-delay").into()),
+        max_delay: Duration::from_millis(settings.uint("api-retry-max-delay").into()),
+    }
+}
+
+pub fn connect_retry_policy_changed<F>(f: F)
+where
+    F: Fn(RetryPolicy) + Clone + 'static,
+{
+    for key in [
+        "api-max-retries",
+        "api-retry-base-delay",
+        "api-retry-max-delay",
+    ] {
+        let f = f.clone();
+        connect_key_changed(key, move || f(retry_policy()));
+    }
+}
+
+pub fn connect_queue_limits_changed<F>(f: F)
+where
+    F: Fn(QueueLimits) + Clone + 'static,
+{
+    for key in ["queue-no-duplicates", "queue-trim", "queue-max-length"] {
+        let f = f.clone();
+        connect_key_changed(key, move || f(queue_limits()));
+    }
+}
+
+// Only meant to keep someone from casually unlocking, not to be a password store
+fn pin_hash(pin: &str) -> String {
+    glib::compute_checksum_for_string(glib::ChecksumType::Sha256, pin)
+        .map(|hash| hash.to_string())
+        .unwrap_or_default()
+}
+
+pub fn has_lock_pin() -> bool {
+    !gio::Settings::new(SETTINGS).string("lock-pin").is_empty()
+}
+
+// An empty PIN removes it, which unlocks too
+pub fn set_lock_pin(pin: &str) -> Option<()> {
+    let settings = gio::Settings::new(SETTINGS);
+    if pin.is_empty() {
+        settings.set_boolean("locked", false).ok()?;
+        settings.set_string("lock-pin", "").ok()
+    } else {
+        settings.set_string("lock-pin", &pin_hash(pin)).ok()
+    }
+}
+
+pub fn check_lock_pin(pin: &str) -> bool {
+    let expected = gio::Settings::new(SETTINGS).string("lock-pin");
+    !expected.is_empty() && expected == pin_hash(pin)
+}
+
+pub fn is_locked() -> bool {
+    let settings = gio::Settings::new(SETTINGS);
+    settings.boolean("locked") && !settings.string("lock-pin").is_empty()
+}
+
+pub fn set_locked(locked: bool) -> Option<()> {
+    gio::Settings::new(SETTINGS)
+        .set_boolean("locked", locked)
+        .ok()
+}
+
+pub fn lock_allowed_playlists() -> Vec<String> {
+    gio::Settings::new(SETTINGS)
+        .strv("lock-allowed-playlists")
+        .iter()
+        .map(|id| id.to_string())
+        .collect()
+}
+
+pub fn set_lock_allowed_playlists(ids: &[String]) -> Option<()> {
+    let ids: Vec<&str> = ids.iter().map(|id| id.as_str()).collect();
+    gio::Settings::new(SETTINGS)
+        .set_strv("lock-allowed-playlists", &ids[..])
+        .ok()
+}
+
+pub fn connect_lock_changed<F>(f: F)
+where
+    F: Fn() + Clone + 'static,
+{
+    for key in ["locked", "lock-pin", "lock-allowed-playlists"] {
+        connect_key_changed(key, f.clone());
+    }
+}
+
+pub fn visualizer_enabled() -> bool {
+    gio::Settings::new(SETTINGS).boolean("visualizer")
+}
+
+pub fn connect_visualizer_changed<F>(f: F)
+where
+    F: Fn(bool) + 'static,
+{
+    connect_key_changed("visualizer", move || f(visualizer_enabled()));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Density {
+    Compact,
+    Comfortable,
+    Spacious,
+}
+
+impl Density {
+    // Set on the main window, see song.css and album.css
+    pub fn css_class(self) -> Option<&'static str> {
+        match self {
+            Self::Compact => Some("density--compact"),
+            Self::Comfortable => None,
+            Self::Spacious => Some("density--spacious"),
+        }
+    }
+}
+
+// What song rows show below the artist, see SongWidget
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SongRows {
+    Standard,
+    // Album, when the song was added, popularity
+    Detailed,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DisplaySettings {
+    pub density: Density,
+    pub font_scale: f64,
+    pub song_rows: SongRows,
+}
+
+impl DisplaySettings {
+    pub fn new_from_gsettings() -> Self {
+        let settings = gio::Settings::new(SETTINGS);
+        let density = match settings.enum_("density") {
+            0 => Density::Compact,
+            2 => Density::Spacious,
+            _ => Density::Comfortable,
+        };
+        let song_rows = match settings.enum_("song-rows") {
+            1 => SongRows::Detailed,
+            _ => SongRows::Standard,
+        };
+        Self {
+            density,
+            font_scale: settings.double("font-scale"),
+            song_rows,
+        }
+    }
+
+    pub fn connect_changed<F>(f: F)
+    where
+        F: Fn(DisplaySettings) + 'static,
+    {
+        let f = Rc::new(f);
+        for key in ["density", "font-scale", "song-rows"] {
+            let f = f.clone();
+            connect_key_changed(key, move || f(Self::new_from_gsettings()));
+        }
+    }
+}
+
+// Cards or compact rows, for screens listing albums or artists
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewMode {
+    Grid,
+    List,
+}
+
+impl ViewMode {
+    // Each screen has its own key, like library-view-mode
+    pub fn new_from_gsettings(key: &str) -> Self {
+        let settings = gio::Settings::new(SETTINGS);
+        match settings.enum_(key) {
+            1 => Self::List,
+            _ => Self::Grid,
+        }
+    }
+
+    pub fn save(self, key: &str) -> Option<()> {
+        let settings = gio::Settings::new(SETTINGS);
+        let value = match self {
+            Self::Grid => 0,
+            Self::List => 1,
+        };
+        settings.set_enum(key, value).ok()
+    }
+}
+
+// About this machine and the last session rather than about how Spot is set up
+// (and the hook, which runs whatever it says, so it shouldn't come with a backup from elsewhere).
+// The lock is left alone too: importing a backup shouldn't change its PIN or what it allows, or turn it off
+const NOT_BACKED_UP: [&str; 9] = [
+    "window-width",
+    "window-height",
+    "window-is-maximized",
+    "navigation-stack",
+    "navigation-scroll-offsets",
+    "locked",
+    "lock-pin",
+    "lock-allowed-playlists",
+    "playback-hook",
+];
+
+// Values are in the GVariant text format, so that they come back with their exact type
+#[derive(Serialize, Deserialize)]
+struct SettingsBackup {
+    settings: BTreeMap<String, String>,
+}
+
+fn backed_up_keys(settings: &gio::Settings) -> Vec<String> {
+    settings
+        .settings_schema()
+        .map(|schema| schema.list_keys())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|key| key.to_string())
+        .filter(|key| !NOT_BACKED_UP.contains(&key.as_str()))
+        .collect()
+}
+
+// Everything that was changed from the defaults, shortcuts included
+pub fn export_backup() -> Result<String, serde_json::Error> {
+    let settings = gio::Settings::new(SETTINGS);
+    let values = backed_up_keys(&settings)
+        .into_iter()
+        .filter_map(|key| {
+            let value = settings.user_value(&key)?;
+            Some((key, value.print(false).to_string()))
+        })
+        .collect();
+    serde_json::to_string_pretty(&SettingsBackup { settings: values })
+}
+
+// Keys missing from the backup go back to their defaults, so that we end up with the very same setup.
+// Values that don't fit (say, from a different version) are skipped
+pub fn import_backup(content: &[u8]) -> Result<(), serde_json::Error> {
+    let backup: SettingsBackup = serde_json::from_slice(content)?;
+    let settings = gio::Settings::new(SETTINGS);
+    let Some(schema) = settings.settings_schema() else {
+        return Ok(());
+    };
+    // All at once, rather than a key at a time
+    settings.delay();
+    for key in backed_up_keys(&settings) {
+        let Some(text) = backup.settings.get(&key) else {
+            settings.reset(&key);
+            continue;
+        };
+        let schema_key = schema.key(&key);
+        let value = glib::Variant::parse(Some(&*schema_key.value_type()), text)
+            .ok()
+            .filter(|value| schema_key.range_check(value));
+        match value {
+            Some(value) => {
+                let _ = settings.set_value(&key, &value);
+            }
+            None => warn!("Skipping invalid value for {}: {}", key, text),
+        }
+    }
+    settings.apply();
+    Ok(())
+}
+
+fn connect_key_changed<F>(key: &str, f: F)
+where
+    F: Fn() + 'static,
+{
+    let settings = gio::Settings::new(SETTINGS);
+    // The handler keeps the settings object alive (on purpose, it's needed for as long as the app runs)
+    settings.clone().connect_changed(Some(key), move |_, _| {
+        let _ = &settings;
+        f()
+    });
+}
+
+fn bitrate_from_gsettings(settings: &gio::Settings) -> Option<Bitrate> {
+    // Mobile data and the like, as told by NetworkManager (or the portal, when sandboxed)
+    let bitrate_key = if gio::NetworkMonitor::default().is_network_metered() {
+        "player-bitrate-metered"
+    } else {
+        "player-bitrate"
+    };
+    match settings.enum_(bitrate_key) {
+        0 => Some(Bitrate::Bitrate96),
+        1 => Some(Bitrate::Bitrate160),
+        2 => Some(Bitrate::Bitrate320),
+        _ => None,
+    }
+}
+
+// What songs are currently streamed at, for size estimates
+pub fn bitrate_kbps() -> u32 {
+    match bitrate_from_gsettings(&gio::Settings::new(SETTINGS)) {
+        Some(Bitrate::Bitrate96) => 96,
+        Some(Bitrate::Bitrate320) => 320,
+        _ => 160,
+    }
+}
+
 // Player (librespot) settings
 impl SpotifyPlayerSettings {
     pub fn new_from_gsettings() -> Option<Self> {
         let settings = gio::Settings::new(SETTINGS);
-        let bitrate = match settings.enum_("player-bitrate") {
-            0 => Some(Bitrate::Bitrate96),
-            1 => Some(Bitrate::Bitrate160),
-            2 => Some(Bitrate::Bitrate320),
-            _ => None,
-        }?;
+        let bitrate = bitrate_from_gsettings(&settings)?;
         let backend = match settings.enum_("audio-backend") {
             0 => Some(AudioBackend::PulseAudio),
             1 => Some(AudioBackend::Alsa(
@@ -75,15 +575,49 @@ impl SpotifyPlayerSettings {
             backend,
             gapless,
             ap_port,
+            mono: settings.boolean("mono-audio"),
+            balance: settings.int("audio-balance"),
+            skip_silence: settings.boolean("skip-silence"),
+            volume_control: match settings.enum_("volume-control") {
+                1 => VolumeControl::System,
+                _ => VolumeControl::Software,
+            },
+            volume_curve: match settings.enum_("volume-curve") {
+                1 => VolumeCurve::Cubic,
+                2 => VolumeCurve::Linear,
+                _ => VolumeCurve::Logarithmic,
+            },
+            precache_album: settings.boolean("precache-album"),
         })
     }
 }
 
+// What happens on launch (see the Startup preferences), screens are restored or not through NavigationSession
+#[derive(Clone, Debug, Default)]
+pub struct StartupSettings {
+    pub minimized: bool,
+    pub resume_playback: bool,
+}
+
+impl StartupSettings {
+    pub fn new_from_gsettings() -> Self {
+        let settings = gio::Settings::new(SETTINGS);
+        Self {
+            minimized: settings.boolean("startup-minimized"),
+            resume_playback: settings.boolean("startup-resume-playback"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SpotSettings {
     pub theme_preference: ColorScheme,
     pub player_settings: SpotifyPlayerSettings,
     pub window: WindowGeometry,
+    pub navigation: NavigationSession,
+    pub startup: StartupSettings,
+    pub grab_media_keys: bool,
+    pub retry_policy: RetryPolicy,
 }
 
 // Application settings
@@ -100,6 +634,15 @@ impl SpotSettings {
             theme_preference,
             player_settings: SpotifyPlayerSettings::new_from_gsettings()?,
             window: WindowGeometry::new_from_gsettings(),
+            // Starting over from the home page otherwise
+            navigation: if settings.boolean("startup-restore-screens") {
+                NavigationSession::new_from_gsettings()
+            } else {
+                Default::default()
+            },
+            startup: StartupSettings::new_from_gsettings(),
+            grab_media_keys: settings.boolean("grab-media-keys"),
+            retry_policy: retry_policy(),
         })
     }
 }
@@ -110,6 +653,10 @@ impl Default for SpotSettings {
             theme_preference: ColorScheme::PreferDark,
             player_settings: Default::default(),
             window: Default::default(),
+            navigation: Default::default(),
+            startup: Default::default(),
+            grab_media_keys: false,
+            retry_policy: Default::default(),
         }
     }
 }
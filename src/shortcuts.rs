@@ -0,0 +1,220 @@
+use gettextrs::*;
+use gio::prelude::*;
+use gtk::prelude::*;
+
+use crate::settings::Keymap;
+
+// A keyboard shortcut triggering one of the app actions (see register_actions in main.rs)
+pub struct AppShortcut {
+    pub action: &'static str,
+    pub default_trigger: &'static str,
+}
+
+pub const SHORTCUTS: &[AppShortcut] = &[
+    AppShortcut {
+        action: "toggle_playback",
+        default_trigger: "space",
+    },
+    AppShortcut {
+        action: "player_prev",
+        default_trigger: "P",
+    },
+    AppShortcut {
+        action: "player_prev_track",
+        default_trigger: "",
+    },
+    AppShortcut {
+        action: "player_next",
+        default_trigger: "N",
+    },
+    AppShortcut {
+        action: "player_prev_chapter",
+        default_trigger: "<Shift>P",
+    },
+    AppShortcut {
+        action: "player_next_chapter",
+        default_trigger: "<Shift>N",
+    },
+    AppShortcut {
+        action: "nav_pop",
+        default_trigger: "<Alt>Left",
+    },
+    AppShortcut {
+        action: "search",
+        default_trigger: "<Ctrl>F",
+    },
+    AppShortcut {
+        action: "quick_open",
+        default_trigger: "<Ctrl>K",
+    },
+    AppShortcut {
+        action: "refresh",
+        default_trigger: "<Ctrl>R",
+    },
+    AppShortcut {
+        action: "queue_selection",
+        default_trigger: "",
+    },
+    AppShortcut {
+        action: "select_all",
+        default_trigger: "<Ctrl>A",
+    },
+    AppShortcut {
+        action: "invert_selection",
+        default_trigger: "<Ctrl>I",
+    },
+    AppShortcut {
+        action: "cancel_selection",
+        default_trigger: "Escape",
+    },
+    AppShortcut {
+        action: "undo",
+        default_trigger: "<Ctrl>Z",
+    },
+    AppShortcut {
+        action: "redo",
+        default_trigger: "<Ctrl><Shift>Z",
+    },
+    AppShortcut {
+        action: "quit",
+        default_trigger: "<Ctrl>Q",
+    },
+    AppShortcut {
+        action: "dump_state_log",
+        default_trigger: "<Ctrl><Shift>D",
+    },
+    AppShortcut {
+        action: "save_state_snapshot",
+        default_trigger: "<Ctrl><Shift>S",
+    },
+];
+
+impl AppShortcut {
+    pub fn title(&self) -> String {
+        match self.action {
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "toggle_playback" => gettext("Play/Pause"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "player_prev" => gettext("Previous track"),
+            // translators: This is the name of a keyboard shortcut, in preferences. Unlike "Previous track", it never just restarts the current song
+            "player_prev_track" => gettext("Back to the previous track"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "player_next" => gettext("Next track"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "player_prev_chapter" => gettext("Previous chapter"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "player_next_chapter" => gettext("Next chapter"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "nav_pop" => gettext("Go back"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "search" => gettext("Search"),
+            // translators: This is the name of a keyboard shortcut, in preferences. It opens a small window to find a playlist, album or artist by name
+            "quick_open" => gettext("Quick open"),
+            // translators: This is the name of a keyboard shortcut, in preferences. Loads the current screen again, skipping what was saved on disk
+            "refresh" => gettext("Refresh"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "queue_selection" => gettext("Queue selected songs"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "select_all" => gettext("Select all songs"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "invert_selection" => gettext("Invert the selection"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "cancel_selection" => gettext("Leave selection mode"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "undo" => gettext("Undo"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "redo" => gettext("Redo"),
+            // translators: This is the name of a keyboard shortcut, in preferences
+            "quit" => gettext("Quit"),
+            // No need to translate these, they're for bug reports
+            "dump_state_log" => "Write state log".to_string(),
+            "save_state_snapshot" => "Save state snapshot".to_string(),
+            _ => self.action.to_string(),
+        }
+    }
+}
+
+// The same combination written differently ("<Ctrl>Z", "<Control>z") compares equal
+fn parse(trigger: &str) -> Option<(gdk::Key, gdk::ModifierType)> {
+    if trigger.is_empty() {
+        return None;
+    }
+    let (key, modifiers) = gtk::accelerator_parse(trigger)?;
+    Some((key.to_lower(), modifiers))
+}
+
+// The other shortcut this trigger is already bound to, if any: a combination only ever does one thing
+pub fn bound_elsewhere(
+    keymap: &Keymap,
+    action: &str,
+    trigger: &str,
+) -> Option<&'static AppShortcut> {
+    let parsed = parse(trigger)?;
+    SHORTCUTS
+        .iter()
+        .filter(|shortcut| shortcut.action != action)
+        .find(|shortcut| {
+            parse(keymap.trigger(shortcut.action, shortcut.default_trigger)) == Some(parsed)
+        })
+}
+
+// Replaces whatever the controller had with the shortcuts from the keymap
+pub fn apply(controller: &gtk::ShortcutController, keymap: &Keymap) {
+    while let Some(shortcut) = controller
+        .item(0)
+        .and_then(|item| item.downcast::<gtk::Shortcut>().ok())
+    {
+        controller.remove_shortcut(&shortcut);
+    }
+
+    // Settings edited by hand can still bind a combination twice, the first one listed wins
+    let mut bound = vec![];
+    for shortcut in SHORTCUTS {
+        let trigger = keymap.trigger(shortcut.action, shortcut.default_trigger);
+        if trigger.is_empty() {
+            continue;
+        }
+        if let Some(parsed) = parse(trigger) {
+            if bound.contains(&parsed) {
+                warn!(
+                    "Shortcut {} for {} is already bound, ignoring it",
+                    trigger, shortcut.action
+                );
+                continue;
+            }
+            bound.push(parsed);
+        }
+        let Some(trigger) = gtk::ShortcutTrigger::parse_string(trigger) else {
+            warn!("Invalid shortcut for {}: {}", shortcut.action, trigger);
+            continue;
+        };
+        let action = gtk::NamedAction::new(&format!("app.{}", shortcut.action));
+        controller.add_shortcut(gtk::Shortcut::new(Some(trigger), Some(action)));
+    }
+}
+
+// Sets up the main window shortcuts, and keeps them up to date when they are changed in preferences
+pub fn register(controller: &gtk::ShortcutController) {
+    apply(controller, &Keymap::new_from_gsettings());
+    Keymap::connect_changed(clone!(@weak controller => move |keymap| {
+        apply(&controller, &keymap);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_default_triggers_are_unique() {
+        let triggers: Vec<&str> = SHORTCUTS
+            .iter()
+            .map(|s| s.default_trigger)
+            .filter(|t| !t.is_empty())
+            .collect();
+        let unique: HashSet<&str> = triggers.iter().copied().collect();
+        assert_eq!(triggers.len(), unique.len());
+    }
+}
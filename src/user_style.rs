@@ -0,0 +1,136 @@
+use gio::prelude::*;
+use gtk::prelude::*;
+use std::path::PathBuf;
+
+use crate::settings::{self, Density, DisplaySettings};
+
+// Themers get to tweak the app without rebuilding it: ~/.config/spot/style.css is loaded on top of everything else
+fn user_stylesheet_path() -> PathBuf {
+    glib::user_config_dir().join("spot").join("style.css")
+}
+
+fn accent_color_css(color: Option<&str>) -> String {
+    match color {
+        Some(color) if gdk::RGBA::parse(color).is_ok() => {
+            format!("@define-color accent_color {color};\n@define-color accent_bg_color {color};\n")
+        }
+        Some(color) => {
+            warn!("Ignoring invalid accent color: {}", color);
+            String::new()
+        }
+        None => String::new(),
+    }
+}
+
+fn font_scale_css(scale: f64) -> String {
+    if (scale - 1.0).abs() < f64::EPSILON {
+        String::new()
+    } else {
+        format!("window {{ font-size: {:.0}%; }}\n", scale * 100.0)
+    }
+}
+
+fn apply_display_settings(
+    window: &libadwaita::ApplicationWindow,
+    provider: &gtk::CssProvider,
+    display_settings: DisplaySettings,
+) {
+    for density in [Density::Compact, Density::Comfortable, Density::Spacious] {
+        if let Some(class) = density.css_class() {
+            window.remove_css_class(class);
+        }
+    }
+    if let Some(class) = display_settings.density.css_class() {
+        window.add_css_class(class);
+    }
+    provider.load_from_data(&font_scale_css(display_settings.font_scale));
+}
+
+// Density is a class on the main window, that the components' stylesheets pick up
+pub fn bind_display_settings(window: &libadwaita::ApplicationWindow) {
+    let provider = gtk::CssProvider::new();
+    gtk::style_context_add_provider_for_display(
+        &window.display(),
+        &provider,
+        gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+    apply_display_settings(window, &provider, DisplaySettings::new_from_gsettings());
+    DisplaySettings::connect_changed(clone!(@weak window => move |display_settings| {
+        apply_display_settings(&window, &provider, display_settings);
+    }));
+}
+
+fn load_user_stylesheet(provider: &gtk::CssProvider, file: &gio::File) {
+    if file.query_exists(gio::Cancellable::NONE) {
+        debug!("Loading user stylesheet {:?}", file.path());
+        provider.load_from_file(file);
+    } else {
+        provider.load_from_data("");
+    }
+}
+
+pub fn setup(display: &gdk::Display) {
+    let accent_provider = gtk::CssProvider::new();
+    accent_provider.load_from_data(&accent_color_css(
+        settings::accent_color_from_gsettings().as_deref(),
+    ));
+    gtk::style_context_add_provider_for_display(
+        display,
+        &accent_provider,
+        gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+    settings::connect_accent_color_changed(move |color| {
+        accent_provider.load_from_data(&accent_color_css(color.as_deref()));
+    });
+
+    let user_provider = gtk::CssProvider::new();
+    user_provider.connect_parsing_error(|_, section, error| {
+        warn!("Error in user stylesheet at {}: {}", section, error);
+    });
+    let file = gio::File::for_path(user_stylesheet_path());
+    load_user_stylesheet(&user_provider, &file);
+    gtk::style_context_add_provider_for_display(
+        display,
+        &user_provider,
+        gtk::STYLE_PROVIDER_PRIORITY_USER,
+    );
+
+    // Live reload, so that tweaking the stylesheet doesn't require restarting the app
+    match file.monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE) {
+        Ok(monitor) => {
+            // Capturing the monitor keeps it alive for as long as the app runs
+            let watched = monitor.clone();
+            monitor.connect_changed(move |_, file, _, event| {
+                let _ = &watched;
+                if matches!(
+                    event,
+                    gio::FileMonitorEvent::ChangesDoneHint
+                        | gio::FileMonitorEvent::Created
+                        | gio::FileMonitorEvent::Deleted
+                ) {
+                    load_user_stylesheet(&user_provider, file);
+                }
+            });
+        }
+        Err(e) => warn!("Could not watch user stylesheet: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_accent_color_css() {
+        assert!(accent_color_css(None).is_empty());
+        assert!(accent_color_css(Some("#3584e4")).contains("@define-color accent_color #3584e4;"));
+        assert!(accent_color_css(Some("not a color")).is_empty());
+    }
+
+    #[test]
+    fn test_font_scale_css() {
+        assert!(font_scale_css(1.0).is_empty());
+        assert_eq!(font_scale_css(1.25), "window { font-size: 125%; }\n");
+    }
+}